@@ -242,6 +242,27 @@ impl fmt::Display for RuntimeError {
                 func_index,
                 frame.module_offset()
             )?;
+            for symbol in frame.symbols() {
+                writeln!(f)?;
+                write!(f, "        at ")?;
+                match symbol.name() {
+                    Some(name) => match rustc_demangle::try_demangle(name) {
+                        Ok(name) => write!(f, "{}", name)?,
+                        Err(_) => write!(f, "{}", name)?,
+                    },
+                    None => write!(f, "<unnamed>")?,
+                }
+                if let Some(file) = symbol.file() {
+                    write!(f, " ({}", file)?;
+                    if let Some(line) = symbol.line() {
+                        write!(f, ":{}", line)?;
+                        if let Some(column) = symbol.column() {
+                            write!(f, ":{}", column)?;
+                        }
+                    }
+                    write!(f, ")")?;
+                }
+            }
         }
         Ok(())
     }
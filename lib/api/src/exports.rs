@@ -1,4 +1,6 @@
 use crate::store::AsStoreRef;
+#[cfg(feature = "sys")]
+use crate::Tag;
 use crate::{Extern, Function, Global, Memory, Table, TypedFunction, WasmTypeList};
 use indexmap::IndexMap;
 use std::fmt;
@@ -127,6 +129,12 @@ impl Exports {
         self.get(name)
     }
 
+    /// Get an export as a `Tag`.
+    #[cfg(feature = "sys")]
+    pub fn get_tag(&self, name: &str) -> Result<&Tag, ExportError> {
+        self.get(name)
+    }
+
     /// Get an export as a `Func`.
     pub fn get_function(&self, name: &str) -> Result<&Function, ExportError> {
         self.get(name)
@@ -250,6 +258,15 @@ where
             _ => None,
         })
     }
+
+    /// Get only the tags.
+    #[cfg(feature = "sys")]
+    pub fn tags(self) -> impl Iterator<Item = (&'a String, &'a Tag)> + Sized {
+        self.iter.filter_map(|(name, export)| match export {
+            Extern::Tag(tag) => Some((name, tag)),
+            _ => None,
+        })
+    }
 }
 
 impl FromIterator<(String, Extern)> for Exports {
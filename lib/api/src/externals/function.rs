@@ -11,10 +11,18 @@ use crate::vm::{VMExtern, VMExternFunction, VMFuncRef, VMFunctionCallback, VMTra
 use crate::{
     Extern, FunctionEnv, FunctionEnvMut, FunctionType, RuntimeError, TypedFunction, Value,
 };
+#[cfg(feature = "sys")]
+use std::{future::Future, pin::Pin};
 use wasmer_types::RawValue;
 
 use crate::native_type::WasmTypeList;
 
+/// The future returned by the async host functions created with
+/// [`Function::new_async_with_env`].
+#[cfg(feature = "sys")]
+pub type HostFunctionFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Vec<Value>, RuntimeError>> + Send + 'a>>;
+
 /// The `HostFunction` trait represents the set of functions that
 /// can be used as host function. To uphold this statement, it is
 /// necessary for a function to be transformed into a
@@ -156,6 +164,70 @@ impl Function {
         Self(function_impl::Function::new_with_env(store, env, ty, func))
     }
 
+    /// Creates a new async host `Function` (dynamic) with the provided
+    /// signature.
+    ///
+    /// The returned future is awaited by the [`Function::call_async`] or
+    /// [`TypedFunction::call_async`] call that ran into the host function:
+    /// the WebAssembly code is suspended while the future is pending, and
+    /// resumes once it is ready. Calling the function outside of a
+    /// `call_async` results in a [`RuntimeError`].
+    #[cfg(feature = "sys")]
+    pub fn new_async<FT, F, Fut>(store: &mut impl AsStoreMut, ty: FT, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(Vec<Value>) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<Vec<Value>, RuntimeError>> + 'static + Send,
+    {
+        let env = FunctionEnv::new(&mut store.as_store_mut(), ());
+        Self::new_async_with_env(store, &env, ty, move |_env, args| {
+            Box::pin(func(args)) as HostFunctionFuture
+        })
+    }
+
+    /// Creates a new async host `Function` (dynamic) with the provided
+    /// signature and a [`FunctionEnv`], see [`Function::new_async`].
+    ///
+    /// The future may hold the [`FunctionEnvMut`] across `.await` points.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionEnv, FunctionEnvMut, Store, Type, Value};
+    /// # let mut store = Store::default();
+    /// # let env = FunctionEnv::new(&mut store, 0);
+    /// #
+    /// let f = Function::new_async_with_env(
+    ///     &mut store,
+    ///     &env,
+    ///     ([Type::I32], [Type::I32]),
+    ///     |mut env: FunctionEnvMut<i32>, args| {
+    ///         Box::pin(async move {
+    ///             *env.data_mut() += args[0].unwrap_i32();
+    ///             Ok(vec![Value::I32(*env.data())])
+    ///         })
+    ///     },
+    /// );
+    /// ```
+    #[cfg(feature = "sys")]
+    pub fn new_async_with_env<FT, F, T: Send + 'static>(
+        store: &mut impl AsStoreMut,
+        env: &FunctionEnv<T>,
+        ty: FT,
+        func: F,
+    ) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: for<'a> Fn(FunctionEnvMut<'a, T>, Vec<Value>) -> HostFunctionFuture<'a>
+            + 'static
+            + Send
+            + Sync,
+    {
+        Self(function_impl::Function::new_async_with_env(
+            store, env, ty, func,
+        ))
+    }
+
     /// Creates a new host `Function` from a native function.
     pub fn new_typed<F, Args, Rets>(store: &mut impl AsStoreMut, func: F) -> Self
     where
@@ -302,6 +374,21 @@ impl Function {
         self.0.call(store, params)
     }
 
+    /// Call the `Function` function asynchronously.
+    ///
+    /// The call runs on a stack of its own, which is suspended whenever an
+    /// async host function created with [`Function::new_async`] waits for
+    /// a pending future. The returned future then yields to the executor,
+    /// and resumes the call once the host future is ready.
+    #[cfg(feature = "sys")]
+    pub async fn call_async(
+        &self,
+        store: &mut impl AsStoreMut,
+        params: &[Value],
+    ) -> Result<Box<[Value]>, RuntimeError> {
+        self.0.call_async(store, params).await
+    }
+
     #[doc(hidden)]
     #[allow(missing_docs)]
     pub fn call_raw(
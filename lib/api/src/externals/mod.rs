@@ -5,12 +5,16 @@ mod memory_view;
 mod table;
 
 pub use self::function::{Function, HostFunction};
+#[cfg(feature = "sys")]
+pub use self::function::HostFunctionFuture;
 pub use self::global::Global;
 pub use self::memory::{Memory, MemoryLocation, SharedMemory};
 pub use self::memory_view::MemoryView;
 pub use self::table::Table;
 
 use crate::exports::{ExportError, Exportable};
+#[cfg(feature = "sys")]
+use crate::sys::Tag;
 use crate::ExternType;
 use std::fmt;
 
@@ -37,6 +41,9 @@ pub enum Extern {
     Table(Table),
     /// A external [`Memory`].
     Memory(Memory),
+    /// A external [`Tag`].
+    #[cfg(feature = "sys")]
+    Tag(Tag),
 }
 
 impl Extern {
@@ -47,6 +54,8 @@ impl Extern {
             Self::Memory(ft) => ExternType::Memory(ft.ty(store)),
             Self::Table(tt) => ExternType::Table(tt.ty(store)),
             Self::Global(gt) => ExternType::Global(gt.ty(store)),
+            #[cfg(feature = "sys")]
+            Self::Tag(tt) => ExternType::Tag(tt.ty(store)),
         }
     }
 
@@ -57,6 +66,8 @@ impl Extern {
             VMExtern::Memory(m) => Self::Memory(Memory::from_vm_extern(store, m)),
             VMExtern::Global(g) => Self::Global(Global::from_vm_extern(store, g)),
             VMExtern::Table(t) => Self::Table(Table::from_vm_extern(store, t)),
+            #[cfg(feature = "sys")]
+            VMExtern::Tag(t) => Self::Tag(Tag::from_vm_extern(store, t)),
         }
     }
 
@@ -67,6 +78,8 @@ impl Extern {
             Self::Global(g) => g.is_from_store(store),
             Self::Memory(m) => m.is_from_store(store),
             Self::Table(t) => t.is_from_store(store),
+            #[cfg(feature = "sys")]
+            Self::Tag(t) => t.is_from_store(store),
         }
     }
 
@@ -77,6 +90,8 @@ impl Extern {
             Self::Global(g) => g.to_vm_extern(),
             Self::Memory(m) => m.to_vm_extern(),
             Self::Table(t) => t.to_vm_extern(),
+            #[cfg(feature = "sys")]
+            Self::Tag(t) => t.to_vm_extern(),
         }
     }
 }
@@ -98,6 +113,8 @@ impl fmt::Debug for Extern {
                 Self::Global(_) => "Global(...)",
                 Self::Memory(_) => "Memory(...)",
                 Self::Table(_) => "Table(...)",
+                #[cfg(feature = "sys")]
+                Self::Tag(_) => "Tag(...)",
             }
         )
     }
@@ -126,3 +143,10 @@ impl From<Table> for Extern {
         Self::Table(r)
     }
 }
+
+#[cfg(feature = "sys")]
+impl From<Tag> for Extern {
+    fn from(r: Tag) -> Self {
+        Self::Tag(r)
+    }
+}
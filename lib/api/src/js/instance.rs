@@ -4,8 +4,8 @@ use crate::js::as_js::AsJs;
 use crate::js::vm::VMInstance;
 use crate::module::Module;
 use crate::store::AsStoreMut;
-use crate::Extern;
 use crate::{errors::InstantiationError, js::js_handle::JsHandle};
+use crate::{Extern, ExternType};
 use js_sys::WebAssembly;
 
 #[derive(Clone, PartialEq, Eq)]
@@ -53,6 +53,8 @@ impl Instance {
 
         let exports = module
             .exports()
+            // Exception tags are not supported by the js backend
+            .filter(|export_type| !matches!(export_type.ty(), ExternType::Tag(_)))
             .map(|export_type| {
                 let name = export_type.name();
                 let extern_type = export_type.ty();
@@ -149,6 +149,10 @@ impl AsJs for Extern {
                     VMTable::new(obj_val, table_type.clone()),
                 )))
             }
+            ExternType::Tag(_) => Err(JSValue::string(
+                &context,
+                "exception tags are not supported by the jsc backend".to_string(),
+            )),
         }
     }
 }
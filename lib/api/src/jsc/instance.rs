@@ -6,7 +6,7 @@ use crate::jsc::engine::JSC;
 use crate::jsc::vm::VMInstance;
 use crate::module::Module;
 use crate::store::AsStoreMut;
-use crate::Extern;
+use crate::{Extern, ExternType};
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct Instance {
@@ -60,6 +60,8 @@ impl Instance {
 
         let exports = exports_ty
             .iter()
+            // Exception tags are not supported by the jsc backend
+            .filter(|export_type| !matches!(export_type.ty(), ExternType::Tag(_)))
             .map(|export_type| {
                 let name = export_type.name();
                 let mut store = store.as_store_mut();
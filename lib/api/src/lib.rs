@@ -491,6 +491,8 @@ mod jsc;
 #[cfg(feature = "jsc")]
 pub use jsc::*;
 
+#[cfg(feature = "sys")]
+pub use crate::externals::HostFunctionFuture;
 pub use crate::externals::{
     Extern, Function, Global, HostFunction, Memory, MemoryLocation, MemoryView, SharedMemory, Table,
 };
@@ -509,7 +511,7 @@ pub use native_type::{FromToNativeWasmType, NativeWasmTypeInto, WasmTypeList};
 pub use ptr::{Memory32, Memory64, MemorySize, WasmPtr, WasmPtr64};
 pub use store::{AsStoreMut, AsStoreRef, OnCalledHandler, Store, StoreId, StoreMut, StoreRef};
 #[cfg(feature = "sys")]
-pub use store::{TrapHandlerFn, Tunables};
+pub use store::{ResourceLimiter, ResourceUsage, TrapHandlerFn, Tunables};
 #[cfg(any(feature = "sys", feature = "jsc"))]
 pub use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Triple, HOST};
 pub use typed_function::TypedFunction;
@@ -521,10 +523,10 @@ pub use wasmer_derive::ValueType;
 // TODO: OnCalledAction is needed for asyncify. It will be refactored with https://github.com/wasmerio/wasmer/issues/3451
 pub use wasmer_types::{
     is_wasm, Bytes, CompileError, CpuFeature, DeserializeError, ExportIndex, ExportType,
-    ExternType, FrameInfo, FunctionType, GlobalInit, GlobalType, ImportType, LocalFunctionIndex,
-    MemoryError, MemoryType, MiddlewareError, Mutability, OnCalledAction, Pages,
-    ParseCpuFeatureError, SerializeError, TableType, Target, Type, ValueType, WasmError,
-    WasmResult, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    ExternType, FrameInfo, FrameSymbol, FunctionType, GlobalInit, GlobalType, ImportType,
+    LocalFunctionIndex, MemoryError, MemoryType, MiddlewareError, Mutability, OnCalledAction,
+    Pages, ParseCpuFeatureError, SerializeError, TableType, TagType, Target, Type, ValueType,
+    WasmError, WasmResult, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
 #[cfg(feature = "wat")]
 pub use wat::parse_bytes as wat2wasm;
@@ -8,9 +8,9 @@ use std::{
 pub use wasmer_compiler::Tunables;
 pub use wasmer_types::{OnCalledAction, StoreId};
 #[cfg(feature = "sys")]
-use wasmer_vm::init_traps;
+use wasmer_vm::{init_traps, VMEpochDeadline};
 #[cfg(feature = "sys")]
-pub use wasmer_vm::TrapHandlerFn;
+pub use wasmer_vm::{ResourceLimiter, ResourceUsage, TrapHandlerFn};
 
 #[cfg(feature = "sys")]
 pub use wasmer_vm::{StoreHandle, StoreObjects};
@@ -64,10 +64,21 @@ impl Store {
         #[cfg(feature = "sys")]
         init_traps();
 
+        let engine = engine.into();
+        #[cfg(feature = "sys")]
+        let objects = {
+            // The stores follow the epoch of their engine.
+            let mut objects = StoreObjects::default();
+            *objects.epoch_deadline_mut() = VMEpochDeadline::new(engine.0.epoch().clone());
+            objects
+        };
+        #[cfg(not(feature = "sys"))]
+        let objects = StoreObjects::default();
+
         Self {
             inner: Box::new(StoreInner {
-                objects: Default::default(),
-                engine: engine.into(),
+                objects,
+                engine,
                 #[cfg(feature = "sys")]
                 trap_handler: None,
                 on_called: None,
@@ -81,6 +92,63 @@ impl Store {
         self.inner.trap_handler = handler;
     }
 
+    #[cfg(feature = "sys")]
+    /// Sets the epoch deadline of this store `ticks` epochs after the
+    /// current epoch of the engine.
+    ///
+    /// Once the deadline is reached, the modules compiled with the
+    /// `EpochInterruption` middleware trap with `TrapCode::Interrupt`, or
+    /// invoke the callback set with [`Store::epoch_deadline_callback`].
+    /// Without a deadline, the code is never interrupted.
+    pub fn set_epoch_deadline(&mut self, ticks: u64) {
+        self.inner.objects.epoch_deadline_mut().set_ticks(ticks);
+    }
+
+    #[cfg(feature = "sys")]
+    /// Makes reaching the epoch deadline trap with `TrapCode::Interrupt`.
+    ///
+    /// This is the default behavior.
+    pub fn epoch_deadline_trap(&mut self) {
+        self.inner.objects.epoch_deadline_mut().set_callback(None);
+    }
+
+    #[cfg(feature = "sys")]
+    /// Makes reaching the epoch deadline invoke the given callback instead
+    /// of trapping.
+    ///
+    /// The callback returns the number of ticks after which the deadline is
+    /// reached again, and the code resumes. If it returns an error, the
+    /// code traps with that error instead.
+    pub fn epoch_deadline_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut() -> Result<u64, Box<dyn std::error::Error + Send + Sync>> + Send + Sync + 'static,
+    {
+        self.inner
+            .objects
+            .epoch_deadline_mut()
+            .set_callback(Some(Box::new(callback)));
+    }
+
+    #[cfg(feature = "sys")]
+    /// Sets the resource limiter of this store.
+    ///
+    /// The limiter is consulted whenever an instance, a memory or a table
+    /// is created in the store, and whenever one of its memories or tables
+    /// grows, with the totals of the resources used by the store.
+    pub fn set_resource_limiter(&mut self, limiter: impl ResourceLimiter + 'static) {
+        self.inner
+            .objects
+            .resource_limiter_mut()
+            .set_limiter(Some(Box::new(limiter)));
+    }
+
+    #[cfg(feature = "sys")]
+    /// Returns the resources used by all the instances, memories and tables
+    /// of this store.
+    pub fn resource_usage(&self) -> ResourceUsage {
+        *self.inner.objects.resource_limiter().usage()
+    }
+
     /// Returns the [`Engine`].
     pub fn engine(&self) -> &Engine {
         &self.inner.engine
@@ -193,6 +261,9 @@ pub struct StoreMut<'a> {
     pub(crate) inner: &'a mut StoreInner,
 }
 
+// A `StoreMut` borrows a `Store`, which is `Send`, mutably.
+unsafe impl Send for StoreMut<'_> {}
+
 impl<'a> StoreMut<'a> {
     /// Returns the [`Engine`].
     pub fn engine(&self) -> &Engine {
@@ -4,9 +4,7 @@ use shared_buffer::OwnedBuffer;
 pub use wasmer_compiler::{
     Artifact, BaseTunables, CompilerConfig, Engine, EngineBuilder, Tunables,
 };
-#[cfg(feature = "compiler")]
-use wasmer_types::Features;
-use wasmer_types::{DeserializeError, Target};
+use wasmer_types::{DeserializeError, Features, Target};
 
 /// Get the default config for the sys Engine
 #[allow(unreachable_code)]
@@ -75,12 +73,22 @@ pub trait NativeEngineExt {
     /// Gets the target
     fn target(&self) -> &Target;
 
+    /// Gets the WebAssembly features enabled in the engine
+    fn features(&self) -> Features;
+
     /// Attach a Tunable to this engine
     fn set_tunables(&mut self, tunables: impl Tunables + Send + Sync + 'static);
 
     /// Get a reference to attached Tunable of this engine
     fn tunables(&self) -> &dyn Tunables;
 
+    /// Increments the epoch of this engine, interrupting the code running
+    /// in the stores whose epoch deadline is reached.
+    ///
+    /// This can be called from any thread. Only the modules compiled with
+    /// the `EpochInterruption` middleware check the epoch.
+    fn increment_epoch(&self);
+
     /// Load a serialized WebAssembly module from a memory mapped file and deserialize it.
     ///
     /// NOTE: you should almost always prefer [`Self::deserialize_from_mmapped_file`].
@@ -116,6 +124,10 @@ impl NativeEngineExt for crate::engine::Engine {
         self.0.target()
     }
 
+    fn features(&self) -> Features {
+        self.0.inner().features().clone()
+    }
+
     fn set_tunables(&mut self, tunables: impl Tunables + Send + Sync + 'static) {
         self.0.set_tunables(tunables)
     }
@@ -124,6 +136,10 @@ impl NativeEngineExt for crate::engine::Engine {
         self.0.tunables()
     }
 
+    fn increment_epoch(&self) {
+        self.0.increment_epoch()
+    }
+
     unsafe fn deserialize_from_mmapped_file_unchecked(
         &self,
         file_ref: &Path,
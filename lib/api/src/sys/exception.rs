@@ -0,0 +1,90 @@
+use crate::store::{AsStoreMut, AsStoreRef};
+use crate::{RuntimeError, Tag, Value};
+use wasmer_vm::{StoreHandle, VMException};
+
+/// A WebAssembly exception, thrown with a [`Tag`] and carrying the values
+/// described by the type of the tag.
+///
+/// An exception thrown by WebAssembly code and not caught by it reaches the
+/// host as a [`RuntimeError`], from which it can be retrieved with
+/// [`RuntimeError::exception`]. Conversely, a host function throws an
+/// exception to the WebAssembly code that called it by returning it as a
+/// [`RuntimeError`], so that it can be caught there.
+#[derive(Debug, Clone)]
+pub struct Exception {
+    exception: VMException,
+}
+
+impl Exception {
+    /// Create a new exception with the given tag and payload.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Exception, RuntimeError, Store, Tag, Type, Value};
+    /// # let mut store = Store::default();
+    /// #
+    /// let tag = Tag::new(&mut store, [Type::I32]);
+    /// let exception = Exception::new(&mut store, &tag, &[Value::I32(42)]).unwrap();
+    ///
+    /// let error: RuntimeError = exception.into();
+    /// let exception = error.exception().unwrap();
+    /// assert_eq!(exception.tag(&store), tag);
+    /// assert_eq!(exception.payload(&mut store), vec![Value::I32(42)]);
+    /// ```
+    pub fn new(
+        store: &mut impl AsStoreMut,
+        tag: &Tag,
+        payload: &[Value],
+    ) -> Result<Self, RuntimeError> {
+        if !tag.is_from_store(store) || !payload.iter().all(|v| v.is_from_store(store)) {
+            return Err(RuntimeError::new("cross-`Store` values are not supported"));
+        }
+        let ty = tag.ty(store);
+        let types = payload.iter().map(Value::ty).collect::<Vec<_>>();
+        if types != ty.params() {
+            return Err(RuntimeError::new(format!(
+                "Attempted to throw an exception carrying {types:?} with a tag of type {ty}",
+            )));
+        }
+        let payload = payload.iter().map(|v| v.as_raw(store)).collect();
+        Ok(Self {
+            exception: VMException::new(tag.internal_handle(), payload),
+        })
+    }
+
+    /// Returns the [`Tag`] the exception was thrown with.
+    pub fn tag(&self, store: &impl AsStoreRef) -> Tag {
+        let handle = unsafe {
+            StoreHandle::from_internal(store.as_store_ref().objects().id(), self.exception.tag())
+        };
+        Tag::from_handle(handle)
+    }
+
+    /// Returns the values carried by the exception.
+    pub fn payload(&self, store: &mut impl AsStoreMut) -> Vec<Value> {
+        let ty = self.tag(store).ty(store);
+        ty.params()
+            .iter()
+            .zip(self.exception.payload())
+            .map(|(ty, raw)| unsafe { Value::from_raw(store, *ty, *raw) })
+            .collect()
+    }
+}
+
+impl From<Exception> for RuntimeError {
+    fn from(exception: Exception) -> Self {
+        Self::user(Box::new(exception.exception))
+    }
+}
+
+impl RuntimeError {
+    /// Returns the WebAssembly exception carried by this error, if it is one
+    /// that was thrown and not caught.
+    pub fn exception(&self) -> Option<Exception> {
+        let exception = VMException::find(&self.inner.source)?;
+        Some(Exception {
+            exception: exception.clone(),
+        })
+    }
+}
@@ -1,4 +1,4 @@
-use crate::externals::function::{HostFunction, WithEnv, WithoutEnv};
+use crate::externals::function::{HostFunction, HostFunctionFuture, WithEnv, WithoutEnv};
 use crate::native_type::{FromToNativeWasmType, IntoResult, NativeWasmTypeInto, WasmTypeList};
 use crate::store::{AsStoreMut, AsStoreRef, StoreInner, StoreMut};
 use crate::sys::engine::NativeEngineExt;
@@ -8,9 +8,10 @@ use std::panic::{self, AssertUnwindSafe};
 use std::{cell::UnsafeCell, cmp::max, ffi::c_void};
 use wasmer_types::{NativeWasmType, RawValue};
 use wasmer_vm::{
-    on_host_stack, raise_user_trap, resume_panic, wasmer_call_trampoline, MaybeInstanceOwned,
-    StoreHandle, VMCallerCheckedAnyfunc, VMContext, VMDynamicFunctionContext, VMExtern, VMFuncRef,
-    VMFunction, VMFunctionContext, VMFunctionKind, VMTrampoline,
+    on_host_stack, raise_user_trap, resume_panic, suspend_until_ready, wasmer_call_trampoline,
+    wasmer_call_trampoline_async, MaybeInstanceOwned, StoreHandle, VMCallerCheckedAnyfunc,
+    VMContext, VMDynamicFunctionContext, VMExtern, VMFuncRef, VMFunction, VMFunctionContext,
+    VMFunctionKind, VMTrampoline,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -45,36 +46,74 @@ impl Function {
         let wrapper = move |values_vec: *mut RawValue| -> Result<(), RuntimeError> {
             unsafe {
                 let mut store = StoreMut::from_raw(raw_store as *mut StoreInner);
-                let mut args = Vec::with_capacity(func_ty.params().len());
-                for (i, ty) in func_ty.params().iter().enumerate() {
-                    args.push(Value::from_raw(&mut store, *ty, *values_vec.add(i)));
-                }
+                let args = dynamic_args(&mut store, &func_ty, values_vec);
                 let store_mut = StoreMut::from_raw(raw_store as *mut StoreInner);
                 let env = FunctionEnvMut {
                     store_mut,
                     func_env: func_env.clone(),
                 };
                 let returns = func(env, &args)?;
+                dynamic_returns(&store, &func_ty, &returns, values_vec)
+            }
+        };
+        Self::new_dynamic(store, function_type, wrapper, false)
+    }
 
-                // We need to dynamically check that the returns
-                // match the expected types, as well as expected length.
-                let return_types = returns.iter().map(|ret| ret.ty());
-                if return_types.ne(func_ty.results().iter().copied()) {
-                    return Err(RuntimeError::new(format!(
-                        "Dynamic function returned wrong signature. Expected {:?} but got {:?}",
-                        func_ty.results(),
-                        returns.iter().map(|ret| ret.ty())
-                    )));
-                }
-                for (i, ret) in returns.iter().enumerate() {
-                    *values_vec.add(i) = ret.as_raw(&store);
-                }
+    pub fn new_async_with_env<FT, F, T: Send + 'static>(
+        store: &mut impl AsStoreMut,
+        env: &FunctionEnv<T>,
+        ty: FT,
+        func: F,
+    ) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: for<'a> Fn(FunctionEnvMut<'a, T>, Vec<Value>) -> HostFunctionFuture<'a>
+            + 'static
+            + Send
+            + Sync,
+    {
+        let function_type = ty.into();
+        let func_ty = function_type.clone();
+        let func_env = env.clone();
+        let raw_store = store.as_store_mut().as_raw() as *mut u8;
+        let wrapper = move |values_vec: *mut RawValue| -> Result<(), RuntimeError> {
+            unsafe {
+                let mut store = StoreMut::from_raw(raw_store as *mut StoreInner);
+                let args = dynamic_args(&mut store, &func_ty, values_vec);
+                let store_mut = StoreMut::from_raw(raw_store as *mut StoreInner);
+                let env = FunctionEnvMut {
+                    store_mut,
+                    func_env: func_env.clone(),
+                };
+                // The host function is only invoked once the future is
+                // polled, on the host stack.
+                let returns =
+                    suspend_until_ready(async { func(env, args).await }).ok_or_else(|| {
+                        RuntimeError::new(
+                            "async host functions can only be called from `call_async`",
+                        )
+                    })??;
+                dynamic_returns(&store, &func_ty, &returns, values_vec)
             }
-            Ok(())
         };
+        Self::new_dynamic(store, function_type, wrapper, true)
+    }
+
+    fn new_dynamic<F>(
+        store: &mut impl AsStoreMut,
+        function_type: FunctionType,
+        wrapper: F,
+        suspendable: bool,
+    ) -> Self
+    where
+        F: Fn(*mut RawValue) -> Result<(), RuntimeError> + 'static,
+    {
         let mut host_data = Box::new(VMDynamicFunctionContext {
             address: std::ptr::null(),
-            ctx: DynamicFunction { func: wrapper },
+            ctx: DynamicFunction {
+                func: wrapper,
+                suspendable,
+            },
         });
         host_data.address = host_data.ctx.func_body_ptr();
 
@@ -213,6 +252,21 @@ impl Function {
         params: &[Value],
         results: &mut [Value],
     ) -> Result<(), RuntimeError> {
+        let values_vec = self.call_values_vec(store, params, results)?;
+
+        // Invoke the call
+        self.call_wasm_raw(store, trampoline, values_vec, results)?;
+        Ok(())
+    }
+
+    /// Checks the parameters and results of a call against the signature,
+    /// and stores the parameters into the values passed to the trampoline.
+    fn call_values_vec(
+        &self,
+        store: &mut impl AsStoreMut,
+        params: &[Value],
+        results: &[Value],
+    ) -> Result<Vec<RawValue>, RuntimeError> {
         let format_types_for_error_message = |items: &[Value]| {
             items
                 .iter()
@@ -255,9 +309,7 @@ impl Function {
             *slot = arg.as_raw(store);
         }
 
-        // Invoke the call
-        self.call_wasm_raw(store, trampoline, values_vec, results)?;
-        Ok(())
+        Ok(values_vec)
     }
 
     fn call_wasm_raw(
@@ -308,15 +360,79 @@ impl Function {
             return Err(error.into());
         }
 
-        // Load the return values out of `values_vec`.
+        self.load_results(store, &params, results);
+        Ok(())
+    }
+
+    /// Loads the return values out of the values passed to the trampoline.
+    fn load_results(
+        &self,
+        store: &mut impl AsStoreMut,
+        values_vec: &[RawValue],
+        results: &mut [Value],
+    ) {
         let signature = self.ty(store);
         for (index, &value_type) in signature.results().iter().enumerate() {
             unsafe {
-                results[index] = Value::from_raw(store, value_type, params[index]);
+                results[index] = Value::from_raw(store, value_type, values_vec[index]);
             }
         }
+    }
 
-        Ok(())
+    pub async fn call_async(
+        &self,
+        store: &mut impl AsStoreMut,
+        params: &[Value],
+    ) -> Result<Box<[Value]>, RuntimeError> {
+        let mut results = vec![Value::null(); self.result_arity(store)];
+        let mut values_vec = self.call_values_vec(store, params, &results)?;
+        self.call_wasm_async(store, &mut values_vec).await?;
+        self.load_results(store, &values_vec, &mut results);
+        Ok(results.into_boxed_slice())
+    }
+
+    /// Calls the function with the arguments in `values_vec`, on a stack
+    /// that the async host functions can suspend. The results are written
+    /// to `values_vec`.
+    pub(crate) async fn call_wasm_async(
+        &self,
+        store: &mut impl AsStoreMut,
+        values_vec: &mut [RawValue],
+    ) -> Result<(), RuntimeError> {
+        // The `on_called` handling mirrors `call_wasm_raw`.
+        loop {
+            let call = {
+                let storeref = store.as_store_ref();
+                let vm_function = self.handle.get(storeref.objects());
+                let config = storeref.engine().tunables().vmconfig();
+                unsafe {
+                    let anyfunc = vm_function.anyfunc.as_ptr().as_ref();
+                    wasmer_call_trampoline_async(
+                        storeref.signal_handler(),
+                        config,
+                        anyfunc.vmctx,
+                        anyfunc.call_trampoline,
+                        anyfunc.func_ptr,
+                        values_vec.as_mut_ptr() as *mut u8,
+                    )
+                }
+            };
+            let result = call.await;
+            let store_mut = store.as_store_mut();
+            if let Some(callback) = store_mut.inner.on_called.take() {
+                match callback(store_mut) {
+                    Ok(wasmer_types::OnCalledAction::InvokeAgain) => {
+                        continue;
+                    }
+                    Ok(wasmer_types::OnCalledAction::Finish) => {}
+                    Ok(wasmer_types::OnCalledAction::Trap(trap)) => {
+                        return Err(RuntimeError::user(trap));
+                    }
+                    Err(trap) => return Err(RuntimeError::user(trap)),
+                }
+            }
+            return result.map_err(Into::into);
+        }
     }
 
     pub fn result_arity(&self, store: &impl AsStoreRef) -> usize {
@@ -410,6 +526,9 @@ impl Function {
 /// Host state for a dynamic function.
 pub(crate) struct DynamicFunction<F> {
     func: F,
+    /// Whether the function suspends the Wasm stack, see
+    /// [`Function::new_async_with_env`].
+    suspendable: bool,
 }
 
 impl<F> DynamicFunction<F>
@@ -422,8 +541,13 @@ where
         this: &mut VMDynamicFunctionContext<Self>,
         values_vec: *mut RawValue,
     ) {
-        let result =
-            on_host_stack(|| panic::catch_unwind(AssertUnwindSafe(|| (this.ctx.func)(values_vec))));
+        // Async host functions only poll their future on the host stack,
+        // since they suspend the Wasm stack in between.
+        let result = if this.ctx.suspendable {
+            Ok((this.ctx.func)(values_vec))
+        } else {
+            on_host_stack(|| panic::catch_unwind(AssertUnwindSafe(|| (this.ctx.func)(values_vec))))
+        };
 
         match result {
             Ok(Ok(())) => {}
@@ -452,6 +576,42 @@ where
     }
 }
 
+/// Reads the arguments of a dynamic function.
+unsafe fn dynamic_args(
+    store: &mut StoreMut,
+    func_ty: &FunctionType,
+    values_vec: *mut RawValue,
+) -> Vec<Value> {
+    let mut args = Vec::with_capacity(func_ty.params().len());
+    for (i, ty) in func_ty.params().iter().enumerate() {
+        args.push(Value::from_raw(store, *ty, *values_vec.add(i)));
+    }
+    args
+}
+
+/// Writes the returns of a dynamic function.
+unsafe fn dynamic_returns(
+    store: &StoreMut,
+    func_ty: &FunctionType,
+    returns: &[Value],
+    values_vec: *mut RawValue,
+) -> Result<(), RuntimeError> {
+    // We need to dynamically check that the returns
+    // match the expected types, as well as expected length.
+    let return_types = returns.iter().map(|ret| ret.ty());
+    if return_types.ne(func_ty.results().iter().copied()) {
+        return Err(RuntimeError::new(format!(
+            "Dynamic function returned wrong signature. Expected {:?} but got {:?}",
+            func_ty.results(),
+            returns.iter().map(|ret| ret.ty())
+        )));
+    }
+    for (i, ret) in returns.iter().enumerate() {
+        *values_vec.add(i) = ret.as_raw(store);
+    }
+    Ok(())
+}
+
 /// Represents a low-level Wasm static host function. See
 /// [`crate::Function::new_typed`] and
 /// [`crate::Function::new_typed_with_env`] to learn more.
@@ -26,6 +26,16 @@ pub struct Memory {
 impl Memory {
     pub fn new(store: &mut impl AsStoreMut, ty: MemoryType) -> Result<Self, MemoryError> {
         let mut store = store.as_store_mut();
+        let allowed = store
+            .objects_mut()
+            .resource_limiter_mut()
+            .memory_creating(&ty)
+            .map_err(|trap| MemoryError::Generic(trap.to_string()))?;
+        if !allowed {
+            return Err(MemoryError::Generic(
+                "the resource limiter of the store denied the memory creation".to_string(),
+            ));
+        }
         let tunables = store.engine().tunables();
         let style = tunables.memory_style(&ty);
         let memory = tunables.create_host_memory(&ty, &style)?;
@@ -52,7 +62,15 @@ impl Memory {
     where
         IntoPages: Into<Pages>,
     {
-        self.handle.get_mut(store.objects_mut()).grow(delta.into())
+        let objects = store.objects_mut();
+        assert_eq!(
+            self.handle.store_id(),
+            objects.id(),
+            "object used with the wrong context"
+        );
+        objects
+            .grow_memory(self.handle.internal_handle(), delta.into())
+            .unwrap_or_else(|trap| Err(MemoryError::Generic(trap.to_string())))
     }
 
     pub fn grow_at_least(
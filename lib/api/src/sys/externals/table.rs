@@ -55,6 +55,15 @@ impl Table {
     ) -> Result<Self, RuntimeError> {
         let item = value_to_table_element(&mut store, init)?;
         let mut store = store.as_store_mut();
+        let allowed = store
+            .objects_mut()
+            .resource_limiter_mut()
+            .table_creating(&ty)?;
+        if !allowed {
+            return Err(RuntimeError::new(
+                "the resource limiter of the store denied the table creation",
+            ));
+        }
         let tunables = store.engine().tunables();
         let style = tunables.table_style(&ty);
         let mut table = tunables
@@ -101,9 +110,14 @@ impl Table {
         init: Value,
     ) -> Result<u32, RuntimeError> {
         let item = value_to_table_element(store, init)?;
-        self.handle
-            .get_mut(store.objects_mut())
-            .grow(delta, item)
+        let objects = store.objects_mut();
+        assert_eq!(
+            self.handle.store_id(),
+            objects.id(),
+            "object used with the wrong context"
+        );
+        objects
+            .grow_table(self.handle.internal_handle(), delta, item)?
             .ok_or_else(|| RuntimeError::new(format!("failed to grow table by `{}`", delta)))
     }
 
@@ -0,0 +1,94 @@
+use crate::exports::{ExportError, Exportable};
+use crate::store::{AsStoreMut, AsStoreRef};
+use crate::{Extern, TagType, Type};
+use wasmer_vm::{InternalStoreHandle, StoreHandle, VMExtern, VMTag};
+
+/// A WebAssembly exception `tag` instance.
+///
+/// A tag identifies the exceptions thrown with it and describes the values
+/// they carry. Two tags are distinct even if they have the same type: an
+/// exception is only caught by a handler for the very tag it was thrown with.
+///
+/// Spec: <https://webassembly.github.io/exception-handling/core/exec/runtime.html#tag-instances>
+#[derive(Debug, Clone)]
+pub struct Tag {
+    handle: StoreHandle<VMTag>,
+}
+
+impl Tag {
+    /// Create a new `Tag` for exceptions carrying values of the given types.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Store, Tag, Type};
+    /// # let mut store = Store::default();
+    /// #
+    /// let tag = Tag::new(&mut store, [Type::I32, Type::F64]);
+    ///
+    /// assert_eq!(tag.ty(&store).params(), &[Type::I32, Type::F64]);
+    /// ```
+    pub fn new<Params>(store: &mut impl AsStoreMut, params: Params) -> Self
+    where
+        Params: Into<Box<[Type]>>,
+    {
+        Self::from_type(store, TagType::new(params))
+    }
+
+    /// Create a new `Tag` with the given [`TagType`].
+    pub fn from_type(store: &mut impl AsStoreMut, ty: TagType) -> Self {
+        Self {
+            handle: StoreHandle::new(store.objects_mut(), VMTag::new(ty)),
+        }
+    }
+
+    /// Returns the [`TagType`] of the `Tag`.
+    pub fn ty(&self, store: &impl AsStoreRef) -> TagType {
+        self.handle.get(store.as_store_ref().objects()).ty().clone()
+    }
+
+    pub(crate) fn from_vm_extern(
+        store: &mut impl AsStoreMut,
+        vm_extern: InternalStoreHandle<VMTag>,
+    ) -> Self {
+        Self {
+            handle: unsafe {
+                StoreHandle::from_internal(store.as_store_ref().objects().id(), vm_extern)
+            },
+        }
+    }
+
+    pub(crate) fn from_handle(handle: StoreHandle<VMTag>) -> Self {
+        Self { handle }
+    }
+
+    pub(crate) fn internal_handle(&self) -> InternalStoreHandle<VMTag> {
+        self.handle.internal_handle()
+    }
+
+    /// Checks whether this `Tag` can be used with the given store.
+    pub fn is_from_store(&self, store: &impl AsStoreRef) -> bool {
+        self.handle.store_id() == store.as_store_ref().objects().id()
+    }
+
+    pub(crate) fn to_vm_extern(&self) -> VMExtern {
+        VMExtern::Tag(self.handle.internal_handle())
+    }
+}
+
+impl std::cmp::PartialEq for Tag {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle
+    }
+}
+
+impl std::cmp::Eq for Tag {}
+
+impl<'a> Exportable<'a> for Tag {
+    fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
+        match _extern {
+            Extern::Tag(tag) => Ok(tag),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+}
@@ -1,20 +1,28 @@
 pub(crate) mod engine;
 pub(crate) mod errors;
+pub(crate) mod exception;
 pub(crate) mod extern_ref;
 pub(crate) mod externals;
 pub(crate) mod instance;
 pub(crate) mod mem_access;
 pub(crate) mod module;
+pub(crate) mod snapshot;
 pub(super) mod tunables;
 pub(crate) mod typed_function;
 
 pub use crate::sys::engine::{get_default_compiler_config, NativeEngineExt};
-pub use crate::sys::tunables::BaseTunables;
+pub use crate::sys::exception::Exception;
+pub use crate::sys::externals::tag::Tag;
+pub use crate::sys::snapshot::{SnapshotError, SnapshotInit};
+pub use crate::sys::tunables::{
+    BaseTunables, PoolingConfig, PoolingInstanceAllocator, PoolingTunables,
+};
 #[cfg(feature = "compiler")]
 pub use wasmer_compiler::{
     wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareReaderState, ModuleMiddleware,
+    TieringConfig,
 };
-pub use wasmer_compiler::{Artifact, EngineBuilder, Features, Tunables};
+pub use wasmer_compiler::{Artifact, EngineBuilder, Features, ProfilingStrategy, Tunables};
 #[cfg(feature = "cranelift")]
 pub use wasmer_compiler_cranelift::{Cranelift, CraneliftOptLevel};
 #[cfg(feature = "llvm")]
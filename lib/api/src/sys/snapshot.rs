@@ -0,0 +1,628 @@
+//! Pre-initialization of WebAssembly modules.
+//!
+//! [`SnapshotInit`] instantiates a module, calls one of its exported
+//! functions, and writes a new module whose memories and globals start in
+//! the state that function left them in. The work done by the function,
+//! like loading the standard library of an interpreter, is then done once
+//! instead of by every instance of the module.
+
+use crate::{
+    AsStoreMut, AsStoreRef, CompileError, ExportError, Function, Imports, Instance,
+    InstantiationError, MemoryAccessError, Module, RuntimeError, Value,
+};
+use std::borrow::Cow;
+use std::error::Error;
+use thiserror::Error;
+use wasmer_compiler::wasmparser::{
+    BinaryReader, BinaryReaderError, DataKind, DataSectionReader, ExportSectionReader,
+    ExternalKind, GlobalSectionReader, ImportSectionReader, MemorySectionReader, TypeRef,
+};
+
+/// The prefix of the exports added to read the defined globals.
+const GLOBAL_EXPORT_PREFIX: &str = "__wasmer_snapshot_global_";
+/// The prefix of the exports added to read the defined memories.
+const MEMORY_EXPORT_PREFIX: &str = "__wasmer_snapshot_memory_";
+/// Runs of zeros up to this length are kept in data segments instead of
+/// splitting them, which would take more space.
+const MAX_SEGMENT_GAP: usize = 16;
+/// The function initializing WASI reactors, called by the runtimes when
+/// instantiating them.
+const INITIALIZE_FUNC: &str = "_initialize";
+
+const WASM_HEADER: &[u8] = b"\0asm\x01\0\0\0";
+
+const SECTION_CUSTOM: u8 = 0;
+const SECTION_IMPORT: u8 = 2;
+const SECTION_MEMORY: u8 = 5;
+const SECTION_GLOBAL: u8 = 6;
+const SECTION_EXPORT: u8 = 7;
+const SECTION_START: u8 = 8;
+const SECTION_DATA: u8 = 11;
+const SECTION_DATA_COUNT: u8 = 12;
+
+/// An error while pre-initializing a module.
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    /// The module couldn't be parsed.
+    #[error("invalid WebAssembly module: {0}")]
+    Parse(#[from] BinaryReaderError),
+    /// The module uses a feature which can't be pre-initialized.
+    #[error("unsupported module: {0}")]
+    Unsupported(String),
+    /// The module couldn't be compiled.
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+    /// The module couldn't be instantiated.
+    #[error("failed to instantiate the module: {0}")]
+    Instantiation(Box<dyn Error + Send + Sync>),
+    /// The init function or a snapshotted item is not exported.
+    #[error(transparent)]
+    Export(#[from] ExportError),
+    /// The init function failed.
+    #[error("the init function failed: {0}")]
+    Init(#[from] RuntimeError),
+    /// A memory couldn't be read.
+    #[error(transparent)]
+    MemoryAccess(#[from] MemoryAccessError),
+}
+
+impl From<InstantiationError> for SnapshotError {
+    fn from(error: InstantiationError) -> Self {
+        Self::Instantiation(Box::new(error))
+    }
+}
+
+/// Pre-initializes a module by snapshotting its state after an init
+/// function ran.
+///
+/// The snapshot captures the contents and sizes of the memories defined by
+/// the module, and the values of its numeric globals. Modules importing a
+/// memory are not supported, and tables are not captured: they are
+/// initialized by the element segments again. The start section is removed,
+/// as its effects are part of the snapshot.
+///
+/// When the module is a WASI reactor, exporting an `_initialize` function,
+/// that function is called before the init function, as a WASI runtime
+/// would, unless [`call_initialize`][Self::call_initialize] is turned off
+/// because the instantiation already called it. The `_initialize` export is
+/// removed from the pre-initialized module so that it doesn't run again.
+///
+/// # Example
+///
+/// ```rust
+/// # use wasmer::{imports, Instance, Module, SnapshotInit, Store, TypedFunction};
+/// # fn main() -> anyhow::Result<()> {
+/// let wasm = wat::parse_str(r#"
+///     (module
+///       (global $answer (mut i32) (i32.const 0))
+///       (func (export "init") (global.set $answer (i32.const 42)))
+///       (func (export "answer") (result i32) (global.get $answer)))
+/// "#)?;
+///
+/// let mut store = Store::default();
+/// let snapshot = SnapshotInit::new("init").run(&mut store, &wasm, &imports! {})?;
+///
+/// let module = Module::new(&store, &snapshot)?;
+/// let instance = Instance::new(&mut store, &module, &imports! {})?;
+/// let answer: TypedFunction<(), i32> = instance.exports.get_typed_function(&store, "answer")?;
+/// assert_eq!(answer.call(&mut store)?, 42);
+/// assert!(instance.exports.get_function("init").is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SnapshotInit {
+    init_func: String,
+    keep_init_func: bool,
+    call_initialize: bool,
+}
+
+impl SnapshotInit {
+    /// Creates a pre-initializer calling the given exported function,
+    /// which must take no parameters and return no results.
+    pub fn new(init_func: impl Into<String>) -> Self {
+        Self {
+            init_func: init_func.into(),
+            keep_init_func: false,
+            call_initialize: true,
+        }
+    }
+
+    /// Whether the init function stays exported by the pre-initialized
+    /// module. It is removed by default.
+    pub fn keep_init_func(mut self, keep: bool) -> Self {
+        self.keep_init_func = keep;
+        self
+    }
+
+    /// Whether the `_initialize` function of WASI reactors is called before
+    /// the init function. It is called by default.
+    pub fn call_initialize(mut self, call: bool) -> Self {
+        self.call_initialize = call;
+        self
+    }
+
+    /// Pre-initializes `wasm`, instantiating it with `imports`, and returns
+    /// the pre-initialized module.
+    pub fn run(
+        &self,
+        store: &mut impl AsStoreMut,
+        wasm: &[u8],
+        imports: &Imports,
+    ) -> Result<Vec<u8>, SnapshotError> {
+        self.run_with(store, wasm, |store, module| {
+            Instance::new(store, module, imports).map_err(Into::into)
+        })
+    }
+
+    /// Pre-initializes `wasm`, instantiating it with `instantiate`, and
+    /// returns the pre-initialized module.
+    ///
+    /// The module given to `instantiate` exports all the globals and
+    /// memories it defines, on top of the exports of `wasm`.
+    pub fn run_with<S, F>(
+        &self,
+        store: &mut S,
+        wasm: &[u8],
+        instantiate: F,
+    ) -> Result<Vec<u8>, SnapshotError>
+    where
+        S: AsStoreMut,
+        F: FnOnce(&mut S, &Module) -> Result<Instance, Box<dyn Error + Send + Sync>>,
+    {
+        let parsed = ParsedModule::parse(wasm)?;
+        if parsed.imported_memories > 0 {
+            return Err(SnapshotError::Unsupported(
+                "imported memories can't be snapshotted".to_string(),
+            ));
+        }
+
+        let module = Module::new(&store.as_store_ref(), parsed.instrument()?)?;
+        let instance = instantiate(store, &module).map_err(SnapshotError::Instantiation)?;
+        let init = Self::get_init_func(store, &instance, &self.init_func)?;
+        if self.call_initialize && self.init_func != INITIALIZE_FUNC {
+            if let Ok(initialize) = Self::get_init_func(store, &instance, INITIALIZE_FUNC) {
+                initialize.call(store, &[])?;
+            }
+        }
+        init.call(store, &[])?;
+
+        let globals = (0..parsed.defined_globals)
+            .map(|index| {
+                let name = format!("{GLOBAL_EXPORT_PREFIX}{}", parsed.imported_globals + index);
+                Ok(instance.exports.get_global(&name)?.get(store))
+            })
+            .collect::<Result<Vec<_>, SnapshotError>>()?;
+        let memories = (0..parsed.defined_memories)
+            .map(|index| {
+                let name = format!("{MEMORY_EXPORT_PREFIX}{index}");
+                let view = instance.exports.get_memory(&name)?.view(store);
+                Ok((view.size().0 as u64, view.copy_to_vec()?))
+            })
+            .collect::<Result<Vec<_>, SnapshotError>>()?;
+
+        let mut removed_exports = vec![INITIALIZE_FUNC];
+        if !self.keep_init_func {
+            removed_exports.push(&self.init_func);
+        }
+        parsed.snapshot(&globals, &memories, &removed_exports)
+    }
+
+    /// Returns the exported function `name`, which must take no parameters
+    /// and return no results.
+    fn get_init_func(
+        store: &impl AsStoreRef,
+        instance: &Instance,
+        name: &str,
+    ) -> Result<Function, SnapshotError> {
+        let func = instance.exports.get_function(name)?;
+        let ty = func.ty(store);
+        if !ty.params().is_empty() || !ty.results().is_empty() {
+            return Err(SnapshotError::Unsupported(format!(
+                "the init function `{}` has type {}, instead of [] -> []",
+                name, ty
+            )));
+        }
+        Ok(func.clone())
+    }
+}
+
+/// A section of a module, with its contents.
+#[derive(Clone)]
+struct Section<'a> {
+    id: u8,
+    // The offset of the contents in the module.
+    offset: usize,
+    contents: Cow<'a, [u8]>,
+}
+
+/// The sections of a module, and the counts of its memories and globals.
+struct ParsedModule<'a> {
+    wasm: &'a [u8],
+    sections: Vec<Section<'a>>,
+    imported_memories: u32,
+    imported_globals: u32,
+    defined_memories: u32,
+    defined_globals: u32,
+}
+
+impl<'a> ParsedModule<'a> {
+    fn parse(wasm: &'a [u8]) -> Result<Self, SnapshotError> {
+        if !wasm.starts_with(WASM_HEADER) {
+            return Err(SnapshotError::Unsupported(
+                "only core WebAssembly modules can be snapshotted".to_string(),
+            ));
+        }
+        let mut reader =
+            BinaryReader::new_with_offset(&wasm[WASM_HEADER.len()..], WASM_HEADER.len());
+        let mut parsed = Self {
+            wasm,
+            sections: Vec::new(),
+            imported_memories: 0,
+            imported_globals: 0,
+            defined_memories: 0,
+            defined_globals: 0,
+        };
+        while !reader.eof() {
+            let id = reader.read_u8()?;
+            let len = reader.read_var_u32()? as usize;
+            let offset = reader.original_position();
+            let contents = reader.read_bytes(len)?;
+            match id {
+                SECTION_IMPORT => {
+                    for import in ImportSectionReader::new(contents, offset)? {
+                        match import?.ty {
+                            TypeRef::Memory(_) => parsed.imported_memories += 1,
+                            TypeRef::Global(_) => parsed.imported_globals += 1,
+                            _ => {}
+                        }
+                    }
+                }
+                SECTION_MEMORY => {
+                    parsed.defined_memories = MemorySectionReader::new(contents, offset)?.count()
+                }
+                SECTION_GLOBAL => {
+                    parsed.defined_globals = GlobalSectionReader::new(contents, offset)?.count()
+                }
+                _ => {}
+            }
+            parsed.sections.push(Section {
+                id,
+                offset,
+                contents: Cow::Borrowed(contents),
+            });
+        }
+        Ok(parsed)
+    }
+
+    fn section(&self, id: u8) -> Option<&Section<'a>> {
+        self.sections.iter().find(|section| section.id == id)
+    }
+
+    /// Returns the position of a section in a module, or `None` for custom
+    /// sections which can be anywhere.
+    fn section_order(id: u8) -> Option<u8> {
+        // The data count and tag sections are out of the order of their ids.
+        match id {
+            SECTION_CUSTOM => None,
+            1..=5 => Some(id),
+            13 => Some(6),
+            6..=9 => Some(id + 1),
+            SECTION_DATA_COUNT => Some(11),
+            10 => Some(12),
+            SECTION_DATA => Some(13),
+            _ => Some(u8::MAX),
+        }
+    }
+
+    /// Replaces the contents of a section, adding it if it is missing.
+    fn set_section(sections: &mut Vec<Section<'a>>, id: u8, contents: Vec<u8>) {
+        let contents = Cow::Owned(contents);
+        if let Some(section) = sections.iter_mut().find(|section| section.id == id) {
+            section.contents = contents;
+            return;
+        }
+        let order = Self::section_order(id);
+        let position = sections
+            .iter()
+            .position(|section| Self::section_order(section.id).map_or(false, |o| Some(o) > order))
+            .unwrap_or(sections.len());
+        sections.insert(
+            position,
+            Section {
+                id,
+                offset: 0,
+                contents,
+            },
+        );
+    }
+
+    fn encode(sections: &[Section<'_>]) -> Vec<u8> {
+        let mut wasm = WASM_HEADER.to_vec();
+        for section in sections {
+            wasm.push(section.id);
+            write_u32(&mut wasm, section.contents.len() as u32);
+            wasm.extend_from_slice(&section.contents);
+        }
+        wasm
+    }
+
+    /// Returns the exports of the module, without the `removed` ones.
+    fn exports(&self, removed: &[&str]) -> Result<Vec<(String, u8, u32)>, SnapshotError> {
+        let section = match self.section(SECTION_EXPORT) {
+            Some(section) => section,
+            None => return Ok(Vec::new()),
+        };
+        let mut exports = Vec::new();
+        for export in ExportSectionReader::new(&section.contents, section.offset)? {
+            let export = export?;
+            if removed.contains(&export.name) {
+                continue;
+            }
+            let kind = match export.kind {
+                ExternalKind::Func => 0,
+                ExternalKind::Table => 1,
+                ExternalKind::Memory => 2,
+                ExternalKind::Global => 3,
+                ExternalKind::Tag => 4,
+            };
+            exports.push((export.name.to_string(), kind, export.index));
+        }
+        Ok(exports)
+    }
+
+    fn encode_exports(exports: &[(String, u8, u32)]) -> Vec<u8> {
+        let mut contents = Vec::new();
+        write_u32(&mut contents, exports.len() as u32);
+        for (name, kind, index) in exports {
+            write_u32(&mut contents, name.len() as u32);
+            contents.extend_from_slice(name.as_bytes());
+            contents.push(*kind);
+            write_u32(&mut contents, *index);
+        }
+        contents
+    }
+
+    /// Returns the module exporting all its defined globals and memories.
+    fn instrument(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mut exports = self.exports(&[])?;
+        for index in 0..self.defined_globals {
+            let index = self.imported_globals + index;
+            exports.push((format!("{GLOBAL_EXPORT_PREFIX}{index}"), 3, index));
+        }
+        for index in 0..self.defined_memories {
+            exports.push((format!("{MEMORY_EXPORT_PREFIX}{index}"), 2, index));
+        }
+
+        let mut sections = self.sections.clone();
+        Self::set_section(
+            &mut sections,
+            SECTION_EXPORT,
+            Self::encode_exports(&exports),
+        );
+        Ok(Self::encode(&sections))
+    }
+
+    /// Returns the module starting with the given globals and memories.
+    fn snapshot(
+        &self,
+        globals: &[Value],
+        memories: &[(u64, Vec<u8>)],
+        removed_exports: &[&str],
+    ) -> Result<Vec<u8>, SnapshotError> {
+        let mut sections: Vec<Section<'a>> = self
+            .sections
+            .iter()
+            .filter(|section| section.id != SECTION_START)
+            .cloned()
+            .collect();
+
+        // The memories start with their current size.
+        let mut memory_types = Vec::new();
+        if let Some(section) = self.section(SECTION_MEMORY) {
+            let mut contents = Vec::new();
+            write_u32(&mut contents, memories.len() as u32);
+            for (memory, (pages, _)) in MemorySectionReader::new(&section.contents, section.offset)?
+                .into_iter()
+                .zip(memories)
+            {
+                let memory = memory?;
+                let flags = u8::from(memory.maximum.is_some())
+                    | u8::from(memory.shared) << 1
+                    | u8::from(memory.memory64) << 2;
+                contents.push(flags);
+                write_u64(&mut contents, *pages);
+                if let Some(maximum) = memory.maximum {
+                    write_u64(&mut contents, maximum);
+                }
+                memory_types.push(memory);
+            }
+            Self::set_section(&mut sections, SECTION_MEMORY, contents);
+        }
+
+        // The numeric globals are initialized with their current value,
+        // the other ones keep their initializer.
+        if let Some(section) = self.section(SECTION_GLOBAL) {
+            let end = section.offset + section.contents.len();
+            let items = GlobalSectionReader::new(&section.contents, section.offset)?
+                .into_iter_with_offsets()
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut contents = Vec::new();
+            write_u32(&mut contents, items.len() as u32);
+            for (i, ((offset, global), value)) in items.iter().zip(globals).enumerate() {
+                let init_offset = global.init_expr.get_binary_reader().original_position();
+                let item_end = items.get(i + 1).map_or(end, |(next, _)| *next);
+                contents.extend_from_slice(&self.wasm[*offset..init_offset]);
+                if !write_const(&mut contents, value) {
+                    contents.extend_from_slice(&self.wasm[init_offset..item_end]);
+                }
+            }
+            Self::set_section(&mut sections, SECTION_GLOBAL, contents);
+        }
+
+        let exports = self.exports(removed_exports)?;
+        if !exports.is_empty() || self.section(SECTION_EXPORT).is_some() {
+            Self::set_section(
+                &mut sections,
+                SECTION_EXPORT,
+                Self::encode_exports(&exports),
+            );
+        }
+
+        // The passive segments are kept, and the active ones are replaced by
+        // empty passive segments so that the data indices don't change.
+        let mut count = 0;
+        let mut contents = Vec::new();
+        if let Some(section) = self.section(SECTION_DATA) {
+            for data in DataSectionReader::new(&section.contents, section.offset)? {
+                let data = data?;
+                match data.kind {
+                    DataKind::Passive => contents.extend_from_slice(&self.wasm[data.range]),
+                    DataKind::Active { .. } => contents.extend_from_slice(&[0x01, 0x00]),
+                }
+                count += 1;
+            }
+        }
+        for (index, ((_, bytes), memory)) in memories.iter().zip(&memory_types).enumerate() {
+            for (start, end) in data_segments(bytes) {
+                if index == 0 {
+                    contents.push(0x00);
+                } else {
+                    contents.push(0x02);
+                    write_u32(&mut contents, index as u32);
+                }
+                if memory.memory64 {
+                    contents.push(0x42);
+                    write_i64(&mut contents, start as i64);
+                } else {
+                    contents.push(0x41);
+                    write_i64(&mut contents, start as u32 as i32 as i64);
+                }
+                contents.push(0x0b);
+                write_u32(&mut contents, (end - start) as u32);
+                contents.extend_from_slice(&bytes[start..end]);
+                count += 1;
+            }
+        }
+        if count > 0 {
+            let mut section = Vec::new();
+            write_u32(&mut section, count);
+            section.extend_from_slice(&contents);
+            Self::set_section(&mut sections, SECTION_DATA, section);
+            if self.section(SECTION_DATA_COUNT).is_some() {
+                let mut data_count = Vec::new();
+                write_u32(&mut data_count, count);
+                Self::set_section(&mut sections, SECTION_DATA_COUNT, data_count);
+            }
+        }
+
+        Ok(Self::encode(&sections))
+    }
+}
+
+/// Returns the ranges of `memory` to store in data segments, leaving out
+/// the zeros.
+fn data_segments(memory: &[u8]) -> Vec<(usize, usize)> {
+    let mut segments: Vec<(usize, usize)> = Vec::new();
+    let mut position = 0;
+    while let Some(start) = memory[position..].iter().position(|b| *b != 0) {
+        let start = position + start;
+        let end = memory[start..]
+            .iter()
+            .position(|b| *b == 0)
+            .map_or(memory.len(), |len| start + len);
+        match segments.last_mut() {
+            Some((_, last_end)) if start - *last_end <= MAX_SEGMENT_GAP => *last_end = end,
+            _ => segments.push((start, end)),
+        }
+        position = end;
+    }
+    segments
+}
+
+/// Writes the constant expression of a numeric value, returning `false`
+/// for the other values.
+fn write_const(bytes: &mut Vec<u8>, value: &Value) -> bool {
+    match value {
+        Value::I32(value) => {
+            bytes.push(0x41);
+            write_i64(bytes, *value as i64);
+        }
+        Value::I64(value) => {
+            bytes.push(0x42);
+            write_i64(bytes, *value);
+        }
+        Value::F32(value) => {
+            bytes.push(0x43);
+            bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+        Value::F64(value) => {
+            bytes.push(0x44);
+            bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+        Value::V128(value) => {
+            bytes.extend_from_slice(&[0xfd, 0x0c]);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        _ => return false,
+    }
+    bytes.push(0x0b);
+    true
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+    write_u64(bytes, value as u64)
+}
+
+fn write_u64(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn write_i64(bytes: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_segments_skip_long_runs_of_zeros() {
+        let mut memory = vec![0u8; 100];
+        memory[3..5].copy_from_slice(b"ab");
+        memory[10] = b'c';
+        memory[60..62].copy_from_slice(b"de");
+        memory[99] = b'f';
+        assert_eq!(data_segments(&memory), vec![(3, 11), (60, 62), (99, 100)]);
+        assert!(data_segments(&[0; 10]).is_empty());
+    }
+
+    #[test]
+    fn leb128() {
+        let mut bytes = Vec::new();
+        write_u32(&mut bytes, 624485);
+        assert_eq!(bytes, [0xe5, 0x8e, 0x26]);
+        bytes.clear();
+        write_i64(&mut bytes, -123456);
+        assert_eq!(bytes, [0xc0, 0xbb, 0x78]);
+        bytes.clear();
+        write_i64(&mut bytes, 64);
+        assert_eq!(bytes, [0xc0, 0x00]);
+    }
+}
@@ -1,4 +1,5 @@
-pub use wasmer_compiler::BaseTunables;
+pub use wasmer_compiler::{BaseTunables, PoolingTunables};
+pub use wasmer_vm::{PoolingConfig, PoolingInstanceAllocator};
 
 // All BaseTunable definition now is in wasmer_compile crate
 // Tests are still here
@@ -296,6 +297,65 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn check_pooling_tunables() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{imports, wat2wasm, Engine, Instance, Module, Store, TypedFunction};
+        use wasmer_compiler_cranelift::Cranelift;
+
+        let wasm_bytes = wat2wasm(
+            br#"(module
+            (memory 1 2)
+            (table 4 funcref)
+            (func (export "swap") (param i32) (result i32)
+              (i32.load (i32.const 0))
+              (i32.store (i32.const 0) (local.get 0))))
+          "#,
+        )?;
+        let base = BaseTunables {
+            static_memory_bound: Pages(2),
+            static_memory_offset_guard_size: WASM_PAGE_SIZE as u64,
+            dynamic_memory_offset_guard_size: WASM_PAGE_SIZE as u64,
+        };
+        let config = PoolingConfig::new()
+            .set_max_instances(2)
+            .set_max_memories(2)
+            .set_memory_reservation(3 * WASM_PAGE_SIZE)
+            .set_max_tables(2)
+            .set_max_table_elements(16);
+        let tunables = PoolingTunables::new(base, config)?;
+        let allocator = tunables.allocator().clone();
+
+        #[allow(deprecated)]
+        let mut engine = Engine::new(
+            Cranelift::default().into(),
+            Default::default(),
+            Default::default(),
+        );
+        engine.set_tunables(tunables);
+        let module = Module::new(&engine, wasm_bytes)?;
+
+        for _ in 0..3 {
+            let mut store = Store::new(engine.clone());
+            let instance = Instance::new(&mut store, &module, &imports! {})?;
+            assert_eq!(allocator.free_slots(), (1, 1, 1));
+            let swap: TypedFunction<i32, i32> =
+                instance.exports.get_typed_function(&store, "swap")?;
+            // The memory of a previous instance is zeroed when its slot is reused
+            assert_eq!(swap.call(&mut store, 42)?, 0);
+            assert_eq!(swap.call(&mut store, 7)?, 42);
+        }
+        assert_eq!(allocator.free_slots(), (2, 2, 2));
+
+        let mut store = Store::new(engine);
+        Instance::new(&mut store, &module, &imports! {})?;
+        Instance::new(&mut store, &module, &imports! {})?;
+        assert!(Instance::new(&mut store, &module, &imports! {}).is_err());
+        drop(store);
+        assert_eq!(allocator.free_slots(), (2, 2, 2));
+
+        Ok(())
+    }
+
     #[test]
     #[cfg(all(
         feature = "singlepass",
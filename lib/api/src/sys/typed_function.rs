@@ -1,4 +1,5 @@
 use crate::{FromToNativeWasmType, RuntimeError, TypedFunction, WasmTypeList};
+use std::cmp::max;
 use wasmer_types::RawValue;
 
 use crate::native_type::NativeWasmTypeInto;
@@ -101,6 +102,29 @@ macro_rules! impl_native_traits {
                 // Ok(Rets::from_c_struct(results))
             }
 
+            /// Call the typed func asynchronously and return results.
+            ///
+            /// See [`Function::call_async`](crate::Function::call_async).
+            #[allow(clippy::too_many_arguments)]
+            pub async fn call_async(&self, store: &mut impl AsStoreMut, $( $x: $x, )* ) -> Result<Rets, RuntimeError> {
+                // Ensure all parameters come from the same context.
+                if $(!FromToNativeWasmType::is_from_store(&$x, store) ||)* false {
+                    return Err(RuntimeError::new(
+                        "cross-`Store` values are not supported",
+                    ));
+                }
+                let params_list = [ $( $x.to_native().into_raw(store) ),* ];
+                let mut rets_list_array = Rets::empty_array();
+                let num_rets = rets_list_array.as_mut().len();
+                let mut args_rets = vec![RawValue { i32: 0 }; max(params_list.len(), num_rets)];
+                args_rets[..params_list.len()].clone_from_slice(&params_list);
+
+                self.func.0.call_wasm_async(store, &mut args_rets).await?;
+
+                rets_list_array.as_mut().clone_from_slice(&args_rets[..num_rets]);
+                Ok(unsafe { Rets::from_array(store, rets_list_array) })
+            }
+
             #[doc(hidden)]
             #[allow(missing_docs)]
             #[allow(unused_mut)]
@@ -0,0 +1,216 @@
+#[cfg(all(feature = "sys", feature = "cranelift"))]
+mod async_functions {
+    use anyhow::Result;
+    use std::future::Future;
+    use std::pin::{pin, Pin};
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+    use wasmer::*;
+
+    const WAT: &str = r#"
+    (module
+      (import "host" "sleep" (func $sleep (param i32) (result i32)))
+      (func (export "sum") (param $n i32) (result i32)
+        (local $sum i32)
+        (block $done
+          (loop $next
+            (br_if $done (i32.eqz (local.get $n)))
+            (local.set $sum (i32.add (local.get $sum) (call $sleep (local.get $n))))
+            (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+            (br $next)))
+        (local.get $sum)))
+    "#;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Polls `future` once, waking the current thread when it makes progress.
+    fn poll_once<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        future.poll(&mut Context::from_waker(&waker))
+    }
+
+    /// Runs `future` to completion, returning its output and the number of
+    /// times it was pending.
+    fn block_on<F: Future>(future: F) -> (F::Output, usize) {
+        let mut future = pin!(future);
+        let mut pending = 0;
+        loop {
+            match poll_once(future.as_mut()) {
+                Poll::Ready(output) => return (output, pending),
+                Poll::Pending => {
+                    pending += 1;
+                    thread::park();
+                }
+            }
+        }
+    }
+
+    /// A future that is pending once before being ready.
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    fn sleep(store: &mut Store) -> Function {
+        Function::new_async(store, ([Type::I32], [Type::I32]), |args| async move {
+            YieldNow(false).await;
+            Ok(vec![Value::I32(args[0].unwrap_i32() * 2)])
+        })
+    }
+
+    #[test]
+    fn call_async_suspends_on_async_host_functions() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let imports = imports! { "host" => { "sleep" => sleep(&mut store) } };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+
+        let sum = instance.exports.get_function("sum")?;
+        let (result, pending) = block_on(sum.call_async(&mut store, &[Value::I32(10)]));
+        assert_eq!(result?.to_vec(), vec![Value::I32(110)]);
+        assert_eq!(pending, 10);
+
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+        let (result, pending) = block_on(sum.call_async(&mut store, 4));
+        assert_eq!(result?, 20);
+        assert_eq!(pending, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn call_async_resumes_on_another_thread() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let imports = imports! { "host" => { "sleep" => sleep(&mut store) } };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+
+        let mut call = Box::pin(sum.call_async(&mut store, 3));
+        assert!(poll_once(call.as_mut()).is_pending());
+        let (result, pending) =
+            thread::scope(|scope| scope.spawn(|| block_on(call)).join().unwrap());
+        assert_eq!(result?, 12);
+        assert_eq!(pending, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn async_host_functions_with_env() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let env = FunctionEnv::new(&mut store, 0);
+        let sleep = Function::new_async_with_env(
+            &mut store,
+            &env,
+            ([Type::I32], [Type::I32]),
+            |mut env: FunctionEnvMut<i32>, args| {
+                Box::pin(async move {
+                    YieldNow(false).await;
+                    *env.data_mut() += 1;
+                    Ok(vec![Value::I32(args[0].unwrap_i32() + *env.data())])
+                })
+            },
+        );
+        let imports = imports! { "host" => { "sleep" => sleep } };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+
+        let (result, _) = block_on(sum.call_async(&mut store, 3));
+        // (3 + 1) + (2 + 2) + (1 + 3)
+        assert_eq!(result?, 12);
+        assert_eq!(*env.as_ref(&store), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn async_host_function_errors() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let sleep = Function::new_async(&mut store, ([Type::I32], [Type::I32]), |_| async {
+            YieldNow(false).await;
+            Err(RuntimeError::new("woke up"))
+        });
+        let imports = imports! { "host" => { "sleep" => sleep } };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+
+        let (result, _) = block_on(sum.call_async(&mut store, 3));
+        assert_eq!(result.unwrap_err().message(), "woke up");
+
+        // The store is still usable after the trap
+        let (result, _) = block_on(sum.call_async(&mut store, 0));
+        assert_eq!(result?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn dropping_a_pending_call_drops_its_future() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let guard = Arc::new(());
+        let host_guard = guard.clone();
+        let sleep = Function::new_async(&mut store, ([Type::I32], [Type::I32]), move |_| {
+            let guard = host_guard.clone();
+            async move {
+                std::future::pending::<()>().await;
+                drop(guard);
+                Ok(vec![])
+            }
+        });
+        let imports = imports! { "host" => { "sleep" => sleep } };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+
+        let mut call = Box::pin(sum.call_async(&mut store, 3));
+        assert!(poll_once(call.as_mut()).is_pending());
+        assert_eq!(Arc::strong_count(&guard), 3);
+        drop(call);
+        assert_eq!(Arc::strong_count(&guard), 2);
+
+        // The store is still usable after the call is dropped
+        let (result, _) = block_on(sum.call_async(&mut store, 0));
+        assert_eq!(result?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn async_host_functions_require_call_async() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let imports = imports! { "host" => { "sleep" => sleep(&mut store) } };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+
+        let err = sum.call(&mut store, 3).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "async host functions can only be called from `call_async`"
+        );
+        // Calls without async host functions still work
+        assert_eq!(sum.call(&mut store, 0)?, 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+#[cfg(all(feature = "sys", feature = "cranelift"))]
+mod exceptions {
+    use anyhow::Result;
+    use wasmer::*;
+
+    // The text format of the `wat` crate doesn't know about `try_table` yet,
+    // so the modules below are written in binary form. Both define a `catch`
+    // function doing:
+    //
+    // (func (export "catch") (param i32) (result i32)
+    //   (block (result i32)
+    //     (try_table (result i32) (catch $tag 0)
+    //       (call $thrower (local.get 0))))
+    //   (i32.const 1)
+    //   (i32.add))
+    const CATCH_BODY: &[u8] = &[
+        0x13, 0x00, 0x02, 0x7f, 0x1f, 0x7f, 0x01, 0x00, 0x00, 0x00, 0x20, 0x00, 0x10, 0x00, 0x0b,
+        0x0b, 0x41, 0x01, 0x6a, 0x0b,
+    ];
+
+    // (module
+    //   (tag $tag (export "tag") (param i32))
+    //   (func $thrower (export "throw") (param i32) (result i32)
+    //     (throw $tag (local.get 0)))
+    //   (func (export "catch") ...))
+    fn wasm_thrower() -> Vec<u8> {
+        let mut wasm = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+            0x01, 0x0a, 0x02, 0x60, 0x01, 0x7f, 0x00, 0x60, 0x01, 0x7f, 0x01, 0x7f, // types
+            0x03, 0x03, 0x02, 0x01, 0x01, // functions
+            0x0d, 0x03, 0x01, 0x00, 0x00, // tags
+            0x07, 0x17, 0x03, // exports
+            0x05, b'c', b'a', b't', b'c', b'h', 0x00, 0x01, //
+            0x05, b't', b'h', b'r', b'o', b'w', 0x00, 0x00, //
+            0x03, b't', b'a', b'g', 0x04, 0x00, //
+            0x0a, 0x1c, 0x02, // code
+            0x06, 0x00, 0x20, 0x00, 0x08, 0x00, 0x0b,
+        ];
+        wasm.extend_from_slice(CATCH_BODY);
+        wasm
+    }
+
+    // (module
+    //   (import "env" "tag" (tag $tag (param i32)))
+    //   (import "env" "host" (func $thrower (param i32) (result i32)))
+    //   (func (export "catch") ...))
+    fn host_thrower() -> Vec<u8> {
+        let mut wasm = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+            0x01, 0x0a, 0x02, 0x60, 0x01, 0x7f, 0x00, 0x60, 0x01, 0x7f, 0x01, 0x7f, // types
+            0x02, 0x17, 0x02, // imports
+            0x03, b'e', b'n', b'v', 0x03, b't', b'a', b'g', 0x04, 0x00, 0x00, //
+            0x03, b'e', b'n', b'v', 0x04, b'h', b'o', b's', b't', 0x00, 0x01, //
+            0x03, 0x02, 0x01, 0x01, // functions
+            0x07, 0x09, 0x01, 0x05, b'c', b'a', b't', b'c', b'h', 0x00, 0x01, // exports
+            0x0a, 0x15, 0x01, // code
+        ];
+        wasm.extend_from_slice(CATCH_BODY);
+        wasm
+    }
+
+    // The deprecated `try` instructions are still understood by the `wat`
+    // crate, but not by the validator, so the module is loaded unchecked.
+    const LEGACY_WAT: &str = r#"
+    (module
+      (tag $tag (export "tag") (param i32))
+      (tag $other)
+      (func $thrower (param i32)
+        (throw $tag (local.get 0)))
+
+      (func (export "catch") (param i32) (result i32)
+        try (result i32)
+          local.get 0
+          i32.eqz
+          if
+            i32.const 100
+            throw $tag
+          end
+          local.get 0
+          call $thrower
+          i32.const 0
+        catch $other
+          i32.const -1
+        catch $tag
+          i32.const 1
+          i32.add
+        end)
+
+      (func (export "rethrow") (param i32) (result i32)
+        try (result i32)
+          try
+            local.get 0
+            call $thrower
+          catch_all
+            rethrow 0
+          end
+          i32.const 0
+        catch $tag
+        end)
+
+      (func (export "rethrow-twice") (param i32) (result i32)
+        try (result i32)
+          local.get 0
+          call $thrower
+          i32.const 0
+        catch $tag
+          try (result i32)
+            rethrow 1
+          catch $tag
+          end
+          i32.add
+        end)
+
+      (func (export "delegate") (param i32) (result i32)
+        try (result i32)
+          block
+            try
+              local.get 0
+              call $thrower
+            delegate 1
+          end
+          i32.const 0
+        catch $tag
+          i32.const 2
+          i32.mul
+        end)
+
+      (func (export "uncaught") (param i32)
+        try
+          local.get 0
+          call $thrower
+        catch $other
+        end))
+    "#;
+
+    fn store() -> Store {
+        let mut features = sys::Features::new();
+        features.exceptions(true);
+        let engine = sys::EngineBuilder::new(Cranelift::default()).set_features(Some(features));
+        Store::new(engine)
+    }
+
+    #[test]
+    fn exception_caught_in_wasm() -> Result<()> {
+        let mut store = store();
+        let module = Module::new(&store, wasm_thrower())?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+        let catch = instance.exports.get_function("catch")?;
+        let results = catch.call(&mut store, &[Value::I32(41)])?;
+        assert_eq!(&*results, &[Value::I32(42)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn exception_escapes_to_host() -> Result<()> {
+        let mut store = store();
+        let module = Module::new(&store, wasm_thrower())?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+        let tag = instance.exports.get_tag("tag")?.clone();
+        let throw = instance.exports.get_function("throw")?;
+        let err = throw.call(&mut store, &[Value::I32(7)]).unwrap_err();
+        let exception = err.exception().expect("the error should be an exception");
+        assert_eq!(exception.tag(&store), tag);
+        assert_eq!(exception.payload(&mut store), vec![Value::I32(7)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn exception_thrown_by_host() -> Result<()> {
+        let mut store = store();
+        let module = Module::new(&store, host_thrower())?;
+        let tag = Tag::new(&mut store, [Type::I32]);
+        let env = FunctionEnv::new(&mut store, tag.clone());
+        let host = Function::new_typed_with_env(
+            &mut store,
+            &env,
+            |mut env: FunctionEnvMut<Tag>, value: i32| -> Result<i32, RuntimeError> {
+                let tag = env.data().clone();
+                Err(Exception::new(&mut env, &tag, &[Value::I32(value * 2)])?.into())
+            },
+        );
+        let imports = imports! {
+            "env" => {
+                "tag" => tag,
+                "host" => host,
+            },
+        };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+
+        let catch = instance.exports.get_function("catch")?;
+        let results = catch.call(&mut store, &[Value::I32(20)])?;
+        assert_eq!(&*results, &[Value::I32(41)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_exceptions() -> Result<()> {
+        let mut store = store();
+        let wasm = wat2wasm(LEGACY_WAT.as_bytes())?;
+        let module = unsafe { Module::from_binary_unchecked(&store, &wasm)? };
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+        let call = |store: &mut Store, name: &str, arg: i32| {
+            let func = instance.exports.get_function(name).unwrap();
+            func.call(store, &[Value::I32(arg)])
+        };
+        assert_eq!(&*call(&mut store, "catch", 0)?, &[Value::I32(101)]);
+        assert_eq!(&*call(&mut store, "catch", 41)?, &[Value::I32(42)]);
+        assert_eq!(&*call(&mut store, "rethrow", 5)?, &[Value::I32(5)]);
+        assert_eq!(&*call(&mut store, "rethrow-twice", 5)?, &[Value::I32(10)]);
+        assert_eq!(&*call(&mut store, "delegate", 21)?, &[Value::I32(42)]);
+
+        let tag = instance.exports.get_tag("tag")?.clone();
+        let err = call(&mut store, "uncaught", 7).unwrap_err();
+        let exception = err.exception().expect("the error should be an exception");
+        assert_eq!(exception.tag(&store), tag);
+        assert_eq!(exception.payload(&mut store), vec![Value::I32(7)]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+#[cfg(feature = "sys")]
+mod limiter {
+    use anyhow::Result;
+    use std::error::Error;
+    use std::fmt;
+    use wasmer::*;
+
+    /// Caps the total size of the memories and tables of a store.
+    struct Caps {
+        memory_bytes: usize,
+        table_elements: usize,
+        instances: usize,
+    }
+
+    #[derive(Debug)]
+    struct TooManyElements;
+
+    impl fmt::Display for TooManyElements {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "too many table elements")
+        }
+    }
+
+    impl Error for TooManyElements {}
+
+    impl ResourceLimiter for Caps {
+        fn memory_growing(
+            &mut self,
+            usage: &ResourceUsage,
+            current: usize,
+            desired: usize,
+            _maximum: Option<usize>,
+        ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+            Ok(usage.memory_bytes - current + desired <= self.memory_bytes)
+        }
+
+        fn table_growing(
+            &mut self,
+            usage: &ResourceUsage,
+            current: u32,
+            desired: u32,
+            _maximum: Option<u32>,
+        ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+            if usage.table_elements - current as usize + desired as usize > self.table_elements {
+                return Err(Box::new(TooManyElements));
+            }
+            Ok(true)
+        }
+
+        fn instance_creating(
+            &mut self,
+            usage: &ResourceUsage,
+        ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+            Ok(usage.instances < self.instances)
+        }
+    }
+
+    fn store(memory_pages: u32, table_elements: usize, instances: usize) -> Store {
+        let mut store = Store::default();
+        store.set_resource_limiter(Caps {
+            memory_bytes: Pages(memory_pages).bytes().0,
+            table_elements,
+            instances,
+        });
+        store
+    }
+
+    #[test]
+    fn memory_growth_is_limited_across_instances() -> Result<()> {
+        let wat = r#"
+        (module
+          (memory 1)
+          (func (export "grow") (param i32) (result i32)
+            (memory.grow (local.get 0))))
+        "#;
+        let mut store = store(4, 0, 10);
+        let module = Module::new(&store, wat)?;
+
+        let first = Instance::new(&mut store, &module, &imports! {})?;
+        let second = Instance::new(&mut store, &module, &imports! {})?;
+        let grow_first: TypedFunction<i32, i32> =
+            first.exports.get_typed_function(&store, "grow")?;
+        let grow_second: TypedFunction<i32, i32> =
+            second.exports.get_typed_function(&store, "grow")?;
+
+        assert_eq!(grow_first.call(&mut store, 1)?, 1);
+        assert_eq!(grow_second.call(&mut store, 2)?, -1);
+        assert_eq!(grow_second.call(&mut store, 1)?, 1);
+        assert_eq!(grow_first.call(&mut store, 1)?, -1);
+
+        let usage = store.resource_usage();
+        assert_eq!(usage.instances, 2);
+        assert_eq!(usage.memories, 2);
+        assert_eq!(usage.memory_bytes, Pages(4).bytes().0);
+
+        // Neither instances nor host memories can take more memory
+        let err = Instance::new(&mut store, &module, &imports! {}).unwrap_err();
+        assert!(matches!(err, InstantiationError::Link(_)), "{err}");
+        assert!(Memory::new(&mut store, MemoryType::new(1, None, false)).is_err());
+        assert!(Memory::new(&mut store, MemoryType::new(0, None, false)).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn instance_creation_is_limited() -> Result<()> {
+        let mut store = store(0, 0, 1);
+        let module = Module::new(&store, "(module)")?;
+
+        Instance::new(&mut store, &module, &imports! {})?;
+        assert!(Instance::new(&mut store, &module, &imports! {}).is_err());
+        assert_eq!(store.resource_usage().instances, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn limiter_errors_trap() -> Result<()> {
+        let wat = r#"
+        (module
+          (table (export "table") 1 funcref)
+          (func (export "grow") (param i32) (result i32)
+            (table.grow (ref.null func) (local.get 0))))
+        "#;
+        let mut store = store(0, 3, 10);
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let grow: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "grow")?;
+
+        assert_eq!(grow.call(&mut store, 2)?, 1);
+        let err = grow.call(&mut store, 1).unwrap_err();
+        assert!(err.downcast::<TooManyElements>().is_ok());
+
+        let table = instance.exports.get_table("table")?;
+        assert!(table.grow(&mut store, 1, Value::FuncRef(None)).is_err());
+        assert_eq!(table.size(&store), 3);
+        assert_eq!(store.resource_usage().table_elements, 3);
+
+        Ok(())
+    }
+}
@@ -76,3 +76,33 @@ fn test_shared_memory_disable_atomics() {
     let err = mem.wait(MemoryLocation::new_32(1), None).unwrap_err();
     assert_eq!(err, AtomicsError::AtomicsDisabled);
 }
+
+#[cfg(feature = "sys")]
+#[test]
+fn test_data_segments_are_private_to_each_instance() {
+    let mut store = Store::default();
+    let wat = r#"(module
+(memory (export "memory") 2)
+(data (i32.const 10) "hello")
+(data (i32.const 65539) "world")
+(data (i32.const 12) "L")
+)"#;
+    let module = Module::new(&store, wat).unwrap();
+
+    let first = Instance::new(&mut store, &module, &imports! {}).unwrap();
+    let second = Instance::new(&mut store, &module, &imports! {}).unwrap();
+    let memory = first.exports.get_memory("memory").unwrap();
+    let mut buf = [0u8; 5];
+    memory.view(&store).read(10, &mut buf).unwrap();
+    assert_eq!(&buf, b"heLlo");
+    memory.view(&store).read(65539, &mut buf).unwrap();
+    assert_eq!(&buf, b"world");
+
+    // Writing to the memory of an instance doesn't change the others
+    memory.view(&store).write(10, b"jelly").unwrap();
+    memory.view(&store).read(10, &mut buf).unwrap();
+    assert_eq!(&buf, b"jelly");
+    let memory = second.exports.get_memory("memory").unwrap();
+    memory.view(&store).read(10, &mut buf).unwrap();
+    assert_eq!(&buf, b"heLlo");
+}
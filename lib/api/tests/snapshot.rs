@@ -0,0 +1,139 @@
+#[cfg(feature = "sys")]
+mod snapshot {
+    use anyhow::Result;
+    use wasmer::*;
+
+    const WAT: &str = r#"
+    (module
+      (memory (export "memory") 1)
+      (global $counter (export "counter") (mut i32) (i32.const 0))
+      (global $ratio (mut f64) (f64.const 0))
+      (global $pages i32 (i32.const 7))
+      (data (i32.const 16) "hello")
+      (data $greeting "bonjour")
+      (func $start (global.set $counter (i32.add (global.get $counter) (i32.const 1))))
+      (start $start)
+      (func (export "init")
+        (drop (memory.grow (i32.const 1)))
+        (memory.init $greeting (i32.const 65536) (i32.const 0) (i32.const 7))
+        (i32.store8 (i32.const 16) (i32.const 74))
+        (global.set $ratio (f64.const 0.5))
+        (global.set $counter (i32.add (global.get $counter) (i32.const 10))))
+      (func (export "ratio") (result f64) (global.get $ratio))
+      (func (export "greet") (param i32)
+        (memory.init $greeting (local.get 0) (i32.const 0) (i32.const 7))))
+    "#;
+
+    fn read(store: &Store, memory: &Memory, offset: u64, len: usize) -> Vec<u8> {
+        let mut buf = vec![0; len];
+        memory.view(store).read(offset, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn snapshots_capture_memories_and_globals() -> Result<()> {
+        let wasm = wat::parse_str(WAT)?;
+        let mut store = Store::default();
+        let snapshot = SnapshotInit::new("init").run(&mut store, &wasm, &imports! {})?;
+
+        let module = Module::new(&store, snapshot)?;
+        assert!(module.exports().all(|export| export.name() != "init"));
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+        // The start function isn't run again
+        let counter = instance.exports.get_global("counter")?;
+        assert_eq!(counter.get(&mut store), Value::I32(11));
+        let ratio: TypedFunction<(), f64> = instance.exports.get_typed_function(&store, "ratio")?;
+        assert_eq!(ratio.call(&mut store)?, 0.5);
+
+        let memory = instance.exports.get_memory("memory")?;
+        assert_eq!(memory.view(&store).size(), Pages(2));
+        assert_eq!(read(&store, memory, 16, 5), b"Jello");
+        assert_eq!(read(&store, memory, 65536, 7), b"bonjour");
+
+        // Passive segments keep their index
+        let greet: TypedFunction<i32, ()> = instance.exports.get_typed_function(&store, "greet")?;
+        greet.call(&mut store, 100)?;
+        assert_eq!(read(&store, memory, 100, 7), b"bonjour");
+
+        Ok(())
+    }
+
+    #[test]
+    fn snapshots_can_keep_the_init_function() -> Result<()> {
+        let wasm = wat::parse_str(WAT)?;
+        let mut store = Store::default();
+        let snapshot =
+            SnapshotInit::new("init")
+                .keep_init_func(true)
+                .run(&mut store, &wasm, &imports! {})?;
+
+        let module = Module::new(&store, snapshot)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let init: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "init")?;
+        init.call(&mut store)?;
+        let counter = instance.exports.get_global("counter")?;
+        assert_eq!(counter.get(&mut store), Value::I32(21));
+
+        Ok(())
+    }
+
+    #[test]
+    fn snapshots_initialize_reactors_first() -> Result<()> {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+              (global $state (export "state") (mut i32) (i32.const 0))
+              (func (export "_initialize") (global.set $state (i32.const 1)))
+              (func (export "init")
+                (global.set $state (i32.add (global.get $state) (i32.const 10)))))
+            "#,
+        )?;
+        let mut store = Store::default();
+        let snapshot = SnapshotInit::new("init").run(&mut store, &wasm, &imports! {})?;
+
+        let module = Module::new(&store, snapshot)?;
+        assert!(module
+            .exports()
+            .all(|export| export.name() != "_initialize"));
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let state = instance.exports.get_global("state")?;
+        assert_eq!(state.get(&mut store), Value::I32(11));
+
+        let snapshot = SnapshotInit::new("init").call_initialize(false).run(
+            &mut store,
+            &wasm,
+            &imports! {},
+        )?;
+        let module = Module::new(&store, snapshot)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let state = instance.exports.get_global("state")?;
+        assert_eq!(state.get(&mut store), Value::I32(10));
+
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_modules_are_rejected() -> Result<()> {
+        let mut store = Store::default();
+
+        let wasm = wat::parse_str(r#"(module (import "env" "memory" (memory 1)))"#)?;
+        let err = SnapshotInit::new("init")
+            .run(&mut store, &wasm, &imports! {})
+            .unwrap_err();
+        assert!(matches!(err, SnapshotError::Unsupported(_)), "{err}");
+
+        let wasm = wat::parse_str(r#"(module (func (export "init") (param i32)))"#)?;
+        let err = SnapshotInit::new("init")
+            .run(&mut store, &wasm, &imports! {})
+            .unwrap_err();
+        assert!(matches!(err, SnapshotError::Unsupported(_)), "{err}");
+
+        let err = SnapshotInit::new("missing")
+            .run(&mut store, &wasm, &imports! {})
+            .unwrap_err();
+        assert!(matches!(err, SnapshotError::Export(_)), "{err}");
+
+        Ok(())
+    }
+}
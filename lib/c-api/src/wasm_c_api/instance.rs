@@ -2,7 +2,7 @@ use super::externals::{wasm_extern_t, wasm_extern_vec_t};
 use super::module::wasm_module_t;
 use super::store::{wasm_store_t, StoreRef};
 use super::trap::wasm_trap_t;
-use wasmer_api::{Extern, Instance, InstantiationError};
+use wasmer_api::{Extern, ExternType, Instance, InstantiationError};
 
 /// Opaque type representing a WebAssembly instance.
 #[allow(non_camel_case_types)]
@@ -47,6 +47,18 @@ pub unsafe extern "C" fn wasm_instance_new(
     let imports = imports?;
 
     let wasm_module = &module.inner;
+
+    // Exception tags are skipped by `wasm_module_imports`, so the imports
+    // given here can't line up with the module's own imports.
+    if wasm_module
+        .imports()
+        .any(|import| matches!(import.ty(), ExternType::Tag(_)))
+    {
+        crate::error::update_last_error("exception tag imports are not supported by the C API");
+
+        return None;
+    }
+
     let module_imports = wasm_module.imports();
     let module_import_count = module_imports.len();
     let externs = imports
@@ -201,6 +213,8 @@ pub unsafe extern "C" fn wasm_instance_exports(
     let extern_vec: Vec<Option<Box<wasm_extern_t>>> = instance
         .exports
         .iter()
+        // Exception tags can't be represented in the C API
+        .filter(|(_name, r#extern)| !matches!(r#extern, Extern::Tag(_)))
         .map(|(_name, r#extern)| {
             Some(Box::new(wasm_extern_t::new(
                 original_instance.store.clone(),
@@ -26,7 +26,7 @@ pub fn store_cache_universal(c: &mut Criterion) {
     c.bench_function("store universal module in filesystem cache", |b| {
         b.iter(|| {
             let key = random_key();
-            fs_cache.store(key, &module).unwrap()
+            fs_cache.store(&store, key, &module).unwrap()
         })
     });
 }
@@ -42,7 +42,7 @@ pub fn load_cache_universal(c: &mut Criterion) {
     )
     .unwrap();
     let key = Hash::new([0u8; 32]);
-    fs_cache.store(key, &module).unwrap();
+    fs_cache.store(&store, key, &module).unwrap();
 
     c.bench_function("load universal module in filesystem cache", |b| {
         b.iter(|| unsafe { fs_cache.load(&store, key).unwrap() })
@@ -63,7 +63,7 @@ pub fn store_cache_native(c: &mut Criterion) {
     c.bench_function("store native module in filesystem cache", |b| {
         b.iter(|| {
             let key = random_key();
-            fs_cache.store(key, &module).unwrap()
+            fs_cache.store(&store, key, &module).unwrap()
         })
     });
 }
@@ -79,7 +79,7 @@ pub fn load_cache_native(c: &mut Criterion) {
     )
     .unwrap();
     let key = Hash::new([0u8; 32]);
-    fs_cache.store(key, &module).unwrap();
+    fs_cache.store(&store, key, &module).unwrap();
 
     c.bench_function("load native module in filesystem cache", |b| {
         b.iter(|| unsafe { fs_cache.load(&store, key).unwrap() })
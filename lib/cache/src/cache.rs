@@ -13,16 +13,23 @@ pub trait Cache {
     /// The deserialization error for the implementation
     type DeserializeError: Error + Send + Sync;
 
-    /// Loads a module using the provided [`wasmer::Store`] and [`crate::Hash`].
+    /// Loads a module using the provided [`wasmer::Engine`] and [`crate::Hash`].
     ///
     /// # Safety
-    /// This function is unsafe as the cache store could be tampered with.
+    /// This function is unsafe as the cache store could be tampered with,
+    /// unless the implementation verifies the integrity of what it loads.
     unsafe fn load(
         &self,
         engine: &impl AsEngineRef,
         key: Hash,
     ) -> Result<Module, Self::DeserializeError>;
 
-    /// Store a [`Module`] into the cache with the given [`crate::Hash`].
-    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError>;
+    /// Store a [`Module`] compiled by the provided [`wasmer::Engine`] into
+    /// the cache with the given [`crate::Hash`].
+    fn store(
+        &mut self,
+        engine: &impl AsEngineRef,
+        key: Hash,
+        module: &Module,
+    ) -> Result<(), Self::SerializeError>;
 }
@@ -1,40 +1,85 @@
 #![cfg_attr(not(feature = "filesystem"), allow(unused))]
 use crate::cache::Cache;
 use crate::hash::Hash;
-use std::fs::{create_dir_all, File};
+use std::fmt;
+use std::fs::{self, create_dir_all};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use wasmer::{AsEngineRef, DeserializeError, Module, SerializeError};
 
+/// The magic bytes starting every cached artifact. They are followed
+/// by the digest of the serialized module, and then by the module.
+const HEADER_MAGIC: &[u8; 16] = b"wasmer-cache-v1\0";
+
+/// The length of the header of the cached artifacts.
+const HEADER_LEN: usize = HEADER_MAGIC.len() + 32;
+
+/// The prefix of the temporary files the artifacts are written to
+/// before being moved into place.
+const TEMP_FILE_PREFIX: &str = ".wasmer-cache-";
+
 /// Representation of a directory that contains compiled wasm artifacts.
 ///
 /// The `FileSystemCache` type implements the [`Cache`] trait, which allows it to be used
 /// generically when some sort of cache is required.
 ///
+/// The artifacts are stored in a sub-directory named after the
+/// fingerprint of the engine that compiled them (the Wasmer version,
+/// the compiler, the target and the WebAssembly features), so
+/// artifacts an engine can't load are skipped.
+///
+/// # Integrity
+///
+/// Each artifact is stored with its checksum, which is verified
+/// before the artifact is loaded. Artifacts are written to a temporary
+/// file first, and then renamed, so that processes sharing the cache
+/// never see partially written artifacts. Corrupted artifacts are
+/// removed.
+///
+/// By default, the checksum only detects accidental corruption. With
+/// [`FileSystemCache::set_integrity_key`], the checksum is keyed, and
+/// artifacts that weren't stored with the same secret key are
+/// rejected.
+///
+/// # Eviction
+///
+/// The cache grows forever unless a limit is set with
+/// [`FileSystemCache::set_max_size`] or
+/// [`FileSystemCache::set_max_age`]. The least recently used
+/// artifacts are then removed when a module is stored, including the
+/// ones of other engines.
+///
 /// # Usage
 ///
 /// ```
 /// use wasmer::{DeserializeError, SerializeError};
 /// use wasmer_cache::{Cache, FileSystemCache, Hash};
 ///
-/// # use wasmer::{Module};
-/// fn store_module(module: &Module, bytes: &[u8]) -> Result<(), SerializeError> {
+/// # use wasmer::{Engine, Module};
+/// fn store_module(engine: &Engine, module: &Module, bytes: &[u8]) -> Result<(), SerializeError> {
 ///     // Create a new file system cache.
 ///     let mut fs_cache = FileSystemCache::new("some/directory/goes/here")?;
 ///
+///     // Keep at most 1 GiB of artifacts.
+///     fs_cache.set_max_size(Some(1 << 30));
+///
 ///     // Compute a key for a given WebAssembly binary
 ///     let key = Hash::generate(bytes);
 ///
 ///     // Store a module into the cache given a key
-///     fs_cache.store(key, module)?;
+///     fs_cache.store(engine, key, module)?;
 ///
 ///     Ok(())
 /// }
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct FileSystemCache {
     path: PathBuf,
     ext: Option<String>,
+    max_size: Option<u64>,
+    max_age: Option<Duration>,
+    integrity_key: Option<[u8; 32]>,
 }
 
 #[cfg(feature = "filesystem")]
@@ -46,7 +91,7 @@ impl FileSystemCache {
             let metadata = path.metadata()?;
             if metadata.is_dir() {
                 if !metadata.permissions().readonly() {
-                    Ok(Self { path, ext: None })
+                    Ok(Self::with_path(path))
                 } else {
                     // This directory is readonly.
                     Err(io::Error::new(
@@ -73,11 +118,21 @@ impl FileSystemCache {
                     format!("failed to create cache directory: {}", path.display()),
                 ))
             } else {
-                Ok(Self { path, ext: None })
+                Ok(Self::with_path(path))
             }
         }
     }
 
+    fn with_path(path: PathBuf) -> Self {
+        Self {
+            path,
+            ext: None,
+            max_size: None,
+            max_age: None,
+            integrity_key: None,
+        }
+    }
+
     /// Set the extension for this cached file.
     ///
     /// This is needed for loading native files from Windows, as otherwise
@@ -85,6 +140,204 @@ impl FileSystemCache {
     pub fn set_cache_extension(&mut self, ext: Option<impl ToString>) {
         self.ext = ext.map(|ext| ext.to_string());
     }
+
+    /// Set the maximum size in bytes of the artifacts in the cache
+    /// directory.
+    ///
+    /// When it is exceeded, the least recently used artifacts are
+    /// removed.
+    pub fn set_max_size(&mut self, max_size: Option<u64>) {
+        self.max_size = max_size;
+    }
+
+    /// Set how long an artifact can stay in the cache directory
+    /// without being used before it is removed.
+    pub fn set_max_age(&mut self, max_age: Option<Duration>) {
+        self.max_age = max_age;
+    }
+
+    /// Set the secret key used to compute the checksums of the
+    /// artifacts.
+    ///
+    /// Artifacts stored without the same key are rejected when they
+    /// are loaded.
+    pub fn set_integrity_key(&mut self, integrity_key: Option<[u8; 32]>) {
+        self.integrity_key = integrity_key;
+    }
+
+    /// Remove the artifacts that are too old, and then the least
+    /// recently used ones until the size of the cache directory is
+    /// within its limit.
+    ///
+    /// This is done automatically when a module is stored.
+    pub fn evict(&self) -> io::Result<()> {
+        if self.max_size.is_none() && self.max_age.is_none() {
+            return Ok(());
+        }
+
+        let mut entries = Vec::new();
+        self.collect_entries(&self.path, &mut entries)?;
+
+        if let Some(max_age) = self.max_age {
+            let now = SystemTime::now();
+            entries.retain(|entry| {
+                let expired = now
+                    .duration_since(entry.modified)
+                    .map_or(false, |age| age > max_age);
+                if expired {
+                    let _ = fs::remove_file(&entry.path);
+                }
+                !expired
+            });
+        }
+
+        if let Some(max_size) = self.max_size {
+            // Artifacts being written by other processes can't be removed.
+            entries.retain(|entry| !entry.temporary);
+            entries.sort_by_key(|entry| entry.modified);
+
+            let mut size: u64 = entries.iter().map(|entry| entry.size).sum();
+            for entry in entries {
+                if size <= max_size {
+                    break;
+                }
+                let _ = fs::remove_file(&entry.path);
+                size -= entry.size;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Collect the artifacts in `dir` and in its sub-directories.
+    fn collect_entries(&self, dir: &Path, entries: &mut Vec<CacheEntry>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            if metadata.is_dir() {
+                if dir == self.path {
+                    let _ = self.collect_entries(&entry.path(), entries);
+                }
+                continue;
+            }
+
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            let temporary = file_name.starts_with(TEMP_FILE_PREFIX);
+            if !temporary && !self.is_artifact_name(&file_name) {
+                continue;
+            }
+
+            entries.push(CacheEntry {
+                path: entry.path(),
+                size: metadata.len(),
+                modified: metadata.modified()?,
+                temporary,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Whether `file_name` is the name of a cached artifact.
+    fn is_artifact_name(&self, file_name: &str) -> bool {
+        let key = match self.ext {
+            Some(ref ext) => file_name
+                .strip_suffix(ext.as_str())
+                .and_then(|name| name.strip_suffix('.')),
+            None => Some(file_name),
+        };
+        key.map_or(false, |key| key.parse::<Hash>().is_ok())
+    }
+
+    /// The path of the artifact of `key` for `engine`.
+    fn artifact_path(&self, engine: &impl AsEngineRef, key: Hash) -> PathBuf {
+        let filename = if let Some(ref ext) = self.ext {
+            format!("{}.{}", key, ext)
+        } else {
+            key.to_string()
+        };
+        self.path.join(fingerprint(engine)).join(filename)
+    }
+
+    /// The checksum of a serialized module.
+    fn digest(&self, bytes: &[u8]) -> [u8; 32] {
+        match self.integrity_key {
+            Some(ref key) => blake3::keyed_hash(key, bytes).into(),
+            None => blake3::hash(bytes).into(),
+        }
+    }
+
+    /// Read an artifact and check its integrity, returning the
+    /// serialized module.
+    fn read_artifact(&self, path: &Path) -> Result<Vec<u8>, DeserializeError> {
+        let mut bytes = fs::read(path)?;
+
+        if bytes.len() < HEADER_LEN || !bytes.starts_with(HEADER_MAGIC) {
+            return Err(DeserializeError::Incompatible(
+                "the file is not a cached artifact".to_string(),
+            ));
+        }
+        if bytes[HEADER_MAGIC.len()..HEADER_LEN] != self.digest(&bytes[HEADER_LEN..]) {
+            return Err(DeserializeError::CorruptedBinary(
+                "the cached artifact doesn't match its checksum".to_string(),
+            ));
+        }
+
+        Ok(bytes.split_off(HEADER_LEN))
+    }
+}
+
+impl fmt::Debug for FileSystemCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileSystemCache")
+            .field("path", &self.path)
+            .field("ext", &self.ext)
+            .field("max_size", &self.max_size)
+            .field("max_age", &self.max_age)
+            .field("integrity_key", &self.integrity_key.map(|_| "<secret>"))
+            .finish()
+    }
+}
+
+/// An artifact found in the cache directory.
+struct CacheEntry {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+    /// Whether the artifact is still being written.
+    temporary: bool,
+}
+
+/// The name of the directory holding the artifacts of `engine`.
+///
+/// Engines share it when they can load each other's artifacts, that
+/// is when they have the same Wasmer version, compiler, target and
+/// WebAssembly features.
+fn fingerprint(engine: &impl AsEngineRef) -> String {
+    let engine = engine.as_engine_ref();
+    let engine = engine.engine();
+
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update(engine.deterministic_id().as_bytes());
+    #[cfg(feature = "sys")]
+    {
+        use wasmer::sys::NativeEngineExt;
+
+        hasher.update(format!("{:?}", engine.target()).as_bytes());
+        hasher.update(format!("{:?}", engine.features()).as_bytes());
+    }
+    let hash = Hash::new(hasher.finalize().into()).to_string();
+
+    format!("{}-{}", engine.deterministic_id(), &hash[..16])
 }
 
 #[cfg(feature = "filesystem")]
@@ -97,32 +350,50 @@ impl Cache for FileSystemCache {
         engine: &impl AsEngineRef,
         key: Hash,
     ) -> Result<Module, Self::DeserializeError> {
-        let filename = if let Some(ref ext) = self.ext {
-            format!("{}.{}", key, ext)
-        } else {
-            key.to_string()
-        };
-        let path = self.path.join(filename);
-        let ret = Module::deserialize_from_file(engine, path.clone());
-        if ret.is_err() {
-            // If an error occurs while deserializing then we can not trust it anymore
-            // so delete the cache file
-            let _ = std::fs::remove_file(path);
+        let path = self.artifact_path(engine, key);
+        let ret = self
+            .read_artifact(&path)
+            .and_then(|bytes| Module::deserialize(engine, bytes));
+        match ret {
+            Ok(_) => {
+                // Mark the artifact as recently used
+                let _ = filetime::set_file_mtime(&path, filetime::FileTime::now());
+            }
+            Err(_) => {
+                // If an error occurs while deserializing then we can not trust it anymore
+                // so delete the cache file
+                let _ = fs::remove_file(path);
+            }
         }
         ret
     }
 
-    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
-        let filename = if let Some(ref ext) = self.ext {
-            format!("{}.{}", key, ext)
-        } else {
-            key.to_string()
-        };
-        let path = self.path.join(filename);
-        let mut file = File::create(path)?;
+    fn store(
+        &mut self,
+        engine: &impl AsEngineRef,
+        key: Hash,
+        module: &Module,
+    ) -> Result<(), Self::SerializeError> {
+        let path = self.artifact_path(engine, key);
+        let dir = path
+            .parent()
+            .expect("Unreachable - always created by joining onto the cache path");
+        create_dir_all(dir)?;
 
         let buffer = module.serialize()?;
+
+        // Note: We write to a temporary file and rename it at the end so
+        // concurrent readers won't see a partially written artifact.
+        let mut file = tempfile::Builder::new()
+            .prefix(TEMP_FILE_PREFIX)
+            .tempfile_in(dir)?;
+        file.write_all(HEADER_MAGIC)?;
+        file.write_all(&self.digest(&buffer))?;
         file.write_all(&buffer)?;
+        file.persist(&path).map_err(|err| err.error)?;
+
+        // The module is stored even if old artifacts can't be removed.
+        let _ = self.evict();
 
         Ok(())
     }
@@ -131,6 +402,16 @@ impl Cache for FileSystemCache {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use filetime::FileTime;
+    use wasmer::sys::EngineBuilder;
+    use wasmer::Engine;
+
+    const EMPTY_MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+    fn set_last_use(path: &Path, seconds_ago: u64) {
+        let time = SystemTime::now() - Duration::from_secs(seconds_ago);
+        filetime::set_file_mtime(path, FileTime::from_system_time(time)).unwrap();
+    }
 
     #[test]
     fn test_fs_cache() {
@@ -145,7 +426,118 @@ mod tests {
         let module = Module::from_binary(&engine, bytes).unwrap();
         let key = Hash::generate(bytes);
 
-        cache.store(key, &module).unwrap();
+        cache.store(&engine, key, &module).unwrap();
+        let _restored = unsafe { cache.load(&engine, key).unwrap() };
+    }
+
+    #[test]
+    fn corrupted_artifacts_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let engine = Engine::default();
+        let module = Module::from_binary(&engine, EMPTY_MODULE).unwrap();
+        let key = Hash::generate(EMPTY_MODULE);
+
+        cache.store(&engine, key, &module).unwrap();
+        let path = cache.artifact_path(&engine, key);
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+
+        let err = unsafe { cache.load(&engine, key).unwrap_err() };
+        assert!(matches!(err, DeserializeError::CorruptedBinary(_)));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn artifacts_need_the_same_integrity_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::default();
+        let module = Module::from_binary(&engine, EMPTY_MODULE).unwrap();
+        let key = Hash::generate(EMPTY_MODULE);
+
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        cache.set_integrity_key(Some([1; 32]));
+        cache.store(&engine, key, &module).unwrap();
+        let _restored = unsafe { cache.load(&engine, key).unwrap() };
+
+        let mut other_cache = FileSystemCache::new(dir.path()).unwrap();
+        other_cache.set_integrity_key(Some([2; 32]));
+        let err = unsafe { other_cache.load(&engine, key).unwrap_err() };
+        assert!(matches!(err, DeserializeError::CorruptedBinary(_)));
+    }
+
+    #[test]
+    fn artifacts_of_other_engines_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let engine = Engine::default();
+        let module = Module::from_binary(&engine, EMPTY_MODULE).unwrap();
+        let key = Hash::generate(EMPTY_MODULE);
+        cache.store(&engine, key, &module).unwrap();
+
+        let other_engine: Engine =
+            EngineBuilder::new(wasmer_compiler_singlepass::Singlepass::default()).into();
+        assert_ne!(
+            cache.artifact_path(&engine, key),
+            cache.artifact_path(&other_engine, key)
+        );
+        let err = unsafe { cache.load(&other_engine, key).unwrap_err() };
+        assert!(matches!(err, DeserializeError::Io(_)));
+
+        // The artifact of the first engine is still there
         let _restored = unsafe { cache.load(&engine, key).unwrap() };
     }
+
+    #[test]
+    fn least_recently_used_artifacts_are_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let engine = Engine::default();
+        let module = Module::from_binary(&engine, EMPTY_MODULE).unwrap();
+        let keys = [Hash::new([1; 32]), Hash::new([2; 32]), Hash::new([3; 32])];
+
+        cache.store(&engine, keys[0], &module).unwrap();
+        cache.store(&engine, keys[1], &module).unwrap();
+        let paths = keys.map(|key| cache.artifact_path(&engine, key));
+        set_last_use(&paths[0], 200);
+        set_last_use(&paths[1], 100);
+
+        // Using the first artifact makes the second one the least
+        // recently used
+        let _restored = unsafe { cache.load(&engine, keys[0]).unwrap() };
+
+        let artifact_size = fs::metadata(&paths[0]).unwrap().len();
+        cache.set_max_size(Some(artifact_size * 2));
+        cache.store(&engine, keys[2], &module).unwrap();
+
+        assert!(paths[0].exists());
+        assert!(!paths[1].exists());
+        assert!(paths[2].exists());
+    }
+
+    #[test]
+    fn old_artifacts_are_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let engine = Engine::default();
+        let module = Module::from_binary(&engine, EMPTY_MODULE).unwrap();
+        let key = Hash::generate(EMPTY_MODULE);
+
+        cache.store(&engine, key, &module).unwrap();
+        let path = cache.artifact_path(&engine, key);
+        set_last_use(&path, 2 * 60 * 60);
+
+        // Files that aren't artifacts are left alone
+        let other_file = dir.path().join("notes.txt");
+        fs::write(&other_file, "").unwrap();
+        set_last_use(&other_file, 2 * 60 * 60);
+
+        cache.set_max_age(Some(Duration::from_secs(60 * 60)));
+        cache.evict().unwrap();
+
+        assert!(!path.exists());
+        assert!(other_file.exists());
+    }
 }
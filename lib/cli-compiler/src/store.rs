@@ -165,6 +165,9 @@ impl CompilerOptions {
         if self.features.reference_types || self.features.all {
             features.reference_types(true);
         }
+        if self.features.tail_call || self.features.all {
+            features.tail_call(true);
+        }
         Ok(features)
     }
 
@@ -5,12 +5,14 @@ mod export;
 mod filter;
 mod import;
 mod inspect;
+mod verify;
 
 pub use compact::*;
 pub use export::*;
 pub use filter::*;
 pub use import::*;
 pub use inspect::*;
+pub use verify::*;
 
 /// Manage Journal files.
 #[derive(clap::Subcommand, Debug)]
@@ -25,6 +27,8 @@ pub enum CmdJournal {
     Inspect(CmdJournaInspect),
     /// Filters out certain events from a journal
     Filter(CmdJournalFilter),
+    /// Verifies the checksums and the signature of a journal
+    Verify(CmdJournalVerify),
 }
 
 impl CliCommand for CmdJournal {
@@ -37,6 +41,7 @@ impl CliCommand for CmdJournal {
             Self::Export(cmd) => cmd.run(),
             Self::Inspect(cmd) => cmd.run(),
             Self::Filter(cmd) => cmd.run(),
+            Self::Verify(cmd) => cmd.run(),
         }
     }
 }
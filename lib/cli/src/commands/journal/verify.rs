@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use wasmer_wasix::journal::LogFileJournal;
+
+use crate::commands::CliCommand;
+
+/// Checks the records of a journal (and its signature) without
+/// modifying it, and fails if the journal is corrupted or was tampered with
+#[derive(Debug, Parser)]
+pub struct CmdJournalVerify {
+    /// Path to the journal that will be verified
+    #[clap(index = 1)]
+    journal_path: PathBuf,
+    /// Path to the file holding the key the journal was signed with
+    #[clap(long = "key-file")]
+    key_file: Option<PathBuf>,
+}
+
+impl CliCommand for CmdJournalVerify {
+    type Output = ();
+
+    fn run(self) -> Result<(), anyhow::Error> {
+        let key = self.key_file.map(std::fs::read).transpose()?;
+        let verification = LogFileJournal::verify(&self.journal_path, key.as_deref())?;
+
+        println!("entries: {}", verification.entries);
+        println!("signatures: {}", verification.signatures);
+        println!("file size: {} bytes", verification.file_size);
+        println!("valid size: {} bytes", verification.valid_size);
+        if let Some(signed_size) = verification.signed_size {
+            println!("signed size: {} bytes", signed_size);
+        }
+
+        if verification.valid_size < verification.file_size {
+            anyhow::bail!(
+                "the journal is corrupted after {} bytes",
+                verification.valid_size
+            );
+        }
+        if key.is_some()
+            && verification.entries > 0
+            && verification.signed_size != Some(verification.valid_size)
+        {
+            anyhow::bail!(
+                "the journal is only signed up to {} bytes",
+                verification.signed_size.unwrap_or_default()
+            );
+        }
+        Ok(())
+    }
+}
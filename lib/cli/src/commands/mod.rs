@@ -26,6 +26,8 @@ mod package;
 mod publish;
 mod run;
 mod self_update;
+#[cfg(feature = "compiler")]
+mod snapshot_init;
 pub mod ssh;
 mod validate;
 #[cfg(feature = "wast")]
@@ -39,6 +41,8 @@ use clap::{CommandFactory, Parser};
 pub use compile::*;
 #[cfg(any(feature = "static-artifact-create", feature = "wasmer-artifact-create"))]
 pub use create_exe::*;
+#[cfg(feature = "compiler")]
+pub use snapshot_init::*;
 #[cfg(feature = "wast")]
 pub use wast::*;
 #[cfg(feature = "static-artifact-create")]
@@ -122,6 +126,8 @@ impl WasmerCmd {
             Some(Cmd::Validate(validate)) => validate.execute(),
             #[cfg(feature = "compiler")]
             Some(Cmd::Compile(compile)) => compile.execute(),
+            #[cfg(feature = "compiler")]
+            Some(Cmd::SnapshotInit(snapshot_init)) => snapshot_init.execute(),
             #[cfg(any(feature = "static-artifact-create", feature = "wasmer-artifact-create"))]
             Some(Cmd::CreateExe(create_exe)) => create_exe.execute(),
             #[cfg(feature = "static-artifact-create")]
@@ -227,6 +233,11 @@ enum Cmd {
     #[cfg(feature = "compiler")]
     Compile(Compile),
 
+    /// Pre-initialize a WebAssembly module by snapshotting its state after running an init function
+    #[cfg(feature = "compiler")]
+    #[clap(name = "snapshot-init")]
+    SnapshotInit(SnapshotInitCmd),
+
     /// Compile a WebAssembly binary into a native executable
     ///
     /// To use, you need to set the `WASMER_DIR` environment variable
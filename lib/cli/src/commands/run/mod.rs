@@ -1,6 +1,6 @@
 #![allow(missing_docs, unused)]
 
-mod wasi;
+pub(crate) mod wasi;
 
 use std::{
     collections::BTreeMap,
@@ -89,6 +89,11 @@ impl Run {
 
     #[tracing::instrument(level = "debug", name = "wasmer_run", skip_all)]
     fn execute_inner(self, output: Output) -> Result<(), Error> {
+        #[cfg(feature = "journal")]
+        if let Some(path) = &self.wasi.journal_pause {
+            return self.wasi.pause_journals(path);
+        }
+
         let pb = ProgressBar::new_spinner();
         pb.set_draw_target(output.draw_target());
         pb.enable_steady_tick(TICK);
@@ -11,11 +11,16 @@ use bytes::Bytes;
 use clap::Parser;
 use tokio::runtime::Handle;
 use url::Url;
-use virtual_fs::{DeviceFile, FileSystem, PassthruFileSystem, RootFileSystemBuilder};
+use virtual_fs::{
+    DeviceFile, FileSystem, PassthruFileSystem, RootFileSystemBuilder, ScopedDirectoryFileSystem,
+};
 use wasmer::{Engine, Function, Instance, Memory32, Memory64, Module, RuntimeError, Store, Value};
 use wasmer_registry::wasmer_env::WasmerEnv;
 #[cfg(feature = "journal")]
-use wasmer_wasix::journal::{LogFileJournal, SnapshotTrigger};
+use wasmer_wasix::journal::{
+    copy_journal, Journal, LogFileJournal, NullJournal, PrintingJournal, RecombinedJournal,
+    ReplayTarget, SnapshotTrigger, TruncatedJournal,
+};
 use wasmer_wasix::{
     bin_factory::BinaryPackage,
     capabilities::Capabilities,
@@ -93,6 +98,67 @@ pub struct Wasi {
     #[clap(long = "net")]
     pub networking: bool,
 
+    /// Only allow the sockets matching one of these rules (requires `--net`).
+    ///
+    /// A rule is a comma-separated list of protocols (`tcp`, `udp`, `icmp`,
+    /// `raw`), accesses (`connect`, `listen`), IP addresses or CIDR ranges
+    /// and ports (`port=443`, `port=8000-8999`), e.g.
+    /// `tcp,connect,10.0.0.0/8,port=443`. Raw sockets are only allowed by
+    /// a rule that names `raw` once any allow or deny rule is given.
+    #[clap(long = "net-allow", name = "ALLOW_RULE", requires = "networking")]
+    pub net_allow: Vec<virtual_net::NetworkRule>,
+
+    /// Deny the sockets matching any of these rules (requires `--net`).
+    ///
+    /// Deny rules take precedence over the allow rules, and have the same
+    /// syntax.
+    #[clap(long = "net-deny", name = "DENY_RULE", requires = "networking")]
+    pub net_deny: Vec<virtual_net::NetworkRule>,
+
+    /// Record the network traffic of the guest into a pcapng file (requires
+    /// `--net`).
+    ///
+    /// The TCP/IP headers of stream sockets are synthesized from the data
+    /// they send and receive, so the file can be opened with Wireshark.
+    #[clap(long = "net-capture", name = "CAPTURE_FILE", requires = "networking")]
+    pub net_capture: Option<PathBuf>,
+
+    /// Restrict the access to a guest directory and everything below it.
+    ///
+    /// The syntax is `PATH:MODES`, where the modes are `rw`, `ro`, `wo` or
+    /// `none`, optionally followed by `noexec` to prevent running the files
+    /// as child processes, e.g. `/data:ro` or `/out:wo,noexec`. The closest
+    /// directory with a rule applies.
+    ///
+    /// Reads and writes are only restricted in the directories mapped with
+    /// `--mapdir`, so rules restricting them elsewhere are rejected, while
+    /// `noexec` applies to every path.
+    #[clap(long = "fs-access", name = "FS_RULE")]
+    pub fs_access: Vec<virtual_fs::FsRule>,
+
+    /// Deny all access to the guest paths matching this glob pattern.
+    ///
+    /// `*` and `?` don't match `/`, while `**` does. Patterns without `/`,
+    /// such as `*.key`, match file names in any directory. Like the
+    /// `--fs-access` rules, the patterns only hide the paths of the mapped
+    /// directories, but no matching file can be run as a child process.
+    #[clap(long = "fs-deny", name = "FS_PATTERN")]
+    pub fs_deny: Vec<virtual_fs::FsPattern>,
+
+    /// Allow, deny or log the calls of a syscall.
+    ///
+    /// The syntax is `NAME=ACTION`, where the name is that of the syscall or
+    /// `*` for all of them, and the action is `allow`, `log`, `deny` or
+    /// `deny:ERRNO`, e.g. `sock_open=deny:access`. Logged syscalls are
+    /// `info` events of the `wasmer_wasix::syscalls` target.
+    #[clap(long = "syscall", name = "SYSCALL_RULE")]
+    pub syscall_rules: Vec<wasmer_wasix::capabilities::SyscallRule>,
+
+    /// Print every syscall to stderr, with its arguments, result and
+    /// duration, like `strace`.
+    #[clap(long = "strace")]
+    pub strace: bool,
+
     /// Disables the TTY bridge
     #[clap(long = "no-tty")]
     pub no_tty: bool,
@@ -154,6 +220,32 @@ pub struct Wasi {
     #[clap(long = "snapshot-period")]
     pub snapshot_interval: Option<u64>,
 
+    /// Replays the last journal file only up to a chosen point, which is
+    /// either an entry (`entry:<index>`), a snapshot (`snapshot:<index>`)
+    /// or the last snapshot taken at or before a time (`time:<seconds since
+    /// the UNIX epoch>`). Indexes start at zero.
+    ///
+    /// The process then resumes its execution from that point. The journal
+    /// file is left untouched, so the events of the resumed execution are
+    /// not written to it.
+    #[cfg(feature = "journal")]
+    #[clap(long = "journal-replay-to", requires = "journals")]
+    pub journal_replay_to: Option<ReplayTarget>,
+
+    /// Instead of resuming the process after replaying the journals up to
+    /// `--journal-replay-to`, saves a snapshot of it into a new journal file
+    /// and prints it. The snapshot can later be resumed with `--journal`.
+    #[cfg(feature = "journal")]
+    #[clap(long = "journal-pause", requires = "journal_replay_to")]
+    pub journal_pause: Option<PathBuf>,
+
+    /// Path to a file holding the key used to sign the journals (with
+    /// HMAC-SHA256). Journals whose signature doesn't match the key are
+    /// rejected when they are restored.
+    #[cfg(feature = "journal")]
+    #[clap(long = "journal-key-file", requires = "journals")]
+    pub journal_key_file: Option<PathBuf>,
+
     /// Allow instances to send http requests.
     ///
     /// Access to domains is granted by default.
@@ -327,7 +419,19 @@ impl Wasi {
                 mapped_dirs.push(mapping);
             }
 
+            // Only the mapped directories are restricted by the capabilities
+            let mount_points: Vec<PathBuf> = self
+                .mapped_dirs
+                .iter()
+                .map(|dir| Path::new("/").join(&dir.guest))
+                .collect();
+            self.capabilities()
+                .filesystem
+                .check_mount_points(&mount_points)
+                .map_err(anyhow::Error::msg)?;
+
             if !mapped_dirs.is_empty() {
+                let capabilities = self.capabilities();
                 let fs_backing: Arc<dyn FileSystem + Send + Sync> =
                     Arc::new(PassthruFileSystem::new(default_fs_backing()));
                 for MappedDirectory { host, guest } in self.mapped_dirs.clone() {
@@ -336,7 +440,16 @@ impl Wasi {
                     } else {
                         host
                     };
-                    root_fs.mount(guest.into(), &fs_backing, host)?;
+                    if capabilities.filesystem.is_unrestricted() {
+                        root_fs.mount(guest.into(), &fs_backing, host)?;
+                        continue;
+                    }
+
+                    // The policy needs the paths relative to the mount point
+                    let fs: Arc<dyn FileSystem + Send + Sync> =
+                        Arc::new(ScopedDirectoryFileSystem::new_with_default_runtime(host));
+                    let fs = capabilities.restrict_filesystem(fs, &Path::new("/").join(&guest));
+                    root_fs.mount(guest.into(), &fs, "/".into())?;
                 }
             }
 
@@ -373,10 +486,17 @@ impl Wasi {
 
     #[cfg(feature = "journal")]
     pub fn build_journals(&self) -> anyhow::Result<Vec<Arc<DynJournal>>> {
+        let key = self.journal_key()?;
+        let key = key.as_deref();
+
         let mut ret = Vec::new();
-        for journal in self.journals.clone() {
-            if self.enable_compaction {
-                let mut journal = CompactingLogFileJournal::new(journal)?;
+        for (index, journal) in self.journals.iter().cloned().enumerate() {
+            let is_last = index + 1 == self.journals.len();
+            if let Some(target) = self.journal_replay_to.filter(|_| is_last) {
+                let journal = Self::build_truncated_journal(&journal, key, target)?;
+                ret.push(Arc::new(journal) as Arc<DynJournal>);
+            } else if self.enable_compaction {
+                let mut journal = CompactingLogFileJournal::new_with_key(journal, key)?;
                 if !self.without_compact_on_drop {
                     journal = journal.with_compact_on_drop()
                 }
@@ -385,17 +505,72 @@ impl Wasi {
                 }
                 ret.push(Arc::new(journal) as Arc<DynJournal>);
             } else {
-                ret.push(Arc::new(LogFileJournal::new(journal)?));
+                ret.push(Arc::new(LogFileJournal::new_with_key(journal, key)?));
             }
         }
         Ok(ret)
     }
 
+    /// Reads the key the journals are signed with
+    #[cfg(feature = "journal")]
+    fn journal_key(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        self.journal_key_file
+            .as_ref()
+            .map(|path| {
+                std::fs::read(path).with_context(|| {
+                    format!("Unable to read the journal key at \"{}\"", path.display())
+                })
+            })
+            .transpose()
+    }
+
+    /// Opens a journal file without modifying it, so that it is only
+    /// replayed up to the target
+    #[cfg(feature = "journal")]
+    fn build_truncated_journal(
+        path: &Path,
+        key: Option<&[u8]>,
+        target: ReplayTarget,
+    ) -> anyhow::Result<TruncatedJournal> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Unable to open the journal at \"{}\"", path.display()))?;
+        let (_, rx) = LogFileJournal::from_file_with_key(file, key)?.split();
+        let journal = RecombinedJournal::new(Box::<NullJournal>::default(), rx);
+        TruncatedJournal::new(journal, target).with_context(|| {
+            format!(
+                "Unable to replay the journal at \"{}\" to {target}",
+                path.display()
+            )
+        })
+    }
+
     #[cfg(not(feature = "journal"))]
     pub fn build_journals(&self) -> anyhow::Result<Vec<Arc<DynJournal>>> {
         Ok(Vec::new())
     }
 
+    /// Replays the journals up to `--journal-replay-to` into a new journal
+    /// file, which is then compacted into a snapshot of the process
+    #[cfg(feature = "journal")]
+    pub fn pause_journals(&self, path: &Path) -> anyhow::Result<()> {
+        if path.exists() {
+            bail!("The journal at \"{}\" already exists", path.display());
+        }
+
+        let key = self.journal_key()?;
+        let compactor =
+            CompactingLogFileJournal::new_with_key(path, key.as_deref())?.with_compact_on_drop();
+        for journal in self.build_journals()? {
+            copy_journal(&journal, &compactor)?;
+        }
+        drop(compactor);
+
+        let journal = LogFileJournal::new_with_key(path, key.as_deref())?;
+        let printer = PrintingJournal::default();
+        copy_journal(&journal, &printer)?;
+        Ok(())
+    }
+
     pub fn build_mapped_directories(&self) -> Result<Vec<MappedDirectory>, anyhow::Error> {
         let mut mapped_dirs = Vec::new();
 
@@ -517,6 +692,12 @@ impl Wasi {
         }
 
         caps.threading.enable_asynchronous_threading = self.enable_async_threads;
+        caps.networking.allow = self.net_allow.clone();
+        caps.networking.deny = self.net_deny.clone();
+        caps.filesystem.rules = self.fs_access.clone();
+        caps.filesystem.deny = self.fs_deny.clone();
+        caps.syscalls.rules = self.syscall_rules.clone();
+        caps.syscalls.strace = self.strace;
 
         caps
     }
@@ -533,7 +714,15 @@ impl Wasi {
         let tokio_task_manager = Arc::new(TokioTaskManager::new(rt_or_handle.into()));
         let mut rt = PluggableRuntime::new(tokio_task_manager.clone());
 
-        if self.networking {
+        if let Some(path) = &self.net_capture {
+            let capture = virtual_net::PacketCapture::create(path).with_context(|| {
+                format!("Unable to create the packet capture \"{}\"", path.display())
+            })?;
+            rt.set_networking_implementation(virtual_net::CaptureNetworking::new(
+                Arc::new(virtual_net::host::LocalNetworking::default()),
+                capture,
+            ));
+        } else if self.networking {
             rt.set_networking_implementation(virtual_net::host::LocalNetworking::default());
         } else {
             rt.set_networking_implementation(virtual_net::UnsupportedVirtualNetworking::default());
@@ -0,0 +1,99 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use wasmer::*;
+use wasmer_registry::wasmer_env::WasmerEnv;
+use wasmer_wasix::{runtime::module_cache::ModuleHash, Runtime};
+
+use crate::{commands::run::wasi::Wasi, store::StoreOptions};
+
+#[derive(Debug, Parser)]
+/// The options for the `wasmer snapshot-init` subcommand
+pub struct SnapshotInitCmd {
+    /// WebAssembly file to pre-initialize
+    #[clap(name = "FILE")]
+    path: PathBuf,
+
+    /// Output file for the pre-initialized module
+    #[clap(name = "OUTPUT PATH", short = 'o')]
+    output: PathBuf,
+
+    /// The export to run before taking the snapshot
+    #[clap(long, default_value = "wizer.initialize")]
+    init_func: String,
+
+    /// Keep the init function exported from the pre-initialized module
+    #[clap(long)]
+    keep_init_func: bool,
+
+    #[clap(flatten)]
+    env: WasmerEnv,
+
+    #[clap(flatten)]
+    store: StoreOptions,
+
+    #[clap(flatten)]
+    wasi: Wasi,
+
+    /// Command-line arguments passed to the module while it is initialized
+    args: Vec<String>,
+}
+
+impl SnapshotInitCmd {
+    /// Runs logic for the `snapshot-init` subcommand
+    pub fn execute(&self) -> Result<()> {
+        self.inner_execute().context(format!(
+            "failed to pre-initialize `{}`",
+            self.path.display()
+        ))
+    }
+
+    fn inner_execute(&self) -> Result<()> {
+        let wasm = std::fs::read(&self.path)?;
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        let handle = runtime.handle().clone();
+        let _guard = handle.enter();
+
+        let (mut store, _compiler_type) = self.store.get_store()?;
+        let runtime: Arc<dyn Runtime + Send + Sync> = Arc::new(self.wasi.prepare_runtime(
+            store.engine().clone(),
+            &self.env,
+            runtime,
+        )?);
+
+        let snapshot = SnapshotInit::new(&self.init_func)
+            .keep_init_func(self.keep_init_func)
+            // WASI reactors are initialized when they are instantiated
+            .call_initialize(false)
+            .run_with(&mut store, &wasm, |store, module| {
+                if !Wasi::has_wasi_imports(module) {
+                    let instance = Instance::new(store, module, &Imports::default())?;
+                    if let Ok(initialize) = instance.exports.get_function("_initialize") {
+                        initialize.call(store, &[])?;
+                    }
+                    return Ok(instance);
+                }
+
+                let program_name = self.path.display().to_string();
+                let builder =
+                    self.wasi
+                        .prepare(module, program_name, self.args.clone(), runtime.clone())?;
+                let (instance, _env) =
+                    builder.instantiate_ext(module.clone(), ModuleHash::hash(&wasm), store)?;
+                Ok(instance)
+            })?;
+
+        std::fs::write(&self.output, snapshot)
+            .with_context(|| format!("unable to write `{}`", self.output.display()))?;
+        eprintln!(
+            "✔ Pre-initialized module written to `{}`.",
+            self.output.display()
+        );
+
+        Ok(())
+    }
+}
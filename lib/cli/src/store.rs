@@ -55,6 +55,10 @@ pub struct WasmFeatures {
     #[clap(long = "enable-bulk-memory")]
     pub bulk_memory: bool,
 
+    /// Enable support for the tail call proposal.
+    #[clap(long = "enable-tail-call")]
+    pub tail_call: bool,
+
     /// Enable support for all pre-standard proposals.
     #[clap(long = "enable-all")]
     pub all: bool,
@@ -88,6 +92,18 @@ pub struct CompilerOptions {
     #[clap(long)]
     llvm_debug_dir: Option<PathBuf>,
 
+    /// Describe the compiled code to Linux `perf`.
+    ///
+    /// Either `perfmap`, to write `/tmp/perf-<pid>.map`, or `jitdump`, to
+    /// write a jitdump file for `perf inject --jit`.
+    #[clap(long)]
+    profiler: Option<wasmer_compiler::ProfilingStrategy>,
+
+    /// Register the DWARF debugging information of the modules with GDB
+    /// and LLDB, to set breakpoints on their source lines.
+    #[clap(long)]
+    debug_info: bool,
+
     #[clap(flatten)]
     features: WasmFeatures,
 }
@@ -139,6 +155,9 @@ impl CompilerOptions {
         if self.features.reference_types || self.features.all {
             features.reference_types(true);
         }
+        if self.features.tail_call || self.features.all {
+            features.tail_call(true);
+        }
         Ok(features)
     }
 
@@ -167,6 +186,10 @@ impl CompilerOptions {
         let engine: Engine = wasmer_compiler::EngineBuilder::new(compiler_config)
             .set_features(Some(features))
             .set_target(Some(target))
+            .set_profiler(self.profiler)
+            .set_debug_info(self.debug_info)
+            // Crash reports show the source lines of modules built with DWARF
+            .set_backtrace_details(true)
             .engine();
 
         Ok(engine)
@@ -382,7 +405,9 @@ impl StoreOptions {
 #[cfg(not(any(feature = "compiler", feature = "jsc")))]
 impl StoreOptions {
     fn get_engine_headless(&self) -> Result<wasmer_compiler::Engine> {
-        let engine: wasmer_compiler::Engine = wasmer_compiler::EngineBuilder::headless().engine();
+        let engine: wasmer_compiler::Engine = wasmer_compiler::EngineBuilder::headless()
+            .set_backtrace_details(true)
+            .engine();
         Ok(engine)
     }
 
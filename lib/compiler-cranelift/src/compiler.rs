@@ -27,9 +27,9 @@ use wasmer_compiler::{
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     CallingConvention, Compilation, CompileError, CompileModuleInfo, CompiledFunction,
-    CompiledFunctionFrameInfo, CompiledFunctionUnwindInfo, Dwarf, FunctionBody, FunctionIndex,
-    LocalFunctionIndex, ModuleInfo, Relocation, RelocationTarget, SectionIndex, SignatureIndex,
-    Target, TrapCode, TrapInformation,
+    CompiledFunctionFrameInfo, CompiledFunctionUnwindInfo, Dwarf, Features, FunctionBody,
+    FunctionIndex, LocalFunctionIndex, ModuleInfo, Relocation, RelocationTarget, SectionIndex,
+    SignatureIndex, Target, TrapCode, TrapInformation,
 };
 
 /// A compiler that compiles a WebAssembly module with Cranelift, translating the Wasm to Cranelift IR,
@@ -55,6 +55,13 @@ impl Compiler for CraneliftCompiler {
         "cranelift"
     }
 
+    fn supported_features(&self, features: &Features) -> Features {
+        let mut features = features.clone();
+        // Tail calls are not implemented yet.
+        features.tail_call = false;
+        features
+    }
+
     /// Get the middlewares for this compiler
     fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
         &self.config.middlewares
@@ -463,9 +470,11 @@ fn translate_ir_trapcode(trap: ir::TrapCode) -> TrapCode {
         ir::TrapCode::IntegerDivisionByZero => TrapCode::IntegerDivisionByZero,
         ir::TrapCode::BadConversionToInteger => TrapCode::BadConversionToInteger,
         ir::TrapCode::UnreachableCodeReached => TrapCode::UnreachableCodeReached,
-        ir::TrapCode::Interrupt => unimplemented!("Interrupts not supported"),
+        ir::TrapCode::Interrupt => TrapCode::Interrupt,
+        ir::TrapCode::User(user_code) if user_code == TrapCode::CallDepthExceeded as u16 => {
+            TrapCode::CallDepthExceeded
+        }
         ir::TrapCode::User(_user_code) => unimplemented!("User trap code not supported"),
-        // ir::TrapCode::Interrupt => TrapCode::Interrupt,
         // ir::TrapCode::User(user_code) => TrapCode::User(user_code),
     }
 }
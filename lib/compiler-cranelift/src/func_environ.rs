@@ -20,7 +20,7 @@ use wasmer_types::VMBuiltinFunctionIndex;
 use wasmer_types::VMOffsets;
 use wasmer_types::{
     FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo,
-    SignatureIndex, TableIndex, Type as WasmerType,
+    SignatureIndex, TableIndex, TagIndex, Type as WasmerType,
 };
 use wasmer_types::{MemoryStyle, TableStyle};
 use wasmer_types::{WasmError, WasmResult};
@@ -113,6 +113,27 @@ pub struct FuncEnvironment<'module_environment> {
     /// The external function signature for implementing wasm's `memory32.atomic.notify`.
     memory32_atomic_notify_sig: Option<ir::SigRef>,
 
+    /// The external function signature for creating the exception of wasm's `throw`.
+    exception_new_sig: Option<ir::SigRef>,
+
+    /// The external function signature for throwing an exception to the caller.
+    exception_raise_sig: Option<ir::SigRef>,
+
+    /// The external function signature for copying the exception of wasm's `throw_ref`.
+    exception_clone_sig: Option<ir::SigRef>,
+
+    /// The external function signature for releasing a caught exception.
+    exception_drop_sig: Option<ir::SigRef>,
+
+    /// The external function signature for checking the epoch deadline of the store.
+    epoch_deadline_sig: Option<ir::SigRef>,
+
+    /// The external function signature for matching an exception against a tag.
+    exception_catch_sig: Option<ir::SigRef>,
+
+    /// The external function signature for calls made inside of a `try_table`.
+    exception_invoke_sig: Option<ir::SigRef>,
+
     /// Offsets to struct fields accessed by JIT code.
     offsets: VMOffsets,
 
@@ -155,6 +176,13 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             memory32_atomic_wait32_sig: None,
             memory32_atomic_wait64_sig: None,
             memory32_atomic_notify_sig: None,
+            exception_new_sig: None,
+            exception_raise_sig: None,
+            exception_clone_sig: None,
+            exception_drop_sig: None,
+            epoch_deadline_sig: None,
+            exception_catch_sig: None,
+            exception_invoke_sig: None,
             offsets: VMOffsets::new(target_config.pointer_bytes(), module),
             memory_styles,
             table_styles,
@@ -829,6 +857,275 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         }
     }
 
+    fn get_exception_new_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.exception_new_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Tag index.
+                    AbiParam::new(I32),
+                    // Payload.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_new_sig = Some(sig);
+        (sig, VMBuiltinFunctionIndex::get_exception_new_index())
+    }
+
+    fn get_exception_raise_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.exception_raise_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Exception.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_raise_sig = Some(sig);
+        (sig, VMBuiltinFunctionIndex::get_exception_raise_index())
+    }
+
+    fn get_exception_clone_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.exception_clone_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Exception.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_clone_sig = Some(sig);
+        (sig, VMBuiltinFunctionIndex::get_exception_clone_index())
+    }
+
+    fn get_exception_drop_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.exception_drop_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Exception.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_drop_sig = Some(sig);
+        (sig, VMBuiltinFunctionIndex::get_exception_drop_index())
+    }
+
+    fn get_epoch_deadline_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.epoch_deadline_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![AbiParam::special(
+                    self.pointer_type(),
+                    ArgumentPurpose::VMContext,
+                )],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.epoch_deadline_sig = Some(sig);
+        (sig, VMBuiltinFunctionIndex::get_epoch_deadline_index())
+    }
+
+    fn get_exception_catch_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.exception_catch_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Exception.
+                    AbiParam::new(I32),
+                    // Tag index.
+                    AbiParam::new(I32),
+                    // Payload.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_catch_sig = Some(sig);
+        (sig, VMBuiltinFunctionIndex::get_exception_catch_index())
+    }
+
+    fn get_exception_invoke_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.exception_invoke_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Signature index.
+                    AbiParam::new(I32),
+                    // Callee address.
+                    AbiParam::new(self.pointer_type()),
+                    // Callee vmctx.
+                    AbiParam::new(self.pointer_type()),
+                    // Arguments and results.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_invoke_sig = Some(sig);
+        (sig, VMBuiltinFunctionIndex::get_exception_invoke_index())
+    }
+
+    /// Insert instructions at `pos` computing the address and the vmctx of
+    /// the function `callee` in the table `table_index`, checking that it has
+    /// the signature `sig_index`.
+    fn translate_call_indirect_target(
+        &mut self,
+        pos: &mut FuncCursor<'_>,
+        table_index: TableIndex,
+        table: ir::Table,
+        sig_index: SignatureIndex,
+        callee: ir::Value,
+    ) -> (ir::Value, ir::Value) {
+        let pointer_type = self.pointer_type();
+
+        let table_entry_addr = pos.ins().table_addr(pointer_type, table, callee, 0);
+
+        // Dereference table_entry_addr to get the function address.
+        let mem_flags = ir::MemFlags::trusted();
+        let table_entry_addr = pos.ins().load(
+            pointer_type,
+            mem_flags,
+            table_entry_addr,
+            i32::from(self.offsets.vm_funcref_anyfunc_ptr()),
+        );
+
+        // check if the funcref is null
+        pos.ins()
+            .trapz(table_entry_addr, ir::TrapCode::IndirectCallToNull);
+
+        let func_addr = pos.ins().load(
+            pointer_type,
+            mem_flags,
+            table_entry_addr,
+            i32::from(self.offsets.vmcaller_checked_anyfunc_func_ptr()),
+        );
+
+        // If necessary, check the signature.
+        match self.table_styles[table_index] {
+            TableStyle::CallerChecksSignature => {
+                let sig_id_size = self.offsets.size_of_vmshared_signature_index();
+                let sig_id_type = ir::Type::int(u16::from(sig_id_size) * 8).unwrap();
+                let vmctx = self.vmctx(pos.func);
+                let base = pos.ins().global_value(pointer_type, vmctx);
+                let offset =
+                    i32::try_from(self.offsets.vmctx_vmshared_signature_id(sig_index)).unwrap();
+
+                // Load the caller ID.
+                let mut mem_flags = ir::MemFlags::trusted();
+                mem_flags.set_readonly();
+                let caller_sig_id = pos.ins().load(sig_id_type, mem_flags, base, offset);
+
+                // Load the callee ID.
+                let mem_flags = ir::MemFlags::trusted();
+                let callee_sig_id = pos.ins().load(
+                    sig_id_type,
+                    mem_flags,
+                    table_entry_addr,
+                    i32::from(self.offsets.vmcaller_checked_anyfunc_type_index()),
+                );
+
+                // Check that they match.
+                let cmp = pos.ins().icmp(IntCC::Equal, callee_sig_id, caller_sig_id);
+                pos.ins().trapz(cmp, ir::TrapCode::BadSignature);
+            }
+        }
+
+        // Load the callee vmctx address.
+        let vmctx = pos.ins().load(
+            pointer_type,
+            mem_flags,
+            table_entry_addr,
+            i32::from(self.offsets.vmcaller_checked_anyfunc_vmctx()),
+        );
+
+        (func_addr, vmctx)
+    }
+
+    /// Insert instructions at `pos` computing the address and the vmctx of
+    /// the imported function `callee_index`.
+    fn translate_imported_call_target(
+        &mut self,
+        pos: &mut FuncCursor<'_>,
+        callee_index: FunctionIndex,
+    ) -> (ir::Value, ir::Value) {
+        // We use an indirect call so that we don't have to patch the code at runtime.
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(pos.func);
+        let base = pos.ins().global_value(pointer_type, vmctx);
+
+        let mem_flags = ir::MemFlags::trusted();
+
+        // Load the callee address.
+        let body_offset =
+            i32::try_from(self.offsets.vmctx_vmfunction_import_body(callee_index)).unwrap();
+        let func_addr = pos.ins().load(pointer_type, mem_flags, base, body_offset);
+
+        // Load the callee vmctx address.
+        let vmctx_offset =
+            i32::try_from(self.offsets.vmctx_vmfunction_import_vmctx(callee_index)).unwrap();
+        let vmctx = pos.ins().load(pointer_type, mem_flags, base, vmctx_offset);
+
+        (func_addr, vmctx)
+    }
+
+    /// Insert instructions at `pos` calling the function at `func_addr`
+    /// through the `exception_invoke` builtin, returning the handle of the
+    /// exception it threw, or zero.
+    fn translate_exception_invoke(
+        &mut self,
+        pos: &mut FuncCursor<'_>,
+        sig_index: SignatureIndex,
+        func_addr: ir::Value,
+        callee_vmctx: ir::Value,
+        values: ir::Value,
+    ) -> ir::Value {
+        let (func_sig, func_idx) = self.get_exception_invoke_func(pos.func);
+        let sig_index_arg = pos.ins().iconst(I32, sig_index.as_u32() as i64);
+        let (vmctx, invoke_addr) = self.translate_load_builtin_function_address(pos, func_idx);
+        let call_inst = pos.ins().call_indirect(
+            func_sig,
+            invoke_addr,
+            &[vmctx, sig_index_arg, func_addr, callee_vmctx, values],
+        );
+        *pos.func.dfg.inst_results(call_inst).first().unwrap()
+    }
+
     /// Translates load of builtin function and returns a pair of values `vmctx`
     /// and address of the loaded function.
     fn translate_load_builtin_function_address(
@@ -1004,9 +1301,11 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         Ok(match ty {
             HeapType::Func => pos.ins().null(self.reference_type()),
             HeapType::Extern => pos.ins().null(self.reference_type()),
+            // `exnref`s are handles to the exceptions kept by the instance.
+            HeapType::Exn => pos.ins().iconst(I32, 0),
             _ => {
                 return Err(WasmError::Unsupported(
-                    "`ref.null T` that is not a `funcref` or an `externref`".into(),
+                    "`ref.null T` that is not a `funcref`, an `externref` or an `exnref`".into(),
                 ));
             }
         })
@@ -1021,7 +1320,8 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
             // `externref`
             ty if ty.is_ref() => pos.ins().is_null(value),
             // `funcref`
-            ty if ty == self.pointer_type() => {
+            // `funcref` and `exnref`
+            ty if ty == self.pointer_type() || ty == I32 => {
                 pos.ins()
                     .icmp_imm(cranelift_codegen::ir::condcodes::IntCC::Equal, value, 0)
             }
@@ -1210,69 +1510,12 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         callee: ir::Value,
         call_args: &[ir::Value],
     ) -> WasmResult<ir::Inst> {
-        let pointer_type = self.pointer_type();
-
-        let table_entry_addr = pos.ins().table_addr(pointer_type, table, callee, 0);
-
-        // Dereference table_entry_addr to get the function address.
-        let mem_flags = ir::MemFlags::trusted();
-        let table_entry_addr = pos.ins().load(
-            pointer_type,
-            mem_flags,
-            table_entry_addr,
-            i32::from(self.offsets.vm_funcref_anyfunc_ptr()),
-        );
-
-        // check if the funcref is null
-        pos.ins()
-            .trapz(table_entry_addr, ir::TrapCode::IndirectCallToNull);
-
-        let func_addr = pos.ins().load(
-            pointer_type,
-            mem_flags,
-            table_entry_addr,
-            i32::from(self.offsets.vmcaller_checked_anyfunc_func_ptr()),
-        );
-
-        // If necessary, check the signature.
-        match self.table_styles[table_index] {
-            TableStyle::CallerChecksSignature => {
-                let sig_id_size = self.offsets.size_of_vmshared_signature_index();
-                let sig_id_type = ir::Type::int(u16::from(sig_id_size) * 8).unwrap();
-                let vmctx = self.vmctx(pos.func);
-                let base = pos.ins().global_value(pointer_type, vmctx);
-                let offset =
-                    i32::try_from(self.offsets.vmctx_vmshared_signature_id(sig_index)).unwrap();
-
-                // Load the caller ID.
-                let mut mem_flags = ir::MemFlags::trusted();
-                mem_flags.set_readonly();
-                let caller_sig_id = pos.ins().load(sig_id_type, mem_flags, base, offset);
-
-                // Load the callee ID.
-                let mem_flags = ir::MemFlags::trusted();
-                let callee_sig_id = pos.ins().load(
-                    sig_id_type,
-                    mem_flags,
-                    table_entry_addr,
-                    i32::from(self.offsets.vmcaller_checked_anyfunc_type_index()),
-                );
-
-                // Check that they match.
-                let cmp = pos.ins().icmp(IntCC::Equal, callee_sig_id, caller_sig_id);
-                pos.ins().trapz(cmp, ir::TrapCode::BadSignature);
-            }
-        }
+        let (func_addr, vmctx) =
+            self.translate_call_indirect_target(&mut pos, table_index, table, sig_index, callee);
 
         let mut real_call_args = Vec::with_capacity(call_args.len() + 2);
 
         // First append the callee vmctx address.
-        let vmctx = pos.ins().load(
-            pointer_type,
-            mem_flags,
-            table_entry_addr,
-            i32::from(self.offsets.vmcaller_checked_anyfunc_vmctx()),
-        );
         real_call_args.push(vmctx);
 
         // Then append the regular call arguments.
@@ -1304,24 +1547,11 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
             return Ok(pos.ins().call(callee, &real_call_args));
         }
 
-        // Handle direct calls to imported functions. We use an indirect call
-        // so that we don't have to patch the code at runtime.
-        let pointer_type = self.pointer_type();
+        // Handle direct calls to imported functions.
         let sig_ref = pos.func.dfg.ext_funcs[callee].signature;
-        let vmctx = self.vmctx(pos.func);
-        let base = pos.ins().global_value(pointer_type, vmctx);
-
-        let mem_flags = ir::MemFlags::trusted();
-
-        // Load the callee address.
-        let body_offset =
-            i32::try_from(self.offsets.vmctx_vmfunction_import_body(callee_index)).unwrap();
-        let func_addr = pos.ins().load(pointer_type, mem_flags, base, body_offset);
+        let (func_addr, vmctx) = self.translate_imported_call_target(&mut pos, callee_index);
 
         // First append the callee vmctx address.
-        let vmctx_offset =
-            i32::try_from(self.offsets.vmctx_vmfunction_import_vmctx(callee_index)).unwrap();
-        let vmctx = pos.ins().load(pointer_type, mem_flags, base, vmctx_offset);
         real_call_args.push(vmctx);
 
         // Then append the regular call arguments.
@@ -1330,6 +1560,121 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         Ok(pos.ins().call_indirect(sig_ref, func_addr, &real_call_args))
     }
 
+    fn translate_call_invoke(
+        &mut self,
+        mut pos: FuncCursor<'_>,
+        callee_index: FunctionIndex,
+        callee: ir::FuncRef,
+        values: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        let (func_addr, vmctx) = if self.module.is_imported_function(callee_index) {
+            self.translate_imported_call_target(&mut pos, callee_index)
+        } else {
+            // Locally-defined functions share the vmctx of the caller.
+            let pointer_type = self.pointer_type();
+            let func_addr = pos.ins().func_addr(pointer_type, callee);
+            let caller_vmctx = pos.func.special_param(ArgumentPurpose::VMContext).unwrap();
+            (func_addr, caller_vmctx)
+        };
+        let sig_index = self.module.functions[callee_index];
+        Ok(self.translate_exception_invoke(&mut pos, sig_index, func_addr, vmctx, values))
+    }
+
+    fn translate_call_indirect_invoke(
+        &mut self,
+        mut pos: FuncCursor<'_>,
+        table_index: TableIndex,
+        table: ir::Table,
+        sig_index: SignatureIndex,
+        callee: ir::Value,
+        values: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        let (func_addr, vmctx) =
+            self.translate_call_indirect_target(&mut pos, table_index, table, sig_index, callee);
+        Ok(self.translate_exception_invoke(&mut pos, sig_index, func_addr, vmctx, values))
+    }
+
+    fn translate_exception_new(
+        &mut self,
+        mut pos: FuncCursor<'_>,
+        tag_index: TagIndex,
+        values: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        let (func_sig, func_idx) = self.get_exception_new_func(pos.func);
+        let tag_index_arg = pos.ins().iconst(I32, tag_index.as_u32() as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst =
+            pos.ins()
+                .call_indirect(func_sig, func_addr, &[vmctx, tag_index_arg, values]);
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+    }
+
+    fn translate_exception_raise(
+        &mut self,
+        mut pos: FuncCursor<'_>,
+        exception: ir::Value,
+    ) -> WasmResult<()> {
+        let (func_sig, func_idx) = self.get_exception_raise_func(pos.func);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        pos.ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, exception]);
+        Ok(())
+    }
+
+    fn translate_exception_clone(
+        &mut self,
+        mut pos: FuncCursor<'_>,
+        exception: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        // A null `exnref` can't be thrown. There is no trap code dedicated to
+        // null references, so report it as reaching unreachable code.
+        pos.ins()
+            .trapz(exception, ir::TrapCode::UnreachableCodeReached);
+        let (func_sig, func_idx) = self.get_exception_clone_func(pos.func);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst = pos
+            .ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, exception]);
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+    }
+
+    fn translate_exception_drop(
+        &mut self,
+        mut pos: FuncCursor<'_>,
+        exception: ir::Value,
+    ) -> WasmResult<()> {
+        let (func_sig, func_idx) = self.get_exception_drop_func(pos.func);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        pos.ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, exception]);
+        Ok(())
+    }
+
+    fn translate_epoch_deadline(&mut self, mut pos: FuncCursor<'_>) -> WasmResult<()> {
+        let (func_sig, func_idx) = self.get_epoch_deadline_func(pos.func);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        pos.ins().call_indirect(func_sig, func_addr, &[vmctx]);
+        Ok(())
+    }
+
+    fn translate_exception_catch(
+        &mut self,
+        mut pos: FuncCursor<'_>,
+        exception: ir::Value,
+        tag_index: TagIndex,
+        values: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        let (func_sig, func_idx) = self.get_exception_catch_func(pos.func);
+        let tag_index_arg = pos.ins().iconst(I32, tag_index.as_u32() as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst = pos.ins().call_indirect(
+            func_sig,
+            func_addr,
+            &[vmctx, exception, tag_index_arg, values],
+        );
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+    }
+
     fn translate_memory_grow(
         &mut self,
         mut pos: FuncCursor<'_>,
@@ -1606,4 +1951,9 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
     fn get_function_sig(&self, sig_index: SignatureIndex) -> Option<&FunctionType> {
         self.module.signatures.get(sig_index)
     }
+
+    fn get_tag_sig(&self, tag_index: TagIndex) -> Option<&FunctionType> {
+        let sig_idx = self.module.tags.get(tag_index)?;
+        Some(&self.module.signatures[*sig_idx])
+    }
 }
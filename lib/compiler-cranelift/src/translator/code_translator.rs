@@ -75,12 +75,15 @@
 //!     ("Relax verification to allow I8X16 to act as a default vector type")
 
 use super::func_environ::{FuncEnvironment, GlobalVariable, ReturnMode};
-use super::func_state::{ControlStackFrame, ElseData, FuncTranslationState};
-use super::translation_utils::{block_with_params, f32_translation, f64_translation};
+use super::func_state::{CatchClause, ControlStackFrame, ElseData, FuncTranslationState};
+use super::translation_utils::{
+    block_with_params, f32_translation, f64_translation, type_to_irtype,
+};
 use crate::{hash_map, HashMap};
 use core::cmp;
 use core::convert::TryFrom;
 use core::{i32, u32};
+use cranelift_codegen::cursor::{Cursor, FuncCursor};
 use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
 use cranelift_codegen::ir::immediates::Offset32;
 use cranelift_codegen::ir::types::*;
@@ -92,10 +95,11 @@ use cranelift_frontend::{FunctionBuilder, Variable};
 use smallvec::SmallVec;
 use std::vec::Vec;
 
-use wasmer_compiler::wasmparser::{MemArg, Operator};
+use wasmer_compiler::wasmparser::{Catch, MemArg, Operator};
 use wasmer_compiler::{from_binaryreadererror_wasmerror, wasm_unsupported, ModuleTranslationState};
 use wasmer_types::{
-    FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, WasmResult,
+    FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, TagIndex, WasmError,
+    WasmResult,
 };
 
 // Clippy warns about "align: _" but its important to document that the align field is ignored
@@ -372,9 +376,10 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 _ => unreachable!(),
             }
         }
-        Operator::End => {
+        Operator::End | Operator::Delegate { .. } => {
             let frame = state.control_stack.pop().unwrap();
             let next_block = frame.following_code();
+            translate_leave_catch_clause(&frame, true, builder, environ)?;
             if !builder.is_unreachable() || builder.func.layout.first_inst(next_block).is_some() {
                 let return_count = frame.num_return_values();
                 let return_args = state.peekn(return_count);
@@ -387,6 +392,10 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 // below.
             }
 
+            // The exceptions thrown inside of a `try_table` or `try` are
+            // dispatched out of the way of the following code.
+            translate_end_exception_handling(&frame, op, builder, state, environ)?;
+
             builder.switch_to_block(next_block);
             builder.seal_block(next_block);
 
@@ -544,17 +553,96 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             state.popn(return_count);
             state.reachable = false;
         }
-        /********************************** Exception handing **********************************/
-        Operator::Try { .. }
-        | Operator::Catch { .. }
-        | Operator::Throw { .. }
-        | Operator::Rethrow { .. }
-        | Operator::Delegate { .. }
-        | Operator::CatchAll => {
-            return Err(wasm_unsupported!(
-                "proposed exception handling operator {:?}",
-                op
-            ));
+        /********************************** Exception handing **********************************
+         * Exceptions are kept by the instance and referred to by an `i32` handle, which is also
+         * how an `exnref` is represented.
+         *
+         * A `try_table` is translated like a block, with an additional `Block` dispatching the
+         * handles of the exceptions thrown inside of it to its catch clauses, once its `End` is
+         * reached. A `throw` jumps to the dispatch of the innermost `try_table`, or throws the
+         * exception out of the function if there is none. Calls made inside of a `try_table` go
+         * through the runtime, which catches the exceptions thrown by the callee and returns
+         * their handle so that they can be dispatched too.
+         *
+         * The legacy `try` is translated the same way, its `catch` clauses being chained from
+         * its dispatch as they come, and a `delegate` dispatching the exceptions of its body
+         * like a `throw` at its label would. The exception caught by a clause is released at
+         * its start, or when it ends if it is rethrown inside of it. Leaving a clause which
+         * rethrows with a branch leaves the exception to the instance, like a dropped `exnref`.
+         ***********************************************************************************/
+        Operator::TryTable { try_table } => {
+            let (params, results) =
+                module_translation_state.blocktype_params_results(&try_table.ty)?;
+            let next = block_with_params(builder, results.iter(), environ)?;
+            let dispatch = builder.create_block();
+            builder.append_block_param(dispatch, I32);
+            state.push_try_table(
+                next,
+                dispatch,
+                try_table.catches.clone(),
+                params.len(),
+                results.len(),
+            );
+        }
+        Operator::Throw { tag_index } => {
+            let tag_index = TagIndex::from_u32(*tag_index);
+            let payload_types = tag_payload_types(tag_index, environ)?;
+            let (_, values) = spill_values(
+                builder,
+                state.peekn(payload_types.len()),
+                payload_types.len(),
+                environ.pointer_type(),
+            );
+            state.popn(payload_types.len());
+            let exception = environ.translate_exception_new(builder.cursor(), tag_index, values)?;
+            translate_throw_exception(exception, builder, state, environ)?;
+            state.reachable = false;
+        }
+        Operator::ThrowRef => {
+            // The `exnref` may be thrown again, so the exception is copied.
+            let exnref = state.pop1();
+            let exception = environ.translate_exception_clone(builder.cursor(), exnref)?;
+            translate_throw_exception(exception, builder, state, environ)?;
+            state.reachable = false;
+        }
+        Operator::Try { blockty } => {
+            let (params, results) = module_translation_state.blocktype_params_results(blockty)?;
+            let next = block_with_params(builder, results.iter(), environ)?;
+            let dispatch = builder.create_block();
+            builder.append_block_param(dispatch, I32);
+            state.push_try(next, dispatch, params.len(), results.len());
+        }
+        Operator::Catch { tag_index } => {
+            let tag_index = TagIndex::from_u32(*tag_index);
+            translate_catch_clause(Some(tag_index), builder, state, environ)?;
+        }
+        Operator::CatchAll => {
+            translate_catch_clause(None, builder, state, environ)?;
+        }
+        Operator::Rethrow { relative_depth } => {
+            let i = state.control_stack.len() - 1 - (*relative_depth as usize);
+            let exception = match state.control_stack[i] {
+                ControlStackFrame::Try {
+                    clause: Some(ref mut clause),
+                    ..
+                } => {
+                    clause.is_rethrown = true;
+                    clause.exception
+                }
+                _ => unreachable!(),
+            };
+            // The clause still needs the exception if it can be caught
+            // again before leaving it.
+            let exception = if state.control_stack[i + 1..]
+                .iter()
+                .any(ControlStackFrame::catches_exceptions)
+            {
+                environ.translate_exception_clone(builder.cursor(), exception)?
+            } else {
+                exception
+            };
+            translate_throw_exception(exception, builder, state, environ)?;
+            state.reachable = false;
         }
         /************************************ Calls ****************************************
          * The call instructions pop off their arguments from the stack and append their
@@ -563,6 +651,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
          ************************************************************************************/
         Operator::Call { function_index } => {
             let (fref, num_args) = state.get_direct_func(builder.func, *function_index, environ)?;
+            let dispatch = state.exception_dispatch();
 
             let args = state.peekn_mut(num_args);
 
@@ -575,6 +664,27 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             bitcast_arguments(args, &types, builder);
             let func_index = FunctionIndex::from_u32(*function_index);
 
+            if let Some(dispatch) = dispatch {
+                let callee_signature =
+                    &builder.func.dfg.signatures[builder.func.dfg.ext_funcs[fref].signature];
+                let result_types = wasm_param_types(&callee_signature.returns, |i| {
+                    environ.is_wasm_return(callee_signature, i)
+                });
+                let (slot, values) = spill_values(
+                    builder,
+                    state.peekn(num_args),
+                    result_types.len(),
+                    environ.pointer_type(),
+                );
+                let exception =
+                    environ.translate_call_invoke(builder.cursor(), func_index, fref, values)?;
+                let results =
+                    translate_invoke_results(exception, dispatch, slot, &result_types, builder);
+                state.popn(num_args);
+                state.pushn(&results);
+                return Ok(());
+            }
+
             let call = environ.translate_call(builder.cursor(), func_index, fref, args)?;
             let inst_results = builder.inst_results(call);
             debug_assert_eq!(
@@ -606,9 +716,35 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             });
             bitcast_arguments(args, &types, builder);
 
-            let args = state.peekn(num_args);
             let sig_idx = SignatureIndex::from_u32(*type_index);
 
+            if let Some(dispatch) = state.exception_dispatch() {
+                let callee_signature = &builder.func.dfg.signatures[sigref];
+                let result_types = wasm_param_types(&callee_signature.returns, |i| {
+                    environ.is_wasm_return(callee_signature, i)
+                });
+                let (slot, values) = spill_values(
+                    builder,
+                    state.peekn(num_args),
+                    result_types.len(),
+                    environ.pointer_type(),
+                );
+                let exception = environ.translate_call_indirect_invoke(
+                    builder.cursor(),
+                    TableIndex::from_u32(*table_index),
+                    table,
+                    sig_idx,
+                    callee,
+                    values,
+                )?;
+                let results =
+                    translate_invoke_results(exception, dispatch, slot, &result_types, builder);
+                state.popn(num_args);
+                state.pushn(&results);
+                return Ok(());
+            }
+
+            let args = state.peekn(num_args);
             let call = environ.translate_call_indirect(
                 builder.cursor(),
                 TableIndex::from_u32(*table_index),
@@ -2056,11 +2192,6 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
         | Operator::I16x8RelaxedQ15mulrS => {
             return Err(wasm_unsupported!("proposed relaxed-simd operator {:?}", op));
         }
-        Operator::TryTable { .. } | Operator::ThrowRef => {
-            return Err(wasm_unsupported!(
-                "exceptions are not supported (operator: {op:?})"
-            ));
-        }
         Operator::RefEq
         | Operator::StructNew { .. }
         | Operator::StructNewDefault { .. }
@@ -2132,9 +2263,24 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
                 blockty,
             );
         }
-        Operator::Loop { blockty: _ } | Operator::Block { blockty: _ } => {
+        Operator::Loop { blockty: _ }
+        | Operator::Block { blockty: _ }
+        | Operator::TryTable { try_table: _ }
+        | Operator::Try { blockty: _ } => {
             state.push_block(ir::Block::reserved_value(), 0, 0);
         }
+        Operator::Catch { .. } | Operator::CatchAll => {
+            // The clauses of a `try` whose body ends unreachable may still
+            // catch the exceptions thrown in it, unlike the ones of a
+            // placeholder.
+            if let Some(ControlStackFrame::Try { .. }) = state.control_stack.last() {
+                let tag_index = match *op {
+                    Operator::Catch { tag_index } => Some(TagIndex::from_u32(tag_index)),
+                    _ => None,
+                };
+                translate_catch_clause(tag_index, builder, state, environ)?;
+            }
+        }
         Operator::Else => {
             let i = state.control_stack.len() - 1;
             match state.control_stack[i] {
@@ -2184,13 +2330,16 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
                 _ => unreachable!(),
             }
         }
-        Operator::End => {
-            let stack = &mut state.stack;
-            let control_stack = &mut state.control_stack;
-            let frame = control_stack.pop().unwrap();
+        Operator::End | Operator::Delegate { .. } => {
+            let frame = state.control_stack.pop().unwrap();
 
             // Pop unused parameters from stack.
-            frame.truncate_value_stack_to_original_size(stack);
+            frame.truncate_value_stack_to_original_size(&mut state.stack);
+
+            // The body of a `try_table` or `try` may end unreachable after
+            // having thrown exceptions, which still have to be dispatched.
+            translate_leave_catch_clause(&frame, false, builder, environ)?;
+            translate_end_exception_handling(&frame, op, builder, state, environ)?;
 
             let reachable_anyway = match frame {
                 // If it is a loop we also have to seal the body loop block
@@ -2227,7 +2376,9 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
 
                 // And add the return values of the block but only if the next block is reachable
                 // (which corresponds to testing if the stack depth is 1)
-                stack.extend_from_slice(builder.block_params(frame.following_code()));
+                state
+                    .stack
+                    .extend_from_slice(builder.block_params(frame.following_code()));
                 state.reachable = true;
             }
         }
@@ -2747,6 +2898,349 @@ fn translate_br_if_args(
     (br_destination, inputs)
 }
 
+/// Size of each value in the buffers exchanged with the runtime, that of a `RawValue`.
+const RAW_VALUE_SIZE: u32 = 16;
+
+/// Create a buffer on the stack with room for `len` values exchanged with the runtime, and store
+/// `values` at its beginning. Return the buffer along with its address.
+fn spill_values(
+    builder: &mut FunctionBuilder,
+    values: &[Value],
+    len: usize,
+    pointer_type: Type,
+) -> (ir::StackSlot, Value) {
+    let len = cmp::max(cmp::max(len, values.len()), 1) as u32;
+    let slot = builder.create_sized_stack_slot(ir::StackSlotData::new(
+        ir::StackSlotKind::ExplicitSlot,
+        len * RAW_VALUE_SIZE,
+    ));
+    for (i, value) in values.iter().enumerate() {
+        let offset = i as u32 * RAW_VALUE_SIZE;
+        builder.ins().stack_store(*value, slot, offset as i32);
+    }
+    let addr = builder.ins().stack_addr(pointer_type, slot, 0);
+    (slot, addr)
+}
+
+/// Load values of the given types from a buffer created by `spill_values`.
+fn load_values(builder: &mut FunctionBuilder, slot: ir::StackSlot, types: &[Type]) -> Vec<Value> {
+    types
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| {
+            let offset = i as u32 * RAW_VALUE_SIZE;
+            builder.ins().stack_load(*ty, slot, offset as i32)
+        })
+        .collect()
+}
+
+/// Get the Cranelift types of the payload of the exceptions with the given tag.
+fn tag_payload_types<FE: FuncEnvironment + ?Sized>(
+    tag_index: TagIndex,
+    environ: &FE,
+) -> WasmResult<Vec<Type>> {
+    let sig = environ
+        .get_tag_sig(tag_index)
+        .ok_or_else(|| WasmError::Generic(format!("unknown tag {}", tag_index.as_u32())))?;
+    sig.params()
+        .iter()
+        .map(|ty| type_to_irtype(*ty, environ.target_config()))
+        .collect()
+}
+
+/// Jump to `dispatch` if the call made through the runtime threw the exception with the handle
+/// `exception`, and otherwise load its results from the buffer `slot`.
+fn translate_invoke_results(
+    exception: Value,
+    dispatch: ir::Block,
+    slot: ir::StackSlot,
+    result_types: &[Type],
+    builder: &mut FunctionBuilder,
+) -> Vec<Value> {
+    canonicalise_then_brnz(builder, exception, dispatch, &[exception]);
+
+    let next_block = builder.create_block();
+    canonicalise_then_jump(builder, next_block, &[]);
+    builder.seal_block(next_block); // The only predecessor is the current block.
+    builder.switch_to_block(next_block);
+
+    load_values(builder, slot, result_types)
+}
+
+/// Throw the exception with the handle `exception`: jump to the dispatch of the innermost
+/// `try_table` or `try` body, or throw it to the caller of the function if there is none.
+fn translate_throw_exception<FE: FuncEnvironment + ?Sized>(
+    exception: Value,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let dispatch = state.exception_dispatch();
+    translate_throw_exception_to(exception, dispatch, builder, environ)
+}
+
+/// Throw the exception with the handle `exception`: jump to `dispatch`, or throw it to the
+/// caller of the function if there is none.
+fn translate_throw_exception_to<FE: FuncEnvironment + ?Sized>(
+    exception: Value,
+    dispatch: Option<ir::Block>,
+    builder: &mut FunctionBuilder,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    match dispatch {
+        Some(dispatch) => {
+            canonicalise_then_jump(builder, dispatch, &[exception]);
+            Ok(())
+        }
+        None => {
+            environ.translate_exception_raise(builder.cursor(), exception)?;
+            // The runtime unwinds the stack, so the call never returns.
+            builder.ins().trap(ir::TrapCode::UnreachableCodeReached);
+            Ok(())
+        }
+    }
+}
+
+/// Translate the `dispatch` block of a `try_table` whose frame was just popped. The catch
+/// clauses are tried in order, each one branching to its label when it catches the exception,
+/// and the exceptions that none of them catches are thrown further.
+fn translate_exception_dispatch<FE: FuncEnvironment + ?Sized>(
+    dispatch: ir::Block,
+    catches: &[Catch],
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    builder.switch_to_block(dispatch);
+    builder.seal_block(dispatch); // All the throws of the body have been translated.
+    let exception = builder.block_params(dispatch)[0];
+
+    for catch in catches {
+        let (tag, label, keeps_exception) = match *catch {
+            Catch::One { tag, label } => (tag, label, false),
+            Catch::OneRef { tag, label } => (tag, label, true),
+            Catch::All { label } => {
+                environ.translate_exception_drop(builder.cursor(), exception)?;
+                translate_br_with_args(label, &[], builder, state);
+                return Ok(());
+            }
+            Catch::AllRef { label } => {
+                translate_br_with_args(label, &[exception], builder, state);
+                return Ok(());
+            }
+        };
+        let tag_index = TagIndex::from_u32(tag);
+        let payload_types = tag_payload_types(tag_index, environ)?;
+        let (slot, values) =
+            spill_values(builder, &[], payload_types.len(), environ.pointer_type());
+        let caught =
+            environ.translate_exception_catch(builder.cursor(), exception, tag_index, values)?;
+
+        let handler = builder.create_block();
+        let next_clause = builder.create_block();
+        canonicalise_then_brz(builder, caught, next_clause, &[]);
+        canonicalise_then_jump(builder, handler, &[]);
+        builder.seal_block(handler);
+        builder.seal_block(next_clause);
+
+        builder.switch_to_block(handler);
+        let mut args = load_values(builder, slot, &payload_types);
+        if keeps_exception {
+            args.push(exception);
+        } else {
+            environ.translate_exception_drop(builder.cursor(), exception)?;
+        }
+        translate_br_with_args(label, &args, builder, state);
+
+        builder.switch_to_block(next_clause);
+    }
+
+    translate_throw_exception(exception, builder, state, environ)
+}
+
+/// Translate the dispatch of the exceptions thrown inside of the `try_table` or `try` whose frame
+/// was just popped by `op`, an `End` or a `Delegate`. The exceptions that the catch clauses of a
+/// `try` don't catch are thrown further, or to the label of the `Delegate`.
+fn translate_end_exception_handling<FE: FuncEnvironment + ?Sized>(
+    frame: &ControlStackFrame,
+    op: &Operator,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    match *frame {
+        ControlStackFrame::TryTable {
+            dispatch,
+            dispatch_is_used: true,
+            ref catches,
+            ..
+        } => translate_exception_dispatch(dispatch, catches, builder, state, environ),
+        ControlStackFrame::Try {
+            dispatch,
+            dispatch_is_used,
+            is_catching,
+            next_clause,
+            ..
+        } => {
+            let uncaught = if is_catching {
+                next_clause
+            } else if dispatch_is_used {
+                builder.seal_block(dispatch); // All the throws of the body have been translated.
+                Some(dispatch)
+            } else {
+                None
+            };
+            if let Some(uncaught) = uncaught {
+                builder.switch_to_block(uncaught);
+                let exception = builder.block_params(dispatch)[0];
+                let dispatch = match *op {
+                    Operator::Delegate { relative_depth } => {
+                        state.delegated_exception_dispatch(relative_depth)
+                    }
+                    _ => state.exception_dispatch(),
+                };
+                translate_throw_exception_to(exception, dispatch, builder, environ)?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Start a catch clause of the innermost `try`, catching the exceptions with the tag `tag_index`,
+/// or all of them, which the previous clauses didn't catch.
+fn translate_catch_clause<FE: FuncEnvironment + ?Sized>(
+    tag_index: Option<TagIndex>,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let i = state.control_stack.len() - 1;
+    translate_leave_catch_clause(&state.control_stack[i], state.reachable, builder, environ)?;
+    if state.reachable {
+        let frame = &mut state.control_stack[i];
+        frame.set_branched_to_exit();
+        let destination = frame.following_code();
+        let return_count = frame.num_return_values();
+        canonicalise_then_jump(builder, destination, state.peekn(return_count));
+    }
+    state.control_stack[i].truncate_value_stack_to_original_size(&mut state.stack);
+
+    let (dispatch, is_catching, clause, next_clause) = match state.control_stack[i] {
+        ControlStackFrame::Try {
+            dispatch,
+            dispatch_is_used,
+            ref mut is_catching,
+            ref mut clause,
+            ref mut next_clause,
+            ..
+        } => {
+            if !*is_catching {
+                *next_clause = if dispatch_is_used {
+                    builder.seal_block(dispatch); // All the throws of the body have been translated.
+                    Some(dispatch)
+                } else {
+                    None
+                };
+            }
+            (dispatch, is_catching, clause, next_clause)
+        }
+        _ => unreachable!(),
+    };
+    *is_catching = true;
+    *clause = None;
+    let tried = match next_clause.take() {
+        Some(tried) => tried,
+        None => {
+            // No exception gets to this clause.
+            state.reachable = false;
+            return Ok(());
+        }
+    };
+
+    builder.switch_to_block(tried);
+    let exception = builder.block_params(dispatch)[0];
+    let handler = builder.create_block();
+    let payload = match tag_index {
+        Some(tag_index) => {
+            let payload_types = tag_payload_types(tag_index, environ)?;
+            let (slot, values) =
+                spill_values(builder, &[], payload_types.len(), environ.pointer_type());
+            let caught = environ.translate_exception_catch(
+                builder.cursor(),
+                exception,
+                tag_index,
+                values,
+            )?;
+            let next = builder.create_block();
+            canonicalise_then_brz(builder, caught, next, &[]);
+            canonicalise_then_jump(builder, handler, &[]);
+            builder.seal_block(next);
+            *next_clause = Some(next);
+            Some((slot, payload_types))
+        }
+        None => {
+            canonicalise_then_jump(builder, handler, &[]);
+            None
+        }
+    };
+    builder.seal_block(handler);
+    builder.switch_to_block(handler);
+    // The exception may be released at the start of the clause once it is
+    // translated, which must then be in the layout.
+    builder.ensure_inserted_block();
+    *clause = Some(CatchClause {
+        handler,
+        exception,
+        is_rethrown: false,
+    });
+
+    if let Some((slot, payload_types)) = payload {
+        let values = load_values(builder, slot, &payload_types);
+        state.pushn(&values);
+    }
+    state.reachable = true;
+    Ok(())
+}
+
+/// Release the exception caught by the catch clause that `frame` is leaving, if any. This is done
+/// at the start of the clause, unless the exception is rethrown inside of it, in which case it is
+/// done at the end of the clause if it is `reachable`.
+fn translate_leave_catch_clause<FE: FuncEnvironment + ?Sized>(
+    frame: &ControlStackFrame,
+    reachable: bool,
+    builder: &mut FunctionBuilder,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    if let ControlStackFrame::Try {
+        clause: Some(clause),
+        ..
+    } = *frame
+    {
+        if !clause.is_rethrown {
+            let pos = FuncCursor::new(builder.func).at_first_insertion_point(clause.handler);
+            environ.translate_exception_drop(pos, clause.exception)?;
+        } else if reachable {
+            environ.translate_exception_drop(builder.cursor(), clause.exception)?;
+        }
+    }
+    Ok(())
+}
+
+/// Branch to the label `relative_depth` with the given arguments, which are not taken from the
+/// value stack.
+fn translate_br_with_args(
+    relative_depth: u32,
+    args: &[Value],
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+) {
+    let i = state.control_stack.len() - 1 - (relative_depth as usize);
+    let frame = &mut state.control_stack[i];
+    frame.set_branched_to_exit();
+    canonicalise_then_jump(builder, frame.br_destination(), args);
+}
+
 /// Determine the returned value type of a WebAssembly operator
 fn type_of(operator: &Operator) -> Type {
     match operator {
@@ -15,7 +15,7 @@ use cranelift_frontend::FunctionBuilder;
 use wasmer_compiler::wasmparser::{HeapType, Operator};
 use wasmer_types::{
     FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, SignatureIndex,
-    TableIndex, Type as WasmerType, WasmResult,
+    TableIndex, TagIndex, Type as WasmerType, WasmResult,
 };
 
 /// The value of a WebAssembly global variable.
@@ -438,6 +438,91 @@ pub trait FuncEnvironment: TargetEnvironment {
         count: ir::Value,
     ) -> WasmResult<ir::Value>;
 
+    /// Translate the creation of an exception for a `throw` WebAssembly
+    /// instruction at `pos`.
+    ///
+    /// The payload of the exception, as described by the tag `tag_index`, is
+    /// read from the buffer at `values`, one 16-byte slot per value.
+    ///
+    /// Returns the handle of the new exception.
+    fn translate_exception_new(
+        &mut self,
+        pos: FuncCursor,
+        tag_index: TagIndex,
+        values: ir::Value,
+    ) -> WasmResult<ir::Value>;
+
+    /// Insert instructions at `pos` throwing the exception with the handle
+    /// `exception` to the caller of the function. They do not return, which the
+    /// translator accounts for by terminating the block after them.
+    fn translate_exception_raise(
+        &mut self,
+        pos: FuncCursor,
+        exception: ir::Value,
+    ) -> WasmResult<()>;
+
+    /// Insert instructions at `pos` copying the exception with the handle
+    /// `exception`, as done by a `throw_ref` WebAssembly instruction.
+    ///
+    /// Returns the handle of the copy.
+    fn translate_exception_clone(
+        &mut self,
+        pos: FuncCursor,
+        exception: ir::Value,
+    ) -> WasmResult<ir::Value>;
+
+    /// Insert instructions at `pos` calling the epoch deadline builtin, once
+    /// the epoch of the engine reached the deadline of the store.
+    fn translate_epoch_deadline(&mut self, pos: FuncCursor) -> WasmResult<()>;
+
+    /// Insert instructions at `pos` releasing the exception with the handle
+    /// `exception`, once it was caught by a handler that does not keep it.
+    fn translate_exception_drop(&mut self, pos: FuncCursor, exception: ir::Value)
+        -> WasmResult<()>;
+
+    /// Insert instructions at `pos` checking whether the exception with the
+    /// handle `exception` was thrown with the tag `tag_index`, in which case
+    /// its payload is written to the buffer at `values`.
+    ///
+    /// Returns an i32, which is non-zero if the tag matched.
+    fn translate_exception_catch(
+        &mut self,
+        pos: FuncCursor,
+        exception: ir::Value,
+        tag_index: TagIndex,
+        values: ir::Value,
+    ) -> WasmResult<ir::Value>;
+
+    /// Translate a `call` WebAssembly instruction at `pos` that is made inside
+    /// of a `try_table`, so that the exceptions thrown by the callee can be
+    /// caught.
+    ///
+    /// The arguments are read from the buffer at `values`, one 16-byte slot
+    /// per value, and the results are written back to it.
+    ///
+    /// Returns an i32 holding the handle of the exception thrown by the
+    /// callee, or zero if it returned normally.
+    fn translate_call_invoke(
+        &mut self,
+        pos: FuncCursor,
+        callee_index: FunctionIndex,
+        callee: ir::FuncRef,
+        values: ir::Value,
+    ) -> WasmResult<ir::Value>;
+
+    /// Translate a `call_indirect` WebAssembly instruction at `pos` that is
+    /// made inside of a `try_table`, in the same way as
+    /// `translate_call_invoke()`.
+    fn translate_call_indirect_invoke(
+        &mut self,
+        pos: FuncCursor,
+        table_index: TableIndex,
+        table: ir::Table,
+        sig_index: SignatureIndex,
+        callee: ir::Value,
+        values: ir::Value,
+    ) -> WasmResult<ir::Value>;
+
     /// Emit code at the beginning of every wasm loop.
     ///
     /// This can be used to insert explicit interrupt or safepoint checking at
@@ -489,4 +574,8 @@ pub trait FuncEnvironment: TargetEnvironment {
 
     /// Get the type of a function with the given signature index.
     fn get_function_sig(&self, sig_index: SignatureIndex) -> Option<&FunctionType>;
+
+    /// Get the signature of the tag with the given index, whose parameters
+    /// are the payload of its exceptions.
+    fn get_tag_sig(&self, tag_index: TagIndex) -> Option<&FunctionType>;
 }
@@ -13,6 +13,7 @@ use super::func_environ::{FuncEnvironment, GlobalVariable};
 use crate::{HashMap, Occupied, Vacant};
 use cranelift_codegen::ir::{self, Block, Inst, Value};
 use std::vec::Vec;
+use wasmer_compiler::wasmparser::Catch;
 use wasmer_types::{
     FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, WasmResult,
 };
@@ -44,8 +45,20 @@ pub enum ElseData {
     },
 }
 
-/// A control stack frame can be an `if`, a `block` or a `loop`, each one having the following
-/// fields:
+/// The `catch` or `catch_all` clause of a legacy `try` being translated.
+#[derive(Debug, Clone, Copy)]
+pub struct CatchClause {
+    /// The block the clause starts at.
+    pub handler: Block,
+    /// The handle of the caught exception.
+    pub exception: Value,
+    /// Is the exception rethrown by the clause? Otherwise it is released as
+    /// soon as the clause is entered.
+    pub is_rethrown: bool,
+}
+
+/// A control stack frame can be an `if`, a `block`, a `loop`, a `try_table` or a legacy `try`,
+/// each one having the following fields:
 ///
 /// - `destination`: reference to the `Block` that will hold the code after the control block;
 /// - `num_return_values`: number of values returned by the control block;
@@ -53,7 +66,10 @@ pub enum ElseData {
 ///
 /// Moreover, the `if` frame has the `branch_inst` field that points to the `brz` instruction
 /// separating the `true` and `false` branch. The `loop` frame has a `header` field that references
-/// the `Block` that contains the beginning of the body of the loop.
+/// the `Block` that contains the beginning of the body of the loop. The `try_table` frame has a
+/// `dispatch` field that references the `Block` receiving the exceptions thrown inside of it,
+/// along with the catch clauses that dispatch them. The `try` frame has a `dispatch` field too,
+/// its catch clauses being translated as they come once its body is done.
 #[derive(Debug)]
 pub enum ControlStackFrame {
     If {
@@ -90,6 +106,36 @@ pub enum ControlStackFrame {
         num_return_values: usize,
         original_stack_size: usize,
     },
+    TryTable {
+        destination: Block,
+        num_param_values: usize,
+        num_return_values: usize,
+        original_stack_size: usize,
+        exit_is_branched_to: bool,
+        /// The block receiving the handle of the exceptions thrown in the body.
+        dispatch: Block,
+        /// Has anything in the body branched to `dispatch`?
+        dispatch_is_used: bool,
+        catches: Vec<Catch>,
+    },
+    Try {
+        destination: Block,
+        num_param_values: usize,
+        num_return_values: usize,
+        original_stack_size: usize,
+        exit_is_branched_to: bool,
+        /// The block receiving the handle of the exceptions thrown in the body.
+        dispatch: Block,
+        /// Has anything in the body branched to `dispatch`?
+        dispatch_is_used: bool,
+        /// Is the body done, the catch clauses being translated?
+        is_catching: bool,
+        /// The catch clause being translated, if it can be reached.
+        clause: Option<CatchClause>,
+        /// The block trying the next catch clauses on the exceptions the
+        /// previous ones didn't catch, if any.
+        next_clause: Option<Block>,
+    },
 }
 
 /// Helper methods for the control stack objects.
@@ -104,6 +150,12 @@ impl ControlStackFrame {
             }
             | Self::Loop {
                 num_return_values, ..
+            }
+            | Self::TryTable {
+                num_return_values, ..
+            }
+            | Self::Try {
+                num_return_values, ..
             } => num_return_values,
         }
     }
@@ -117,6 +169,12 @@ impl ControlStackFrame {
             }
             | Self::Loop {
                 num_param_values, ..
+            }
+            | Self::TryTable {
+                num_param_values, ..
+            }
+            | Self::Try {
+                num_param_values, ..
             } => num_param_values,
         }
     }
@@ -124,12 +182,17 @@ impl ControlStackFrame {
         match *self {
             Self::If { destination, .. }
             | Self::Block { destination, .. }
-            | Self::Loop { destination, .. } => destination,
+            | Self::Loop { destination, .. }
+            | Self::TryTable { destination, .. }
+            | Self::Try { destination, .. } => destination,
         }
     }
     pub fn br_destination(&self) -> Block {
         match *self {
-            Self::If { destination, .. } | Self::Block { destination, .. } => destination,
+            Self::If { destination, .. }
+            | Self::Block { destination, .. }
+            | Self::TryTable { destination, .. }
+            | Self::Try { destination, .. } => destination,
             Self::Loop { header, .. } => header,
         }
     }
@@ -148,12 +211,22 @@ impl ControlStackFrame {
             | Self::Loop {
                 original_stack_size,
                 ..
+            }
+            | Self::TryTable {
+                original_stack_size,
+                ..
+            }
+            | Self::Try {
+                original_stack_size,
+                ..
             } => original_stack_size,
         }
     }
     pub fn is_loop(&self) -> bool {
         match *self {
-            Self::If { .. } | Self::Block { .. } => false,
+            Self::If { .. } | Self::Block { .. } | Self::TryTable { .. } | Self::Try { .. } => {
+                false
+            }
             Self::Loop { .. } => true,
         }
     }
@@ -167,6 +240,14 @@ impl ControlStackFrame {
             | Self::Block {
                 exit_is_branched_to,
                 ..
+            }
+            | Self::TryTable {
+                exit_is_branched_to,
+                ..
+            }
+            | Self::Try {
+                exit_is_branched_to,
+                ..
             } => exit_is_branched_to,
             Self::Loop { .. } => false,
         }
@@ -181,11 +262,54 @@ impl ControlStackFrame {
             | Self::Block {
                 ref mut exit_is_branched_to,
                 ..
+            }
+            | Self::TryTable {
+                ref mut exit_is_branched_to,
+                ..
+            }
+            | Self::Try {
+                ref mut exit_is_branched_to,
+                ..
             } => *exit_is_branched_to = true,
             Self::Loop { .. } => {}
         }
     }
 
+    /// The block dispatching the exceptions thrown inside of a `try_table`,
+    /// or of the body of a `try`, if this is one. The block is then
+    /// considered to be branched to.
+    pub fn use_exception_dispatch(&mut self) -> Option<Block> {
+        match *self {
+            Self::TryTable {
+                dispatch,
+                ref mut dispatch_is_used,
+                ..
+            }
+            | Self::Try {
+                dispatch,
+                ref mut dispatch_is_used,
+                is_catching: false,
+                ..
+            } => {
+                *dispatch_is_used = true;
+                Some(dispatch)
+            }
+            Self::If { .. } | Self::Block { .. } | Self::Loop { .. } | Self::Try { .. } => None,
+        }
+    }
+
+    /// Can the exceptions thrown at this point be caught by this frame?
+    pub fn catches_exceptions(&self) -> bool {
+        matches!(
+            *self,
+            Self::TryTable { .. }
+                | Self::Try {
+                    is_catching: false,
+                    ..
+                }
+        )
+    }
+
     /// Pop values from the value stack so that it is left at the
     /// input-parameters to an else-block.
     pub fn truncate_value_stack_to_else_params(&self, stack: &mut Vec<Value>) {
@@ -412,6 +536,71 @@ impl FuncTranslationState {
         });
     }
 
+    /// Push a try_table on the control stack.
+    pub(crate) fn push_try_table(
+        &mut self,
+        following_code: Block,
+        dispatch: Block,
+        catches: Vec<Catch>,
+        num_param_types: usize,
+        num_result_types: usize,
+    ) {
+        debug_assert!(num_param_types <= self.stack.len());
+        self.control_stack.push(ControlStackFrame::TryTable {
+            destination: following_code,
+            original_stack_size: self.stack.len() - num_param_types,
+            num_param_values: num_param_types,
+            num_return_values: num_result_types,
+            exit_is_branched_to: false,
+            dispatch,
+            dispatch_is_used: false,
+            catches,
+        });
+    }
+
+    /// Push a legacy try on the control stack.
+    pub(crate) fn push_try(
+        &mut self,
+        following_code: Block,
+        dispatch: Block,
+        num_param_types: usize,
+        num_result_types: usize,
+    ) {
+        debug_assert!(num_param_types <= self.stack.len());
+        self.control_stack.push(ControlStackFrame::Try {
+            destination: following_code,
+            original_stack_size: self.stack.len() - num_param_types,
+            num_param_values: num_param_types,
+            num_return_values: num_result_types,
+            exit_is_branched_to: false,
+            dispatch,
+            dispatch_is_used: false,
+            is_catching: false,
+            clause: None,
+            next_clause: None,
+        });
+    }
+
+    /// Get the block dispatching the exceptions thrown at this point, that is
+    /// the one of the innermost `try_table`, or `try` body, if any.
+    pub(crate) fn exception_dispatch(&mut self) -> Option<Block> {
+        self.control_stack
+            .iter_mut()
+            .rev()
+            .find_map(ControlStackFrame::use_exception_dispatch)
+    }
+
+    /// Get the block dispatching the exceptions delegated to the label
+    /// `relative_depth`, that is the one of the innermost `try_table`, or
+    /// `try` body, starting from this label, if any.
+    pub(crate) fn delegated_exception_dispatch(&mut self, relative_depth: u32) -> Option<Block> {
+        let i = self.control_stack.len() - 1 - (relative_depth as usize);
+        self.control_stack[..=i]
+            .iter_mut()
+            .rev()
+            .find_map(ControlStackFrame::use_exception_dispatch)
+    }
+
     /// Push an if on the control stack.
     pub(crate) fn push_if(
         &mut self,
@@ -17,7 +17,7 @@ use cranelift_codegen::timing;
 use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
 use wasmer_compiler::{wasm_unsupported, wasmparser};
 use wasmer_compiler::{wptype_to_type, FunctionBinaryReader, ModuleTranslationState};
-use wasmer_types::{LocalFunctionIndex, WasmResult};
+use wasmer_types::{LocalFunctionIndex, TrapCode, Type, WasmResult};
 
 /// WebAssembly to Cranelift IR function translator.
 ///
@@ -197,13 +197,20 @@ fn declare_locals<FE: FuncEnvironment + ?Sized>(
         Ref(ty) => {
             if ty.is_func_ref() || ty.is_extern_ref() {
                 builder.ins().null(environ.reference_type())
+            } else if ty.heap_type() == wasmparser::HeapType::Exn {
+                builder.ins().iconst(ir::types::I32, 0)
             } else {
                 return Err(wasm_unsupported!("unsupported reference type: {:?}", ty));
             }
         }
     };
 
-    let wasmer_ty = wptype_to_type(wasm_type).unwrap();
+    // An `exnref` is the handle of an exception kept by the instance, and is
+    // only ever found within a function.
+    let wasmer_ty = match wasm_type {
+        Ref(ty) if ty.heap_type() == wasmparser::HeapType::Exn => Type::I32,
+        _ => wptype_to_type(wasm_type).unwrap(),
+    };
     let ty = builder.func.dfg.value_type(zeroval);
     for _ in 0..count {
         let local = Variable::new(*next_local);
@@ -235,7 +242,18 @@ fn parse_function_body<FE: FuncEnvironment + ?Sized>(
         builder.set_srcloc(cur_srcloc(reader));
         let op = reader.read_operator()?;
         environ.before_translate_operator(&op, builder, state)?;
-        translate_operator(module_translation_state, &op, builder, state, environ)?;
+        match reader.trap_code() {
+            // An `Unreachable` pushed by a middleware with its own trap code
+            Some(trap_code) if state.reachable => {
+                builder.ins().trap(translate_trapcode(trap_code));
+                state.reachable = false;
+            }
+            // A `Nop` pushed by a middleware to check the epoch deadline
+            _ if reader.epoch_deadline() && state.reachable => {
+                environ.translate_epoch_deadline(builder.cursor())?;
+            }
+            _ => translate_operator(module_translation_state, &op, builder, state, environ)?,
+        }
         environ.after_translate_operator(&op, builder, state)?;
     }
 
@@ -269,6 +287,24 @@ fn parse_function_body<FE: FuncEnvironment + ?Sized>(
     Ok(())
 }
 
+/// Translates a generic Trap Code into the Cranelift IR TrapCode.
+fn translate_trapcode(trap: TrapCode) -> ir::TrapCode {
+    match trap {
+        TrapCode::StackOverflow => ir::TrapCode::StackOverflow,
+        TrapCode::HeapAccessOutOfBounds => ir::TrapCode::HeapOutOfBounds,
+        TrapCode::HeapMisaligned | TrapCode::UnalignedAtomic => ir::TrapCode::HeapMisaligned,
+        TrapCode::TableAccessOutOfBounds => ir::TrapCode::TableOutOfBounds,
+        TrapCode::IndirectCallToNull => ir::TrapCode::IndirectCallToNull,
+        TrapCode::BadSignature => ir::TrapCode::BadSignature,
+        TrapCode::IntegerOverflow => ir::TrapCode::IntegerOverflow,
+        TrapCode::IntegerDivisionByZero => ir::TrapCode::IntegerDivisionByZero,
+        TrapCode::BadConversionToInteger => ir::TrapCode::BadConversionToInteger,
+        TrapCode::UnreachableCodeReached => ir::TrapCode::UnreachableCodeReached,
+        TrapCode::CallDepthExceeded => ir::TrapCode::User(TrapCode::CallDepthExceeded as u16),
+        TrapCode::Interrupt => ir::TrapCode::Interrupt,
+    }
+}
+
 /// Get the current source location from a reader.
 fn cur_srcloc(reader: &dyn FunctionBinaryReader) -> ir::SourceLoc {
     // We record source locations as byte code offsets relative to the beginning of the file.
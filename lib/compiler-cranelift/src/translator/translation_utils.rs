@@ -113,6 +113,9 @@ pub fn block_with_params<'a, PE: TargetEnvironment + ?Sized>(
             wasmparser::ValType::Ref(ty) => {
                 if ty.is_extern_ref() || ty.is_func_ref() {
                     builder.append_block_param(block, environ.reference_type());
+                } else if ty.heap_type() == wasmparser::HeapType::Exn {
+                    // An `exnref` is the handle of an exception kept by the instance.
+                    builder.append_block_param(block, ir::types::I32);
                 } else {
                     return Err(WasmError::Unsupported(format!(
                         "unsupported reference type: {:?}",
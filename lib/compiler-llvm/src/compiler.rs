@@ -14,8 +14,8 @@ use wasmer_compiler::{Compiler, FunctionBodyData, ModuleMiddleware, ModuleTransl
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     Compilation, CompileError, CompileModuleInfo, CustomSection, CustomSectionProtection, Dwarf,
-    FunctionIndex, LocalFunctionIndex, RelocationTarget, SectionBody, SectionIndex, SignatureIndex,
-    Symbol, SymbolRegistry, Target,
+    Features, FunctionIndex, LocalFunctionIndex, RelocationTarget, SectionBody, SectionIndex,
+    SignatureIndex, Symbol, SymbolRegistry, Target,
 };
 
 //use std::sync::Mutex;
@@ -199,6 +199,13 @@ impl Compiler for LLVMCompiler {
         "llvm"
     }
 
+    fn supported_features(&self, features: &Features) -> Features {
+        let mut features = features.clone();
+        // Exceptions are not implemented yet.
+        features.exceptions = false;
+        features
+    }
+
     /// Get the middlewares for this compiler
     fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
         &self.config.middlewares
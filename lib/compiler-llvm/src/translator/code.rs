@@ -14,8 +14,9 @@ use inkwell::{
     targets::{FileType, TargetMachine},
     types::{BasicType, BasicTypeEnum, FloatMathType, IntType, PointerType, VectorType},
     values::{
-        BasicMetadataValueEnum, BasicValue, BasicValueEnum, FloatValue, FunctionValue,
-        InstructionOpcode, InstructionValue, IntValue, PhiValue, PointerValue, VectorValue,
+        BasicMetadataValueEnum, BasicValue, BasicValueEnum, CallSiteValue, FloatValue,
+        FunctionValue, InstructionOpcode, InstructionValue, IntValue, PhiValue, PointerValue,
+        VectorValue,
     },
     AddressSpace, AtomicOrdering, AtomicRMWBinOp, DLLStorageClass, FloatPredicate, IntPredicate,
 };
@@ -31,10 +32,11 @@ use wasmer_compiler::{
 };
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
-    CompileError, FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex,
-    ModuleInfo, RelocationTarget, SignatureIndex, Symbol, SymbolRegistry, TableIndex, Type,
+    CompileError, ExportIndex, FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex,
+    MemoryIndex, ModuleInfo, RelocationTarget, SignatureIndex, Symbol, SymbolRegistry, TableIndex,
+    TrapCode, Type,
 };
-use wasmer_vm::{MemoryStyle, TableStyle, VMOffsets};
+use wasmer_vm::{MemoryStyle, TableStyle, VMOffsets, EPOCH_GLOBAL_NAME};
 
 const FUNCTION_SECTION: &str = "__TEXT,wasmer_function";
 
@@ -196,6 +198,8 @@ impl FuncTranslator {
             locals: params_locals,
             ctx: CtxType::new(wasm_module, &func, &cache_builder, &*self.abi),
             unreachable_depth: 0,
+            unreachable_trap_code: None,
+            epoch_deadline: false,
             memory_styles,
             _table_styles,
             module: &module,
@@ -216,6 +220,8 @@ impl FuncTranslator {
         while fcg.state.has_control_frames() {
             let pos = reader.current_position() as u32;
             let op = reader.read_operator()?;
+            fcg.unreachable_trap_code = reader.trap_code();
+            fcg.epoch_deadline = reader.epoch_deadline();
             fcg.translate_operator(op, pos)?;
         }
 
@@ -1226,6 +1232,51 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         self.builder.position_at_end(continue_block);
     }
 
+    /// Branches to the return block of the function with the values on top of the stack.
+    fn translate_return(&mut self) -> Result<(), CompileError> {
+        let current_block = self
+            .builder
+            .get_insert_block()
+            .ok_or_else(|| CompileError::Codegen("not currently in a block".to_string()))?;
+
+        let frame = self.state.outermost_frame()?;
+        for phi in frame.phis().to_vec().iter().rev() {
+            let (arg, info) = self.state.pop1_extra()?;
+            let arg = self.apply_pending_canonicalization(arg, info);
+            phi.add_incoming(&[(&arg, current_block)]);
+        }
+        let frame = self.state.outermost_frame()?;
+        self.builder.build_unconditional_branch(*frame.br_dest());
+
+        self.state.reachable = false;
+        Ok(())
+    }
+
+    /// Returns the results of a call to a function with the same results as the current one.
+    ///
+    /// Unless the results are returned through memory, the call is marked as a tail call and
+    /// directly followed by a return, so that LLVM turns it into a jump.
+    fn translate_return_call(
+        &mut self,
+        call_site: CallSiteValue<'ctx>,
+        func_type: &FunctionType,
+    ) -> Result<(), CompileError> {
+        if self.abi.is_sret(func_type)? {
+            self.abi
+                .rets_from_call(&self.builder, self.intrinsics, call_site, func_type)
+                .iter()
+                .for_each(|ret| self.state.push1(*ret));
+            return self.translate_return();
+        }
+
+        call_site.set_tail_call(true);
+        let ret = call_site.try_as_basic_value().left();
+        self.builder
+            .build_return(ret.as_ref().map(|ret| ret as &dyn BasicValue));
+        self.state.reachable = false;
+        Ok(())
+    }
+
     fn finalize(&mut self, wasm_fn_type: &FunctionType) -> Result<(), CompileError> {
         let func_type = self.function.get_type();
 
@@ -1372,6 +1423,11 @@ pub struct LLVMFunctionCodeGenerator<'ctx, 'a> {
     locals: Vec<(BasicTypeEnum<'ctx>, PointerValue<'ctx>)>, // Contains params and locals
     ctx: CtxType<'ctx, 'a>,
     unreachable_depth: usize,
+    // Trap code of the next `Unreachable` operator, if a middleware set one.
+    unreachable_trap_code: Option<TrapCode>,
+    // Whether the next `Nop` operator checks the epoch deadline, if a
+    // middleware set it.
+    epoch_deadline: bool,
     memory_styles: &'a PrimaryMap<MemoryIndex, MemoryStyle>,
     _table_styles: &'a PrimaryMap<TableIndex, TableStyle>,
 
@@ -1829,23 +1885,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     }
                 }
             }
-            Operator::Return => {
-                let current_block = self
-                    .builder
-                    .get_insert_block()
-                    .ok_or_else(|| CompileError::Codegen("not currently in a block".to_string()))?;
-
-                let frame = self.state.outermost_frame()?;
-                for phi in frame.phis().to_vec().iter().rev() {
-                    let (arg, info) = self.state.pop1_extra()?;
-                    let arg = self.apply_pending_canonicalization(arg, info);
-                    phi.add_incoming(&[(&arg, current_block)]);
-                }
-                let frame = self.state.outermost_frame()?;
-                self.builder.build_unconditional_branch(*frame.br_dest());
-
-                self.state.reachable = false;
-            }
+            Operator::Return => self.translate_return()?,
 
             Operator::Unreachable => {
                 // Emit an unreachable instruction.
@@ -1882,11 +1922,16 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
-                self.builder.build_call(
-                    self.intrinsics.throw_trap,
-                    &[self.intrinsics.trap_unreachable.into()],
-                    "throw",
-                );
+                let trap_code = match self.unreachable_trap_code {
+                    Some(trap_code) => self
+                        .intrinsics
+                        .i32_ty
+                        .const_int(trap_code as _, false)
+                        .as_basic_value_enum(),
+                    None => self.intrinsics.trap_unreachable,
+                };
+                self.builder
+                    .build_call(self.intrinsics.throw_trap, &[trap_code.into()], "throw");
                 self.builder.build_unreachable();
 
                 self.state.reachable = false;
@@ -1897,7 +1942,15 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
              * https://github.com/sunfishcode/wasm-reference-manual/blob/master/WebAssembly.md#basic-instructions
              ***************************/
             Operator::Nop => {
-                // Do nothing.
+                if self.epoch_deadline {
+                    let deadline_fn_ptr = self.ctx.epoch_deadline(self.intrinsics);
+                    self.builder.build_indirect_call(
+                        self.intrinsics.epoch_deadline_ty,
+                        deadline_fn_ptr,
+                        &[vmctx.as_basic_value_enum().into()],
+                        "",
+                    );
+                }
             }
             Operator::Drop => {
                 self.state.pop1()?;
@@ -2084,6 +2137,17 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                             format!("global {}", global_index.as_u32()),
                             value.as_instruction_value().unwrap(),
                         );
+                        // The epoch is incremented by other threads, its
+                        // loads must not be hoisted out of loops.
+                        if self.wasm_module.exports.get(EPOCH_GLOBAL_NAME)
+                            == Some(&ExportIndex::Global(global_index))
+                        {
+                            value
+                                .as_instruction_value()
+                                .unwrap()
+                                .set_volatile(true)
+                                .unwrap();
+                        }
                         self.state.push1(value);
                     }
                 }
@@ -2159,7 +2223,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 };
                 self.state.push1_extra(res, info);
             }
-            Operator::Call { function_index } => {
+            Operator::Call { function_index } | Operator::ReturnCall { function_index } => {
                 let func_index = FunctionIndex::from_u32(function_index);
                 let sigindex = &self.wasm_module.functions[func_index];
                 let func_type = &self.wasm_module.signatures[*sigindex];
@@ -2276,15 +2340,26 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
-                self.abi
-                    .rets_from_call(&self.builder, self.intrinsics, call_site, func_type)
-                    .iter()
-                    .for_each(|ret| self.state.push1(*ret));
+                if matches!(
+                    op,
+                    Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. }
+                ) {
+                    self.translate_return_call(call_site, func_type)?;
+                } else {
+                    self.abi
+                        .rets_from_call(&self.builder, self.intrinsics, call_site, func_type)
+                        .iter()
+                        .for_each(|ret| self.state.push1(*ret));
+                }
             }
             Operator::CallIndirect {
                 type_index,
                 table_index,
                 table_byte: _,
+            }
+            | Operator::ReturnCallIndirect {
+                type_index,
+                table_index,
             } => {
                 let sigindex = SignatureIndex::from_u32(type_index);
                 let func_type = &self.wasm_module.signatures[sigindex];
@@ -2592,10 +2667,17 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
-                self.abi
-                    .rets_from_call(&self.builder, self.intrinsics, call_site, func_type)
-                    .iter()
-                    .for_each(|ret| self.state.push1(*ret));
+                if matches!(
+                    op,
+                    Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. }
+                ) {
+                    self.translate_return_call(call_site, func_type)?;
+                } else {
+                    self.abi
+                        .rets_from_call(&self.builder, self.intrinsics, call_site, func_type)
+                        .iter()
+                        .for_each(|ret| self.state.push1(*ret));
+                }
             }
 
             /***************************
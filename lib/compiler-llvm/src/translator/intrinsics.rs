@@ -242,6 +242,7 @@ pub struct Intrinsics<'ctx> {
     pub imported_memory_fill: FunctionValue<'ctx>,
     pub memory_size_ty: FunctionType<'ctx>,
     pub memory_grow_ty: FunctionType<'ctx>,
+    pub epoch_deadline_ty: FunctionType<'ctx>,
     pub memory_wait32: FunctionValue<'ctx>,
     pub memory_wait32_ty: FunctionType<'ctx>,
     pub imported_memory_wait32: FunctionValue<'ctx>,
@@ -275,6 +276,7 @@ pub struct Intrinsics<'ctx> {
     pub imported_memory32_wait64_ptr_ty: PointerType<'ctx>,
     pub memory32_notify_ptr_ty: PointerType<'ctx>,
     pub imported_memory32_notify_ptr_ty: PointerType<'ctx>,
+    pub epoch_deadline_ptr_ty: PointerType<'ctx>,
 
     // Pointer to the VM.
     pub ctx_ptr_ty: PointerType<'ctx>,
@@ -1011,6 +1013,7 @@ impl<'ctx> Intrinsics<'ctx> {
                 &[ctx_ptr_ty_basic_md, i32_ty_basic_md, i32_ty_basic_md],
                 false,
             ),
+            epoch_deadline_ty: void_ty.fn_type(&[ctx_ptr_ty_basic_md], false),
             data_drop: module.add_function(
                 "wasmer_vm_data_drop",
                 void_ty.fn_type(&[ctx_ptr_ty_basic_md, i32_ty_basic_md], false),
@@ -1245,6 +1248,9 @@ impl<'ctx> Intrinsics<'ctx> {
                     false,
                 )
                 .ptr_type(AddressSpace::default()),
+            epoch_deadline_ptr_ty: void_ty
+                .fn_type(&[ctx_ptr_ty_basic_md], false)
+                .ptr_type(AddressSpace::default()),
 
             ctx_ptr_ty,
         };
@@ -1311,6 +1317,7 @@ pub struct CtxType<'ctx, 'a> {
     cached_functions: HashMap<FunctionIndex, FunctionCache<'ctx>>,
     cached_memory_grow: HashMap<MemoryIndex, PointerValue<'ctx>>,
     cached_memory_size: HashMap<MemoryIndex, PointerValue<'ctx>>,
+    cached_epoch_deadline: Option<PointerValue<'ctx>>,
 
     offsets: VMOffsets,
 }
@@ -1336,6 +1343,7 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
             cached_functions: HashMap::new(),
             cached_memory_grow: HashMap::new(),
             cached_memory_size: HashMap::new(),
+            cached_epoch_deadline: None,
 
             // TODO: pointer width
             offsets: VMOffsets::new(8, wasm_module),
@@ -2035,6 +2043,36 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
         })
     }
 
+    pub fn epoch_deadline(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let (cached_epoch_deadline, offsets, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_epoch_deadline,
+            &self.offsets,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        *cached_epoch_deadline.get_or_insert_with(|| {
+            let offset =
+                offsets.vmctx_builtin_function(VMBuiltinFunctionIndex::get_epoch_deadline_index());
+            let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+            let deadline_fn_ptr_ptr =
+                unsafe { cache_builder.build_gep(intrinsics.i8_ty, *ctx_ptr_value, &[offset], "") };
+
+            let deadline_fn_ptr_ptr = cache_builder
+                .build_bitcast(
+                    deadline_fn_ptr_ptr,
+                    intrinsics
+                        .epoch_deadline_ptr_ty
+                        .ptr_type(AddressSpace::default()),
+                    "",
+                )
+                .into_pointer_value();
+
+            cache_builder
+                .build_load(intrinsics.epoch_deadline_ptr_ty, deadline_fn_ptr_ptr, "")
+                .into_pointer_value()
+        })
+    }
+
     pub fn get_offsets(&self) -> &VMOffsets {
         &self.offsets
     }
@@ -3,7 +3,7 @@ use crate::codegen_error;
 #[cfg(feature = "unwind")]
 use crate::dwarf::WriterRelocate;
 use crate::location::{Location, Reg};
-use crate::machine::{Label, Machine, MachineStackOffset, NATIVE_PAGE_SIZE};
+use crate::machine::{lower_v128_params, Label, Machine, MachineStackOffset, NATIVE_PAGE_SIZE};
 use crate::unwind::UnwindFrame;
 use crate::{common_decl::*, config::Singlepass};
 #[cfg(feature = "unwind")]
@@ -12,7 +12,7 @@ use smallvec::{smallvec, SmallVec};
 use std::cmp;
 use std::iter;
 use wasmer_compiler::wasmparser::{
-    BlockType as WpTypeOrFuncType, HeapType as WpHeapType, Operator, RefType as WpRefType,
+    BlockType as WpTypeOrFuncType, HeapType as WpHeapType, MemArg, Operator, RefType as WpRefType,
     ValType as WpType,
 };
 use wasmer_compiler::FunctionBodyData;
@@ -21,8 +21,8 @@ use wasmer_types::CompiledFunctionUnwindInfo;
 use wasmer_types::{
     entity::{EntityRef, PrimaryMap},
     CallingConvention, CompileError, FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex,
-    LocalMemoryIndex, MemoryIndex, MemoryStyle, ModuleInfo, Relocation, RelocationTarget,
-    SectionIndex, SignatureIndex, TableIndex, TableStyle, TrapCode, Type, VMBuiltinFunctionIndex,
+    MemoryIndex, MemoryStyle, ModuleInfo, Relocation, RelocationTarget, SectionIndex,
+    SignatureIndex, SimdOp, TableIndex, TableStyle, TrapCode, Type, VMBuiltinFunctionIndex,
     VMOffsets,
 };
 use wasmer_types::{CompiledFunction, CompiledFunctionFrameInfo, FunctionBody};
@@ -54,9 +54,17 @@ pub struct FuncGen<'a, M: Machine> {
     /// Types of local variables, including arguments.
     local_types: Vec<WpType>,
 
+    /// Index into `locals` of the first slot of each local variable, including arguments.
+    /// `V128` locals use two consecutive slots.
+    local_slots: Vec<usize>,
+
     /// Value stack.
     value_stack: Vec<Location<M::GPR, M::SIMD>>,
 
+    /// Value stack depths of the high halves of `V128` values.
+    /// A `V128` value is held as two 64-bit halves, the low half being pushed first.
+    v128_stack: Vec<usize>,
+
     /// Metadata about floating point values on the stack.
     fp_stack: Vec<FloatValue>,
 
@@ -77,6 +85,13 @@ pub struct FuncGen<'a, M: Machine> {
     /// Nesting level of unreachable code.
     unreachable_depth: usize,
 
+    /// Trap code of the next `Unreachable` operator, if a middleware set one.
+    unreachable_trap_code: Option<TrapCode>,
+
+    /// Whether the next `Nop` operator checks the epoch deadline, if a
+    /// middleware set it.
+    epoch_deadline: bool,
+
     /// Function state map. Not yet used in the reborn version but let's keep it.
     fsm: FunctionStateMap,
 
@@ -243,6 +258,169 @@ fn type_to_wp_type(ty: Type) -> WpType {
     }
 }
 
+/// Returns the `SimdOp` of a SIMD operator that does not access memory, along with its
+/// lane immediates.
+fn simd_operator(op: &Operator) -> Option<(SimdOp, SmallVec<[u8; 16]>)> {
+    macro_rules! simd_operators {
+        (lane: $($lane_op:ident)*; plain: $($op:ident)*) => {
+            match *op {
+                Operator::I8x16Shuffle { lanes } => {
+                    Some((SimdOp::I8x16Shuffle, SmallVec::from_slice(&lanes)))
+                }
+                $(Operator::$lane_op { lane } => Some((SimdOp::$lane_op, smallvec![lane])),)*
+                $(Operator::$op => Some((SimdOp::$op, smallvec![])),)*
+                _ => None,
+            }
+        };
+    }
+    simd_operators! {
+        lane:
+            I8x16ExtractLaneS I8x16ExtractLaneU I8x16ReplaceLane I16x8ExtractLaneS
+            I16x8ExtractLaneU I16x8ReplaceLane I32x4ExtractLane I32x4ReplaceLane I64x2ExtractLane
+            I64x2ReplaceLane F32x4ExtractLane F32x4ReplaceLane F64x2ExtractLane F64x2ReplaceLane;
+        plain:
+            I8x16Swizzle I8x16Splat I16x8Splat I32x4Splat I64x2Splat F32x4Splat F64x2Splat I8x16Eq
+            I8x16Ne I8x16LtS I8x16LtU I8x16GtS I8x16GtU I8x16LeS I8x16LeU I8x16GeS I8x16GeU I16x8Eq
+            I16x8Ne I16x8LtS I16x8LtU I16x8GtS I16x8GtU I16x8LeS I16x8LeU I16x8GeS I16x8GeU I32x4Eq
+            I32x4Ne I32x4LtS I32x4LtU I32x4GtS I32x4GtU I32x4LeS I32x4LeU I32x4GeS I32x4GeU F32x4Eq
+            F32x4Ne F32x4Lt F32x4Gt F32x4Le F32x4Ge F64x2Eq F64x2Ne F64x2Lt F64x2Gt F64x2Le F64x2Ge
+            V128Not V128And V128AndNot V128Or V128Xor V128Bitselect V128AnyTrue
+            F32x4DemoteF64x2Zero F64x2PromoteLowF32x4 I8x16Abs I8x16Neg I8x16Popcnt I8x16AllTrue
+            I8x16Bitmask I8x16NarrowI16x8S I8x16NarrowI16x8U F32x4Ceil F32x4Floor F32x4Trunc
+            F32x4Nearest I8x16Shl I8x16ShrS I8x16ShrU I8x16Add I8x16AddSatS I8x16AddSatU I8x16Sub
+            I8x16SubSatS I8x16SubSatU F64x2Ceil F64x2Floor I8x16MinS I8x16MinU I8x16MaxS I8x16MaxU
+            F64x2Trunc I8x16AvgrU I16x8ExtAddPairwiseI8x16S I16x8ExtAddPairwiseI8x16U
+            I32x4ExtAddPairwiseI16x8S I32x4ExtAddPairwiseI16x8U I16x8Abs I16x8Neg I16x8Q15MulrSatS
+            I16x8AllTrue I16x8Bitmask I16x8NarrowI32x4S I16x8NarrowI32x4U I16x8ExtendLowI8x16S
+            I16x8ExtendHighI8x16S I16x8ExtendLowI8x16U I16x8ExtendHighI8x16U I16x8Shl I16x8ShrS
+            I16x8ShrU I16x8Add I16x8AddSatS I16x8AddSatU I16x8Sub I16x8SubSatS I16x8SubSatU
+            F64x2Nearest I16x8Mul I16x8MinS I16x8MinU I16x8MaxS I16x8MaxU I16x8AvgrU
+            I16x8ExtMulLowI8x16S I16x8ExtMulHighI8x16S I16x8ExtMulLowI8x16U I16x8ExtMulHighI8x16U
+            I32x4Abs I32x4Neg I32x4AllTrue I32x4Bitmask I32x4ExtendLowI16x8S I32x4ExtendHighI16x8S
+            I32x4ExtendLowI16x8U I32x4ExtendHighI16x8U I32x4Shl I32x4ShrS I32x4ShrU I32x4Add
+            I32x4Sub I32x4Mul I32x4MinS I32x4MinU I32x4MaxS I32x4MaxU I32x4DotI16x8S
+            I32x4ExtMulLowI16x8S I32x4ExtMulHighI16x8S I32x4ExtMulLowI16x8U I32x4ExtMulHighI16x8U
+            I64x2Abs I64x2Neg I64x2AllTrue I64x2Bitmask I64x2ExtendLowI32x4S I64x2ExtendHighI32x4S
+            I64x2ExtendLowI32x4U I64x2ExtendHighI32x4U I64x2Shl I64x2ShrS I64x2ShrU I64x2Add
+            I64x2Sub I64x2Mul I64x2Eq I64x2Ne I64x2LtS I64x2GtS I64x2LeS I64x2GeS
+            I64x2ExtMulLowI32x4S I64x2ExtMulHighI32x4S I64x2ExtMulLowI32x4U I64x2ExtMulHighI32x4U
+            F32x4Abs F32x4Neg F32x4Sqrt F32x4Add F32x4Sub F32x4Mul F32x4Div F32x4Min F32x4Max
+            F32x4PMin F32x4PMax F64x2Abs F64x2Neg F64x2Sqrt F64x2Add F64x2Sub F64x2Mul F64x2Div
+            F64x2Min F64x2Max F64x2PMin F64x2PMax I32x4TruncSatF32x4S I32x4TruncSatF32x4U
+            F32x4ConvertI32x4S F32x4ConvertI32x4U I32x4TruncSatF64x2SZero I32x4TruncSatF64x2UZero
+            F64x2ConvertLowI32x4S F64x2ConvertLowI32x4U
+    }
+}
+
+/// Parameter and result types of a SIMD operation.
+fn simd_op_signature(op: SimdOp) -> (&'static [WpType], WpType) {
+    use SimdOp::*;
+    match op {
+        I8x16Splat | I16x8Splat | I32x4Splat => (&[WpType::I32], WpType::V128),
+        I64x2Splat => (&[WpType::I64], WpType::V128),
+        F32x4Splat => (&[WpType::F32], WpType::V128),
+        F64x2Splat => (&[WpType::F64], WpType::V128),
+        I8x16ExtractLaneS | I8x16ExtractLaneU | I16x8ExtractLaneS | I16x8ExtractLaneU
+        | I32x4ExtractLane => (&[WpType::V128], WpType::I32),
+        I64x2ExtractLane => (&[WpType::V128], WpType::I64),
+        F32x4ExtractLane => (&[WpType::V128], WpType::F32),
+        F64x2ExtractLane => (&[WpType::V128], WpType::F64),
+        I8x16ReplaceLane | I16x8ReplaceLane | I32x4ReplaceLane | I8x16Shl | I8x16ShrS
+        | I8x16ShrU | I16x8Shl | I16x8ShrS | I16x8ShrU | I32x4Shl | I32x4ShrS | I32x4ShrU
+        | I64x2Shl | I64x2ShrS | I64x2ShrU => (&[WpType::V128, WpType::I32], WpType::V128),
+        I64x2ReplaceLane => (&[WpType::V128, WpType::I64], WpType::V128),
+        F32x4ReplaceLane => (&[WpType::V128, WpType::F32], WpType::V128),
+        F64x2ReplaceLane => (&[WpType::V128, WpType::F64], WpType::V128),
+        V128AnyTrue | I8x16AllTrue | I16x8AllTrue | I32x4AllTrue | I64x2AllTrue | I8x16Bitmask
+        | I16x8Bitmask | I32x4Bitmask | I64x2Bitmask => (&[WpType::V128], WpType::I32),
+        V128Bitselect => (&[WpType::V128, WpType::V128, WpType::V128], WpType::V128),
+        V128Not
+        | I8x16Abs
+        | I8x16Neg
+        | I8x16Popcnt
+        | I16x8Abs
+        | I16x8Neg
+        | I32x4Abs
+        | I32x4Neg
+        | I64x2Abs
+        | I64x2Neg
+        | F32x4Abs
+        | F32x4Neg
+        | F32x4Sqrt
+        | F32x4Ceil
+        | F32x4Floor
+        | F32x4Trunc
+        | F32x4Nearest
+        | F64x2Abs
+        | F64x2Neg
+        | F64x2Sqrt
+        | F64x2Ceil
+        | F64x2Floor
+        | F64x2Trunc
+        | F64x2Nearest
+        | I16x8ExtendLowI8x16S
+        | I16x8ExtendHighI8x16S
+        | I16x8ExtendLowI8x16U
+        | I16x8ExtendHighI8x16U
+        | I32x4ExtendLowI16x8S
+        | I32x4ExtendHighI16x8S
+        | I32x4ExtendLowI16x8U
+        | I32x4ExtendHighI16x8U
+        | I64x2ExtendLowI32x4S
+        | I64x2ExtendHighI32x4S
+        | I64x2ExtendLowI32x4U
+        | I64x2ExtendHighI32x4U
+        | I16x8ExtAddPairwiseI8x16S
+        | I16x8ExtAddPairwiseI8x16U
+        | I32x4ExtAddPairwiseI16x8S
+        | I32x4ExtAddPairwiseI16x8U
+        | I32x4TruncSatF32x4S
+        | I32x4TruncSatF32x4U
+        | I32x4TruncSatF64x2SZero
+        | I32x4TruncSatF64x2UZero
+        | F32x4ConvertI32x4S
+        | F32x4ConvertI32x4U
+        | F64x2ConvertLowI32x4S
+        | F64x2ConvertLowI32x4U
+        | F32x4DemoteF64x2Zero
+        | F64x2PromoteLowF32x4 => (&[WpType::V128], WpType::V128),
+        _ => (&[WpType::V128, WpType::V128], WpType::V128),
+    }
+}
+
+/// Mask applied to the shift count of a SIMD shift operation, if `op` is one.
+fn simd_shift_mask(op: SimdOp) -> Option<u32> {
+    use SimdOp::*;
+    match op {
+        I8x16Shl | I8x16ShrS | I8x16ShrU => Some(7),
+        I16x8Shl | I16x8ShrS | I16x8ShrU => Some(15),
+        I32x4Shl | I32x4ShrS | I32x4ShrU => Some(31),
+        I64x2Shl | I64x2ShrS | I64x2ShrU => Some(63),
+        _ => None,
+    }
+}
+
+/// Number of machine slots used by a local variable of the given type.
+fn local_slot_count(ty: WpType) -> usize {
+    match ty {
+        WpType::V128 => 2,
+        _ => 1,
+    }
+}
+
+/// A scalar load or store method of `Machine`.
+type MemoryAccess<M> = fn(
+    &mut M,
+    Location<<M as Machine>::GPR, <M as Machine>::SIMD>,
+    &MemArg,
+    Location<<M as Machine>::GPR, <M as Machine>::SIMD>,
+    bool,
+    bool,
+    i32,
+    Label,
+    Label,
+) -> Result<(), CompileError>;
+
 /// Abstraction for a 2-input, 1-output operator. Can be an integer/floating-point
 /// binop/cmpop.
 struct I2O1<R: Reg, S: Reg> {
@@ -560,10 +738,8 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         //
         // `rep stosq` writes data from low address to high address and may skip the stack guard page.
         // so here we probe it explicitly when needed.
-        for i in (sig.params().len()..n)
-            .step_by(NATIVE_PAGE_SIZE / 8)
-            .skip(1)
-        {
+        let params = lower_v128_params(sig.params());
+        for i in (params.len()..n).step_by(NATIVE_PAGE_SIZE / 8).skip(1) {
             self.machine.zero_location(Size::S64, locations[i])?;
         }
 
@@ -618,7 +794,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         // Locals are allocated on the stack from higher address to lower address,
         // so we won't skip the stack guard page here.
         let mut stack_offset: usize = 0;
-        for (i, param) in sig.params().iter().enumerate() {
+        for (i, (param, _)) in params.iter().enumerate() {
             let sz = match *param {
                 Type::I32 | Type::F32 => Size::S32,
                 Type::I64 | Type::F64 => Size::S64,
@@ -646,7 +822,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         // Initialize all normal locals to zero.
         let mut init_stack_loc_cnt = 0;
         let mut last_stack_loc = Location::Memory(self.machine.local_pointer(), i32::MAX);
-        for location in locations.iter().take(n).skip(sig.params().len()) {
+        for location in locations.iter().take(n).skip(params.len()) {
             match location {
                 Location::Memory(_, _) => {
                     init_stack_loc_cnt += 1;
@@ -727,6 +903,426 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         Ok(I2O1 { loc_a, loc_b, ret })
     }
 
+    /// Pops a `V128` value, returning the released locations of its (low, high) halves.
+    #[allow(clippy::type_complexity)]
+    fn pop_v128_released(
+        &mut self,
+    ) -> Result<(Location<M::GPR, M::SIMD>, Location<M::GPR, M::SIMD>), CompileError> {
+        self.v128_stack.pop();
+        let hi = self.pop_value_released()?;
+        let lo = self.pop_value_released()?;
+        Ok((lo, hi))
+    }
+
+    /// Acquires locations for a new `V128` value and pushes its halves to the value stack.
+    #[allow(clippy::type_complexity)]
+    fn push_v128(
+        &mut self,
+    ) -> Result<(Location<M::GPR, M::SIMD>, Location<M::GPR, M::SIMD>), CompileError> {
+        let lo = self.acquire_locations(
+            &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+            false,
+        )?[0];
+        self.value_stack.push(lo);
+        let hi = self.acquire_locations(
+            &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+            false,
+        )?[0];
+        self.value_stack.push(hi);
+        self.v128_stack.push(self.value_stack.len() - 1);
+        Ok((lo, hi))
+    }
+
+    /// Moves the value of the given type on top of the value stack to the return registers.
+    fn emit_return_value(&mut self, ty: WpType) -> Result<(), CompileError> {
+        if ty == WpType::V128 {
+            let len = self.value_stack.len();
+            self.machine.emit_relaxed_mov(
+                Size::S64,
+                self.value_stack[len - 2],
+                Location::GPR(self.machine.get_gpr_for_ret()),
+            )?;
+            return self.machine.emit_relaxed_mov(
+                Size::S64,
+                self.value_stack[len - 1],
+                Location::GPR(self.machine.get_gpr_for_ret_hi()),
+            );
+        }
+        let loc = *self.value_stack.last().unwrap();
+        let canonicalize = if ty.is_float() {
+            let fp = self.fp_stack.peek1()?;
+            self.machine.arch_supports_canonicalize_nan()
+                && self.config.enable_nan_canonicalization
+                && fp.canonicalization.is_some()
+        } else {
+            false
+        };
+        self.machine
+            .emit_function_return_value(ty, canonicalize, loc)
+    }
+
+    /// Pushes a `V128` value returned in the return registers.
+    fn push_v128_return_value(&mut self) -> Result<(), CompileError> {
+        let (lo, hi) = self.push_v128()?;
+        self.machine
+            .move_location(Size::S64, Location::GPR(self.machine.get_gpr_for_ret()), lo)?;
+        self.machine.move_location(
+            Size::S64,
+            Location::GPR(self.machine.get_gpr_for_ret_hi()),
+            hi,
+        )
+    }
+
+    /// Is the value on top of the value stack a `V128`?
+    fn top_is_v128(&self) -> bool {
+        !self.value_stack.is_empty()
+            && self.v128_stack.last() == Some(&(self.value_stack.len() - 1))
+    }
+
+    /// Forgets about the `V128` values above the given value stack depth.
+    fn truncate_v128_stack(&mut self, depth: usize) {
+        while matches!(self.v128_stack.last(), Some(&x) if x >= depth) {
+            self.v128_stack.pop();
+        }
+    }
+
+    /// Moves the halves of a `V128` value. The destination may overlap the source.
+    #[allow(clippy::type_complexity)]
+    fn move_v128(
+        &mut self,
+        src: (Location<M::GPR, M::SIMD>, Location<M::GPR, M::SIMD>),
+        dst: (Location<M::GPR, M::SIMD>, Location<M::GPR, M::SIMD>),
+    ) -> Result<(), CompileError> {
+        if dst.0 != src.1 {
+            self.machine.emit_relaxed_mov(Size::S64, src.0, dst.0)?;
+            self.machine.emit_relaxed_mov(Size::S64, src.1, dst.1)
+        } else if dst.1 != src.0 {
+            self.machine.emit_relaxed_mov(Size::S64, src.1, dst.1)?;
+            self.machine.emit_relaxed_mov(Size::S64, src.0, dst.0)
+        } else {
+            let tmp = self.machine.acquire_temp_gpr().unwrap();
+            self.machine
+                .emit_relaxed_mov(Size::S64, src.0, Location::GPR(tmp))?;
+            self.machine.emit_relaxed_mov(Size::S64, src.1, dst.1)?;
+            self.machine
+                .emit_relaxed_mov(Size::S64, Location::GPR(tmp), dst.0)?;
+            self.machine.release_gpr(tmp);
+            Ok(())
+        }
+    }
+
+    /// Moves a scalar value to the low bits of a SIMD register.
+    fn move_scalar_to_simd(
+        &mut self,
+        loc: Location<M::GPR, M::SIMD>,
+        dst: M::SIMD,
+    ) -> Result<(), CompileError> {
+        match loc {
+            Location::Imm8(_) | Location::Imm32(_) | Location::Imm64(_) => {
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                self.machine
+                    .move_location(Size::S64, loc, Location::GPR(tmp))?;
+                self.machine
+                    .move_location(Size::S64, Location::GPR(tmp), Location::SIMD(dst))?;
+                self.machine.release_gpr(tmp);
+                Ok(())
+            }
+            _ => self
+                .machine
+                .move_location(Size::S64, loc, Location::SIMD(dst)),
+        }
+    }
+
+    /// Pushes the result of a SIMD operation, held in (the low bits of) a SIMD register.
+    fn push_simd_result(&mut self, ty: WpType, src: M::SIMD) -> Result<(), CompileError> {
+        if ty == WpType::V128 {
+            let (lo, hi) = self.push_v128()?;
+            return self.machine.v128_to_halves(src, lo, hi);
+        }
+        let ret = self.acquire_locations(
+            &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
+            false,
+        )?[0];
+        self.value_stack.push(ret);
+        if ty.is_float() {
+            self.fp_stack
+                .push(FloatValue::new(self.value_stack.len() - 1));
+        }
+        let size = match ty {
+            WpType::I32 | WpType::F32 => Size::S32,
+            _ => Size::S64,
+        };
+        self.machine.move_location(size, Location::SIMD(src), ret)
+    }
+
+    /// Emits a SIMD operation that does not access memory.
+    ///
+    /// The operands are loaded in temporary SIMD registers. Operations that the machine can't
+    /// lower natively are computed by the `wasmer_vm_simd_op` builtin function, on a copy of
+    /// the operands spilled to a scratch area of the stack.
+    fn emit_simd_operator(&mut self, op: SimdOp, lanes: &[u8]) -> Result<(), CompileError> {
+        let (params, result) = simd_op_signature(op);
+
+        let mut operands: SmallVec<[_; 3]> = smallvec![];
+        for ty in params.iter().rev() {
+            if *ty == WpType::V128 {
+                let (lo, hi) = self.pop_v128_released()?;
+                operands.push((lo, Some(hi)));
+                continue;
+            }
+            let loc = self.pop_value_released()?;
+            if ty.is_float() {
+                let fp = self.fp_stack.pop1()?;
+                if self.machine.arch_supports_canonicalize_nan()
+                    && self.config.enable_nan_canonicalization
+                {
+                    if let Some(cncl) = fp.canonicalization {
+                        self.machine.canonicalize_nan(cncl.to_size(), loc, loc)?;
+                    }
+                }
+            }
+            operands.push((loc, None));
+        }
+        operands.reverse();
+
+        let regs: SmallVec<[M::SIMD; 3]> = (0..3)
+            .map(|_| self.machine.acquire_temp_simd().unwrap())
+            .collect();
+        for (i, (loc, hi)) in operands.iter().enumerate() {
+            match (hi, simd_shift_mask(op)) {
+                (Some(hi), _) => self.machine.v128_from_halves(*loc, *hi, regs[i])?,
+                (None, Some(mask)) => {
+                    let tmp = self.machine.acquire_temp_gpr().unwrap();
+                    self.machine
+                        .move_location(Size::S32, *loc, Location::GPR(tmp))?;
+                    self.machine.location_and(
+                        Size::S32,
+                        Location::Imm32(mask),
+                        Location::GPR(tmp),
+                        false,
+                    )?;
+                    self.machine.move_location(
+                        Size::S64,
+                        Location::GPR(tmp),
+                        Location::SIMD(regs[i]),
+                    )?;
+                    self.machine.release_gpr(tmp);
+                }
+                (None, None) => self.move_scalar_to_simd(*loc, regs[i])?,
+            }
+        }
+
+        if self.machine.simd_op_supported(op) {
+            self.machine.emit_simd_op(op, lanes, &regs)?;
+        } else {
+            // Spill the operands and the lane immediates to a scratch area.
+            self.stack_offset.0 += 48;
+            let scratch_size = self.machine.round_stack_adjust(48);
+            self.machine.adjust_stack(scratch_size as u32)?;
+            for _ in 0..6 {
+                self.state.stack_values.push(MachineValue::Undefined);
+            }
+            let (local_pointer, base) =
+                (self.machine.local_pointer(), -(self.stack_offset.0 as i32));
+            let scratch = |offset: usize| Location::Memory(local_pointer, base + offset as i32);
+            for i in 0..operands.len() {
+                let (lo, hi) = (scratch(i * 16), scratch(i * 16 + 8));
+                self.machine.v128_to_halves(regs[i], lo, hi)?;
+            }
+            if !lanes.is_empty() {
+                let mut bytes = [0u8; 16];
+                bytes[..lanes.len()].copy_from_slice(lanes);
+                for (i, half) in bytes.chunks(8).enumerate() {
+                    let value = u64::from_le_bytes(half.try_into().unwrap());
+                    let dst = scratch(32 + i * 8);
+                    self.machine
+                        .emit_relaxed_mov(Size::S64, Location::Imm64(value), dst)?;
+                }
+            }
+            for reg in regs.iter().rev() {
+                self.machine.release_simd(*reg);
+            }
+
+            self.machine.move_location(
+                Size::S64,
+                Location::Memory(
+                    self.machine.get_vmctx_reg(),
+                    self.vmoffsets
+                        .vmctx_builtin_function(VMBuiltinFunctionIndex::get_simd_op_index())
+                        as i32,
+                ),
+                Location::GPR(self.machine.get_grp_for_call()),
+            )?;
+            let operands_loc = scratch(0);
+            let calling_convention = self.calling_convention;
+            self.emit_call_native(
+                |this| {
+                    this.machine.location_address(
+                        Size::S64,
+                        operands_loc,
+                        this.machine
+                            .get_simple_param_location(2, calling_convention),
+                    )?;
+                    this.machine
+                        .emit_call_register(this.machine.get_grp_for_call())
+                },
+                // [vmctx, op, operands]
+                iter::once(Location::Imm32(op as u32)),
+                iter::once(WpType::I32),
+            )?;
+
+            // The result is written over the first operand.
+            for reg in regs.iter() {
+                self.machine.reserve_simd(*reg);
+            }
+            let (lo, hi) = (scratch(0), scratch(8));
+            self.machine.v128_from_halves(lo, hi, regs[0])?;
+            self.stack_offset.0 -= 48;
+            self.machine.restore_stack(scratch_size as u32)?;
+            for _ in 0..6 {
+                self.state.stack_values.pop().unwrap();
+            }
+        }
+
+        self.push_simd_result(result, regs[0])?;
+        for reg in regs.iter().rev() {
+            self.machine.release_simd(*reg);
+        }
+        Ok(())
+    }
+
+    /// Emits a scalar memory access, with the given `Machine` load (`a` being the address and
+    /// `b` the destination) or store (`a` being the value and `b` the address) method.
+    fn emit_memory_access(
+        &mut self,
+        access: MemoryAccess<M>,
+        a: Location<M::GPR, M::SIMD>,
+        memarg: &MemArg,
+        b: Location<M::GPR, M::SIMD>,
+    ) -> Result<(), CompileError> {
+        self.op_memory(
+            memarg.memory,
+            |this, need_check, imported_memories, offset, heap_access_oob, unaligned_atomic| {
+                access(
+                    &mut this.machine,
+                    a,
+                    memarg,
+                    b,
+                    need_check,
+                    imported_memories,
+                    offset,
+                    heap_access_oob,
+                    unaligned_atomic,
+                )
+            },
+        )
+    }
+
+    /// Returns the memory immediate of the high half of a `V128` memory access.
+    ///
+    /// If the offset of the high half overflows, the access is always out of bounds: a jump
+    /// to the trap is emitted instead and `None` is returned.
+    fn v128_high_memarg(&mut self, memarg: &MemArg) -> Result<Option<MemArg>, CompileError> {
+        if memarg.offset + 8 > u32::MAX as u64 {
+            self.machine
+                .jmp_unconditionnal(self.special_labels.heap_access_oob)?;
+            return Ok(None);
+        }
+        Ok(Some(MemArg {
+            offset: memarg.offset + 8,
+            ..*memarg
+        }))
+    }
+
+    /// Loads the 64 low bits of a `V128` value, the high bits being zeroed, and applies
+    /// `op` on the result if any.
+    fn emit_v128_load_low(
+        &mut self,
+        access: MemoryAccess<M>,
+        memarg: &MemArg,
+        op: Option<SimdOp>,
+    ) -> Result<(), CompileError> {
+        let target = self.pop_value_released()?;
+        let (lo, hi) = self.push_v128()?;
+        self.emit_memory_access(access, target, memarg, lo)?;
+        self.machine.zero_location(Size::S64, hi)?;
+        match op {
+            Some(op) => self.emit_simd_operator(op, &[]),
+            None => Ok(()),
+        }
+    }
+
+    /// Loads a scalar and splats it with `op`.
+    fn emit_v128_load_splat(
+        &mut self,
+        access: MemoryAccess<M>,
+        memarg: &MemArg,
+        ty: WpType,
+        op: SimdOp,
+    ) -> Result<(), CompileError> {
+        let target = self.pop_value_released()?;
+        let ret = self.acquire_locations(
+            &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
+            false,
+        )?[0];
+        self.value_stack.push(ret);
+        self.emit_memory_access(access, target, memarg, ret)?;
+        self.emit_simd_operator(op, &[])
+    }
+
+    /// Loads a scalar in a lane of a `V128` value, replaced with `op`.
+    fn emit_v128_load_lane(
+        &mut self,
+        access: MemoryAccess<M>,
+        memarg: &MemArg,
+        lane: u8,
+        op: SimdOp,
+    ) -> Result<(), CompileError> {
+        let (lo, hi) = self.pop_v128_released()?;
+        let target = self.pop_value_released()?;
+        let regs: SmallVec<[M::SIMD; 3]> = (0..3)
+            .map(|_| self.machine.acquire_temp_simd().unwrap())
+            .collect();
+        self.machine.v128_from_halves(lo, hi, regs[0])?;
+        let value = self.machine.acquire_temp_gpr().unwrap();
+        self.emit_memory_access(access, target, memarg, Location::GPR(value))?;
+        self.machine
+            .move_location(Size::S64, Location::GPR(value), Location::SIMD(regs[1]))?;
+        self.machine.release_gpr(value);
+        self.machine.emit_simd_op(op, &[lane], &regs)?;
+        self.push_simd_result(WpType::V128, regs[0])?;
+        for reg in regs.iter().rev() {
+            self.machine.release_simd(*reg);
+        }
+        Ok(())
+    }
+
+    /// Stores a lane of a `V128` value, extracted with `op`.
+    fn emit_v128_store_lane(
+        &mut self,
+        access: MemoryAccess<M>,
+        memarg: &MemArg,
+        lane: u8,
+        op: SimdOp,
+    ) -> Result<(), CompileError> {
+        let (lo, hi) = self.pop_v128_released()?;
+        let target = self.pop_value_released()?;
+        let regs: SmallVec<[M::SIMD; 3]> = (0..3)
+            .map(|_| self.machine.acquire_temp_simd().unwrap())
+            .collect();
+        self.machine.v128_from_halves(lo, hi, regs[0])?;
+        self.machine.emit_simd_op(op, &[lane], &regs)?;
+        let value = self.machine.acquire_temp_gpr().unwrap();
+        self.machine
+            .move_location(Size::S64, Location::SIMD(regs[0]), Location::GPR(value))?;
+        for reg in regs.iter().rev() {
+            self.machine.release_simd(*reg);
+        }
+        self.emit_memory_access(access, Location::GPR(value), memarg, target)?;
+        self.machine.release_gpr(value);
+        Ok(())
+    }
+
     fn mark_trappable(&mut self) {
         let state_diff_id = self.get_state_diff();
         let offset = self.machine.assembler_get_offset().0;
@@ -999,6 +1595,187 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         Ok(())
     }
 
+    /// Number of bytes of arguments passed on the stack to a function of the given signature.
+    fn stack_arguments_size(&self, sig: &FunctionType) -> usize {
+        let mut stack_offset: usize = 0;
+        for (i, (ty, _)) in lower_v128_params(sig.params()).into_iter().enumerate() {
+            let size = match ty {
+                Type::F32 | Type::I32 => Size::S32,
+                _ => Size::S64,
+            };
+            self.machine.get_param_location(
+                1 + i,
+                size,
+                &mut stack_offset,
+                self.calling_convention,
+            );
+        }
+        stack_offset
+    }
+
+    /// Checks that a function of the given signature can be tail called from the current
+    /// function.
+    ///
+    /// The callee reuses the stack arguments area of the current function, so it
+    /// must not need more room than it: that area belongs to the caller of the current
+    /// function, which can't be asked to grow it.
+    fn check_tail_call(&self, sig: &FunctionType) -> Result<(), CompileError> {
+        let needed = self.stack_arguments_size(sig);
+        let available = self.stack_arguments_size(&self.signature);
+        if needed > available {
+            return Err(CompileError::UnsupportedFeature(format!(
+                "tail call to a function taking {} bytes of stack arguments from one taking {}",
+                needed, available
+            )));
+        }
+        Ok(())
+    }
+
+    /// Emits a Native ABI tail call sequence.
+    ///
+    /// The parameters are moved to where the caller of the current function placed its own
+    /// ones, and the frame of the current function is torn down. `cb` must emit the epilog
+    /// and the jump to the callee.
+    ///
+    /// The caller MUST NOT hold any temporary registers allocated by `acquire_temp_gpr` when calling
+    /// this function.
+    fn emit_tail_call_native<
+        I: Iterator<Item = Location<M::GPR, M::SIMD>>,
+        J: Iterator<Item = WpType>,
+        F: FnOnce(&mut Self) -> Result<(), CompileError>,
+    >(
+        &mut self,
+        cb: F,
+        vmctx: Location<M::GPR, M::SIMD>,
+        params: I,
+        params_type: J,
+    ) -> Result<(), CompileError> {
+        let params: Vec<_> = params.collect();
+        let params_size: Vec<_> = params_type
+            .map(|x| match x {
+                WpType::F32 | WpType::I32 => Size::S32,
+                WpType::V128 => unimplemented!(),
+                _ => Size::S64,
+            })
+            .collect();
+
+        // mark the GPR used for Call as used
+        self.machine
+            .reserve_unused_temp_gpr(self.machine.get_grp_for_call());
+
+        let calling_convention = self.calling_convention;
+
+        // The parameters may live where the arguments of the callee go: copy them
+        // below the value stack first.
+        let base = self.machine.round_stack_adjust(self.get_stack_offset());
+        let staging_size = self.machine.round_stack_adjust(params.len() * 8);
+        if staging_size > 0 {
+            self.machine.adjust_stack(staging_size as u32)?;
+        }
+        let mut staged = vec![];
+        for (i, param) in params.iter().enumerate() {
+            let loc = self.machine.local_on_stack((base + (i + 1) * 8) as i32);
+            self.machine
+                .move_location_for_native(Size::S64, *param, loc)?;
+            staged.push(loc);
+        }
+
+        // Move the stack arguments first, as moving them may need temporary registers.
+        let mut stack_offset: usize = 0;
+        let mut call_movs = vec![];
+        for (i, loc) in staged.iter().enumerate() {
+            match self.machine.get_call_param_location(
+                1 + i,
+                params_size[i],
+                &mut stack_offset,
+                calling_convention,
+            ) {
+                Location::GPR(x) => call_movs.push((*loc, x)),
+                dest @ Location::Memory(_, _) => {
+                    self.machine
+                        .move_location_for_native(params_size[i], *loc, dest)?;
+                }
+                _ => {
+                    return Err(CompileError::Codegen(
+                        "emit_tail_call_native loc: unreachable code".to_owned(),
+                    ))
+                }
+            }
+        }
+        for (loc, gpr) in call_movs {
+            self.machine
+                .move_location(Size::S64, loc, Location::GPR(gpr))?;
+        }
+
+        // Put vmctx as the first parameter.
+        self.machine.move_location(
+            Size::S64,
+            vmctx,
+            self.machine
+                .get_simple_param_location(0, calling_convention),
+        )?; // vmctx
+
+        self.finalize_locals(calling_convention)?;
+        cb(self)?;
+
+        // release the GPR used for call
+        self.machine.release_gpr(self.machine.get_grp_for_call());
+        Ok(())
+    }
+
+    /// Pushes the value returned by a call of a function with the given results.
+    fn push_call_return_value(&mut self, return_types: &[WpType]) -> Result<(), CompileError> {
+        if return_types.first() == Some(&WpType::V128) {
+            self.push_v128_return_value()?;
+        } else if !return_types.is_empty() {
+            let ret = self.acquire_locations(
+                &[(
+                    return_types[0],
+                    MachineValue::WasmStack(self.value_stack.len()),
+                )],
+                false,
+            )?[0];
+            self.value_stack.push(ret);
+            if return_types[0].is_float() {
+                self.machine.move_location(
+                    Size::S64,
+                    Location::SIMD(self.machine.get_simd_for_ret()),
+                    ret,
+                )?;
+                self.fp_stack
+                    .push(FloatValue::new(self.value_stack.len() - 1));
+            } else {
+                self.machine.move_location(
+                    Size::S64,
+                    Location::GPR(self.machine.get_gpr_for_ret()),
+                    ret,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns from the function with the value on top of the value stack, if any.
+    fn emit_return(&mut self) -> Result<(), CompileError> {
+        let frame = &self.control_stack[0];
+        if !frame.returns.is_empty() {
+            if frame.returns.len() != 1 {
+                return Err(CompileError::Codegen(
+                    "Return: incorrect frame.returns".to_owned(),
+                ));
+            }
+            let first_return = frame.returns[0];
+            self.emit_return_value(first_return)?;
+        }
+        let frame = &self.control_stack[0];
+        let frame_depth = frame.value_stack_depth;
+        let label = frame.label;
+        self.release_locations_keep_state(frame_depth)?;
+        self.machine.jmp_unconditionnal(label)?;
+        self.unreachable_depth = 1;
+        Ok(())
+    }
+
     /// Emits a Native ABI call sequence, specialized for labels as the call target.
     fn _emit_call_native_label<
         I: Iterator<Item = Location<M::GPR, M::SIMD>>,
@@ -1022,24 +1799,30 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         F: FnOnce(&mut Self, bool, bool, i32, Label, Label) -> Result<(), CompileError>,
     >(
         &mut self,
+        memory: u32,
         cb: F,
     ) -> Result<(), CompileError> {
-        let need_check = match self.memory_styles[MemoryIndex::new(0)] {
+        let memory_index = MemoryIndex::from_u32(memory);
+        let need_check = match self.memory_styles[memory_index] {
             MemoryStyle::Static { .. } => false,
             MemoryStyle::Dynamic { .. } => true,
         };
 
-        let offset = if self.module.num_imported_memories != 0 {
-            self.vmoffsets
-                .vmctx_vmmemory_import_definition(MemoryIndex::new(0))
-        } else {
-            self.vmoffsets
-                .vmctx_vmmemory_definition(LocalMemoryIndex::new(0))
+        let (offset, imported) = match self.module.local_memory_index(memory_index) {
+            Some(local_memory_index) => (
+                self.vmoffsets.vmctx_vmmemory_definition(local_memory_index),
+                false,
+            ),
+            None => (
+                self.vmoffsets
+                    .vmctx_vmmemory_import_definition(memory_index),
+                true,
+            ),
         };
         cb(
             self,
             need_check,
-            self.module.num_imported_memories != 0,
+            imported,
             offset as i32,
             self.special_labels.heap_access_oob,
             self.special_labels.unaligned_atomic,
@@ -1065,7 +1848,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
 
         // Initialize locals.
         self.locals = self.init_locals(
-            self.local_types.len(),
+            self.local_types
+                .iter()
+                .map(|&ty| local_slot_count(ty))
+                .sum(),
             self.signature.clone(),
             self.calling_convention,
         )?;
@@ -1134,6 +1920,15 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             .map(|&x| type_to_wp_type(x))
             .collect();
         local_types.extend_from_slice(local_types_excluding_arguments);
+        let local_slots: Vec<usize> = local_types
+            .iter()
+            .scan(0, |slot, &ty| {
+                let first = *slot;
+                *slot += local_slot_count(ty);
+                Some(first)
+            })
+            .collect();
+        let num_local_slots: usize = local_types.iter().map(|&ty| local_slot_count(ty)).sum();
 
         let mut machine = machine;
         let special_labels = SpecialLabelSet {
@@ -1150,7 +1945,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             machine.new_machine_state(),
             local_func_index.index() as usize,
             32,
-            (0..local_types.len())
+            (0..num_local_slots)
                 .map(|_| WasmAbstractValue::Runtime)
                 .collect(),
         );
@@ -1164,7 +1959,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             signature,
             locals: vec![], // initialization deferred to emit_head
             local_types,
+            local_slots,
             value_stack: vec![],
+            v128_stack: vec![],
             fp_stack: vec![],
             control_stack: vec![],
             stack_offset: MachineStackOffset(0),
@@ -1173,6 +1970,8 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             track_state: true,
             machine,
             unreachable_depth: 0,
+            unreachable_trap_code: None,
+            epoch_deadline: false,
             fsm,
             relocations: vec![],
             special_labels,
@@ -1186,6 +1985,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         !self.control_stack.is_empty()
     }
 
+    /// Sets the trap code of the next operator if it is an `Unreachable`.
+    pub fn set_unreachable_trap_code(&mut self, trap_code: Option<TrapCode>) {
+        self.unreachable_trap_code = trap_code;
+    }
+
+    /// Sets whether the next operator, if it is a `Nop`, calls the epoch
+    /// deadline builtin.
+    pub fn set_epoch_deadline(&mut self, epoch_deadline: bool) {
+        self.epoch_deadline = epoch_deadline;
+    }
+
     pub fn feed_operator(&mut self, op: Operator) -> Result<(), CompileError> {
         assert!(self.fp_stack.len() <= self.value_stack.len());
 
@@ -1231,11 +2041,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 if ty.is_float() {
                     self.fp_stack.push(FloatValue::new(self.value_stack.len()));
                 }
-                let loc = self.acquire_locations(
-                    &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
-                    false,
-                )?[0];
-                self.value_stack.push(loc);
+                let (loc, hi) = if ty == WpType::V128 {
+                    let (lo, hi) = self.push_v128()?;
+                    (lo, Some(hi))
+                } else {
+                    let loc = self.acquire_locations(
+                        &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
+                        false,
+                    )?[0];
+                    self.value_stack.push(loc);
+                    (loc, None)
+                };
 
                 let tmp = self.machine.acquire_temp_gpr().unwrap();
 
@@ -1263,6 +2079,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 };
 
                 self.machine.emit_relaxed_mov(Size::S64, src, loc)?;
+                if let Some(hi) = hi {
+                    self.machine
+                        .emit_relaxed_mov(Size::S64, Location::Memory(tmp, 8), hi)?;
+                }
 
                 self.machine.release_gpr(tmp);
             }
@@ -1292,6 +2112,14 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     Location::Memory(tmp, 0)
                 };
                 let ty = type_to_wp_type(self.module.globals[global_index].ty);
+                if ty == WpType::V128 {
+                    let (lo, hi) = self.pop_v128_released()?;
+                    self.machine.emit_relaxed_mov(Size::S64, lo, dst)?;
+                    self.machine
+                        .emit_relaxed_mov(Size::S64, hi, Location::Memory(tmp, 8))?;
+                    self.machine.release_gpr(tmp);
+                    return Ok(());
+                }
                 let loc = self.pop_value_released()?;
                 if ty.is_float() {
                     let fp = self.fp_stack.pop1()?;
@@ -1318,12 +2146,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             }
             Operator::LocalGet { local_index } => {
                 let local_index = local_index as usize;
+                let slot = self.local_slots[local_index];
+                if self.local_types[local_index] == WpType::V128 {
+                    let (lo, hi) = self.push_v128()?;
+                    self.machine
+                        .emit_relaxed_mov(Size::S64, self.locals[slot], lo)?;
+                    self.machine
+                        .emit_relaxed_mov(Size::S64, self.locals[slot + 1], hi)?;
+                    return Ok(());
+                }
                 let ret = self.acquire_locations(
                     &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
                 )?[0];
                 self.machine
-                    .emit_relaxed_mov(Size::S64, self.locals[local_index], ret)?;
+                    .emit_relaxed_mov(Size::S64, self.locals[slot], ret)?;
                 self.value_stack.push(ret);
                 if self.local_types[local_index].is_float() {
                     self.fp_stack
@@ -1332,6 +2169,15 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             }
             Operator::LocalSet { local_index } => {
                 let local_index = local_index as usize;
+                let slot = self.local_slots[local_index];
+                if self.local_types[local_index] == WpType::V128 {
+                    let (lo, hi) = self.pop_v128_released()?;
+                    self.machine
+                        .emit_relaxed_mov(Size::S64, lo, self.locals[slot])?;
+                    self.machine
+                        .emit_relaxed_mov(Size::S64, hi, self.locals[slot + 1])?;
+                    return Ok(());
+                }
                 let loc = self.pop_value_released()?;
 
                 if self.local_types[local_index].is_float() {
@@ -1347,19 +2193,29 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                                 _ => codegen_error!("singlepass Operator::LocalSet unreachable"),
                             },
                             loc,
-                            self.locals[local_index],
+                            self.locals[slot],
                         )
                     } else {
                         self.machine
-                            .emit_relaxed_mov(Size::S64, loc, self.locals[local_index])
+                            .emit_relaxed_mov(Size::S64, loc, self.locals[slot])
                     }
                 } else {
                     self.machine
-                        .emit_relaxed_mov(Size::S64, loc, self.locals[local_index])
+                        .emit_relaxed_mov(Size::S64, loc, self.locals[slot])
                 }?;
             }
             Operator::LocalTee { local_index } => {
                 let local_index = local_index as usize;
+                let slot = self.local_slots[local_index];
+                if self.local_types[local_index] == WpType::V128 {
+                    let len = self.value_stack.len();
+                    let (lo, hi) = (self.value_stack[len - 2], self.value_stack[len - 1]);
+                    self.machine
+                        .emit_relaxed_mov(Size::S64, lo, self.locals[slot])?;
+                    self.machine
+                        .emit_relaxed_mov(Size::S64, hi, self.locals[slot + 1])?;
+                    return Ok(());
+                }
                 let loc = *self.value_stack.last().unwrap();
 
                 if self.local_types[local_index].is_float() {
@@ -1375,15 +2231,15 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                                 _ => codegen_error!("singlepass Operator::LocalTee unreachable"),
                             },
                             loc,
-                            self.locals[local_index],
+                            self.locals[slot],
                         )
                     } else {
                         self.machine
-                            .emit_relaxed_mov(Size::S64, loc, self.locals[local_index])
+                            .emit_relaxed_mov(Size::S64, loc, self.locals[slot])
                     }
                 } else {
                     self.machine
-                        .emit_relaxed_mov(Size::S64, loc, self.locals[local_index])
+                        .emit_relaxed_mov(Size::S64, loc, self.locals[slot])
                 }?;
             }
             Operator::I32Const { value } => {
@@ -2619,8 +3475,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 self.machine.convert_f64_i64(loc, false, ret)?;
             }
 
-            Operator::Call { function_index } => {
+            Operator::Call { function_index } | Operator::ReturnCall { function_index } => {
                 let function_index = function_index as usize;
+                let is_tail_call = matches!(op, Operator::ReturnCall { .. });
 
                 let sig_index = *self
                     .module
@@ -2628,8 +3485,14 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     .get(FunctionIndex::new(function_index))
                     .unwrap();
                 let sig = self.module.signatures.get(sig_index).unwrap();
-                let param_types: SmallVec<[WpType; 8]> =
-                    sig.params().iter().cloned().map(type_to_wp_type).collect();
+                if is_tail_call {
+                    self.check_tail_call(sig)?;
+                }
+                // `V128` arguments are passed as two 64-bit halves.
+                let param_types: SmallVec<[WpType; 8]> = lower_v128_params(sig.params())
+                    .into_iter()
+                    .map(|(ty, _)| type_to_wp_type(ty))
+                    .collect();
                 let return_types: SmallVec<[WpType; 1]> =
                     sig.results().iter().cloned().map(type_to_wp_type).collect();
 
@@ -2637,6 +3500,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     .value_stack
                     .drain(self.value_stack.len() - param_types.len()..)
                     .collect();
+                self.truncate_v128_stack(self.value_stack.len());
                 self.release_locations_only_regs(&params)?;
 
                 self.release_locations_only_osr_state(params.len())?;
@@ -2672,62 +3536,66 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 };
                 let calling_convention = self.calling_convention;
 
-                self.emit_call_native(
-                    |this| {
-                        let offset = this
-                            .machine
-                            .mark_instruction_with_trap_code(TrapCode::StackOverflow);
-                        let mut relocations = this
-                            .machine
-                            .emit_call_with_reloc(calling_convention, reloc_target)?;
-                        this.machine.mark_instruction_address_end(offset);
-                        this.relocations.append(&mut relocations);
-                        Ok(())
-                    },
-                    params.iter().copied(),
-                    param_types.iter().copied(),
-                )?;
-
-                self.release_locations_only_stack(&params)?;
+                if is_tail_call {
+                    self.emit_tail_call_native(
+                        |this| {
+                            this.machine.emit_function_epilog()?;
+                            let mut relocations = this
+                                .machine
+                                .emit_jmp_with_reloc(calling_convention, reloc_target)?;
+                            this.relocations.append(&mut relocations);
+                            Ok(())
+                        },
+                        Location::GPR(self.machine.get_vmctx_reg()),
+                        params.iter().copied(),
+                        param_types.iter().copied(),
+                    )?;
+                    self.release_locations_only_stack(&params)?;
+                    self.unreachable_depth = 1;
+                } else {
+                    self.emit_call_native(
+                        |this| {
+                            let offset = this
+                                .machine
+                                .mark_instruction_with_trap_code(TrapCode::StackOverflow);
+                            let mut relocations = this
+                                .machine
+                                .emit_call_with_reloc(calling_convention, reloc_target)?;
+                            this.machine.mark_instruction_address_end(offset);
+                            this.relocations.append(&mut relocations);
+                            Ok(())
+                        },
+                        params.iter().copied(),
+                        param_types.iter().copied(),
+                    )?;
 
-                if !return_types.is_empty() {
-                    let ret = self.acquire_locations(
-                        &[(
-                            return_types[0],
-                            MachineValue::WasmStack(self.value_stack.len()),
-                        )],
-                        false,
-                    )?[0];
-                    self.value_stack.push(ret);
-                    if return_types[0].is_float() {
-                        self.machine.move_location(
-                            Size::S64,
-                            Location::SIMD(self.machine.get_simd_for_ret()),
-                            ret,
-                        )?;
-                        self.fp_stack
-                            .push(FloatValue::new(self.value_stack.len() - 1));
-                    } else {
-                        self.machine.move_location(
-                            Size::S64,
-                            Location::GPR(self.machine.get_gpr_for_ret()),
-                            ret,
-                        )?;
-                    }
+                    self.release_locations_only_stack(&params)?;
+                    self.push_call_return_value(&return_types)?;
                 }
             }
             Operator::CallIndirect {
                 type_index,
                 table_index,
                 table_byte: _,
+            }
+            | Operator::ReturnCallIndirect {
+                type_index,
+                table_index,
             } => {
+                let is_tail_call = matches!(op, Operator::ReturnCallIndirect { .. });
                 // TODO: removed restriction on always being table idx 0;
                 // does any code depend on this?
                 let table_index = TableIndex::new(table_index as _);
                 let index = SignatureIndex::new(type_index as usize);
                 let sig = self.module.signatures.get(index).unwrap();
-                let param_types: SmallVec<[WpType; 8]> =
-                    sig.params().iter().cloned().map(type_to_wp_type).collect();
+                if is_tail_call {
+                    self.check_tail_call(sig)?;
+                }
+                // `V128` arguments are passed as two 64-bit halves.
+                let param_types: SmallVec<[WpType; 8]> = lower_v128_params(sig.params())
+                    .into_iter()
+                    .map(|(ty, _)| type_to_wp_type(ty))
+                    .collect();
                 let return_types: SmallVec<[WpType; 1]> =
                     sig.results().iter().cloned().map(type_to_wp_type).collect();
 
@@ -2737,6 +3605,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     .value_stack
                     .drain(self.value_stack.len() - param_types.len()..)
                     .collect();
+                self.truncate_v128_stack(self.value_stack.len());
                 self.release_locations_only_regs(&params)?;
 
                 // Pop arguments off the FP stack and canonicalize them if needed.
@@ -2881,6 +3750,31 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     self.vmoffsets.vmcaller_checked_anyfunc_vmctx() as usize;
                 let calling_convention = self.calling_convention;
 
+                if is_tail_call {
+                    self.emit_tail_call_native(
+                        |this| {
+                            // The register holding the callee may be restored by the epilog.
+                            let target = this.machine.get_gpr_for_tail_call();
+                            this.machine.move_location(
+                                Size::S64,
+                                Location::Memory(
+                                    gpr_for_call,
+                                    vmcaller_checked_anyfunc_func_ptr as i32,
+                                ),
+                                Location::GPR(target),
+                            )?;
+                            this.machine.emit_function_epilog()?;
+                            this.machine.emit_jmp_register(target)
+                        },
+                        Location::Memory(gpr_for_call, vmcaller_checked_anyfunc_vmctx as i32),
+                        params.iter().copied(),
+                        param_types.iter().copied(),
+                    )?;
+                    self.release_locations_only_stack(&params)?;
+                    self.unreachable_depth = 1;
+                    return Ok(());
+                }
+
                 self.emit_call_native(
                     |this| {
                         if this.machine.arch_requires_indirect_call_trampoline() {
@@ -2918,32 +3812,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?;
 
                 self.release_locations_only_stack(&params)?;
-
-                if !return_types.is_empty() {
-                    let ret = self.acquire_locations(
-                        &[(
-                            return_types[0],
-                            MachineValue::WasmStack(self.value_stack.len()),
-                        )],
-                        false,
-                    )?[0];
-                    self.value_stack.push(ret);
-                    if return_types[0].is_float() {
-                        self.machine.move_location(
-                            Size::S64,
-                            Location::SIMD(self.machine.get_simd_for_ret()),
-                            ret,
-                        )?;
-                        self.fp_stack
-                            .push(FloatValue::new(self.value_stack.len() - 1));
-                    } else {
-                        self.machine.move_location(
-                            Size::S64,
-                            Location::GPR(self.machine.get_gpr_for_ret()),
-                            ret,
-                        )?;
-                    }
-                }
+                self.push_call_return_value(&return_types)?;
             }
             Operator::If { blockty } => {
                 let label_end = self.machine.get_label();
@@ -2979,17 +3848,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
 
                 if !was_unreachable && !frame.returns.is_empty() {
                     let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    let canonicalize = if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        self.machine.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                    } else {
-                        false
-                    };
-                    self.machine
-                        .emit_function_return_value(first_return, canonicalize, loc)?;
+                    self.emit_return_value(first_return)?;
                 }
 
                 let frame = &self.control_stack.last_mut().unwrap();
@@ -2997,6 +3856,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let fp_depth = frame.fp_stack_depth;
                 self.release_locations_value(stack_depth)?;
                 self.value_stack.truncate(stack_depth);
+                self.truncate_v128_stack(stack_depth);
                 self.fp_stack.truncate(fp_depth);
                 let frame = &mut self.control_stack.last_mut().unwrap();
 
@@ -3017,6 +3877,24 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             // be done with TypedSelect. But otherwise they're the same.
             Operator::TypedSelect { .. } | Operator::Select => {
                 let cond = self.pop_value_released()?;
+                if self.top_is_v128() {
+                    let v_b = self.pop_v128_released()?;
+                    let v_a = self.pop_v128_released()?;
+                    let ret = self.push_v128()?;
+
+                    let end_label = self.machine.get_label();
+                    let zero_label = self.machine.get_label();
+
+                    self.machine
+                        .emit_relaxed_cmp(Size::S32, Location::Imm32(0), cond)?;
+                    self.machine.jmp_on_equal(zero_label)?;
+                    self.move_v128(v_a, ret)?;
+                    self.machine.jmp_unconditionnal(end_label)?;
+                    self.machine.emit_label(zero_label)?;
+                    self.move_v128(v_b, ret)?;
+                    self.machine.emit_label(end_label)?;
+                    return Ok(());
+                }
                 let v_b = self.pop_value_released()?;
                 let v_a = self.pop_value_released()?;
                 let cncl: Option<(Option<CanonicalizeType>, Option<CanonicalizeType>)> =
@@ -3122,7 +4000,29 @@ impl<'a, M: Machine> FuncGen<'a, M> {
 
                 // TODO: Re-enable interrupt signal check without branching
             }
-            Operator::Nop => {}
+            Operator::Nop => {
+                if self.epoch_deadline {
+                    self.machine.move_location(
+                        Size::S64,
+                        Location::Memory(
+                            self.machine.get_vmctx_reg(),
+                            self.vmoffsets.vmctx_builtin_function(
+                                VMBuiltinFunctionIndex::get_epoch_deadline_index(),
+                            ) as i32,
+                        ),
+                        Location::GPR(self.machine.get_grp_for_call()),
+                    )?;
+                    self.emit_call_native(
+                        |this| {
+                            this.machine
+                                .emit_call_register(this.machine.get_grp_for_call())
+                        },
+                        // [vmctx]
+                        iter::empty(),
+                        iter::empty(),
+                    )?;
+                }
+            }
             Operator::MemorySize { mem, mem_byte: _ } => {
                 let memory_index = MemoryIndex::new(mem as usize);
                 self.machine.move_location(
@@ -3384,6 +4284,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3413,6 +4314,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1));
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3440,6 +4342,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3467,6 +4370,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3494,6 +4398,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3521,6 +4426,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3544,6 +4450,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3569,6 +4476,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let fp = self.fp_stack.pop1()?;
                 let config_nan_canonicalization = self.config.enable_nan_canonicalization;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3593,6 +4501,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3616,6 +4525,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3643,6 +4553,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3672,6 +4583,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1));
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3699,6 +4611,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3726,6 +4639,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3753,6 +4667,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3780,6 +4695,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3807,6 +4723,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3834,6 +4751,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3858,6 +4776,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_addr = self.pop_value_released()?;
 
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3883,6 +4802,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let fp = self.fp_stack.pop1()?;
                 let config_nan_canonicalization = self.config.enable_nan_canonicalization;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3907,6 +4827,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3930,6 +4851,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3953,6 +4875,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -3974,38 +4897,13 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             }
             Operator::Unreachable => {
                 self.mark_trappable();
-                self.machine
-                    .emit_illegal_op(TrapCode::UnreachableCodeReached)?;
-                self.unreachable_depth = 1;
-            }
-            Operator::Return => {
-                let frame = &self.control_stack[0];
-                if !frame.returns.is_empty() {
-                    if frame.returns.len() != 1 {
-                        return Err(CompileError::Codegen(
-                            "Return: incorrect frame.returns".to_owned(),
-                        ));
-                    }
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    let canonicalize = if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        self.machine.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                    } else {
-                        false
-                    };
-                    self.machine
-                        .emit_function_return_value(first_return, canonicalize, loc)?;
-                }
-                let frame = &self.control_stack[0];
-                let frame_depth = frame.value_stack_depth;
-                let label = frame.label;
-                self.release_locations_keep_state(frame_depth)?;
-                self.machine.jmp_unconditionnal(label)?;
+                let trap_code = self
+                    .unreachable_trap_code
+                    .unwrap_or(TrapCode::UnreachableCodeReached);
+                self.machine.emit_illegal_op(trap_code)?;
                 self.unreachable_depth = 1;
             }
+            Operator::Return => self.emit_return()?,
             Operator::Br { relative_depth } => {
                 let frame =
                     &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
@@ -4016,17 +4914,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         ));
                     }
                     let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    let canonicalize = if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        self.machine.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                    } else {
-                        false
-                    };
-                    self.machine
-                        .emit_function_return_value(first_return, canonicalize, loc)?;
+                    self.emit_return_value(first_return)?;
                 }
                 let stack_len = self.control_stack.len();
                 let frame = &mut self.control_stack[stack_len - 1 - (relative_depth as usize)];
@@ -4054,17 +4942,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     }
 
                     let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    let canonicalize = if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        self.machine.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                    } else {
-                        false
-                    };
-                    self.machine
-                        .emit_function_return_value(first_return, canonicalize, loc)?;
+                    self.emit_return_value(first_return)?;
                 }
                 let stack_len = self.control_stack.len();
                 let frame = &mut self.control_stack[stack_len - 1 - (relative_depth as usize)];
@@ -4109,17 +4987,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         }
 
                         let first_return = frame.returns[0];
-                        let loc = *self.value_stack.last().unwrap();
-                        let canonicalize = if first_return.is_float() {
-                            let fp = self.fp_stack.peek1()?;
-                            self.machine.arch_supports_canonicalize_nan()
-                                && self.config.enable_nan_canonicalization
-                                && fp.canonicalization.is_some()
-                        } else {
-                            false
-                        };
-                        self.machine
-                            .emit_function_return_value(first_return, canonicalize, loc)?;
+                        self.emit_return_value(first_return)?;
                     }
                     let frame =
                         &self.control_stack[self.control_stack.len() - 1 - (*target as usize)];
@@ -4141,17 +5009,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         }
 
                         let first_return = frame.returns[0];
-                        let loc = *self.value_stack.last().unwrap();
-                        let canonicalize = if first_return.is_float() {
-                            let fp = self.fp_stack.peek1()?;
-                            self.machine.arch_supports_canonicalize_nan()
-                                && self.config.enable_nan_canonicalization
-                                && fp.canonicalization.is_some()
-                        } else {
-                            false
-                        };
-                        self.machine
-                            .emit_function_return_value(first_return, canonicalize, loc)?;
+                        self.emit_return_value(first_return)?;
                     }
                     let frame = &self.control_stack
                         [self.control_stack.len() - 1 - (default_target as usize)];
@@ -4168,6 +5026,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 self.unreachable_depth = 1;
             }
             Operator::Drop => {
+                if self.top_is_v128() {
+                    self.pop_v128_released()?;
+                    return Ok(());
+                }
                 self.pop_value_released()?;
                 if let Some(x) = self.fp_stack.last() {
                     if x.depth == self.value_stack.len() {
@@ -4179,17 +5041,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let frame = self.control_stack.pop().unwrap();
 
                 if !was_unreachable && !frame.returns.is_empty() {
-                    let loc = *self.value_stack.last().unwrap();
-                    let canonicalize = if frame.returns[0].is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        self.machine.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                    } else {
-                        false
-                    };
-                    self.machine
-                        .emit_function_return_value(frame.returns[0], canonicalize, loc)?;
+                    self.emit_return_value(frame.returns[0])?;
                 }
 
                 if self.control_stack.is_empty() {
@@ -4209,6 +5061,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     let released = &self.value_stack.clone()[frame.value_stack_depth..];
                     self.release_locations(released)?;
                     self.value_stack.truncate(frame.value_stack_depth);
+                    self.truncate_v128_stack(frame.value_stack_depth);
                     self.fp_stack.truncate(frame.fp_stack_depth);
 
                     if !frame.loop_like {
@@ -4225,6 +5078,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                                 "End: incorrect frame.returns".to_owned(),
                             ));
                         }
+                        if frame.returns[0] == WpType::V128 {
+                            return self.push_v128_return_value();
+                        }
                         let loc = self.acquire_locations(
                             &[(
                                 frame.returns[0],
@@ -4264,6 +5120,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4291,6 +5148,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4318,6 +5176,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4341,6 +5200,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4364,6 +5224,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4387,6 +5248,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4414,6 +5276,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4441,6 +5304,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4468,6 +5332,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4495,6 +5360,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4518,6 +5384,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4541,6 +5408,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4564,6 +5432,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4587,6 +5456,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released()?;
                 let target_addr = self.pop_value_released()?;
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4615,6 +5485,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4644,6 +5515,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4673,6 +5545,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4702,6 +5575,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4731,6 +5605,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4760,6 +5635,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4789,6 +5665,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4818,6 +5695,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4847,6 +5725,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4876,6 +5755,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4905,6 +5785,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4934,6 +5815,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4963,6 +5845,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -4992,6 +5875,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5021,6 +5905,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5050,6 +5935,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5079,6 +5965,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5108,6 +5995,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5137,6 +6025,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5166,6 +6055,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5195,6 +6085,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5224,6 +6115,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5253,6 +6145,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5282,6 +6175,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5311,6 +6205,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5340,6 +6235,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5369,6 +6265,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5398,6 +6295,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5427,6 +6325,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5456,6 +6355,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5485,6 +6385,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5514,6 +6415,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5543,6 +6445,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5572,6 +6475,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5601,6 +6505,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5630,6 +6535,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5659,6 +6565,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5688,6 +6595,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5717,6 +6625,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5746,6 +6655,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5775,6 +6685,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5804,6 +6715,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5834,6 +6746,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5865,6 +6778,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5896,6 +6810,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5927,6 +6842,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5958,6 +6874,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -5989,6 +6906,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -6020,6 +6938,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this,
                      need_check,
                      imported_memories,
@@ -6604,7 +7523,102 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     ret,
                 )?;
             }
+            Operator::V128Const { value } => {
+                let value = value.i128() as u128;
+                for half in [value as u64, (value >> 64) as u64] {
+                    self.value_stack.push(Location::Imm64(half));
+                    self.state.wasm_stack.push(WasmAbstractValue::Const(half));
+                }
+                self.v128_stack.push(self.value_stack.len() - 1);
+            }
+            Operator::V128Load { ref memarg } => {
+                let target = self.pop_value_released()?;
+                // The address is kept in a temporary register, as it may share its location
+                // with one of the halves of the result.
+                let addr = self.machine.acquire_temp_gpr().unwrap();
+                self.machine
+                    .move_location(Size::S32, target, Location::GPR(addr))?;
+                let (lo, hi) = self.push_v128()?;
+                if let Some(memarg_hi) = self.v128_high_memarg(memarg)? {
+                    self.emit_memory_access(M::i64_load, Location::GPR(addr), memarg, lo)?;
+                    self.emit_memory_access(M::i64_load, Location::GPR(addr), &memarg_hi, hi)?;
+                }
+                self.machine.release_gpr(addr);
+            }
+            Operator::V128Store { ref memarg } => {
+                let (lo, hi) = self.pop_v128_released()?;
+                let target = self.pop_value_released()?;
+                // The high half is stored first, so that nothing is written if the access
+                // is out of bounds.
+                if let Some(memarg_hi) = self.v128_high_memarg(memarg)? {
+                    self.emit_memory_access(M::i64_save, hi, &memarg_hi, target)?;
+                    self.emit_memory_access(M::i64_save, lo, memarg, target)?;
+                }
+            }
+            Operator::V128Load8x8S { ref memarg } => {
+                self.emit_v128_load_low(M::i64_load, memarg, Some(SimdOp::I16x8ExtendLowI8x16S))?
+            }
+            Operator::V128Load8x8U { ref memarg } => {
+                self.emit_v128_load_low(M::i64_load, memarg, Some(SimdOp::I16x8ExtendLowI8x16U))?
+            }
+            Operator::V128Load16x4S { ref memarg } => {
+                self.emit_v128_load_low(M::i64_load, memarg, Some(SimdOp::I32x4ExtendLowI16x8S))?
+            }
+            Operator::V128Load16x4U { ref memarg } => {
+                self.emit_v128_load_low(M::i64_load, memarg, Some(SimdOp::I32x4ExtendLowI16x8U))?
+            }
+            Operator::V128Load32x2S { ref memarg } => {
+                self.emit_v128_load_low(M::i64_load, memarg, Some(SimdOp::I64x2ExtendLowI32x4S))?
+            }
+            Operator::V128Load32x2U { ref memarg } => {
+                self.emit_v128_load_low(M::i64_load, memarg, Some(SimdOp::I64x2ExtendLowI32x4U))?
+            }
+            Operator::V128Load32Zero { ref memarg } => {
+                self.emit_v128_load_low(M::i64_load_32u, memarg, None)?
+            }
+            Operator::V128Load64Zero { ref memarg } => {
+                self.emit_v128_load_low(M::i64_load, memarg, None)?
+            }
+            Operator::V128Load8Splat { ref memarg } => {
+                self.emit_v128_load_splat(M::i32_load_8u, memarg, WpType::I32, SimdOp::I8x16Splat)?
+            }
+            Operator::V128Load16Splat { ref memarg } => {
+                self.emit_v128_load_splat(M::i32_load_16u, memarg, WpType::I32, SimdOp::I16x8Splat)?
+            }
+            Operator::V128Load32Splat { ref memarg } => {
+                self.emit_v128_load_splat(M::i32_load, memarg, WpType::I32, SimdOp::I32x4Splat)?
+            }
+            Operator::V128Load64Splat { ref memarg } => {
+                self.emit_v128_load_splat(M::i64_load, memarg, WpType::I64, SimdOp::I64x2Splat)?
+            }
+            Operator::V128Load8Lane { ref memarg, lane } => {
+                self.emit_v128_load_lane(M::i32_load_8u, memarg, lane, SimdOp::I8x16ReplaceLane)?
+            }
+            Operator::V128Load16Lane { ref memarg, lane } => {
+                self.emit_v128_load_lane(M::i32_load_16u, memarg, lane, SimdOp::I16x8ReplaceLane)?
+            }
+            Operator::V128Load32Lane { ref memarg, lane } => {
+                self.emit_v128_load_lane(M::i32_load, memarg, lane, SimdOp::I32x4ReplaceLane)?
+            }
+            Operator::V128Load64Lane { ref memarg, lane } => {
+                self.emit_v128_load_lane(M::i64_load, memarg, lane, SimdOp::I64x2ReplaceLane)?
+            }
+            Operator::V128Store8Lane { ref memarg, lane } => {
+                self.emit_v128_store_lane(M::i32_save_8, memarg, lane, SimdOp::I8x16ExtractLaneU)?
+            }
+            Operator::V128Store16Lane { ref memarg, lane } => {
+                self.emit_v128_store_lane(M::i32_save_16, memarg, lane, SimdOp::I16x8ExtractLaneU)?
+            }
+            Operator::V128Store32Lane { ref memarg, lane } => {
+                self.emit_v128_store_lane(M::i32_save, memarg, lane, SimdOp::I32x4ExtractLane)?
+            }
+            Operator::V128Store64Lane { ref memarg, lane } => {
+                self.emit_v128_store_lane(M::i64_save, memarg, lane, SimdOp::I64x2ExtractLane)?
+            }
             _ => {
+                if let Some((simd_op, lanes)) = simd_operator(&op) {
+                    return self.emit_simd_operator(simd_op, &lanes);
+                }
                 return Err(CompileError::Codegen(format!(
                     "not yet implemented: {:?}",
                     op
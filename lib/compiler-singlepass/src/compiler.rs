@@ -27,7 +27,7 @@ use wasmer_compiler::{
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     Architecture, CallingConvention, Compilation, CompileError, CompileModuleInfo,
-    CompiledFunction, CpuFeature, Dwarf, FunctionBody, FunctionIndex, FunctionType,
+    CompiledFunction, CpuFeature, Dwarf, Features, FunctionBody, FunctionIndex, FunctionType,
     LocalFunctionIndex, MemoryIndex, ModuleInfo, OperatingSystem, SectionIndex, TableIndex, Target,
     TrapCode, TrapInformation, VMOffsets,
 };
@@ -55,6 +55,13 @@ impl Compiler for SinglepassCompiler {
         "singlepass"
     }
 
+    fn supported_features(&self, features: &Features) -> Features {
+        let mut features = features.clone();
+        // Exceptions are not implemented yet.
+        features.exceptions = false;
+        features
+    }
+
     /// Get the middlewares for this compiler
     fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
         &self.config.middlewares
@@ -174,6 +181,8 @@ impl Compiler for SinglepassCompiler {
                         while generator.has_control_frames() {
                             generator.set_srcloc(reader.original_position() as u32);
                             let op = reader.read_operator()?;
+                            generator.set_unreachable_trap_code(reader.trap_code());
+                            generator.set_epoch_deadline(reader.epoch_deadline());
                             generator.feed_operator(op)?;
                         }
 
@@ -195,6 +204,8 @@ impl Compiler for SinglepassCompiler {
                         while generator.has_control_frames() {
                             generator.set_srcloc(reader.original_position() as u32);
                             let op = reader.read_operator()?;
+                            generator.set_unreachable_trap_code(reader.trap_code());
+                            generator.set_epoch_deadline(reader.epoch_deadline());
                             generator.feed_operator(op)?;
                         }
 
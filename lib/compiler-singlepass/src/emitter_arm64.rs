@@ -3,6 +3,7 @@ use crate::codegen_error;
 use crate::common_decl::Size;
 use crate::location::Location as AbstractLocation;
 pub use crate::location::{Multiplier, Reg};
+use crate::machine::lower_v128_params;
 pub use crate::machine::{Label, Offset};
 use dynasm::dynasm;
 pub use dynasmrt::aarch64::{encode_logical_immediate_32bit, encode_logical_immediate_64bit};
@@ -84,6 +85,137 @@ pub enum GPROrMemory {
     Memory(GPR, i32),
 }
 
+macro_rules! neon_vector_ops {
+    (
+        binary { $($bname:ident => $bins:ident $barr:ident,)* }
+        unary { $($uname:ident => $uins:ident $uarr:ident,)* }
+    ) => {
+        /// NEON instructions working on whole vectors, used to lower `v128` operations.
+        #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+        pub enum NeonVector {
+            $($bname,)*
+            $($uname,)*
+        }
+
+        fn emit_neon_vector_op(
+            a: &mut Assembler,
+            op: NeonVector,
+            src1: u32,
+            src2: u32,
+            dst: u32,
+        ) -> Result<(), CompileError> {
+            match op {
+                $(NeonVector::$bname => dynasm!(a ; $bins V(dst).$barr, V(src1).$barr, V(src2).$barr),)*
+                $(NeonVector::$uname => dynasm!(a ; $uins V(dst).$uarr, V(src1).$uarr),)*
+            }
+            Ok(())
+        }
+    };
+}
+
+neon_vector_ops! {
+    binary {
+        And => and B16,
+        Bic => bic B16,
+        Orr => orr B16,
+        Eor => eor B16,
+        Bsl => bsl B16,
+        AddB => add B16,
+        AddH => add H8,
+        AddS => add S4,
+        AddD => add D2,
+        SubB => sub B16,
+        SubH => sub H8,
+        SubS => sub S4,
+        SubD => sub D2,
+        SqaddB => sqadd B16,
+        SqaddH => sqadd H8,
+        UqaddB => uqadd B16,
+        UqaddH => uqadd H8,
+        SqsubB => sqsub B16,
+        SqsubH => sqsub H8,
+        UqsubB => uqsub B16,
+        UqsubH => uqsub H8,
+        SminB => smin B16,
+        SminH => smin H8,
+        SminS => smin S4,
+        UminB => umin B16,
+        UminH => umin H8,
+        UminS => umin S4,
+        SmaxB => smax B16,
+        SmaxH => smax H8,
+        SmaxS => smax S4,
+        UmaxB => umax B16,
+        UmaxH => umax H8,
+        UmaxS => umax S4,
+        UrhaddB => urhadd B16,
+        UrhaddH => urhadd H8,
+        MulH => mul H8,
+        MulS => mul S4,
+        CmeqB => cmeq B16,
+        CmeqH => cmeq H8,
+        CmeqS => cmeq S4,
+        CmeqD => cmeq D2,
+        CmgtB => cmgt B16,
+        CmgtH => cmgt H8,
+        CmgtS => cmgt S4,
+        CmgtD => cmgt D2,
+        CmgeB => cmge B16,
+        CmgeH => cmge H8,
+        CmgeS => cmge S4,
+        CmgeD => cmge D2,
+        CmhiB => cmhi B16,
+        CmhiH => cmhi H8,
+        CmhiS => cmhi S4,
+        CmhsB => cmhs B16,
+        CmhsH => cmhs H8,
+        CmhsS => cmhs S4,
+        FaddS => fadd S4,
+        FaddD => fadd D2,
+        FsubS => fsub S4,
+        FsubD => fsub D2,
+        FmulS => fmul S4,
+        FmulD => fmul D2,
+        FdivS => fdiv S4,
+        FdivD => fdiv D2,
+        FminS => fmin S4,
+        FminD => fmin D2,
+        FmaxS => fmax S4,
+        FmaxD => fmax D2,
+        FcmeqS => fcmeq S4,
+        FcmeqD => fcmeq D2,
+        FcmgtS => fcmgt S4,
+        FcmgtD => fcmgt D2,
+        FcmgeS => fcmge S4,
+        FcmgeD => fcmge D2,
+    }
+    unary {
+        Mvn => mvn B16,
+        NegB => neg B16,
+        NegH => neg H8,
+        NegS => neg S4,
+        NegD => neg D2,
+        AbsB => abs B16,
+        AbsH => abs H8,
+        AbsS => abs S4,
+        AbsD => abs D2,
+        FabsS => fabs S4,
+        FabsD => fabs D2,
+        FnegS => fneg S4,
+        FnegD => fneg D2,
+        FsqrtS => fsqrt S4,
+        FsqrtD => fsqrt D2,
+        FrintnS => frintn S4,
+        FrintnD => frintn D2,
+        FrintmS => frintm S4,
+        FrintmD => frintm D2,
+        FrintpS => frintp S4,
+        FrintpD => frintp D2,
+        FrintzS => frintz S4,
+        FrintzD => frintz D2,
+    }
+}
+
 pub trait EmitterARM64 {
     fn get_label(&mut self) -> Label;
     fn get_offset(&self) -> Offset;
@@ -383,6 +515,38 @@ pub trait EmitterARM64 {
     fn emit_dmb(&mut self) -> Result<(), CompileError>;
     fn emit_brk(&mut self) -> Result<(), CompileError>;
 
+    fn emit_neon_vector(
+        &mut self,
+        op: NeonVector,
+        src1: NEON,
+        src2: NEON,
+        dst: NEON,
+    ) -> Result<(), CompileError>;
+    fn emit_neon_dup(&mut self, sz: Size, src: NEON, dst: NEON) -> Result<(), CompileError>;
+    fn emit_neon_ins(
+        &mut self,
+        sz: Size,
+        src: NEON,
+        src_lane: u32,
+        dst: NEON,
+        dst_lane: u32,
+    ) -> Result<(), CompileError>;
+    fn emit_neon_mov_to_lane(
+        &mut self,
+        sz: Size,
+        src: GPR,
+        lane: u32,
+        dst: NEON,
+    ) -> Result<(), CompileError>;
+    fn emit_neon_mov_from_lane(
+        &mut self,
+        sz: Size,
+        signed: bool,
+        src: NEON,
+        lane: u32,
+        dst: GPR,
+    ) -> Result<(), CompileError>;
+
     fn emit_fcmp(&mut self, sz: Size, src1: Location, src2: Location) -> Result<(), CompileError>;
     fn emit_fneg(&mut self, sz: Size, src: Location, dst: Location) -> Result<(), CompileError>;
     fn emit_fsqrt(&mut self, sz: Size, src: Location, dst: Location) -> Result<(), CompileError>;
@@ -2728,6 +2892,88 @@ impl EmitterARM64 for Assembler {
         Ok(())
     }
 
+    fn emit_neon_vector(
+        &mut self,
+        op: NeonVector,
+        src1: NEON,
+        src2: NEON,
+        dst: NEON,
+    ) -> Result<(), CompileError> {
+        emit_neon_vector_op(
+            self,
+            op,
+            src1.into_index() as u32,
+            src2.into_index() as u32,
+            dst.into_index() as u32,
+        )
+    }
+    fn emit_neon_dup(&mut self, sz: Size, src: NEON, dst: NEON) -> Result<(), CompileError> {
+        let src = src.into_index() as u32;
+        let dst = dst.into_index() as u32;
+        match sz {
+            Size::S8 => dynasm!(self ; dup V(dst).B16, V(src).B[0]),
+            Size::S16 => dynasm!(self ; dup V(dst).H8, V(src).H[0]),
+            Size::S32 => dynasm!(self ; dup V(dst).S4, V(src).S[0]),
+            Size::S64 => dynasm!(self ; dup V(dst).D2, V(src).D[0]),
+        }
+        Ok(())
+    }
+    fn emit_neon_ins(
+        &mut self,
+        sz: Size,
+        src: NEON,
+        src_lane: u32,
+        dst: NEON,
+        dst_lane: u32,
+    ) -> Result<(), CompileError> {
+        let src = src.into_index() as u32;
+        let dst = dst.into_index() as u32;
+        match sz {
+            Size::S8 => dynasm!(self ; mov V(dst).B[dst_lane], V(src).B[src_lane]),
+            Size::S16 => dynasm!(self ; mov V(dst).H[dst_lane], V(src).H[src_lane]),
+            Size::S32 => dynasm!(self ; mov V(dst).S[dst_lane], V(src).S[src_lane]),
+            Size::S64 => dynasm!(self ; mov V(dst).D[dst_lane], V(src).D[src_lane]),
+        }
+        Ok(())
+    }
+    fn emit_neon_mov_to_lane(
+        &mut self,
+        sz: Size,
+        src: GPR,
+        lane: u32,
+        dst: NEON,
+    ) -> Result<(), CompileError> {
+        let src = src.into_index() as u32;
+        let dst = dst.into_index() as u32;
+        match sz {
+            Size::S8 => dynasm!(self ; mov V(dst).B[lane], W(src)),
+            Size::S16 => dynasm!(self ; mov V(dst).H[lane], W(src)),
+            Size::S32 => dynasm!(self ; mov V(dst).S[lane], W(src)),
+            Size::S64 => dynasm!(self ; mov V(dst).D[lane], X(src)),
+        }
+        Ok(())
+    }
+    fn emit_neon_mov_from_lane(
+        &mut self,
+        sz: Size,
+        signed: bool,
+        src: NEON,
+        lane: u32,
+        dst: GPR,
+    ) -> Result<(), CompileError> {
+        let src = src.into_index() as u32;
+        let dst = dst.into_index() as u32;
+        match (sz, signed) {
+            (Size::S8, false) => dynasm!(self ; umov W(dst), V(src).B[lane]),
+            (Size::S8, true) => dynasm!(self ; smov W(dst), V(src).B[lane]),
+            (Size::S16, false) => dynasm!(self ; umov W(dst), V(src).H[lane]),
+            (Size::S16, true) => dynasm!(self ; smov W(dst), V(src).H[lane]),
+            (Size::S32, _) => dynasm!(self ; mov W(dst), V(src).S[lane]),
+            (Size::S64, _) => dynasm!(self ; mov X(dst), V(src).D[lane]),
+        }
+        Ok(())
+    }
+
     fn emit_fcmp(&mut self, sz: Size, src1: Location, src2: Location) -> Result<(), CompileError> {
         match (sz, src1, src2) {
             (Size::S32, Location::SIMD(src1), Location::SIMD(src2)) => {
@@ -3224,6 +3470,7 @@ pub fn gen_std_trampoline_arm64(
     calling_convention: CallingConvention,
 ) -> Result<FunctionBody, CompileError> {
     let mut a = Assembler::new(0);
+    let params = lower_v128_params(sig.params());
 
     let fptr = GPR::X27;
     let args = GPR::X28;
@@ -3237,7 +3484,7 @@ pub fn gen_std_trampoline_arm64(
         ; mov X(args as u32), x2
     );
 
-    let stack_args = sig.params().len().saturating_sub(7); //1st arg is ctx, not an actual arg
+    let stack_args = params.len().saturating_sub(7); //1st arg is ctx, not an actual arg
     let mut stack_offset = stack_args as u32 * 8;
     if stack_args > 0 {
         if stack_offset % 16 != 0 {
@@ -3250,7 +3497,7 @@ pub fn gen_std_trampoline_arm64(
     // Move arguments to their locations.
     // `callee_vmctx` is already in the first argument register, so no need to move.
     let mut caller_stack_offset: i32 = 0;
-    for (i, param) in sig.params().iter().enumerate() {
+    for (i, (param, offset)) in params.iter().enumerate() {
         let sz = match *param {
             Type::I32 | Type::F32 => Size::S32,
             Type::I64 | Type::F64 => Size::S64,
@@ -3266,7 +3513,7 @@ pub fn gen_std_trampoline_arm64(
                 a.emit_ldr(
                     sz,
                     Location::GPR(GPR::from_index(i + 1).unwrap()),
-                    Location::Memory(args, (i * 16) as i32),
+                    Location::Memory(args, *offset as i32),
                 )?;
             }
             _ => {
@@ -3291,7 +3538,7 @@ pub fn gen_std_trampoline_arm64(
                 a.emit_ldr(
                     sz,
                     Location::GPR(GPR::X16),
-                    Location::Memory(args, (i * 16) as i32),
+                    Location::Memory(args, *offset as i32),
                 )?;
                 a.emit_str(
                     sz,
@@ -3321,6 +3568,9 @@ pub fn gen_std_trampoline_arm64(
     // Write return value.
     if !sig.results().is_empty() {
         a.emit_str(Size::S64, Location::GPR(GPR::X0), Location::Memory(args, 0))?;
+        if sig.results()[0] == Type::V128 {
+            a.emit_str(Size::S64, Location::GPR(GPR::X1), Location::Memory(args, 8))?;
+        }
     }
 
     // Restore stack.
@@ -3382,13 +3632,13 @@ pub fn gen_std_dynamic_import_trampoline_arm64(
 
         let mut stack_param_count: usize = 0;
 
-        for (i, ty) in sig.params().iter().enumerate() {
-            let source_loc = match argalloc.next(*ty, calling_convention) {
+        for (ty, offset) in lower_v128_params(sig.params()) {
+            let source_loc = match argalloc.next(ty, calling_convention) {
                 Some(ARM64Register::GPR(gpr)) => Location::GPR(gpr),
                 Some(ARM64Register::NEON(neon)) => Location::SIMD(neon),
                 None => {
                     let sz = match calling_convention {
-                        CallingConvention::AppleAarch64 => match *ty {
+                        CallingConvention::AppleAarch64 => match ty {
                             Type::I32 | Type::F32 => Size::S32,
                             _ => {
                                 if stack_param_count & 7 != 0 {
@@ -3417,15 +3667,17 @@ pub fn gen_std_dynamic_import_trampoline_arm64(
             a.emit_str(
                 Size::S64,
                 source_loc,
-                Location::Memory(GPR::XzrSp, (i * 16) as _),
+                Location::Memory(GPR::XzrSp, offset as _),
             )?;
 
-            // Zero upper 64 bits.
-            a.emit_str(
-                Size::S64,
-                Location::GPR(GPR::XzrSp),                       // XZR here
-                Location::Memory(GPR::XzrSp, (i * 16 + 8) as _), // XSP here
-            )?;
+            // Zero upper 64 bits. V128 values fill both halves of their slot.
+            if sig.params()[offset / 16] != Type::V128 {
+                a.emit_str(
+                    Size::S64,
+                    Location::GPR(GPR::XzrSp), // XZR here
+                    Location::Memory(GPR::XzrSp, (offset + 8) as _), // XSP here
+                )?;
+            }
         }
     }
 
@@ -3456,6 +3708,13 @@ pub fn gen_std_dynamic_import_trampoline_arm64(
             Location::GPR(GPR::X0),
             Location::Memory(GPR::XzrSp, 0),
         )?;
+        if sig.results()[0] == Type::V128 {
+            a.emit_ldr(
+                Size::S64,
+                Location::GPR(GPR::X1),
+                Location::Memory(GPR::XzrSp, 8),
+            )?;
+        }
     }
 
     // Release values array.
@@ -3503,24 +3762,24 @@ pub fn gen_import_call_trampoline_arm64(
     calling_convention: CallingConvention,
 ) -> Result<CustomSection, CompileError> {
     let mut a = Assembler::new(0);
+    let params: Vec<Type> = lower_v128_params(sig.params())
+        .into_iter()
+        .map(|(ty, _)| ty)
+        .collect();
 
     // Singlepass internally treats all arguments as integers
     // For the standard System V calling convention requires
     //  floating point arguments to be passed in NEON registers.
     //  Translation is expensive, so only do it if needed.
-    if sig
-        .params()
-        .iter()
-        .any(|&x| x == Type::F32 || x == Type::F64)
-    {
+    if params.iter().any(|&x| x == Type::F32 || x == Type::F64) {
         #[allow(clippy::match_single_binding)]
         match calling_convention {
             _ => {
                 // Allocate stack space for arguments.
-                let stack_offset: i32 = if sig.params().len() > 7 {
+                let stack_offset: i32 = if params.len() > 7 {
                     7 * 8
                 } else {
-                    (sig.params().len() as i32) * 8
+                    (params.len() as i32) * 8
                 };
                 let stack_offset = if stack_offset & 15 != 0 {
                     stack_offset + 8
@@ -3559,7 +3818,7 @@ pub fn gen_import_call_trampoline_arm64(
                 let mut param_locations = vec![];
                 /* Clippy is wrong about using `i` to index `PARAM_REGS` here. */
                 #[allow(clippy::needless_range_loop)]
-                for i in 0..sig.params().len() {
+                for i in 0..params.len() {
                     let loc = match i {
                         0..=6 => {
                             let loc = Location::Memory(GPR::XzrSp, (i * 8) as i32);
@@ -3575,7 +3834,7 @@ pub fn gen_import_call_trampoline_arm64(
                 let mut caller_stack_offset: i32 = 0;
                 let mut argalloc = ArgumentRegisterAllocator::default();
                 argalloc.next(Type::I64, calling_convention).unwrap(); // skip VMContext
-                for (i, ty) in sig.params().iter().enumerate() {
+                for (i, ty) in params.iter().enumerate() {
                     let prev_loc = param_locations[i];
                     let targ = match argalloc.next(*ty, calling_convention) {
                         Some(ARM64Register::GPR(gpr)) => Location::GPR(gpr),
@@ -61,6 +61,140 @@ pub enum Precision {
     Double,
 }
 
+macro_rules! sse_packed_ops {
+    ($($name:ident => $ins:ident,)*) => {
+        /// Packed SSE instructions taking two XMM registers, used to lower
+        /// `v128` operations.
+        #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+        pub enum SsePacked {
+            $($name,)*
+        }
+
+        fn emit_sse_packed_op(a: &mut AssemblerX64, op: SsePacked, src: XMM, dst: XMM) {
+            match op {
+                $(SsePacked::$name => dynasm!(a ; $ins Rx(dst as u8), Rx(src as u8)),)*
+            }
+        }
+    };
+}
+
+sse_packed_ops! {
+    Movdqa => movdqa,
+    Pand => pand,
+    Pandn => pandn,
+    Por => por,
+    Pxor => pxor,
+    Ptest => ptest,
+    Paddb => paddb,
+    Paddw => paddw,
+    Paddd => paddd,
+    Paddq => paddq,
+    Paddsb => paddsb,
+    Paddsw => paddsw,
+    Paddusb => paddusb,
+    Paddusw => paddusw,
+    Psubb => psubb,
+    Psubw => psubw,
+    Psubd => psubd,
+    Psubq => psubq,
+    Psubsb => psubsb,
+    Psubsw => psubsw,
+    Psubusb => psubusb,
+    Psubusw => psubusw,
+    Pminsb => pminsb,
+    Pminsw => pminsw,
+    Pminsd => pminsd,
+    Pminub => pminub,
+    Pminuw => pminuw,
+    Pminud => pminud,
+    Pmaxsb => pmaxsb,
+    Pmaxsw => pmaxsw,
+    Pmaxsd => pmaxsd,
+    Pmaxub => pmaxub,
+    Pmaxuw => pmaxuw,
+    Pmaxud => pmaxud,
+    Pavgb => pavgb,
+    Pavgw => pavgw,
+    Pmullw => pmullw,
+    Pmulld => pmulld,
+    Pmaddwd => pmaddwd,
+    Pmuludq => pmuludq,
+    Pcmpeqb => pcmpeqb,
+    Pcmpeqw => pcmpeqw,
+    Pcmpeqd => pcmpeqd,
+    Pcmpeqq => pcmpeqq,
+    Pcmpgtb => pcmpgtb,
+    Pcmpgtw => pcmpgtw,
+    Pcmpgtd => pcmpgtd,
+    Pcmpgtq => pcmpgtq,
+    Pabsb => pabsb,
+    Pabsw => pabsw,
+    Pabsd => pabsd,
+    Psllw => psllw,
+    Pslld => pslld,
+    Psllq => psllq,
+    Psrlw => psrlw,
+    Psrld => psrld,
+    Psrlq => psrlq,
+    Psraw => psraw,
+    Psrad => psrad,
+    Pshufb => pshufb,
+    Packsswb => packsswb,
+    Packuswb => packuswb,
+    Packssdw => packssdw,
+    Packusdw => packusdw,
+    Pmovsxbw => pmovsxbw,
+    Pmovsxwd => pmovsxwd,
+    Pmovsxdq => pmovsxdq,
+    Pmovzxbw => pmovzxbw,
+    Pmovzxwd => pmovzxwd,
+    Pmovzxdq => pmovzxdq,
+    Punpcklbw => punpcklbw,
+    Punpckhbw => punpckhbw,
+    Punpcklqdq => punpcklqdq,
+    Unpcklps => unpcklps,
+    Andps => andps,
+    Andpd => andpd,
+    Xorps => xorps,
+    Xorpd => xorpd,
+    Addps => addps,
+    Addpd => addpd,
+    Subps => subps,
+    Subpd => subpd,
+    Mulps => mulps,
+    Mulpd => mulpd,
+    Divps => divps,
+    Divpd => divpd,
+    Minps => minps,
+    Minpd => minpd,
+    Maxps => maxps,
+    Maxpd => maxpd,
+    Sqrtps => sqrtps,
+    Sqrtpd => sqrtpd,
+    Movsd => movsd,
+    Unpcklpd => unpcklpd,
+    Cvtdq2ps => cvtdq2ps,
+    Cvtdq2pd => cvtdq2pd,
+    Cvtps2pd => cvtps2pd,
+    Cvtpd2ps => cvtpd2ps,
+    Cvttps2dq => cvttps2dq,
+    Cvttpd2dq => cvttpd2dq,
+}
+
+/// Packed SSE instructions taking two XMM registers and an 8 bits immediate.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SsePackedImm8 {
+    Pshufd,
+    Pshuflw,
+    Roundps,
+    Roundpd,
+    Cmpps,
+    Cmppd,
+    Insertps,
+    Pblendw,
+    Shufps,
+}
+
 pub trait EmitterX64 {
     fn get_simd_arch(&self) -> Option<&CpuFeature>;
     fn get_label(&mut self) -> Label;
@@ -342,6 +476,18 @@ pub trait EmitterX64 {
         dst: XMM,
     ) -> Result<(), CompileError>;
 
+    fn emit_sse_packed(&mut self, op: SsePacked, src: XMM, dst: XMM) -> Result<(), CompileError>;
+    fn emit_sse_packed_imm8(
+        &mut self,
+        op: SsePackedImm8,
+        imm: u8,
+        src: XMM,
+        dst: XMM,
+    ) -> Result<(), CompileError>;
+    fn emit_pinsr(&mut self, sz: Size, src: GPR, lane: u8, dst: XMM) -> Result<(), CompileError>;
+    fn emit_pextr(&mut self, sz: Size, src: XMM, lane: u8, dst: GPR) -> Result<(), CompileError>;
+    fn emit_movmsk(&mut self, sz: Size, src: XMM, dst: GPR) -> Result<(), CompileError>;
+
     fn emit_test_gpr_64(&mut self, reg: GPR) -> Result<(), CompileError>;
 
     fn emit_ud2(&mut self) -> Result<(), CompileError>;
@@ -2308,6 +2454,65 @@ impl EmitterX64 for AssemblerX64 {
         Ok(())
     }
 
+    fn emit_sse_packed(&mut self, op: SsePacked, src: XMM, dst: XMM) -> Result<(), CompileError> {
+        emit_sse_packed_op(self, op, src, dst);
+        Ok(())
+    }
+
+    fn emit_sse_packed_imm8(
+        &mut self,
+        op: SsePackedImm8,
+        imm: u8,
+        src: XMM,
+        dst: XMM,
+    ) -> Result<(), CompileError> {
+        let imm = imm as i8;
+        match op {
+            SsePackedImm8::Pshufd => dynasm!(self ; pshufd Rx(dst as u8), Rx(src as u8), imm),
+            SsePackedImm8::Pshuflw => dynasm!(self ; pshuflw Rx(dst as u8), Rx(src as u8), imm),
+            SsePackedImm8::Roundps => dynasm!(self ; roundps Rx(dst as u8), Rx(src as u8), imm),
+            SsePackedImm8::Roundpd => dynasm!(self ; roundpd Rx(dst as u8), Rx(src as u8), imm),
+            SsePackedImm8::Cmpps => dynasm!(self ; cmpps Rx(dst as u8), Rx(src as u8), imm),
+            SsePackedImm8::Cmppd => dynasm!(self ; cmppd Rx(dst as u8), Rx(src as u8), imm),
+            SsePackedImm8::Insertps => dynasm!(self ; insertps Rx(dst as u8), Rx(src as u8), imm),
+            SsePackedImm8::Pblendw => dynasm!(self ; pblendw Rx(dst as u8), Rx(src as u8), imm),
+            SsePackedImm8::Shufps => dynasm!(self ; shufps Rx(dst as u8), Rx(src as u8), imm),
+        }
+        Ok(())
+    }
+
+    fn emit_pinsr(&mut self, sz: Size, src: GPR, lane: u8, dst: XMM) -> Result<(), CompileError> {
+        let lane = lane as i8;
+        match sz {
+            Size::S8 => dynasm!(self ; pinsrb Rx(dst as u8), Rd(src as u8), lane),
+            Size::S16 => dynasm!(self ; pinsrw Rx(dst as u8), Rd(src as u8), lane),
+            Size::S32 => dynasm!(self ; pinsrd Rx(dst as u8), Rd(src as u8), lane),
+            Size::S64 => dynasm!(self ; pinsrq Rx(dst as u8), Rq(src as u8), lane),
+        }
+        Ok(())
+    }
+
+    fn emit_pextr(&mut self, sz: Size, src: XMM, lane: u8, dst: GPR) -> Result<(), CompileError> {
+        let lane = lane as i8;
+        match sz {
+            Size::S8 => dynasm!(self ; pextrb Rd(dst as u8), Rx(src as u8), lane),
+            Size::S16 => dynasm!(self ; pextrw Rd(dst as u8), Rx(src as u8), lane),
+            Size::S32 => dynasm!(self ; pextrd Rd(dst as u8), Rx(src as u8), lane),
+            Size::S64 => dynasm!(self ; pextrq Rq(dst as u8), Rx(src as u8), lane),
+        }
+        Ok(())
+    }
+
+    fn emit_movmsk(&mut self, sz: Size, src: XMM, dst: GPR) -> Result<(), CompileError> {
+        match sz {
+            Size::S8 => dynasm!(self ; pmovmskb Rd(dst as u8), Rx(src as u8)),
+            Size::S32 => dynasm!(self ; movmskps Rd(dst as u8), Rx(src as u8)),
+            Size::S64 => dynasm!(self ; movmskpd Rd(dst as u8), Rx(src as u8)),
+            _ => codegen_error!("singlepass can't emit MOVMSK {:?} {:?} {:?}", sz, src, dst),
+        }
+        Ok(())
+    }
+
     fn emit_ucomiss(&mut self, src: XMMOrMemory, dst: XMM) -> Result<(), CompileError> {
         match src {
             XMMOrMemory::XMM(x) => dynasm!(self ; ucomiss Rx(dst as u8), Rx(x as u8)),
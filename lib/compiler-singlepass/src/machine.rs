@@ -10,8 +10,8 @@ pub use wasmer_compiler::wasmparser::MemArg;
 use wasmer_compiler::wasmparser::ValType as WpType;
 use wasmer_types::{
     Architecture, CallingConvention, CompileError, CustomSection, FunctionBody, FunctionIndex,
-    FunctionType, InstructionAddressMap, Relocation, RelocationTarget, Target, TrapCode,
-    TrapInformation, VMOffsets,
+    FunctionType, InstructionAddressMap, Relocation, RelocationTarget, SimdOp, Target, TrapCode,
+    TrapInformation, Type, VMOffsets,
 };
 
 pub type Label = DynamicLabel;
@@ -277,6 +277,10 @@ pub trait Machine {
     fn emit_call_register(&mut self, register: Self::GPR) -> Result<(), CompileError>;
     /// Emit a call to a label
     fn emit_call_label(&mut self, label: Label) -> Result<(), CompileError>;
+    /// get the gpr holding the target of an indirect tail call, left untouched by the epilog
+    fn get_gpr_for_tail_call(&self) -> Self::GPR;
+    /// Emit a jump to the address in register
+    fn emit_jmp_register(&mut self, register: Self::GPR) -> Result<(), CompileError>;
     /// Does an trampoline is neededfor indirect call
     fn arch_requires_indirect_call_trampoline(&self) -> bool;
     /// indirect call with trampoline
@@ -293,6 +297,8 @@ pub trait Machine {
     fn get_gpr_for_ret(&self) -> Self::GPR;
     /// get the simd for the return of float/double values
     fn get_simd_for_ret(&self) -> Self::SIMD;
+    /// get the gpr for the high half of returned v128 values
+    fn get_gpr_for_ret_hi(&self) -> Self::GPR;
 
     /// Emit a debug breakpoint
     fn emit_debug_breakpoint(&mut self) -> Result<(), CompileError>;
@@ -1144,6 +1150,12 @@ pub trait Machine {
         calling_convention: CallingConvention,
         reloc_target: RelocationTarget,
     ) -> Result<Vec<Relocation>, CompileError>;
+    /// emit a jump to a function, using appropriate relocation
+    fn emit_jmp_with_reloc(
+        &mut self,
+        calling_convention: CallingConvention,
+        reloc_target: RelocationTarget,
+    ) -> Result<Vec<Relocation>, CompileError>;
     /// Add with location directly from the stack
     fn emit_binop_add64(
         &mut self,
@@ -2372,6 +2384,35 @@ pub trait Machine {
         ret: Location<Self::GPR, Self::SIMD>,
     ) -> Result<(), CompileError>;
 
+    /// Load a v128 value, given as its low and high 64bits halves, in a SIMD register
+    fn v128_from_halves(
+        &mut self,
+        lo: Location<Self::GPR, Self::SIMD>,
+        hi: Location<Self::GPR, Self::SIMD>,
+        dst: Self::SIMD,
+    ) -> Result<(), CompileError>;
+    /// Split the v128 value of a SIMD register in its low and high 64bits halves
+    fn v128_to_halves(
+        &mut self,
+        src: Self::SIMD,
+        lo: Location<Self::GPR, Self::SIMD>,
+        hi: Location<Self::GPR, Self::SIMD>,
+    ) -> Result<(), CompileError>;
+    /// Can `emit_simd_op` emit this operation, or does it need the runtime fallback?
+    fn simd_op_supported(&self, op: SimdOp) -> bool;
+    /// Emit a SIMD operation on operands held in temporary SIMD registers.
+    ///
+    /// The result is written to `regs[0]`. Scalar operands (splat value, replaced
+    /// lane, shift count already masked to the lane width) are in the low bits of
+    /// their register, and so are scalar results. `lanes` holds the lane index of
+    /// lane accessors, or the 16 lane indices of `i8x16.shuffle`.
+    fn emit_simd_op(
+        &mut self,
+        op: SimdOp,
+        lanes: &[u8],
+        regs: &[Self::SIMD],
+    ) -> Result<(), CompileError>;
+
     /// Standard function Trampoline generation
     fn gen_std_trampoline(
         &self,
@@ -2464,6 +2505,23 @@ pub fn gen_import_call_trampoline(
     }
 }
 
+/// Singlepass passes a V128 value as two I64 values, its low and high halves.
+///
+/// Returns the lowered parameter types, along with the offset of each of them
+/// in the 16 bytes slots of a `values_vec`.
+pub fn lower_v128_params(params: &[Type]) -> Vec<(Type, usize)> {
+    let mut lowered = Vec::with_capacity(params.len());
+    for (i, ty) in params.iter().enumerate() {
+        if *ty == Type::V128 {
+            lowered.push((Type::I64, i * 16));
+            lowered.push((Type::I64, i * 16 + 8));
+        } else {
+            lowered.push((*ty, i * 16));
+        }
+    }
+    lowered
+}
+
 // Constants for the bounds of truncation operations. These are the least or
 // greatest exact floats in either f32 or f64 representation less-than (for
 // least) or greater-than (for greatest) the i32 or i64 or u32 or u64
@@ -5,8 +5,8 @@ use gimli::{write::CallFrameInstruction, AArch64};
 use wasmer_compiler::wasmparser::ValType as WpType;
 use wasmer_types::{
     CallingConvention, CompileError, CustomSection, FunctionBody, FunctionIndex, FunctionType,
-    InstructionAddressMap, Relocation, RelocationKind, RelocationTarget, SourceLoc, TrapCode,
-    TrapInformation, VMOffsets,
+    InstructionAddressMap, Relocation, RelocationKind, RelocationTarget, SimdOp, SourceLoc,
+    TrapCode, TrapInformation, VMOffsets,
 };
 
 use crate::arm64_decl::new_machine_state;
@@ -2265,9 +2265,8 @@ impl Machine for MachineARM64 {
                 Location::GPR(GPR::XzrSp),
             )?;
         } else {
-            let tmp = self.acquire_temp_gpr().ok_or_else(|| {
-                CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
-            })?;
+            // Don't use a temp gpr: they may hold the arguments of a tail call.
+            let tmp = GPR::X17;
             self.assembler
                 .emit_mov_imm(Location::GPR(tmp), real_delta as u64)?;
             self.assembler.emit_sub(
@@ -2276,7 +2275,6 @@ impl Machine for MachineARM64 {
                 Location::GPR(tmp),
                 Location::GPR(GPR::XzrSp),
             )?;
-            self.release_gpr(tmp);
         }
         Ok(())
     }
@@ -2440,12 +2438,21 @@ impl Machine for MachineARM64 {
     fn emit_call_label(&mut self, label: Label) -> Result<(), CompileError> {
         self.assembler.emit_call_label(label)
     }
+    fn get_gpr_for_tail_call(&self) -> GPR {
+        GPR::X16
+    }
+    fn emit_jmp_register(&mut self, reg: GPR) -> Result<(), CompileError> {
+        self.assembler.emit_b_register(reg)
+    }
     fn get_gpr_for_ret(&self) -> GPR {
         GPR::X0
     }
     fn get_simd_for_ret(&self) -> NEON {
         NEON::V0
     }
+    fn get_gpr_for_ret_hi(&self) -> GPR {
+        GPR::X1
+    }
 
     fn arch_requires_indirect_call_trampoline(&self) -> bool {
         self.assembler.arch_requires_indirect_call_trampoline()
@@ -4851,6 +4858,26 @@ impl Machine for MachineARM64 {
         Ok(relocations)
     }
 
+    fn emit_jmp_with_reloc(
+        &mut self,
+        _calling_convention: CallingConvention,
+        reloc_target: RelocationTarget,
+    ) -> Result<Vec<Relocation>, CompileError> {
+        let mut relocations = vec![];
+        let next = self.get_label();
+        let reloc_at = self.assembler.get_offset().0;
+        self.emit_label(next)?; // this is to be sure the current imm26 value is 0
+        self.assembler.emit_b_label(next)?;
+        // `b` encodes its offset like `bl`, so the same relocation applies.
+        relocations.push(Relocation {
+            kind: RelocationKind::Arm64Call,
+            reloc_target,
+            offset: reloc_at as u32,
+            addend: 0,
+        });
+        Ok(relocations)
+    }
+
     fn emit_binop_add64(
         &mut self,
         loc_a: Location,
@@ -8400,6 +8427,144 @@ impl Machine for MachineARM64 {
         )
     }
 
+    fn v128_from_halves(
+        &mut self,
+        lo: Location,
+        hi: Location,
+        dst: NEON,
+    ) -> Result<(), CompileError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| {
+            CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
+        })?;
+        for (lane, half) in [(0, lo), (1, hi)] {
+            let src = match half {
+                Location::GPR(x) => x,
+                _ => {
+                    self.move_location(Size::S64, half, Location::GPR(tmp))?;
+                    tmp
+                }
+            };
+            self.assembler
+                .emit_neon_mov_to_lane(Size::S64, src, lane, dst)?;
+        }
+        self.release_gpr(tmp);
+        Ok(())
+    }
+    fn v128_to_halves(
+        &mut self,
+        src: NEON,
+        lo: Location,
+        hi: Location,
+    ) -> Result<(), CompileError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| {
+            CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
+        })?;
+        for (lane, half) in [(0, lo), (1, hi)] {
+            match half {
+                Location::GPR(x) => {
+                    self.assembler
+                        .emit_neon_mov_from_lane(Size::S64, false, src, lane, x)?;
+                }
+                _ => {
+                    self.assembler
+                        .emit_neon_mov_from_lane(Size::S64, false, src, lane, tmp)?;
+                    self.move_location(Size::S64, Location::GPR(tmp), half)?;
+                }
+            }
+        }
+        self.release_gpr(tmp);
+        Ok(())
+    }
+    fn simd_op_supported(&self, op: SimdOp) -> bool {
+        use SimdOp::*;
+        matches!(
+            op,
+            I8x16Splat
+                | I16x8Splat
+                | I32x4Splat
+                | I64x2Splat
+                | F32x4Splat
+                | F64x2Splat
+                | I8x16ExtractLaneS
+                | I8x16ExtractLaneU
+                | I16x8ExtractLaneS
+                | I16x8ExtractLaneU
+                | I32x4ExtractLane
+                | I64x2ExtractLane
+                | F32x4ExtractLane
+                | F64x2ExtractLane
+                | I8x16ReplaceLane
+                | I16x8ReplaceLane
+                | I32x4ReplaceLane
+                | I64x2ReplaceLane
+                | F32x4ReplaceLane
+                | F64x2ReplaceLane
+                | V128Not
+                | V128Bitselect
+        ) || neon_simd_op(op).is_some()
+    }
+    fn emit_simd_op(
+        &mut self,
+        op: SimdOp,
+        lanes: &[u8],
+        regs: &[NEON],
+    ) -> Result<(), CompileError> {
+        use SimdOp::*;
+        let (a, b, c) = (regs[0], regs[1], regs[2]);
+        let lane = lanes.first().copied().unwrap_or(0) as u32;
+        match op {
+            I8x16Splat => self.assembler.emit_neon_dup(Size::S8, a, a)?,
+            I16x8Splat => self.assembler.emit_neon_dup(Size::S16, a, a)?,
+            I32x4Splat | F32x4Splat => self.assembler.emit_neon_dup(Size::S32, a, a)?,
+            I64x2Splat | F64x2Splat => self.assembler.emit_neon_dup(Size::S64, a, a)?,
+            I8x16ExtractLaneS | I8x16ExtractLaneU | I16x8ExtractLaneS | I16x8ExtractLaneU => {
+                let sz = match op {
+                    I8x16ExtractLaneS | I8x16ExtractLaneU => Size::S8,
+                    _ => Size::S16,
+                };
+                let signed = matches!(op, I8x16ExtractLaneS | I16x8ExtractLaneS);
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| {
+                    CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
+                })?;
+                self.assembler
+                    .emit_neon_mov_from_lane(sz, signed, a, lane, tmp)?;
+                self.assembler
+                    .emit_mov(Size::S32, Location::GPR(tmp), Location::SIMD(a))?;
+                self.release_gpr(tmp);
+            }
+            I32x4ExtractLane | F32x4ExtractLane => {
+                self.assembler.emit_neon_ins(Size::S32, a, lane, a, 0)?
+            }
+            I64x2ExtractLane | F64x2ExtractLane => {
+                self.assembler.emit_neon_ins(Size::S64, a, lane, a, 0)?
+            }
+            I8x16ReplaceLane => self.assembler.emit_neon_ins(Size::S8, b, 0, a, lane)?,
+            I16x8ReplaceLane => self.assembler.emit_neon_ins(Size::S16, b, 0, a, lane)?,
+            I32x4ReplaceLane | F32x4ReplaceLane => {
+                self.assembler.emit_neon_ins(Size::S32, b, 0, a, lane)?
+            }
+            I64x2ReplaceLane | F64x2ReplaceLane => {
+                self.assembler.emit_neon_ins(Size::S64, b, 0, a, lane)?
+            }
+            V128Not => self.assembler.emit_neon_vector(NeonVector::Mvn, a, a, a)?,
+            V128Bitselect => {
+                self.assembler.emit_neon_vector(NeonVector::Bsl, a, b, c)?;
+                self.assembler.emit_neon_vector(NeonVector::Orr, c, c, a)?;
+            }
+            _ => match neon_simd_op(op) {
+                Some((ins, swap, invert)) => {
+                    let (src1, src2) = if swap { (b, a) } else { (a, b) };
+                    self.assembler.emit_neon_vector(ins, src1, src2, a)?;
+                    if invert {
+                        self.assembler.emit_neon_vector(NeonVector::Mvn, a, a, a)?;
+                    }
+                }
+                None => codegen_error!("singlepass can't emit SIMD operation {}", op),
+            },
+        }
+        Ok(())
+    }
+
     fn gen_std_trampoline(
         &self,
         sig: &FunctionType,
@@ -8487,6 +8652,135 @@ impl Machine for MachineARM64 {
     }
 }
 
+/// The NEON instruction lowering a lane-wise SIMD operation, if any, along with
+/// whether its operands must be swapped and its result inverted.
+fn neon_simd_op(op: SimdOp) -> Option<(NeonVector, bool, bool)> {
+    use NeonVector as N;
+    use SimdOp::*;
+    let lowered = match op {
+        V128And => (N::And, false, false),
+        V128AndNot => (N::Bic, false, false),
+        V128Or => (N::Orr, false, false),
+        V128Xor => (N::Eor, false, false),
+        I8x16Eq => (N::CmeqB, false, false),
+        I16x8Eq => (N::CmeqH, false, false),
+        I32x4Eq => (N::CmeqS, false, false),
+        I64x2Eq => (N::CmeqD, false, false),
+        I8x16Ne => (N::CmeqB, false, true),
+        I16x8Ne => (N::CmeqH, false, true),
+        I32x4Ne => (N::CmeqS, false, true),
+        I64x2Ne => (N::CmeqD, false, true),
+        I8x16GtS => (N::CmgtB, false, false),
+        I16x8GtS => (N::CmgtH, false, false),
+        I32x4GtS => (N::CmgtS, false, false),
+        I64x2GtS => (N::CmgtD, false, false),
+        I8x16LtS => (N::CmgtB, true, false),
+        I16x8LtS => (N::CmgtH, true, false),
+        I32x4LtS => (N::CmgtS, true, false),
+        I64x2LtS => (N::CmgtD, true, false),
+        I8x16GeS => (N::CmgeB, false, false),
+        I16x8GeS => (N::CmgeH, false, false),
+        I32x4GeS => (N::CmgeS, false, false),
+        I64x2GeS => (N::CmgeD, false, false),
+        I8x16LeS => (N::CmgeB, true, false),
+        I16x8LeS => (N::CmgeH, true, false),
+        I32x4LeS => (N::CmgeS, true, false),
+        I64x2LeS => (N::CmgeD, true, false),
+        I8x16GtU => (N::CmhiB, false, false),
+        I16x8GtU => (N::CmhiH, false, false),
+        I32x4GtU => (N::CmhiS, false, false),
+        I8x16LtU => (N::CmhiB, true, false),
+        I16x8LtU => (N::CmhiH, true, false),
+        I32x4LtU => (N::CmhiS, true, false),
+        I8x16GeU => (N::CmhsB, false, false),
+        I16x8GeU => (N::CmhsH, false, false),
+        I32x4GeU => (N::CmhsS, false, false),
+        I8x16LeU => (N::CmhsB, true, false),
+        I16x8LeU => (N::CmhsH, true, false),
+        I32x4LeU => (N::CmhsS, true, false),
+        F32x4Eq => (N::FcmeqS, false, false),
+        F64x2Eq => (N::FcmeqD, false, false),
+        F32x4Ne => (N::FcmeqS, false, true),
+        F64x2Ne => (N::FcmeqD, false, true),
+        F32x4Gt => (N::FcmgtS, false, false),
+        F64x2Gt => (N::FcmgtD, false, false),
+        F32x4Lt => (N::FcmgtS, true, false),
+        F64x2Lt => (N::FcmgtD, true, false),
+        F32x4Ge => (N::FcmgeS, false, false),
+        F64x2Ge => (N::FcmgeD, false, false),
+        F32x4Le => (N::FcmgeS, true, false),
+        F64x2Le => (N::FcmgeD, true, false),
+        I8x16Add => (N::AddB, false, false),
+        I16x8Add => (N::AddH, false, false),
+        I32x4Add => (N::AddS, false, false),
+        I64x2Add => (N::AddD, false, false),
+        I8x16Sub => (N::SubB, false, false),
+        I16x8Sub => (N::SubH, false, false),
+        I32x4Sub => (N::SubS, false, false),
+        I64x2Sub => (N::SubD, false, false),
+        I8x16AddSatS => (N::SqaddB, false, false),
+        I16x8AddSatS => (N::SqaddH, false, false),
+        I8x16AddSatU => (N::UqaddB, false, false),
+        I16x8AddSatU => (N::UqaddH, false, false),
+        I8x16SubSatS => (N::SqsubB, false, false),
+        I16x8SubSatS => (N::SqsubH, false, false),
+        I8x16SubSatU => (N::UqsubB, false, false),
+        I16x8SubSatU => (N::UqsubH, false, false),
+        I8x16MinS => (N::SminB, false, false),
+        I16x8MinS => (N::SminH, false, false),
+        I32x4MinS => (N::SminS, false, false),
+        I8x16MinU => (N::UminB, false, false),
+        I16x8MinU => (N::UminH, false, false),
+        I32x4MinU => (N::UminS, false, false),
+        I8x16MaxS => (N::SmaxB, false, false),
+        I16x8MaxS => (N::SmaxH, false, false),
+        I32x4MaxS => (N::SmaxS, false, false),
+        I8x16MaxU => (N::UmaxB, false, false),
+        I16x8MaxU => (N::UmaxH, false, false),
+        I32x4MaxU => (N::UmaxS, false, false),
+        I8x16AvgrU => (N::UrhaddB, false, false),
+        I16x8AvgrU => (N::UrhaddH, false, false),
+        I16x8Mul => (N::MulH, false, false),
+        I32x4Mul => (N::MulS, false, false),
+        I8x16Abs => (N::AbsB, false, false),
+        I16x8Abs => (N::AbsH, false, false),
+        I32x4Abs => (N::AbsS, false, false),
+        I64x2Abs => (N::AbsD, false, false),
+        I8x16Neg => (N::NegB, false, false),
+        I16x8Neg => (N::NegH, false, false),
+        I32x4Neg => (N::NegS, false, false),
+        I64x2Neg => (N::NegD, false, false),
+        F32x4Add => (N::FaddS, false, false),
+        F64x2Add => (N::FaddD, false, false),
+        F32x4Sub => (N::FsubS, false, false),
+        F64x2Sub => (N::FsubD, false, false),
+        F32x4Mul => (N::FmulS, false, false),
+        F64x2Mul => (N::FmulD, false, false),
+        F32x4Div => (N::FdivS, false, false),
+        F64x2Div => (N::FdivD, false, false),
+        F32x4Min => (N::FminS, false, false),
+        F64x2Min => (N::FminD, false, false),
+        F32x4Max => (N::FmaxS, false, false),
+        F64x2Max => (N::FmaxD, false, false),
+        F32x4Abs => (N::FabsS, false, false),
+        F64x2Abs => (N::FabsD, false, false),
+        F32x4Neg => (N::FnegS, false, false),
+        F64x2Neg => (N::FnegD, false, false),
+        F32x4Sqrt => (N::FsqrtS, false, false),
+        F64x2Sqrt => (N::FsqrtD, false, false),
+        F32x4Nearest => (N::FrintnS, false, false),
+        F64x2Nearest => (N::FrintnD, false, false),
+        F32x4Floor => (N::FrintmS, false, false),
+        F64x2Floor => (N::FrintmD, false, false),
+        F32x4Ceil => (N::FrintpS, false, false),
+        F64x2Ceil => (N::FrintpD, false, false),
+        F32x4Trunc => (N::FrintzS, false, false),
+        F64x2Trunc => (N::FrintzD, false, false),
+        _ => return None,
+    };
+    Some(lowered)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
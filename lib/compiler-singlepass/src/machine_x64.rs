@@ -16,7 +16,7 @@ use std::ops::{Deref, DerefMut};
 use wasmer_compiler::wasmparser::ValType as WpType;
 use wasmer_types::{
     CallingConvention, CompileError, CpuFeature, CustomSection, CustomSectionProtection,
-    Relocation, RelocationKind, RelocationTarget, SectionBody, Target,
+    Relocation, RelocationKind, RelocationTarget, SectionBody, SimdOp, Target,
 };
 use wasmer_types::{FunctionBody, InstructionAddressMap, SourceLoc, TrapInformation};
 use wasmer_types::{FunctionIndex, FunctionType, TrapCode, Type, VMOffsets};
@@ -1924,6 +1924,225 @@ impl MachineX86_64 {
         let v = trap as u8;
         self.assembler.emit_ud1_payload(v)
     }
+
+    /// Load `value` in both 64bits halves of `dst`.
+    fn emit_simd_splat_const(&mut self, value: u64, dst: XMM) -> Result<(), CompileError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| {
+            CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
+        })?;
+        self.assembler
+            .emit_mov(Size::S64, Location::Imm64(value), Location::GPR(tmp))?;
+        self.assembler
+            .emit_mov(Size::S64, Location::GPR(tmp), Location::SIMD(dst))?;
+        self.assembler
+            .emit_sse_packed(SsePacked::Punpcklqdq, dst, dst)?;
+        self.release_gpr(tmp);
+        Ok(())
+    }
+    /// Load the 128 bits value made of `lo` and `hi` in `dst`.
+    fn emit_simd_const(&mut self, lo: u64, hi: u64, dst: XMM) -> Result<(), CompileError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| {
+            CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
+        })?;
+        self.assembler
+            .emit_mov(Size::S64, Location::Imm64(lo), Location::GPR(tmp))?;
+        self.assembler
+            .emit_mov(Size::S64, Location::GPR(tmp), Location::SIMD(dst))?;
+        self.assembler
+            .emit_mov(Size::S64, Location::Imm64(hi), Location::GPR(tmp))?;
+        self.assembler.emit_pinsr(Size::S64, tmp, 1, dst)?;
+        self.release_gpr(tmp);
+        Ok(())
+    }
+    /// Add `value` to the shift count in the low bits of `count`.
+    fn emit_simd_shift_count_add(&mut self, value: u32, count: XMM) -> Result<(), CompileError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| {
+            CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
+        })?;
+        self.assembler
+            .emit_mov(Size::S64, Location::SIMD(count), Location::GPR(tmp))?;
+        self.assembler
+            .emit_add(Size::S64, Location::Imm32(value), Location::GPR(tmp))?;
+        self.assembler
+            .emit_mov(Size::S64, Location::GPR(tmp), Location::SIMD(count))?;
+        self.release_gpr(tmp);
+        Ok(())
+    }
+    /// `dst = op(dst, src)` on the bytes of `dst`, for a 16 bits `shift` by `count`, a byte
+    /// shift right. The bytes are duplicated in 16 bits lanes shifted by 8 more bits.
+    fn emit_simd_i8x16_shr(
+        &mut self,
+        shift: SsePacked,
+        pack: SsePacked,
+        count: XMM,
+        dst: XMM,
+        tmp: XMM,
+    ) -> Result<(), CompileError> {
+        self.emit_simd_shift_count_add(8, count)?;
+        self.assembler
+            .emit_sse_packed(SsePacked::Movdqa, dst, tmp)?;
+        self.assembler
+            .emit_sse_packed(SsePacked::Punpckhbw, tmp, tmp)?;
+        self.assembler
+            .emit_sse_packed(SsePacked::Punpcklbw, dst, dst)?;
+        self.assembler.emit_sse_packed(shift, count, tmp)?;
+        self.assembler.emit_sse_packed(shift, count, dst)?;
+        self.assembler.emit_sse_packed(pack, tmp, dst)
+    }
+    /// `dst = min_or_max(dst, src)` with the Wasm semantics, where NaNs are propagated and
+    /// `-0 < +0`. x86 returns `src` when the operands are NaNs or zeros, so the operation is
+    /// done in both orders and the results merged. `nan_payload` is the mask of the payload
+    /// bits cleared from the resulting NaNs.
+    #[allow(clippy::too_many_arguments)]
+    fn emit_simd_float_min_max(
+        &mut self,
+        min_or_max: SsePacked,
+        cmp: SsePackedImm8,
+        sub: Option<SsePacked>,
+        nan_payload: u64,
+        src: XMM,
+        dst: XMM,
+        tmp: XMM,
+    ) -> Result<(), CompileError> {
+        self.assembler
+            .emit_sse_packed(SsePacked::Movdqa, src, tmp)?;
+        self.assembler.emit_sse_packed(min_or_max, dst, tmp)?;
+        self.assembler.emit_sse_packed(min_or_max, src, dst)?;
+        match sub {
+            // The results of `max` only differ on NaNs and zeros: the difference propagates
+            // the NaNs and the sign of `+0`.
+            Some(sub) => {
+                self.assembler.emit_sse_packed(SsePacked::Pxor, tmp, dst)?;
+                self.assembler.emit_sse_packed(SsePacked::Por, dst, tmp)?;
+                self.assembler.emit_sse_packed(sub, dst, tmp)?;
+            }
+            // The union of the results of `min` propagates the NaNs and the sign of `-0`.
+            None => {
+                self.assembler.emit_sse_packed(SsePacked::Por, dst, tmp)?;
+            }
+        }
+        // Canonicalize the NaNs.
+        self.assembler.emit_sse_packed_imm8(cmp, 3, tmp, dst)?;
+        self.assembler.emit_sse_packed(SsePacked::Por, dst, tmp)?;
+        self.emit_simd_splat_const(nan_payload, src)?;
+        self.assembler.emit_sse_packed(SsePacked::Pand, src, dst)?;
+        self.assembler.emit_sse_packed(SsePacked::Pandn, tmp, dst)
+    }
+    /// `dst = !dst`, clobbering `tmp`.
+    fn emit_simd_not(&mut self, dst: XMM, tmp: XMM) -> Result<(), CompileError> {
+        self.assembler
+            .emit_sse_packed(SsePacked::Pcmpeqd, tmp, tmp)?;
+        self.assembler.emit_sse_packed(SsePacked::Pxor, tmp, dst)
+    }
+    /// `dst = op(src, dst)`, for instructions whose operands need to be swapped.
+    fn emit_simd_reversed(
+        &mut self,
+        op: SsePacked,
+        src: XMM,
+        dst: XMM,
+        tmp: XMM,
+    ) -> Result<(), CompileError> {
+        self.assembler
+            .emit_sse_packed(SsePacked::Movdqa, src, tmp)?;
+        self.assembler.emit_sse_packed(op, dst, tmp)?;
+        self.assembler.emit_sse_packed(SsePacked::Movdqa, tmp, dst)
+    }
+    /// Same as `emit_simd_reversed`, for instructions taking an immediate.
+    fn emit_simd_reversed_imm8(
+        &mut self,
+        op: SsePackedImm8,
+        imm: u8,
+        src: XMM,
+        dst: XMM,
+        tmp: XMM,
+    ) -> Result<(), CompileError> {
+        self.assembler
+            .emit_sse_packed(SsePacked::Movdqa, src, tmp)?;
+        self.assembler.emit_sse_packed_imm8(op, imm, dst, tmp)?;
+        self.assembler.emit_sse_packed(SsePacked::Movdqa, tmp, dst)
+    }
+    /// `dst = (max_or_min(dst, src) == dst)`, an unsigned comparison.
+    fn emit_simd_cmp_unsigned(
+        &mut self,
+        max_or_min: SsePacked,
+        eq: SsePacked,
+        src: XMM,
+        dst: XMM,
+        tmp: XMM,
+    ) -> Result<(), CompileError> {
+        self.assembler
+            .emit_sse_packed(SsePacked::Movdqa, dst, tmp)?;
+        self.assembler.emit_sse_packed(max_or_min, src, tmp)?;
+        self.assembler.emit_sse_packed(eq, tmp, dst)
+    }
+    /// Move the `condition` flag, as an i32, in the low bits of `dst`.
+    fn emit_simd_set_flag(&mut self, condition: Condition, dst: XMM) -> Result<(), CompileError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| {
+            CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
+        })?;
+        self.assembler.emit_set(condition, tmp)?;
+        self.assembler
+            .emit_movzx(Size::S8, Location::GPR(tmp), Size::S32, Location::GPR(tmp))?;
+        self.assembler
+            .emit_mov(Size::S32, Location::GPR(tmp), Location::SIMD(dst))?;
+        self.release_gpr(tmp);
+        Ok(())
+    }
+    /// Move the `sz` lanes sign bits of `src` in the low bits of `dst`.
+    fn emit_simd_bitmask(&mut self, sz: Size, src: XMM, dst: XMM) -> Result<(), CompileError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| {
+            CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
+        })?;
+        self.assembler.emit_movmsk(sz, src, tmp)?;
+        self.assembler
+            .emit_mov(Size::S32, Location::GPR(tmp), Location::SIMD(dst))?;
+        self.release_gpr(tmp);
+        Ok(())
+    }
+    /// Move the lane `lane` of `src`, extended to an i32, in the low bits of `dst`.
+    fn emit_simd_extract_lane(
+        &mut self,
+        sz: Size,
+        signed: bool,
+        lane: u8,
+        src: XMM,
+        dst: XMM,
+    ) -> Result<(), CompileError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| {
+            CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
+        })?;
+        self.assembler.emit_pextr(sz, src, lane, tmp)?;
+        if signed {
+            self.assembler
+                .emit_movsx(sz, Location::GPR(tmp), Size::S32, Location::GPR(tmp))?;
+        }
+        self.assembler
+            .emit_mov(Size::S32, Location::GPR(tmp), Location::SIMD(dst))?;
+        self.release_gpr(tmp);
+        Ok(())
+    }
+    /// Replace the lane `lane` of `dst` with the low bits of `src`.
+    fn emit_simd_replace_lane(
+        &mut self,
+        sz: Size,
+        lane: u8,
+        src: XMM,
+        dst: XMM,
+    ) -> Result<(), CompileError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| {
+            CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
+        })?;
+        let mov_size = if sz == Size::S64 {
+            Size::S64
+        } else {
+            Size::S32
+        };
+        self.assembler
+            .emit_mov(mov_size, Location::SIMD(src), Location::GPR(tmp))?;
+        self.assembler.emit_pinsr(sz, tmp, lane, dst)?;
+        self.release_gpr(tmp);
+        Ok(())
+    }
 }
 
 impl Machine for MachineX86_64 {
@@ -2647,12 +2866,21 @@ impl Machine for MachineX86_64 {
     fn emit_call_label(&mut self, label: Label) -> Result<(), CompileError> {
         self.assembler.emit_call_label(label)
     }
+    fn get_gpr_for_tail_call(&self) -> GPR {
+        GPR::RAX
+    }
+    fn emit_jmp_register(&mut self, reg: GPR) -> Result<(), CompileError> {
+        self.assembler.emit_jmp_location(Location::GPR(reg))
+    }
     fn get_gpr_for_ret(&self) -> GPR {
         GPR::RAX
     }
     fn get_simd_for_ret(&self) -> XMM {
         XMM::XMM0
     }
+    fn get_gpr_for_ret_hi(&self) -> GPR {
+        GPR::RDX
+    }
 
     fn arch_requires_indirect_call_trampoline(&self) -> bool {
         self.assembler.arch_requires_indirect_call_trampoline()
@@ -4554,6 +4782,25 @@ impl Machine for MachineX86_64 {
         Ok(relocations)
     }
 
+    fn emit_jmp_with_reloc(
+        &mut self,
+        _calling_convention: CallingConvention,
+        reloc_target: RelocationTarget,
+    ) -> Result<Vec<Relocation>, CompileError> {
+        let mut relocations = vec![];
+        let next = self.get_label();
+        let reloc_at = self.assembler.get_offset().0 + 1; // skip E9
+        self.assembler.emit_jmp(Condition::None, next)?;
+        self.emit_label(next)?;
+        relocations.push(Relocation {
+            kind: RelocationKind::X86CallPCRel4,
+            reloc_target,
+            offset: reloc_at as u32,
+            addend: -4,
+        });
+        Ok(relocations)
+    }
+
     fn emit_binop_add64(
         &mut self,
         loc_a: Location,
@@ -7825,6 +8072,544 @@ impl Machine for MachineX86_64 {
         self.emit_relaxed_avx(AssemblerX64::emit_vdivss, loc_a, loc_b, ret)
     }
 
+    fn v128_from_halves(
+        &mut self,
+        lo: Location,
+        hi: Location,
+        dst: XMM,
+    ) -> Result<(), CompileError> {
+        let tmp = self.acquire_temp_gpr().ok_or_else(|| {
+            CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
+        })?;
+        match lo {
+            Location::GPR(_) | Location::Memory(_, _) => {
+                self.assembler
+                    .emit_mov(Size::S64, lo, Location::SIMD(dst))?;
+            }
+            _ => {
+                self.move_location(Size::S64, lo, Location::GPR(tmp))?;
+                self.assembler
+                    .emit_mov(Size::S64, Location::GPR(tmp), Location::SIMD(dst))?;
+            }
+        }
+        let hi = match hi {
+            Location::GPR(x) => x,
+            _ => {
+                self.move_location(Size::S64, hi, Location::GPR(tmp))?;
+                tmp
+            }
+        };
+        self.assembler.emit_pinsr(Size::S64, hi, 1, dst)?;
+        self.release_gpr(tmp);
+        Ok(())
+    }
+    fn v128_to_halves(&mut self, src: XMM, lo: Location, hi: Location) -> Result<(), CompileError> {
+        match lo {
+            Location::GPR(_) | Location::Memory(_, _) => {
+                self.assembler
+                    .emit_mov(Size::S64, Location::SIMD(src), lo)?;
+            }
+            _ => codegen_error!("singlepass v128_to_halves unreachable"),
+        }
+        match hi {
+            Location::GPR(x) => self.assembler.emit_pextr(Size::S64, src, 1, x)?,
+            Location::Memory(_, _) => {
+                let tmp = self.acquire_temp_gpr().ok_or_else(|| {
+                    CompileError::Codegen("singlepass cannot acquire temp gpr".to_owned())
+                })?;
+                self.assembler.emit_pextr(Size::S64, src, 1, tmp)?;
+                self.assembler.emit_mov(Size::S64, Location::GPR(tmp), hi)?;
+                self.release_gpr(tmp);
+            }
+            _ => codegen_error!("singlepass v128_to_halves unreachable"),
+        }
+        Ok(())
+    }
+    fn simd_op_supported(&self, op: SimdOp) -> bool {
+        use SimdOp::*;
+        // The remaining operations are rare enough to be left to the runtime.
+        !matches!(
+            op,
+            I8x16Popcnt
+                | I16x8Q15MulrSatS
+                | I16x8ExtAddPairwiseI8x16S
+                | I16x8ExtAddPairwiseI8x16U
+                | I32x4ExtAddPairwiseI16x8S
+                | I32x4ExtAddPairwiseI16x8U
+                | I16x8ExtMulLowI8x16S
+                | I16x8ExtMulHighI8x16S
+                | I16x8ExtMulLowI8x16U
+                | I16x8ExtMulHighI8x16U
+                | I32x4ExtMulLowI16x8S
+                | I32x4ExtMulHighI16x8S
+                | I32x4ExtMulLowI16x8U
+                | I32x4ExtMulHighI16x8U
+                | I64x2ExtMulLowI32x4S
+                | I64x2ExtMulHighI32x4S
+                | I64x2ExtMulLowI32x4U
+                | I64x2ExtMulHighI32x4U
+                | I64x2Abs
+                | I64x2ShrS
+        )
+    }
+    fn emit_simd_op(&mut self, op: SimdOp, lanes: &[u8], regs: &[XMM]) -> Result<(), CompileError> {
+        use SimdOp::*;
+        use SsePacked as P;
+        use SsePackedImm8 as P8;
+        let (a, b, c) = (regs[0], regs[1], regs[2]);
+        let lane = lanes.first().copied().unwrap_or(0);
+        match op {
+            I8x16Splat => {
+                self.assembler.emit_sse_packed(P::Pxor, b, b)?;
+                self.assembler.emit_sse_packed(P::Pshufb, b, a)?;
+            }
+            I16x8Splat => {
+                self.assembler.emit_sse_packed_imm8(P8::Pshuflw, 0, a, a)?;
+                self.assembler.emit_sse_packed_imm8(P8::Pshufd, 0, a, a)?;
+            }
+            I32x4Splat | F32x4Splat => self.assembler.emit_sse_packed_imm8(P8::Pshufd, 0, a, a)?,
+            I64x2Splat | F64x2Splat => self.assembler.emit_sse_packed(P::Punpcklqdq, a, a)?,
+            I8x16ExtractLaneS => self.emit_simd_extract_lane(Size::S8, true, lane, a, a)?,
+            I8x16ExtractLaneU => self.emit_simd_extract_lane(Size::S8, false, lane, a, a)?,
+            I16x8ExtractLaneS => self.emit_simd_extract_lane(Size::S16, true, lane, a, a)?,
+            I16x8ExtractLaneU => self.emit_simd_extract_lane(Size::S16, false, lane, a, a)?,
+            I32x4ExtractLane | F32x4ExtractLane => {
+                self.assembler
+                    .emit_sse_packed_imm8(P8::Pshufd, lane, a, a)?
+            }
+            I64x2ExtractLane | F64x2ExtractLane => {
+                if lane != 0 {
+                    self.assembler
+                        .emit_sse_packed_imm8(P8::Pshufd, 0xee, a, a)?;
+                }
+            }
+            I8x16ReplaceLane => self.emit_simd_replace_lane(Size::S8, lane, b, a)?,
+            I16x8ReplaceLane => self.emit_simd_replace_lane(Size::S16, lane, b, a)?,
+            I32x4ReplaceLane => self.emit_simd_replace_lane(Size::S32, lane, b, a)?,
+            I64x2ReplaceLane => self.emit_simd_replace_lane(Size::S64, lane, b, a)?,
+            F32x4ReplaceLane => {
+                self.assembler
+                    .emit_sse_packed_imm8(P8::Insertps, lane << 4, b, a)?
+            }
+            F64x2ReplaceLane => {
+                if lane == 0 {
+                    self.assembler.emit_sse_packed(P::Movsd, b, a)?;
+                } else {
+                    self.assembler.emit_sse_packed(P::Unpcklpd, b, a)?;
+                }
+            }
+            I8x16Shuffle => {
+                // `pshufb` selects 0 for the indices with the top bit set, so each operand is
+                // shuffled with the indices of its own lanes, and the results merged.
+                let (mut from_a, mut from_b) = ([0x80u8; 16], [0x80u8; 16]);
+                for (i, lane) in lanes.iter().enumerate() {
+                    match *lane {
+                        lane @ 0..=15 => from_a[i] = lane,
+                        lane => from_b[i] = lane - 16,
+                    }
+                }
+                let halves = |bytes: [u8; 16]| {
+                    (
+                        u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+                        u64::from_le_bytes(bytes[8..].try_into().unwrap()),
+                    )
+                };
+                let (lo, hi) = halves(from_a);
+                self.emit_simd_const(lo, hi, c)?;
+                self.assembler.emit_sse_packed(P::Pshufb, c, a)?;
+                let (lo, hi) = halves(from_b);
+                self.emit_simd_const(lo, hi, c)?;
+                self.assembler.emit_sse_packed(P::Pshufb, c, b)?;
+                self.assembler.emit_sse_packed(P::Por, b, a)?;
+            }
+            I8x16Swizzle => {
+                // Indices out of range must select 0, which `pshufb` does when the top bit is set.
+                self.emit_simd_splat_const(0x7070_7070_7070_7070, c)?;
+                self.assembler.emit_sse_packed(P::Paddusb, c, b)?;
+                self.assembler.emit_sse_packed(P::Pshufb, b, a)?;
+            }
+
+            I8x16Eq => self.assembler.emit_sse_packed(P::Pcmpeqb, b, a)?,
+            I16x8Eq => self.assembler.emit_sse_packed(P::Pcmpeqw, b, a)?,
+            I32x4Eq => self.assembler.emit_sse_packed(P::Pcmpeqd, b, a)?,
+            I64x2Eq => self.assembler.emit_sse_packed(P::Pcmpeqq, b, a)?,
+            I8x16Ne | I16x8Ne | I32x4Ne | I64x2Ne => {
+                let eq = match op {
+                    I8x16Ne => P::Pcmpeqb,
+                    I16x8Ne => P::Pcmpeqw,
+                    I32x4Ne => P::Pcmpeqd,
+                    _ => P::Pcmpeqq,
+                };
+                self.assembler.emit_sse_packed(eq, b, a)?;
+                self.emit_simd_not(a, c)?;
+            }
+            I8x16GtS => self.assembler.emit_sse_packed(P::Pcmpgtb, b, a)?,
+            I16x8GtS => self.assembler.emit_sse_packed(P::Pcmpgtw, b, a)?,
+            I32x4GtS => self.assembler.emit_sse_packed(P::Pcmpgtd, b, a)?,
+            I64x2GtS => self.assembler.emit_sse_packed(P::Pcmpgtq, b, a)?,
+            I8x16LtS => self.emit_simd_reversed(P::Pcmpgtb, b, a, c)?,
+            I16x8LtS => self.emit_simd_reversed(P::Pcmpgtw, b, a, c)?,
+            I32x4LtS => self.emit_simd_reversed(P::Pcmpgtd, b, a, c)?,
+            I64x2LtS => self.emit_simd_reversed(P::Pcmpgtq, b, a, c)?,
+            I8x16LeS | I16x8LeS | I32x4LeS | I64x2LeS => {
+                let gt = match op {
+                    I8x16LeS => P::Pcmpgtb,
+                    I16x8LeS => P::Pcmpgtw,
+                    I32x4LeS => P::Pcmpgtd,
+                    _ => P::Pcmpgtq,
+                };
+                self.assembler.emit_sse_packed(gt, b, a)?;
+                self.emit_simd_not(a, c)?;
+            }
+            I8x16GeS | I16x8GeS | I32x4GeS | I64x2GeS => {
+                let gt = match op {
+                    I8x16GeS => P::Pcmpgtb,
+                    I16x8GeS => P::Pcmpgtw,
+                    I32x4GeS => P::Pcmpgtd,
+                    _ => P::Pcmpgtq,
+                };
+                self.emit_simd_reversed(gt, b, a, c)?;
+                self.emit_simd_not(a, c)?;
+            }
+            I8x16GeU => self.emit_simd_cmp_unsigned(P::Pmaxub, P::Pcmpeqb, b, a, c)?,
+            I16x8GeU => self.emit_simd_cmp_unsigned(P::Pmaxuw, P::Pcmpeqw, b, a, c)?,
+            I32x4GeU => self.emit_simd_cmp_unsigned(P::Pmaxud, P::Pcmpeqd, b, a, c)?,
+            I8x16LeU => self.emit_simd_cmp_unsigned(P::Pminub, P::Pcmpeqb, b, a, c)?,
+            I16x8LeU => self.emit_simd_cmp_unsigned(P::Pminuw, P::Pcmpeqw, b, a, c)?,
+            I32x4LeU => self.emit_simd_cmp_unsigned(P::Pminud, P::Pcmpeqd, b, a, c)?,
+            I8x16LtU | I16x8LtU | I32x4LtU => {
+                let (max, eq) = match op {
+                    I8x16LtU => (P::Pmaxub, P::Pcmpeqb),
+                    I16x8LtU => (P::Pmaxuw, P::Pcmpeqw),
+                    _ => (P::Pmaxud, P::Pcmpeqd),
+                };
+                self.emit_simd_cmp_unsigned(max, eq, b, a, c)?;
+                self.emit_simd_not(a, c)?;
+            }
+            I8x16GtU | I16x8GtU | I32x4GtU => {
+                let (min, eq) = match op {
+                    I8x16GtU => (P::Pminub, P::Pcmpeqb),
+                    I16x8GtU => (P::Pminuw, P::Pcmpeqw),
+                    _ => (P::Pminud, P::Pcmpeqd),
+                };
+                self.emit_simd_cmp_unsigned(min, eq, b, a, c)?;
+                self.emit_simd_not(a, c)?;
+            }
+            F32x4Eq => self.assembler.emit_sse_packed_imm8(P8::Cmpps, 0, b, a)?,
+            F32x4Lt => self.assembler.emit_sse_packed_imm8(P8::Cmpps, 1, b, a)?,
+            F32x4Le => self.assembler.emit_sse_packed_imm8(P8::Cmpps, 2, b, a)?,
+            F32x4Ne => self.assembler.emit_sse_packed_imm8(P8::Cmpps, 4, b, a)?,
+            F32x4Gt => self.emit_simd_reversed_imm8(P8::Cmpps, 1, b, a, c)?,
+            F32x4Ge => self.emit_simd_reversed_imm8(P8::Cmpps, 2, b, a, c)?,
+            F64x2Eq => self.assembler.emit_sse_packed_imm8(P8::Cmppd, 0, b, a)?,
+            F64x2Lt => self.assembler.emit_sse_packed_imm8(P8::Cmppd, 1, b, a)?,
+            F64x2Le => self.assembler.emit_sse_packed_imm8(P8::Cmppd, 2, b, a)?,
+            F64x2Ne => self.assembler.emit_sse_packed_imm8(P8::Cmppd, 4, b, a)?,
+            F64x2Gt => self.emit_simd_reversed_imm8(P8::Cmppd, 1, b, a, c)?,
+            F64x2Ge => self.emit_simd_reversed_imm8(P8::Cmppd, 2, b, a, c)?,
+
+            V128Not => self.emit_simd_not(a, b)?,
+            V128And => self.assembler.emit_sse_packed(P::Pand, b, a)?,
+            V128AndNot => self.emit_simd_reversed(P::Pandn, b, a, c)?,
+            V128Or => self.assembler.emit_sse_packed(P::Por, b, a)?,
+            V128Xor => self.assembler.emit_sse_packed(P::Pxor, b, a)?,
+            V128Bitselect => {
+                self.assembler.emit_sse_packed(P::Pxor, b, a)?;
+                self.assembler.emit_sse_packed(P::Pand, c, a)?;
+                self.assembler.emit_sse_packed(P::Pxor, b, a)?;
+            }
+            V128AnyTrue => {
+                self.assembler.emit_sse_packed(P::Ptest, a, a)?;
+                self.emit_simd_set_flag(Condition::NotEqual, a)?;
+            }
+            I8x16AllTrue | I16x8AllTrue | I32x4AllTrue | I64x2AllTrue => {
+                let eq = match op {
+                    I8x16AllTrue => P::Pcmpeqb,
+                    I16x8AllTrue => P::Pcmpeqw,
+                    I32x4AllTrue => P::Pcmpeqd,
+                    _ => P::Pcmpeqq,
+                };
+                self.assembler.emit_sse_packed(P::Pxor, c, c)?;
+                self.assembler.emit_sse_packed(eq, a, c)?;
+                self.assembler.emit_sse_packed(P::Ptest, c, c)?;
+                self.emit_simd_set_flag(Condition::Equal, a)?;
+            }
+            I8x16Bitmask => self.emit_simd_bitmask(Size::S8, a, a)?,
+            I16x8Bitmask => {
+                self.assembler.emit_sse_packed(P::Pxor, b, b)?;
+                self.assembler.emit_sse_packed(P::Packsswb, b, a)?;
+                self.emit_simd_bitmask(Size::S8, a, a)?;
+            }
+            I32x4Bitmask => self.emit_simd_bitmask(Size::S32, a, a)?,
+            I64x2Bitmask => self.emit_simd_bitmask(Size::S64, a, a)?,
+
+            I8x16Abs => self.assembler.emit_sse_packed(P::Pabsb, a, a)?,
+            I16x8Abs => self.assembler.emit_sse_packed(P::Pabsw, a, a)?,
+            I32x4Abs => self.assembler.emit_sse_packed(P::Pabsd, a, a)?,
+            I8x16Neg | I16x8Neg | I32x4Neg | I64x2Neg => {
+                let sub = match op {
+                    I8x16Neg => P::Psubb,
+                    I16x8Neg => P::Psubw,
+                    I32x4Neg => P::Psubd,
+                    _ => P::Psubq,
+                };
+                self.assembler.emit_sse_packed(P::Pxor, b, b)?;
+                self.assembler.emit_sse_packed(sub, a, b)?;
+                self.assembler.emit_sse_packed(P::Movdqa, b, a)?;
+            }
+            I8x16Add => self.assembler.emit_sse_packed(P::Paddb, b, a)?,
+            I16x8Add => self.assembler.emit_sse_packed(P::Paddw, b, a)?,
+            I32x4Add => self.assembler.emit_sse_packed(P::Paddd, b, a)?,
+            I64x2Add => self.assembler.emit_sse_packed(P::Paddq, b, a)?,
+            I8x16AddSatS => self.assembler.emit_sse_packed(P::Paddsb, b, a)?,
+            I16x8AddSatS => self.assembler.emit_sse_packed(P::Paddsw, b, a)?,
+            I8x16AddSatU => self.assembler.emit_sse_packed(P::Paddusb, b, a)?,
+            I16x8AddSatU => self.assembler.emit_sse_packed(P::Paddusw, b, a)?,
+            I8x16Sub => self.assembler.emit_sse_packed(P::Psubb, b, a)?,
+            I16x8Sub => self.assembler.emit_sse_packed(P::Psubw, b, a)?,
+            I32x4Sub => self.assembler.emit_sse_packed(P::Psubd, b, a)?,
+            I64x2Sub => self.assembler.emit_sse_packed(P::Psubq, b, a)?,
+            I8x16SubSatS => self.assembler.emit_sse_packed(P::Psubsb, b, a)?,
+            I16x8SubSatS => self.assembler.emit_sse_packed(P::Psubsw, b, a)?,
+            I8x16SubSatU => self.assembler.emit_sse_packed(P::Psubusb, b, a)?,
+            I16x8SubSatU => self.assembler.emit_sse_packed(P::Psubusw, b, a)?,
+            I8x16MinS => self.assembler.emit_sse_packed(P::Pminsb, b, a)?,
+            I16x8MinS => self.assembler.emit_sse_packed(P::Pminsw, b, a)?,
+            I32x4MinS => self.assembler.emit_sse_packed(P::Pminsd, b, a)?,
+            I8x16MinU => self.assembler.emit_sse_packed(P::Pminub, b, a)?,
+            I16x8MinU => self.assembler.emit_sse_packed(P::Pminuw, b, a)?,
+            I32x4MinU => self.assembler.emit_sse_packed(P::Pminud, b, a)?,
+            I8x16MaxS => self.assembler.emit_sse_packed(P::Pmaxsb, b, a)?,
+            I16x8MaxS => self.assembler.emit_sse_packed(P::Pmaxsw, b, a)?,
+            I32x4MaxS => self.assembler.emit_sse_packed(P::Pmaxsd, b, a)?,
+            I8x16MaxU => self.assembler.emit_sse_packed(P::Pmaxub, b, a)?,
+            I16x8MaxU => self.assembler.emit_sse_packed(P::Pmaxuw, b, a)?,
+            I32x4MaxU => self.assembler.emit_sse_packed(P::Pmaxud, b, a)?,
+            I8x16AvgrU => self.assembler.emit_sse_packed(P::Pavgb, b, a)?,
+            I16x8AvgrU => self.assembler.emit_sse_packed(P::Pavgw, b, a)?,
+            I16x8Mul => self.assembler.emit_sse_packed(P::Pmullw, b, a)?,
+            I32x4Mul => self.assembler.emit_sse_packed(P::Pmulld, b, a)?,
+            I64x2Mul => {
+                // `lo(a) * lo(b) + ((lo(a) * hi(b) + hi(a) * lo(b)) << 32)`.
+                self.assembler
+                    .emit_sse_packed_imm8(P8::Pshufd, 0xb1, b, c)?;
+                self.assembler.emit_sse_packed(P::Pmulld, a, c)?;
+                self.assembler.emit_sse_packed(P::Pmuludq, b, a)?;
+                self.assembler
+                    .emit_sse_packed_imm8(P8::Pshufd, 0xb1, c, b)?;
+                self.assembler.emit_sse_packed(P::Paddd, c, b)?;
+                self.assembler.emit_sse_packed(P::Pxor, c, c)?;
+                self.assembler
+                    .emit_sse_packed_imm8(P8::Pblendw, 0x33, c, b)?;
+                self.assembler.emit_sse_packed(P::Paddq, b, a)?;
+            }
+            I32x4DotI16x8S => self.assembler.emit_sse_packed(P::Pmaddwd, b, a)?,
+            I8x16Shl => {
+                // Shift the 16 bits lanes, clearing the bits shifted in from the other byte.
+                self.assembler.emit_sse_packed(P::Pcmpeqd, c, c)?;
+                self.assembler.emit_sse_packed(P::Psllw, b, c)?;
+                self.assembler.emit_sse_packed(P::Punpcklbw, c, c)?;
+                self.assembler.emit_sse_packed_imm8(P8::Pshuflw, 0, c, c)?;
+                self.assembler.emit_sse_packed_imm8(P8::Pshufd, 0, c, c)?;
+                self.assembler.emit_sse_packed(P::Psllw, b, a)?;
+                self.assembler.emit_sse_packed(P::Pand, c, a)?;
+            }
+            I8x16ShrS => self.emit_simd_i8x16_shr(P::Psraw, P::Packsswb, b, a, c)?,
+            I8x16ShrU => self.emit_simd_i8x16_shr(P::Psrlw, P::Packuswb, b, a, c)?,
+            I16x8Shl => self.assembler.emit_sse_packed(P::Psllw, b, a)?,
+            I16x8ShrS => self.assembler.emit_sse_packed(P::Psraw, b, a)?,
+            I16x8ShrU => self.assembler.emit_sse_packed(P::Psrlw, b, a)?,
+            I32x4Shl => self.assembler.emit_sse_packed(P::Pslld, b, a)?,
+            I32x4ShrS => self.assembler.emit_sse_packed(P::Psrad, b, a)?,
+            I32x4ShrU => self.assembler.emit_sse_packed(P::Psrld, b, a)?,
+            I64x2Shl => self.assembler.emit_sse_packed(P::Psllq, b, a)?,
+            I64x2ShrU => self.assembler.emit_sse_packed(P::Psrlq, b, a)?,
+            I8x16NarrowI16x8S => self.assembler.emit_sse_packed(P::Packsswb, b, a)?,
+            I8x16NarrowI16x8U => self.assembler.emit_sse_packed(P::Packuswb, b, a)?,
+            I16x8NarrowI32x4S => self.assembler.emit_sse_packed(P::Packssdw, b, a)?,
+            I16x8NarrowI32x4U => self.assembler.emit_sse_packed(P::Packusdw, b, a)?,
+            I16x8ExtendLowI8x16S => self.assembler.emit_sse_packed(P::Pmovsxbw, a, a)?,
+            I16x8ExtendLowI8x16U => self.assembler.emit_sse_packed(P::Pmovzxbw, a, a)?,
+            I32x4ExtendLowI16x8S => self.assembler.emit_sse_packed(P::Pmovsxwd, a, a)?,
+            I32x4ExtendLowI16x8U => self.assembler.emit_sse_packed(P::Pmovzxwd, a, a)?,
+            I64x2ExtendLowI32x4S => self.assembler.emit_sse_packed(P::Pmovsxdq, a, a)?,
+            I64x2ExtendLowI32x4U => self.assembler.emit_sse_packed(P::Pmovzxdq, a, a)?,
+            I16x8ExtendHighI8x16S
+            | I16x8ExtendHighI8x16U
+            | I32x4ExtendHighI16x8S
+            | I32x4ExtendHighI16x8U
+            | I64x2ExtendHighI32x4S
+            | I64x2ExtendHighI32x4U => {
+                let extend = match op {
+                    I16x8ExtendHighI8x16S => P::Pmovsxbw,
+                    I16x8ExtendHighI8x16U => P::Pmovzxbw,
+                    I32x4ExtendHighI16x8S => P::Pmovsxwd,
+                    I32x4ExtendHighI16x8U => P::Pmovzxwd,
+                    I64x2ExtendHighI32x4S => P::Pmovsxdq,
+                    _ => P::Pmovzxdq,
+                };
+                self.assembler
+                    .emit_sse_packed_imm8(P8::Pshufd, 0xee, a, a)?;
+                self.assembler.emit_sse_packed(extend, a, a)?;
+            }
+
+            F32x4Abs => {
+                self.emit_simd_splat_const(0x7fff_ffff_7fff_ffff, b)?;
+                self.assembler.emit_sse_packed(P::Andps, b, a)?;
+            }
+            F64x2Abs => {
+                self.emit_simd_splat_const(0x7fff_ffff_ffff_ffff, b)?;
+                self.assembler.emit_sse_packed(P::Andpd, b, a)?;
+            }
+            F32x4Neg => {
+                self.emit_simd_splat_const(0x8000_0000_8000_0000, b)?;
+                self.assembler.emit_sse_packed(P::Xorps, b, a)?;
+            }
+            F64x2Neg => {
+                self.emit_simd_splat_const(0x8000_0000_0000_0000, b)?;
+                self.assembler.emit_sse_packed(P::Xorpd, b, a)?;
+            }
+            F32x4Sqrt => self.assembler.emit_sse_packed(P::Sqrtps, a, a)?,
+            F64x2Sqrt => self.assembler.emit_sse_packed(P::Sqrtpd, a, a)?,
+            F32x4Add => self.assembler.emit_sse_packed(P::Addps, b, a)?,
+            F64x2Add => self.assembler.emit_sse_packed(P::Addpd, b, a)?,
+            F32x4Sub => self.assembler.emit_sse_packed(P::Subps, b, a)?,
+            F64x2Sub => self.assembler.emit_sse_packed(P::Subpd, b, a)?,
+            F32x4Mul => self.assembler.emit_sse_packed(P::Mulps, b, a)?,
+            F64x2Mul => self.assembler.emit_sse_packed(P::Mulpd, b, a)?,
+            F32x4Div => self.assembler.emit_sse_packed(P::Divps, b, a)?,
+            F64x2Div => self.assembler.emit_sse_packed(P::Divpd, b, a)?,
+            // `minps dst, src` is `dst < src ? dst : src`, the pseudo-min with swapped operands.
+            F32x4PMin => self.emit_simd_reversed(P::Minps, b, a, c)?,
+            F32x4PMax => self.emit_simd_reversed(P::Maxps, b, a, c)?,
+            F64x2PMin => self.emit_simd_reversed(P::Minpd, b, a, c)?,
+            F64x2PMax => self.emit_simd_reversed(P::Maxpd, b, a, c)?,
+            F32x4Min => self.emit_simd_float_min_max(
+                P::Minps,
+                P8::Cmpps,
+                None,
+                0x003f_ffff_003f_ffff,
+                b,
+                a,
+                c,
+            )?,
+            F32x4Max => self.emit_simd_float_min_max(
+                P::Maxps,
+                P8::Cmpps,
+                Some(P::Subps),
+                0x003f_ffff_003f_ffff,
+                b,
+                a,
+                c,
+            )?,
+            F64x2Min => self.emit_simd_float_min_max(
+                P::Minpd,
+                P8::Cmppd,
+                None,
+                0x0007_ffff_ffff_ffff,
+                b,
+                a,
+                c,
+            )?,
+            F64x2Max => self.emit_simd_float_min_max(
+                P::Maxpd,
+                P8::Cmppd,
+                Some(P::Subpd),
+                0x0007_ffff_ffff_ffff,
+                b,
+                a,
+                c,
+            )?,
+            F32x4Nearest => self.assembler.emit_sse_packed_imm8(P8::Roundps, 0, a, a)?,
+            F32x4Floor => self.assembler.emit_sse_packed_imm8(P8::Roundps, 1, a, a)?,
+            F32x4Ceil => self.assembler.emit_sse_packed_imm8(P8::Roundps, 2, a, a)?,
+            F32x4Trunc => self.assembler.emit_sse_packed_imm8(P8::Roundps, 3, a, a)?,
+            F64x2Nearest => self.assembler.emit_sse_packed_imm8(P8::Roundpd, 0, a, a)?,
+            F64x2Floor => self.assembler.emit_sse_packed_imm8(P8::Roundpd, 1, a, a)?,
+            F64x2Ceil => self.assembler.emit_sse_packed_imm8(P8::Roundpd, 2, a, a)?,
+            F64x2Trunc => self.assembler.emit_sse_packed_imm8(P8::Roundpd, 3, a, a)?,
+            F32x4ConvertI32x4S => self.assembler.emit_sse_packed(P::Cvtdq2ps, a, a)?,
+            F64x2ConvertLowI32x4S => self.assembler.emit_sse_packed(P::Cvtdq2pd, a, a)?,
+            F32x4ConvertI32x4U => {
+                // The low 16 bits are converted exactly, and the high ones halved to be
+                // converted as signed integers, then doubled.
+                self.assembler.emit_sse_packed(P::Pxor, b, b)?;
+                self.assembler
+                    .emit_sse_packed_imm8(P8::Pblendw, 0x55, a, b)?;
+                self.assembler.emit_sse_packed(P::Psubd, b, a)?;
+                self.assembler.emit_sse_packed(P::Cvtdq2ps, b, b)?;
+                self.emit_simd_splat_const(1, c)?;
+                self.assembler.emit_sse_packed(P::Psrld, c, a)?;
+                self.assembler.emit_sse_packed(P::Cvtdq2ps, a, a)?;
+                self.assembler.emit_sse_packed(P::Addps, a, a)?;
+                self.assembler.emit_sse_packed(P::Addps, b, a)?;
+            }
+            F64x2ConvertLowI32x4U => {
+                // `0x4330_0000_xxxx_xxxx` is the double `2^52 + xxxx_xxxx`.
+                self.emit_simd_splat_const(0x4330_0000_4330_0000, b)?;
+                self.assembler.emit_sse_packed(P::Unpcklps, b, a)?;
+                self.emit_simd_splat_const(0x4330_0000_0000_0000, b)?;
+                self.assembler.emit_sse_packed(P::Subpd, b, a)?;
+            }
+            I32x4TruncSatF32x4S => {
+                // NaNs are zeroed, and the lanes overflowing to `0x8000_0000` from positive
+                // values set to `0x7fff_ffff`.
+                self.assembler.emit_sse_packed(P::Movdqa, a, c)?;
+                self.assembler.emit_sse_packed_imm8(P8::Cmpps, 0, c, c)?;
+                self.assembler.emit_sse_packed(P::Pand, c, a)?;
+                self.assembler.emit_sse_packed(P::Pxor, a, c)?;
+                self.assembler.emit_sse_packed(P::Cvttps2dq, a, a)?;
+                self.assembler.emit_sse_packed(P::Pand, a, c)?;
+                self.assembler.emit_sse_packed(P::Pxor, b, b)?;
+                self.assembler.emit_sse_packed(P::Pcmpgtd, c, b)?;
+                self.assembler.emit_sse_packed(P::Pxor, b, a)?;
+            }
+            I32x4TruncSatF32x4U => {
+                // NaNs and negative values are zeroed. The values from `2^31` are converted
+                // minus `2^31`, and added to the `0x8000_0000` they overflow to.
+                self.assembler.emit_sse_packed(P::Pxor, b, b)?;
+                self.assembler.emit_sse_packed(P::Maxps, b, a)?;
+                self.emit_simd_splat_const(0x4f00_0000_4f00_0000, b)?;
+                self.assembler.emit_sse_packed(P::Movdqa, a, c)?;
+                self.assembler.emit_sse_packed(P::Subps, b, c)?;
+                self.assembler.emit_sse_packed_imm8(P8::Cmpps, 2, c, b)?;
+                self.assembler.emit_sse_packed(P::Cvttps2dq, c, c)?;
+                self.assembler.emit_sse_packed(P::Pxor, b, c)?;
+                self.assembler.emit_sse_packed(P::Pxor, b, b)?;
+                self.assembler.emit_sse_packed(P::Pmaxsd, b, c)?;
+                self.assembler.emit_sse_packed(P::Cvttps2dq, a, a)?;
+                self.assembler.emit_sse_packed(P::Paddd, c, a)?;
+            }
+            I32x4TruncSatF64x2SZero => {
+                // NaNs are replaced by 0 and the other values clamped to `2^31 - 1`, the
+                // negative overflow giving `0x8000_0000`.
+                self.assembler.emit_sse_packed(P::Movdqa, a, c)?;
+                self.assembler.emit_sse_packed_imm8(P8::Cmppd, 0, a, c)?;
+                self.emit_simd_splat_const(0x41df_ffff_ffc0_0000, b)?;
+                self.assembler.emit_sse_packed(P::Pand, b, c)?;
+                self.assembler.emit_sse_packed(P::Minpd, c, a)?;
+                self.assembler.emit_sse_packed(P::Cvttpd2dq, a, a)?;
+            }
+            I32x4TruncSatF64x2UZero => {
+                // The values clamped to `[0, 2^32 - 1]` are truncated and added to `2^52`,
+                // which leaves them in the low 32 bits of the doubles.
+                self.assembler.emit_sse_packed(P::Pxor, b, b)?;
+                self.assembler.emit_sse_packed(P::Maxpd, b, a)?;
+                self.emit_simd_splat_const(0x41ef_ffff_ffe0_0000, c)?;
+                self.assembler.emit_sse_packed(P::Minpd, c, a)?;
+                self.assembler.emit_sse_packed_imm8(P8::Roundpd, 3, a, a)?;
+                self.emit_simd_splat_const(0x4330_0000_0000_0000, c)?;
+                self.assembler.emit_sse_packed(P::Addpd, c, a)?;
+                self.assembler
+                    .emit_sse_packed_imm8(P8::Shufps, 0x88, b, a)?;
+            }
+            F32x4DemoteF64x2Zero => self.assembler.emit_sse_packed(P::Cvtpd2ps, a, a)?,
+            F64x2PromoteLowF32x4 => self.assembler.emit_sse_packed(P::Cvtps2pd, a, a)?,
+            _ => codegen_error!("singlepass can't emit SIMD operation {}", op),
+        }
+        Ok(())
+    }
+
     fn gen_std_trampoline(
         &self,
         sig: &FunctionType,
@@ -7832,10 +8617,11 @@ impl Machine for MachineX86_64 {
     ) -> Result<FunctionBody, CompileError> {
         // the cpu feature here is irrelevant
         let mut a = AssemblerX64::new(0, None)?;
+        let params = lower_v128_params(sig.params());
 
         // Calculate stack offset.
         let mut stack_offset: u32 = 0;
-        for (i, _param) in sig.params().iter().enumerate() {
+        for (i, _param) in params.iter().enumerate() {
             if let Location::Memory(_, _) =
                 self.get_simple_param_location(1 + i, calling_convention)
             {
@@ -7879,8 +8665,8 @@ impl Machine for MachineX86_64 {
         // `callee_vmctx` is already in the first argument register, so no need to move.
         {
             let mut n_stack_args: usize = 0;
-            for (i, _param) in sig.params().iter().enumerate() {
-                let src_loc = Location::Memory(GPR::R14, (i * 16) as _); // args_rets[i]
+            for (i, (_param, offset)) in params.iter().enumerate() {
+                let src_loc = Location::Memory(GPR::R14, *offset as _); // args_rets[i]
                 let dst_loc = self.get_simple_param_location(1 + i, calling_convention);
 
                 match dst_loc {
@@ -7923,6 +8709,13 @@ impl Machine for MachineX86_64 {
                 Location::GPR(GPR::RAX),
                 Location::Memory(GPR::R14, 0),
             )?;
+            if sig.results()[0] == Type::V128 {
+                a.emit_mov(
+                    Size::S64,
+                    Location::GPR(GPR::RDX),
+                    Location::Memory(GPR::R14, 8),
+                )?;
+            }
         }
 
         // Restore callee-saved registers.
@@ -7967,8 +8760,8 @@ impl Machine for MachineX86_64 {
 
             let mut stack_param_count: usize = 0;
 
-            for (i, ty) in sig.params().iter().enumerate() {
-                let source_loc = match argalloc.next(*ty, calling_convention) {
+            for (ty, offset) in lower_v128_params(sig.params()) {
+                let source_loc = match argalloc.next(ty, calling_convention) {
                     Some(X64Register::GPR(gpr)) => Location::GPR(gpr),
                     Some(X64Register::XMM(xmm)) => Location::SIMD(xmm),
                     None => {
@@ -7987,15 +8780,17 @@ impl Machine for MachineX86_64 {
                 a.emit_mov(
                     Size::S64,
                     source_loc,
-                    Location::Memory(GPR::RSP, (stack_padding + i * 16) as _),
+                    Location::Memory(GPR::RSP, (stack_padding + offset) as _),
                 )?;
 
-                // Zero upper 64 bits.
-                a.emit_mov(
-                    Size::S64,
-                    Location::Imm32(0),
-                    Location::Memory(GPR::RSP, (stack_padding + i * 16 + 8) as _),
-                )?;
+                // Zero upper 64 bits. V128 values fill both halves of their slot.
+                if sig.params()[offset / 16] != Type::V128 {
+                    a.emit_mov(
+                        Size::S64,
+                        Location::Imm32(0),
+                        Location::Memory(GPR::RSP, (stack_padding + offset + 8) as _),
+                    )?;
+                }
             }
         }
 
@@ -8043,6 +8838,13 @@ impl Machine for MachineX86_64 {
                 Location::Memory(GPR::RSP, stack_padding as i32),
                 Location::GPR(GPR::RAX),
             )?;
+            if sig.results()[0] == Type::V128 {
+                a.emit_mov(
+                    Size::S64,
+                    Location::Memory(GPR::RSP, (stack_padding + 8) as i32),
+                    Location::GPR(GPR::RDX),
+                )?;
+            }
         }
 
         // Release values array.
@@ -8072,6 +8874,10 @@ impl Machine for MachineX86_64 {
     ) -> Result<CustomSection, CompileError> {
         // the cpu feature here is irrelevant
         let mut a = AssemblerX64::new(0, None)?;
+        let params: Vec<Type> = lower_v128_params(sig.params())
+            .into_iter()
+            .map(|(ty, _)| ty)
+            .collect();
 
         // TODO: ARM entry trampoline is not emitted.
 
@@ -8082,17 +8888,13 @@ impl Machine for MachineX86_64 {
         // For the standard System V calling convention requires
         //  floating point arguments to be passed in XMM registers.
         //  Translation is expensive, so only do it if needed.
-        if sig
-            .params()
-            .iter()
-            .any(|&x| x == Type::F32 || x == Type::F64)
-        {
+        if params.iter().any(|&x| x == Type::F32 || x == Type::F64) {
             match calling_convention {
                 CallingConvention::WindowsFastcall => {
                     let mut param_locations: Vec<Location> = vec![];
                     static PARAM_REGS: &[GPR] = &[GPR::RDX, GPR::R8, GPR::R9];
                     #[allow(clippy::needless_range_loop)]
-                    for i in 0..sig.params().len() {
+                    for i in 0..params.len() {
                         let loc = match i {
                             0..=2 => Location::GPR(PARAM_REGS[i]),
                             _ => Location::Memory(GPR::RSP, 32 + 8 + ((i - 3) * 8) as i32), // will not be used anyway
@@ -8102,7 +8904,7 @@ impl Machine for MachineX86_64 {
 
                     // Copy Float arguments to XMM from GPR.
                     let mut argalloc = ArgumentRegisterAllocator::default();
-                    for (i, ty) in sig.params().iter().enumerate() {
+                    for (i, ty) in params.iter().enumerate() {
                         let prev_loc = param_locations[i];
                         match argalloc.next(*ty, calling_convention) {
                             Some(X64Register::GPR(_gpr)) => continue,
@@ -8117,10 +8919,10 @@ impl Machine for MachineX86_64 {
                     let mut param_locations = vec![];
 
                     // Allocate stack space for arguments.
-                    let stack_offset: i32 = if sig.params().len() > 5 {
+                    let stack_offset: i32 = if params.len() > 5 {
                         5 * 8
                     } else {
-                        (sig.params().len() as i32) * 8
+                        (params.len() as i32) * 8
                     };
                     if stack_offset > 0 {
                         a.emit_sub(
@@ -8133,7 +8935,7 @@ impl Machine for MachineX86_64 {
                     // Store all arguments to the stack to prevent overwrite.
                     static PARAM_REGS: &[GPR] = &[GPR::RSI, GPR::RDX, GPR::RCX, GPR::R8, GPR::R9];
                     #[allow(clippy::needless_range_loop)]
-                    for i in 0..sig.params().len() {
+                    for i in 0..params.len() {
                         let loc = match i {
                             0..=4 => {
                                 let loc = Location::Memory(GPR::RSP, (i * 8) as i32);
@@ -8151,7 +8953,7 @@ impl Machine for MachineX86_64 {
                     let mut argalloc = ArgumentRegisterAllocator::default();
                     argalloc.next(Type::I64, calling_convention).unwrap(); // skip VMContext
                     let mut caller_stack_offset: i32 = 0;
-                    for (i, ty) in sig.params().iter().enumerate() {
+                    for (i, ty) in params.iter().enumerate() {
                         let prev_loc = param_locations[i];
                         let targ = match argalloc.next(*ty, calling_convention) {
                             Some(X64Register::GPR(gpr)) => Location::GPR(gpr),
@@ -66,6 +66,19 @@ impl ArtifactBuild {
         let middlewares = compiler.get_middlewares();
         middlewares.apply_on_module_info(&mut module);
 
+        // The memories and tables added by the middlewares get the most
+        // conservative styles.
+        let mut memory_styles = memory_styles;
+        while memory_styles.len() < module.memories.len() {
+            memory_styles.push(MemoryStyle::Dynamic {
+                offset_guard_size: 0,
+            });
+        }
+        let mut table_styles = table_styles;
+        while table_styles.len() < module.tables.len() {
+            table_styles.push(TableStyle::CallerChecksSignature);
+        }
+
         let compile_info = CompileModuleInfo {
             module: Arc::new(module),
             features,
@@ -74,39 +74,27 @@ pub trait Compiler: Send {
     /// Note that this is an API breaking change since 3.0
     fn name(&self) -> &str;
 
+    /// Returns the subset of the given features this compiler can generate code for.
+    fn supported_features(&self, features: &Features) -> Features {
+        features.clone()
+    }
+
     /// Validates a module.
     ///
     /// It returns the a succesful Result in case is valid, `CompileError` in case is not.
+    /// A module only valid thanks to features the compiler doesn't support
+    /// (see [`Compiler::supported_features`]) results in a
+    /// `CompileError::UnsupportedFeature`.
     fn validate_module(&self, features: &Features, data: &[u8]) -> Result<(), CompileError> {
-        let wasm_features = WasmFeatures {
-            bulk_memory: features.bulk_memory,
-            threads: features.threads,
-            reference_types: features.reference_types,
-            multi_value: features.multi_value,
-            simd: features.simd,
-            tail_call: features.tail_call,
-            multi_memory: features.multi_memory,
-            memory64: features.memory64,
-            exceptions: features.exceptions,
-            extended_const: features.extended_const,
-            relaxed_simd: features.relaxed_simd,
-            mutable_global: true,
-            saturating_float_to_int: true,
-            floats: true,
-            sign_extension: true,
-
-            // Not supported
-            component_model: false,
-            function_references: false,
-            memory_control: false,
-            gc: false,
-            component_model_values: false,
-            component_model_nested_names: false,
-        };
-        let mut validator = Validator::new_with_features(wasm_features);
-        validator
-            .validate_all(data)
-            .map_err(|e| CompileError::Validate(format!("{}", e)))?;
+        validate(features, data).map_err(|e| CompileError::Validate(format!("{}", e)))?;
+        let supported = self.supported_features(features);
+        if supported != *features && validate(&supported, data).is_err() {
+            return Err(CompileError::UnsupportedFeature(format!(
+                "{} with the {} compiler",
+                unsupported_features(features, &supported).join(", "),
+                self.name()
+            )));
+        }
         Ok(())
     }
 
@@ -147,3 +135,76 @@ pub trait Compiler: Send {
         *cpu_features
     }
 }
+
+fn validate(features: &Features, data: &[u8]) -> Result<(), wasmparser::BinaryReaderError> {
+    let wasm_features = WasmFeatures {
+        bulk_memory: features.bulk_memory,
+        threads: features.threads,
+        reference_types: features.reference_types,
+        multi_value: features.multi_value,
+        simd: features.simd,
+        tail_call: features.tail_call,
+        multi_memory: features.multi_memory,
+        memory64: features.memory64,
+        exceptions: features.exceptions,
+        extended_const: features.extended_const,
+        relaxed_simd: features.relaxed_simd,
+        mutable_global: true,
+        saturating_float_to_int: true,
+        floats: true,
+        sign_extension: true,
+
+        // Not supported
+        component_model: false,
+        function_references: false,
+        memory_control: false,
+        gc: false,
+        component_model_values: false,
+        component_model_nested_names: false,
+    };
+    let mut validator = Validator::new_with_features(wasm_features);
+    validator.validate_all(data)?;
+    Ok(())
+}
+
+/// Names of the features enabled in `features` but not in `supported`.
+fn unsupported_features(features: &Features, supported: &Features) -> Vec<&'static str> {
+    [
+        ("threads", features.threads, supported.threads),
+        (
+            "reference_types",
+            features.reference_types,
+            supported.reference_types,
+        ),
+        ("simd", features.simd, supported.simd),
+        ("bulk_memory", features.bulk_memory, supported.bulk_memory),
+        ("multi_value", features.multi_value, supported.multi_value),
+        ("tail_call", features.tail_call, supported.tail_call),
+        (
+            "module_linking",
+            features.module_linking,
+            supported.module_linking,
+        ),
+        (
+            "multi_memory",
+            features.multi_memory,
+            supported.multi_memory,
+        ),
+        ("memory64", features.memory64, supported.memory64),
+        ("exceptions", features.exceptions, supported.exceptions),
+        (
+            "relaxed_simd",
+            features.relaxed_simd,
+            supported.relaxed_simd,
+        ),
+        (
+            "extended_const",
+            features.extended_const,
+            supported.extended_const,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, enabled, supported)| *enabled && !*supported)
+    .map(|(name, _, _)| name)
+    .collect()
+}
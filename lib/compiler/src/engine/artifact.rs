@@ -6,11 +6,13 @@ use crate::lib::std::vec::IntoIter;
 use crate::ArtifactBuild;
 use crate::ArtifactBuildFromArchive;
 use crate::ArtifactCreate;
+#[cfg(feature = "compiler")]
+use crate::ArtifactTiering;
 use crate::Features;
 use crate::ModuleEnvironment;
 use crate::{
-    register_frame_info, resolve_imports, FunctionExtent, GlobalFrameInfoRegistration,
-    InstantiationError, Tunables,
+    create_gdb_jit_image, register_frame_info, resolve_imports, FunctionExtent,
+    GlobalFrameInfoRegistration, InstantiationError, LinkError, Tunables,
 };
 #[cfg(feature = "static-artifact-create")]
 use crate::{Compiler, FunctionBodyData, ModuleTranslationState};
@@ -36,11 +38,13 @@ use wasmer_types::DataInitializerLocationLike;
 use wasmer_types::MetadataHeader;
 use wasmer_types::{
     CompileError, CpuFeature, DataInitializer, DeserializeError, FunctionIndex, LocalFunctionIndex,
-    MemoryIndex, ModuleInfo, OwnedDataInitializer, SignatureIndex, TableIndex, Target,
+    LocalTagIndex, MemoryIndex, ModuleInfo, OwnedDataInitializer, SignatureIndex, TableIndex,
+    Target,
 };
 use wasmer_types::{SerializableModule, SerializeError};
 use wasmer_vm::{FunctionBodyPtr, MemoryStyle, TableStyle, VMSharedSignatureIndex, VMTrampoline};
-use wasmer_vm::{InstanceAllocator, StoreObjects, TrapHandlerFn, VMConfig, VMExtern, VMInstance};
+use wasmer_vm::{InternalStoreHandle, MemoryImages, VMTag};
+use wasmer_vm::{StoreObjects, TrapHandlerFn, VMConfig, VMExtern, VMInstance};
 
 pub struct AllocatedArtifact {
     // This shows if the frame info has been regestered already or not.
@@ -57,6 +61,9 @@ pub struct AllocatedArtifact {
     finished_dynamic_function_trampolines: BoxedSlice<FunctionIndex, FunctionBodyPtr>,
     signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
     finished_function_lengths: BoxedSlice<LocalFunctionIndex, usize>,
+    // The copy-on-write images of the local memories, built once so that
+    // instantiating doesn't copy the data segments.
+    memory_images: MemoryImages,
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -95,6 +102,9 @@ pub struct Artifact {
     // The artifact will only be allocated in memory in case we can execute it
     // (that means, if the target != host then this will be None).
     allocated: Option<AllocatedArtifact>,
+    // The tiering state, if the artifact was compiled by a tiered engine.
+    #[cfg(feature = "compiler")]
+    tiering: Option<Arc<ArtifactTiering>>,
 }
 
 /// Artifacts may be created as the result of the compilation of a wasm
@@ -281,6 +291,8 @@ impl Artifact {
                 id: Default::default(),
                 artifact,
                 allocated: None,
+                #[cfg(feature = "compiler")]
+                tiering: None,
             });
         } else {
             // check if cpu features are compatible before anything else
@@ -381,6 +393,22 @@ impl Artifact {
 
         engine_inner.publish_eh_frame(eh_frame)?;
 
+        if engine_inner.debug_info() {
+            let image = match &artifact {
+                ArtifactBuildVariant::Plain(p) => {
+                    create_gdb_jit_image(module_info, &finished_functions, p.get_frame_info_ref())
+                }
+                ArtifactBuildVariant::Archived(a) => create_gdb_jit_image(
+                    module_info,
+                    &finished_functions,
+                    &a.deserialize_frame_info_ref()?,
+                ),
+            };
+            if let Some(image) = image {
+                engine_inner.register_debug_image(image);
+            }
+        }
+
         let finished_function_lengths = finished_functions
             .values()
             .map(|extent| extent.length)
@@ -396,6 +424,7 @@ impl Artifact {
         let finished_dynamic_function_trampolines =
             finished_dynamic_function_trampolines.into_boxed_slice();
         let signatures = signatures.into_boxed_slice();
+        let memory_images = build_memory_images(&artifact);
 
         let mut artifact = Self {
             id: Default::default(),
@@ -408,13 +437,19 @@ impl Artifact {
                 finished_dynamic_function_trampolines,
                 signatures,
                 finished_function_lengths,
+                memory_images,
             }),
+            #[cfg(feature = "compiler")]
+            tiering: None,
         };
 
         artifact
             .internal_register_frame_info()
             .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
         if let Some(frame_info) = artifact.internal_take_frame_info_registration() {
+            if engine_inner.backtrace_details() {
+                frame_info.enable_backtrace_details();
+            }
             engine_inner.register_frame_info(frame_info);
         }
 
@@ -629,6 +664,22 @@ impl<'a> DataInitializerLocationLike for DataInitializerLocationVariant<'a> {
     }
 }
 
+/// Returns the data initializers of an artifact, borrowing their data.
+fn plain_data_initializers(artifact: &ArtifactBuildVariant) -> Vec<DataInitializer<'_>> {
+    artifact
+        .data_initializers()
+        .map(|init| DataInitializer {
+            location: init.location().clone_to_plain(),
+            data: init.data(),
+        })
+        .collect()
+}
+
+/// Builds the copy-on-write images of the local memories of an artifact.
+fn build_memory_images(artifact: &ArtifactBuildVariant) -> MemoryImages {
+    MemoryImages::new(artifact.module_info(), &plain_data_initializers(artifact))
+}
+
 impl Artifact {
     /// Register thie `Artifact` stack frame information into the global scope.
     ///
@@ -720,6 +771,23 @@ impl Artifact {
             .finished_functions
     }
 
+    /// Sets the tiering state of the artifact.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn set_tiering(&mut self, tiering: Arc<ArtifactTiering>) {
+        self.tiering = Some(tiering);
+    }
+
+    /// Returns the functions the instances of this `Artifact` are created
+    /// with. Those are the finished functions, unless a tiered engine
+    /// swapped some of them for optimized code.
+    pub fn instance_functions(&self) -> BoxedSlice<LocalFunctionIndex, FunctionBodyPtr> {
+        #[cfg(feature = "compiler")]
+        if let Some(tiering) = &self.tiering {
+            return tiering.functions();
+        }
+        self.finished_functions().clone()
+    }
+
     /// Returns the function call trampolines allocated in memory of this
     /// `Artifact`, ready to be run.
     pub fn finished_function_call_trampolines(&self) -> &BoxedSlice<SignatureIndex, VMTrampoline> {
@@ -792,11 +860,34 @@ impl Artifact {
         )
         .map_err(InstantiationError::Link)?;
 
+        let local_memories = module
+            .memories
+            .values()
+            .skip(module.num_imported_memories)
+            .copied()
+            .collect::<Vec<_>>();
+        let local_tables = module
+            .tables
+            .values()
+            .skip(module.num_imported_tables)
+            .copied()
+            .collect::<Vec<_>>();
+        let allowed = context
+            .resource_limiter_mut()
+            .instance_creating(&local_memories, &local_tables)
+            .map_err(InstantiationError::Start)?;
+        if !allowed {
+            return Err(InstantiationError::Link(LinkError::Resource(
+                "the resource limiter of the store denied the instantiation".to_string(),
+            )));
+        }
+
         // Get pointers to where metadata about local memories should live in VM memory.
         // Get pointers to where metadata about local tables should live in VM memory.
 
-        let (allocator, memory_definition_locations, table_definition_locations) =
-            InstanceAllocator::new(&module);
+        let (allocator, memory_definition_locations, table_definition_locations) = tunables
+            .allocate_instance(&module)
+            .map_err(InstantiationError::Link)?;
         let finished_memories = tunables
             .create_memories(
                 context,
@@ -819,20 +910,32 @@ impl Artifact {
             .create_globals(context, &module)
             .map_err(InstantiationError::Link)?
             .into_boxed_slice();
+        let finished_tags = module
+            .tags
+            .keys()
+            .skip(module.num_imported_tags)
+            .map(|index| InternalStoreHandle::new(context, VMTag::new(module.tag_type(index))))
+            .collect::<PrimaryMap<LocalTagIndex, _>>()
+            .into_boxed_slice();
 
-        let handle = VMInstance::new(
+        let mut handle = VMInstance::new(
             allocator,
             module,
             context,
-            self.finished_functions().clone(),
+            self.instance_functions(),
             self.finished_function_call_trampolines().clone(),
             finished_memories,
             finished_tables,
             finished_globals,
+            finished_tags,
             imports,
             self.signatures().clone(),
         )
         .map_err(InstantiationError::Start)?;
+        #[cfg(feature = "compiler")]
+        if let Some(tiering) = &self.tiering {
+            tiering.attach(context, &mut handle, &memory_definition_locations);
+        }
         Ok(handle)
     }
 
@@ -848,15 +951,14 @@ impl Artifact {
         trap_handler: Option<*const TrapHandlerFn<'static>>,
         handle: &mut VMInstance,
     ) -> Result<(), InstantiationError> {
-        let data_initializers = self
-            .data_initializers()
-            .map(|init| DataInitializer {
-                location: init.location().clone_to_plain(),
-                data: init.data(),
-            })
-            .collect::<Vec<_>>();
+        let data_initializers = plain_data_initializers(&self.artifact);
+        let memory_images = &self
+            .allocated
+            .as_ref()
+            .expect("It must be allocated")
+            .memory_images;
         handle
-            .finish_instantiation(config, trap_handler, &data_initializers)
+            .finish_instantiation(config, trap_handler, &data_initializers, memory_images)
             .map_err(InstantiationError::Start)
     }
 
@@ -1163,12 +1265,14 @@ impl Artifact {
             finished_dynamic_function_trampolines.push(fp);
         }
 
-        let artifact = ArtifactBuild::from_serializable(SerializableModule {
-            compilation: SerializableCompilation::default(),
-            compile_info: metadata.compile_info,
-            data_initializers: metadata.data_initializers,
-            cpu_features: metadata.cpu_features,
-        });
+        let artifact =
+            ArtifactBuildVariant::Plain(ArtifactBuild::from_serializable(SerializableModule {
+                compilation: SerializableCompilation::default(),
+                compile_info: metadata.compile_info,
+                data_initializers: metadata.data_initializers,
+                cpu_features: metadata.cpu_features,
+            }));
+        let memory_images = build_memory_images(&artifact);
 
         let finished_function_lengths = finished_functions
             .values()
@@ -1178,7 +1282,7 @@ impl Artifact {
 
         Ok(Self {
             id: Default::default(),
-            artifact: ArtifactBuildVariant::Plain(artifact),
+            artifact,
             allocated: Some(AllocatedArtifact {
                 frame_info_registered: false,
                 frame_info_registration: None,
@@ -1189,7 +1293,10 @@ impl Artifact {
                     .into_boxed_slice(),
                 signatures: signatures.into_boxed_slice(),
                 finished_function_lengths,
+                memory_images,
             }),
+            #[cfg(feature = "compiler")]
+            tiering: None,
         })
     }
 }
@@ -1,5 +1,10 @@
 use super::Engine;
 use crate::CompilerConfig;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::ProfilingStrategy;
+#[cfg(feature = "compiler")]
+#[cfg(not(target_arch = "wasm32"))]
+use crate::TieringConfig;
 use wasmer_types::{Features, Target};
 
 /// The Builder contents of `Engine`
@@ -10,6 +15,19 @@ pub struct EngineBuilder {
     target: Option<Target>,
     /// The features to compile the Wasm module with
     features: Option<Features>,
+    /// The tiered compilation configuration
+    #[cfg(feature = "compiler")]
+    #[cfg(not(target_arch = "wasm32"))]
+    tiering: Option<TieringConfig>,
+    /// The profiler the compiled code is described to
+    #[cfg(not(target_arch = "wasm32"))]
+    profiler: Option<ProfilingStrategy>,
+    /// Whether the debugging information is registered with the debuggers
+    #[cfg(not(target_arch = "wasm32"))]
+    debug_info: bool,
+    /// Whether the backtraces are resolved to source locations
+    #[cfg(not(target_arch = "wasm32"))]
+    backtrace_details: bool,
 }
 
 impl EngineBuilder {
@@ -22,6 +40,15 @@ impl EngineBuilder {
             compiler_config: Some(compiler_config.into()),
             target: None,
             features: None,
+            #[cfg(feature = "compiler")]
+            #[cfg(not(target_arch = "wasm32"))]
+            tiering: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            profiler: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            debug_info: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            backtrace_details: false,
         }
     }
 
@@ -31,6 +58,15 @@ impl EngineBuilder {
             compiler_config: None,
             target: None,
             features: None,
+            #[cfg(feature = "compiler")]
+            #[cfg(not(target_arch = "wasm32"))]
+            tiering: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            profiler: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            debug_info: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            backtrace_details: false,
         }
     }
 
@@ -46,24 +82,90 @@ impl EngineBuilder {
         self
     }
 
+    /// Set the tiered compilation configuration
+    ///
+    /// The modules are compiled with the compiler of the builder first, and
+    /// their hot functions are recompiled with the optimizing compiler of the
+    /// tiering configuration.
+    #[cfg(feature = "compiler")]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_tiering(mut self, tiering: Option<TieringConfig>) -> Self {
+        self.tiering = tiering;
+        self
+    }
+
+    /// Set the profiler the compiled code is described to
+    ///
+    /// This lets Linux `perf` attribute samples to the WebAssembly
+    /// functions, named after the name section of their module.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_profiler(mut self, profiler: Option<ProfilingStrategy>) -> Self {
+        self.profiler = profiler;
+        self
+    }
+
+    /// Set whether the debugging information of the modules is registered
+    /// with the native debuggers
+    ///
+    /// The DWARF sections of the modules are translated to their compiled
+    /// code, so that GDB and LLDB can set breakpoints on their source lines.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_debug_info(mut self, debug_info: bool) -> Self {
+        self.debug_info = debug_info;
+        self
+    }
+
+    /// Set whether the backtraces of runtime errors are resolved to source
+    /// locations
+    ///
+    /// The frames of the modules with DWARF sections get the source files,
+    /// lines and inlined functions of their instructions, which are parsed
+    /// the first time a backtrace goes through the module.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_backtrace_details(mut self, backtrace_details: bool) -> Self {
+        self.backtrace_details = backtrace_details;
+        self
+    }
+
     /// Build the `Engine` for this configuration
     #[cfg(feature = "compiler")]
     pub fn engine(self) -> Engine {
         let target = self.target.unwrap_or_default();
-        if let Some(compiler_config) = self.compiler_config {
+        let engine = if let Some(compiler_config) = self.compiler_config {
             let features = self
                 .features
                 .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(tiering) = self.tiering {
+                Engine::new_tiered(compiler_config, tiering, target, features)
+            } else {
+                Engine::new(compiler_config, target, features)
+            }
+            #[cfg(target_arch = "wasm32")]
             Engine::new(compiler_config, target, features)
         } else {
             Engine::headless()
-        }
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        engine.set_profiler(self.profiler);
+        #[cfg(not(target_arch = "wasm32"))]
+        engine.set_debug_info(self.debug_info);
+        #[cfg(not(target_arch = "wasm32"))]
+        engine.set_backtrace_details(self.backtrace_details);
+        engine
     }
 
     /// Build the `Engine` for this configuration
     #[cfg(not(feature = "compiler"))]
     pub fn engine(self) -> Engine {
-        Engine::headless()
+        let engine = Engine::headless();
+        #[cfg(not(target_arch = "wasm32"))]
+        engine.set_profiler(self.profiler);
+        #[cfg(not(target_arch = "wasm32"))]
+        engine.set_debug_info(self.debug_info);
+        #[cfg(not(target_arch = "wasm32"))]
+        engine.set_backtrace_details(self.backtrace_details);
+        engine
     }
 
     /// The Wasm features
@@ -3,7 +3,7 @@
 
 //! Memory management for executable code.
 use super::unwind::UnwindRegistry;
-use crate::GlobalFrameInfoRegistration;
+use crate::{GdbJitImageRegistration, GlobalFrameInfoRegistration};
 use wasmer_types::{
     compilation::unwind::CompiledFunctionUnwindInfoLike, CompiledFunctionUnwindInfoReference,
     CustomSectionLike, FunctionBodyLike,
@@ -25,6 +25,8 @@ const DATA_SECTION_ALIGNMENT: usize = 64;
 pub struct CodeMemory {
     // frame info is placed first, to ensure it's dropped before the mmap
     frame_info_registration: Option<GlobalFrameInfoRegistration>,
+    // the debuggers must forget the code before it's unmapped too
+    debug_registration: Option<GdbJitImageRegistration>,
     unwind_registry: UnwindRegistry,
     mmap: Mmap,
     start_of_nonexecutable_pages: usize,
@@ -38,6 +40,7 @@ impl CodeMemory {
             mmap: Mmap::new(),
             start_of_nonexecutable_pages: 0,
             frame_info_registration: None,
+            debug_registration: None,
         }
     }
 
@@ -231,6 +234,12 @@ impl CodeMemory {
     pub fn register_frame_info(&mut self, frame_info: GlobalFrameInfoRegistration) {
         self.frame_info_registration = Some(frame_info);
     }
+
+    /// Register the debug image, so it's unregistered when the memory gets
+    /// freed
+    pub(crate) fn register_debug_image(&mut self, registration: GdbJitImageRegistration) {
+        self.debug_registration = Some(registration);
+    }
 }
 
 fn round_up(size: usize, multiple: usize) -> usize {
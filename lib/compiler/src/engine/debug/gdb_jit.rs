@@ -0,0 +1,156 @@
+//! The JIT compilation interface of GDB, also implemented by LLDB.
+//!
+//! Debuggers put a breakpoint on `__jit_debug_register_code`, and read the
+//! in-memory object files listed by `__jit_debug_descriptor` whenever it is
+//! called. See the "JIT Interface" chapter of the GDB manual.
+
+use lazy_static::lazy_static;
+use std::ptr;
+use std::sync::Mutex;
+
+const JIT_NOACTION: u32 = 0;
+const JIT_REGISTER_FN: u32 = 1;
+const JIT_UNREGISTER_FN: u32 = 2;
+
+#[repr(C)]
+struct JitCodeEntry {
+    next_entry: *mut JitCodeEntry,
+    prev_entry: *mut JitCodeEntry,
+    symfile_addr: *const u8,
+    symfile_size: u64,
+}
+
+#[repr(C)]
+struct JitDescriptor {
+    version: u32,
+    action_flag: u32,
+    relevant_entry: *mut JitCodeEntry,
+    first_entry: *mut JitCodeEntry,
+}
+
+#[no_mangle]
+#[used]
+static mut __jit_debug_descriptor: JitDescriptor = JitDescriptor {
+    version: 1,
+    action_flag: JIT_NOACTION,
+    relevant_entry: ptr::null_mut(),
+    first_entry: ptr::null_mut(),
+};
+
+#[no_mangle]
+#[inline(never)]
+extern "C" fn __jit_debug_register_code() {
+    // The debugger breaks here, so the call must not be optimized away.
+    unsafe {
+        ptr::read_volatile(ptr::addr_of!(__jit_debug_descriptor.action_flag));
+    }
+}
+
+lazy_static! {
+    /// Serializes the updates of the descriptor, which is shared by all the
+    /// engines of the process.
+    static ref GDB_REGISTRATION: Mutex<()> = Mutex::new(());
+}
+
+/// An object file registered with the debuggers, unregistered when dropped.
+pub(crate) struct GdbJitImageRegistration {
+    entry: *mut JitCodeEntry,
+    image: Box<[u8]>,
+}
+
+impl GdbJitImageRegistration {
+    /// Registers an in-memory object file describing compiled code.
+    pub(crate) fn register(image: Vec<u8>) -> Self {
+        let image = image.into_boxed_slice();
+        let entry = Box::into_raw(Box::new(JitCodeEntry {
+            next_entry: ptr::null_mut(),
+            prev_entry: ptr::null_mut(),
+            symfile_addr: image.as_ptr(),
+            symfile_size: image.len() as u64,
+        }));
+        let _guard = GDB_REGISTRATION.lock().unwrap();
+        unsafe {
+            let descriptor = &mut *ptr::addr_of_mut!(__jit_debug_descriptor);
+            (*entry).next_entry = descriptor.first_entry;
+            if !descriptor.first_entry.is_null() {
+                (*descriptor.first_entry).prev_entry = entry;
+            }
+            descriptor.first_entry = entry;
+            notify_debugger(descriptor, entry, JIT_REGISTER_FN);
+        }
+        Self { entry, image }
+    }
+
+    /// The registered object file.
+    #[cfg(test)]
+    fn image(&self) -> &[u8] {
+        &self.image
+    }
+}
+
+unsafe fn notify_debugger(descriptor: &mut JitDescriptor, entry: *mut JitCodeEntry, action: u32) {
+    descriptor.relevant_entry = entry;
+    descriptor.action_flag = action;
+    __jit_debug_register_code();
+    descriptor.relevant_entry = ptr::null_mut();
+    descriptor.action_flag = JIT_NOACTION;
+}
+
+impl Drop for GdbJitImageRegistration {
+    fn drop(&mut self) {
+        let _guard = GDB_REGISTRATION.lock().unwrap();
+        unsafe {
+            let descriptor = &mut *ptr::addr_of_mut!(__jit_debug_descriptor);
+            let entry = &mut *self.entry;
+            debug_assert_eq!(entry.symfile_addr, self.image.as_ptr());
+            if entry.prev_entry.is_null() {
+                descriptor.first_entry = entry.next_entry;
+            } else {
+                (*entry.prev_entry).next_entry = entry.next_entry;
+            }
+            if !entry.next_entry.is_null() {
+                (*entry.next_entry).prev_entry = entry.prev_entry;
+            }
+            notify_debugger(descriptor, self.entry, JIT_UNREGISTER_FN);
+            drop(Box::from_raw(self.entry));
+        }
+    }
+}
+
+// The entry is only accessed with the registration lock held.
+unsafe impl Send for GdbJitImageRegistration {}
+unsafe impl Sync for GdbJitImageRegistration {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registered_images() -> Vec<*const u8> {
+        let _guard = GDB_REGISTRATION.lock().unwrap();
+        let mut images = vec![];
+        unsafe {
+            let mut entry = (*ptr::addr_of!(__jit_debug_descriptor)).first_entry;
+            while !entry.is_null() {
+                images.push((*entry).symfile_addr);
+                entry = (*entry).next_entry;
+            }
+        }
+        images
+    }
+
+    #[test]
+    fn images_are_listed_while_registered() {
+        let first = GdbJitImageRegistration::register(vec![1, 2, 3]);
+        let second = GdbJitImageRegistration::register(vec![4, 5]);
+        let images = registered_images();
+        assert!(images.contains(&first.image().as_ptr()));
+        assert!(images.contains(&second.image().as_ptr()));
+
+        let first_image = first.image().as_ptr();
+        drop(first);
+        let images = registered_images();
+        assert!(!images.contains(&first_image));
+        assert!(images.contains(&second.image().as_ptr()));
+        assert_eq!(second.image(), &[4, 5]);
+    }
+}
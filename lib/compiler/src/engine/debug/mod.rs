@@ -0,0 +1,15 @@
+//! Source-level debugging of the compiled code.
+//!
+//! An engine with debug info enabled translates the DWARF debugging
+//! information of the modules it loads to their compiled code, and
+//! registers it with the JIT interface of GDB and LLDB, so that they can
+//! set breakpoints on the source lines of the modules.
+
+mod gdb_jit;
+mod transform;
+
+pub(crate) use self::gdb_jit::GdbJitImageRegistration;
+pub(crate) use self::transform::create_gdb_jit_image;
+
+#[cfg(test)]
+pub(crate) use self::transform::tests;
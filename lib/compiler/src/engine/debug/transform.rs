@@ -0,0 +1,828 @@
+//! Translation of the DWARF debugging information of a module to the code
+//! compiled from it.
+//!
+//! The code addresses in the DWARF of a WebAssembly module are offsets in
+//! its code section. The line programs of the module are rewritten for the
+//! native code with the address maps of the compiled functions, and the
+//! functions are described as the subprograms of a single compilation unit.
+//! Variables and types aren't translated: debuggers can set breakpoints on
+//! source lines and step through them, but not inspect the locals.
+//!
+//! The translated sections are wrapped in an ELF image whose `.text`
+//! section is the code of the module, as expected by the GDB JIT interface.
+
+use crate::FunctionExtent;
+use gimli::write::{
+    self, Address, AttributeValue, EndianVec, FileId, LineProgram, LineString, Range, RangeList,
+    Sections, Unit,
+};
+use gimli::{constants, read, Encoding, Format, LineEncoding, LittleEndian, SectionId};
+use object::elf;
+use object::write::elf::{FileHeader, ProgramHeader, SectionHeader, Sym, Writer};
+use object::Endianness;
+use std::collections::{BTreeMap, HashMap};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{
+    CompiledFunctionFrameInfo, FunctionAddressMap, LocalFunctionIndex, ModuleInfo, SourceLoc,
+};
+
+type Reader<'a> = read::EndianSlice<'a, LittleEndian>;
+
+/// A compiled function of the module.
+struct CompiledFunction<'a> {
+    name: String,
+    address: u64,
+    len: u64,
+    address_map: &'a FunctionAddressMap,
+}
+
+/// A source line of the module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SourceLine {
+    /// The index of the file in [`ModuleDebugInfo::files`].
+    file: usize,
+    line: u64,
+    column: u64,
+}
+
+/// A sequence of a line program of the module, mapping a range of its code
+/// section to source lines.
+struct Sequence {
+    start: u64,
+    end: u64,
+    rows: Vec<(u64, SourceLine)>,
+}
+
+/// The source of a function of the module.
+struct Subprogram {
+    name: Option<String>,
+    linkage_name: Option<String>,
+    decl: Option<SourceLine>,
+}
+
+/// The DWARF debugging information of a module.
+#[derive(Default)]
+struct ModuleDebugInfo {
+    name: Option<String>,
+    comp_dir: Option<String>,
+    language: Option<constants::DwLang>,
+    /// The paths of the source files.
+    files: Vec<String>,
+    file_indices: HashMap<String, usize>,
+    /// The sequences of the line programs, sorted by address.
+    sequences: Vec<Sequence>,
+    /// The subprograms, by the address of their code.
+    subprograms: BTreeMap<u64, Subprogram>,
+}
+
+impl ModuleDebugInfo {
+    fn read(module: &ModuleInfo) -> read::Result<Self> {
+        let dwarf = read::Dwarf::load(|id: SectionId| -> read::Result<Reader> {
+            let data = module
+                .custom_sections
+                .get(id.name())
+                .map_or(&[][..], |index| &module.custom_sections_data[*index]);
+            Ok(read::EndianSlice::new(data, LittleEndian))
+        })?;
+        let mut info = Self::default();
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            info.read_unit(&dwarf, &unit)?;
+        }
+        info.sequences.sort_by_key(|sequence| sequence.start);
+        Ok(info)
+    }
+
+    fn read_unit(
+        &mut self,
+        dwarf: &read::Dwarf<Reader>,
+        unit: &read::Unit<Reader>,
+    ) -> read::Result<()> {
+        if self.name.is_none() {
+            self.name = unit.name.map(|name| name.to_string_lossy().into_owned());
+            self.comp_dir = unit.comp_dir.map(|dir| dir.to_string_lossy().into_owned());
+        }
+
+        if let Some(program) = unit.line_program.clone() {
+            let mut rows = program.rows();
+            let mut sequence: Option<Sequence> = None;
+            while let Some((header, row)) = rows.next_row()? {
+                if row.end_sequence() {
+                    if let Some(mut sequence) = sequence.take() {
+                        sequence.end = row.address();
+                        // Sequences at address 0 describe functions removed
+                        // by the linker, as no function starts there.
+                        if sequence.start != 0 && sequence.start < sequence.end {
+                            self.sequences.push(sequence);
+                        }
+                    }
+                    continue;
+                }
+                let file = match self.file(dwarf, unit, header, row.file_index())? {
+                    Some(file) => file,
+                    None => continue,
+                };
+                let line = SourceLine {
+                    file,
+                    line: row.line().map_or(0, |line| line.get()),
+                    column: match row.column() {
+                        read::ColumnType::LeftEdge => 0,
+                        read::ColumnType::Column(column) => column.get(),
+                    },
+                };
+                sequence
+                    .get_or_insert_with(|| Sequence {
+                        start: row.address(),
+                        end: 0,
+                        rows: vec![],
+                    })
+                    .rows
+                    .push((row.address(), line));
+            }
+        }
+
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs()? {
+            match entry.tag() {
+                constants::DW_TAG_compile_unit => {
+                    if let Some(read::AttributeValue::Language(language)) =
+                        entry.attr_value(constants::DW_AT_language)?
+                    {
+                        self.language.get_or_insert(language);
+                    }
+                }
+                constants::DW_TAG_subprogram => {
+                    let low_pc = match entry.attr_value(constants::DW_AT_low_pc)? {
+                        Some(low_pc) => match dwarf.attr_address(unit, low_pc)? {
+                            Some(low_pc) if low_pc != 0 => low_pc,
+                            _ => continue,
+                        },
+                        None => continue,
+                    };
+                    // The names and declarations of methods and of inlined
+                    // functions are on their declaration.
+                    let mut declaration = None;
+                    for attr in [
+                        constants::DW_AT_specification,
+                        constants::DW_AT_abstract_origin,
+                    ] {
+                        if let Some(read::AttributeValue::UnitRef(offset)) =
+                            entry.attr_value(attr)?
+                        {
+                            declaration = Some(unit.entry(offset)?);
+                            break;
+                        }
+                    }
+                    let attr = |name| match entry.attr_value(name)? {
+                        Some(value) => Ok(Some(value)),
+                        None => match &declaration {
+                            Some(declaration) => declaration.attr_value(name),
+                            None => Ok(None),
+                        },
+                    };
+                    let string = |name| -> read::Result<Option<String>> {
+                        attr(name)?
+                            .map(|value| {
+                                dwarf
+                                    .attr_string(unit, value)
+                                    .map(|name| name.to_string_lossy().into_owned())
+                            })
+                            .transpose()
+                    };
+                    let name = string(constants::DW_AT_name)?;
+                    let linkage_name = string(constants::DW_AT_linkage_name)?;
+                    let decl_file = match attr(constants::DW_AT_decl_file)? {
+                        Some(read::AttributeValue::FileIndex(file)) => Some(file),
+                        _ => None,
+                    };
+                    let decl_line =
+                        attr(constants::DW_AT_decl_line)?.and_then(|line| line.udata_value());
+                    let decl = match (decl_file, decl_line, &unit.line_program) {
+                        (Some(file), Some(line), Some(program)) => self
+                            .file(dwarf, unit, program.header(), file)?
+                            .map(|file| SourceLine {
+                                file,
+                                line,
+                                column: 0,
+                            }),
+                        _ => None,
+                    };
+                    self.subprograms.insert(
+                        low_pc,
+                        Subprogram {
+                            name,
+                            linkage_name,
+                            decl,
+                        },
+                    );
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the index of a file of the line program of a unit.
+    fn file(
+        &mut self,
+        dwarf: &read::Dwarf<Reader>,
+        unit: &read::Unit<Reader>,
+        header: &read::LineProgramHeader<Reader>,
+        index: u64,
+    ) -> read::Result<Option<usize>> {
+        let file = match header.file(index) {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+        let mut path = String::new();
+        if let Some(comp_dir) = unit.comp_dir {
+            path = comp_dir.to_string_lossy().into_owned();
+        }
+        if let Some(directory) = file.directory(header) {
+            let directory = dwarf.attr_string(unit, directory)?;
+            path = join(&path, &directory.to_string_lossy());
+        }
+        let name = dwarf.attr_string(unit, file.path_name())?;
+        let path = join(&path, &name.to_string_lossy());
+        if let Some(file) = self.file_indices.get(&path) {
+            return Ok(Some(*file));
+        }
+        self.files.push(path.clone());
+        self.file_indices.insert(path, self.files.len() - 1);
+        Ok(Some(self.files.len() - 1))
+    }
+
+    /// Returns the source line of an offset in the code section.
+    fn line(&self, address: u64) -> Option<SourceLine> {
+        let index = self
+            .sequences
+            .partition_point(|sequence| sequence.start <= address);
+        let sequence = self.sequences[..index]
+            .last()
+            .filter(|sequence| address < sequence.end)?;
+        let index = sequence.rows.partition_point(|(row, _)| *row <= address);
+        sequence.rows[..index].last().map(|(_, line)| *line)
+    }
+}
+
+fn join(directory: &str, path: &str) -> String {
+    let is_absolute = path.starts_with('/') || path.get(1..2) == Some(":");
+    if directory.is_empty() || is_absolute {
+        path.to_string()
+    } else {
+        format!("{}/{}", directory.trim_end_matches('/'), path)
+    }
+}
+
+/// Creates the ELF image describing the code compiled from a module to
+/// debuggers, if the module has DWARF debugging information.
+pub(crate) fn create_gdb_jit_image(
+    module: &ModuleInfo,
+    functions: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    frame_info: &PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+) -> Option<Vec<u8>> {
+    if !module.custom_sections.contains_key(".debug_info") {
+        return None;
+    }
+    let machine = elf_machine()?;
+    let functions = functions
+        .iter()
+        .map(|(index, extent)| {
+            let function_index = module.func_index(index);
+            CompiledFunction {
+                name: match module.function_names.get(&function_index) {
+                    Some(name) => name.clone(),
+                    None => format!("function[{}]", function_index.index()),
+                },
+                address: extent.ptr.0 as u64,
+                len: extent.length as u64,
+                address_map: &frame_info[index].address_map,
+            }
+        })
+        .collect::<Vec<_>>();
+    let info = ModuleDebugInfo::read(module).ok()?;
+    let sections = translate(module, &info, &functions).ok()?;
+    write_image(machine, &sections, &functions).ok()
+}
+
+/// The source location of the code of a function, from its address map.
+fn source_locations(
+    address_map: &FunctionAddressMap,
+) -> impl Iterator<Item = (u64, SourceLoc)> + '_ {
+    // The address map of some compilers only has the start of the function.
+    let start = match address_map.instructions.first() {
+        Some(instruction) if instruction.code_offset == 0 => None,
+        _ => Some((0, address_map.start_srcloc)),
+    };
+    start.into_iter().chain(
+        address_map
+            .instructions
+            .iter()
+            .map(|instruction| (instruction.code_offset as u64, instruction.srcloc)),
+    )
+}
+
+/// Translates the debugging information of a module to its compiled
+/// functions.
+fn translate(
+    module: &ModuleInfo,
+    info: &ModuleDebugInfo,
+    functions: &[CompiledFunction],
+) -> write::Result<Sections<EndianVec<LittleEndian>>> {
+    let encoding = Encoding {
+        format: Format::Dwarf32,
+        version: 4,
+        address_size: 8,
+    };
+    let code_section_offset = module.code_section_offset as u64;
+    let to_code_offset = |srcloc: SourceLoc| {
+        if srcloc.is_default() {
+            None
+        } else {
+            (srcloc.bits() as u64).checked_sub(code_section_offset)
+        }
+    };
+    let name = info
+        .name
+        .clone()
+        .or_else(|| module.name.clone())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "wasm".to_string());
+    let comp_dir = info.comp_dir.clone().unwrap_or_default();
+
+    let mut program = LineProgram::new(
+        encoding,
+        LineEncoding::default(),
+        LineString::String(comp_dir.clone().into_bytes()),
+        LineString::String(name.clone().into_bytes()),
+        None,
+    );
+    let mut file_ids: HashMap<usize, FileId> = HashMap::new();
+    let mut file_id = |program: &mut LineProgram, file: usize| {
+        *file_ids.entry(file).or_insert_with(|| {
+            let path = &info.files[file];
+            let (directory, name) = match path.rfind('/') {
+                Some(0) => ("/", &path[1..]),
+                Some(index) => (&path[..index], &path[index + 1..]),
+                None => ("", path.as_str()),
+            };
+            let directory = if directory.is_empty() {
+                program.default_directory()
+            } else {
+                program.add_directory(LineString::String(directory.as_bytes().to_vec()))
+            };
+            let name = if name.is_empty() { path } else { name };
+            program.add_file(
+                LineString::String(name.as_bytes().to_vec()),
+                directory,
+                None,
+            )
+        })
+    };
+
+    for function in functions {
+        program.begin_sequence(Some(Address::Constant(function.address)));
+        let mut previous = None;
+        for (code_offset, srcloc) in source_locations(function.address_map) {
+            let line = match to_code_offset(srcloc).and_then(|address| info.line(address)) {
+                Some(line) => line,
+                None => continue,
+            };
+            if previous == Some(line) {
+                continue;
+            }
+            previous = Some(line);
+            let file = file_id(&mut program, line.file);
+            let row = program.row();
+            row.address_offset = code_offset;
+            row.file = file;
+            row.line = line.line;
+            row.column = line.column;
+            row.is_statement = true;
+            program.generate_row();
+        }
+        program.end_sequence(function.len);
+    }
+
+    let mut dwarf = write::Dwarf::new();
+    let unit_id = dwarf.units.add(Unit::new(encoding, program));
+    let unit = dwarf.units.get_mut(unit_id);
+    let ranges = unit.ranges.add(RangeList(
+        functions
+            .iter()
+            .map(|function| Range::StartLength {
+                begin: Address::Constant(function.address),
+                length: function.len,
+            })
+            .collect(),
+    ));
+    let root = unit.root();
+    let entry = unit.get_mut(root);
+    entry.set(
+        constants::DW_AT_producer,
+        AttributeValue::String(b"wasmer".to_vec()),
+    );
+    entry.set(
+        constants::DW_AT_name,
+        AttributeValue::String(name.into_bytes()),
+    );
+    entry.set(
+        constants::DW_AT_comp_dir,
+        AttributeValue::String(comp_dir.into_bytes()),
+    );
+    if let Some(language) = info.language {
+        entry.set(
+            constants::DW_AT_language,
+            AttributeValue::Language(language),
+        );
+    }
+    entry.set(
+        constants::DW_AT_low_pc,
+        AttributeValue::Address(Address::Constant(0)),
+    );
+    entry.set(
+        constants::DW_AT_ranges,
+        AttributeValue::RangeListRef(ranges),
+    );
+
+    for function in functions {
+        let subprogram = match (
+            to_code_offset(function.address_map.start_srcloc),
+            to_code_offset(function.address_map.end_srcloc),
+        ) {
+            (Some(start), Some(end)) => info.subprograms.range(start..end.max(start + 1)).next(),
+            _ => None,
+        }
+        .map(|(_, subprogram)| subprogram);
+        let name = subprogram
+            .and_then(|subprogram| subprogram.name.clone())
+            .unwrap_or_else(|| function.name.clone());
+        let decl = subprogram.and_then(|subprogram| subprogram.decl);
+        let decl_file = decl.map(|decl| file_id(&mut unit.line_program, decl.file));
+
+        let id = unit.add(root, constants::DW_TAG_subprogram);
+        let entry = unit.get_mut(id);
+        entry.set(
+            constants::DW_AT_name,
+            AttributeValue::String(name.into_bytes()),
+        );
+        if let Some(linkage_name) =
+            subprogram.and_then(|subprogram| subprogram.linkage_name.clone())
+        {
+            entry.set(
+                constants::DW_AT_linkage_name,
+                AttributeValue::String(linkage_name.into_bytes()),
+            );
+        }
+        entry.set(constants::DW_AT_external, AttributeValue::Flag(true));
+        entry.set(
+            constants::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(function.address)),
+        );
+        entry.set(
+            constants::DW_AT_high_pc,
+            AttributeValue::Udata(function.len),
+        );
+        if let (Some(decl), Some(decl_file)) = (decl, decl_file) {
+            entry.set(
+                constants::DW_AT_decl_file,
+                AttributeValue::FileIndex(Some(decl_file)),
+            );
+            entry.set(constants::DW_AT_decl_line, AttributeValue::Udata(decl.line));
+        }
+    }
+
+    let mut sections = Sections::new(EndianVec::new(LittleEndian));
+    dwarf.write(&mut sections)?;
+    Ok(sections)
+}
+
+/// Writes an ELF image with the debugging sections, and a `.text` section
+/// and a loadable segment at the address of the functions.
+fn write_image(
+    machine: u16,
+    sections: &Sections<EndianVec<LittleEndian>>,
+    functions: &[CompiledFunction],
+) -> object::write::Result<Vec<u8>> {
+    let mut debug_sections = vec![];
+    sections.for_each(|id, data| -> object::write::Result<()> {
+        if !data.slice().is_empty() {
+            debug_sections.push((id.name(), data.slice().to_vec()));
+        }
+        Ok(())
+    })?;
+    let text_start = functions
+        .iter()
+        .map(|function| function.address)
+        .min()
+        .unwrap_or(0);
+    let text_end = functions
+        .iter()
+        .map(|function| function.address + function.len)
+        .max()
+        .unwrap_or(0);
+
+    let mut image = vec![];
+    let mut writer = Writer::new(Endianness::Little, true, &mut image);
+    writer.reserve_file_header();
+    writer.reserve_program_headers(1);
+    writer.reserve_null_section_index();
+    let text_name = writer.add_section_name(b".text");
+    let text_index = writer.reserve_section_index();
+    let debug_sections = debug_sections
+        .into_iter()
+        .map(|(name, data)| {
+            let name = writer.add_section_name(name.as_bytes());
+            writer.reserve_section_index();
+            let offset = writer.reserve(data.len(), 1);
+            (name, offset, data)
+        })
+        .collect::<Vec<_>>();
+    writer.reserve_null_symbol_index();
+    let symbols = functions
+        .iter()
+        .map(|function| {
+            writer.reserve_symbol_index(Some(text_index));
+            writer.add_string(function.name.as_bytes())
+        })
+        .collect::<Vec<_>>();
+    writer.reserve_symtab_section_index();
+    writer.reserve_strtab_section_index();
+    writer.reserve_shstrtab_section_index();
+    writer.reserve_symtab();
+    writer.reserve_strtab();
+    writer.reserve_shstrtab();
+    writer.reserve_section_headers();
+
+    writer.write_file_header(&FileHeader {
+        os_abi: elf::ELFOSABI_NONE,
+        abi_version: 0,
+        e_type: elf::ET_EXEC,
+        e_machine: machine,
+        e_entry: 0,
+        e_flags: 0,
+    })?;
+    writer.write_align_program_headers();
+    writer.write_program_header(&ProgramHeader {
+        p_type: elf::PT_LOAD,
+        p_flags: elf::PF_R | elf::PF_X,
+        p_offset: 0,
+        p_vaddr: text_start,
+        p_paddr: text_start,
+        p_filesz: 0,
+        p_memsz: text_end - text_start,
+        p_align: 1,
+    });
+    for (_, offset, data) in &debug_sections {
+        writer.pad_until(*offset);
+        writer.write(data);
+    }
+    writer.write_null_symbol();
+    for (function, name) in functions.iter().zip(symbols) {
+        writer.write_symbol(&Sym {
+            name: Some(name),
+            section: Some(text_index),
+            st_info: (elf::STB_GLOBAL << 4) | elf::STT_FUNC,
+            st_other: elf::STV_DEFAULT,
+            st_shndx: 0,
+            st_value: function.address,
+            st_size: function.len,
+        });
+    }
+    writer.write_strtab();
+    writer.write_shstrtab();
+
+    writer.write_null_section_header();
+    writer.write_section_header(&SectionHeader {
+        name: Some(text_name),
+        sh_type: elf::SHT_NOBITS,
+        sh_flags: (elf::SHF_ALLOC | elf::SHF_EXECINSTR) as u64,
+        sh_addr: text_start,
+        sh_offset: 0,
+        sh_size: text_end - text_start,
+        sh_link: 0,
+        sh_info: 0,
+        sh_addralign: 16,
+        sh_entsize: 0,
+    });
+    for (name, offset, data) in &debug_sections {
+        writer.write_section_header(&SectionHeader {
+            name: Some(*name),
+            sh_type: elf::SHT_PROGBITS,
+            sh_flags: 0,
+            sh_addr: 0,
+            sh_offset: *offset as u64,
+            sh_size: data.len() as u64,
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: 1,
+            sh_entsize: 0,
+        });
+    }
+    writer.write_symtab_section_header(1);
+    writer.write_strtab_section_header();
+    writer.write_shstrtab_section_header();
+    debug_assert_eq!(writer.reserved_len(), writer.len());
+    Ok(image)
+}
+
+/// The ELF machine of the host, if the image can describe its code.
+fn elf_machine() -> Option<u16> {
+    if cfg!(target_arch = "x86_64") {
+        Some(elf::EM_X86_64)
+    } else if cfg!(target_arch = "aarch64") && cfg!(target_endian = "little") {
+        Some(elf::EM_AARCH64)
+    } else if cfg!(target_arch = "riscv64") {
+        Some(elf::EM_RISCV)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use wasmer_types::InstructionAddressMap;
+    use wasmer_vm::{FunctionBodyPtr, VMFunctionBody};
+
+    pub(crate) const CODE_SECTION_OFFSET: u32 = 100;
+
+    /// A module whose function at offset 0x10 of the code section has its
+    /// code on lines 1, 2 and 3 of `/src/main.rs`.
+    pub(crate) fn module() -> ModuleInfo {
+        let encoding = Encoding {
+            format: Format::Dwarf32,
+            version: 4,
+            address_size: 4,
+        };
+        let mut program = LineProgram::new(
+            encoding,
+            LineEncoding::default(),
+            LineString::String(b"/src".to_vec()),
+            LineString::String(b"main.rs".to_vec()),
+            None,
+        );
+        let directory = program.default_directory();
+        let file = program.add_file(LineString::String(b"main.rs".to_vec()), directory, None);
+        program.begin_sequence(Some(Address::Constant(0x10)));
+        for (address_offset, line) in [(0, 1), (8, 2), (16, 3)] {
+            let row = program.row();
+            row.address_offset = address_offset;
+            row.file = file;
+            row.line = line;
+            program.generate_row();
+        }
+        program.end_sequence(0x20);
+
+        let mut dwarf = write::Dwarf::new();
+        let unit_id = dwarf.units.add(Unit::new(encoding, program));
+        let unit = dwarf.units.get_mut(unit_id);
+        let root = unit.root();
+        let entry = unit.get_mut(root);
+        entry.set(
+            constants::DW_AT_name,
+            AttributeValue::String(b"main.rs".to_vec()),
+        );
+        entry.set(
+            constants::DW_AT_comp_dir,
+            AttributeValue::String(b"/src".to_vec()),
+        );
+        let subprogram = unit.add(root, constants::DW_TAG_subprogram);
+        let entry = unit.get_mut(subprogram);
+        entry.set(
+            constants::DW_AT_name,
+            AttributeValue::String(b"main".to_vec()),
+        );
+        entry.set(
+            constants::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(0x10)),
+        );
+        entry.set(constants::DW_AT_high_pc, AttributeValue::Udata(0x20));
+        entry.set(
+            constants::DW_AT_decl_file,
+            AttributeValue::FileIndex(Some(file)),
+        );
+        entry.set(constants::DW_AT_decl_line, AttributeValue::Udata(1));
+        let mut sections = Sections::new(EndianVec::new(LittleEndian));
+        dwarf.write(&mut sections).unwrap();
+
+        let mut module = ModuleInfo::new();
+        module.code_section_offset = CODE_SECTION_OFFSET as usize;
+        for (name, data) in section_data(&sections) {
+            let index = module.custom_sections_data.push(data.into());
+            module.custom_sections.insert(name.to_string(), index);
+        }
+        module
+    }
+
+    fn section_data(
+        sections: &Sections<EndianVec<LittleEndian>>,
+    ) -> HashMap<&'static str, Vec<u8>> {
+        let mut data = HashMap::new();
+        sections
+            .for_each(|id, section| -> Result<(), ()> {
+                if !section.slice().is_empty() {
+                    data.insert(id.name(), section.slice().to_vec());
+                }
+                Ok(())
+            })
+            .unwrap();
+        data
+    }
+
+    fn address_map() -> FunctionAddressMap {
+        let instruction = |srcloc: u32, code_offset| InstructionAddressMap {
+            srcloc: SourceLoc::new(CODE_SECTION_OFFSET + srcloc),
+            code_offset,
+            code_len: 4,
+        };
+        FunctionAddressMap {
+            instructions: vec![
+                instruction(0x10, 0),
+                instruction(0x14, 4),
+                instruction(0x18, 12),
+                instruction(0x20, 16),
+                instruction(0x28, 24),
+            ],
+            start_srcloc: SourceLoc::new(CODE_SECTION_OFFSET + 0x10),
+            end_srcloc: SourceLoc::new(CODE_SECTION_OFFSET + 0x30),
+            body_offset: 0,
+            body_len: 32,
+        }
+    }
+
+    #[test]
+    fn lines_are_translated_to_native_addresses() {
+        let module = module();
+        let address_map = address_map();
+        let functions = [CompiledFunction {
+            name: "function[0]".to_string(),
+            address: 0x1000,
+            len: 32,
+            address_map: &address_map,
+        }];
+        let info = ModuleDebugInfo::read(&module).unwrap();
+        let sections = section_data(&translate(&module, &info, &functions).unwrap());
+        let dwarf = read::Dwarf::load(|id: SectionId| -> read::Result<Reader> {
+            let data = sections.get(id.name()).map_or(&[][..], |data| &data[..]);
+            Ok(read::EndianSlice::new(data, LittleEndian))
+        })
+        .unwrap();
+
+        let mut units = dwarf.units();
+        let unit = dwarf.unit(units.next().unwrap().unwrap()).unwrap();
+        let mut rows = unit.line_program.clone().unwrap().rows();
+        let mut lines = vec![];
+        while let Some((header, row)) = rows.next_row().unwrap() {
+            if row.end_sequence() {
+                assert_eq!(row.address(), 0x1020);
+                continue;
+            }
+            let file = row.file(header).unwrap();
+            let name = dwarf.attr_string(&unit, file.path_name()).unwrap();
+            assert_eq!(&*name.to_string_lossy(), "main.rs");
+            lines.push((row.address(), row.line().unwrap().get()));
+        }
+        assert_eq!(lines, [(0x1000, 1), (0x100c, 2), (0x1010, 3)]);
+
+        let mut entries = unit.entries();
+        let mut subprograms = vec![];
+        while let Some((_, entry)) = entries.next_dfs().unwrap() {
+            if entry.tag() == constants::DW_TAG_subprogram {
+                let name = entry.attr_value(constants::DW_AT_name).unwrap().unwrap();
+                let name = dwarf.attr_string(&unit, name).unwrap();
+                let low_pc = entry.attr_value(constants::DW_AT_low_pc).unwrap().unwrap();
+                let decl_line = entry.attr_value(constants::DW_AT_decl_line).unwrap();
+                subprograms.push((
+                    name.to_string_lossy().into_owned(),
+                    dwarf.attr_address(&unit, low_pc).unwrap().unwrap(),
+                    decl_line.and_then(|line| line.udata_value()),
+                ));
+            }
+        }
+        assert_eq!(subprograms, [("main".to_string(), 0x1000, Some(1))]);
+    }
+
+    #[test]
+    fn images_are_only_created_for_modules_with_debug_info() {
+        let mut functions = PrimaryMap::<LocalFunctionIndex, _>::new();
+        functions.push(FunctionExtent {
+            ptr: FunctionBodyPtr(0x1000 as *const VMFunctionBody),
+            length: 32,
+        });
+        let mut frame_info = PrimaryMap::<LocalFunctionIndex, _>::new();
+        frame_info.push(CompiledFunctionFrameInfo {
+            traps: vec![],
+            address_map: address_map(),
+        });
+        let mut module = module();
+
+        if elf_machine().is_some() {
+            let image = create_gdb_jit_image(&module, &functions, &frame_info).unwrap();
+            assert_eq!(&image[..4], b"\x7fELF");
+        }
+        module.custom_sections.clear();
+        assert!(create_gdb_jit_image(&module, &functions, &frame_info).is_none());
+    }
+}
@@ -7,13 +7,18 @@ use crate::Artifact;
 use crate::BaseTunables;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::CodeMemory;
-#[cfg(not(target_arch = "wasm32"))]
-use crate::GlobalFrameInfoRegistration;
 #[cfg(feature = "compiler")]
 use crate::{Compiler, CompilerConfig};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::{FunctionExtent, Tunables};
 #[cfg(not(target_arch = "wasm32"))]
+use crate::{GdbJitImageRegistration, GlobalFrameInfoRegistration};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{Profiler, ProfilingStrategy};
+#[cfg(feature = "compiler")]
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{Tiering, TieringConfig};
+#[cfg(not(target_arch = "wasm32"))]
 use shared_buffer::OwnedBuffer;
 #[cfg(not(target_arch = "wasm32"))]
 use std::path::Path;
@@ -29,8 +34,8 @@ use wasmer_types::{CompileError, Features, ModuleInfo, Target};
 use wasmer_types::{CustomSectionLike, CustomSectionProtection, SectionIndex};
 #[cfg(not(target_arch = "wasm32"))]
 use wasmer_vm::{
-    FunctionBodyPtr, SectionBodyPtr, SignatureRegistry, VMFunctionBody, VMSharedSignatureIndex,
-    VMTrampoline,
+    FunctionBodyPtr, SectionBodyPtr, SignatureRegistry, VMEpoch, VMFunctionBody,
+    VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// A WebAssembly `Universal` Engine.
@@ -43,6 +48,11 @@ pub struct Engine {
     #[cfg(not(target_arch = "wasm32"))]
     tunables: Arc<dyn Tunables + Send + Sync>,
     name: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    epoch: Arc<VMEpoch>,
+    #[cfg(feature = "compiler")]
+    #[cfg(not(target_arch = "wasm32"))]
+    tiering: Option<Arc<Tiering>>,
 }
 
 impl Engine {
@@ -65,15 +75,47 @@ impl Engine {
                 code_memory: vec![],
                 #[cfg(not(target_arch = "wasm32"))]
                 signatures: SignatureRegistry::new(),
+                #[cfg(not(target_arch = "wasm32"))]
+                profiler: None,
+                #[cfg(not(target_arch = "wasm32"))]
+                debug_info: false,
+                #[cfg(not(target_arch = "wasm32"))]
+                backtrace_details: false,
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
             #[cfg(not(target_arch = "wasm32"))]
             tunables: Arc::new(tunables),
             name,
+            #[cfg(not(target_arch = "wasm32"))]
+            epoch: Arc::new(VMEpoch::new()),
+            #[cfg(not(target_arch = "wasm32"))]
+            tiering: None,
         }
     }
 
+    /// Create a new `Engine` compiling with the given config first, and
+    /// recompiling the hot functions with the optimizing compiler of the
+    /// tiering config in the background.
+    #[cfg(feature = "compiler")]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new_tiered(
+        mut compiler_config: Box<dyn CompilerConfig>,
+        tiering: TieringConfig,
+        target: Target,
+        features: Features,
+    ) -> Self {
+        let tiering = Tiering::new(tiering, compiler_config.as_mut(), &target, &features);
+        let mut engine = Self::new(compiler_config, target, features);
+        engine.name = format!(
+            "{}-tiered-{}",
+            engine.name,
+            tiering.optimizing().name().trim_start_matches("engine-")
+        );
+        engine.tiering = Some(Arc::new(tiering));
+        engine
+    }
+
     #[cfg(not(feature = "compiler"))]
     pub fn new(
         compiler_config: Box<dyn CompilerConfig>,
@@ -124,12 +166,23 @@ impl Engine {
                 code_memory: vec![],
                 #[cfg(not(target_arch = "wasm32"))]
                 signatures: SignatureRegistry::new(),
+                #[cfg(not(target_arch = "wasm32"))]
+                profiler: None,
+                #[cfg(not(target_arch = "wasm32"))]
+                debug_info: false,
+                #[cfg(not(target_arch = "wasm32"))]
+                backtrace_details: false,
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
             #[cfg(not(target_arch = "wasm32"))]
             tunables: Arc::new(tunables),
             name: "engine-headless".to_string(),
+            #[cfg(not(target_arch = "wasm32"))]
+            epoch: Arc::new(VMEpoch::new()),
+            #[cfg(feature = "compiler")]
+            #[cfg(not(target_arch = "wasm32"))]
+            tiering: None,
         }
     }
 
@@ -148,6 +201,60 @@ impl Engine {
         &self.target
     }
 
+    /// Returns the epoch counter of this engine, shared by all its stores.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn epoch(&self) -> &Arc<VMEpoch> {
+        &self.epoch
+    }
+
+    /// Increments the epoch of this engine, interrupting the code running
+    /// in the stores whose epoch deadline is reached.
+    ///
+    /// This can be called from any thread, and only affects the modules
+    /// compiled with the `EpochInterruption` middleware.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn increment_epoch(&self) {
+        self.epoch.increment();
+    }
+
+    /// Sets the profiler the functions compiled from now on are described
+    /// to, including the ones recompiled by tiered compilation.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_profiler(&self, profiler: Option<ProfilingStrategy>) {
+        self.inner_mut().set_profiler(profiler);
+        #[cfg(feature = "compiler")]
+        if let Some(tiering) = &self.tiering {
+            tiering.optimizing().set_profiler(profiler);
+        }
+    }
+
+    /// Sets whether the DWARF debugging information of the modules compiled
+    /// from now on is registered with the native debuggers, including for
+    /// the functions recompiled by tiered compilation.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_debug_info(&self, debug_info: bool) {
+        self.inner_mut().set_debug_info(debug_info);
+        #[cfg(feature = "compiler")]
+        if let Some(tiering) = &self.tiering {
+            tiering.optimizing().set_debug_info(debug_info);
+        }
+    }
+
+    /// Sets whether the backtraces through the modules compiled from now on
+    /// are resolved to source locations with their DWARF debugging
+    /// information, including for the functions recompiled by tiered
+    /// compilation.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_backtrace_details(&self, backtrace_details: bool) {
+        self.inner_mut().set_backtrace_details(backtrace_details);
+        #[cfg(feature = "compiler")]
+        if let Some(tiering) = &self.tiering {
+            tiering
+                .optimizing()
+                .set_backtrace_details(backtrace_details);
+        }
+    }
+
     /// Register a signature
     #[cfg(not(target_arch = "wasm32"))]
     pub fn register_signature(&self, func_type: &FunctionType) -> VMSharedSignatureIndex {
@@ -172,11 +279,21 @@ impl Engine {
     #[cfg(feature = "compiler")]
     #[cfg(not(target_arch = "wasm32"))]
     pub fn compile(&self, binary: &[u8]) -> Result<Arc<Artifact>, CompileError> {
-        Ok(Arc::new(Artifact::new(
-            self,
-            binary,
-            self.tunables.as_ref(),
-        )?))
+        let mut artifact = Artifact::new(self, binary, self.tunables.as_ref())?;
+        if let Some(tiering) = &self.tiering {
+            tiering.register(&mut artifact, binary, self.tunables.clone());
+        }
+        Ok(Arc::new(artifact))
+    }
+
+    /// The number of functions whose code was swapped for the code of the
+    /// optimizing compiler, if the engine does tiered compilation.
+    #[cfg(feature = "compiler")]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn optimized_functions(&self) -> Option<usize> {
+        self.tiering
+            .as_ref()
+            .map(|tiering| tiering.optimized_functions())
     }
 
     /// Compile a WebAssembly binary
@@ -304,6 +421,17 @@ pub struct EngineInner {
     /// performantly.
     #[cfg(not(target_arch = "wasm32"))]
     signatures: SignatureRegistry,
+    /// The profiler the compiled functions are described to, if any.
+    #[cfg(not(target_arch = "wasm32"))]
+    profiler: Option<Profiler>,
+    /// Whether the debugging information of the compiled modules is
+    /// registered with the native debuggers.
+    #[cfg(not(target_arch = "wasm32"))]
+    debug_info: bool,
+    /// Whether the backtraces through the compiled modules are resolved to
+    /// source locations.
+    #[cfg(not(target_arch = "wasm32"))]
+    backtrace_details: bool,
 }
 
 impl EngineInner {
@@ -336,7 +464,7 @@ impl EngineInner {
     #[allow(clippy::type_complexity)]
     pub(crate) fn allocate<'a, FunctionBody, CustomSection>(
         &'a mut self,
-        _module: &ModuleInfo,
+        module: &ModuleInfo,
         functions: impl ExactSizeIterator<Item = &'a FunctionBody> + 'a,
         function_call_trampolines: impl ExactSizeIterator<Item = &'a FunctionBody> + 'a,
         dynamic_function_trampolines: impl ExactSizeIterator<Item = &'a FunctionBody> + 'a,
@@ -382,6 +510,15 @@ impl EngineInner {
                     ))
                 })?;
 
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.add_module(
+                module,
+                &allocated_functions,
+                functions_len,
+                function_call_trampolines_len,
+            );
+        }
+
         let allocated_functions_result = allocated_functions
             .drain(0..functions_len)
             .map(|slice| FunctionExtent {
@@ -434,6 +571,50 @@ impl EngineInner {
     /// Make memory containing compiled code executable.
     pub(crate) fn publish_compiled_code(&mut self) {
         self.code_memory.last_mut().unwrap().publish();
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.publish();
+        }
+    }
+
+    /// The profiler the compiled functions are described to, if any.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn profiler(&self) -> Option<ProfilingStrategy> {
+        self.profiler.as_ref().map(Profiler::strategy)
+    }
+
+    /// Sets the profiler the functions compiled from now on are described
+    /// to.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_profiler(&mut self, profiler: Option<ProfilingStrategy>) {
+        self.profiler = profiler.map(Profiler::new);
+    }
+
+    /// Whether the debugging information of the compiled modules is
+    /// registered with the native debuggers.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn debug_info(&self) -> bool {
+        self.debug_info
+    }
+
+    /// Sets whether the debugging information of the modules compiled from
+    /// now on is registered with the native debuggers.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_debug_info(&mut self, debug_info: bool) {
+        self.debug_info = debug_info;
+    }
+
+    /// Whether the backtraces through the compiled modules are resolved to
+    /// source locations.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn backtrace_details(&self) -> bool {
+        self.backtrace_details
+    }
+
+    /// Sets whether the backtraces through the modules compiled from now on
+    /// are resolved to source locations.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_backtrace_details(&mut self, backtrace_details: bool) {
+        self.backtrace_details = backtrace_details;
     }
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -464,6 +645,15 @@ impl EngineInner {
             .unwrap()
             .register_frame_info(frame_info);
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Register the debug image of the code memory with the native debuggers
+    pub(crate) fn register_debug_image(&mut self, image: Vec<u8>) {
+        self.code_memory
+            .last_mut()
+            .unwrap()
+            .register_debug_image(GdbJitImageRegistration::register(image));
+    }
 }
 
 #[cfg(feature = "compiler")]
@@ -17,12 +17,21 @@ mod builder;
 #[cfg(not(target_arch = "wasm32"))]
 mod code_memory;
 #[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
+mod debug;
+#[cfg(feature = "translator")]
 mod inner;
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
 mod link;
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
+mod profiler;
+#[cfg(feature = "compiler")]
+#[cfg(not(target_arch = "wasm32"))]
+mod tiering;
+#[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
 mod unwind;
 
 pub use self::error::{InstantiationError, LinkError};
@@ -31,7 +40,7 @@ pub use self::resolver::resolve_imports;
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::trap::*;
 #[cfg(not(target_arch = "wasm32"))]
-pub use self::tunables::{BaseTunables, Tunables};
+pub use self::tunables::{BaseTunables, PoolingTunables, Tunables};
 
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
@@ -42,7 +51,22 @@ pub use self::builder::EngineBuilder;
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::code_memory::CodeMemory;
 #[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use self::debug::{create_gdb_jit_image, GdbJitImageRegistration};
+#[cfg(feature = "translator")]
 pub use self::inner::{Engine, EngineInner};
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::link::link_module;
+#[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use self::profiler::Profiler;
+#[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::profiler::ProfilingStrategy;
+#[cfg(feature = "compiler")]
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use self::tiering::{ArtifactTiering, Tiering};
+#[cfg(feature = "compiler")]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::tiering::{TieringConfig, DEFAULT_HOT_THRESHOLD, DEFAULT_POLL_INTERVAL};
@@ -0,0 +1,266 @@
+//! Registration of the compiled code with the Linux `perf` profiler.
+//!
+//! An engine configured with a [`ProfilingStrategy`] describes every
+//! function and trampoline it publishes, named after the name section of
+//! its module, so that `perf report` can attribute samples to them instead
+//! of to anonymous addresses. The files are shared by all the engines of
+//! the process, and profiling is best-effort: failing to write them never
+//! makes compilation fail.
+
+use lazy_static::lazy_static;
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::mem;
+use std::str::FromStr;
+use std::sync::Mutex;
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{LocalFunctionIndex, ModuleInfo};
+use wasmer_vm::VMFunctionBody;
+
+/// How an engine describes its compiled code to `perf`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilingStrategy {
+    /// Append the address, size and name of every function to
+    /// `/tmp/perf-<pid>.map`, which `perf report` reads on its own.
+    PerfMap,
+    /// Write every function, with its code, to `jit-<pid>.dump` in the
+    /// temporary directory. The records are merged into a profile taken
+    /// with `perf record -k mono` by `perf inject --jit`, which allows
+    /// annotating the code. Only supported on Linux.
+    JitDump,
+}
+
+impl FromStr for ProfilingStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "perfmap" => Ok(Self::PerfMap),
+            "jitdump" => Ok(Self::JitDump),
+            _ => Err(format!(
+                "unknown profiling strategy `{}`, expected `perfmap` or `jitdump`",
+                s
+            )),
+        }
+    }
+}
+
+/// A function or trampoline to describe to the profiler.
+struct ProfiledCode {
+    name: String,
+    address: usize,
+    len: usize,
+}
+
+/// Describes the code allocated by an engine to the profiler, once it is
+/// published.
+pub(crate) struct Profiler {
+    strategy: ProfilingStrategy,
+    pending: Vec<ProfiledCode>,
+}
+
+impl Profiler {
+    pub(crate) fn new(strategy: ProfilingStrategy) -> Self {
+        Self {
+            strategy,
+            pending: vec![],
+        }
+    }
+
+    pub(crate) fn strategy(&self) -> ProfilingStrategy {
+        self.strategy
+    }
+
+    /// Queues the functions of a module just allocated, followed by its
+    /// function call trampolines and its dynamic function trampolines.
+    pub(crate) fn add_module(
+        &mut self,
+        module: &ModuleInfo,
+        functions: &[&mut [VMFunctionBody]],
+        num_functions: usize,
+        num_function_call_trampolines: usize,
+    ) {
+        let prefix = match &module.name {
+            Some(name) => format!("wasm[{}]", name),
+            None => "wasm".to_string(),
+        };
+        for (i, body) in functions.iter().enumerate() {
+            let name = if i < num_functions {
+                let index = module.func_index(LocalFunctionIndex::new(i));
+                match module.function_names.get(&index) {
+                    Some(name) => format!("{}::{}", prefix, name),
+                    None => format!("{}::function[{}]", prefix, index.index()),
+                }
+            } else if i < num_functions + num_function_call_trampolines {
+                format!("{}::call_trampoline[{}]", prefix, i - num_functions)
+            } else {
+                format!(
+                    "{}::dynamic_trampoline[{}]",
+                    prefix,
+                    i - num_functions - num_function_call_trampolines
+                )
+            };
+            self.pending.push(ProfiledCode {
+                // The perf map is line-based.
+                name: name.replace('\n', " "),
+                address: body.as_ptr() as usize,
+                len: body.len(),
+            });
+        }
+    }
+
+    /// Describes the queued code to the profiler. The code must have been
+    /// published, as the jitdump records contain it.
+    pub(crate) fn publish(&mut self) {
+        let pending = mem::take(&mut self.pending);
+        let _ = match self.strategy {
+            ProfilingStrategy::PerfMap => write_perf_map(&pending),
+            ProfilingStrategy::JitDump => write_jit_dump(&pending),
+        };
+    }
+}
+
+lazy_static! {
+    static ref PERF_MAP: Mutex<Option<File>> = Mutex::new(None);
+}
+
+fn write_perf_map(code: &[ProfiledCode]) -> io::Result<()> {
+    let mut perf_map = PERF_MAP.lock().unwrap();
+    if perf_map.is_none() {
+        let path = format!("/tmp/perf-{}.map", std::process::id());
+        *perf_map = Some(OpenOptions::new().create(true).append(true).open(path)?);
+    }
+    let mut lines = String::new();
+    for code in code {
+        let _ = writeln!(lines, "{:x} {:x} {}", code.address, code.len, code.name);
+    }
+    perf_map.as_mut().unwrap().write_all(lines.as_bytes())
+}
+
+#[cfg(target_os = "linux")]
+fn write_jit_dump(code: &[ProfiledCode]) -> io::Result<()> {
+    let mut jit_dump = JIT_DUMP.lock().unwrap();
+    if jit_dump.is_none() {
+        *jit_dump = Some(JitDump::create()?);
+    }
+    let jit_dump = jit_dump.as_mut().unwrap();
+    for code in code {
+        jit_dump.code_load(code)?;
+    }
+    jit_dump.file.flush()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn write_jit_dump(_code: &[ProfiledCode]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "jitdump files are only supported on Linux",
+    ))
+}
+
+#[cfg(target_os = "linux")]
+lazy_static! {
+    static ref JIT_DUMP: Mutex<Option<JitDump>> = Mutex::new(None);
+}
+
+/// A jitdump file, in the format described in `tools/perf/Documentation/
+/// jitdump-specification.txt` of the Linux sources.
+#[cfg(target_os = "linux")]
+struct JitDump {
+    file: io::BufWriter<File>,
+    // `perf inject` finds the file through this executable mapping.
+    _marker: memmap2::Mmap,
+    code_index: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl JitDump {
+    const MAGIC: u32 = 0x4A69_5444;
+    const VERSION: u32 = 1;
+    const HEADER_SIZE: u32 = 40;
+    const JIT_CODE_LOAD: u32 = 0;
+    const CODE_LOAD_SIZE: usize = 56;
+
+    fn create() -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("jit-{}.dump", std::process::id()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let marker = unsafe {
+            memmap2::MmapOptions::new()
+                .len(Self::HEADER_SIZE as usize)
+                .map_exec(&file)?
+        };
+        let mut file = io::BufWriter::new(file);
+        let mut header = Vec::with_capacity(Self::HEADER_SIZE as usize);
+        header.extend_from_slice(&Self::MAGIC.to_ne_bytes());
+        header.extend_from_slice(&Self::VERSION.to_ne_bytes());
+        header.extend_from_slice(&Self::HEADER_SIZE.to_ne_bytes());
+        header.extend_from_slice(&elf_machine().to_ne_bytes());
+        header.extend_from_slice(&0u32.to_ne_bytes());
+        header.extend_from_slice(&std::process::id().to_ne_bytes());
+        header.extend_from_slice(&timestamp().to_ne_bytes());
+        header.extend_from_slice(&0u64.to_ne_bytes());
+        file.write_all(&header)?;
+        Ok(Self {
+            file,
+            _marker: marker,
+            code_index: 0,
+        })
+    }
+
+    fn code_load(&mut self, code: &ProfiledCode) -> io::Result<()> {
+        let size = Self::CODE_LOAD_SIZE + code.name.len() + 1 + code.len;
+        let mut record = Vec::with_capacity(Self::CODE_LOAD_SIZE);
+        record.extend_from_slice(&Self::JIT_CODE_LOAD.to_ne_bytes());
+        record.extend_from_slice(&(size as u32).to_ne_bytes());
+        record.extend_from_slice(&timestamp().to_ne_bytes());
+        record.extend_from_slice(&std::process::id().to_ne_bytes());
+        record
+            .extend_from_slice(&(unsafe { libc::syscall(libc::SYS_gettid) } as u32).to_ne_bytes());
+        record.extend_from_slice(&(code.address as u64).to_ne_bytes());
+        record.extend_from_slice(&(code.address as u64).to_ne_bytes());
+        record.extend_from_slice(&(code.len as u64).to_ne_bytes());
+        record.extend_from_slice(&self.code_index.to_ne_bytes());
+        self.file.write_all(&record)?;
+        self.file.write_all(code.name.as_bytes())?;
+        self.file.write_all(&[0])?;
+        // The code is published, so it is readable.
+        let body = unsafe { std::slice::from_raw_parts(code.address as *const u8, code.len) };
+        self.file.write_all(body)?;
+        self.code_index += 1;
+        Ok(())
+    }
+}
+
+/// The time on the clock used by `perf record -k mono`.
+#[cfg(target_os = "linux")]
+fn timestamp() -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+    }
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+#[cfg(target_os = "linux")]
+fn elf_machine() -> u32 {
+    if cfg!(target_arch = "x86_64") {
+        62
+    } else if cfg!(target_arch = "aarch64") {
+        183
+    } else if cfg!(target_arch = "riscv64") {
+        243
+    } else if cfg!(target_arch = "x86") {
+        3
+    } else {
+        0
+    }
+}
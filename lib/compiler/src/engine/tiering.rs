@@ -0,0 +1,582 @@
+//! Tiered compilation.
+//!
+//! A tiered [`Engine`] compiles modules with a baseline compiler (usually
+//! Singlepass) so that they can be instantiated quickly, and counts the calls
+//! of their functions. A background thread recompiles the modules whose
+//! functions get hot with an optimizing compiler (Cranelift or LLVM), and
+//! swaps the code of the hot functions for the optimized one.
+//!
+//! The instances created afterwards are created with the optimized code. The
+//! instances already running keep their baseline code, but each baseline
+//! function starts by checking whether it was swapped, in which case it
+//! forwards the call to the optimized code through a slot of the instance
+//! that the tiering thread patches. This also covers the direct calls, which
+//! the baseline code makes to the baseline functions.
+
+use crate::translator::{FunctionMiddleware, MiddlewareReaderState, ModuleMiddleware};
+use crate::{Artifact, ArtifactCreate, CompilerConfig, Engine, Tunables};
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering::SeqCst};
+use std::sync::{Arc, Mutex, Once, RwLock, Weak};
+use std::thread;
+use std::time::Duration;
+use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
+use wasmer_types::{
+    CompileError, Features, LocalFunctionIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
+    MemoryType, MiddlewareError, ModuleInfo, Pages, SignatureIndex, TableIndex, TableType, Target,
+    Type, WASM_PAGE_SIZE,
+};
+use wasmer_vm::{
+    FunctionBodyPtr, InternalStoreHandle, StoreObjects, TableElement, VMCallerCheckedAnyfunc,
+    VMExternObj, VMFuncRef, VMFunctionContext, VMInstance, VMMemoryDefinition,
+    VMSharedSignatureIndex, VMTrampoline,
+};
+use wasmparser::{BlockType, MemArg, Operator};
+
+/// The default number of calls after which a function is recompiled with
+/// the optimizing compiler.
+pub const DEFAULT_HOT_THRESHOLD: u64 = 10_000;
+
+/// The default interval at which the call counters are checked.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The value of the counter of a swapped function. The counter stays
+/// negative when the baseline code keeps incrementing it.
+const SWAPPED: i64 = i64::MIN;
+
+/// The configuration of the tiered compilation of an [`Engine`].
+pub struct TieringConfig {
+    optimizing: Box<dyn CompilerConfig>,
+    hot_threshold: u64,
+    poll_interval: Duration,
+}
+
+impl TieringConfig {
+    /// Creates a configuration recompiling the hot functions with the given
+    /// optimizing compiler.
+    ///
+    /// The optimizing compiler must be configured with the same middlewares
+    /// as the baseline compiler, as the optimized code runs in the instances
+    /// created from the baseline code.
+    pub fn new<T>(optimizing: T) -> Self
+    where
+        T: Into<Box<dyn CompilerConfig>>,
+    {
+        Self {
+            optimizing: optimizing.into(),
+            hot_threshold: DEFAULT_HOT_THRESHOLD,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// Set the number of calls after which a function is recompiled
+    pub fn set_hot_threshold(mut self, hot_threshold: u64) -> Self {
+        self.hot_threshold = hot_threshold;
+        self
+    }
+
+    /// Set the interval at which the call counters are checked
+    pub fn set_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+}
+
+impl fmt::Debug for TieringConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TieringConfig")
+            .field("hot_threshold", &self.hot_threshold)
+            .field("poll_interval", &self.poll_interval)
+            .finish()
+    }
+}
+
+/// The tiering state of an [`Engine`].
+pub(crate) struct Tiering {
+    /// The engine compiling the optimized code.
+    optimizing: Engine,
+    hot_threshold: u64,
+    poll_interval: Duration,
+    /// The artifacts compiled by the engine.
+    artifacts: Mutex<Vec<Weak<ArtifactTiering>>>,
+    /// The number of functions whose code was swapped for optimized code.
+    optimized_functions: AtomicUsize,
+    worker: Once,
+}
+
+impl Tiering {
+    /// Creates the tiering state of an engine compiling with the `baseline`
+    /// compiler, adding the call counting middleware to both compilers.
+    pub(crate) fn new(
+        config: TieringConfig,
+        baseline: &mut dyn CompilerConfig,
+        target: &Target,
+        features: &Features,
+    ) -> Self {
+        let mut optimizing = config.optimizing;
+        baseline.push_middleware(Arc::new(CallCounting::new(true)));
+        optimizing.push_middleware(Arc::new(CallCounting::new(false)));
+        Self {
+            optimizing: Engine::new(optimizing, target.clone(), features.clone()),
+            hot_threshold: config.hot_threshold,
+            poll_interval: config.poll_interval,
+            artifacts: Mutex::new(vec![]),
+            optimized_functions: AtomicUsize::new(0),
+            worker: Once::new(),
+        }
+    }
+
+    /// The engine compiling the optimized code.
+    pub(crate) fn optimizing(&self) -> &Engine {
+        &self.optimizing
+    }
+
+    /// The number of functions whose code was swapped for optimized code.
+    pub(crate) fn optimized_functions(&self) -> usize {
+        self.optimized_functions.load(SeqCst)
+    }
+
+    /// Starts counting the calls of the functions of an artifact compiled
+    /// with the baseline compiler.
+    pub(crate) fn register(
+        self: &Arc<Self>,
+        artifact: &mut Artifact,
+        binary: &[u8],
+        tunables: Arc<dyn Tunables + Send + Sync>,
+    ) {
+        if !artifact.allocated() {
+            return;
+        }
+        let tiering = Arc::new(ArtifactTiering::new(artifact, binary, tunables));
+        self.artifacts
+            .lock()
+            .unwrap()
+            .push(Arc::downgrade(&tiering));
+        artifact.set_tiering(tiering);
+
+        self.worker.call_once(|| {
+            let tiering = Arc::downgrade(self);
+            let poll_interval = self.poll_interval;
+            // Tiering is only an optimization, the baseline code keeps
+            // running if the thread can't be spawned.
+            let _ = thread::Builder::new()
+                .name("wasmer-tiering".to_string())
+                .spawn(move || loop {
+                    thread::sleep(poll_interval);
+                    match tiering.upgrade() {
+                        Some(tiering) => tiering.tier_up(),
+                        None => break,
+                    }
+                });
+        });
+    }
+
+    /// Swaps the hot functions of the artifacts for optimized code.
+    fn tier_up(&self) {
+        let artifacts = {
+            let mut artifacts = self.artifacts.lock().unwrap();
+            artifacts.retain(|artifact| artifact.strong_count() > 0);
+            artifacts
+                .iter()
+                .filter_map(Weak::upgrade)
+                .collect::<Vec<_>>()
+        };
+        for artifact in artifacts {
+            let swapped = artifact.tier_up(&self.optimizing, self.hot_threshold);
+            self.optimized_functions.fetch_add(swapped, SeqCst);
+        }
+    }
+}
+
+/// The optimized code of an artifact.
+enum Optimized {
+    /// No function got hot yet.
+    Pending,
+    /// The module was compiled by the optimizing compiler. The artifact is
+    /// kept so that the traps of the optimized code are still recognized.
+    Ready(Artifact),
+    /// The optimizing compiler failed to compile the module, or the code it
+    /// generated isn't compatible with the baseline one.
+    Failed,
+}
+
+/// The tiering state of an [`Artifact`].
+pub(crate) struct ArtifactTiering {
+    /// The module, to compile it with the optimizing compiler.
+    binary: Vec<u8>,
+    tunables: Arc<dyn Tunables + Send + Sync>,
+    /// The module, that the optimized code must agree with.
+    module_info: Arc<ModuleInfo>,
+    /// The call counters of the functions, shared by all the instances.
+    counters: Arc<CallCounters>,
+    /// The memory the baseline code increments the counters in.
+    counters_memory: LocalMemoryIndex,
+    /// The table the baseline code forwards the swapped functions through.
+    slots_table: LocalTableIndex,
+    /// The signature and call trampoline of each function.
+    signatures: BoxedSlice<LocalFunctionIndex, (VMSharedSignatureIndex, VMTrampoline)>,
+    /// The functions the instances are created with.
+    functions: RwLock<BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>>,
+    /// The slots of the instances, patched when a function is swapped.
+    instances: Mutex<Vec<Weak<InstanceSlots>>>,
+    /// Whether the code of each function was swapped.
+    swapped: Mutex<Vec<bool>>,
+    optimized: Mutex<Optimized>,
+}
+
+impl ArtifactTiering {
+    fn new(artifact: &Artifact, binary: &[u8], tunables: Arc<dyn Tunables + Send + Sync>) -> Self {
+        let module_info = artifact.module_info();
+        let functions = artifact.finished_functions().clone();
+        let signatures = module_info
+            .functions
+            .values()
+            .skip(module_info.num_imported_functions)
+            .map(|signature| {
+                (
+                    artifact.signatures()[*signature],
+                    artifact.finished_function_call_trampolines()[*signature],
+                )
+            })
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+        // The `CallCounting` middleware is the last one of the baseline
+        // compiler, so its memory and table are the last ones of the module.
+        let num_local_memories = module_info.memories.len() - module_info.num_imported_memories;
+        let num_local_tables = module_info.tables.len() - module_info.num_imported_tables;
+        Self {
+            binary: binary.to_vec(),
+            tunables,
+            module_info: Arc::new(module_info.clone()),
+            counters: Arc::new(CallCounters::new(functions.len())),
+            counters_memory: LocalMemoryIndex::new(num_local_memories - 1),
+            slots_table: LocalTableIndex::new(num_local_tables - 1),
+            signatures: signatures.into_boxed_slice(),
+            swapped: Mutex::new(vec![false; functions.len()]),
+            functions: RwLock::new(functions),
+            instances: Mutex::new(vec![]),
+            optimized: Mutex::new(Optimized::Pending),
+        }
+    }
+
+    /// The functions, with the optimized code of the hot ones.
+    pub(crate) fn functions(&self) -> BoxedSlice<LocalFunctionIndex, FunctionBodyPtr> {
+        self.functions.read().unwrap().clone()
+    }
+
+    /// Makes a new instance increment the counters shared by all the
+    /// instances, and gives it the slots its swapped functions are called
+    /// through.
+    ///
+    /// # Safety
+    /// `memory_definitions` must be the locations of the definitions of the
+    /// local memories of `handle`, which must not have run yet.
+    pub(crate) unsafe fn attach(
+        &self,
+        context: &mut StoreObjects,
+        handle: &mut VMInstance,
+        memory_definitions: &[NonNull<VMMemoryDefinition>],
+    ) {
+        // The counters memory of the instance is never accessed, its
+        // definition is redirected to the shared counters instead.
+        *memory_definitions[self.counters_memory.index()].as_ptr() = VMMemoryDefinition {
+            base: self.counters.base(),
+            current_length: self.counters.len() * 8,
+        };
+
+        // The slots are created with the lock held, so that they are either
+        // created with the swapped functions or patched by `tier_up`.
+        let mut instances = self.instances.lock().unwrap();
+        let functions = self.functions.read().unwrap();
+        let slots = Arc::new(InstanceSlots {
+            anyfuncs: functions
+                .iter()
+                .map(|(index, function)| {
+                    let (type_index, call_trampoline) = self.signatures[index];
+                    UnsafeCell::new(VMCallerCheckedAnyfunc {
+                        func_ptr: function.0,
+                        type_index,
+                        vmctx: VMFunctionContext {
+                            vmctx: handle.vmctx_ptr(),
+                        },
+                        call_trampoline,
+                    })
+                })
+                .collect(),
+            _counters: self.counters.clone(),
+        });
+        for (index, anyfunc) in slots.anyfuncs.iter().enumerate() {
+            let anyfunc = VMFuncRef(NonNull::new_unchecked(anyfunc.get()));
+            handle
+                .table_set(
+                    self.slots_table,
+                    index as u32,
+                    TableElement::FuncRef(Some(anyfunc)),
+                )
+                .expect("The slots table has an element per function");
+        }
+        instances.retain(|slots| slots.strong_count() > 0);
+        instances.push(Arc::downgrade(&slots));
+        // The store keeps the slots and the counters alive for as long as
+        // the instance.
+        InternalStoreHandle::new(context, VMExternObj::new(slots));
+    }
+
+    /// Swaps the code of the functions called at least `hot_threshold`
+    /// times, compiling the optimized code first if needed. Returns the
+    /// number of functions swapped.
+    fn tier_up(&self, optimizing: &Engine, hot_threshold: u64) -> usize {
+        let mut swapped = self.swapped.lock().unwrap();
+        let hot = (0..self.counters.len())
+            .filter(|index| !swapped[*index] && self.counters.count(*index) >= hot_threshold as i64)
+            .collect::<Vec<_>>();
+        if hot.is_empty() {
+            return 0;
+        }
+
+        let mut optimized = self.optimized.lock().unwrap();
+        if let Optimized::Pending = *optimized {
+            *optimized = match self.compile(optimizing) {
+                Ok(artifact) => Optimized::Ready(artifact),
+                Err(_) => Optimized::Failed,
+            };
+        }
+        let optimized = match &*optimized {
+            Optimized::Ready(artifact) => artifact.finished_functions(),
+            _ => return 0,
+        };
+
+        {
+            let mut instances = self.instances.lock().unwrap();
+            instances.retain(|slots| slots.strong_count() > 0);
+            let instances = instances
+                .iter()
+                .filter_map(Weak::upgrade)
+                .collect::<Vec<_>>();
+            let mut functions = self.functions.write().unwrap();
+            for index in hot.iter().copied() {
+                let index = LocalFunctionIndex::new(index);
+                functions[index] = optimized[index];
+                for slots in &instances {
+                    slots.patch(index, optimized[index]);
+                }
+            }
+        }
+        // The slots are patched before the baseline code is told to use
+        // them.
+        for index in hot.iter().copied() {
+            self.counters.swap(index);
+            swapped[index] = true;
+        }
+        hot.len()
+    }
+
+    /// Compiles the module with the optimizing compiler.
+    fn compile(&self, optimizing: &Engine) -> Result<Artifact, CompileError> {
+        let artifact = Artifact::new(optimizing, &self.binary, self.tunables.as_ref())?;
+        let module_info = artifact.module_info();
+        if module_info.globals != self.module_info.globals
+            || module_info.memories != self.module_info.memories
+            || module_info.tables != self.module_info.tables
+        {
+            return Err(CompileError::Codegen(
+                "The optimizing compiler doesn't lay out the module like the baseline compiler"
+                    .to_string(),
+            ));
+        }
+        Ok(artifact)
+    }
+}
+
+/// The call counters of the functions of an artifact.
+///
+/// They are the memory the code generated by the `CallCounting` middleware
+/// atomically increments them in.
+struct CallCounters(Box<[AtomicI64]>);
+
+impl CallCounters {
+    fn new(len: usize) -> Self {
+        Self((0..len).map(|_| AtomicI64::new(0)).collect())
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn base(&self) -> *mut u8 {
+        self.0.as_ptr() as *mut u8
+    }
+
+    fn count(&self, index: usize) -> i64 {
+        self.0[index].load(SeqCst)
+    }
+
+    fn swap(&self, index: usize) {
+        self.0[index].store(SWAPPED, SeqCst);
+    }
+}
+
+/// The functions of an instance, as called by the baseline code once they
+/// are swapped.
+struct InstanceSlots {
+    anyfuncs: Box<[UnsafeCell<VMCallerCheckedAnyfunc>]>,
+    /// The counters the instance increments.
+    _counters: Arc<CallCounters>,
+}
+
+/// # Safety
+/// The slots are only written by the tiering thread, before the counter of
+/// their function tells the generated code to read them.
+unsafe impl Send for InstanceSlots {}
+/// # Safety
+/// See the `Send` implementation.
+unsafe impl Sync for InstanceSlots {}
+
+impl InstanceSlots {
+    fn patch(&self, index: LocalFunctionIndex, function: FunctionBodyPtr) {
+        unsafe {
+            ptr::addr_of_mut!((*self.anyfuncs[index.index()].get()).func_ptr)
+                .write_volatile(function.0);
+        }
+    }
+}
+
+/// The function the code generated for a local function forwards its calls
+/// to once it is swapped.
+#[derive(Debug, Clone, Copy)]
+struct Forward {
+    signature: SignatureIndex,
+    num_params: u32,
+}
+
+/// Where the code generated for the module being compiled counts and
+/// forwards the calls.
+#[derive(Debug)]
+struct CallCountingLayout {
+    counters: MemoryIndex,
+    slots: TableIndex,
+    functions: PrimaryMap<LocalFunctionIndex, Forward>,
+}
+
+/// A middleware counting the calls of each local function.
+///
+/// The counters are stored in a memory, added after the ones of the module.
+/// Each function forwards its calls to the slot of its optimized code in a
+/// table, added after the ones of the module, once its counter is negative.
+#[derive(Debug)]
+struct CallCounting {
+    /// Whether the calls are counted. The optimizing compiler only adds the
+    /// memory and the table, so that its code agrees with the baseline code
+    /// on them.
+    count: bool,
+    /// The layout of the module being compiled.
+    ///
+    /// The modules are compiled one at a time with the lock of the engine
+    /// held, so a single value is enough.
+    layout: Mutex<Option<CallCountingLayout>>,
+}
+
+impl CallCounting {
+    fn new(count: bool) -> Self {
+        Self {
+            count,
+            layout: Mutex::new(None),
+        }
+    }
+}
+
+impl ModuleMiddleware for CallCounting {
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let layout = self.layout.lock().unwrap();
+        let layout = layout.as_ref().unwrap();
+        Box::new(FunctionCallCounting {
+            prologue: self.count.then(|| {
+                let forward = layout.functions[local_function_index];
+                let mut prologue = vec![
+                    Operator::I32Const { value: 0 },
+                    Operator::I64Const { value: 1 },
+                    Operator::I64AtomicRmwAdd {
+                        memarg: MemArg {
+                            align: 3,
+                            max_align: 3,
+                            offset: local_function_index.index() as u64 * 8,
+                            memory: layout.counters.as_u32(),
+                        },
+                    },
+                    Operator::I64Const { value: 0 },
+                    Operator::I64LtS,
+                    Operator::If {
+                        blockty: BlockType::Empty,
+                    },
+                ];
+                prologue.extend(
+                    (0..forward.num_params).map(|local_index| Operator::LocalGet { local_index }),
+                );
+                prologue.extend([
+                    Operator::I32Const {
+                        value: local_function_index.as_u32() as i32,
+                    },
+                    Operator::CallIndirect {
+                        type_index: forward.signature.as_u32(),
+                        table_index: layout.slots.as_u32(),
+                        table_byte: 0,
+                    },
+                    Operator::Return,
+                    Operator::End,
+                ]);
+                prologue
+            }),
+        })
+    }
+
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let functions = module_info
+            .functions
+            .values()
+            .skip(module_info.num_imported_functions)
+            .map(|signature| Forward {
+                signature: *signature,
+                num_params: module_info.signatures[*signature].params().len() as u32,
+            })
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+        let num_functions = functions.len() as u32;
+        let pages = Pages((num_functions * 8).div_ceil(WASM_PAGE_SIZE as u32).max(1));
+        let counters = module_info
+            .memories
+            .push(MemoryType::new(pages, Some(pages), false));
+        let slots = module_info.tables.push(TableType::new(
+            Type::FuncRef,
+            num_functions,
+            Some(num_functions),
+        ));
+        *self.layout.lock().unwrap() = Some(CallCountingLayout {
+            counters,
+            slots,
+            functions,
+        });
+    }
+}
+
+#[derive(Debug)]
+struct FunctionCallCounting {
+    /// The code to run on entry, until it is emitted.
+    prologue: Option<Vec<Operator<'static>>>,
+}
+
+impl FunctionMiddleware for FunctionCallCounting {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if let Some(prologue) = self.prologue.take() {
+            state.extend(&prologue);
+        }
+        state.push_operator(operator);
+        Ok(())
+    }
+}
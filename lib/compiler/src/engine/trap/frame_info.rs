@@ -11,6 +11,7 @@
 //! let module: ModuleInfo = ...;
 //! FRAME_INFO.register(module, compiled_functions);
 //! ```
+use super::symbolize::Symbolizer;
 use std::cmp;
 use std::collections::BTreeMap;
 use std::sync::{Arc, RwLock};
@@ -51,12 +52,14 @@ pub struct GlobalFrameInfoRegistration {
     key: usize,
 }
 
-#[derive(Debug)]
 struct ModuleInfoFrameInfo {
     start: usize,
     functions: BTreeMap<usize, FunctionInfo>,
     module: Arc<ModuleInfo>,
     frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+    /// Resolves the frames to source code locations, if backtrace details
+    /// are enabled and the module has debugging information.
+    symbolizer: Option<Symbolizer>,
 }
 
 impl ModuleInfoFrameInfo {
@@ -130,13 +133,17 @@ impl GlobalFrameInfo {
             None => instr_map.start_srcloc,
         };
         let func_index = module.module.func_index(func.local_index);
-        Some(FrameInfo::new(
+        let frame = FrameInfo::new(
             module.module.name(),
             func_index.index() as u32,
             module.module.function_names.get(&func_index).cloned(),
             instr_map.start_srcloc,
             instr,
-        ))
+        );
+        Some(match &module.symbolizer {
+            Some(symbolizer) => frame.with_symbols(symbolizer.symbols(instr)),
+            None => frame,
+        })
     }
 
     /// Fetches trap information about a program counter in a backtrace.
@@ -161,6 +168,21 @@ impl GlobalFrameInfo {
     }
 }
 
+impl GlobalFrameInfoRegistration {
+    /// Resolves the frames of the registered module to source files and
+    /// lines, using the DWARF debugging information of the module.
+    ///
+    /// The debugging information is only parsed when a frame of the module
+    /// is first looked up. Modules without debugging information are not
+    /// affected.
+    pub fn enable_backtrace_details(&self) {
+        let mut info = FRAME_INFO.write().unwrap();
+        if let Some(module) = info.ranges.get_mut(&self.key) {
+            module.symbolizer = Symbolizer::new(module.module.clone());
+        }
+    }
+}
+
 impl Drop for GlobalFrameInfoRegistration {
     fn drop(&mut self) {
         if let Ok(mut info) = FRAME_INFO.write() {
@@ -236,6 +258,7 @@ pub fn register(
             functions,
             module,
             frame_infos,
+            symbolizer: None,
         },
     );
     assert!(prev.is_none());
@@ -1,5 +1,6 @@
 mod frame_info;
 mod stack;
+mod symbolize;
 pub use frame_info::{
     register as register_frame_info, FunctionExtent, GlobalFrameInfoRegistration, FRAME_INFO,
 };
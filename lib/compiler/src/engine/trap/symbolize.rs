@@ -0,0 +1,132 @@
+//! Resolution of the frames of a backtrace to source code locations, using
+//! the DWARF debugging information embedded in the custom sections of the
+//! module.
+//!
+//! The debugging information describes the offsets of the instructions
+//! relative to the start of the code section. It is only parsed the first
+//! time one of the frames of the module is resolved, as most modules never
+//! trap.
+
+use addr2line::gimli::{Dwarf, EndianSlice, LittleEndian};
+use addr2line::Context;
+use self_cell::self_cell;
+use std::sync::{Arc, Mutex, OnceLock};
+use wasmer_types::{FrameSymbol, ModuleInfo, SourceLoc};
+
+type DebugContext<'a> = Context<EndianSlice<'a, LittleEndian>>;
+
+self_cell!(
+    struct ModuleDebugContext {
+        owner: Arc<ModuleInfo>,
+
+        #[not_covariant]
+        dependent: DebugContext,
+    }
+);
+
+/// Resolves the instructions of a module to the source code locations they
+/// were compiled from.
+pub(crate) struct Symbolizer {
+    module: Arc<ModuleInfo>,
+    // The context caches the units it parses, so lookups need exclusive
+    // access to it.
+    context: OnceLock<Option<Mutex<ModuleDebugContext>>>,
+}
+
+impl Symbolizer {
+    /// Creates a symbolizer for a module, if it has DWARF debugging
+    /// information.
+    pub(crate) fn new(module: Arc<ModuleInfo>) -> Option<Self> {
+        if !module.custom_sections.contains_key(".debug_info") {
+            return None;
+        }
+        Some(Self {
+            module,
+            context: OnceLock::new(),
+        })
+    }
+
+    /// Returns the source code locations of the instruction at the given
+    /// offset of the module, the innermost inlined function first.
+    pub(crate) fn symbols(&self, instr: SourceLoc) -> Vec<FrameSymbol> {
+        let address = match (instr.bits() as usize).checked_sub(self.module.code_section_offset) {
+            Some(address) if !instr.is_default() => address as u64,
+            _ => return vec![],
+        };
+        let context = match self.context.get_or_init(|| self.parse()) {
+            Some(context) => context,
+            None => return vec![],
+        };
+        let context = context.lock().unwrap();
+        context.with_dependent(|_, context| find_symbols(context, address))
+    }
+
+    fn parse(&self) -> Option<Mutex<ModuleDebugContext>> {
+        ModuleDebugContext::try_new(self.module.clone(), |module| {
+            let dwarf = Dwarf::load(|id| -> Result<_, ()> {
+                let data = module
+                    .custom_sections
+                    .get(id.name())
+                    .map_or(&[][..], |index| &module.custom_sections_data[*index]);
+                Ok(EndianSlice::new(data, LittleEndian))
+            })?;
+            Context::from_dwarf(dwarf).map_err(|_| ())
+        })
+        .ok()
+        .map(Mutex::new)
+    }
+}
+
+fn find_symbols(context: &DebugContext, address: u64) -> Vec<FrameSymbol> {
+    let mut symbols = vec![];
+    let mut frames = match context.find_frames(address).skip_all_loads() {
+        Ok(frames) => frames,
+        Err(_) => return symbols,
+    };
+    while let Ok(Some(frame)) = frames.next() {
+        let name = frame
+            .function
+            .and_then(|function| function.raw_name().ok().map(|name| name.into_owned()));
+        let (file, line, column) = match frame.location {
+            Some(location) => (
+                location.file.map(str::to_string),
+                location.line,
+                location.column,
+            ),
+            None => (None, None, None),
+        };
+        symbols.push(FrameSymbol::new(name, file, line, column));
+    }
+    symbols
+}
+
+#[cfg(test)]
+#[cfg(feature = "translator")]
+mod tests {
+    use super::*;
+    use crate::engine::debug::tests::{module, CODE_SECTION_OFFSET};
+
+    #[test]
+    fn instructions_are_resolved_to_source_lines() {
+        let symbolizer = Symbolizer::new(Arc::new(module())).unwrap();
+        let symbols = symbolizer.symbols(SourceLoc::new(CODE_SECTION_OFFSET + 0x1a));
+        assert_eq!(
+            symbols,
+            vec![FrameSymbol::new(
+                Some("main".to_string()),
+                Some("/src/main.rs".to_string()),
+                Some(2),
+                None,
+            )]
+        );
+        assert!(symbolizer
+            .symbols(SourceLoc::new(CODE_SECTION_OFFSET + 0x40))
+            .is_empty());
+        assert!(symbolizer.symbols(SourceLoc::default()).is_empty());
+    }
+
+    #[test]
+    fn modules_without_debug_info_are_not_symbolized() {
+        assert!(Symbolizer::new(Arc::new(ModuleInfo::new())).is_none());
+    }
+}
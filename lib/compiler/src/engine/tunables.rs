@@ -1,12 +1,14 @@
 use crate::engine::error::LinkError;
 use std::ptr::NonNull;
+use std::sync::Arc;
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     GlobalType, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, MemoryType,
     ModuleInfo, Pages, PointerWidth, TableIndex, TableType, Target,
 };
-use wasmer_vm::{InternalStoreHandle, MemoryError, StoreObjects};
+use wasmer_vm::{InstanceAllocator, InternalStoreHandle, MemoryError, StoreObjects};
 use wasmer_vm::{MemoryStyle, TableStyle};
+use wasmer_vm::{PoolingConfig, PoolingInstanceAllocator};
 use wasmer_vm::{VMConfig, VMGlobal, VMMemory, VMTable};
 use wasmer_vm::{VMMemoryDefinition, VMTableDefinition};
 
@@ -51,6 +53,23 @@ pub trait Tunables {
         vm_definition_location: NonNull<VMTableDefinition>,
     ) -> Result<VMTable, String>;
 
+    /// Allocate the instance data of a module, returning the allocator
+    /// and the locations of its memory and table definitions.
+    #[allow(clippy::type_complexity, clippy::result_large_err)]
+    fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        Ok(InstanceAllocator::new(module))
+    }
+
     /// Create a global with an unset value.
     fn create_global(&self, ty: GlobalType) -> Result<VMGlobal, String> {
         Ok(VMGlobal::new(ty))
@@ -71,13 +90,13 @@ pub trait Tunables {
         let num_imports = module.num_imported_memories;
         let mut memories: PrimaryMap<LocalMemoryIndex, _> =
             PrimaryMap::with_capacity(module.memories.len() - num_imports);
+        // The location of a local memory is at its local index.
         for (index, mdl) in memory_definition_locations
             .iter()
+            .take(module.memories.len() - num_imports)
             .enumerate()
-            .take(module.memories.len())
-            .skip(num_imports)
         {
-            let mi = MemoryIndex::new(index);
+            let mi = MemoryIndex::new(num_imports + index);
             let ty = &module.memories[mi];
             let style = &memory_styles[mi];
             memories.push(InternalStoreHandle::new(
@@ -105,13 +124,13 @@ pub trait Tunables {
         let num_imports = module.num_imported_tables;
         let mut tables: PrimaryMap<LocalTableIndex, _> =
             PrimaryMap::with_capacity(module.tables.len() - num_imports);
+        // The location of a local table is at its local index.
         for (index, tdl) in table_definition_locations
             .iter()
+            .take(module.tables.len() - num_imports)
             .enumerate()
-            .take(module.tables.len())
-            .skip(num_imports)
         {
-            let ti = TableIndex::new(index);
+            let ti = TableIndex::new(num_imports + index);
             let ty = &module.tables[ti];
             let style = &table_styles[ti];
             tables.push(InternalStoreHandle::new(
@@ -323,9 +342,24 @@ impl Tunables for Box<dyn Tunables + Send + Sync> {
         self.as_ref()
             .create_vm_table(ty, style, vm_definition_location)
     }
+
+    #[allow(clippy::type_complexity, clippy::result_large_err)]
+    fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        self.as_ref().allocate_instance(module)
+    }
 }
 
-impl Tunables for std::sync::Arc<dyn Tunables + Send + Sync> {
+impl Tunables for Arc<dyn Tunables + Send + Sync> {
     fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
         self.as_ref().memory_style(memory)
     }
@@ -365,4 +399,131 @@ impl Tunables for std::sync::Arc<dyn Tunables + Send + Sync> {
         self.as_ref()
             .create_vm_table(ty, style, vm_definition_location)
     }
+
+    #[allow(clippy::type_complexity, clippy::result_large_err)]
+    fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        self.as_ref().allocate_instance(module)
+    }
+}
+
+/// Tunables allocating instances, memories and tables from a
+/// [`PoolingInstanceAllocator`], on top of a base `Tunables`.
+///
+/// Memories and tables which don't fit in the slots of the pool, as well
+/// as the ones owned by the host, are created by the base tunables.
+/// Memories are only pooled when their style is static, with a bound and
+/// a guard which fit in the memory reservation of the pool.
+#[derive(Clone)]
+pub struct PoolingTunables<T = BaseTunables> {
+    base: T,
+    allocator: Arc<PoolingInstanceAllocator>,
+}
+
+impl<T: Tunables> PoolingTunables<T> {
+    /// Creates pooling tunables, reserving the slots of the pool.
+    pub fn new(base: T, config: PoolingConfig) -> Result<Self, String> {
+        Ok(Self {
+            base,
+            allocator: Arc::new(PoolingInstanceAllocator::new(config)?),
+        })
+    }
+
+    /// Returns the pool the instances are allocated from.
+    pub fn allocator(&self) -> &Arc<PoolingInstanceAllocator> {
+        &self.allocator
+    }
+}
+
+impl<T: Tunables> Tunables for PoolingTunables<T> {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.base.memory_style(memory)
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<VMMemory, MemoryError> {
+        self.base.create_host_memory(ty, style)
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<VMMemory, MemoryError> {
+        match self
+            .allocator
+            .create_memory(ty, style, vm_definition_location)?
+        {
+            Some(memory) => Ok(memory),
+            None => self
+                .base
+                .create_vm_memory(ty, style, vm_definition_location),
+        }
+    }
+
+    fn create_host_table(&self, ty: &TableType, style: &TableStyle) -> Result<VMTable, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<VMTable, String> {
+        match self
+            .allocator
+            .create_table(ty, style, vm_definition_location)?
+        {
+            Some(table) => Ok(table),
+            None => self.base.create_vm_table(ty, style, vm_definition_location),
+        }
+    }
+
+    #[allow(clippy::type_complexity, clippy::result_large_err)]
+    fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        match self
+            .allocator
+            .allocate_instance(module)
+            .map_err(LinkError::Resource)?
+        {
+            Some(allocation) => Ok(allocation),
+            None => self.base.allocate_instance(module),
+        }
+    }
+
+    fn create_global(&self, ty: GlobalType) -> Result<VMGlobal, String> {
+        self.base.create_global(ty)
+    }
+
+    fn vmconfig(&self) -> &VMConfig {
+        self.base.vmconfig()
+    }
 }
@@ -5,7 +5,9 @@ use smallvec::SmallVec;
 use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::ops::{Deref, Range};
-use wasmer_types::{LocalFunctionIndex, MiddlewareError, ModuleInfo, WasmResult};
+use wasmer_types::{
+    LocalFunctionIndex, MiddlewareError, ModuleInfo, TrapCode, WasmError, WasmResult,
+};
 use wasmparser::{BinaryReader, Operator, ValType};
 
 use super::error::from_binaryreadererror_wasmerror;
@@ -29,6 +31,17 @@ pub trait ModuleMiddleware: Debug + Send + Sync {
 
 /// A function middleware specialized for a single function.
 pub trait FunctionMiddleware: Debug {
+    /// Declares the locals the middleware needs, which are added after
+    /// the locals of the function and those of the previous middlewares.
+    ///
+    /// It is called once before any operator is fed, with the number of
+    /// locals declared so far, not counting the parameters of the
+    /// function. The first local returned gets that number plus the number
+    /// of parameters as its index.
+    fn declare_locals(&mut self, _num_locals: u32) -> Vec<ValType> {
+        Vec::new()
+    }
+
     /// Processes the given operator.
     fn feed<'a>(
         &mut self,
@@ -48,6 +61,23 @@ pub struct MiddlewareBinaryReader<'a> {
 
     /// The backing middleware chain for this reader.
     chain: Vec<Box<dyn FunctionMiddleware>>,
+
+    /// The annotation attached to the last operator returned.
+    annotation: Option<Annotation>,
+
+    /// The local declarations of the function, followed by those of the
+    /// middlewares.
+    local_decls: VecDeque<(u32, ValType)>,
+}
+
+/// Information attached by a middleware to an operator it pushes, that
+/// the following middlewares don't see but the compilers act on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Annotation {
+    /// An `Unreachable` operator trapping with this trap code.
+    Trap(TrapCode),
+    /// A `Nop` operator calling the epoch deadline builtin.
+    EpochDeadline,
 }
 
 /// The state of the binary reader. Exposed to middlewares to push their outputs.
@@ -56,8 +86,18 @@ pub struct MiddlewareReaderState<'a> {
     /// Raw binary reader.
     inner: BinaryReader<'a>,
 
-    /// The pending operations added by the middleware.
-    pending_operations: VecDeque<Operator<'a>>,
+    /// The pending operations added by the middleware, along with the
+    /// annotation of the operators pushed by `push_trap` and
+    /// `push_epoch_deadline`.
+    pending_operations: VecDeque<(Operator<'a>, Option<Annotation>)>,
+
+    /// The annotation of the operator currently fed to a middleware.
+    ///
+    /// A middleware forwarding that operator keeps its annotation.
+    fed_annotation: Option<Annotation>,
+
+    /// The offset in the module of the operator read last.
+    operator_offset: usize,
 }
 
 /// Trait for generating middleware chains from "prototype" (generator) chains.
@@ -94,19 +134,58 @@ impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
 impl<'a> MiddlewareReaderState<'a> {
     /// Push an operator.
     pub fn push_operator(&mut self, operator: Operator<'a>) {
-        self.pending_operations.push_back(operator);
+        let annotation = match (&operator, self.fed_annotation) {
+            (Operator::Unreachable, Some(Annotation::Trap(_)))
+            | (Operator::Nop, Some(Annotation::EpochDeadline)) => self.fed_annotation.take(),
+            _ => None,
+        };
+        self.pending_operations.push_back((operator, annotation));
+    }
+
+    /// Push an operator that traps with the given trap code.
+    ///
+    /// This is an `Unreachable` operator for the following middlewares, but
+    /// the compilers report `trap_code` instead of
+    /// `TrapCode::UnreachableCodeReached`.
+    pub fn push_trap(&mut self, trap_code: TrapCode) {
+        self.pending_operations
+            .push_back((Operator::Unreachable, Some(Annotation::Trap(trap_code))));
+    }
+
+    /// The offset in the module of the operator being fed.
+    ///
+    /// The operators pushed by a previous middleware have the offset of
+    /// the operator it was fed at the time.
+    pub fn original_position(&self) -> usize {
+        self.operator_offset
+    }
+
+    /// Push an operator that checks the epoch deadline of the store.
+    ///
+    /// This is a `Nop` operator for the following middlewares, but the
+    /// compilers call the epoch deadline builtin, which either traps with
+    /// `TrapCode::Interrupt` or invokes the deadline callback of the store.
+    /// It is meant to be pushed in a branch only taken once the deadline
+    /// has been reached.
+    pub fn push_epoch_deadline(&mut self) {
+        self.pending_operations
+            .push_back((Operator::Nop, Some(Annotation::EpochDeadline)));
     }
 }
 
 impl<'a> Extend<Operator<'a>> for MiddlewareReaderState<'a> {
     fn extend<I: IntoIterator<Item = Operator<'a>>>(&mut self, iter: I) {
-        self.pending_operations.extend(iter);
+        for operator in iter {
+            self.push_operator(operator);
+        }
     }
 }
 
 impl<'a: 'b, 'b> Extend<&'b Operator<'a>> for MiddlewareReaderState<'a> {
     fn extend<I: IntoIterator<Item = &'b Operator<'a>>>(&mut self, iter: I) {
-        self.pending_operations.extend(iter.into_iter().cloned());
+        for operator in iter {
+            self.push_operator(operator.clone());
+        }
     }
 }
 
@@ -118,8 +197,12 @@ impl<'a> MiddlewareBinaryReader<'a> {
             state: MiddlewareReaderState {
                 inner,
                 pending_operations: VecDeque::new(),
+                fed_annotation: None,
+                operator_offset: original_offset,
             },
             chain: vec![],
+            annotation: None,
+            local_decls: VecDeque::new(),
         }
     }
 
@@ -131,24 +214,47 @@ impl<'a> MiddlewareBinaryReader<'a> {
 
 impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
     fn read_local_count(&mut self) -> WasmResult<u32> {
-        self.state
-            .inner
-            .read_var_u32()
-            .map_err(from_binaryreadererror_wasmerror)
-    }
-
-    fn read_local_decl(&mut self) -> WasmResult<(u32, ValType)> {
         let count = self
             .state
             .inner
             .read_var_u32()
             .map_err(from_binaryreadererror_wasmerror)?;
-        let ty: ValType = self
-            .state
-            .inner
-            .read::<ValType>()
-            .map_err(from_binaryreadererror_wasmerror)?;
-        Ok((count, ty))
+
+        // Read the declarations of the function upfront, so that the
+        // middlewares can add theirs after them.
+        let mut num_locals: u32 = 0;
+        for _ in 0..count {
+            let count = self
+                .state
+                .inner
+                .read_var_u32()
+                .map_err(from_binaryreadererror_wasmerror)?;
+            let ty: ValType = self
+                .state
+                .inner
+                .read::<ValType>()
+                .map_err(from_binaryreadererror_wasmerror)?;
+            num_locals = num_locals
+                .checked_add(count)
+                .ok_or(WasmError::ImplLimitExceeded)?;
+            self.local_decls.push_back((count, ty));
+        }
+        for stage in &mut self.chain {
+            for ty in stage.declare_locals(num_locals) {
+                num_locals = num_locals
+                    .checked_add(1)
+                    .ok_or(WasmError::ImplLimitExceeded)?;
+                self.local_decls.push_back((1, ty));
+            }
+        }
+
+        Ok(self.local_decls.len() as u32)
+    }
+
+    fn read_local_decl(&mut self) -> WasmResult<(u32, ValType)> {
+        self.local_decls.pop_front().ok_or_else(|| {
+            WasmError::Generic("read more local declarations than declared".to_string())
+        })
     }
 
     fn read_operator(&mut self) -> WasmResult<Operator<'a>> {
@@ -163,6 +269,7 @@ impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
 
         // Try to fill the `self.pending_operations` buffer, until it is non-empty.
         while self.state.pending_operations.is_empty() {
+            self.state.operator_offset = self.state.inner.original_position();
             let raw_op = self
                 .state
                 .inner
@@ -170,22 +277,26 @@ impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
                 .map_err(from_binaryreadererror_wasmerror)?;
 
             // Fill the initial raw operator into pending buffer.
-            self.state.pending_operations.push_back(raw_op);
+            self.state.pending_operations.push_back((raw_op, None));
 
             // Run the operator through each stage.
             for stage in &mut self.chain {
                 // Take the outputs from the previous stage.
-                let pending: SmallVec<[Operator<'a>; 2]> =
+                let pending: SmallVec<[(Operator<'a>, Option<Annotation>); 2]> =
                     self.state.pending_operations.drain(0..).collect();
 
                 // ...and feed them into the current stage.
-                for pending_op in pending {
+                for (pending_op, annotation) in pending {
+                    self.state.fed_annotation = annotation;
                     stage.feed(pending_op, &mut self.state)?;
                 }
+                self.state.fed_annotation = None;
             }
         }
 
-        Ok(self.state.pending_operations.pop_front().unwrap())
+        let (operator, annotation) = self.state.pending_operations.pop_front().unwrap();
+        self.annotation = annotation;
+        Ok(operator)
     }
 
     fn current_position(&self) -> usize {
@@ -207,4 +318,15 @@ impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
     fn range(&self) -> Range<usize> {
         self.state.inner.range()
     }
+
+    fn trap_code(&self) -> Option<TrapCode> {
+        match self.annotation {
+            Some(Annotation::Trap(trap_code)) => Some(trap_code),
+            _ => None,
+        }
+    }
+
+    fn epoch_deadline(&self) -> bool {
+        self.annotation == Some(Annotation::EpochDeadline)
+    }
 }
@@ -9,6 +9,9 @@ use super::*;
 
 pub const JOURNAL_MAGIC_NUMBER: u64 = 0x310d6dd027362979;
 pub const JOURNAL_MAGIC_NUMBER_BYTES: [u8; 8] = JOURNAL_MAGIC_NUMBER.to_be_bytes();
+/// Magic number of the journals whose records are protected by checksums
+pub const JOURNAL_MAGIC_NUMBER_V2: u64 = 0x310d6dd02736297a;
+pub const JOURNAL_MAGIC_NUMBER_V2_BYTES: [u8; 8] = JOURNAL_MAGIC_NUMBER_V2.to_be_bytes();
 
 #[repr(u16)]
 #[derive(
@@ -3,6 +3,8 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+use derivative::Derivative;
+
 use super::*;
 
 #[derive(Debug)]
@@ -22,12 +24,15 @@ pub struct CompactingLogFileJournal {
     rx: CompactingLogFileJournalRx,
 }
 
-#[derive(Debug)]
+#[derive(Derivative)]
+#[derivative(Debug)]
 pub struct CompactingLogFileJournalTx {
     state: Arc<Mutex<State>>,
     inner: CompactingJournalTx,
     main_path: PathBuf,
     temp_path: PathBuf,
+    #[derivative(Debug = "ignore")]
+    key: Option<Vec<u8>>,
 }
 
 #[derive(Debug)]
@@ -45,6 +50,11 @@ impl CompactingLogFileJournalRx {
 
 impl CompactingLogFileJournal {
     pub fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::new_with_key(path, None)
+    }
+
+    /// Opens a journal that is signed with the given key
+    pub fn new_with_key(path: impl AsRef<Path>, key: Option<&[u8]>) -> anyhow::Result<Self> {
         // We prepare a compacting journal which does nothing
         // with the events other than learn from them
         let counting = CountingJournal::default();
@@ -52,7 +62,7 @@ impl CompactingLogFileJournal {
 
         // We first feed all the entries into the compactor so that
         // it learns all the records
-        let log_file = LogFileJournal::new(path.as_ref())?;
+        let log_file = LogFileJournal::new_with_key(path.as_ref(), key)?;
         copy_journal(&log_file, &compacting)?;
 
         // Now everything is learned its time to attach the
@@ -88,6 +98,7 @@ impl CompactingLogFileJournal {
             inner: tx,
             main_path: path.as_ref().to_path_buf(),
             temp_path,
+            key: key.map(|key| key.to_vec()),
         };
         let rx = CompactingLogFileJournalRx { state, inner: rx };
 
@@ -138,7 +149,7 @@ impl CompactingLogFileJournalTx {
 
         // Create the staging file and open it
         std::fs::remove_file(&self.temp_path).ok();
-        let target = LogFileJournal::new(self.temp_path.clone())?;
+        let target = LogFileJournal::new_with_key(self.temp_path.clone(), self.key.as_deref())?;
 
         // Compact the data into the new target and rename it over the last one
         let result = self.inner.compact_to(target)?;
@@ -147,7 +158,7 @@ impl CompactingLogFileJournalTx {
         // Renaming the file has quite a detrimental effect on the file as
         // it means any new mmap operations will fail, hence we need to
         // reopen the log file, seek to the end and reattach it
-        let target = LogFileJournal::new(self.main_path.clone())?;
+        let target = LogFileJournal::new_with_key(self.main_path.clone(), self.key.as_deref())?;
 
         // We prepare a compacting journal which does nothing
         // with the events other than learn from them
@@ -1,7 +1,9 @@
 use bytes::Buf;
+use hmac::{Hmac, Mac};
 use rkyv::ser::serializers::{
     AllocScratch, CompositeSerializer, SharedSerializeMap, WriteSerializer,
 };
+use sha2::Sha256;
 use shared_buffer::OwnedBuffer;
 use std::{
     fs::File,
@@ -12,6 +14,15 @@ use std::{
 
 use super::*;
 
+/// Record type of the signatures, they are stored between the journal
+/// entries but never returned when the journal is read
+const SIGNATURE_RECORD_TYPE: u16 = u16::MAX;
+
+/// Size of a signature record (HMAC-SHA256)
+const SIGNATURE_SIZE: usize = 32;
+
+type JournalMac = Hmac<Sha256>;
+
 /// The LogFile snapshot capturer will write its snapshots to a linear journal
 /// and read them when restoring. It uses the `bincode` serializer which
 /// means that forwards and backwards compatibility must be dealt with
@@ -23,26 +34,257 @@ use super::*;
 ///
 /// The logfile snapshot capturer uses a 64bit number as a entry encoding
 /// delimiter.
+///
+/// Every record is protected by a CRC32 checksum. When a journal is opened,
+/// anything after the last valid record (e.g. a torn write or a corrupted
+/// record) is truncated.
+///
+/// Journals opened with a signing key are signed with HMAC-SHA256 after
+/// each snapshot and when they are closed. When they are read, a signature
+/// that doesn't match fails the restoration and the entries written after
+/// the last signature are ignored.
 pub struct LogFileJournal {
     tx: LogFileJournalTx,
     rx: LogFileJournalRx,
 }
 
+/// Formats of the records in a journal file, each one starts with its own
+/// magic number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFileFormat {
+    /// Records without checksums
+    V1,
+    /// Records with a CRC32 checksum
+    V2,
+}
+
+impl LogFileFormat {
+    fn from_magic(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 8 {
+            return None;
+        }
+        if bytes[0..8] == JOURNAL_MAGIC_NUMBER_BYTES {
+            Some(Self::V1)
+        } else if bytes[0..8] == JOURNAL_MAGIC_NUMBER_V2_BYTES {
+            Some(Self::V2)
+        } else {
+            None
+        }
+    }
+
+    fn header_size(self) -> usize {
+        match self {
+            Self::V1 => 8,
+            Self::V2 => 16,
+        }
+    }
+}
+
+/// Encodes the header of a record in the latest format
+fn encode_header(record_type: u16, data: &[u8]) -> [u8; 16] {
+    let mut header = [0u8; 16];
+    header[0..2].copy_from_slice(&record_type.to_be_bytes());
+    header[2..8].copy_from_slice(&(data.len() as u64).to_be_bytes()[2..8]); // record and pad size (48 bits)
+
+    let mut checksum = crc32fast::Hasher::new();
+    checksum.update(&header[0..8]);
+    checksum.update(data);
+    header[8..12].copy_from_slice(&checksum.finalize().to_be_bytes());
+    header
+}
+
+/// Summary of the records of a journal file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFileVerification {
+    /// Number of valid journal entries
+    pub entries: u64,
+    /// Number of signatures
+    pub signatures: u64,
+    /// Size of the journal file
+    pub file_size: u64,
+    /// Size of the valid records, anything after it is either torn
+    /// or corrupted
+    pub valid_size: u64,
+    /// Size of the records covered by the last signature, when the
+    /// journal is verified with a signing key
+    pub signed_size: Option<u64>,
+}
+
+/// Result of scanning the records of a journal file
+#[derive(Debug)]
+struct LogFileScan {
+    report: LogFileVerification,
+    valid_format: Option<LogFileFormat>,
+    signed_format: Option<LogFileFormat>,
+}
+
+impl LogFileScan {
+    /// Checks every record of the journal, failing if a signature
+    /// doesn't match the key
+    fn new(buffer: &[u8], key: Option<&JournalMac>) -> anyhow::Result<Self> {
+        if buffer.len() >= 8 && LogFileFormat::from_magic(buffer).is_none() {
+            let magic = u64::from_be_bytes(buffer[0..8].try_into().unwrap());
+            return Err(anyhow::format_err!(
+                "invalid magic number of journal ({} vs {})",
+                magic,
+                JOURNAL_MAGIC_NUMBER_V2
+            ));
+        }
+
+        let mut ret = Self {
+            report: LogFileVerification {
+                file_size: buffer.len() as u64,
+                ..Default::default()
+            },
+            valid_format: None,
+            signed_format: None,
+        };
+        let mut mac = key.cloned();
+        let mut pos = 0usize;
+        loop {
+            let b = &buffer[pos..];
+
+            // Journals that were concatenated have magic numbers in
+            // the middle, which may switch the format
+            let record_len = if let Some(format) = LogFileFormat::from_magic(b) {
+                ret.valid_format = Some(format);
+                8
+            } else {
+                let format = match ret.valid_format {
+                    Some(format) => format,
+                    None => break,
+                };
+                let header_size = format.header_size();
+                if b.len() < header_size {
+                    break;
+                }
+                let record_type = u16::from_be_bytes([b[0], b[1]]);
+                let record_size =
+                    u64::from_be_bytes([0u8, 0u8, b[2], b[3], b[4], b[5], b[6], b[7]]);
+                if record_size > (b.len() - header_size) as u64 {
+                    break;
+                }
+                let record_len = header_size + record_size as usize;
+                let data = &b[header_size..record_len];
+                if format == LogFileFormat::V2
+                    && b[8..16] != encode_header(record_type, data)[8..16]
+                {
+                    break;
+                }
+
+                if record_type == SIGNATURE_RECORD_TYPE {
+                    if data.len() != SIGNATURE_SIZE {
+                        break;
+                    }
+                    if let Some(mac) = mac.as_ref() {
+                        if mac.clone().verify_slice(data).is_err() {
+                            return Err(anyhow::format_err!(
+                                "the journal signature at offset {} does not match",
+                                pos
+                            ));
+                        }
+                        ret.report.signed_size = Some((pos + record_len) as u64);
+                        ret.signed_format = ret.valid_format;
+                    }
+                    ret.report.signatures += 1;
+                } else {
+                    ret.report.entries += 1;
+                }
+                record_len
+            };
+
+            if let Some(mac) = mac.as_mut() {
+                mac.update(&b[..record_len]);
+            }
+            pos += record_len;
+            ret.report.valid_size = pos as u64;
+        }
+
+        Ok(ret)
+    }
+
+    /// Returns the size of the journal that can be read, and the format
+    /// of the records at its end
+    fn readable(&self, signed: bool) -> anyhow::Result<(usize, Option<LogFileFormat>)> {
+        if !signed {
+            return Ok((self.report.valid_size as usize, self.valid_format));
+        }
+        match self.report.signed_size {
+            Some(size) => Ok((size as usize, self.signed_format)),
+            None if self.report.entries == 0 => {
+                Ok((self.report.valid_size as usize, self.valid_format))
+            }
+            None => Err(anyhow::format_err!("the journal is not signed")),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct TxState {
     file: File,
-    serializer: CompositeSerializer<WriteSerializer<File>, AllocScratch, SharedSerializeMap>,
+    /// A magic number must be written before the next record when the
+    /// records at the end of the file use another format
+    needs_magic: bool,
+    /// Signature of everything that was written to the file
+    mac: Option<JournalMac>,
+    /// Whether records were written since the last signature
+    unsigned: bool,
+}
+
+impl TxState {
+    fn write_record(&mut self, record_type: u16, data: &[u8]) -> anyhow::Result<()> {
+        let mut bytes = Vec::with_capacity(8 + 16 + data.len());
+        if self.needs_magic {
+            bytes.extend_from_slice(&JOURNAL_MAGIC_NUMBER_V2_BYTES);
+        }
+        bytes.extend_from_slice(&encode_header(record_type, data));
+        bytes.extend_from_slice(data);
+
+        // The record is written in one go to limit torn writes
+        self.file.write_all(&bytes)?;
+        self.needs_magic = false;
+
+        if let Some(mac) = self.mac.as_mut() {
+            mac.update(&bytes);
+            self.unsigned = record_type != SIGNATURE_RECORD_TYPE;
+        }
+        Ok(())
+    }
+
+    fn sign(&mut self) -> anyhow::Result<()> {
+        if let Some(signature) = self.mac.as_ref().map(|mac| mac.clone().finalize()) {
+            self.write_record(SIGNATURE_RECORD_TYPE, &signature.into_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TxState {
+    fn drop(&mut self) {
+        if self.unsigned {
+            if let Err(err) = self.sign() {
+                tracing::warn!("failed to sign the journal - {}", err);
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct LogFileJournalTx {
     state: Arc<Mutex<TxState>>,
+    key: Option<JournalMac>,
+}
+
+#[derive(Debug)]
+struct RxPosition {
+    offset: usize,
+    format: LogFileFormat,
 }
 
 #[derive(Debug)]
 pub struct LogFileJournalRx {
     tx: LogFileJournalTx,
-    buffer_pos: Mutex<usize>,
+    position: Mutex<RxPosition>,
     buffer: OwnedBuffer,
 }
 
@@ -53,61 +295,92 @@ impl LogFileJournalTx {
 
         let buffer = OwnedBuffer::from_file(&file)?;
 
-        // If the buffer exists we valid the magic number
-        let mut buffer_pos = 0;
-        let mut buffer_ptr = buffer.as_ref();
-        if buffer_ptr.len() >= 8 {
-            let magic = u64::from_be_bytes(buffer_ptr[0..8].try_into().unwrap());
-            if magic != JOURNAL_MAGIC_NUMBER {
-                return Err(anyhow::format_err!(
-                    "invalid magic number of journal ({} vs {})",
-                    magic,
-                    JOURNAL_MAGIC_NUMBER
-                ));
-            }
-            buffer_ptr.advance(8);
-            buffer_pos += 8;
-        } else {
+        // Only the valid (and signed) records are read
+        let scan = LogFileScan::new(buffer.as_ref(), self.key.as_ref())?;
+        let (readable, _) = scan.readable(self.key.is_some())?;
+        if readable == 0 {
             tracing::trace!("journal has no magic (could be empty?)");
         }
 
         Ok(LogFileJournalRx {
             tx: self.clone(),
-            buffer_pos: Mutex::new(buffer_pos),
-            buffer,
+            position: Mutex::new(RxPosition {
+                offset: 0,
+                format: LogFileFormat::V2,
+            }),
+            buffer: buffer.slice(..readable),
         })
     }
 }
 
 impl LogFileJournal {
     pub fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::new_with_key(path, None)
+    }
+
+    /// Opens a journal that is signed with the given key
+    pub fn new_with_key(path: impl AsRef<Path>, key: Option<&[u8]>) -> anyhow::Result<Self> {
         let file = std::fs::File::options()
             .read(true)
             .write(true)
             .create(true)
             .open(path)?;
-        Self::from_file(file)
+        Self::from_file_with_key(file, key)
     }
 
-    pub fn from_file(mut file: std::fs::File) -> anyhow::Result<Self> {
+    pub fn from_file(file: std::fs::File) -> anyhow::Result<Self> {
+        Self::from_file_with_key(file, None)
+    }
+
+    /// Opens a journal that is signed with the given key
+    pub fn from_file_with_key(mut file: std::fs::File, key: Option<&[u8]>) -> anyhow::Result<Self> {
+        let key = key
+            .map(JournalMac::new_from_slice)
+            .transpose()
+            .map_err(|err| anyhow::format_err!("invalid journal signing key - {}", err))?;
+
         // Move to the end of the file and write the
         // magic if one is needed
         if file.seek(SeekFrom::End(0)).unwrap() == 0 {
-            let magic = JOURNAL_MAGIC_NUMBER;
+            let magic = JOURNAL_MAGIC_NUMBER_V2;
             let magic = magic.to_be_bytes();
             file.write_all(&magic)?;
         }
 
+        // Find the last valid record so that torn writes and corrupted
+        // records are not followed by new ones
+        let buffer = OwnedBuffer::from_file(&file)?;
+        let scan = LogFileScan::new(buffer.as_ref(), key.as_ref())?;
+        let (readable, format) = scan.readable(key.is_some())?;
+        let mac = key.clone().map(|mut mac| {
+            mac.update(&buffer.as_ref()[..readable]);
+            mac
+        });
+        drop(buffer);
+
+        if readable < scan.report.file_size as usize {
+            tracing::warn!(
+                "journal is truncated to its last valid record ({} bytes to {} bytes)",
+                scan.report.file_size,
+                readable
+            );
+            // Journals that are only read can't be truncated, but the
+            // invalid records are still skipped when reading them
+            if let Err(err) = file.set_len(readable as u64) {
+                tracing::warn!("failed to truncate the journal - {}", err);
+            }
+            file.seek(SeekFrom::End(0))?;
+        }
+
         // Create the tx
         let tx = LogFileJournalTx {
             state: Arc::new(Mutex::new(TxState {
-                file: file.try_clone()?,
-                serializer: CompositeSerializer::new(
-                    WriteSerializer::new(file),
-                    AllocScratch::default(),
-                    SharedSerializeMap::default(),
-                ),
+                file,
+                needs_magic: format != Some(LogFileFormat::V2),
+                mac,
+                unsigned: false,
             })),
+            key,
         };
 
         // First we create the readable journal
@@ -115,25 +388,41 @@ impl LogFileJournal {
 
         Ok(Self { rx, tx })
     }
+
+    /// Checks the records of a journal file without modifying it, and
+    /// its signatures if a key is given
+    pub fn verify(
+        path: impl AsRef<Path>,
+        key: Option<&[u8]>,
+    ) -> anyhow::Result<LogFileVerification> {
+        let key = key
+            .map(JournalMac::new_from_slice)
+            .transpose()
+            .map_err(|err| anyhow::format_err!("invalid journal signing key - {}", err))?;
+
+        let file = std::fs::File::open(path)?;
+        let buffer = OwnedBuffer::from_file(&file)?;
+        let scan = LogFileScan::new(buffer.as_ref(), key.as_ref())?;
+        Ok(scan.report)
+    }
 }
 
 impl WritableJournal for LogFileJournalTx {
     fn write<'a>(&'a self, entry: JournalEntry<'a>) -> anyhow::Result<u64> {
         tracing::debug!("journal event: {:?}", entry);
 
-        let mut state = self.state.lock().unwrap();
-
-        // Write the header (with a record size of zero)
         let record_type: JournalEntryRecordType = entry.archive_record_type();
-        state.file.write_all(&(record_type as u16).to_be_bytes())?;
-        let offset_size = state.file.stream_position()?;
-        state.file.write_all(&[0u8; 6])?; // record and pad size (48 bits)
-
-        // Now serialize the actual data to the log
-        let offset_start = state.file.stream_position()?;
-        entry.serialize_archive(&mut state.serializer)?;
-        let offset_end = state.file.stream_position()?;
-        let record_size = offset_end - offset_start;
+        let is_snapshot = matches!(entry, JournalEntry::SnapshotV1 { .. });
+
+        // Serialize the record so that its checksum can be computed
+        let mut data = Vec::new();
+        let mut serializer = CompositeSerializer::new(
+            WriteSerializer::new(&mut data),
+            AllocScratch::default(),
+            SharedSerializeMap::default(),
+        );
+        entry.serialize_archive(&mut serializer)?;
+        let record_size = data.len() as u64;
 
         // If the alightment is out then fail
         if record_size % 8 != 0 {
@@ -145,12 +434,15 @@ impl WritableJournal for LogFileJournalTx {
             );
         }
 
-        // Write the record and then move back to the end again
-        state.file.seek(SeekFrom::Start(offset_size))?;
-        state.file.write_all(&record_size.to_be_bytes()[2..8])?;
-        state.file.seek(SeekFrom::Start(offset_end))?;
+        let mut state = self.state.lock().unwrap();
+        state.write_record(record_type as u16, &data)?;
+
+        // Snapshots are signed so that the journal can be restored up
+        // to them even if the process is killed
+        if is_snapshot {
+            state.sign()?;
+        }
 
-        // Now write the actual data and update the offsets
         Ok(record_size)
     }
 }
@@ -159,12 +451,12 @@ impl ReadableJournal for LogFileJournalRx {
     /// UNSAFE: This method uses unsafe operations to remove the need to zero
     /// the buffer before its read the log entries into it
     fn read(&self) -> anyhow::Result<Option<JournalEntry<'_>>> {
-        let mut buffer_pos = self.buffer_pos.lock().unwrap();
+        let mut position = self.position.lock().unwrap();
 
         // Get a memory reference to the data on the disk at
         // the current read location
         let mut buffer_ptr = self.buffer.as_ref();
-        buffer_ptr.advance(*buffer_pos);
+        buffer_ptr.advance(position.offset);
         loop {
             // Read the headers and advance
             if buffer_ptr.len() < 8 {
@@ -178,24 +470,30 @@ impl ReadableJournal for LogFileJournalRx {
                 // in the journal itself. This can happen if someone
                 // concat's multiple journals together to make a combined
                 // journal
-                if b[0..8] == JOURNAL_MAGIC_NUMBER_BYTES[0..8] {
+                if let Some(format) = LogFileFormat::from_magic(b) {
+                    position.format = format;
                     buffer_ptr.advance(8);
-                    *buffer_pos += 8;
+                    position.offset += 8;
                     continue;
                 }
 
-                // Otherwise we decode the header
+                // Otherwise we decode the header (the checksums were
+                // verified when the journal was opened)
+                let header_size = position.format.header_size();
+                if b.len() < header_size {
+                    return Ok(None);
+                }
                 let header = JournalEntryHeader {
                     record_type: u16::from_be_bytes([b[0], b[1]]),
                     record_size: u64::from_be_bytes([0u8, 0u8, b[2], b[3], b[4], b[5], b[6], b[7]]),
                 };
-                buffer_ptr.advance(8);
-                *buffer_pos += 8;
+                buffer_ptr.advance(header_size);
+                position.offset += header_size;
                 header
             };
 
             if header.record_size as usize > buffer_ptr.len() {
-                *buffer_pos += buffer_ptr.len();
+                position.offset += buffer_ptr.len();
                 tracing::trace!(
                     "journal is corrupt (record_size={} vs remaining={})",
                     header.record_size,
@@ -207,7 +505,12 @@ impl ReadableJournal for LogFileJournalRx {
             // Move the buffer position forward past the record
             let entry = &buffer_ptr[..(header.record_size as usize)];
             buffer_ptr.advance(header.record_size as usize);
-            *buffer_pos += header.record_size as usize;
+            position.offset += header.record_size as usize;
+
+            // Signatures were also verified when the journal was opened
+            if header.record_type == SIGNATURE_RECORD_TYPE {
+                continue;
+            }
 
             // Now we read the entry
             let record_type: JournalEntryRecordType = match header.record_type.try_into() {
@@ -402,4 +705,152 @@ mod tests {
         );
         assert_eq!(event6, None);
     }
+
+    fn read_all(journal: &LogFileJournal) -> Vec<JournalEntry<'static>> {
+        let mut ret = Vec::new();
+        while let Some(entry) = journal.read().unwrap() {
+            ret.push(entry.into_owned());
+        }
+        ret
+    }
+
+    fn snapshot() -> JournalEntry<'static> {
+        JournalEntry::SnapshotV1 {
+            when: std::time::SystemTime::UNIX_EPOCH,
+            trigger: SnapshotTrigger::Idle,
+        }
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_torn_writes_are_truncated() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let journal = LogFileJournal::new(file.path()).unwrap();
+        journal
+            .write(JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 })
+            .unwrap();
+        drop(journal);
+        let valid_size = std::fs::metadata(file.path()).unwrap().len();
+
+        // Only the start of the next record makes it to the disk
+        let mut f = File::options().append(true).open(file.path()).unwrap();
+        f.write_all(&[0, 22, 0, 0, 0]).unwrap();
+        drop(f);
+
+        let verification = LogFileJournal::verify(file.path(), None).unwrap();
+        assert_eq!(verification.entries, 1);
+        assert_eq!(verification.valid_size, valid_size);
+        assert_eq!(verification.file_size, valid_size + 5);
+
+        // Opening the journal truncates the torn record so that the next
+        // records can be read
+        let journal = LogFileJournal::new(file.path()).unwrap();
+        assert_eq!(std::fs::metadata(file.path()).unwrap().len(), valid_size);
+        journal.write(JournalEntry::PortAddrClearV1).unwrap();
+        drop(journal);
+
+        let journal = LogFileJournal::new(file.path()).unwrap();
+        assert_eq!(
+            read_all(&journal),
+            vec![
+                JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 },
+                JournalEntry::PortAddrClearV1
+            ]
+        );
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_corrupted_records_are_truncated() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let journal = LogFileJournal::new(file.path()).unwrap();
+        journal
+            .write(JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 })
+            .unwrap();
+        journal
+            .write(JournalEntry::CreatePipeV1 { fd1: 3, fd2: 4 })
+            .unwrap();
+        drop(journal);
+
+        // Flip a bit of the last record
+        let mut bytes = std::fs::read(file.path()).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        std::fs::write(file.path(), bytes).unwrap();
+
+        let journal = LogFileJournal::new(file.path()).unwrap();
+        assert_eq!(
+            read_all(&journal),
+            vec![JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 }]
+        );
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_signed_journal() {
+        let key = b"secret";
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let journal = LogFileJournal::new_with_key(file.path(), Some(key)).unwrap();
+        journal
+            .write(JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 })
+            .unwrap();
+        journal.write(snapshot()).unwrap();
+        drop(journal);
+
+        let verification = LogFileJournal::verify(file.path(), Some(key)).unwrap();
+        assert_eq!(verification.entries, 2);
+        assert_eq!(verification.signatures, 1);
+        assert_eq!(verification.signed_size, Some(verification.file_size));
+
+        // Entries that are appended without the key are not signed
+        let journal = LogFileJournal::new(file.path()).unwrap();
+        journal.write(JournalEntry::PortAddrClearV1).unwrap();
+        drop(journal);
+
+        let journal = LogFileJournal::new_with_key(file.path(), Some(key)).unwrap();
+        assert_eq!(
+            read_all(&journal),
+            vec![JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 }, snapshot()]
+        );
+        drop(journal);
+
+        // The wrong key is rejected
+        assert!(LogFileJournal::verify(file.path(), Some(b"other")).is_err());
+        assert!(LogFileJournal::new_with_key(file.path(), Some(b"other")).is_err());
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_tampered_journal_is_rejected() {
+        let key = b"secret";
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let journal = LogFileJournal::new_with_key(file.path(), Some(key)).unwrap();
+        journal
+            .write(JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 })
+            .unwrap();
+        drop(journal);
+
+        // Change the record and fix its checksum
+        let mut bytes = std::fs::read(file.path()).unwrap();
+        let header = 8;
+        let size = u64::from_be_bytes([
+            0,
+            0,
+            bytes[header + 2],
+            bytes[header + 3],
+            bytes[header + 4],
+            bytes[header + 5],
+            bytes[header + 6],
+            bytes[header + 7],
+        ]) as usize;
+        let data = header + 16..header + 16 + size;
+        bytes[data.start] ^= 1;
+        let checksum = encode_header(JournalEntryRecordType::CreatePipeV1 as u16, &bytes[data]);
+        bytes[header..header + 16].copy_from_slice(&checksum);
+        std::fs::write(file.path(), bytes).unwrap();
+
+        assert!(LogFileJournal::verify(file.path(), None).is_ok());
+        assert!(LogFileJournal::verify(file.path(), Some(key)).is_err());
+        assert!(LogFileJournal::new_with_key(file.path(), Some(key)).is_err());
+    }
 }
@@ -16,6 +16,7 @@ mod printing;
 mod recombined;
 #[cfg(test)]
 mod tests;
+mod truncated;
 mod unsupported;
 
 pub(super) use super::*;
@@ -35,4 +36,5 @@ pub use null::*;
 pub use pipe::*;
 pub use printing::*;
 pub use recombined::*;
+pub use truncated::*;
 pub use unsupported::*;
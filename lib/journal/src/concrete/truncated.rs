@@ -0,0 +1,177 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use derivative::Derivative;
+
+use super::*;
+
+/// Journal that stops reading the entries of another journal once a
+/// [`ReplayTarget`] is reached, which allows a process to be restored
+/// to a chosen point of its history. Writes are passed through to the
+/// inner journal.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct TruncatedJournal {
+    #[derivative(Debug = "ignore")]
+    tx: Box<DynWritableJournal>,
+    rx: TruncatedJournalRx,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct TruncatedJournalRx {
+    #[derivative(Debug = "ignore")]
+    inner: Box<DynReadableJournal>,
+    limit: u64,
+    remaining: AtomicU64,
+}
+
+impl TruncatedJournal {
+    /// Reads the inner journal to locate the target, failing if the
+    /// journal ends before it
+    pub fn new<J>(inner: J, target: ReplayTarget) -> anyhow::Result<Self>
+    where
+        J: Journal,
+    {
+        let limit = target.entry_count(inner.as_restarted()?.as_ref())?;
+        let (tx, rx) = inner.split();
+        Ok(Self {
+            tx,
+            rx: TruncatedJournalRx {
+                inner: rx,
+                limit,
+                remaining: AtomicU64::new(limit),
+            },
+        })
+    }
+
+    /// Number of entries that will be read from the inner journal
+    pub fn limit(&self) -> u64 {
+        self.rx.limit
+    }
+}
+
+impl WritableJournal for TruncatedJournal {
+    fn write<'a>(&'a self, entry: JournalEntry<'a>) -> anyhow::Result<u64> {
+        self.tx.write(entry)
+    }
+}
+
+impl ReadableJournal for TruncatedJournalRx {
+    fn read(&self) -> anyhow::Result<Option<JournalEntry<'_>>> {
+        let remaining = self.remaining.load(Ordering::SeqCst);
+        if remaining == 0 {
+            return Ok(None);
+        }
+        let ret = self.inner.read()?;
+        if ret.is_some() {
+            self.remaining.store(remaining - 1, Ordering::SeqCst);
+        }
+        Ok(ret)
+    }
+
+    fn as_restarted(&self) -> anyhow::Result<Box<DynReadableJournal>> {
+        Ok(Box::new(TruncatedJournalRx {
+            inner: self.inner.as_restarted()?,
+            limit: self.limit,
+            remaining: AtomicU64::new(self.limit),
+        }))
+    }
+}
+
+impl ReadableJournal for TruncatedJournal {
+    fn read(&self) -> anyhow::Result<Option<JournalEntry<'_>>> {
+        self.rx.read()
+    }
+
+    fn as_restarted(&self) -> anyhow::Result<Box<DynReadableJournal>> {
+        self.rx.as_restarted()
+    }
+}
+
+impl Journal for TruncatedJournal {
+    fn split(self) -> (Box<DynWritableJournal>, Box<DynReadableJournal>) {
+        (self.tx, Box::new(self.rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    fn snapshot(secs: u64) -> JournalEntry<'static> {
+        JournalEntry::SnapshotV1 {
+            when: UNIX_EPOCH + Duration::from_secs(secs),
+            trigger: SnapshotTrigger::Idle,
+        }
+    }
+
+    fn exit(code: i32) -> JournalEntry<'static> {
+        JournalEntry::ProcessExitV1 {
+            exit_code: Some(wasmer_wasix_types::wasi::ExitCode::Other(code)),
+        }
+    }
+
+    fn run_test(target: ReplayTarget, expected: Vec<JournalEntry<'static>>) {
+        let inner = BufferedJournal::default();
+        for entry in [exit(0), snapshot(10), exit(1), snapshot(20), exit(2)] {
+            inner.write(entry).unwrap();
+        }
+
+        let journal = TruncatedJournal::new(inner, target).unwrap();
+        assert_eq!(journal.limit(), expected.len() as u64);
+
+        let mut entries = Vec::new();
+        while let Some(entry) = journal.read().unwrap() {
+            entries.push(entry.into_owned());
+        }
+        assert_eq!(entries, expected);
+
+        // Restarting the journal replays the same entries
+        let restarted = journal.as_restarted().unwrap();
+        let mut count = 0;
+        while restarted.read().unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, expected.len());
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_truncate_at_entry() {
+        run_test(ReplayTarget::Entry(0), vec![exit(0)]);
+        run_test(ReplayTarget::Entry(2), vec![exit(0), snapshot(10), exit(1)]);
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_truncate_at_snapshot() {
+        run_test(ReplayTarget::Snapshot(0), vec![exit(0), snapshot(10)]);
+        run_test(
+            ReplayTarget::Snapshot(1),
+            vec![exit(0), snapshot(10), exit(1), snapshot(20)],
+        );
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_truncate_at_time() {
+        run_test("time:15".parse().unwrap(), vec![exit(0), snapshot(10)]);
+        run_test(
+            "time:20".parse().unwrap(),
+            vec![exit(0), snapshot(10), exit(1), snapshot(20)],
+        );
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_truncate_past_the_end() {
+        let inner = BufferedJournal::default();
+        inner.write(snapshot(10)).unwrap();
+
+        assert!(TruncatedJournal::new(inner, ReplayTarget::Entry(1)).is_err());
+        let inner = BufferedJournal::default();
+        assert!(TruncatedJournal::new(inner, "time:5".parse().unwrap()).is_err());
+    }
+}
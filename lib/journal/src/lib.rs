@@ -1,11 +1,13 @@
 mod base64;
 mod concrete;
 mod entry;
+mod replay;
 mod snapshot;
 mod util;
 
 pub use concrete::*;
 pub use entry::*;
+pub use replay::*;
 pub use snapshot::*;
 pub use util::*;
 
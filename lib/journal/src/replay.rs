@@ -0,0 +1,99 @@
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::*;
+
+/// The point in a journal up to which it will be replayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ReplayTarget {
+    /// Replays the entries up to and including the entry at this index
+    /// (counting from zero)
+    Entry(u64),
+    /// Replays the entries up to and including the snapshot at this index
+    /// (counting from zero)
+    Snapshot(u64),
+    /// Replays the entries up to and including the last snapshot that was
+    /// taken at or before this time
+    Timestamp(SystemTime),
+}
+
+impl ReplayTarget {
+    /// Reads the journal to find the number of entries that must be
+    /// replayed to reach this target
+    pub fn entry_count<R>(&self, journal: &R) -> anyhow::Result<u64>
+    where
+        R: ReadableJournal + ?Sized,
+    {
+        let mut count = 0u64;
+        let mut snapshots = 0u64;
+        let mut last_snapshot = None;
+        while let Some(entry) = journal.read()? {
+            count += 1;
+            match (self, entry) {
+                (Self::Entry(index), _) if count > *index => return Ok(count),
+                (Self::Snapshot(index), JournalEntry::SnapshotV1 { .. }) => {
+                    if snapshots == *index {
+                        return Ok(count);
+                    }
+                    snapshots += 1;
+                }
+                (Self::Timestamp(time), JournalEntry::SnapshotV1 { when, .. }) => {
+                    if when > *time {
+                        break;
+                    }
+                    last_snapshot.replace(count);
+                }
+                _ => {}
+            }
+        }
+
+        match (self, last_snapshot) {
+            (Self::Timestamp(_), Some(count)) => Ok(count),
+            (Self::Timestamp(_), None) => Err(anyhow::format_err!(
+                "the journal has no snapshot taken before {self}"
+            )),
+            _ => Err(anyhow::format_err!(
+                "the journal ends before {self} ({count} entries, {snapshots} snapshots)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ReplayTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Entry(index) => write!(f, "entry:{index}"),
+            Self::Snapshot(index) => write!(f, "snapshot:{index}"),
+            Self::Timestamp(time) => {
+                let time = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+                write!(f, "time:{}", time.as_secs_f64())
+            }
+        }
+    }
+}
+
+impl FromStr for ReplayTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let (kind, value) = s.split_once(':').unwrap_or(("entry", s.as_str()));
+        Ok(match kind {
+            "entry" => Self::Entry(value.parse()?),
+            "snapshot" => Self::Snapshot(value.parse()?),
+            "time" | "timestamp" => {
+                let secs: f64 = value.parse()?;
+                let time = Duration::try_from_secs_f64(secs)
+                    .map_err(|_| anyhow::format_err!("invalid timestamp ({value})"))?;
+                Self::Timestamp(UNIX_EPOCH + time)
+            }
+            a => {
+                return Err(anyhow::format_err!(
+                    "invalid or unknown replay target ({a})"
+                ))
+            }
+        })
+    }
+}
@@ -0,0 +1,780 @@
+//! `call_depth` is a middleware for putting a limit on the depth of
+//! nested WebAssembly function calls, independently of the size of
+//! the native stack. The WebAssembly instance execution is stopped
+//! with a [`TrapCode::CallDepthExceeded`] trap when the limit is
+//! reached, whichever compiler is used.
+//!
+//! The middleware also keeps per-instance counters of the current
+//! and peak call depths, and of the number of function calls, see
+//! [`get_call_depth_counters`].
+//!
+//! Each function keeps its own call depth in a local, which it sets
+//! back before calling other functions and when returning. This way
+//! the frames unwound by an exception caught in a calling function are
+//! not counted anymore once it carries on. The frames unwound by a trap
+//! are still counted though, so the call depth of an instance should be
+//! reset with [`reset_call_depth`] after a call traps.
+//!
+//! [`TrapCode::CallDepthExceeded`]: wasmer_types::TrapCode::CallDepthExceeded
+
+use std::convert::TryInto;
+use std::fmt;
+use std::sync::Mutex;
+use wasmer::wasmparser::{BlockType as WpTypeOrFuncType, Operator, ValType};
+use wasmer::{
+    AsStoreMut, ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance,
+    LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+};
+use wasmer_types::{entity::EntityRef, GlobalIndex, ModuleInfo, TrapCode};
+
+#[derive(Clone)]
+struct CallDepthGlobalIndexes {
+    depth: GlobalIndex,
+    peak_depth: GlobalIndex,
+    calls: GlobalIndex,
+    limit: GlobalIndex,
+    /// Not exported, holds the operand of a `br_if` or `br_table`
+    /// while the call depth is updated.
+    scratch: GlobalIndex,
+}
+
+impl fmt::Debug for CallDepthGlobalIndexes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallDepthGlobalIndexes")
+            .field("depth", &self.depth)
+            .field("peak_depth", &self.peak_depth)
+            .field("calls", &self.calls)
+            .field("limit", &self.limit)
+            .field("scratch", &self.scratch)
+            .finish()
+    }
+}
+
+/// The module-level call depth middleware.
+///
+/// # Panic
+///
+/// An instance of `CallDepth` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// global index to store the call depth. Attempts to use a `CallDepth`
+/// instance from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::CallDepth;
+///
+/// fn create_call_depth_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     // Let's allow at most 1000 nested function calls.
+///     let call_depth = Arc::new(CallDepth::new(1000));
+///
+///     // Finally, let's push the middleware.
+///     compiler_config.push_middleware(call_depth);
+/// }
+/// ```
+pub struct CallDepth {
+    /// Initial limit of the call depth.
+    initial_limit: u32,
+
+    /// The global indexes for the call depth state.
+    global_indexes: Mutex<Option<CallDepthGlobalIndexes>>,
+
+    /// The number of parameters of each local function.
+    num_params: Mutex<Vec<u32>>,
+}
+
+/// The function-level call depth middleware.
+pub struct FunctionCallDepth {
+    /// The global indexes for the call depth state.
+    global_indexes: CallDepthGlobalIndexes,
+
+    /// The number of parameters of the function.
+    num_params: u32,
+
+    /// The index of the local holding the call depth of the function.
+    depth_local: Option<u32>,
+
+    /// Whether the function entry has been instrumented.
+    entered: bool,
+
+    /// Nesting level of the blocks in the function body.
+    block_depth: usize,
+}
+
+/// The counters kept by the [`CallDepth`] middleware for an
+/// [`Instance`][wasmer::Instance].
+///
+/// # Example
+///
+/// See the [`get_call_depth_counters`] function to get an example.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CallDepthCounters {
+    /// The number of WebAssembly functions currently on the call stack.
+    pub depth: u32,
+
+    /// The highest call depth that has been reached.
+    pub peak_depth: u32,
+
+    /// The number of WebAssembly functions that have been called.
+    pub calls: u64,
+}
+
+impl CallDepth {
+    /// Creates a `CallDepth` middleware allowing at most `limit`
+    /// nested function calls.
+    pub fn new(limit: u32) -> Self {
+        Self {
+            initial_limit: limit,
+            global_indexes: Mutex::new(None),
+            num_params: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl fmt::Debug for CallDepth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallDepth")
+            .field("initial_limit", &self.initial_limit)
+            .field("global_indexes", &self.global_indexes)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for CallDepth {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionCallDepth {
+            global_indexes: self.global_indexes.lock().unwrap().clone().unwrap(),
+            num_params: self.num_params.lock().unwrap()[local_function_index.index()],
+            depth_local: None,
+            entered: false,
+            block_depth: 0,
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut global_indexes = self.global_indexes.lock().unwrap();
+
+        if global_indexes.is_some() {
+            panic!("CallDepth::transform_module_info: Attempting to use a `CallDepth` middleware from multiple modules.");
+        }
+
+        *self.num_params.lock().unwrap() = module_info
+            .functions
+            .values()
+            .skip(module_info.num_imported_functions)
+            .map(|signature| module_info.signatures[*signature].params().len() as u32)
+            .collect();
+
+        let mut push_global = |name: &str, ty: Type, init: GlobalInit| {
+            let global_index = module_info
+                .globals
+                .push(GlobalType::new(ty, Mutability::Var));
+            module_info.global_initializers.push(init);
+            module_info
+                .exports
+                .insert(name.to_string(), ExportIndex::Global(global_index));
+            global_index
+        };
+
+        *global_indexes = Some(CallDepthGlobalIndexes {
+            depth: push_global("wasmer_call_depth", Type::I32, GlobalInit::I32Const(0)),
+            peak_depth: push_global("wasmer_call_depth_peak", Type::I32, GlobalInit::I32Const(0)),
+            calls: push_global(
+                "wasmer_call_depth_calls",
+                Type::I64,
+                GlobalInit::I64Const(0),
+            ),
+            limit: push_global(
+                "wasmer_call_depth_limit",
+                Type::I32,
+                GlobalInit::I32Const(self.initial_limit as i32),
+            ),
+            scratch: {
+                let global_index = module_info
+                    .globals
+                    .push(GlobalType::new(Type::I32, Mutability::Var));
+                module_info
+                    .global_initializers
+                    .push(GlobalInit::I32Const(0));
+                global_index
+            },
+        });
+    }
+}
+
+impl fmt::Debug for FunctionCallDepth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionCallDepth")
+            .field("global_indexes", &self.global_indexes)
+            .field("num_params", &self.num_params)
+            .field("depth_local", &self.depth_local)
+            .field("entered", &self.entered)
+            .field("block_depth", &self.block_depth)
+            .finish()
+    }
+}
+
+impl FunctionCallDepth {
+    /// The index of the local holding the call depth of the function.
+    fn depth_local(&self) -> Result<u32, MiddlewareError> {
+        self.depth_local.ok_or_else(|| {
+            MiddlewareError::new("call_depth", "the locals of the function were not declared")
+        })
+    }
+
+    /// Sets the call depth to the one of the caller, or to the one of the
+    /// function minus the `i32` 0 or 1 value computed by `amount` if given.
+    fn decrement_depth<'a>(
+        &self,
+        state: &mut MiddlewareReaderState<'a>,
+        amount: Option<&[Operator<'a>]>,
+    ) -> Result<(), MiddlewareError> {
+        let depth = self.global_indexes.depth.as_u32();
+
+        // globals[depth] = locals[depth_local] - amount;
+        state.push_operator(Operator::LocalGet {
+            local_index: self.depth_local()?,
+        });
+        match amount {
+            Some(amount) => state.extend(amount),
+            None => state.push_operator(Operator::I32Const { value: 1 }),
+        }
+        state.extend(&[
+            Operator::I32Sub,
+            Operator::GlobalSet {
+                global_index: depth,
+            },
+        ]);
+        Ok(())
+    }
+}
+
+impl FunctionMiddleware for FunctionCallDepth {
+    fn declare_locals(&mut self, num_locals: u32) -> Vec<ValType> {
+        self.depth_local = Some(self.num_params + num_locals);
+        vec![ValType::I32]
+    }
+
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let depth = self.global_indexes.depth.as_u32();
+        let peak_depth = self.global_indexes.peak_depth.as_u32();
+        let calls = self.global_indexes.calls.as_u32();
+        let limit = self.global_indexes.limit.as_u32();
+
+        // Function entry, before the first operator of the body.
+        if !self.entered {
+            self.entered = true;
+
+            // if unsigned(globals[depth]) >= unsigned(globals[limit]) { throw(); }
+            state.extend(&[
+                Operator::GlobalGet {
+                    global_index: depth,
+                },
+                Operator::GlobalGet {
+                    global_index: limit,
+                },
+                Operator::I32GeU,
+                Operator::If {
+                    blockty: WpTypeOrFuncType::Empty,
+                },
+            ]);
+            state.push_trap(TrapCode::CallDepthExceeded);
+            state.extend(&[
+                Operator::End,
+                // globals[depth] += 1;
+                // locals[depth_local] = globals[depth];
+                Operator::GlobalGet {
+                    global_index: depth,
+                },
+                Operator::I32Const { value: 1 },
+                Operator::I32Add,
+                Operator::LocalTee {
+                    local_index: self.depth_local()?,
+                },
+                Operator::GlobalSet {
+                    global_index: depth,
+                },
+                // globals[peak_depth] = max(globals[peak_depth], globals[depth]);
+                Operator::GlobalGet {
+                    global_index: depth,
+                },
+                Operator::GlobalGet {
+                    global_index: peak_depth,
+                },
+                Operator::I32GtU,
+                Operator::If {
+                    blockty: WpTypeOrFuncType::Empty,
+                },
+                Operator::GlobalGet {
+                    global_index: depth,
+                },
+                Operator::GlobalSet {
+                    global_index: peak_depth,
+                },
+                Operator::End,
+                // globals[calls] += 1;
+                Operator::GlobalGet {
+                    global_index: calls,
+                },
+                Operator::I64Const { value: 1 },
+                Operator::I64Add,
+                Operator::GlobalSet {
+                    global_index: calls,
+                },
+            ]);
+        }
+
+        // Function exits: returns, tail calls, the final `end` of the body
+        // and branches to it.
+        let function_label = self.block_depth as u32;
+        match operator {
+            Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Try { .. }
+            | Operator::TryTable { .. } => self.block_depth += 1,
+            Operator::End | Operator::Delegate { .. } => match self.block_depth.checked_sub(1) {
+                Some(block_depth) => self.block_depth = block_depth,
+                None => self.decrement_depth(state, None)?,
+            },
+            Operator::Return
+            | Operator::ReturnCall { .. }
+            | Operator::ReturnCallIndirect { .. }
+            | Operator::ReturnCallRef { .. } => self.decrement_depth(state, None)?,
+            Operator::Br { relative_depth } if relative_depth == function_label => {
+                self.decrement_depth(state, None)?
+            }
+            // An exception caught since the last call may have left the
+            // depth of the unwound frames behind.
+            Operator::Call { .. } | Operator::CallIndirect { .. } | Operator::CallRef { .. } => {
+                state.extend(&[
+                    Operator::LocalGet {
+                        local_index: self.depth_local()?,
+                    },
+                    Operator::GlobalSet {
+                        global_index: depth,
+                    },
+                ]);
+            }
+            Operator::BrIf { relative_depth } if relative_depth == function_label => {
+                let scratch = self.global_indexes.scratch.as_u32();
+                state.push_operator(Operator::GlobalSet {
+                    global_index: scratch,
+                });
+                // The depth is only decremented if the branch is taken.
+                self.decrement_depth(
+                    state,
+                    Some(&[
+                        Operator::GlobalGet {
+                            global_index: scratch,
+                        },
+                        Operator::I32Eqz,
+                        Operator::I32Eqz,
+                    ]),
+                )?;
+                state.push_operator(Operator::GlobalGet {
+                    global_index: scratch,
+                });
+            }
+            Operator::BrTable { ref targets } => {
+                let default_exits = targets.default() == function_label;
+                let exiting_targets = targets
+                    .targets()
+                    .enumerate()
+                    .filter_map(|(index, target)| match target {
+                        Ok(target) if target == function_label => Some(Ok(index as i32)),
+                        Ok(_) => None,
+                        Err(err) => Some(Err(err)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|err| MiddlewareError::new("call_depth", err.to_string()))?;
+
+                if default_exits || !exiting_targets.is_empty() {
+                    let scratch = self.global_indexes.scratch.as_u32();
+                    state.push_operator(Operator::GlobalSet {
+                        global_index: scratch,
+                    });
+                    // Decrement the depth if the index selects a target that
+                    // exits the function.
+                    let mut amount = Vec::new();
+                    for index in exiting_targets {
+                        amount.extend([
+                            Operator::GlobalGet {
+                                global_index: scratch,
+                            },
+                            Operator::I32Const { value: index },
+                            Operator::I32Eq,
+                        ]);
+                    }
+                    if default_exits {
+                        amount.extend([
+                            Operator::GlobalGet {
+                                global_index: scratch,
+                            },
+                            Operator::I32Const {
+                                value: targets.len() as i32,
+                            },
+                            Operator::I32GeU,
+                        ]);
+                    }
+                    let conditions = amount.len() / 3;
+                    amount.extend((1..conditions).map(|_| Operator::I32Or));
+                    self.decrement_depth(state, Some(&amount))?;
+                    state.push_operator(Operator::GlobalGet {
+                        global_index: scratch,
+                    });
+                }
+            }
+            _ => {}
+        }
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// Get the call depth counters of an [`Instance`][wasmer::Instance].
+///
+/// Note: This can be used in a headless engine after an ahead-of-time
+/// compilation as all required state lives in the instance.
+///
+/// # Panic
+///
+/// The [`Instance`][wasmer::Instance] must have been processed with
+/// the [`CallDepth`] middleware at compile time, otherwise this will
+/// panic.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{AsStoreMut, Instance};
+/// use wasmer_middlewares::call_depth::get_call_depth_counters;
+///
+/// /// Check whether the instance has ever nested calls more than
+/// /// 100 deep.
+/// fn uses_deep_recursion(store: &mut impl AsStoreMut, instance: &Instance) -> bool {
+///     get_call_depth_counters(store, instance).peak_depth > 100
+/// }
+/// ```
+pub fn get_call_depth_counters(
+    ctx: &mut impl AsStoreMut,
+    instance: &Instance,
+) -> CallDepthCounters {
+    let depth: i32 = instance
+        .exports
+        .get_global("wasmer_call_depth")
+        .expect("Can't get `wasmer_call_depth` from Instance")
+        .get(ctx)
+        .try_into()
+        .expect("`wasmer_call_depth` from Instance has wrong type");
+
+    let peak_depth: i32 = instance
+        .exports
+        .get_global("wasmer_call_depth_peak")
+        .expect("Can't get `wasmer_call_depth_peak` from Instance")
+        .get(ctx)
+        .try_into()
+        .expect("`wasmer_call_depth_peak` from Instance has wrong type");
+
+    let calls: i64 = instance
+        .exports
+        .get_global("wasmer_call_depth_calls")
+        .expect("Can't get `wasmer_call_depth_calls` from Instance")
+        .get(ctx)
+        .try_into()
+        .expect("`wasmer_call_depth_calls` from Instance has wrong type");
+
+    CallDepthCounters {
+        depth: depth as u32,
+        peak_depth: peak_depth as u32,
+        calls: calls as u64,
+    }
+}
+
+/// Reset the call depth of an [`Instance`][wasmer::Instance] to 0.
+///
+/// This must be done after a call into the instance traps, since the
+/// frames unwound by the trap are still counted. The peak call depth
+/// and the number of calls are left untouched.
+///
+/// # Panic
+///
+/// The given [`Instance`][wasmer::Instance] must have been processed
+/// with the [`CallDepth`] middleware at compile time, otherwise this
+/// will panic.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{AsStoreMut, Instance, RuntimeError, Value};
+/// use wasmer_middlewares::call_depth::reset_call_depth;
+///
+/// fn call_run(store: &mut impl AsStoreMut, instance: &Instance) -> Result<Box<[Value]>, RuntimeError> {
+///     let run = instance.exports.get_function("run").unwrap();
+///     let result = run.call(store, &[]);
+///     if result.is_err() {
+///         reset_call_depth(store, instance);
+///     }
+///     result
+/// }
+/// ```
+pub fn reset_call_depth(ctx: &mut impl AsStoreMut, instance: &Instance) {
+    instance
+        .exports
+        .get_global("wasmer_call_depth")
+        .expect("Can't get `wasmer_call_depth` from Instance")
+        .set(ctx, 0i32.into())
+        .expect("Can't set `wasmer_call_depth` in Instance");
+}
+
+/// Set the maximum call depth of an [`Instance`][wasmer::Instance],
+/// replacing the limit given to [`CallDepth::new`].
+///
+/// # Panic
+///
+/// The given [`Instance`][wasmer::Instance] must have been processed
+/// with the [`CallDepth`] middleware at compile time, otherwise this
+/// will panic.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{AsStoreMut, Instance};
+/// use wasmer_middlewares::call_depth::set_call_depth_limit;
+///
+/// fn restrict_untrusted_tenant(store: &mut impl AsStoreMut, instance: &Instance) {
+///     set_call_depth_limit(store, instance, 100);
+/// }
+/// ```
+pub fn set_call_depth_limit(ctx: &mut impl AsStoreMut, instance: &Instance, limit: u32) {
+    instance
+        .exports
+        .get_global("wasmer_call_depth_limit")
+        .expect("Can't get `wasmer_call_depth_limit` from Instance")
+        .set(ctx, (limit as i32).into())
+        .expect("Can't set `wasmer_call_depth_limit` in Instance");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::sys::EngineBuilder;
+    use wasmer::{
+        imports, sys::Features, wat2wasm, CompilerConfig, Cranelift, Module, Store, TypedFunction,
+    };
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (func $recurse_f (param $n i32) (result i32)
+                local.get $n
+                i32.eqz
+                if
+                    i32.const 0
+                    return
+                end
+                local.get $n
+                i32.const 1
+                i32.sub
+                call $recurse_f
+                i32.const 1
+                i32.add)
+            (func $branch_f (param $n i32) (result i32)
+                i32.const 1
+                local.get $n
+                i32.const 1
+                i32.eq
+                br_if 0
+                drop
+                block (result i32)
+                    i32.const 2
+                    local.get $n
+                    br_table 1 1 0 1
+                end
+                drop
+                i32.const 3)
+            (export "recurse" (func $recurse_f))
+            (export "branch" (func $branch_f)))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn instantiate(limit: u32) -> (Store, Instance) {
+        let call_depth = Arc::new(CallDepth::new(limit));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(call_depth);
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(&store, bytecode()).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        (store, instance)
+    }
+
+    #[test]
+    fn call_depth_is_limited() {
+        let (mut store, instance) = instantiate(10);
+        let recurse: TypedFunction<i32, i32> = instance
+            .exports
+            .get_function("recurse")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+
+        // `recurse(9)` nests 10 calls, which is the limit
+        assert_eq!(recurse.call(&mut store, 9).unwrap(), 9);
+        assert_eq!(
+            get_call_depth_counters(&mut store, &instance),
+            CallDepthCounters {
+                depth: 0,
+                peak_depth: 10,
+                calls: 10,
+            }
+        );
+
+        // `recurse(10)` nests one call too many
+        let err = recurse.call(&mut store, 10).unwrap_err();
+        assert_eq!(err.to_trap(), Some(TrapCode::CallDepthExceeded));
+        assert_eq!(get_call_depth_counters(&mut store, &instance).depth, 10);
+
+        // The instance can be used again once its call depth is reset
+        reset_call_depth(&mut store, &instance);
+        assert_eq!(recurse.call(&mut store, 9).unwrap(), 9);
+        assert_eq!(
+            get_call_depth_counters(&mut store, &instance),
+            CallDepthCounters {
+                depth: 0,
+                peak_depth: 10,
+                calls: 30,
+            }
+        );
+    }
+
+    #[test]
+    fn set_call_depth_limit_works() {
+        let (mut store, instance) = instantiate(10);
+        let recurse: TypedFunction<i32, i32> = instance
+            .exports
+            .get_function("recurse")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+
+        set_call_depth_limit(&mut store, &instance, 100);
+        assert_eq!(recurse.call(&mut store, 50).unwrap(), 50);
+        assert_eq!(
+            get_call_depth_counters(&mut store, &instance).peak_depth,
+            51
+        );
+
+        set_call_depth_limit(&mut store, &instance, 5);
+        let err = recurse.call(&mut store, 5).unwrap_err();
+        assert_eq!(err.to_trap(), Some(TrapCode::CallDepthExceeded));
+    }
+
+    #[test]
+    fn branches_to_the_function_end_return() {
+        let (mut store, instance) = instantiate(10);
+        let branch: TypedFunction<i32, i32> = instance
+            .exports
+            .get_function("branch")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+
+        // 1 is returned by `br_if`, 2 by `br_table` and 3 by the end
+        // of the function.
+        for (value, expected) in [(0, 2), (1, 1), (2, 3), (3, 2), (7, 2)] {
+            assert_eq!(branch.call(&mut store, value).unwrap(), expected);
+            assert_eq!(get_call_depth_counters(&mut store, &instance).depth, 0);
+        }
+    }
+
+    #[test]
+    fn caught_exceptions_dont_leak_depth() {
+        // The text format doesn't know about `try_table` yet, so this is:
+        //
+        // (module
+        //   (tag $tag (param i32))
+        //   (func $thrower (param i32) (result i32)
+        //     (throw $tag (local.get 0)))
+        //   ;; Catches in a block of the function
+        //   (func $catch (export "catch") (param i32) (result i32)
+        //     (block (result i32)
+        //       (try_table (result i32) (catch $tag 0)
+        //         (call $thrower (local.get 0))))
+        //     (i32.const 1)
+        //     (i32.add))
+        //   ;; Catches by returning from the function
+        //   (func $catch_return (export "catch_return") (param i32) (result i32)
+        //     (try_table (result i32) (catch $tag 0)
+        //       (call $thrower (local.get 0))))
+        //   (func (export "run") (param i32) (result i32)
+        //     (loop
+        //       (drop (call $catch (local.get 0)))
+        //       (drop (call $catch_return (local.get 0)))
+        //       (br_if 0 (local.tee 0 (i32.sub (local.get 0) (i32.const 1)))))
+        //     (local.get 0)))
+        let wasm: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+            0x01, 0x0a, 0x02, 0x60, 0x01, 0x7f, 0x00, 0x60, 0x01, 0x7f, 0x01, 0x7f, // types
+            0x03, 0x05, 0x04, 0x01, 0x01, 0x01, 0x01, // functions
+            0x0d, 0x03, 0x01, 0x00, 0x00, // tags
+            0x07, 0x1e, 0x03, // exports
+            0x05, b'c', b'a', b't', b'c', b'h', 0x00, 0x01, //
+            0x0c, b'c', b'a', b't', b'c', b'h', b'_', b'r', b'e', b't', b'u', b'r', b'n', 0x00,
+            0x02, //
+            0x03, b'r', b'u', b'n', 0x00, 0x03, //
+            0x0a, 0x45, 0x04, // code
+            0x06, 0x00, 0x20, 0x00, 0x08, 0x00, 0x0b, //
+            0x13, 0x00, 0x02, 0x7f, 0x1f, 0x7f, 0x01, 0x00, 0x00, 0x00, 0x20, 0x00, 0x10, 0x00,
+            0x0b, 0x0b, 0x41, 0x01, 0x6a, 0x0b, //
+            0x0d, 0x00, 0x1f, 0x7f, 0x01, 0x00, 0x00, 0x00, 0x20, 0x00, 0x10, 0x00, 0x0b,
+            0x0b, //
+            0x1a, 0x00, 0x03, 0x40, 0x20, 0x00, 0x10, 0x01, 0x1a, 0x20, 0x00, 0x10, 0x02, 0x1a,
+            0x20, 0x00, 0x41, 0x01, 0x6b, 0x22, 0x00, 0x0d, 0x00, 0x0b, 0x20, 0x00, 0x0b,
+        ];
+
+        let call_depth = Arc::new(CallDepth::new(10));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(call_depth);
+        let mut features = Features::new();
+        features.exceptions(true);
+        let engine = EngineBuilder::new(compiler_config).set_features(Some(features));
+        let mut store = Store::new(engine);
+        let module = Module::new(&store, wasm).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        let run: TypedFunction<i32, i32> = instance
+            .exports
+            .get_function("run")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+
+        // Each iteration unwinds two frames, which would exceed the limit
+        // long before the end if they were still counted.
+        assert_eq!(run.call(&mut store, 100).unwrap(), 0);
+        assert_eq!(
+            get_call_depth_counters(&mut store, &instance),
+            CallDepthCounters {
+                depth: 0,
+                peak_depth: 3,
+                calls: 401,
+            }
+        );
+    }
+}
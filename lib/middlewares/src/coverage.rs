@@ -0,0 +1,625 @@
+//! `coverage` is a middleware for counting how many times each basic
+//! block of the WebAssembly functions is executed. The counts can be
+//! written as an lcov tracefile to measure code coverage, or in the
+//! folded stacks format used by flamegraph tools to profile where
+//! instructions are executed.
+//!
+//! The counters are kept in a memory added to the module, which has
+//! room for a fixed number of basic blocks, see
+//! [`Coverage::with_max_blocks`].
+//!
+//! The middleware can be pushed after other middlewares: the
+//! operators they insert are counted with the operator of the module
+//! they were generated for.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::{MemArg, Operator};
+use wasmer::{
+    AsStoreMut, ExportIndex, FunctionMiddleware, Instance, LocalFunctionIndex, MemoryType,
+    MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Pages,
+};
+use wasmer_types::{FunctionIndex, MemoryIndex, ModuleInfo};
+
+/// The number of basic blocks a module can have by default.
+const DEFAULT_MAX_BLOCKS: u32 = 1 << 16;
+
+/// The size of a counter in the counters memory.
+const COUNTER_SIZE: u32 = 8;
+
+/// A basic block found when instrumenting a function body.
+#[derive(Debug, Clone)]
+struct BasicBlock {
+    /// Offset of the first operator of the block in the module.
+    offset: usize,
+
+    /// Number of operators in the block.
+    instructions: u64,
+
+    /// Index of the counter of the block in the counters memory.
+    counter: u32,
+}
+
+/// The state computed when the middleware is applied to a module.
+#[derive(Debug)]
+struct CoverageState {
+    /// Index of the memory holding the counters.
+    memory: MemoryIndex,
+
+    /// Names of the local functions.
+    function_names: Vec<String>,
+
+    /// The basic blocks of each local function, once it has been
+    /// instrumented.
+    blocks: Vec<Mutex<Vec<BasicBlock>>>,
+
+    /// The index of the next counter to hand out.
+    next_counter: AtomicU32,
+}
+
+/// The module-level coverage middleware.
+///
+/// # Panic
+///
+/// An instance of `Coverage` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// location of the counters. Attempts to use a `Coverage` instance
+/// from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::Coverage;
+///
+/// fn create_coverage_middleware(compiler_config: &mut dyn CompilerConfig) -> Arc<Coverage> {
+///     let coverage = Arc::new(Coverage::new());
+///
+///     // Let's push the middleware, and keep it to get the report
+///     // once the instance has run.
+///     compiler_config.push_middleware(coverage.clone());
+///     coverage
+/// }
+/// ```
+pub struct Coverage {
+    /// The maximum number of basic blocks of the module.
+    max_blocks: u32,
+
+    /// The location of the counters and the basic blocks found.
+    state: Mutex<Option<Arc<CoverageState>>>,
+}
+
+/// The function-level coverage middleware.
+pub struct FunctionCoverage {
+    /// The state shared by the functions of the module.
+    state: Arc<CoverageState>,
+
+    /// The maximum number of basic blocks of the module.
+    max_blocks: u32,
+
+    /// Index of the function among the local functions.
+    function: usize,
+
+    /// The basic blocks of the function found so far.
+    blocks: Vec<BasicBlock>,
+
+    /// Whether the next operator of the module starts a basic block.
+    block_start: bool,
+
+    /// Offset of the last operator of the module fed, the operators
+    /// inserted by the previous middlewares sharing the offset of the
+    /// operator they were generated for.
+    position: Option<usize>,
+
+    /// Tracks where the basic blocks start.
+    tracker: BlockTracker,
+}
+
+/// The number of executions of each basic block of an
+/// [`Instance`][wasmer::Instance], as returned by [`Coverage::report`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CoverageReport {
+    /// The local functions of the module.
+    pub functions: Vec<FunctionReport>,
+}
+
+/// The number of executions of each basic block of a function.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FunctionReport {
+    /// The name of the function, from the name section of the module.
+    pub name: String,
+
+    /// The basic blocks of the function, the first one being the
+    /// entry of the function.
+    pub blocks: Vec<BlockReport>,
+}
+
+/// The number of executions of a basic block.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BlockReport {
+    /// Offset of the first operator of the block in the module.
+    pub offset: usize,
+
+    /// Number of operators in the block.
+    pub instructions: u64,
+
+    /// Number of times the block has been executed.
+    pub count: u64,
+}
+
+/// Tracks where basic blocks start in a function body: at the start
+/// of the function, inside `loop`, `if` and `else`, after the end of a
+/// block and after a `br_if`.
+#[derive(Debug, Default)]
+struct BlockTracker {
+    /// Nesting level of the blocks in the function body.
+    block_depth: usize,
+}
+
+impl BlockTracker {
+    /// Returns whether a basic block starts right after `operator`.
+    fn ends_block(&mut self, operator: &Operator) -> bool {
+        match operator {
+            Operator::Block { .. } | Operator::Try { .. } | Operator::TryTable { .. } => {
+                self.block_depth += 1;
+                false
+            }
+            Operator::Loop { .. } | Operator::If { .. } => {
+                self.block_depth += 1;
+                true
+            }
+            Operator::Else | Operator::BrIf { .. } => true,
+            // The final `end` of the body ends the function.
+            Operator::End | Operator::Delegate { .. } => match self.block_depth.checked_sub(1) {
+                Some(block_depth) => {
+                    self.block_depth = block_depth;
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns whether `operator` is the final `end` of the body.
+    fn ends_function(&self, operator: &Operator) -> bool {
+        matches!(operator, Operator::End) && self.block_depth == 0
+    }
+}
+
+impl Coverage {
+    /// Creates a `Coverage` middleware.
+    pub fn new() -> Self {
+        Self {
+            max_blocks: DEFAULT_MAX_BLOCKS,
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Sets the maximum number of basic blocks of the module, 65536
+    /// by default.
+    ///
+    /// The counters memory is allocated for that many blocks, and
+    /// compiling a module with more blocks fails.
+    pub fn with_max_blocks(mut self, max_blocks: u32) -> Self {
+        self.max_blocks = max_blocks;
+        self
+    }
+
+    /// Get the number of executions of each basic block of an
+    /// [`Instance`][wasmer::Instance].
+    ///
+    /// # Panic
+    ///
+    /// The [`Instance`][wasmer::Instance] must have been processed
+    /// with this middleware at compile time, otherwise this will
+    /// panic.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::fs::File;
+    /// use wasmer::{AsStoreMut, Instance};
+    /// use wasmer_middlewares::Coverage;
+    ///
+    /// fn write_coverage(coverage: &Coverage, store: &mut impl AsStoreMut, instance: &Instance) {
+    ///     let report = coverage.report(store, instance);
+    ///     let mut file = File::create("coverage.info").unwrap();
+    ///     report.write_lcov(&mut file, "module.wasm").unwrap();
+    /// }
+    /// ```
+    pub fn report(&self, ctx: &mut impl AsStoreMut, instance: &Instance) -> CoverageReport {
+        let state = self.state.lock().unwrap();
+        let state = state
+            .as_ref()
+            .expect("`Coverage` hasn't been applied to a module");
+        let memory = instance
+            .exports
+            .get_memory("wasmer_coverage_counters")
+            .expect("Can't get `wasmer_coverage_counters` from Instance");
+        let view = memory.view(ctx);
+
+        let functions = state
+            .blocks
+            .iter()
+            .zip(&state.function_names)
+            .map(|(blocks, name)| FunctionReport {
+                name: name.clone(),
+                blocks: blocks
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|block| {
+                        let mut count = [0; COUNTER_SIZE as usize];
+                        view.read(u64::from(block.counter * COUNTER_SIZE), &mut count)
+                            .expect("Can't read the counters of Instance");
+
+                        BlockReport {
+                            offset: block.offset,
+                            instructions: block.instructions,
+                            count: u64::from_le_bytes(count),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        CoverageReport { functions }
+    }
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coverage")
+            .field("max_blocks", &self.max_blocks)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for Coverage {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let state = self.state.lock().unwrap();
+
+        Box::new(FunctionCoverage {
+            state: state.clone().unwrap(),
+            max_blocks: self.max_blocks,
+            function: local_function_index.as_u32() as usize,
+            blocks: Vec::new(),
+            block_start: true,
+            position: None,
+            tracker: BlockTracker::default(),
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.is_some() {
+            panic!("Coverage::transform_module_info: Attempting to use a `Coverage` middleware from multiple modules.");
+        }
+
+        let function_names = (module_info.num_imported_functions..module_info.functions.len())
+            .map(|index| {
+                let function_index = FunctionIndex::from_u32(index as u32);
+                module_info
+                    .function_names
+                    .get(&function_index)
+                    .cloned()
+                    .unwrap_or_else(|| format!("wasm-function[{}]", index))
+            })
+            .collect::<Vec<_>>();
+
+        // Append a memory holding the counters.
+        let size = u64::from(self.max_blocks) * u64::from(COUNTER_SIZE);
+        let pages = Pages(
+            ((size + wasmer::WASM_PAGE_SIZE as u64 - 1) / wasmer::WASM_PAGE_SIZE as u64) as u32,
+        );
+        let memory = module_info
+            .memories
+            .push(MemoryType::new(pages, Some(pages), false));
+
+        module_info.exports.insert(
+            "wasmer_coverage_counters".to_string(),
+            ExportIndex::Memory(memory),
+        );
+
+        *state = Some(Arc::new(CoverageState {
+            memory,
+            blocks: function_names
+                .iter()
+                .map(|_| Mutex::new(Vec::new()))
+                .collect(),
+            function_names,
+            next_counter: AtomicU32::new(0),
+        }));
+    }
+}
+
+impl fmt::Debug for FunctionCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionCoverage")
+            .field("function", &self.function)
+            .field("blocks", &self.blocks)
+            .field("block_start", &self.block_start)
+            .field("position", &self.position)
+            .field("tracker", &self.tracker)
+            .finish()
+    }
+}
+
+impl FunctionMiddleware for FunctionCoverage {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let position = state.original_position();
+        let new_operator = self.position != Some(position);
+        self.position = Some(position);
+
+        if new_operator && self.block_start {
+            let counter = self.state.next_counter.fetch_add(1, Ordering::Relaxed);
+            if counter >= self.max_blocks {
+                return Err(MiddlewareError::new(
+                    "coverage",
+                    format!(
+                        "the module has more than {} basic blocks, see `Coverage::with_max_blocks`",
+                        self.max_blocks
+                    ),
+                ));
+            }
+            self.blocks.push(BasicBlock {
+                offset: position,
+                instructions: 0,
+                counter,
+            });
+
+            // counters[counter] += 1;
+            let memarg = MemArg {
+                align: 3,
+                max_align: 3,
+                offset: u64::from(counter * COUNTER_SIZE),
+                memory: self.state.memory.as_u32(),
+            };
+            state.extend(&[
+                Operator::I32Const { value: 0 },
+                Operator::I32Const { value: 0 },
+                Operator::I64Load { memarg },
+                Operator::I64Const { value: 1 },
+                Operator::I64Add,
+                Operator::I64Store { memarg },
+            ]);
+        }
+        if new_operator {
+            if let Some(block) = self.blocks.last_mut() {
+                block.instructions += 1;
+            }
+        }
+
+        if self.tracker.ends_function(&operator) {
+            *self.state.blocks[self.function].lock().unwrap() = std::mem::take(&mut self.blocks);
+        }
+        // The blocks inserted by the previous middlewares are balanced,
+        // so whether a basic block starts depends on the last operator
+        // fed for an operator of the module.
+        self.block_start = self.tracker.ends_block(&operator);
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+impl CoverageReport {
+    /// Writes the report as an lcov tracefile.
+    ///
+    /// The debug information of the module isn't used, so the "line"
+    /// of a basic block, and of a function, is the byte offset of its
+    /// first operator in the module, rather than a line of the source
+    /// code it was compiled from. `source_name` is used as the source
+    /// file of every function.
+    pub fn write_lcov(&self, out: &mut impl Write, source_name: &str) -> io::Result<()> {
+        writeln!(out, "TN:")?;
+        writeln!(out, "SF:{}", source_name)?;
+
+        for function in &self.functions {
+            if let Some(entry) = function.blocks.first() {
+                writeln!(out, "FN:{},{}", entry.offset, function.name)?;
+            }
+        }
+        for function in &self.functions {
+            if let Some(entry) = function.blocks.first() {
+                writeln!(out, "FNDA:{},{}", entry.count, function.name)?;
+            }
+        }
+        writeln!(out, "FNF:{}", self.functions.len())?;
+        writeln!(
+            out,
+            "FNH:{}",
+            self.functions
+                .iter()
+                .filter(|function| function.blocks.first().map_or(false, |b| b.count > 0))
+                .count()
+        )?;
+
+        let mut lines_found = 0;
+        let mut lines_hit = 0;
+        for block in self.functions.iter().flat_map(|function| &function.blocks) {
+            writeln!(out, "DA:{},{}", block.offset, block.count)?;
+            lines_found += 1;
+            if block.count > 0 {
+                lines_hit += 1;
+            }
+        }
+        writeln!(out, "LF:{}", lines_found)?;
+        writeln!(out, "LH:{}", lines_hit)?;
+        writeln!(out, "end_of_record")
+    }
+
+    /// Writes the number of instructions executed by each function in
+    /// the folded stacks format, as read by flamegraph tools.
+    ///
+    /// Functions that haven't been executed are left out.
+    pub fn write_folded(&self, out: &mut impl Write) -> io::Result<()> {
+        for function in &self.functions {
+            let instructions: u64 = function
+                .blocks
+                .iter()
+                .map(|block| block.count * block.instructions)
+                .sum();
+            if instructions > 0 {
+                writeln!(out, "{} {}", function.name, instructions)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Metering;
+    use wasmer::sys::EngineBuilder;
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, TypedFunction};
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (func $abs_f (param $value i32) (result i32)
+                local.get $value
+                i32.const 0
+                i32.lt_s
+                if (result i32)
+                    i32.const 0
+                    local.get $value
+                    i32.sub
+                else
+                    local.get $value
+                end)
+            (func $unused_f)
+            (export "abs" (func $abs_f)))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn report_after_calls(values: &[i32]) -> CoverageReport {
+        report_after_calls_with(Cranelift::default(), values)
+    }
+
+    fn report_after_calls_with(
+        mut compiler_config: impl CompilerConfig + 'static,
+        values: &[i32],
+    ) -> CoverageReport {
+        let coverage = Arc::new(Coverage::new());
+        compiler_config.push_middleware(coverage.clone());
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(&store, bytecode()).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+
+        let abs: TypedFunction<i32, i32> = instance
+            .exports
+            .get_function("abs")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+        for value in values {
+            assert_eq!(abs.call(&mut store, *value).unwrap(), value.abs());
+        }
+
+        coverage.report(&mut store, &instance)
+    }
+
+    #[test]
+    fn counts_basic_blocks() {
+        let report = report_after_calls(&[-1, 2, 3]);
+
+        assert_eq!(report.functions.len(), 2);
+        let abs = &report.functions[0];
+        assert_eq!(abs.name, "abs_f");
+        // The entry, the `if` and `else` branches, and after the `if`
+        assert_eq!(
+            abs.blocks
+                .iter()
+                .map(|block| (block.instructions, block.count))
+                .collect::<Vec<_>>(),
+            vec![(4, 3), (4, 1), (2, 2), (1, 3)]
+        );
+
+        let unused = &report.functions[1];
+        assert_eq!(unused.name, "unused_f");
+        assert_eq!(unused.blocks.len(), 1);
+        assert_eq!(unused.blocks[0].count, 0);
+    }
+
+    #[test]
+    fn counts_basic_blocks_after_other_middlewares() {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(Metering::new(100, |_: &Operator| 1)));
+        let report = report_after_calls_with(compiler_config, &[-1, 2, 3]);
+
+        assert_eq!(
+            report.functions[0]
+                .blocks
+                .iter()
+                .map(|block| (block.instructions, block.count))
+                .collect::<Vec<_>>(),
+            vec![(4, 3), (4, 1), (2, 2), (1, 3)]
+        );
+        assert_eq!(
+            report.functions[0].blocks,
+            report_after_calls(&[-1, 2, 3]).functions[0].blocks
+        );
+    }
+
+    #[test]
+    fn fails_when_there_are_too_many_blocks() {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(Coverage::new().with_max_blocks(4)));
+        let store = Store::new(EngineBuilder::new(compiler_config));
+        assert!(Module::new(&store, bytecode()).is_err());
+    }
+
+    #[test]
+    fn writes_lcov_and_folded_stacks() {
+        let report = report_after_calls(&[5]);
+        let abs = &report.functions[0];
+        let unused = &report.functions[1];
+
+        let mut lcov = Vec::new();
+        report.write_lcov(&mut lcov, "abs.wasm").unwrap();
+        let expected = format!(
+            "TN:\nSF:abs.wasm\nFN:{},abs_f\nFN:{},unused_f\nFNDA:1,abs_f\nFNDA:0,unused_f\n\
+             FNF:2\nFNH:1\nDA:{},1\nDA:{},0\nDA:{},1\nDA:{},1\nDA:{},0\nLF:5\nLH:3\nend_of_record\n",
+            abs.blocks[0].offset,
+            unused.blocks[0].offset,
+            abs.blocks[0].offset,
+            abs.blocks[1].offset,
+            abs.blocks[2].offset,
+            abs.blocks[3].offset,
+            unused.blocks[0].offset,
+        );
+        assert_eq!(String::from_utf8(lcov).unwrap(), expected);
+
+        let mut folded = Vec::new();
+        report.write_folded(&mut folded).unwrap();
+        assert_eq!(String::from_utf8(folded).unwrap(), "abs_f 7\n");
+    }
+}
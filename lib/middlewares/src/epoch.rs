@@ -0,0 +1,333 @@
+//! `epoch` is a middleware for interrupting the WebAssembly code
+//! running in a [`Store`][wasmer::Store] from another thread, at a
+//! much lower cost than [`Metering`][crate::Metering].
+//!
+//! The compiled code compares the epoch of the engine with the epoch
+//! deadline of the store at every function entry and loop header. The
+//! epoch is incremented with `Engine::increment_epoch`, and the
+//! deadline is set with `Store::set_epoch_deadline`. Once the deadline
+//! is reached, the code traps with [`TrapCode::Interrupt`], or invokes
+//! the callback set with `Store::epoch_deadline_callback`.
+//!
+//! [`TrapCode::Interrupt`]: wasmer_types::TrapCode::Interrupt
+
+use std::fmt;
+use std::sync::Mutex;
+use wasmer::wasmparser::{BlockType as WpTypeOrFuncType, Operator};
+use wasmer::{
+    ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, LocalFunctionIndex, MiddlewareError,
+    MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+};
+use wasmer_types::{GlobalIndex, ModuleInfo};
+use wasmer_vm::{EPOCH_DEADLINE_GLOBAL_NAME, EPOCH_GLOBAL_NAME};
+
+#[derive(Clone)]
+struct EpochGlobalIndexes {
+    epoch: GlobalIndex,
+    deadline: GlobalIndex,
+}
+
+impl fmt::Debug for EpochGlobalIndexes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EpochGlobalIndexes")
+            .field("epoch", &self.epoch)
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+/// The module-level epoch interruption middleware.
+///
+/// # Panic
+///
+/// An instance of `EpochInterruption` should _not_ be shared among
+/// different modules, since it tracks module-specific information
+/// like the global indexes of the epoch and the deadline. Attempts to
+/// use an `EpochInterruption` instance from multiple modules will
+/// result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::EpochInterruption;
+///
+/// fn create_epoch_interruption_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     compiler_config.push_middleware(Arc::new(EpochInterruption::new()));
+/// }
+/// ```
+pub struct EpochInterruption {
+    /// The global indexes for the epoch and the deadline.
+    global_indexes: Mutex<Option<EpochGlobalIndexes>>,
+}
+
+/// The function-level epoch interruption middleware.
+pub struct FunctionEpochInterruption {
+    /// The global indexes for the epoch and the deadline.
+    global_indexes: EpochGlobalIndexes,
+
+    /// Whether the function entry has been instrumented.
+    entered: bool,
+}
+
+impl EpochInterruption {
+    /// Creates an `EpochInterruption` middleware.
+    pub fn new() -> Self {
+        Self {
+            global_indexes: Mutex::new(None),
+        }
+    }
+}
+
+impl Default for EpochInterruption {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for EpochInterruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EpochInterruption")
+            .field("global_indexes", &self.global_indexes)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for EpochInterruption {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionEpochInterruption {
+            global_indexes: self.global_indexes.lock().unwrap().clone().unwrap(),
+            entered: false,
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut global_indexes = self.global_indexes.lock().unwrap();
+
+        if global_indexes.is_some() {
+            panic!("EpochInterruption::transform_module_info: Attempting to use an `EpochInterruption` middleware from multiple modules.");
+        }
+
+        let mut push_global = |name: &str| {
+            let global_index = module_info
+                .globals
+                .push(GlobalType::new(Type::I64, Mutability::Var));
+            // The global is bound to the engine or to the store when the
+            // module is instantiated, and initialized with its own value
+            // so that instantiating doesn't reset it.
+            module_info
+                .global_initializers
+                .push(GlobalInit::GetGlobal(global_index));
+            module_info
+                .exports
+                .insert(name.to_string(), ExportIndex::Global(global_index));
+            global_index
+        };
+
+        *global_indexes = Some(EpochGlobalIndexes {
+            epoch: push_global(EPOCH_GLOBAL_NAME),
+            deadline: push_global(EPOCH_DEADLINE_GLOBAL_NAME),
+        });
+    }
+}
+
+impl fmt::Debug for FunctionEpochInterruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionEpochInterruption")
+            .field("global_indexes", &self.global_indexes)
+            .field("entered", &self.entered)
+            .finish()
+    }
+}
+
+impl FunctionEpochInterruption {
+    /// Checks the epoch deadline.
+    fn check_deadline(&self, state: &mut MiddlewareReaderState<'_>) {
+        // if unsigned(globals[epoch]) >= unsigned(globals[deadline]) { deadline_reached(); }
+        state.extend(&[
+            Operator::GlobalGet {
+                global_index: self.global_indexes.epoch.as_u32(),
+            },
+            Operator::GlobalGet {
+                global_index: self.global_indexes.deadline.as_u32(),
+            },
+            Operator::I64GeU,
+            Operator::If {
+                blockty: WpTypeOrFuncType::Empty,
+            },
+        ]);
+        state.push_epoch_deadline();
+        state.push_operator(Operator::End);
+    }
+}
+
+impl FunctionMiddleware for FunctionEpochInterruption {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        // Function entry, before the first operator of the body.
+        if !self.entered {
+            self.entered = true;
+            self.check_deadline(state);
+        }
+
+        match operator {
+            // Loop headers, checked on every iteration.
+            Operator::Loop { .. } => {
+                state.push_operator(operator);
+                self.check_deadline(state);
+            }
+            _ => state.push_operator(operator),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+    use wasmer::sys::{EngineBuilder, NativeEngineExt};
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Instance, Module, Store};
+    use wasmer::{RuntimeError, TypedFunction};
+    use wasmer_types::TrapCode;
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"(module
+            (func $spin_f
+                loop
+                    br 0
+                end)
+            (func $count_f (param $n i32) (result i32)
+                (local $i i32)
+                loop
+                    local.get $i
+                    i32.const 1
+                    i32.add
+                    local.tee $i
+                    local.get $n
+                    i32.lt_u
+                    br_if 0
+                end
+                local.get $i)
+            (export "spin" (func $spin_f))
+            (export "count" (func $count_f)))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn instantiate() -> (Store, Instance) {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(EpochInterruption::new()));
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(&store, bytecode()).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        (store, instance)
+    }
+
+    #[test]
+    fn no_deadline_is_never_reached() {
+        let (mut store, instance) = instantiate();
+        let count: TypedFunction<i32, i32> = instance
+            .exports
+            .get_function("count")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+
+        store.engine().increment_epoch();
+        assert_eq!(count.call(&mut store, 1000).unwrap(), 1000);
+    }
+
+    #[test]
+    fn incrementing_the_epoch_interrupts() {
+        let (mut store, instance) = instantiate();
+        let spin: TypedFunction<(), ()> = instance
+            .exports
+            .get_function("spin")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+
+        store.set_epoch_deadline(1);
+        let engine = store.engine().clone();
+        let ticker = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            engine.increment_epoch();
+        });
+        let err = spin.call(&mut store).unwrap_err();
+        ticker.join().unwrap();
+        assert_eq!(err.to_trap(), Some(TrapCode::Interrupt));
+
+        // The deadline stays reached until it is set again
+        let err = spin.call(&mut store).unwrap_err();
+        assert_eq!(err.to_trap(), Some(TrapCode::Interrupt));
+    }
+
+    #[test]
+    fn deadline_callback_is_invoked() {
+        let (mut store, instance) = instantiate();
+        let count: TypedFunction<i32, i32> = instance
+            .exports
+            .get_function("count")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+
+        let invocations = Arc::new(AtomicUsize::new(0));
+        store.epoch_deadline_callback({
+            let invocations = invocations.clone();
+            move || {
+                invocations.fetch_add(1, Ordering::SeqCst);
+                Ok(0)
+            }
+        });
+        store.set_epoch_deadline(0);
+
+        // Once on entry, and once per iteration
+        assert_eq!(count.call(&mut store, 10).unwrap(), 10);
+        assert_eq!(invocations.load(Ordering::SeqCst), 11);
+    }
+
+    #[test]
+    fn deadline_callback_errors_trap() {
+        let (mut store, instance) = instantiate();
+        let spin: TypedFunction<(), ()> = instance
+            .exports
+            .get_function("spin")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+
+        let mut remaining = 3;
+        store.epoch_deadline_callback(move || {
+            if remaining == 0 {
+                return Err(Box::new(RuntimeError::new("timeout")));
+            }
+            remaining -= 1;
+            Ok(0)
+        });
+        store.set_epoch_deadline(0);
+
+        let err = spin.call(&mut store).unwrap_err();
+        assert_eq!(err.message(), "timeout");
+
+        // Trapping is the default behavior again
+        store.epoch_deadline_trap();
+        let err = spin.call(&mut store).unwrap_err();
+        assert_eq!(err.to_trap(), Some(TrapCode::Interrupt));
+    }
+}
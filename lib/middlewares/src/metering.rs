@@ -3,6 +3,10 @@
 //! operators executed. The WebAssembly instance execution is stopped
 //! when the limit is reached.
 //!
+//! Calls to imported functions can be given a cost too, see
+//! [`Metering::with_import_cost_function`], and host functions can
+//! charge for the work they do through a [`MeteringHandle`].
+//!
 //! # Example
 //!
 //! [See the `metering` detailed and complete
@@ -13,10 +17,15 @@ use std::fmt;
 use std::sync::{Arc, Mutex};
 use wasmer::wasmparser::{BlockType as WpTypeOrFuncType, Operator};
 use wasmer::{
-    AsStoreMut, ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance,
-    LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+    AsStoreMut, ExportError, ExportIndex, FunctionMiddleware, Global, GlobalInit, GlobalType,
+    Instance, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware,
+    Mutability, RuntimeError, Type,
 };
-use wasmer_types::{GlobalIndex, ModuleInfo};
+use wasmer_types::{entity::EntityRef, FunctionIndex, GlobalIndex, ImportIndex, ModuleInfo};
+
+/// Function that maps an imported function, by module and field
+/// name, to the cost in "points" of calling it.
+type ImportCostFunction = dyn Fn(&str, &str) -> u64 + Send + Sync;
 
 #[derive(Clone)]
 struct MeteringGlobalIndexes(GlobalIndex, GlobalIndex);
@@ -87,8 +96,19 @@ pub struct Metering<F: Fn(&Operator) -> u64 + Send + Sync> {
     /// Function that maps each operator to a cost in "points".
     cost_function: Arc<F>,
 
+    /// Function that maps each imported function to the cost in
+    /// "points" of calling it.
+    import_cost_function: Option<Arc<ImportCostFunction>>,
+
     /// The global indexes for metering points.
     global_indexes: Mutex<Option<MeteringGlobalIndexes>>,
+
+    /// The cost of calling each imported function, by function index.
+    import_costs: Mutex<Arc<[u64]>>,
+
+    /// The cost of the imported functions reachable through an indirect
+    /// call, by signature index.
+    indirect_call_costs: Mutex<Arc<[u64]>>,
 }
 
 /// The function-level metering middleware.
@@ -99,6 +119,13 @@ pub struct FunctionMetering<F: Fn(&Operator) -> u64 + Send + Sync> {
     /// The global indexes for metering points.
     global_indexes: MeteringGlobalIndexes,
 
+    /// The cost of calling each imported function, by function index.
+    import_costs: Arc<[u64]>,
+
+    /// The cost of the imported functions reachable through an indirect
+    /// call, by signature index.
+    indirect_call_costs: Arc<[u64]>,
+
     /// Accumulated cost of the current basic block.
     accumulated_cost: u64,
 }
@@ -128,9 +155,41 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> Metering<F> {
         Self {
             initial_limit,
             cost_function: Arc::new(cost_function),
+            import_cost_function: None,
             global_indexes: Mutex::new(None),
+            import_costs: Mutex::new(Arc::new([])),
+            indirect_call_costs: Mutex::new(Arc::new([])),
         }
     }
+
+    /// Sets the function giving the cost in "points" of calling an
+    /// imported function, from its module and field names.
+    ///
+    /// The cost is charged on top of the cost of the `call` operator
+    /// itself, before the imported function runs. As the callee of an
+    /// indirect call (`call_indirect`, `call_ref` and their tail call
+    /// variants) is only known at runtime, those calls are charged the
+    /// highest cost of the imported functions with the same signature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use wasmer::wasmparser::Operator;
+    /// use wasmer_middlewares::Metering;
+    ///
+    /// let metering = Metering::new(1_000, |_operator: &Operator| -> u64 { 1 })
+    ///     .with_import_cost_function(|module, field| match (module, field) {
+    ///         ("wasi_snapshot_preview1", "fd_write") => 100,
+    ///         _ => 10,
+    ///     });
+    /// ```
+    pub fn with_import_cost_function(
+        mut self,
+        import_cost_function: impl Fn(&str, &str) -> u64 + Send + Sync + 'static,
+    ) -> Self {
+        self.import_cost_function = Some(Arc::new(import_cost_function));
+        self
+    }
 }
 
 impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Metering<F> {
@@ -138,6 +197,10 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Metering<F> {
         f.debug_struct("Metering")
             .field("initial_limit", &self.initial_limit)
             .field("cost_function", &"<function>")
+            .field(
+                "import_cost_function",
+                &self.import_cost_function.as_ref().map(|_| "<function>"),
+            )
             .field("global_indexes", &self.global_indexes)
             .finish()
     }
@@ -149,6 +212,8 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Meter
         Box::new(FunctionMetering {
             cost_function: self.cost_function.clone(),
             global_indexes: self.global_indexes.lock().unwrap().clone().unwrap(),
+            import_costs: self.import_costs.lock().unwrap().clone(),
+            indirect_call_costs: self.indirect_call_costs.lock().unwrap().clone(),
             accumulated_cost: 0,
         })
     }
@@ -192,7 +257,43 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Meter
         *global_indexes = Some(MeteringGlobalIndexes(
             remaining_points_global_index,
             points_exhausted_global_index,
-        ))
+        ));
+
+        // Compute the cost of calling each imported function.
+        if let Some(import_cost_function) = &self.import_cost_function {
+            let mut import_costs = vec![0; module_info.num_imported_functions];
+
+            for (key, index) in module_info.imports.iter() {
+                if let ImportIndex::Function(function_index) = index {
+                    import_costs[function_index.as_u32() as usize] =
+                        import_cost_function(&key.module, &key.field);
+                }
+            }
+
+            // Any imported function could have been put in a table, so an
+            // indirect call costs as much as the most expensive import it
+            // could reach.
+            let indirect_call_costs: Vec<u64> = module_info
+                .signatures
+                .values()
+                .map(|signature| {
+                    import_costs
+                        .iter()
+                        .enumerate()
+                        .filter(|(index, _)| {
+                            let function_index = FunctionIndex::new(*index);
+                            &module_info.signatures[module_info.functions[function_index]]
+                                == signature
+                        })
+                        .map(|(_, cost)| *cost)
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+
+            *self.import_costs.lock().unwrap() = import_costs.into();
+            *self.indirect_call_costs.lock().unwrap() = indirect_call_costs.into();
+        }
     }
 }
 
@@ -216,6 +317,25 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMeter
         // corner cases.
         self.accumulated_cost += (self.cost_function)(&operator);
 
+        // Calling an imported function also costs what the import has been
+        // assigned, so that it is charged before the host does the work.
+        match operator {
+            Operator::Call { function_index } | Operator::ReturnCall { function_index } => {
+                if let Some(cost) = self.import_costs.get(function_index as usize) {
+                    self.accumulated_cost += cost;
+                }
+            }
+            Operator::CallIndirect { type_index, .. }
+            | Operator::ReturnCallIndirect { type_index, .. }
+            | Operator::CallRef { type_index }
+            | Operator::ReturnCallRef { type_index } => {
+                if let Some(cost) = self.indirect_call_costs.get(type_index as usize) {
+                    self.accumulated_cost += cost;
+                }
+            }
+            _ => {}
+        }
+
         // Possible sources and targets of a branch. Finalize the cost of the previous basic block and perform necessary checks.
         match operator {
             Operator::Loop { .. } // loop headers are branch targets
@@ -226,6 +346,10 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMeter
             | Operator::BrIf { .. } // branch source
             | Operator::Call { .. } // function call - branch source
             | Operator::CallIndirect { .. } // function call - branch source
+            | Operator::ReturnCall { .. } // tail call - branch source
+            | Operator::ReturnCallIndirect { .. } // tail call - branch source
+            | Operator::CallRef { .. } // function call - branch source
+            | Operator::ReturnCallRef { .. } // tail call - branch source
             | Operator::Return // end of function - branch source
             => {
                 if self.accumulated_cost > 0 {
@@ -283,27 +407,9 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMeter
 /// }
 /// ```
 pub fn get_remaining_points(ctx: &mut impl AsStoreMut, instance: &Instance) -> MeteringPoints {
-    let exhausted: i32 = instance
-        .exports
-        .get_global("wasmer_metering_points_exhausted")
-        .expect("Can't get `wasmer_metering_points_exhausted` from Instance")
-        .get(ctx)
-        .try_into()
-        .expect("`wasmer_metering_points_exhausted` from Instance has wrong type");
-
-    if exhausted > 0 {
-        return MeteringPoints::Exhausted;
-    }
-
-    let points = instance
-        .exports
-        .get_global("wasmer_metering_remaining_points")
-        .expect("Can't get `wasmer_metering_remaining_points` from Instance")
-        .get(ctx)
-        .try_into()
-        .expect("`wasmer_metering_remaining_points` from Instance has wrong type");
-
-    MeteringPoints::Remaining(points)
+    MeteringHandle::new(instance)
+        .expect("Can't get the metering globals from Instance")
+        .remaining_points(ctx)
 }
 
 /// Set the new provided remaining points in an
@@ -333,19 +439,120 @@ pub fn get_remaining_points(ctx: &mut impl AsStoreMut, instance: &Instance) -> M
 /// }
 /// ```
 pub fn set_remaining_points(ctx: &mut impl AsStoreMut, instance: &Instance, points: u64) {
-    instance
-        .exports
-        .get_global("wasmer_metering_remaining_points")
-        .expect("Can't get `wasmer_metering_remaining_points` from Instance")
-        .set(ctx, points.into())
-        .expect("Can't set `wasmer_metering_remaining_points` in Instance");
-
-    instance
-        .exports
-        .get_global("wasmer_metering_points_exhausted")
-        .expect("Can't get `wasmer_metering_points_exhausted` from Instance")
-        .set(ctx, 0i32.into())
-        .expect("Can't set `wasmer_metering_points_exhausted` in Instance");
+    MeteringHandle::new(instance)
+        .expect("Can't get the metering globals from Instance")
+        .set_remaining_points(ctx, points)
+}
+
+/// A handle on the metering points of an [`Instance`][wasmer::Instance].
+///
+/// Unlike [`get_remaining_points`] and [`set_remaining_points`], it
+/// doesn't need the instance once created, so it can be stored in the
+/// environment of host functions to charge for the work they do
+/// through their [`FunctionEnvMut`][wasmer::FunctionEnvMut]. The points
+/// are shared with the ones consumed by WebAssembly code.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{FunctionEnvMut, RuntimeError};
+/// use wasmer_middlewares::metering::MeteringHandle;
+///
+/// struct Env {
+///     // Set once the instance has been created.
+///     metering: Option<MeteringHandle>,
+/// }
+///
+/// /// A host function whose cost depends on its input.
+/// fn hash(mut env: FunctionEnvMut<Env>, len: u32) -> Result<(), RuntimeError> {
+///     let metering = env.data().metering.clone().unwrap();
+///     metering.consume_points(&mut env, u64::from(len) * 10)?;
+///
+///     // Do the actual work...
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MeteringHandle {
+    remaining_points: Global,
+    points_exhausted: Global,
+}
+
+impl MeteringHandle {
+    /// Creates a handle on the metering points of an instance.
+    ///
+    /// The instance must have been processed with the [`Metering`]
+    /// middleware at compile time, otherwise an error is returned.
+    pub fn new(instance: &Instance) -> Result<Self, ExportError> {
+        Ok(Self {
+            remaining_points: instance
+                .exports
+                .get_global("wasmer_metering_remaining_points")?
+                .clone(),
+            points_exhausted: instance
+                .exports
+                .get_global("wasmer_metering_points_exhausted")?
+                .clone(),
+        })
+    }
+
+    /// Get the remaining points, see [`get_remaining_points`].
+    pub fn remaining_points(&self, store: &mut impl AsStoreMut) -> MeteringPoints {
+        let exhausted: i32 = self
+            .points_exhausted
+            .get(store)
+            .try_into()
+            .expect("`wasmer_metering_points_exhausted` from Instance has wrong type");
+
+        if exhausted > 0 {
+            return MeteringPoints::Exhausted;
+        }
+
+        let points = self
+            .remaining_points
+            .get(store)
+            .try_into()
+            .expect("`wasmer_metering_remaining_points` from Instance has wrong type");
+
+        MeteringPoints::Remaining(points)
+    }
+
+    /// Set the remaining points, see [`set_remaining_points`].
+    pub fn set_remaining_points(&self, store: &mut impl AsStoreMut, points: u64) {
+        self.remaining_points
+            .set(store, points.into())
+            .expect("Can't set `wasmer_metering_remaining_points` in Instance");
+        self.points_exhausted
+            .set(store, 0i32.into())
+            .expect("Can't set `wasmer_metering_points_exhausted` in Instance");
+    }
+
+    /// Consume the given number of points.
+    ///
+    /// If there aren't enough points left, the points are marked as
+    /// exhausted and an error is returned, which stops the execution
+    /// when returned from a host function, like WebAssembly code
+    /// running out of points would.
+    pub fn consume_points(
+        &self,
+        store: &mut impl AsStoreMut,
+        points: u64,
+    ) -> Result<(), RuntimeError> {
+        match self.remaining_points(store) {
+            MeteringPoints::Remaining(remaining) if remaining >= points => {
+                self.remaining_points
+                    .set(store, (remaining - points).into())
+                    .expect("Can't set `wasmer_metering_remaining_points` in Instance");
+                Ok(())
+            }
+            _ => {
+                self.points_exhausted
+                    .set(store, 1i32.into())
+                    .expect("Can't set `wasmer_metering_points_exhausted` in Instance");
+                Err(RuntimeError::new("metering points exhausted"))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -354,7 +561,10 @@ mod tests {
 
     use std::sync::Arc;
     use wasmer::sys::EngineBuilder;
-    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, TypedFunction};
+    use wasmer::{
+        imports, wat2wasm, CompilerConfig, Cranelift, Function, FunctionEnv, FunctionEnvMut,
+        Module, Store, TypedFunction,
+    };
 
     fn cost_function(operator: &Operator) -> u64 {
         match operator {
@@ -484,4 +694,193 @@ mod tests {
             MeteringPoints::Remaining(4)
         );
     }
+
+    fn bytecode_with_imports() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (import "env" "cheap" (func $cheap_f))
+            (import "env" "expensive" (func $expensive_f (param i32)))
+            (func (export "run") (param $value i32)
+                call $cheap_f
+                local.get $value
+                call $expensive_f))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn import_costs_are_charged() {
+        let metering = Arc::new(Metering::new(30, cost_function).with_import_cost_function(
+            |module, field| match (module, field) {
+                ("env", "cheap") => 1,
+                ("env", "expensive") => 10,
+                _ => unreachable!(),
+            },
+        ));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(&store, bytecode_with_imports()).unwrap();
+
+        let imports = imports! {
+            "env" => {
+                "cheap" => Function::new_typed(&mut store, || {}),
+                "expensive" => Function::new_typed(&mut store, |_: i32| {}),
+            },
+        };
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        let run: TypedFunction<i32, ()> = instance
+            .exports
+            .get_function("run")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+
+        // Calling run costs 12 points: 1 for `cheap`, 1 for `local.get`
+        // and 10 for `expensive`.
+        run.call(&mut store, 1).unwrap();
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(18)
+        );
+
+        run.call(&mut store, 1).unwrap();
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(6)
+        );
+
+        assert!(run.call(&mut store, 1).is_err());
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Exhausted
+        );
+    }
+
+    #[test]
+    fn indirect_calls_to_imports_are_charged() {
+        let metering = Arc::new(Metering::new(30, cost_function).with_import_cost_function(
+            |module, field| match (module, field) {
+                ("env", "cheap") => 1,
+                ("env", "expensive") => 10,
+                _ => unreachable!(),
+            },
+        ));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let wasm = wat2wasm(
+            br#"
+            (module
+            (type $void_t (func))
+            (import "env" "cheap" (func $cheap_f))
+            (import "env" "expensive" (func $expensive_f (param i32)))
+            (table 2 funcref)
+            (elem (i32.const 0) $cheap_f $expensive_f)
+            (func (export "run") (param $value i32)
+                local.get $value
+                i32.const 1
+                call_indirect (param i32)))
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&store, wasm).unwrap();
+
+        let imports = imports! {
+            "env" => {
+                "cheap" => Function::new_typed(&mut store, || {}),
+                "expensive" => Function::new_typed(&mut store, |_: i32| {}),
+            },
+        };
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        let run: TypedFunction<i32, ()> = instance
+            .exports
+            .get_function("run")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+
+        // Calling run costs 12 points: 1 for `local.get`, 1 for
+        // `i32.const` and 10 for the only import taking an `i32`.
+        run.call(&mut store, 1).unwrap();
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(18)
+        );
+
+        run.call(&mut store, 1).unwrap();
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(6)
+        );
+
+        assert!(run.call(&mut store, 1).is_err());
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Exhausted
+        );
+    }
+
+    #[test]
+    fn host_functions_consume_points() {
+        struct Env {
+            metering: Option<MeteringHandle>,
+        }
+
+        fn expensive(mut env: FunctionEnvMut<Env>, value: i32) -> Result<(), RuntimeError> {
+            let metering = env.data().metering.clone().unwrap();
+            metering.consume_points(&mut env, value as u64)
+        }
+
+        let metering = Arc::new(Metering::new(20, cost_function));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(&store, bytecode_with_imports()).unwrap();
+
+        let env = FunctionEnv::new(&mut store, Env { metering: None });
+        let imports = imports! {
+            "env" => {
+                "cheap" => Function::new_typed(&mut store, || {}),
+                "expensive" => Function::new_typed_with_env(&mut store, &env, expensive),
+            },
+        };
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        let handle = MeteringHandle::new(&instance).unwrap();
+        env.as_mut(&mut store).metering = Some(handle.clone());
+        let run: TypedFunction<i32, ()> = instance
+            .exports
+            .get_function("run")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+
+        // Calling run costs 1 point for `local.get`, and the host
+        // function consumes as many points as its argument.
+        run.call(&mut store, 9).unwrap();
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(10)
+        );
+        assert_eq!(
+            handle.remaining_points(&mut store),
+            MeteringPoints::Remaining(10)
+        );
+
+        assert!(run.call(&mut store, 10).is_err());
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Exhausted
+        );
+
+        handle.set_remaining_points(&mut store, 10);
+        run.call(&mut store, 9).unwrap();
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(0)
+        );
+    }
 }
@@ -16,6 +16,8 @@ pub struct FrameInfo {
     func_start: SourceLoc,
     /// The source location of the instruction
     instr: SourceLoc,
+    /// The source code locations of the instruction, if known.
+    symbols: Vec<FrameSymbol>,
 }
 
 impl FrameInfo {
@@ -33,9 +35,16 @@ impl FrameInfo {
             function_name,
             func_start,
             instr,
+            symbols: Vec::new(),
         }
     }
 
+    /// Attaches the source code locations of the instruction to this frame.
+    pub fn with_symbols(mut self, symbols: Vec<FrameSymbol>) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
     /// Returns the WebAssembly function index for this frame.
     ///
     /// This function index is the index in the function index space of the
@@ -93,4 +102,71 @@ impl FrameInfo {
     pub fn func_offset(&self) -> usize {
         (self.instr.bits() - self.func_start.bits()) as usize
     }
+
+    /// Returns the source code locations of the instruction this frame
+    /// points to, as described by the DWARF debugging information of the
+    /// module.
+    ///
+    /// There is more than one symbol when the instruction belongs to
+    /// functions inlined into the function of this frame, in which case the
+    /// innermost inlined function comes first. The list is empty when the
+    /// module has no debugging information, or when the engine is not
+    /// configured to resolve it.
+    pub fn symbols(&self) -> &[FrameSymbol] {
+        &self.symbols
+    }
+}
+
+/// A source code location of a frame in a backtrace, from the DWARF
+/// debugging information of its module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameSymbol {
+    /// The name of the function, possibly mangled
+    name: Option<String>,
+    /// The path of the source file
+    file: Option<String>,
+    /// The line in the source file, starting at 1
+    line: Option<u32>,
+    /// The column in the line, starting at 1
+    column: Option<u32>,
+}
+
+impl FrameSymbol {
+    /// Creates a new [FrameSymbol].
+    pub fn new(
+        name: Option<String>,
+        file: Option<String>,
+        line: Option<u32>,
+        column: Option<u32>,
+    ) -> Self {
+        Self {
+            name,
+            file,
+            line,
+            column,
+        }
+    }
+
+    /// Returns the name of the function of this location, if known.
+    ///
+    /// This is the name found in the debugging information, which may be a
+    /// mangled one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the path of the source file, if known.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Returns the line in the source file, if known.
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Returns the column in the source line, if known.
+    pub fn column(&self) -> Option<u32> {
+        self.column
+    }
 }
@@ -69,6 +69,13 @@ pub enum TrapCode {
 
     /// An atomic memory access was attempted with an unaligned pointer.
     UnalignedAtomic = 10,
+
+    /// The call depth limit enforced by a middleware was exceeded.
+    CallDepthExceeded = 11,
+
+    /// Execution was interrupted because the epoch deadline of the store
+    /// was reached.
+    Interrupt = 12,
 }
 
 impl TrapCode {
@@ -86,6 +93,8 @@ impl TrapCode {
             Self::BadConversionToInteger => "invalid conversion to integer",
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unaligned atomic access",
+            Self::CallDepthExceeded => "call depth limit exceeded",
+            Self::Interrupt => "interrupted",
         }
     }
 }
@@ -104,6 +113,8 @@ impl Display for TrapCode {
             Self::BadConversionToInteger => "bad_toint",
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unalign_atom",
+            Self::CallDepthExceeded => "call_depth",
+            Self::Interrupt => "interrupt",
         };
         f.write_str(identifier)
     }
@@ -125,6 +136,8 @@ impl FromStr for TrapCode {
             "bad_toint" => Ok(Self::BadConversionToInteger),
             "unreachable" => Ok(Self::UnreachableCodeReached),
             "unalign_atom" => Ok(Self::UnalignedAtomic),
+            "call_depth" => Ok(Self::CallDepthExceeded),
+            "interrupt" => Ok(Self::Interrupt),
             _ => Err(()),
         }
     }
@@ -148,7 +161,7 @@ mod tests {
     use super::*;
 
     // Everything but user-defined codes.
-    const CODES: [TrapCode; 11] = [
+    const CODES: [TrapCode; 13] = [
         TrapCode::StackOverflow,
         TrapCode::HeapAccessOutOfBounds,
         TrapCode::HeapMisaligned,
@@ -160,6 +173,8 @@ mod tests {
         TrapCode::BadConversionToInteger,
         TrapCode::UnreachableCodeReached,
         TrapCode::UnalignedAtomic,
+        TrapCode::CallDepthExceeded,
+        TrapCode::Interrupt,
     ];
 
     #[test]
@@ -140,9 +140,41 @@ impl VMBuiltinFunctionIndex {
     pub const fn get_imported_memory_atomic_notify_index() -> Self {
         Self(29)
     }
+    /// Returns an index for the fallback implementation of SIMD operations.
+    pub const fn get_simd_op_index() -> Self {
+        Self(30)
+    }
+    /// Returns an index for wasm's `throw`.
+    pub const fn get_exception_new_index() -> Self {
+        Self(31)
+    }
+    /// Returns an index for throwing an exception out of the instance.
+    pub const fn get_exception_raise_index() -> Self {
+        Self(32)
+    }
+    /// Returns an index for copying a caught exception.
+    pub const fn get_exception_clone_index() -> Self {
+        Self(33)
+    }
+    /// Returns an index for releasing a caught exception.
+    pub const fn get_exception_drop_index() -> Self {
+        Self(34)
+    }
+    /// Returns an index for matching a caught exception against a tag.
+    pub const fn get_exception_catch_index() -> Self {
+        Self(35)
+    }
+    /// Returns an index for calling a function inside of a `try_table` block.
+    pub const fn get_exception_invoke_index() -> Self {
+        Self(36)
+    }
+    /// Returns an index for checking the epoch deadline of the store.
+    pub const fn get_epoch_deadline_index() -> Self {
+        Self(37)
+    }
     /// Returns the total number of builtin functions.
     pub const fn builtin_functions_total_number() -> u32 {
-        30
+        38
     }
 
     /// Return the index as an u32 number.
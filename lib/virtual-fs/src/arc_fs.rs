@@ -2,7 +2,10 @@
 //! can pass clonable file systems with a `Box<dyn FileSystem>` to other
 //! interfaces
 
-use std::{path::Path, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use crate::*;
 
@@ -42,6 +45,14 @@ impl FileSystem for ArcFileSystem {
         self.fs.symlink_metadata(path)
     }
 
+    fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        self.fs.symlink(target, link)
+    }
+
+    fn readlink(&self, path: &Path) -> Result<PathBuf> {
+        self.fs.readlink(path)
+    }
+
     fn remove_file(&self, path: &Path) -> Result<()> {
         self.fs.remove_file(path)
     }
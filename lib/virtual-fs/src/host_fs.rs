@@ -153,6 +153,45 @@ impl crate::FileSystem for FileSystem {
             .and_then(TryInto::try_into)
             .map_err(Into::into)
     }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        fs::symlink_metadata(path)
+            .and_then(TryInto::try_into)
+            .map_err(Into::into)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        if link.parent().is_none() {
+            return Err(FsError::BaseNotDirectory);
+        }
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(target, link).map_err(Into::into)
+        }
+        #[cfg(windows)]
+        {
+            // Windows needs to know whether the link points to a directory,
+            // relative targets being relative to the directory of the link.
+            let is_dir = link
+                .parent()
+                .map(|parent| parent.join(target))
+                .map_or(false, |target| target.is_dir());
+            if is_dir {
+                std::os::windows::fs::symlink_dir(target, link).map_err(Into::into)
+            } else {
+                std::os::windows::fs::symlink_file(target, link).map_err(Into::into)
+            }
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            let _ = target;
+            Err(FsError::PermissionDenied)
+        }
+    }
+
+    fn readlink(&self, path: &Path) -> Result<PathBuf> {
+        fs::read_link(path).map_err(Into::into)
+    }
 }
 
 impl TryInto<Metadata> for std::fs::Metadata {
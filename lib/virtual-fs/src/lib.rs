@@ -40,6 +40,7 @@ mod filesystems;
 pub(crate) mod ops;
 mod overlay_fs;
 pub mod pipe;
+mod policy_fs;
 #[cfg(feature = "host-fs")]
 mod scoped_directory_fs;
 mod static_file;
@@ -67,6 +68,7 @@ pub use null_file::*;
 pub use overlay_fs::OverlayFileSystem;
 pub use passthru_fs::*;
 pub use pipe::*;
+pub use policy_fs::{FsAccess, FsPattern, FsPolicy, FsRule, PolicyFileSystem};
 #[cfg(feature = "host-fs")]
 pub use scoped_directory_fs::ScopedDirectoryFileSystem;
 pub use special_file::*;
@@ -96,12 +98,29 @@ pub trait FileSystem: fmt::Debug + Send + Sync + 'static + Upcastable {
     fn remove_dir(&self, path: &Path) -> Result<()>;
     fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<()>>;
     fn metadata(&self, path: &Path) -> Result<Metadata>;
-    /// This method gets metadata without following symlinks in the path.
-    /// Currently identical to `metadata` because symlinks aren't implemented
-    /// yet.
+    /// This method gets metadata without following a symlink at the end of
+    /// the path, so the metadata of the link itself is returned.
+    ///
+    /// File systems that don't support symlinks can rely on the default
+    /// implementation, which is identical to `metadata`.
     fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
         self.metadata(path)
     }
+    /// Creates a new symbolic link at `link` pointing to `target`.
+    ///
+    /// The target is stored as-is and is not required to exist. File systems
+    /// that don't support symlinks return [`FsError::PermissionDenied`].
+    fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        let _ = (target, link);
+        Err(FsError::PermissionDenied)
+    }
+    /// Reads the target of the symbolic link at `path`.
+    ///
+    /// Returns [`FsError::InvalidInput`] when `path` isn't a symlink.
+    fn readlink(&self, path: &Path) -> Result<PathBuf> {
+        self.symlink_metadata(path)?;
+        Err(FsError::InvalidInput)
+    }
     fn remove_file(&self, path: &Path) -> Result<()>;
 
     fn new_open_options(&self) -> OpenOptions;
@@ -144,6 +163,18 @@ where
         (**self).metadata(path)
     }
 
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        (**self).symlink_metadata(path)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        (**self).symlink(target, link)
+    }
+
+    fn readlink(&self, path: &Path) -> Result<PathBuf> {
+        (**self).readlink(path)
+    }
+
     fn remove_file(&self, path: &Path) -> Result<()> {
         (**self).remove_file(path)
     }
@@ -494,6 +525,9 @@ pub enum FsError {
     DirectoryNotEmpty,
     #[error("storage full")]
     StorageFull,
+    /// Too many symbolic links were encountered while resolving a path
+    #[error("too many levels of symbolic links")]
+    TooManySymlinks,
     /// Some other unhandled error. If you see this, it's probably a bug.
     #[error("unknown error found")]
     UnknownError,
@@ -557,6 +591,7 @@ impl From<FsError> for io::Error {
             FsError::DirectoryNotEmpty => io::ErrorKind::Other,
             FsError::UnknownError => io::ErrorKind::Other,
             FsError::StorageFull => io::ErrorKind::Other,
+            FsError::TooManySymlinks => io::ErrorKind::Other,
             // NOTE: Add this once the "io_error_more" Rust feature is stabilized
             // FsError::StorageFull => io::ErrorKind::StorageFull,
         };
@@ -683,6 +718,13 @@ impl FileType {
         }
     }
 
+    pub fn new_symlink() -> Self {
+        Self {
+            symlink: true,
+            ..Default::default()
+        }
+    }
+
     pub fn is_dir(&self) -> bool {
         self.dir
     }
@@ -329,6 +329,15 @@ impl crate::FileOpener for FileSystem {
     ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
         debug!(path=%path.display(), "open");
 
+        // Opening a symlink opens the node it points to.
+        let target = {
+            let fs = self.inner.read().map_err(|_| FsError::Lock)?;
+            fs.follow_symlinks(path)?
+        };
+        if let Some(target) = target {
+            return self.open(target.as_path(), conf);
+        }
+
         let read = conf.read();
         let mut write = conf.write();
         let append = conf.append();
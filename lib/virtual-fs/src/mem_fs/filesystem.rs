@@ -11,6 +11,10 @@ use std::fmt;
 use std::path::{Component, Path, PathBuf};
 use std::sync::{Arc, RwLock};
 
+/// The maximum number of symlinks followed while resolving a path,
+/// like `MAXSYMLINKS` on Linux.
+const MAX_SYMLINK_DEPTH: usize = 40;
+
 /// The in-memory file system!
 ///
 /// This `FileSystem` type can be cloned, it's a light copy of the
@@ -545,6 +549,117 @@ impl crate::FileSystem for FileSystem {
         }
     }
 
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        // Read lock.
+        let guard = self.inner.read().map_err(|_| FsError::Lock)?;
+        match guard.inode_of_nofollow(path)? {
+            InodeResolution::Found(inode) => Ok(guard
+                .storage
+                .get(inode)
+                .ok_or(FsError::UnknownError)?
+                .metadata()
+                .clone()),
+            InodeResolution::Redirect(fs, path) => {
+                drop(guard);
+                fs.symlink_metadata(path.as_path())
+            }
+        }
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        if target.as_os_str().is_empty() {
+            return Err(FsError::EntryNotFound);
+        }
+
+        let (inode_of_parent, name_of_link) = {
+            // Read lock.
+            let guard = self.inner.read().map_err(|_| FsError::Lock)?;
+
+            // Canonicalize the path without checking the path exists,
+            // because it's about to be created.
+            let path = guard.canonicalize_without_inode(link)?;
+
+            // Check the path has a parent.
+            let parent_of_path = path.parent().ok_or(FsError::BaseNotDirectory)?;
+
+            // Check the link name.
+            let name_of_link = path
+                .file_name()
+                .ok_or(FsError::InvalidInput)?
+                .to_os_string();
+
+            // Find the parent inode.
+            let inode_of_parent = match guard.inode_of_parent(parent_of_path)? {
+                InodeResolution::Found(a) => a,
+                InodeResolution::Redirect(fs, mut path) => {
+                    drop(guard);
+                    path.push(name_of_link);
+                    return fs.symlink(target, path.as_path());
+                }
+            };
+
+            // Check nothing already exists with the same name.
+            if guard
+                .as_parent_get_position_and_inode(inode_of_parent, &name_of_link)?
+                .is_some()
+            {
+                return Err(FsError::AlreadyExists);
+            }
+
+            (inode_of_parent, name_of_link)
+        };
+
+        {
+            // Write lock.
+            let mut fs = self.inner.write().map_err(|_| FsError::Lock)?;
+
+            // Creating the symlink in the storage.
+            let inode_of_link = fs.storage.vacant_entry().key();
+            let real_inode_of_link = fs.storage.insert(Node::Symlink(SymlinkNode {
+                inode: inode_of_link,
+                name: name_of_link,
+                target: target.to_path_buf(),
+                metadata: {
+                    let time = time();
+
+                    Metadata {
+                        ft: FileType::new_symlink(),
+                        accessed: time,
+                        created: time,
+                        modified: time,
+                        len: target.as_os_str().len() as u64,
+                    }
+                },
+            }));
+
+            assert_eq!(
+                inode_of_link, real_inode_of_link,
+                "new symlink inode should have been correctly calculated",
+            );
+
+            // Adding the new symlink to its parent.
+            fs.add_child_to_node(inode_of_parent, inode_of_link)?;
+        }
+
+        Ok(())
+    }
+
+    fn readlink(&self, path: &Path) -> Result<PathBuf> {
+        // Read lock.
+        let guard = self.inner.read().map_err(|_| FsError::Lock)?;
+        match guard.inode_of_nofollow(path)? {
+            InodeResolution::Found(inode) => match guard.storage.get(inode) {
+                Some(Node::Symlink(SymlinkNode { target, .. })) => Ok(target.clone()),
+                Some(_) => Err(FsError::InvalidInput),
+                None => Err(FsError::UnknownError),
+            },
+            InodeResolution::Redirect(fs, path) => {
+                drop(guard);
+                fs.readlink(path.as_path())
+            }
+        }
+    }
+
     fn remove_file(&self, path: &Path) -> Result<()> {
         let (inode_of_parent, position, inode_of_file) = {
             // Read lock.
@@ -641,8 +756,45 @@ impl InodeResolution {
 }
 
 impl FileSystemInner {
-    /// Get the inode associated to a path if it exists.
+    /// Get the inode associated to a path if it exists, following
+    /// symlinks.
     pub(super) fn inode_of(&self, path: &Path) -> Result<InodeResolution> {
+        self.resolve(path, true, 0)
+    }
+
+    /// Get the inode associated to a path if it exists. Symlinks are
+    /// followed except the last component of the path, so the inode
+    /// of a symlink is the inode of the link itself.
+    pub(super) fn inode_of_nofollow(&self, path: &Path) -> Result<InodeResolution> {
+        self.resolve(path, false, 0)
+    }
+
+    /// If the last component of `path` is a symlink, returns the path
+    /// it points to (after following any chain of symlinks), which may
+    /// not exist.
+    pub(super) fn follow_symlinks(&self, path: &Path) -> Result<Option<PathBuf>> {
+        let mut path = path.to_path_buf();
+        let mut followed = false;
+
+        for _ in 0..MAX_SYMLINK_DEPTH {
+            let target = match self.inode_of_nofollow(&path) {
+                Ok(InodeResolution::Found(inode)) => match self.storage.get(inode) {
+                    Some(Node::Symlink(SymlinkNode { target, .. })) => target,
+                    _ => return Ok(followed.then_some(path)),
+                },
+                _ => return Ok(followed.then_some(path)),
+            };
+
+            // The root is a directory, so a symlink always has a parent.
+            let parent_of_path = path.parent().ok_or(FsError::UnknownError)?;
+            path = self.canonicalize_without_inode(&parent_of_path.join(target))?;
+            followed = true;
+        }
+
+        Err(FsError::TooManySymlinks)
+    }
+
+    fn resolve(&self, path: &Path, follow_last: bool, depth: usize) -> Result<InodeResolution> {
         // SAFETY: The root node always exists, so it's safe to unwrap here.
         let mut node = self.storage.get(ROOT_INODE).unwrap();
         let mut components = path.components();
@@ -652,6 +804,9 @@ impl FileSystemInner {
             _ => return Err(FsError::BaseNotDirectory),
         }
 
+        // The path of the directory `node` is in.
+        let mut current_path = PathBuf::from("/");
+
         while let Some(component) = components.next() {
             node = match node {
                 Node::Directory(DirectoryNode { children, .. }) => children
@@ -671,6 +826,26 @@ impl FileSystemInner {
                 }
                 _ => return Err(FsError::BaseNotDirectory),
             };
+
+            if let Node::Symlink(SymlinkNode { target, .. }) = node {
+                let remaining = components.as_path();
+
+                if follow_last || !remaining.as_os_str().is_empty() {
+                    if depth >= MAX_SYMLINK_DEPTH {
+                        return Err(FsError::TooManySymlinks);
+                    }
+
+                    // Restart the resolution from the target of the
+                    // symlink, relative to the directory containing it.
+                    let mut path = current_path.join(target);
+                    path.push(remaining);
+                    let path = self.canonicalize_without_inode(&path)?;
+
+                    return self.resolve(&path, follow_last, depth + 1);
+                }
+            }
+
+            current_path.push(component);
         }
 
         Ok(InodeResolution::Found(node.inode()))
@@ -757,6 +932,7 @@ impl FileSystemInner {
                     | Node::ReadOnlyFile(ReadOnlyFileNode { inode, name, .. })
                     | Node::CustomFile(CustomFileNode { inode, name, .. })
                     | Node::ArcFile(ArcFileNode { inode, name, .. })
+                    | Node::Symlink(SymlinkNode { inode, name, .. })
                         if name.as_os_str() == name_of_file =>
                     {
                         Some(Some((nth, InodeResolution::Found(*inode))))
@@ -797,6 +973,7 @@ impl FileSystemInner {
                     | Node::ReadOnlyFile(ReadOnlyFileNode { inode, name, .. })
                     | Node::CustomFile(CustomFileNode { inode, name, .. })
                     | Node::ArcFile(ArcFileNode { inode, name, .. })
+                    | Node::Symlink(SymlinkNode { inode, name, .. })
                         if name.as_os_str() == name_of =>
                     {
                         Some(Some((nth, InodeResolution::Found(*inode))))
@@ -961,6 +1138,7 @@ impl fmt::Debug for FileSystemInner {
                         Node::CustomFile { .. } => "custom-file",
                         Node::Directory { .. } => "dir",
                         Node::ArcDirectory { .. } => "arc-dir",
+                        Node::Symlink { .. } => "symlink",
                     },
                     name = node.name().to_string_lossy(),
                     indentation_symbol = " ",
@@ -1605,6 +1783,108 @@ mod test_filesystem {
         );
     }
 
+    #[tokio::test]
+    async fn test_symlink() {
+        let fs = FileSystem::default();
+
+        assert_eq!(fs.create_dir(path!("/foo")), Ok(()));
+        assert_eq!(
+            fs.symlink(path!("foo"), path!("/bar")),
+            Ok(()),
+            "creating a symlink to a directory",
+        );
+        assert_eq!(
+            fs.symlink(path!("/foo"), path!("/bar")),
+            Err(FsError::AlreadyExists),
+            "creating a symlink that already exists",
+        );
+        assert_eq!(
+            fs.symlink(path!("/foo"), path!("/qux/bar")),
+            Err(FsError::EntryNotFound),
+            "creating a symlink in a directory that doesn't exist",
+        );
+
+        assert_eq!(fs.readlink(path!("/bar")), Ok(path!(buf "foo")));
+        assert_eq!(
+            fs.readlink(path!("/foo")),
+            Err(FsError::InvalidInput),
+            "reading a link that isn't a symlink",
+        );
+
+        assert!(
+            fs.metadata(path!("/bar")).unwrap().is_dir(),
+            "follows links"
+        );
+        assert!(
+            fs.symlink_metadata(path!("/bar"))
+                .unwrap()
+                .file_type()
+                .is_symlink(),
+            "doesn't follow links",
+        );
+
+        assert!(
+            matches!(
+                fs.new_open_options()
+                    .write(true)
+                    .create_new(true)
+                    .open(path!("/bar/baz.txt")),
+                Ok(_)
+            ),
+            "creating a file through a symlink",
+        );
+        assert!(fs.metadata(path!("/foo/baz.txt")).unwrap().is_file());
+
+        assert_eq!(
+            fs.symlink(path!("../foo/baz.txt"), path!("/foo/link.txt")),
+            Ok(())
+        );
+        assert_eq!(
+            fs.metadata(path!("/bar/link.txt")),
+            fs.metadata(path!("/foo/baz.txt")),
+            "relative links are resolved from the directory containing them",
+        );
+
+        assert_eq!(fs.remove_file(path!("/bar")), Ok(()), "removing a symlink",);
+        assert!(
+            fs.metadata(path!("/foo")).unwrap().is_dir(),
+            "removing a symlink doesn't touch its target",
+        );
+        assert_eq!(
+            fs.metadata(path!("/bar")),
+            Err(FsError::EntryNotFound),
+            "the symlink is gone",
+        );
+    }
+
+    #[tokio::test]
+    async fn test_symlink_loop() {
+        let fs = FileSystem::default();
+
+        assert_eq!(fs.symlink(path!("/b"), path!("/a")), Ok(()));
+        assert_eq!(fs.symlink(path!("/a"), path!("/b")), Ok(()));
+
+        assert_eq!(
+            fs.metadata(path!("/a")),
+            Err(FsError::TooManySymlinks),
+            "resolving a symlink loop",
+        );
+        assert!(
+            matches!(
+                fs.new_open_options().read(true).open(path!("/a")),
+                Err(FsError::TooManySymlinks)
+            ),
+            "opening a symlink loop",
+        );
+        assert!(
+            fs.symlink_metadata(path!("/a"))
+                .unwrap()
+                .file_type()
+                .is_symlink(),
+            "a dangling symlink still exists",
+        );
+    }
+
     #[tokio::test]
     async fn test_readdir() {
         let fs = FileSystem::default();
@@ -17,6 +17,9 @@ use crate::{
     ReadDir, VirtualFile,
 };
 
+/// The maximum number of symlinks followed when opening a file.
+const MAX_SYMLINK_DEPTH: usize = 40;
+
 /// A primary filesystem and chain of secondary filesystems that are overlayed
 /// on top of each other.
 ///
@@ -105,6 +108,41 @@ where
 
         Err(FsError::EntryNotFound)
     }
+
+    /// Look up the metadata of a file, with `get_metadata` being either
+    /// [`FileSystem::metadata()`] or [`FileSystem::symlink_metadata()`].
+    fn layered_metadata(
+        &self,
+        path: &Path,
+        get_metadata: impl Fn(&(dyn FileSystem + Send), &Path) -> Result<Metadata, FsError>,
+    ) -> Result<Metadata, FsError> {
+        // Whiteout files can not be read, they are just markers
+        if ops::is_white_out(path).is_some() {
+            return Err(FsError::EntryNotFound);
+        }
+
+        // Check if the file is in the primary
+        match get_metadata(self.primary.as_ref(), path) {
+            Ok(meta) => return Ok(meta),
+            Err(e) if should_continue(e) => {}
+            Err(e) => return Err(e),
+        }
+
+        // There might be a whiteout, search for this
+        if ops::has_white_out(&self.primary, path) {
+            return Err(FsError::EntryNotFound);
+        }
+
+        // Otherwise scan the secondaries
+        for fs in self.secondaries.filesystems() {
+            match get_metadata(fs, path) {
+                Err(e) if should_continue(e) => continue,
+                other => return other,
+            }
+        }
+
+        Err(FsError::EntryNotFound)
+    }
 }
 
 impl<P, S> FileSystem for OverlayFileSystem<P, S>
@@ -300,16 +338,55 @@ where
     }
 
     fn metadata(&self, path: &Path) -> Result<Metadata, FsError> {
+        self.layered_metadata(path, |fs, path| fs.metadata(path))
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata, FsError> {
+        self.layered_metadata(path, |fs, path| fs.symlink_metadata(path))
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> Result<(), FsError> {
+        // You can not create symlinks that use the whiteout prefix
+        if ops::is_white_out(link).is_some() {
+            return Err(FsError::InvalidInput);
+        }
+
+        // The link can't shadow something that already exists
+        if self.symlink_metadata(link).is_ok() {
+            return Err(FsError::AlreadyExists);
+        }
+
+        // It could be the case that the link was earlier hidden in the secondaries
+        // by a whiteout file, hence we need to make sure those are cleared out.
+        ops::remove_white_out(self.primary.as_ref(), link);
+
+        // Make sure the parent tree is in place on the primary
+        if let Some(parent) = link.parent() {
+            if self.read_dir(parent).is_ok() {
+                ops::create_dir_all(&self.primary, parent).ok();
+            }
+        }
+
+        // Create the symlink in the primary
+        match self.primary.symlink(target, link) {
+            Err(e) if should_continue(e) => {}
+            other => return other,
+        }
+
+        self.permission_error_or_not_found(link)
+    }
+
+    fn readlink(&self, path: &Path) -> Result<PathBuf, FsError> {
         // Whiteout files can not be read, they are just markers
         if ops::is_white_out(path).is_some() {
             return Err(FsError::EntryNotFound);
         }
 
-        // Check if the file is in the primary
-        match self.primary.metadata(path) {
-            Ok(meta) => return Ok(meta),
-            Err(e) if should_continue(e) => {}
-            Err(e) => return Err(e),
+        // The link is read from the file system it is found in, as an
+        // entry in the primary shadows the secondaries even when it's
+        // not a symlink
+        if self.primary.symlink_metadata(path).is_ok() {
+            return self.primary.readlink(path);
         }
 
         // There might be a whiteout, search for this
@@ -319,9 +396,8 @@ where
 
         // Otherwise scan the secondaries
         for fs in self.secondaries.filesystems() {
-            match fs.metadata(path) {
-                Err(e) if should_continue(e) => continue,
-                other => return other,
+            if fs.symlink_metadata(path).is_ok() {
+                return fs.readlink(path);
             }
         }
 
@@ -338,7 +414,7 @@ where
         // If the file is contained in a secondary then then we need to create a
         // whiteout file so that it is suppressed.
         let had_at_least_one_success = self.secondaries.filesystems().into_iter().any(|fs| {
-            fs.metadata(path).is_ok() && ops::create_white_out(&self.primary, path).is_ok()
+            fs.symlink_metadata(path).is_ok() && ops::create_white_out(&self.primary, path).is_ok()
         });
 
         // Attempt to remove it from the primary
@@ -379,6 +455,12 @@ where
             return Err(FsError::InvalidInput);
         }
 
+        // A symlink may point to a file living in another file system of the
+        // overlay, so the link is resolved here rather than by each layer
+        if let Some(target) = follow_symlinks(self, path)? {
+            return self.open(&target, conf);
+        }
+
         // Check if the file is in the primary (without actually creating it) as
         // when the file is in the primary it takes preference over any of file
         {
@@ -1035,6 +1117,29 @@ where
     }
 }
 
+/// If `path` is a symlink, returns the path it points to (after following
+/// any chain of symlinks), which may not exist.
+fn follow_symlinks(fs: &impl FileSystem, path: &Path) -> Result<Option<PathBuf>, FsError> {
+    let mut path = path.to_path_buf();
+    let mut followed = false;
+
+    for _ in 0..MAX_SYMLINK_DEPTH {
+        match fs.symlink_metadata(&path) {
+            Ok(meta) if meta.file_type().is_symlink() => {}
+            _ => return Ok(followed.then_some(path)),
+        }
+
+        let target = fs.readlink(&path)?;
+        path = match path.parent() {
+            Some(parent) => parent.join(target),
+            None => target,
+        };
+        followed = true;
+    }
+
+    Err(FsError::TooManySymlinks)
+}
+
 fn should_continue(e: FsError) -> bool {
     // HACK: We shouldn't really be ignoring FsError::BaseNotDirectory, but
     // it's needed because the mem_fs::FileSystem doesn't return
@@ -1559,6 +1664,65 @@ mod tests {
         assert!(ops::is_dir(&fs.secondaries[0], "/secondary"));
     }
 
+    #[tokio::test]
+    async fn symlink_to_secondary_fs() {
+        let primary = MemFS::default();
+        let secondary = MemFS::default();
+        ops::create_dir_all(&secondary, "/usr/bin").unwrap();
+        ops::write(&secondary, "/usr/bin/python", b"Hello, World!")
+            .await
+            .unwrap();
+
+        let fs = OverlayFileSystem::new(primary, [secondary]);
+
+        ops::create_dir_all(&fs, "/venv/bin").unwrap();
+        fs.symlink(Path::new("/usr/bin/python"), Path::new("/venv/bin/python"))
+            .unwrap();
+
+        assert_eq!(
+            fs.readlink(Path::new("/venv/bin/python")).unwrap(),
+            Path::new("/usr/bin/python"),
+        );
+        assert!(fs
+            .symlink_metadata(Path::new("/venv/bin/python"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert!(fs
+            .primary
+            .symlink_metadata(Path::new("/venv/bin/python"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+
+        // The link lives in the primary but its target in the secondary
+        let contents = ops::read_to_string(&fs, "/venv/bin/python").await.unwrap();
+        assert_eq!(contents, "Hello, World!");
+    }
+
+    #[tokio::test]
+    async fn symlink_shadowing_secondary_fs_file() {
+        let primary = MemFS::default();
+        let secondary = MemFS::default();
+        ops::touch(&secondary, "/file.txt").unwrap();
+
+        let fs = OverlayFileSystem::new(primary, [secondary]);
+
+        assert_eq!(
+            fs.symlink(Path::new("/other.txt"), Path::new("/file.txt")),
+            Err(FsError::AlreadyExists),
+        );
+
+        fs.remove_file(Path::new("/file.txt")).unwrap();
+        fs.symlink(Path::new("/other.txt"), Path::new("/file.txt"))
+            .unwrap();
+        assert_eq!(
+            fs.readlink(Path::new("/file.txt")).unwrap(),
+            Path::new("/other.txt"),
+        );
+        assert!(!ops::is_file(&fs.primary, "/.wh.file.txt"));
+    }
+
     #[tokio::test]
     async fn rmdir_sub_from_secondary_fs() {
         let primary = MemFS::default();
@@ -2,7 +2,7 @@
 //! needed so that a `Box<dyn VirtualFileSystem>` can be wrapped in an Arc and
 //! shared - some of the interfaces pass around a `Box<dyn VirtualFileSystem>`
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::*;
 
@@ -42,6 +42,14 @@ impl FileSystem for PassthruFileSystem {
         self.fs.symlink_metadata(path)
     }
 
+    fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        self.fs.symlink(target, link)
+    }
+
+    fn readlink(&self, path: &Path) -> Result<PathBuf> {
+        self.fs.readlink(path)
+    }
+
     fn remove_file(&self, path: &Path) -> Result<()> {
         self.fs.remove_file(path)
     }
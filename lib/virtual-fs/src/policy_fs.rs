@@ -0,0 +1,728 @@
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    fmt,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    FileOpener, FileSystem, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir, Result,
+    VirtualFile,
+};
+
+/// What a guest can do with the files below a directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsAccess {
+    /// Reading files and listing directories.
+    pub read: bool,
+    /// Creating, modifying, renaming and removing files and directories.
+    pub write: bool,
+    /// Running files as the binaries of child processes.
+    pub exec: bool,
+}
+
+impl FsAccess {
+    pub const ALL: FsAccess = FsAccess {
+        read: true,
+        write: true,
+        exec: true,
+    };
+}
+
+impl Default for FsAccess {
+    fn default() -> Self {
+        FsAccess::ALL
+    }
+}
+
+impl FromStr for FsAccess {
+    type Err = String;
+
+    /// Parses a comma-separated list of modes: `rw`, `ro`, `wo` or `none`,
+    /// optionally followed by `noexec`, e.g. `ro,noexec`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut access = FsAccess::ALL;
+        for mode in s.split(',').map(str::trim) {
+            let (read, write) = match mode {
+                "rw" => (true, true),
+                "ro" => (true, false),
+                "wo" => (false, true),
+                "none" => (false, false),
+                "noexec" => {
+                    access.exec = false;
+                    continue;
+                }
+                _ => {
+                    return Err(format!(
+                        "unknown access mode `{}`, expected `rw`, `ro`, `wo`, `none` or `noexec`",
+                        mode
+                    ))
+                }
+            };
+            access.read = read;
+            access.write = write;
+        }
+        Ok(access)
+    }
+}
+
+impl fmt::Display for FsAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = match (self.read, self.write) {
+            (true, true) => "rw",
+            (true, false) => "ro",
+            (false, true) => "wo",
+            (false, false) => "none",
+        };
+        write!(f, "{}", mode)?;
+        if !self.exec {
+            write!(f, ",noexec")?;
+        }
+        Ok(())
+    }
+}
+
+/// The access to a directory of the guest, and to everything below it.
+///
+/// The syntax is `PATH:MODES`, where the modes are those of [`FsAccess`],
+/// e.g. `/data:ro` or `/out:wo,noexec`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsRule {
+    pub path: PathBuf,
+    pub access: FsAccess,
+}
+
+impl FsRule {
+    pub fn new(path: impl Into<PathBuf>, access: FsAccess) -> Self {
+        FsRule {
+            path: path.into(),
+            access,
+        }
+    }
+}
+
+impl FromStr for FsRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (path, access) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("expected `PATH:MODES`, found `{}`", s))?;
+        if !path.starts_with('/') {
+            return Err(format!("the path of `{}` must be absolute", s));
+        }
+        Ok(FsRule::new(path, access.parse()?))
+    }
+}
+
+impl fmt::Display for FsRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.access)
+    }
+}
+
+/// A glob pattern of the guest paths that can't be accessed at all.
+///
+/// `?` matches any character and `*` any sequence of characters, except
+/// `/`, while `**` also matches `/`. A pattern without `/`, such as
+/// `*.key`, matches file names in any directory. Otherwise the pattern
+/// must be absolute, such as `/data/**/secrets`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsPattern {
+    pattern: String,
+}
+
+impl FsPattern {
+    /// Whether the pattern matches a normalized guest path.
+    pub fn matches(&self, path: &Path) -> bool {
+        let pattern: Vec<char> = self.pattern.chars().collect();
+        if self.pattern.contains('/') {
+            let path: Vec<char> = path.to_string_lossy().chars().collect();
+            glob_match(&pattern, &path)
+        } else {
+            match path.file_name() {
+                Some(name) => {
+                    let name: Vec<char> = name.to_string_lossy().chars().collect();
+                    glob_match(&pattern, &name)
+                }
+                None => false,
+            }
+        }
+    }
+}
+
+impl FromStr for FsPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("the pattern is empty".to_string());
+        }
+        if s.contains('/') && !s.starts_with('/') {
+            return Err(format!(
+                "the pattern `{}` must be absolute or a file name",
+                s
+            ));
+        }
+        Ok(FsPattern {
+            pattern: s.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for FsPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `**/` also matches no directory at all.
+            (rest.first() == Some(&'/') && glob_match(&rest[1..], text))
+                || (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        ['*', rest @ ..] => {
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        ['?', rest @ ..] => {
+            matches!(text.first(), Some(c) if *c != '/') && glob_match(rest, &text[1..])
+        }
+        [c, rest @ ..] => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Decides what a guest can do with each of its paths.
+///
+/// The access to a path is that of the rule of its closest directory, or
+/// [`FsAccess::ALL`] if there is none. A path is denied entirely if it, or
+/// one of its directories, matches any of the deny patterns.
+#[derive(Debug, Clone, Default)]
+pub struct FsPolicy {
+    rules: Vec<FsRule>,
+    deny: Vec<FsPattern>,
+}
+
+impl FsPolicy {
+    pub fn new(rules: Vec<FsRule>, deny: Vec<FsPattern>) -> Self {
+        let rules = rules
+            .into_iter()
+            .map(|rule| FsRule::new(normalize_path(&rule.path), rule.access))
+            .collect();
+        FsPolicy { rules, deny }
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        self.deny.is_empty() && self.rules.iter().all(|rule| rule.access == FsAccess::ALL)
+    }
+
+    /// The access to a guest path, ignoring the deny patterns.
+    pub fn access(&self, path: &Path) -> FsAccess {
+        let path = normalize_path(path);
+        self.rules
+            .iter()
+            .filter(|rule| path.starts_with(&rule.path))
+            .max_by_key(|rule| rule.path.components().count())
+            .map_or(FsAccess::ALL, |rule| rule.access)
+    }
+
+    /// Whether a guest path matches one of the deny patterns.
+    pub fn is_denied(&self, path: &Path) -> bool {
+        let path = normalize_path(path);
+        path.ancestors()
+            .any(|path| self.deny.iter().any(|pattern| pattern.matches(path)))
+    }
+
+    /// Whether a guest path can be run as the binary of a child process.
+    pub fn can_execute(&self, path: &Path) -> bool {
+        !self.is_denied(path) && self.access(path).exec
+    }
+
+    /// Whether a guest path can be run as the binary of a child process,
+    /// once the symlinks leading to it are resolved in the filesystem of
+    /// the guest.
+    pub fn can_execute_in<F>(&self, fs: &F, path: &Path) -> bool
+    where
+        F: FileSystem + ?Sized,
+    {
+        self.can_execute(path)
+            && resolve_symlinks(fs, path, true).map_or(false, |path| self.can_execute(&path))
+    }
+
+    fn check(&self, path: &Path, read: bool, write: bool) -> Result<()> {
+        if self.is_denied(path) {
+            return Err(FsError::PermissionDenied);
+        }
+        let access = self.access(path);
+        if (read && !access.read) || (write && !access.write) {
+            return Err(FsError::PermissionDenied);
+        }
+        Ok(())
+    }
+}
+
+/// Resolves the `.` and `..` components of a path, relative to the root.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+        }
+    }
+    normalized
+}
+
+/// A [`FileSystem`] wrapper that enforces a [`FsPolicy`] on a directory
+/// mounted in the guest.
+///
+/// The paths given to the wrapped filesystem are relative to the mount
+/// point, which is where the policy applies. Denied operations fail with
+/// [`FsError::PermissionDenied`], and the denied entries are left out of
+/// the listed directories.
+#[derive(Debug, Clone)]
+pub struct PolicyFileSystem<F> {
+    inner: F,
+    policy: Arc<FsPolicy>,
+    mount_point: PathBuf,
+}
+
+impl<F> PolicyFileSystem<F> {
+    pub fn new(inner: F, policy: Arc<FsPolicy>, mount_point: impl Into<PathBuf>) -> Self {
+        PolicyFileSystem {
+            inner,
+            policy,
+            mount_point: mount_point.into(),
+        }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn policy(&self) -> &FsPolicy {
+        &self.policy
+    }
+
+    fn guest_path(&self, path: &Path) -> PathBuf {
+        let path = path.strip_prefix("/").unwrap_or(path);
+        normalize_path(&self.mount_point.join(path))
+    }
+
+    /// Checks a path, and the entry it really refers to once the symlinks
+    /// leading to it are resolved. The last component is only resolved when
+    /// `follow_symlinks` is set.
+    fn check(&self, path: &Path, follow_symlinks: bool, read: bool, write: bool) -> Result<()>
+    where
+        F: FileSystem,
+    {
+        self.policy.check(&self.guest_path(path), read, write)?;
+        let resolved = resolve_symlinks(&self.inner, path, follow_symlinks)?;
+        self.policy.check(&self.guest_path(&resolved), read, write)
+    }
+}
+
+/// Resolves the symlinks of a path of a filesystem. The last component is
+/// only resolved when `follow_symlinks` is set.
+fn resolve_symlinks<F>(fs: &F, path: &Path, follow_symlinks: bool) -> Result<PathBuf>
+where
+    F: FileSystem + ?Sized,
+{
+    let mut remaining: VecDeque<OsString> = path_components(path).collect();
+    let mut resolved = PathBuf::from("/");
+    let mut links = 0;
+
+    while let Some(name) = remaining.pop_front() {
+        if name == ".." {
+            resolved.pop();
+            continue;
+        }
+        resolved.push(name);
+        if remaining.is_empty() && !follow_symlinks {
+            break;
+        }
+
+        let is_symlink = fs
+            .symlink_metadata(&resolved)
+            .map_or(false, |metadata| metadata.file_type().is_symlink());
+        if !is_symlink {
+            continue;
+        }
+        links += 1;
+        if links > MAX_SYMLINKS {
+            return Err(FsError::InvalidInput);
+        }
+
+        let target = fs.readlink(&resolved)?;
+        resolved.pop();
+        if target.has_root() {
+            resolved = PathBuf::from("/");
+        }
+        for name in path_components(&target).rev() {
+            remaining.push_front(name);
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// The names and `..` components of a path.
+fn path_components(path: &Path) -> impl DoubleEndedIterator<Item = OsString> + '_ {
+    path.components().filter_map(|component| match component {
+        Component::Normal(_) | Component::ParentDir => Some(component.as_os_str().to_owned()),
+        Component::Prefix(_) | Component::RootDir | Component::CurDir => None,
+    })
+}
+
+/// The number of symlinks followed while resolving a path before giving up.
+const MAX_SYMLINKS: usize = 40;
+
+impl<F> FileSystem for PolicyFileSystem<F>
+where
+    F: FileSystem,
+{
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        self.check(path, true, true, false)?;
+        let dir = self.guest_path(path);
+        let entries = self
+            .inner
+            .read_dir(path)?
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .filter(|entry| match entry.path.file_name() {
+                Some(name) => !self.policy.is_denied(&dir.join(name)),
+                None => true,
+            })
+            .collect();
+        Ok(ReadDir::new(entries))
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        self.check(path, false, false, true)?;
+        self.inner.create_dir(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        self.check(path, false, false, true)?;
+        self.inner.remove_dir(path)
+    }
+
+    fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            self.check(from, false, false, true)?;
+            self.check(to, false, false, true)?;
+            self.inner.rename(from, to).await
+        })
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        self.check(path, true, false, false)?;
+        self.inner.metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        self.check(path, false, false, false)?;
+        self.inner.symlink_metadata(path)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        self.check(link, false, false, true)?;
+
+        // Absolute targets are guest paths, which the wrapped filesystem
+        // sees relative to the mount point.
+        let target = if target.has_root() {
+            let target = normalize_path(target);
+            let target = target
+                .strip_prefix(&self.mount_point)
+                .map_err(|_| FsError::PermissionDenied)?;
+            Path::new("/").join(target)
+        } else {
+            target.to_owned()
+        };
+
+        // A link must not give access to the denied paths. The access modes
+        // are checked when the link is followed, as they depend on how the
+        // target is opened.
+        let parent = link.parent().unwrap_or_else(|| Path::new("/"));
+        self.check(&parent.join(&target), true, false, false)?;
+        self.inner.symlink(&target, link)
+    }
+
+    fn readlink(&self, path: &Path) -> Result<PathBuf> {
+        self.check(path, false, false, false)?;
+        let target = self.inner.readlink(path)?;
+        match target.strip_prefix("/") {
+            Ok(target) => Ok(self.mount_point.join(target)),
+            Err(_) => Ok(target),
+        }
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        self.check(path, false, false, true)?;
+        self.inner.remove_file(path)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(self)
+    }
+}
+
+impl<F> FileOpener for PolicyFileSystem<F>
+where
+    F: FileSystem,
+{
+    fn open(
+        &self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        let write = conf.write || conf.append || conf.truncate || conf.create || conf.create_new;
+        self.check(path, true, conf.read, write)?;
+        self.inner
+            .new_open_options()
+            .options(conf.clone())
+            .open(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem_fs;
+
+    fn policy(rules: &[&str], deny: &[&str]) -> Arc<FsPolicy> {
+        Arc::new(FsPolicy::new(
+            rules.iter().map(|rule| rule.parse().unwrap()).collect(),
+            deny.iter()
+                .map(|pattern| pattern.parse().unwrap())
+                .collect(),
+        ))
+    }
+
+    fn mounted_fs(policy: Arc<FsPolicy>) -> PolicyFileSystem<mem_fs::FileSystem> {
+        let fs = mem_fs::FileSystem::default();
+        fs.create_dir(Path::new("/out")).unwrap();
+        fs.new_open_options()
+            .write(true)
+            .create(true)
+            .open("/input.txt")
+            .unwrap();
+        fs.new_open_options()
+            .write(true)
+            .create(true)
+            .open("/server.key")
+            .unwrap();
+        PolicyFileSystem::new(fs, policy, "/data")
+    }
+
+    #[test]
+    fn rules_and_patterns_are_parsed() {
+        let rule: FsRule = "/data:ro,noexec".parse().unwrap();
+        assert_eq!(
+            rule,
+            FsRule::new(
+                "/data",
+                FsAccess {
+                    read: true,
+                    write: false,
+                    exec: false,
+                }
+            )
+        );
+        assert_eq!(rule.to_string(), "/data:ro,noexec");
+        assert_eq!("/out:wo".parse::<FsRule>().unwrap().to_string(), "/out:wo");
+        assert!("data:ro".parse::<FsRule>().is_err());
+        assert!("/data:rx".parse::<FsRule>().is_err());
+        assert!("/data".parse::<FsRule>().is_err());
+
+        assert!("data/*.txt".parse::<FsPattern>().is_err());
+        assert!("".parse::<FsPattern>().is_err());
+    }
+
+    #[test]
+    fn patterns_match_guest_paths() {
+        let pattern = |s: &str| s.parse::<FsPattern>().unwrap();
+        assert!(pattern("*.key").matches(Path::new("/data/server.key")));
+        assert!(!pattern("*.key").matches(Path::new("/data/server.key.pub")));
+        assert!(pattern("/data/*.txt").matches(Path::new("/data/a.txt")));
+        assert!(!pattern("/data/*.txt").matches(Path::new("/data/dir/a.txt")));
+        assert!(pattern("/data/**/a.txt").matches(Path::new("/data/a.txt")));
+        assert!(pattern("/data/**/a.txt").matches(Path::new("/data/x/y/a.txt")));
+        assert!(pattern("/data/?.txt").matches(Path::new("/data/a.txt")));
+        assert!(!pattern("/data/?.txt").matches(Path::new("/data/ab.txt")));
+    }
+
+    #[test]
+    fn read_only_mounts_deny_writes() {
+        let fs = mounted_fs(policy(&["/data:ro"], &[]));
+
+        assert!(fs.new_open_options().read(true).open("/input.txt").is_ok());
+        assert!(fs.read_dir(Path::new("/")).is_ok());
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .open("/input.txt")
+                .unwrap_err(),
+            FsError::PermissionDenied
+        );
+        assert_eq!(
+            fs.new_open_options()
+                .read(true)
+                .create(true)
+                .open("/new.txt")
+                .unwrap_err(),
+            FsError::PermissionDenied
+        );
+        assert_eq!(
+            fs.remove_file(Path::new("/input.txt")),
+            Err(FsError::PermissionDenied)
+        );
+        assert_eq!(
+            fs.create_dir(Path::new("/dir")),
+            Err(FsError::PermissionDenied)
+        );
+        assert!(fs.metadata(Path::new("/input.txt")).is_ok());
+    }
+
+    #[test]
+    fn nested_rules_take_precedence() {
+        let fs = mounted_fs(policy(&["/data:ro", "/data/out:wo,noexec"], &[]));
+
+        assert!(fs
+            .new_open_options()
+            .write(true)
+            .create(true)
+            .open("/out/result.txt")
+            .is_ok());
+        assert_eq!(
+            fs.new_open_options()
+                .read(true)
+                .open("/out/result.txt")
+                .unwrap_err(),
+            FsError::PermissionDenied
+        );
+        assert_eq!(
+            fs.read_dir(Path::new("/out")).unwrap_err(),
+            FsError::PermissionDenied
+        );
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .open("/out/../input.txt")
+                .unwrap_err(),
+            FsError::PermissionDenied
+        );
+
+        assert!(fs.policy().can_execute(Path::new("/data/input.txt")));
+        assert!(!fs.policy().can_execute(Path::new("/data/out/result.txt")));
+    }
+
+    #[test]
+    fn symlinks_cant_bypass_the_access_modes() {
+        let fs = mounted_fs(policy(&["/data:ro", "/data/out:rw"], &[]));
+
+        fs.symlink(Path::new("../input.txt"), Path::new("/out/relative"))
+            .unwrap();
+        fs.symlink(Path::new("/data/input.txt"), Path::new("/out/absolute"))
+            .unwrap();
+        assert_eq!(
+            fs.readlink(Path::new("/out/absolute")).unwrap(),
+            PathBuf::from("/data/input.txt")
+        );
+        assert_eq!(
+            fs.inner().readlink(Path::new("/out/absolute")).unwrap(),
+            PathBuf::from("/input.txt")
+        );
+
+        for link in ["/out/relative", "/out/absolute"] {
+            assert!(fs.new_open_options().read(true).open(link).is_ok());
+            assert_eq!(
+                fs.new_open_options().write(true).open(link).unwrap_err(),
+                FsError::PermissionDenied
+            );
+        }
+        assert_eq!(
+            fs.symlink(Path::new("/etc/passwd"), Path::new("/out/outside")),
+            Err(FsError::PermissionDenied)
+        );
+    }
+
+    #[test]
+    fn denied_paths_are_hidden() {
+        let fs = mounted_fs(policy(&[], &["*.key"]));
+
+        let entries: Vec<_> = fs
+            .read_dir(Path::new("/"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path.file_name().unwrap().to_owned())
+            .collect();
+        assert!(entries.contains(&"input.txt".into()));
+        assert!(!entries.contains(&"server.key".into()));
+
+        assert_eq!(
+            fs.new_open_options()
+                .read(true)
+                .open("/server.key")
+                .unwrap_err(),
+            FsError::PermissionDenied
+        );
+        assert_eq!(
+            fs.metadata(Path::new("/server.key")).unwrap_err(),
+            FsError::PermissionDenied
+        );
+        assert_eq!(
+            fs.symlink(Path::new("server.key"), Path::new("/link")),
+            Err(FsError::PermissionDenied)
+        );
+        assert!(!fs.policy().can_execute(Path::new("/data/server.key")));
+    }
+
+    #[test]
+    fn symlinks_cant_bypass_noexec() {
+        let policy = policy(&["/data/out:rw,noexec"], &[]);
+        let fs = mem_fs::FileSystem::default();
+        fs.create_dir(Path::new("/data")).unwrap();
+        fs.create_dir(Path::new("/data/out")).unwrap();
+        fs.new_open_options()
+            .write(true)
+            .create(true)
+            .open("/data/out/tool")
+            .unwrap();
+        fs.symlink(Path::new("out/tool"), Path::new("/data/relative"))
+            .unwrap();
+        fs.symlink(Path::new("/data/out"), Path::new("/data/dir"))
+            .unwrap();
+
+        assert!(policy.can_execute(Path::new("/data/relative")));
+        assert!(!policy.can_execute_in(&fs, Path::new("/data/relative")));
+        assert!(!policy.can_execute_in(&fs, Path::new("/data/dir/tool")));
+        assert!(!policy.can_execute_in(&fs, Path::new("/data/out/tool")));
+
+        fs.new_open_options()
+            .write(true)
+            .create(true)
+            .open("/data/allowed")
+            .unwrap();
+        fs.symlink(Path::new("allowed"), Path::new("/data/out/link"))
+            .unwrap();
+        assert!(policy.can_execute_in(&fs, Path::new("/data/allowed")));
+        assert!(!policy.can_execute_in(&fs, Path::new("/data/out/link")));
+    }
+}
@@ -48,22 +48,57 @@ impl ScopedDirectoryFileSystem {
         debug_assert!(path.starts_with(&self.root));
         path
     }
+
+    /// Resolve a path to its location on the host, failing if any symlink
+    /// along the way leads outside of the scoped directory.
+    ///
+    /// The last component is only resolved when `follow_symlinks` is set,
+    /// so operations on the symlink itself can still reach it.
+    fn resolve(&self, path: &Path, follow_symlinks: bool) -> Result<PathBuf, FsError> {
+        let root = self.root.canonicalize()?;
+        let mut path = self.prepare_path(path);
+
+        for _ in 0..MAX_SYMLINKS {
+            let resolved = match (path.parent(), path.file_name()) {
+                (Some(parent), Some(name)) => parent.canonicalize()?.join(name),
+                _ => path.canonicalize()?,
+            };
+            if !resolved.starts_with(&root) {
+                tracing::debug!(
+                    path = %path.display(),
+                    "path resolves outside of the scoped directory",
+                );
+                return Err(FsError::PermissionDenied);
+            }
+
+            match std::fs::read_link(&resolved) {
+                Ok(target) if follow_symlinks => {
+                    let parent = resolved.parent().unwrap_or(&root);
+                    path = parent.join(target);
+                }
+                _ => return Ok(resolved),
+            }
+        }
+
+        Err(FsError::InvalidInput)
+    }
 }
 
+/// The number of symlinks followed while resolving a path before giving up.
+const MAX_SYMLINKS: usize = 40;
+
 impl FileSystem for ScopedDirectoryFileSystem {
     fn read_dir(&self, path: &Path) -> Result<ReadDir, FsError> {
-        let path = self.prepare_path(path);
+        let dir = Path::new("/").join(normalize_path(path));
+        let path = self.resolve(path, true)?;
 
         let mut entries = Vec::new();
 
         for entry in self.inner.read_dir(&path)? {
             let entry = entry?;
-            let path = entry
-                .path
-                .strip_prefix(&self.root)
-                .map_err(|_| FsError::InvalidData)?;
+            let name = entry.path.file_name().ok_or(FsError::InvalidData)?;
             entries.push(DirEntry {
-                path: Path::new("/").join(path),
+                path: dir.join(name),
                 ..entry
             });
         }
@@ -72,30 +107,74 @@ impl FileSystem for ScopedDirectoryFileSystem {
     }
 
     fn create_dir(&self, path: &Path) -> Result<(), FsError> {
-        let path = self.prepare_path(path);
+        let path = self.resolve(path, false)?;
         self.inner.create_dir(&path)
     }
 
     fn remove_dir(&self, path: &Path) -> Result<(), FsError> {
-        let path = self.prepare_path(path);
+        let path = self.resolve(path, false)?;
         self.inner.remove_dir(&path)
     }
 
     fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<(), FsError>> {
         Box::pin(async move {
-            let from = self.prepare_path(from);
-            let to = self.prepare_path(to);
+            let from = self.resolve(from, false)?;
+            let to = self.resolve(to, false)?;
             self.inner.rename(&from, &to).await
         })
     }
 
     fn metadata(&self, path: &Path) -> Result<Metadata, FsError> {
-        let path = self.prepare_path(path);
+        let path = self.resolve(path, true)?;
         self.inner.metadata(&path)
     }
 
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata, FsError> {
+        let path = self.resolve(path, false)?;
+        self.inner.symlink_metadata(&path)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> Result<(), FsError> {
+        let root = self.root.canonicalize()?;
+        let link = self.resolve(link, false)?;
+
+        // The host resolves the link, so absolute targets are moved into the
+        // scoped directory and relative targets can't use ".." to escape it.
+        // The link's directory is already resolved, so symlinks leading to
+        // it can't be used to make ".." point somewhere else.
+        let target = if target.has_root() {
+            root.join(normalize_path(target))
+        } else {
+            let link_dir = link
+                .parent()
+                .and_then(|parent| parent.strip_prefix(&root).ok())
+                .unwrap_or_else(|| Path::new(""));
+            if escapes_root(&link_dir.join(target)) {
+                return Err(FsError::PermissionDenied);
+            }
+            target.to_owned()
+        };
+
+        self.inner.symlink(&target, &link)
+    }
+
+    fn readlink(&self, path: &Path) -> Result<PathBuf, FsError> {
+        let path = self.resolve(path, false)?;
+        let target = self.inner.readlink(&path)?;
+
+        // Map absolute targets back into the scoped directory
+        if target.has_root() {
+            let root = std::fs::canonicalize(&self.root)?;
+            if let Ok(target) = target.strip_prefix(&root) {
+                return Ok(Path::new("/").join(target));
+            }
+        }
+
+        Ok(target)
+    }
+
     fn remove_file(&self, path: &Path) -> Result<(), FsError> {
-        let path = self.prepare_path(path);
+        let path = self.resolve(path, false)?;
         self.inner.remove_file(&path)
     }
 
@@ -110,7 +189,7 @@ impl FileOpener for ScopedDirectoryFileSystem {
         path: &Path,
         conf: &OpenOptionsConfig,
     ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>, FsError> {
-        let path = self.prepare_path(path);
+        let path = self.resolve(path, true)?;
         self.inner
             .new_open_options()
             .options(conf.clone())
@@ -149,6 +228,25 @@ fn normalize_path(path: &Path) -> PathBuf {
     ret
 }
 
+/// Check whether a relative path uses ".." to go above its starting point.
+fn escapes_root(path: &Path) -> bool {
+    let mut depth = 0usize;
+
+    for component in path.components() {
+        match component {
+            Component::Prefix(..) | Component::RootDir => return true,
+            Component::CurDir => {}
+            Component::ParentDir => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => return true,
+            },
+            Component::Normal(_) => depth += 1,
+        }
+    }
+
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use tempfile::TempDir;
@@ -195,4 +293,102 @@ mod tests {
             FsError::EntryNotFound
         );
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlinks_stay_inside_the_scoped_directory() {
+        let scoped_directory = TempDir::new().unwrap();
+        std::fs::write(scoped_directory.path().join("file.txt"), "Hello, World!").unwrap();
+        std::fs::create_dir(scoped_directory.path().join("nested")).unwrap();
+        let fs = ScopedDirectoryFileSystem::new_with_default_runtime(scoped_directory.path());
+
+        // Absolute targets are relative to the scoped directory
+        fs.symlink("/file.txt".as_ref(), "/nested/absolute".as_ref())
+            .unwrap();
+        assert_eq!(
+            fs.readlink("/nested/absolute".as_ref()).unwrap(),
+            PathBuf::from("/file.txt"),
+        );
+        let mut f = fs
+            .new_open_options()
+            .read(true)
+            .open("/nested/absolute")
+            .unwrap();
+        let mut contents = String::new();
+        f.read_to_string(&mut contents).await.unwrap();
+        assert_eq!(contents, "Hello, World!");
+
+        // Relative targets are kept as-is
+        fs.symlink("../file.txt".as_ref(), "/nested/relative".as_ref())
+            .unwrap();
+        assert_eq!(
+            fs.readlink("/nested/relative".as_ref()).unwrap(),
+            PathBuf::from("../file.txt"),
+        );
+        assert!(fs
+            .symlink_metadata("/nested/relative".as_ref())
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert!(fs.metadata("/nested/relative".as_ref()).unwrap().is_file());
+
+        // But they can't escape the scoped directory
+        assert_eq!(
+            fs.symlink("../../file.txt".as_ref(), "/nested/escape".as_ref())
+                .unwrap_err(),
+            FsError::PermissionDenied,
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlinks_in_the_parent_path_cant_escape() {
+        let outside = TempDir::new().unwrap();
+        let scoped_directory = outside.path().join("scoped");
+        std::fs::create_dir(&scoped_directory).unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+        let fs = ScopedDirectoryFileSystem::new_with_default_runtime(&scoped_directory);
+
+        // "/d1/d2" is really "/d2", so "../.." from there leaves the root
+        fs.symlink("/".as_ref(), "/d1".as_ref()).unwrap();
+        fs.create_dir("/d2".as_ref()).unwrap();
+        assert_eq!(
+            fs.symlink("../..".as_ref(), "/d1/d2/l".as_ref())
+                .unwrap_err(),
+            FsError::PermissionDenied,
+        );
+        assert!(fs.metadata("/d2/l/secret.txt".as_ref()).is_err());
+
+        // Links escaping the root that were created on the host can't be
+        // followed either
+        std::os::unix::fs::symlink("../..", scoped_directory.join("d2").join("l")).unwrap();
+        assert_eq!(
+            fs.metadata("/d2/l/secret.txt".as_ref()).unwrap_err(),
+            FsError::PermissionDenied,
+        );
+        assert_eq!(
+            fs.new_open_options()
+                .read(true)
+                .open("/d1/d2/l/secret.txt")
+                .unwrap_err(),
+            FsError::PermissionDenied,
+        );
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .create(true)
+                .open("/d2/l/new.txt")
+                .unwrap_err(),
+            FsError::PermissionDenied,
+        );
+        assert!(!outside.path().join("new.txt").exists());
+
+        // While the link itself can still be inspected and removed
+        assert!(fs
+            .symlink_metadata("/d2/l".as_ref())
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        fs.remove_file("/d2/l".as_ref()).unwrap();
+    }
 }
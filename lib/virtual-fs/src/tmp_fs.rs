@@ -85,6 +85,14 @@ impl FileSystem for TmpFileSystem {
         self.fs.symlink_metadata(path)
     }
 
+    fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        self.fs.symlink(target, link)
+    }
+
+    fn readlink(&self, path: &Path) -> Result<PathBuf> {
+        self.fs.readlink(path)
+    }
+
     fn remove_file(&self, path: &Path) -> Result<()> {
         self.fs.remove_file(path)
     }
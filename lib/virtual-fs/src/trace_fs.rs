@@ -68,6 +68,21 @@ where
         self.0.metadata(path)
     }
 
+    #[tracing::instrument(level = "trace", skip(self), err)]
+    fn symlink_metadata(&self, path: &std::path::Path) -> crate::Result<crate::Metadata> {
+        self.0.symlink_metadata(path)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self), err)]
+    fn symlink(&self, target: &std::path::Path, link: &std::path::Path) -> crate::Result<()> {
+        self.0.symlink(target, link)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self), err)]
+    fn readlink(&self, path: &std::path::Path) -> crate::Result<PathBuf> {
+        self.0.readlink(path)
+    }
+
     #[tracing::instrument(level = "trace", skip(self), err)]
     fn remove_file(&self, path: &std::path::Path) -> crate::Result<()> {
         self.0.remove_file(path)
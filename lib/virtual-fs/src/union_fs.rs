@@ -350,6 +350,46 @@ impl FileSystem for UnionFileSystem {
         debug!("symlink_metadata: failed={}", ret_error);
         Err(ret_error)
     }
+    fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        debug!(
+            "symlink: target={} link={}",
+            target.display(),
+            link.display()
+        );
+        if link.parent().is_none() {
+            return Err(FsError::BaseNotDirectory);
+        }
+        let mut ret_error = FsError::EntryNotFound;
+        let link = link.to_string_lossy();
+        for (path, mount) in filter_mounts(&self.mounts, link.as_ref()) {
+            match mount.fs.symlink(target, Path::new(path.as_str())) {
+                Ok(ret) => {
+                    return Ok(ret);
+                }
+                Err(err) => {
+                    ret_error = err;
+                }
+            }
+        }
+        Err(ret_error)
+    }
+    fn readlink(&self, path: &Path) -> Result<PathBuf> {
+        debug!("readlink: path={}", path.display());
+        let mut ret_error = FsError::EntryNotFound;
+        let path = path.to_string_lossy();
+        for (path_inner, mount) in filter_mounts(&self.mounts, path.as_ref()) {
+            match mount.fs.readlink(Path::new(path_inner.as_str())) {
+                Ok(ret) => {
+                    return Ok(ret);
+                }
+                Err(err) => {
+                    debug!("readlink failed: (path={}) - {}", path, err);
+                    ret_error = err;
+                }
+            }
+        }
+        Err(ret_error)
+    }
     fn remove_file(&self, path: &Path) -> Result<()> {
         println!("remove_file: path={}", path.display());
         let mut ret_error = FsError::EntryNotFound;
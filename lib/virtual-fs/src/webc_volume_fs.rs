@@ -134,6 +134,34 @@ impl FileSystem for WebcVolumeFileSystem {
             .ok_or(FsError::EntryNotFound)
     }
 
+    fn symlink(&self, _target: &Path, link: &Path) -> Result<(), FsError> {
+        // the link shouldn't exist yet
+        if self.metadata(link).is_ok() {
+            return Err(FsError::AlreadyExists);
+        }
+
+        // it's parent should exist
+        let parent = link.parent().unwrap_or_else(|| Path::new("/"));
+
+        match self.metadata(parent) {
+            Ok(parent_meta) if parent_meta.is_dir() => {
+                // The operation would normally be doable... but we're a readonly
+                // filesystem
+                Err(FsError::PermissionDenied)
+            }
+            Ok(_) | Err(FsError::EntryNotFound) => Err(FsError::BaseNotDirectory),
+            Err(other) => Err(other),
+        }
+    }
+
+    fn readlink(&self, path: &Path) -> Result<PathBuf, FsError> {
+        // The file should exist
+        let _ = self.metadata(path)?;
+
+        // but volumes can't contain symlinks
+        Err(FsError::InvalidInput)
+    }
+
     fn remove_file(&self, path: &Path) -> Result<(), FsError> {
         let meta = self.metadata(path)?;
 
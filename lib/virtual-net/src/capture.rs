@@ -0,0 +1,1230 @@
+//! Packet capture of the network traffic of a guest.
+//!
+//! [`CaptureNetworking`] wraps another [`VirtualNetworking`] implementation
+//! and records the traffic of its sockets into a [`PacketCapture`], written
+//! in the pcapng format read by tools such as Wireshark.
+//!
+//! Stream sockets only see the payload of their connections, so the TCP/IP
+//! headers of their packets are synthesized: the handshake is recorded when
+//! a connection is opened or accepted, every send and receive becomes a
+//! segment, and the end of the stream and its shutdown become FIN segments.
+//! UDP and ICMP datagrams get synthesized IP (and UDP) headers, while the
+//! Ethernet frames of raw sockets are recorded as they are.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::mem::MaybeUninit;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    DynVirtualNetworking, InterestHandler, IpCidr, IpRoute, NetworkError, Result, SocketStatus,
+    StreamSecurity, VirtualConnectedSocket, VirtualConnectionlessSocket, VirtualIcmpSocket,
+    VirtualIoSource, VirtualNetworking, VirtualRawSocket, VirtualSocket, VirtualTcpListener,
+    VirtualTcpSocket, VirtualUdpSocket,
+};
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const OPTION_END: u16 = 0;
+const OPTION_IF_NAME: u16 = 2;
+
+/// Packets starting with an IPv4 or IPv6 header
+const LINKTYPE_RAW: u16 = 101;
+const LINKTYPE_ETHERNET: u16 = 1;
+
+const PROTOCOL_ICMP: u8 = 1;
+const PROTOCOL_TCP: u8 = 6;
+const PROTOCOL_UDP: u8 = 17;
+const PROTOCOL_ICMPV6: u8 = 58;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_PSH: u8 = 0x08;
+const TCP_ACK: u8 = 0x10;
+
+/// The largest payload of a synthesized TCP segment, so that the segment
+/// fits in an IPv4 packet
+const MAX_SEGMENT_SIZE: usize = u16::MAX as usize - 40;
+const TTL: u8 = 64;
+/// How long recorded packets may stay buffered before they are written out
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The capture interfaces packets are recorded on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Link {
+    /// IP packets, with the synthesized headers of the stream and datagram
+    /// sockets
+    Ip = 0,
+    /// The Ethernet frames of the raw sockets
+    Ethernet = 1,
+}
+
+/// A pcapng file that packets are recorded into.
+///
+/// Recording is best-effort: the packets that fail to be written are
+/// dropped, so that capturing never disturbs the guest. The writer is
+/// flushed at most once every [`FLUSH_INTERVAL`] and when the capture is
+/// dropped, rather than after every packet.
+pub struct PacketCapture {
+    writer: Mutex<CaptureWriter>,
+}
+
+struct CaptureWriter {
+    inner: Box<dyn Write + Send>,
+    last_flush: Instant,
+}
+
+impl fmt::Debug for PacketCapture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PacketCapture").finish_non_exhaustive()
+    }
+}
+
+impl PacketCapture {
+    /// Starts a capture by writing the header of the file
+    pub fn new(writer: impl Write + Send + 'static) -> io::Result<Self> {
+        let mut writer: Box<dyn Write + Send> = Box::new(writer);
+
+        let mut body = Vec::new();
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        // The length of the section is not known in advance
+        body.extend_from_slice(&(-1i64).to_le_bytes());
+        write_block(&mut writer, SECTION_HEADER_BLOCK, &body)?;
+
+        for (link_type, name) in [(LINKTYPE_RAW, "ip"), (LINKTYPE_ETHERNET, "ethernet")] {
+            let mut body = Vec::new();
+            body.extend_from_slice(&link_type.to_le_bytes());
+            body.extend_from_slice(&0u16.to_le_bytes());
+            // No limit on the length of the captured packets
+            body.extend_from_slice(&0u32.to_le_bytes());
+            push_option(&mut body, OPTION_IF_NAME, name.as_bytes());
+            push_option(&mut body, OPTION_END, &[]);
+            write_block(&mut writer, INTERFACE_DESCRIPTION_BLOCK, &body)?;
+        }
+        writer.flush()?;
+
+        Ok(Self {
+            writer: Mutex::new(CaptureWriter {
+                inner: writer,
+                last_flush: Instant::now(),
+            }),
+        })
+    }
+
+    /// Starts a capture into a new file, replacing any existing one
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    fn record(&self, link: Link, packet: &[u8]) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+
+        let mut body = Vec::with_capacity(20 + packet.len() + 3);
+        body.extend_from_slice(&(link as u32).to_le_bytes());
+        body.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(timestamp as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(packet);
+        pad(&mut body);
+
+        let mut writer = self.writer.lock().unwrap();
+        let mut ret = write_block(&mut writer.inner, ENHANCED_PACKET_BLOCK, &body);
+        if ret.is_ok() && writer.last_flush.elapsed() >= FLUSH_INTERVAL {
+            writer.last_flush = Instant::now();
+            ret = writer.inner.flush();
+        }
+        if let Err(err) = ret {
+            tracing::debug!("failed to record a packet - {}", err);
+        }
+    }
+
+    /// Writes out the packets that are still buffered
+    pub fn flush(&self) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.last_flush = Instant::now();
+        writer.inner.flush()
+    }
+
+    fn record_ip(&self, src: IpAddr, dst: IpAddr, protocol: u8, payload: &[u8]) {
+        self.record(Link::Ip, &ip_packet(src, dst, protocol, payload));
+    }
+
+    fn record_udp(&self, src: SocketAddr, dst: SocketAddr, payload: &[u8]) {
+        let (src_ip, dst_ip) = same_family(src.ip(), dst.ip());
+        let mut datagram = Vec::with_capacity(8 + payload.len());
+        datagram.extend_from_slice(&src.port().to_be_bytes());
+        datagram.extend_from_slice(&dst.port().to_be_bytes());
+        datagram.extend_from_slice(&length(8 + payload.len()).to_be_bytes());
+        datagram.extend_from_slice(&[0, 0]);
+        datagram.extend_from_slice(payload);
+        let checksum = match transport_checksum(src_ip, dst_ip, PROTOCOL_UDP, &datagram) {
+            0 => 0xFFFF,
+            checksum => checksum,
+        };
+        datagram[6..8].copy_from_slice(&checksum.to_be_bytes());
+        self.record_ip(src_ip, dst_ip, PROTOCOL_UDP, &datagram);
+    }
+
+    fn record_tcp(
+        &self,
+        src: SocketAddr,
+        dst: SocketAddr,
+        seq: u32,
+        ack: u32,
+        flags: u8,
+        payload: &[u8],
+    ) {
+        let (src_ip, dst_ip) = same_family(src.ip(), dst.ip());
+        let mut segment = Vec::with_capacity(20 + payload.len());
+        segment.extend_from_slice(&src.port().to_be_bytes());
+        segment.extend_from_slice(&dst.port().to_be_bytes());
+        segment.extend_from_slice(&seq.to_be_bytes());
+        segment.extend_from_slice(&ack.to_be_bytes());
+        segment.extend_from_slice(&[5 << 4, flags]);
+        segment.extend_from_slice(&u16::MAX.to_be_bytes());
+        segment.extend_from_slice(&[0, 0, 0, 0]);
+        segment.extend_from_slice(payload);
+        let checksum = transport_checksum(src_ip, dst_ip, PROTOCOL_TCP, &segment);
+        segment[16..18].copy_from_slice(&checksum.to_be_bytes());
+        self.record_ip(src_ip, dst_ip, PROTOCOL_TCP, &segment);
+    }
+
+    fn record_icmp(&self, src: IpAddr, dst: IpAddr, message: &[u8]) {
+        let (src, dst) = same_family(src, dst);
+        let protocol = match src {
+            IpAddr::V4(_) => PROTOCOL_ICMP,
+            IpAddr::V6(_) => PROTOCOL_ICMPV6,
+        };
+        self.record_ip(src, dst, protocol, message);
+    }
+}
+
+impl Drop for PacketCapture {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            tracing::debug!("failed to flush the packet capture - {}", err);
+        }
+    }
+}
+
+fn write_block(writer: &mut dyn Write, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let length = (body.len() as u32 + 12).to_le_bytes();
+    writer.write_all(&block_type.to_le_bytes())?;
+    writer.write_all(&length)?;
+    writer.write_all(body)?;
+    writer.write_all(&length)
+}
+
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    body.extend_from_slice(value);
+    pad(body);
+}
+
+fn pad(body: &mut Vec<u8>) {
+    body.resize((body.len() + 3) & !3, 0);
+}
+
+fn length(len: usize) -> u16 {
+    u16::try_from(len).unwrap_or(u16::MAX)
+}
+
+/// Converts the addresses of a packet to the same family, preferring IPv4
+fn same_family(src: IpAddr, dst: IpAddr) -> (IpAddr, IpAddr) {
+    fn as_v4(ip: IpAddr) -> Option<Ipv4Addr> {
+        match ip {
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(ip) => ip.to_ipv4_mapped(),
+        }
+    }
+    match (as_v4(src), as_v4(dst)) {
+        (Some(src), Some(dst)) => (IpAddr::V4(src), IpAddr::V4(dst)),
+        _ => (IpAddr::V6(as_v6(src)), IpAddr::V6(as_v6(dst))),
+    }
+}
+
+fn as_v6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+/// Wraps the payload of a transport protocol into an IP packet, which is an
+/// IPv6 packet unless both addresses are IPv4 ones.
+fn ip_packet(src: IpAddr, dst: IpAddr, protocol: u8, payload: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(40 + payload.len());
+    match (src, dst) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            packet.extend_from_slice(&[0x45, 0]);
+            packet.extend_from_slice(&length(20 + payload.len()).to_be_bytes());
+            // No identification, and the don't fragment flag
+            packet.extend_from_slice(&[0, 0, 0x40, 0]);
+            packet.extend_from_slice(&[TTL, protocol, 0, 0]);
+            packet.extend_from_slice(&src.octets());
+            packet.extend_from_slice(&dst.octets());
+            let checksum = !fold(sum(&packet));
+            packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+        }
+        (src, dst) => {
+            let (src, dst) = (as_v6(src), as_v6(dst));
+            packet.extend_from_slice(&[0x60, 0, 0, 0]);
+            packet.extend_from_slice(&length(payload.len()).to_be_bytes());
+            packet.extend_from_slice(&[protocol, TTL]);
+            packet.extend_from_slice(&src.octets());
+            packet.extend_from_slice(&dst.octets());
+        }
+    }
+    packet.extend_from_slice(payload);
+    packet
+}
+
+/// Computes the checksum of a TCP segment or UDP datagram, which covers a
+/// pseudo header made of the addresses of the IP packet
+fn transport_checksum(src: IpAddr, dst: IpAddr, protocol: u8, segment: &[u8]) -> u16 {
+    let mut pseudo_header = Vec::with_capacity(40);
+    match (src, dst) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            pseudo_header.extend_from_slice(&src.octets());
+            pseudo_header.extend_from_slice(&dst.octets());
+            pseudo_header.extend_from_slice(&[0, protocol]);
+            pseudo_header.extend_from_slice(&length(segment.len()).to_be_bytes());
+        }
+        (src, dst) => {
+            let (src, dst) = (as_v6(src), as_v6(dst));
+            pseudo_header.extend_from_slice(&src.octets());
+            pseudo_header.extend_from_slice(&dst.octets());
+            pseudo_header.extend_from_slice(&(segment.len() as u32).to_be_bytes());
+            pseudo_header.extend_from_slice(&[0, 0, 0, protocol]);
+        }
+    }
+    !fold(sum(&pseudo_header) + sum(segment))
+}
+
+/// Adds up the big endian 16-bit words of the data, for the Internet
+/// checksum
+fn sum(data: &[u8]) -> u64 {
+    data.chunks(2)
+        .map(|word| match word {
+            [high, low] => u16::from_be_bytes([*high, *low]) as u64,
+            [high] => u16::from_be_bytes([*high, 0]) as u64,
+            _ => 0,
+        })
+        .sum()
+}
+
+fn fold(mut sum: u64) -> u16 {
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    sum as u16
+}
+
+/// Returns the bytes written by a successful read.
+///
+/// # Safety
+///
+/// The first `amt` bytes of the buffer must be initialized.
+unsafe fn assume_init(buf: &[MaybeUninit<u8>], amt: usize) -> &[u8] {
+    std::slice::from_raw_parts(buf.as_ptr() as *const u8, amt.min(buf.len()))
+}
+
+/// Networking that records the traffic of its sockets into a
+/// [`PacketCapture`].
+///
+/// The management of the interface and DNS resolution are passed through
+/// without being recorded.
+#[derive(Debug)]
+pub struct CaptureNetworking {
+    inner: DynVirtualNetworking,
+    capture: Arc<PacketCapture>,
+}
+
+impl CaptureNetworking {
+    pub fn new(inner: DynVirtualNetworking, capture: PacketCapture) -> Self {
+        Self {
+            inner,
+            capture: Arc::new(capture),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl VirtualNetworking for CaptureNetworking {
+    async fn bridge(
+        &self,
+        network: &str,
+        access_token: &str,
+        security: StreamSecurity,
+    ) -> Result<()> {
+        self.inner.bridge(network, access_token, security).await
+    }
+
+    async fn unbridge(&self) -> Result<()> {
+        self.inner.unbridge().await
+    }
+
+    async fn dhcp_acquire(&self) -> Result<Vec<IpAddr>> {
+        self.inner.dhcp_acquire().await
+    }
+
+    async fn ip_add(&self, ip: IpAddr, prefix: u8) -> Result<()> {
+        self.inner.ip_add(ip, prefix).await
+    }
+
+    async fn ip_remove(&self, ip: IpAddr) -> Result<()> {
+        self.inner.ip_remove(ip).await
+    }
+
+    async fn ip_clear(&self) -> Result<()> {
+        self.inner.ip_clear().await
+    }
+
+    async fn ip_list(&self) -> Result<Vec<IpCidr>> {
+        self.inner.ip_list().await
+    }
+
+    async fn mac(&self) -> Result<[u8; 6]> {
+        self.inner.mac().await
+    }
+
+    async fn gateway_set(&self, ip: IpAddr) -> Result<()> {
+        self.inner.gateway_set(ip).await
+    }
+
+    async fn route_add(
+        &self,
+        cidr: IpCidr,
+        via_router: IpAddr,
+        preferred_until: Option<Duration>,
+        expires_at: Option<Duration>,
+    ) -> Result<()> {
+        self.inner
+            .route_add(cidr, via_router, preferred_until, expires_at)
+            .await
+    }
+
+    async fn route_remove(&self, cidr: IpAddr) -> Result<()> {
+        self.inner.route_remove(cidr).await
+    }
+
+    async fn route_clear(&self) -> Result<()> {
+        self.inner.route_clear().await
+    }
+
+    async fn route_list(&self) -> Result<Vec<IpRoute>> {
+        self.inner.route_list().await
+    }
+
+    async fn bind_raw(&self) -> Result<Box<dyn VirtualRawSocket + Sync>> {
+        let socket = self.inner.bind_raw().await?;
+        Ok(Box::new(CaptureRawSocket {
+            inner: socket,
+            capture: self.capture.clone(),
+        }))
+    }
+
+    async fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        let listener = self
+            .inner
+            .listen_tcp(addr, only_v6, reuse_port, reuse_addr)
+            .await?;
+        Ok(Box::new(CaptureTcpListener {
+            inner: listener,
+            capture: self.capture.clone(),
+        }))
+    }
+
+    async fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualUdpSocket + Sync>> {
+        let socket = self.inner.bind_udp(addr, reuse_port, reuse_addr).await?;
+        Ok(Box::new(CaptureUdpSocket {
+            inner: socket,
+            capture: self.capture.clone(),
+        }))
+    }
+
+    async fn bind_icmp(&self, addr: IpAddr) -> Result<Box<dyn VirtualIcmpSocket + Sync>> {
+        let socket = self.inner.bind_icmp(addr).await?;
+        Ok(Box::new(CaptureIcmpSocket {
+            inner: socket,
+            capture: self.capture.clone(),
+        }))
+    }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let socket = self.inner.connect_tcp(addr, peer).await?;
+        let local = socket.addr_local().unwrap_or(addr);
+        let peer = socket.addr_peer().unwrap_or(peer);
+        let socket = CaptureTcpSocket::new(socket, self.capture.clone(), local, peer);
+        socket.record_handshake(true);
+        Ok(Box::new(socket))
+    }
+
+    async fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        self.inner.resolve(host, port, dns_server).await
+    }
+}
+
+/// A TCP listener whose accepted connections are recorded.
+#[derive(Debug)]
+struct CaptureTcpListener {
+    inner: Box<dyn VirtualTcpListener + Sync>,
+    capture: Arc<PacketCapture>,
+}
+
+impl VirtualIoSource for CaptureTcpListener {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualTcpListener for CaptureTcpListener {
+    fn try_accept(&mut self) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        let (socket, peer) = self.inner.try_accept()?;
+        let local = socket
+            .addr_local()
+            .or_else(|_| self.inner.addr_local())
+            .unwrap_or_else(|_| SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0));
+        let socket = CaptureTcpSocket::new(socket, self.capture.clone(), local, peer);
+        socket.record_handshake(false);
+        Ok((Box::new(socket), peer))
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn set_ttl(&mut self, ttl: u8) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u8> {
+        self.inner.ttl()
+    }
+}
+
+/// A TCP connection whose payload is recorded as synthesized segments.
+///
+/// The sequence numbers of both sides start at zero, and advance with the
+/// bytes sent and received by the guest.
+#[derive(Debug)]
+struct CaptureTcpSocket {
+    inner: Box<dyn VirtualTcpSocket + Sync>,
+    capture: Arc<PacketCapture>,
+    local: SocketAddr,
+    peer: SocketAddr,
+    /// The sequence number of the next byte sent by the guest
+    seq: u32,
+    /// The sequence number of the next byte received from the peer
+    ack: u32,
+    fin_sent: bool,
+    fin_received: bool,
+    reset: bool,
+}
+
+impl CaptureTcpSocket {
+    fn new(
+        inner: Box<dyn VirtualTcpSocket + Sync>,
+        capture: Arc<PacketCapture>,
+        local: SocketAddr,
+        peer: SocketAddr,
+    ) -> Self {
+        Self {
+            inner,
+            capture,
+            local,
+            peer,
+            seq: 1,
+            ack: 1,
+            fin_sent: false,
+            fin_received: false,
+            reset: false,
+        }
+    }
+
+    fn record_segment(&self, outgoing: bool, flags: u8, payload: &[u8]) {
+        let (src, dst, seq, ack) = match outgoing {
+            true => (self.local, self.peer, self.seq, self.ack),
+            false => (self.peer, self.local, self.ack, self.seq),
+        };
+        self.capture.record_tcp(src, dst, seq, ack, flags, payload);
+    }
+
+    /// Records the three-way handshake that opened the connection, which
+    /// consumed the first sequence number of both sides
+    fn record_handshake(&self, outgoing: bool) {
+        let (initiator, responder) = match outgoing {
+            true => (self.local, self.peer),
+            false => (self.peer, self.local),
+        };
+        let capture = &self.capture;
+        capture.record_tcp(initiator, responder, 0, 0, TCP_SYN, &[]);
+        capture.record_tcp(responder, initiator, 0, 1, TCP_SYN | TCP_ACK, &[]);
+        capture.record_tcp(initiator, responder, 1, 1, TCP_ACK, &[]);
+    }
+
+    fn record_sent(&mut self, data: &[u8]) {
+        for chunk in data.chunks(MAX_SEGMENT_SIZE) {
+            self.record_segment(true, TCP_PSH | TCP_ACK, chunk);
+            self.seq = self.seq.wrapping_add(chunk.len() as u32);
+        }
+    }
+
+    fn record_received(&mut self, data: &[u8]) {
+        for chunk in data.chunks(MAX_SEGMENT_SIZE) {
+            self.record_segment(false, TCP_PSH | TCP_ACK, chunk);
+            self.ack = self.ack.wrapping_add(chunk.len() as u32);
+        }
+    }
+
+    fn record_fin(&mut self, outgoing: bool) {
+        if self.reset || (outgoing && self.fin_sent) || (!outgoing && self.fin_received) {
+            return;
+        }
+        self.record_segment(outgoing, TCP_FIN | TCP_ACK, &[]);
+        if outgoing {
+            self.fin_sent = true;
+            self.seq = self.seq.wrapping_add(1);
+        } else {
+            self.fin_received = true;
+            self.ack = self.ack.wrapping_add(1);
+        }
+    }
+
+    fn record_error(&mut self, err: NetworkError) {
+        if !self.reset && err == NetworkError::ConnectionReset {
+            self.record_segment(false, TCP_RST | TCP_ACK, &[]);
+            self.reset = true;
+        }
+    }
+}
+
+impl Drop for CaptureTcpSocket {
+    fn drop(&mut self) {
+        self.record_fin(true);
+    }
+}
+
+impl VirtualIoSource for CaptureTcpSocket {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualSocket for CaptureTcpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+}
+
+impl VirtualConnectedSocket for CaptureTcpSocket {
+    fn set_linger(&mut self, linger: Option<Duration>) -> Result<()> {
+        self.inner.set_linger(linger)
+    }
+
+    fn linger(&self) -> Result<Option<Duration>> {
+        self.inner.linger()
+    }
+
+    fn try_send(&mut self, data: &[u8]) -> Result<usize> {
+        match self.inner.try_send(data) {
+            Ok(amt) => {
+                self.record_sent(&data[..amt.min(data.len())]);
+                Ok(amt)
+            }
+            Err(err) => {
+                self.record_error(err);
+                Err(err)
+            }
+        }
+    }
+
+    fn try_flush(&mut self) -> Result<()> {
+        self.inner.try_flush()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.inner.close()?;
+        self.record_fin(true);
+        Ok(())
+    }
+
+    fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize> {
+        match self.inner.try_recv(buf) {
+            Ok(0) if !buf.is_empty() => {
+                self.record_fin(false);
+                Ok(0)
+            }
+            Ok(amt) => {
+                self.record_received(unsafe { assume_init(buf, amt) });
+                Ok(amt)
+            }
+            Err(err) => {
+                self.record_error(err);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl VirtualTcpSocket for CaptureTcpSocket {
+    fn set_recv_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_recv_buf_size(size)
+    }
+
+    fn recv_buf_size(&self) -> Result<usize> {
+        self.inner.recv_buf_size()
+    }
+
+    fn set_send_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_send_buf_size(size)
+    }
+
+    fn send_buf_size(&self) -> Result<usize> {
+        self.inner.send_buf_size()
+    }
+
+    fn set_nodelay(&mut self, reuse: bool) -> Result<()> {
+        self.inner.set_nodelay(reuse)
+    }
+
+    fn nodelay(&self) -> Result<bool> {
+        self.inner.nodelay()
+    }
+
+    fn set_keepalive(&mut self, keepalive: bool) -> Result<()> {
+        self.inner.set_keepalive(keepalive)
+    }
+
+    fn keepalive(&self) -> Result<bool> {
+        self.inner.keepalive()
+    }
+
+    fn set_dontroute(&mut self, keepalive: bool) -> Result<()> {
+        self.inner.set_dontroute(keepalive)
+    }
+
+    fn dontroute(&self) -> Result<bool> {
+        self.inner.dontroute()
+    }
+
+    fn addr_peer(&self) -> Result<SocketAddr> {
+        self.inner.addr_peer()
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+        self.inner.shutdown(how)?;
+        if how != Shutdown::Read {
+            self.record_fin(true);
+        }
+        Ok(())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+}
+
+/// A UDP socket whose datagrams are recorded.
+///
+/// Sockets bound to an unspecified address record it as the address of the
+/// guest, as the one chosen by the host is not known.
+#[derive(Debug)]
+struct CaptureUdpSocket {
+    inner: Box<dyn VirtualUdpSocket + Sync>,
+    capture: Arc<PacketCapture>,
+}
+
+impl CaptureUdpSocket {
+    fn local(&self) -> SocketAddr {
+        self.inner
+            .addr_local()
+            .unwrap_or_else(|_| SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0))
+    }
+}
+
+impl VirtualIoSource for CaptureUdpSocket {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualSocket for CaptureUdpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+}
+
+impl VirtualConnectionlessSocket for CaptureUdpSocket {
+    fn try_send_to(&mut self, data: &[u8], addr: SocketAddr) -> Result<usize> {
+        let amt = self.inner.try_send_to(data, addr)?;
+        self.capture
+            .record_udp(self.local(), addr, &data[..amt.min(data.len())]);
+        Ok(amt)
+    }
+
+    fn try_recv_from(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<(usize, SocketAddr)> {
+        let (amt, addr) = self.inner.try_recv_from(buf)?;
+        self.capture
+            .record_udp(addr, self.local(), unsafe { assume_init(buf, amt) });
+        Ok((amt, addr))
+    }
+}
+
+impl VirtualUdpSocket for CaptureUdpSocket {
+    fn set_broadcast(&mut self, broadcast: bool) -> Result<()> {
+        self.inner.set_broadcast(broadcast)
+    }
+
+    fn broadcast(&self) -> Result<bool> {
+        self.inner.broadcast()
+    }
+
+    fn set_multicast_loop_v4(&mut self, val: bool) -> Result<()> {
+        self.inner.set_multicast_loop_v4(val)
+    }
+
+    fn multicast_loop_v4(&self) -> Result<bool> {
+        self.inner.multicast_loop_v4()
+    }
+
+    fn set_multicast_loop_v6(&mut self, val: bool) -> Result<()> {
+        self.inner.set_multicast_loop_v6(val)
+    }
+
+    fn multicast_loop_v6(&self) -> Result<bool> {
+        self.inner.multicast_loop_v6()
+    }
+
+    fn set_multicast_ttl_v4(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_multicast_ttl_v4(ttl)
+    }
+
+    fn multicast_ttl_v4(&self) -> Result<u32> {
+        self.inner.multicast_ttl_v4()
+    }
+
+    fn join_multicast_v4(&mut self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<()> {
+        self.inner.join_multicast_v4(multiaddr, iface)
+    }
+
+    fn leave_multicast_v4(&mut self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<()> {
+        self.inner.leave_multicast_v4(multiaddr, iface)
+    }
+
+    fn join_multicast_v6(&mut self, multiaddr: Ipv6Addr, iface: u32) -> Result<()> {
+        self.inner.join_multicast_v6(multiaddr, iface)
+    }
+
+    fn leave_multicast_v6(&mut self, multiaddr: Ipv6Addr, iface: u32) -> Result<()> {
+        self.inner.leave_multicast_v6(multiaddr, iface)
+    }
+
+    fn addr_peer(&self) -> Result<Option<SocketAddr>> {
+        self.inner.addr_peer()
+    }
+}
+
+/// An ICMP socket whose messages are recorded.
+#[derive(Debug)]
+struct CaptureIcmpSocket {
+    inner: Box<dyn VirtualIcmpSocket + Sync>,
+    capture: Arc<PacketCapture>,
+}
+
+impl CaptureIcmpSocket {
+    fn local(&self) -> IpAddr {
+        self.inner
+            .addr_local()
+            .map(|addr| addr.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+    }
+}
+
+impl VirtualIoSource for CaptureIcmpSocket {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualSocket for CaptureIcmpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+}
+
+impl VirtualConnectionlessSocket for CaptureIcmpSocket {
+    fn try_send_to(&mut self, data: &[u8], addr: SocketAddr) -> Result<usize> {
+        let amt = self.inner.try_send_to(data, addr)?;
+        self.capture
+            .record_icmp(self.local(), addr.ip(), &data[..amt.min(data.len())]);
+        Ok(amt)
+    }
+
+    fn try_recv_from(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<(usize, SocketAddr)> {
+        let (amt, addr) = self.inner.try_recv_from(buf)?;
+        self.capture
+            .record_icmp(addr.ip(), self.local(), unsafe { assume_init(buf, amt) });
+        Ok((amt, addr))
+    }
+}
+
+impl VirtualIcmpSocket for CaptureIcmpSocket {}
+
+/// A raw socket whose Ethernet frames are recorded.
+#[derive(Debug)]
+struct CaptureRawSocket {
+    inner: Box<dyn VirtualRawSocket + Sync>,
+    capture: Arc<PacketCapture>,
+}
+
+impl VirtualIoSource for CaptureRawSocket {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualSocket for CaptureRawSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+}
+
+impl VirtualRawSocket for CaptureRawSocket {
+    fn try_send(&mut self, data: &[u8]) -> Result<usize> {
+        let amt = self.inner.try_send(data)?;
+        self.capture
+            .record(Link::Ethernet, &data[..amt.min(data.len())]);
+        Ok(amt)
+    }
+
+    fn try_flush(&mut self) -> Result<()> {
+        self.inner.try_flush()
+    }
+
+    fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize> {
+        let amt = self.inner.try_recv(buf)?;
+        self.capture
+            .record(Link::Ethernet, unsafe { assume_init(buf, amt) });
+        Ok(amt)
+    }
+
+    fn set_promiscuous(&mut self, promiscuous: bool) -> Result<()> {
+        self.inner.set_promiscuous(promiscuous)
+    }
+
+    fn promiscuous(&self) -> Result<bool> {
+        self.inner.promiscuous()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        LoopbackNetworking, VirtualConnectedSocketExt, VirtualConnectionlessSocketExt,
+        VirtualTcpListenerExt,
+    };
+
+    /// A writer whose output outlives the capture
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Splits a capture into the types and bodies of its blocks
+    fn blocks(mut data: &[u8]) -> Vec<(u32, Vec<u8>)> {
+        let mut blocks = Vec::new();
+        while !data.is_empty() {
+            let block_type = u32::from_le_bytes(data[0..4].try_into().unwrap());
+            let len = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+            assert_eq!(len % 4, 0);
+            assert_eq!(data[len - 4..len], data[4..8]);
+            blocks.push((block_type, data[8..len - 4].to_vec()));
+            data = &data[len..];
+        }
+        blocks
+    }
+
+    /// Returns the IP packets of a capture
+    fn packets(data: &[u8]) -> Vec<Vec<u8>> {
+        blocks(data)
+            .into_iter()
+            .filter(|(block_type, _)| *block_type == ENHANCED_PACKET_BLOCK)
+            .map(|(_, body)| {
+                assert_eq!(body[0..4], (Link::Ip as u32).to_le_bytes());
+                let len = u32::from_le_bytes(body[12..16].try_into().unwrap()) as usize;
+                body[20..20 + len].to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn captures_start_with_a_section_and_interfaces() {
+        let buffer = SharedBuffer::default();
+        PacketCapture::new(buffer.clone()).unwrap();
+        let data = buffer.0.lock().unwrap();
+        let blocks = blocks(&data);
+        let types: Vec<_> = blocks.iter().map(|(block_type, _)| *block_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                SECTION_HEADER_BLOCK,
+                INTERFACE_DESCRIPTION_BLOCK,
+                INTERFACE_DESCRIPTION_BLOCK
+            ]
+        );
+        assert_eq!(blocks[0].1[0..4], BYTE_ORDER_MAGIC.to_le_bytes());
+        assert_eq!(blocks[1].1[0..2], LINKTYPE_RAW.to_le_bytes());
+        assert_eq!(blocks[2].1[0..2], LINKTYPE_ETHERNET.to_le_bytes());
+    }
+
+    #[test]
+    fn packets_are_buffered_until_the_capture_is_dropped() {
+        let buffer = SharedBuffer::default();
+        let capture = PacketCapture::new(BufWriter::new(buffer.clone())).unwrap();
+        let header = buffer.0.lock().unwrap().len();
+        let addr = "127.0.0.1:53".parse().unwrap();
+        capture.record_udp(addr, addr, b"query");
+        assert_eq!(buffer.0.lock().unwrap().len(), header);
+
+        drop(capture);
+        assert_eq!(packets(&buffer.0.lock().unwrap()).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn tcp_streams_are_recorded_as_segments() {
+        let buffer = SharedBuffer::default();
+        let net = CaptureNetworking::new(
+            Arc::new(LoopbackNetworking::new()),
+            PacketCapture::new(buffer.clone()).unwrap(),
+        );
+        let mut listener = net
+            .listen_tcp("127.0.0.1:8080".parse().unwrap(), false, false, false)
+            .await
+            .unwrap();
+        let mut client = net
+            .connect_tcp(
+                "0.0.0.0:0".parse().unwrap(),
+                "127.0.0.1:8080".parse().unwrap(),
+            )
+            .await
+            .unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+        client.send(b"hello").await.unwrap();
+        let mut buf = [MaybeUninit::uninit(); 16];
+        assert_eq!(server.recv(&mut buf).await.unwrap(), 5);
+        drop(client);
+        assert_eq!(server.recv(&mut buf).await.unwrap(), 0);
+        drop(server);
+
+        let packets = packets(&buffer.0.lock().unwrap());
+        // The handshake and the payload are recorded by both the client and
+        // the server, followed by the FIN of the client (seen from both
+        // sides) and the FIN of the server
+        assert_eq!(packets.len(), 11);
+        for packet in &packets {
+            assert_eq!(packet[0], 0x45);
+            assert_eq!(packet[9], PROTOCOL_TCP);
+            assert_eq!(fold(sum(&packet[..20])), 0xFFFF);
+            let segment = &packet[20..];
+            let src = IpAddr::V4(Ipv4Addr::LOCALHOST);
+            assert_eq!(transport_checksum(src, src, PROTOCOL_TCP, segment), 0);
+        }
+        let flags: Vec<u8> = packets.iter().map(|packet| packet[33]).collect();
+        assert_eq!(
+            flags,
+            vec![
+                TCP_SYN,
+                TCP_SYN | TCP_ACK,
+                TCP_ACK,
+                TCP_SYN,
+                TCP_SYN | TCP_ACK,
+                TCP_ACK,
+                TCP_PSH | TCP_ACK,
+                TCP_PSH | TCP_ACK,
+                TCP_FIN | TCP_ACK,
+                TCP_FIN | TCP_ACK,
+                TCP_FIN | TCP_ACK,
+            ]
+        );
+        let payload = &packets[6][40..];
+        assert_eq!(payload, b"hello");
+        assert_eq!(packets[7][40..], packets[6][40..]);
+    }
+
+    #[tokio::test]
+    async fn udp_datagrams_are_recorded() {
+        let buffer = SharedBuffer::default();
+        let net = CaptureNetworking::new(
+            Arc::new(LoopbackNetworking::new()),
+            PacketCapture::new(buffer.clone()).unwrap(),
+        );
+        let mut socket = net
+            .bind_udp("127.0.0.1:5353".parse().unwrap(), false, false)
+            .await
+            .unwrap();
+        let addr = socket.addr_local().unwrap();
+        socket.send_to(b"query", addr).await.unwrap();
+
+        let packets = packets(&buffer.0.lock().unwrap());
+        assert_eq!(packets.len(), 1);
+        let packet = &packets[0];
+        assert_eq!(packet[9], PROTOCOL_UDP);
+        assert_eq!(packet[20..22], 5353u16.to_be_bytes());
+        assert_eq!(packet[22..24], 5353u16.to_be_bytes());
+        assert_eq!(packet[24..26], 13u16.to_be_bytes());
+        assert_eq!(&packet[28..], b"query");
+    }
+}
@@ -1,10 +1,13 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+pub mod capture;
 #[cfg(feature = "remote")]
 pub mod client;
 #[cfg(feature = "host-net")]
 pub mod host;
+pub mod loopback;
 pub mod meta;
+pub mod policy;
 #[cfg(feature = "remote")]
 pub mod rx_tx;
 #[cfg(feature = "remote")]
@@ -13,9 +16,12 @@ pub mod server;
 #[cfg(test)]
 mod tests;
 
+pub use capture::{CaptureNetworking, PacketCapture};
 #[cfg(feature = "remote")]
 pub use client::{RemoteNetworkingClient, RemoteNetworkingClientDriver};
+pub use loopback::LoopbackNetworking;
 use pin_project_lite::pin_project;
+pub use policy::{NetworkPolicy, NetworkRule, PolicyNetworking};
 #[cfg(feature = "rkyv")]
 use rkyv::{Archive, CheckBytes, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 #[cfg(feature = "remote")]
@@ -0,0 +1,1227 @@
+//! Networking between the processes of a host, purely in memory.
+//!
+//! [`LoopbackNetworking`] never touches the network of the host: TCP
+//! connections and UDP datagrams are handed from one socket to another
+//! through memory buffers, which makes the communication between processes
+//! deterministic and independent of the ports that are free on the host.
+//!
+//! Every [`LoopbackNetworking`] is an interface attached to a virtual
+//! network segment. Clones share the interface, while
+//! [`LoopbackNetworking::new_interface`] attaches another interface to the
+//! same segment, for instance to give each process its own addresses.
+//! Addresses are assigned with [`VirtualNetworking::ip_add`] or leased from
+//! the `10.0.0.0/24` subnet with [`VirtualNetworking::dhcp_acquire`]. The
+//! loopback addresses are always available and only reach the sockets of
+//! the same interface. All the interfaces of a segment reach each other
+//! directly, so the routing table is only recorded.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem::MaybeUninit;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use bytes::Bytes;
+use derivative::Derivative;
+use virtual_mio::InterestType;
+
+use crate::{
+    InterestHandler, IpCidr, IpRoute, NetworkError, Result, SocketStatus, VirtualConnectedSocket,
+    VirtualConnectionlessSocket, VirtualIoSource, VirtualNetworking, VirtualSocket,
+    VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+};
+
+/// The subnet the addresses handed out by `dhcp_acquire` are taken from
+const DHCP_SUBNET: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 0);
+const DHCP_PREFIX: u8 = 24;
+/// The gateway of the leased addresses, which is never assigned itself
+const DHCP_GATEWAY: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+
+const EPHEMERAL_PORTS: std::ops::RangeInclusive<u16> = 49152..=65535;
+const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+const DEFAULT_TTL: u32 = 64;
+/// The number of connections waiting to be accepted before new ones are
+/// refused
+const MAX_BACKLOG: usize = 1024;
+/// The number of datagrams waiting to be received before new ones are
+/// dropped
+const MAX_DATAGRAMS: usize = 1024;
+
+/// An interface attached to a network segment that only exists in memory.
+#[derive(Debug, Clone)]
+pub struct LoopbackNetworking {
+    segment: Arc<Mutex<Segment>>,
+    interface: Arc<Interface>,
+}
+
+impl LoopbackNetworking {
+    /// Creates a new network segment with a single interface
+    pub fn new() -> Self {
+        Self::attach(Arc::new(Mutex::new(Segment::default())))
+    }
+
+    /// Attaches another interface, with its own addresses and loopback, to
+    /// the network segment of this interface
+    pub fn new_interface(&self) -> Self {
+        Self::attach(self.segment.clone())
+    }
+
+    fn attach(segment: Arc<Mutex<Segment>>) -> Self {
+        let id = {
+            let mut state = segment.lock().unwrap();
+            let id = state.next_interface;
+            state.next_interface += 1;
+            state.interfaces.insert(id, InterfaceState::default());
+            id
+        };
+        Self {
+            interface: Arc::new(Interface {
+                id,
+                segment: segment.clone(),
+            }),
+            segment,
+        }
+    }
+
+    fn id(&self) -> usize {
+        self.interface.id
+    }
+
+    fn with_interface<T>(&self, f: impl FnOnce(&mut InterfaceState) -> T) -> T {
+        let mut segment = self.segment.lock().unwrap();
+        f(segment.interfaces.get_mut(&self.id()).unwrap())
+    }
+}
+
+impl Default for LoopbackNetworking {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Detaches the interface from the segment once all its clones are gone
+#[derive(Debug)]
+struct Interface {
+    id: usize,
+    segment: Arc<Mutex<Segment>>,
+}
+
+impl Drop for Interface {
+    fn drop(&mut self) {
+        if let Ok(mut segment) = self.segment.lock() {
+            segment.interfaces.remove(&self.id);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct InterfaceState {
+    ips: Vec<IpCidr>,
+    routes: Vec<IpRoute>,
+}
+
+/// The bindings of all the interfaces of a network segment. The sockets are
+/// keyed by their interface and local address.
+#[derive(Debug, Default)]
+struct Segment {
+    interfaces: HashMap<usize, InterfaceState>,
+    next_interface: usize,
+    next_port: u16,
+    listeners: HashMap<(usize, SocketAddr), Arc<Mutex<ListenerState>>>,
+    udp_sockets: HashMap<(usize, SocketAddr), Arc<Mutex<UdpState>>>,
+}
+
+impl Segment {
+    fn owns(&self, interface: usize, ip: IpAddr) -> bool {
+        self.interfaces
+            .get(&interface)
+            .map_or(false, |state| state.ips.iter().any(|cidr| cidr.ip == ip))
+    }
+
+    /// Checks that a socket of the interface can be bound to an address
+    fn check_local(&self, interface: usize, ip: IpAddr) -> Result<()> {
+        if ip.is_unspecified() || ip.is_loopback() || self.owns(interface, ip) {
+            Ok(())
+        } else {
+            Err(NetworkError::AddressNotAvailable)
+        }
+    }
+
+    /// Returns the interface an address belongs to, as seen from another
+    /// interface
+    fn route(&self, interface: usize, ip: IpAddr) -> Option<usize> {
+        if ip.is_loopback() {
+            return Some(interface);
+        }
+        self.interfaces
+            .iter()
+            .find(|(_, state)| state.ips.iter().any(|cidr| cidr.ip == ip))
+            .map(|(id, _)| *id)
+    }
+
+    /// Picks the address a socket bound to `local` sends from when it
+    /// reaches out to `peer`
+    fn source_ip(&self, interface: usize, local: IpAddr, peer: IpAddr) -> Result<IpAddr> {
+        if !local.is_unspecified() {
+            return Ok(local);
+        }
+        if peer.is_loopback() {
+            return Ok(peer);
+        }
+        let ips = self
+            .interfaces
+            .get(&interface)
+            .map(|state| state.ips.as_slice())
+            .unwrap_or_default();
+        ips.iter()
+            .find(|cidr| cidr_contains(cidr, peer))
+            .or_else(|| ips.iter().find(|cidr| cidr.ip.is_ipv4() == peer.is_ipv4()))
+            .map(|cidr| cidr.ip)
+            .ok_or(NetworkError::AddressNotAvailable)
+    }
+
+    /// Assigns a free port for an address of an interface
+    fn ephemeral_port(&mut self, interface: usize, ip: IpAddr) -> Result<u16> {
+        let count = EPHEMERAL_PORTS.len();
+        for _ in 0..count {
+            let offset = self.next_port as usize % count;
+            self.next_port = self.next_port.wrapping_add(1);
+            let port = EPHEMERAL_PORTS.start() + offset as u16;
+            let key = (interface, SocketAddr::new(ip, port));
+            if !self.listeners.contains_key(&key) && !self.udp_sockets.contains_key(&key) {
+                return Ok(port);
+            }
+        }
+        Err(NetworkError::AddressInUse)
+    }
+
+    fn bind_addr(&mut self, interface: usize, addr: SocketAddr) -> Result<SocketAddr> {
+        self.check_local(interface, addr.ip())?;
+        match addr.port() {
+            0 => Ok(SocketAddr::new(
+                addr.ip(),
+                self.ephemeral_port(interface, addr.ip())?,
+            )),
+            _ => Ok(addr),
+        }
+    }
+
+    fn listener(&self, interface: usize, addr: SocketAddr) -> Option<Arc<Mutex<ListenerState>>> {
+        self.listeners
+            .get(&(interface, addr))
+            .or_else(|| self.listeners.get(&(interface, unspecified(addr))))
+            .cloned()
+    }
+
+    fn udp_socket(&self, interface: usize, addr: SocketAddr) -> Option<Arc<Mutex<UdpState>>> {
+        self.udp_sockets
+            .get(&(interface, addr))
+            .or_else(|| self.udp_sockets.get(&(interface, unspecified(addr))))
+            .cloned()
+    }
+}
+
+fn unspecified(addr: SocketAddr) -> SocketAddr {
+    let ip = match addr {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    SocketAddr::new(ip, addr.port())
+}
+
+fn cidr_contains(cidr: &IpCidr, ip: IpAddr) -> bool {
+    match (cidr.ip, ip) {
+        (IpAddr::V4(net), IpAddr::V4(ip)) => {
+            let mask = u32::MAX.checked_shl(32 - cidr.prefix.min(32) as u32);
+            let mask = mask.unwrap_or(0);
+            u32::from(net) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(net), IpAddr::V6(ip)) => {
+            let mask = u128::MAX.checked_shl(128 - cidr.prefix.min(128) as u32);
+            let mask = mask.unwrap_or(0);
+            u128::from(net) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+fn default_route(ip: IpAddr) -> IpCidr {
+    let ip = match ip {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    IpCidr { ip, prefix: 0 }
+}
+
+#[async_trait::async_trait]
+impl VirtualNetworking for LoopbackNetworking {
+    async fn dhcp_acquire(&self) -> Result<Vec<IpAddr>> {
+        let subnet = IpCidr {
+            ip: IpAddr::V4(DHCP_SUBNET),
+            prefix: DHCP_PREFIX,
+        };
+        let mut segment = self.segment.lock().unwrap();
+        let interface = segment.interfaces.get(&self.id()).unwrap();
+        if let Some(cidr) = interface
+            .ips
+            .iter()
+            .find(|cidr| cidr_contains(&subnet, cidr.ip))
+        {
+            return Ok(vec![cidr.ip]);
+        }
+
+        let base = u32::from(DHCP_SUBNET);
+        let ip = (2..(1u32 << (32 - DHCP_PREFIX)) - 1)
+            .map(|host| IpAddr::V4(Ipv4Addr::from(base + host)))
+            .find(|ip| segment.route(self.id(), *ip).is_none())
+            .ok_or(NetworkError::AddressNotAvailable)?;
+
+        let interface = segment.interfaces.get_mut(&self.id()).unwrap();
+        interface.ips.push(IpCidr {
+            ip,
+            prefix: DHCP_PREFIX,
+        });
+        let route = IpRoute {
+            cidr: default_route(ip),
+            via_router: IpAddr::V4(DHCP_GATEWAY),
+            preferred_until: None,
+            expires_at: None,
+        };
+        interface.routes.retain(|r| r.cidr != route.cidr);
+        interface.routes.push(route);
+        Ok(vec![ip])
+    }
+
+    async fn ip_add(&self, ip: IpAddr, prefix: u8) -> Result<()> {
+        let max_prefix = if ip.is_ipv4() { 32 } else { 128 };
+        if ip.is_unspecified() || ip.is_loopback() || prefix > max_prefix {
+            return Err(NetworkError::InvalidInput);
+        }
+        let mut segment = self.segment.lock().unwrap();
+        match segment.route(self.id(), ip) {
+            Some(id) if id != self.id() => return Err(NetworkError::AddressInUse),
+            _ => {}
+        }
+        let interface = segment.interfaces.get_mut(&self.id()).unwrap();
+        interface.ips.retain(|cidr| cidr.ip != ip);
+        interface.ips.push(IpCidr { ip, prefix });
+        Ok(())
+    }
+
+    async fn ip_remove(&self, ip: IpAddr) -> Result<()> {
+        self.with_interface(|interface| interface.ips.retain(|cidr| cidr.ip != ip));
+        Ok(())
+    }
+
+    async fn ip_clear(&self) -> Result<()> {
+        self.with_interface(|interface| interface.ips.clear());
+        Ok(())
+    }
+
+    async fn ip_list(&self) -> Result<Vec<IpCidr>> {
+        Ok(self.with_interface(|interface| interface.ips.clone()))
+    }
+
+    async fn mac(&self) -> Result<[u8; 6]> {
+        // A locally administered address derived from the interface
+        let id = (self.id() as u32).to_be_bytes();
+        Ok([0x02, 0x00, id[0], id[1], id[2], id[3]])
+    }
+
+    async fn gateway_set(&self, ip: IpAddr) -> Result<()> {
+        self.route_add(default_route(ip), ip, None, None).await
+    }
+
+    async fn route_add(
+        &self,
+        cidr: IpCidr,
+        via_router: IpAddr,
+        preferred_until: Option<Duration>,
+        expires_at: Option<Duration>,
+    ) -> Result<()> {
+        self.with_interface(|interface| {
+            interface.routes.retain(|route| route.cidr != cidr);
+            interface.routes.push(IpRoute {
+                cidr,
+                via_router,
+                preferred_until,
+                expires_at,
+            });
+        });
+        Ok(())
+    }
+
+    async fn route_remove(&self, cidr: IpAddr) -> Result<()> {
+        self.with_interface(|interface| interface.routes.retain(|route| route.cidr.ip != cidr));
+        Ok(())
+    }
+
+    async fn route_clear(&self) -> Result<()> {
+        self.with_interface(|interface| interface.routes.clear());
+        Ok(())
+    }
+
+    async fn route_list(&self) -> Result<Vec<IpRoute>> {
+        Ok(self.with_interface(|interface| interface.routes.clone()))
+    }
+
+    /// Listens for connections on an address of this interface. The reuse
+    /// flags are ignored, so an address can only have one listener.
+    async fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        _only_v6: bool,
+        _reuse_port: bool,
+        _reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        let mut segment = self.segment.lock().unwrap();
+        let addr = segment.bind_addr(self.id(), addr)?;
+        let key = (self.id(), addr);
+        if segment.listeners.contains_key(&key) {
+            return Err(NetworkError::AddressInUse);
+        }
+        let state = Arc::new(Mutex::new(ListenerState::default()));
+        segment.listeners.insert(key, state.clone());
+        Ok(Box::new(LoopbackTcpListener {
+            segment: self.segment.clone(),
+            interface: self.id(),
+            addr,
+            state,
+            ttl: DEFAULT_TTL as u8,
+        }))
+    }
+
+    async fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        _reuse_port: bool,
+        _reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualUdpSocket + Sync>> {
+        let mut segment = self.segment.lock().unwrap();
+        let addr = segment.bind_addr(self.id(), addr)?;
+        let key = (self.id(), addr);
+        if segment.udp_sockets.contains_key(&key) {
+            return Err(NetworkError::AddressInUse);
+        }
+        let state = Arc::new(Mutex::new(UdpState::default()));
+        segment.udp_sockets.insert(key, state.clone());
+        Ok(Box::new(LoopbackUdpSocket {
+            segment: self.segment.clone(),
+            interface: self.id(),
+            addr,
+            state,
+            ttl: DEFAULT_TTL,
+            broadcast: false,
+        }))
+    }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let mut segment = self.segment.lock().unwrap();
+        segment.check_local(self.id(), addr.ip())?;
+        let ip = segment.source_ip(self.id(), addr.ip(), peer.ip())?;
+        let listener = segment
+            .route(self.id(), peer.ip())
+            .and_then(|interface| segment.listener(interface, peer))
+            .ok_or(NetworkError::ConnectionRefused)?;
+        let port = match addr.port() {
+            0 => segment.ephemeral_port(self.id(), ip)?,
+            port => port,
+        };
+        let local = SocketAddr::new(ip, port);
+        drop(segment);
+
+        let connection = Arc::new(Mutex::new(TcpConnection::new()));
+        let client = LoopbackTcpSocket::new(connection.clone(), 0, local, peer);
+        let server = LoopbackTcpSocket::new(connection, 1, peer, local);
+
+        let mut listener = listener.lock().unwrap();
+        if listener.backlog.len() >= MAX_BACKLOG {
+            return Err(NetworkError::ConnectionRefused);
+        }
+        listener.backlog.push_back((server, local));
+        listener.waiters.notify(InterestType::Readable);
+        Ok(Box::new(client))
+    }
+
+    /// Resolves `localhost` and IP addresses, as there are no DNS servers
+    async fn resolve(
+        &self,
+        host: &str,
+        _port: Option<u16>,
+        _dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        if host.eq_ignore_ascii_case("localhost") {
+            return Ok(vec![
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ]);
+        }
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        Ok(host.parse::<IpAddr>().into_iter().collect())
+    }
+}
+
+/// The interests waiting on one side of a socket.
+///
+/// Like the sockets of the host, readiness is reported to handlers when it
+/// changes. The changes that happen while there is no handler are kept
+/// until the next one is set.
+#[derive(Derivative, Default)]
+#[derivative(Debug)]
+struct Waiters {
+    #[derivative(Debug = "ignore")]
+    handler: Option<Box<dyn InterestHandler + Send + Sync>>,
+    wakers: Vec<Waker>,
+    triggered: HashSet<InterestType>,
+}
+
+impl Waiters {
+    fn writable() -> Self {
+        Self {
+            triggered: [InterestType::Writable].into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    fn notify(&mut self, interest: InterestType) {
+        match self.handler.as_mut() {
+            Some(handler) => handler.push_interest(interest),
+            None => {
+                self.triggered.insert(interest);
+            }
+        }
+        for waker in self.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    fn set_handler(&mut self, mut handler: Box<dyn InterestHandler + Send + Sync>) {
+        for interest in self.triggered.drain() {
+            handler.push_interest(interest);
+        }
+        self.handler = Some(handler);
+    }
+
+    fn add_waker(&mut self, waker: &Waker) {
+        if !self.wakers.iter().any(|w| w.will_wake(waker)) {
+            self.wakers.push(waker.clone());
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ListenerState {
+    backlog: VecDeque<(LoopbackTcpSocket, SocketAddr)>,
+    waiters: Waiters,
+}
+
+#[derive(Debug)]
+pub struct LoopbackTcpListener {
+    segment: Arc<Mutex<Segment>>,
+    interface: usize,
+    addr: SocketAddr,
+    state: Arc<Mutex<ListenerState>>,
+    ttl: u8,
+}
+
+impl Drop for LoopbackTcpListener {
+    fn drop(&mut self) {
+        if let Ok(mut segment) = self.segment.lock() {
+            segment.listeners.remove(&(self.interface, self.addr));
+        }
+    }
+}
+
+impl VirtualTcpListener for LoopbackTcpListener {
+    fn try_accept(&mut self) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        let mut state = self.state.lock().unwrap();
+        match state.backlog.pop_front() {
+            Some((socket, addr)) => Ok((Box::new(socket), addr)),
+            None => Err(NetworkError::WouldBlock),
+        }
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.waiters.set_handler(handler);
+        if !state.backlog.is_empty() {
+            state.waiters.notify(InterestType::Readable);
+        }
+        Ok(())
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    fn set_ttl(&mut self, ttl: u8) -> Result<()> {
+        self.ttl = ttl;
+        Ok(())
+    }
+
+    fn ttl(&self) -> Result<u8> {
+        Ok(self.ttl)
+    }
+}
+
+impl VirtualIoSource for LoopbackTcpListener {
+    fn remove_handler(&mut self) {
+        self.state.lock().unwrap().waiters.handler.take();
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        let mut state = self.state.lock().unwrap();
+        if !state.backlog.is_empty() {
+            return Poll::Ready(Ok(state.backlog.len()));
+        }
+        state.waiters.add_waker(cx.waker());
+        Poll::Pending
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.poll_read_ready(cx)
+    }
+}
+
+/// The bytes flowing in one direction of a connection
+#[derive(Debug)]
+struct Pipe {
+    data: VecDeque<u8>,
+    capacity: usize,
+    /// The writer shut down its side, so the reader gets an end of file once
+    /// the data is drained
+    write_closed: bool,
+    /// The reader shut down its side, so writing fails
+    read_closed: bool,
+}
+
+impl Pipe {
+    fn new() -> Self {
+        Self {
+            data: VecDeque::new(),
+            capacity: DEFAULT_BUFFER_SIZE,
+            write_closed: false,
+            read_closed: false,
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.write_closed && self.read_closed
+    }
+}
+
+/// The state shared by the two sides of a TCP connection. Side `n` reads
+/// from `pipes[n]` and writes to the other pipe.
+#[derive(Debug)]
+struct TcpConnection {
+    pipes: [Pipe; 2],
+    waiters: [Waiters; 2],
+}
+
+impl TcpConnection {
+    fn new() -> Self {
+        Self {
+            pipes: [Pipe::new(), Pipe::new()],
+            waiters: [Waiters::writable(), Waiters::writable()],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LoopbackTcpSocket {
+    connection: Arc<Mutex<TcpConnection>>,
+    side: usize,
+    addr: SocketAddr,
+    peer: SocketAddr,
+    ttl: u32,
+    nodelay: bool,
+    keepalive: bool,
+    dontroute: bool,
+    linger: Option<Duration>,
+    send_buf_size: usize,
+}
+
+impl LoopbackTcpSocket {
+    fn new(
+        connection: Arc<Mutex<TcpConnection>>,
+        side: usize,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> Self {
+        Self {
+            connection,
+            side,
+            addr,
+            peer,
+            ttl: DEFAULT_TTL,
+            nodelay: false,
+            keepalive: false,
+            dontroute: false,
+            linger: None,
+            send_buf_size: DEFAULT_BUFFER_SIZE,
+        }
+    }
+
+    fn peer_side(&self) -> usize {
+        1 - self.side
+    }
+
+    fn shutdown_sides(&mut self, read: bool, write: bool) {
+        let (side, peer) = (self.side, self.peer_side());
+        let mut connection = self.connection.lock().unwrap();
+        if read && !connection.pipes[side].read_closed {
+            connection.pipes[side].read_closed = true;
+            connection.pipes[side].data.clear();
+            connection.waiters[peer].notify(InterestType::Writable);
+        }
+        if write && !connection.pipes[peer].write_closed {
+            connection.pipes[peer].write_closed = true;
+            connection.waiters[peer].notify(InterestType::Readable);
+        }
+        if connection.pipes[side].is_closed() && connection.pipes[peer].is_closed() {
+            connection.waiters[peer].notify(InterestType::Closed);
+        }
+    }
+}
+
+impl Drop for LoopbackTcpSocket {
+    fn drop(&mut self) {
+        self.shutdown_sides(true, true);
+    }
+}
+
+impl VirtualTcpSocket for LoopbackTcpSocket {
+    fn set_recv_buf_size(&mut self, size: usize) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        connection.pipes[self.side].capacity = size.max(1);
+        Ok(())
+    }
+
+    fn recv_buf_size(&self) -> Result<usize> {
+        let connection = self.connection.lock().unwrap();
+        Ok(connection.pipes[self.side].capacity)
+    }
+
+    fn set_send_buf_size(&mut self, size: usize) -> Result<()> {
+        self.send_buf_size = size;
+        Ok(())
+    }
+
+    fn send_buf_size(&self) -> Result<usize> {
+        Ok(self.send_buf_size)
+    }
+
+    fn set_nodelay(&mut self, reuse: bool) -> Result<()> {
+        self.nodelay = reuse;
+        Ok(())
+    }
+
+    fn nodelay(&self) -> Result<bool> {
+        Ok(self.nodelay)
+    }
+
+    fn set_keepalive(&mut self, keepalive: bool) -> Result<()> {
+        self.keepalive = keepalive;
+        Ok(())
+    }
+
+    fn keepalive(&self) -> Result<bool> {
+        Ok(self.keepalive)
+    }
+
+    fn set_dontroute(&mut self, keepalive: bool) -> Result<()> {
+        self.dontroute = keepalive;
+        Ok(())
+    }
+
+    fn dontroute(&self) -> Result<bool> {
+        Ok(self.dontroute)
+    }
+
+    fn addr_peer(&self) -> Result<SocketAddr> {
+        Ok(self.peer)
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+        match how {
+            Shutdown::Read => self.shutdown_sides(true, false),
+            Shutdown::Write => self.shutdown_sides(false, true),
+            Shutdown::Both => self.shutdown_sides(true, true),
+        }
+        Ok(())
+    }
+
+    fn is_closed(&self) -> bool {
+        let connection = self.connection.lock().unwrap();
+        connection.pipes[self.side].read_closed && connection.pipes[self.peer_side()].write_closed
+    }
+}
+
+impl VirtualConnectedSocket for LoopbackTcpSocket {
+    fn set_linger(&mut self, linger: Option<Duration>) -> Result<()> {
+        self.linger = linger;
+        Ok(())
+    }
+
+    fn linger(&self) -> Result<Option<Duration>> {
+        Ok(self.linger)
+    }
+
+    fn try_send(&mut self, data: &[u8]) -> Result<usize> {
+        let (side, peer) = (self.side, self.peer_side());
+        let mut connection = self.connection.lock().unwrap();
+        let pipe = &mut connection.pipes[peer];
+        if pipe.write_closed || pipe.read_closed {
+            return Err(NetworkError::BrokenPipe);
+        }
+        if data.is_empty() {
+            return Ok(0);
+        }
+        let amt = data
+            .len()
+            .min(pipe.capacity.saturating_sub(pipe.data.len()));
+        if amt == 0 {
+            connection.waiters[side]
+                .triggered
+                .remove(&InterestType::Writable);
+            return Err(NetworkError::WouldBlock);
+        }
+        pipe.data.extend(&data[..amt]);
+        connection.waiters[peer].notify(InterestType::Readable);
+        Ok(amt)
+    }
+
+    fn try_flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.shutdown_sides(true, true);
+        Ok(())
+    }
+
+    fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize> {
+        let (side, peer) = (self.side, self.peer_side());
+        let mut connection = self.connection.lock().unwrap();
+        let pipe = &mut connection.pipes[side];
+        if pipe.data.is_empty() {
+            if pipe.write_closed || pipe.read_closed {
+                return Ok(0);
+            }
+            connection.waiters[side]
+                .triggered
+                .remove(&InterestType::Readable);
+            return Err(NetworkError::WouldBlock);
+        }
+        let amt = buf.len().min(pipe.data.len());
+        for (dst, src) in buf.iter_mut().zip(pipe.data.drain(..amt)) {
+            dst.write(src);
+        }
+        connection.waiters[peer].notify(InterestType::Writable);
+        Ok(amt)
+    }
+}
+
+impl VirtualSocket for LoopbackTcpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.ttl = ttl;
+        Ok(())
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        Ok(self.ttl)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        match self.is_closed() {
+            true => Ok(SocketStatus::Closed),
+            false => Ok(SocketStatus::Opened),
+        }
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        let (side, peer) = (self.side, self.peer_side());
+        let mut connection = self.connection.lock().unwrap();
+        let readable = {
+            let pipe = &connection.pipes[side];
+            !pipe.data.is_empty() || pipe.write_closed
+        };
+        let closed = connection.pipes[peer].read_closed;
+        let waiters = &mut connection.waiters[side];
+        waiters.set_handler(handler);
+        if readable {
+            waiters.notify(InterestType::Readable);
+        }
+        if closed {
+            waiters.notify(InterestType::Closed);
+        }
+        Ok(())
+    }
+}
+
+impl VirtualIoSource for LoopbackTcpSocket {
+    fn remove_handler(&mut self) {
+        let mut connection = self.connection.lock().unwrap();
+        connection.waiters[self.side].handler.take();
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        let mut connection = self.connection.lock().unwrap();
+        let pipe = &connection.pipes[self.side];
+        if !pipe.data.is_empty() {
+            return Poll::Ready(Ok(pipe.data.len()));
+        }
+        if pipe.write_closed || pipe.read_closed {
+            return Poll::Ready(Ok(0));
+        }
+        connection.waiters[self.side].add_waker(cx.waker());
+        Poll::Pending
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        let mut connection = self.connection.lock().unwrap();
+        let pipe = &connection.pipes[self.peer_side()];
+        if pipe.write_closed || pipe.read_closed {
+            return Poll::Ready(Ok(0));
+        }
+        let space = pipe.capacity.saturating_sub(pipe.data.len());
+        if space > 0 {
+            return Poll::Ready(Ok(space));
+        }
+        connection.waiters[self.side].add_waker(cx.waker());
+        Poll::Pending
+    }
+}
+
+#[derive(Debug)]
+struct UdpState {
+    datagrams: VecDeque<(Bytes, SocketAddr)>,
+    waiters: Waiters,
+}
+
+impl Default for UdpState {
+    fn default() -> Self {
+        Self {
+            datagrams: VecDeque::new(),
+            waiters: Waiters::writable(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LoopbackUdpSocket {
+    segment: Arc<Mutex<Segment>>,
+    interface: usize,
+    addr: SocketAddr,
+    state: Arc<Mutex<UdpState>>,
+    ttl: u32,
+    broadcast: bool,
+}
+
+impl Drop for LoopbackUdpSocket {
+    fn drop(&mut self) {
+        if let Ok(mut segment) = self.segment.lock() {
+            segment.udp_sockets.remove(&(self.interface, self.addr));
+        }
+    }
+}
+
+impl VirtualUdpSocket for LoopbackUdpSocket {
+    fn set_broadcast(&mut self, broadcast: bool) -> Result<()> {
+        self.broadcast = broadcast;
+        Ok(())
+    }
+
+    fn broadcast(&self) -> Result<bool> {
+        Ok(self.broadcast)
+    }
+
+    fn set_multicast_loop_v4(&mut self, _val: bool) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn multicast_loop_v4(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn set_multicast_loop_v6(&mut self, _val: bool) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn multicast_loop_v6(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn set_multicast_ttl_v4(&mut self, _ttl: u32) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn multicast_ttl_v4(&self) -> Result<u32> {
+        Ok(1)
+    }
+
+    fn join_multicast_v4(&mut self, _multiaddr: Ipv4Addr, _iface: Ipv4Addr) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn leave_multicast_v4(&mut self, _multiaddr: Ipv4Addr, _iface: Ipv4Addr) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn join_multicast_v6(&mut self, _multiaddr: Ipv6Addr, _iface: u32) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn leave_multicast_v6(&mut self, _multiaddr: Ipv6Addr, _iface: u32) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn addr_peer(&self) -> Result<Option<SocketAddr>> {
+        Ok(None)
+    }
+}
+
+impl VirtualConnectionlessSocket for LoopbackUdpSocket {
+    /// Delivers a datagram to the socket bound to the address. Like on a
+    /// real network, datagrams nobody is waiting for are silently dropped.
+    fn try_send_to(&mut self, data: &[u8], addr: SocketAddr) -> Result<usize> {
+        let (source, target) = {
+            let segment = self.segment.lock().unwrap();
+            let ip = segment.source_ip(self.interface, self.addr.ip(), addr.ip())?;
+            let target = segment
+                .route(self.interface, addr.ip())
+                .and_then(|interface| segment.udp_socket(interface, addr));
+            (SocketAddr::new(ip, self.addr.port()), target)
+        };
+        if let Some(target) = target {
+            let mut target = target.lock().unwrap();
+            if target.datagrams.len() < MAX_DATAGRAMS {
+                target
+                    .datagrams
+                    .push_back((Bytes::copy_from_slice(data), source));
+                target.waiters.notify(InterestType::Readable);
+            }
+        }
+        Ok(data.len())
+    }
+
+    fn try_recv_from(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<(usize, SocketAddr)> {
+        let mut state = self.state.lock().unwrap();
+        let (data, addr) = match state.datagrams.pop_front() {
+            Some(datagram) => datagram,
+            None => {
+                state.waiters.triggered.remove(&InterestType::Readable);
+                return Err(NetworkError::WouldBlock);
+            }
+        };
+        // Like on a real socket, the rest of a datagram that does not fit
+        // in the buffer is discarded
+        let amt = buf.len().min(data.len());
+        for (dst, src) in buf.iter_mut().zip(&data[..amt]) {
+            dst.write(*src);
+        }
+        Ok((amt, addr))
+    }
+}
+
+impl VirtualSocket for LoopbackUdpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.ttl = ttl;
+        Ok(())
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        Ok(self.ttl)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        Ok(SocketStatus::Opened)
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.waiters.set_handler(handler);
+        if !state.datagrams.is_empty() {
+            state.waiters.notify(InterestType::Readable);
+        }
+        Ok(())
+    }
+}
+
+impl VirtualIoSource for LoopbackUdpSocket {
+    fn remove_handler(&mut self) {
+        self.state.lock().unwrap().waiters.handler.take();
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        let mut state = self.state.lock().unwrap();
+        if let Some((data, _)) = state.datagrams.front() {
+            return Poll::Ready(Ok(data.len()));
+        }
+        state.waiters.add_waker(cx.waker());
+        Poll::Pending
+    }
+
+    fn poll_write_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        Poll::Ready(Ok(u16::MAX as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{VirtualConnectedSocketExt, VirtualConnectionlessSocketExt, VirtualTcpListenerExt};
+
+    fn read(buf: &[MaybeUninit<u8>], amt: usize) -> Vec<u8> {
+        buf[..amt]
+            .iter()
+            .map(|b| unsafe { b.assume_init() })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn tcp_connections_are_made_in_memory() {
+        let net = LoopbackNetworking::new();
+        let mut listener = net
+            .listen_tcp("127.0.0.1:0".parse().unwrap(), false, false, false)
+            .await
+            .unwrap();
+        let addr = listener.addr_local().unwrap();
+        assert!(EPHEMERAL_PORTS.contains(&addr.port()));
+
+        let mut client = net
+            .connect_tcp("0.0.0.0:0".parse().unwrap(), addr)
+            .await
+            .unwrap();
+        let (mut server, peer) = listener.accept().await.unwrap();
+        assert_eq!(peer, client.addr_local().unwrap());
+        assert_eq!(server.addr_peer().unwrap(), peer);
+        assert_eq!(server.addr_local().unwrap(), addr);
+
+        let mut buf = [MaybeUninit::uninit(); 16];
+        client.send(b"ping").await.unwrap();
+        let amt = server.recv(&mut buf).await.unwrap();
+        assert_eq!(read(&buf, amt), b"ping");
+        server.send(b"pong").await.unwrap();
+        let amt = client.recv(&mut buf).await.unwrap();
+        assert_eq!(read(&buf, amt), b"pong");
+        assert_eq!(server.try_recv(&mut buf), Err(NetworkError::WouldBlock));
+
+        drop(client);
+        assert_eq!(server.recv(&mut buf).await, Ok(0));
+        assert_eq!(server.try_send(b"late"), Err(NetworkError::BrokenPipe));
+
+        drop(listener);
+        let err = net
+            .connect_tcp("0.0.0.0:0".parse().unwrap(), addr)
+            .await
+            .unwrap_err();
+        assert_eq!(err, NetworkError::ConnectionRefused);
+    }
+
+    #[tokio::test]
+    async fn interfaces_reach_each_other_through_their_addresses() {
+        let server_net = LoopbackNetworking::new();
+        let client_net = server_net.new_interface();
+        let server_ip = server_net.dhcp_acquire().await.unwrap();
+        let client_ip = client_net.dhcp_acquire().await.unwrap();
+        assert_eq!(server_ip, vec!["10.0.0.2".parse::<IpAddr>().unwrap()]);
+        assert_eq!(client_ip, vec!["10.0.0.3".parse::<IpAddr>().unwrap()]);
+        assert_eq!(client_net.dhcp_acquire().await.unwrap(), client_ip);
+        let routes = client_net.route_list().await.unwrap();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].via_router, IpAddr::V4(DHCP_GATEWAY));
+
+        let mut listener = server_net
+            .listen_tcp("0.0.0.0:80".parse().unwrap(), false, false, false)
+            .await
+            .unwrap();
+        let client = client_net
+            .connect_tcp("0.0.0.0:0".parse().unwrap(), "10.0.0.2:80".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(client.addr_local().unwrap().ip(), client_ip[0]);
+        let (server, _) = listener.accept().await.unwrap();
+        assert_eq!(
+            server.addr_local().unwrap(),
+            "10.0.0.2:80".parse::<SocketAddr>().unwrap()
+        );
+
+        // The loopback of an interface is private to it
+        let err = client_net
+            .connect_tcp(
+                "0.0.0.0:0".parse().unwrap(),
+                "127.0.0.1:80".parse().unwrap(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err, NetworkError::ConnectionRefused);
+
+        let ip = "192.168.1.10".parse().unwrap();
+        server_net.ip_add(ip, 24).await.unwrap();
+        assert_eq!(
+            client_net.ip_add(ip, 24).await,
+            Err(NetworkError::AddressInUse)
+        );
+        let err = client_net
+            .listen_tcp(SocketAddr::new(ip, 80), false, false, false)
+            .await
+            .unwrap_err();
+        assert_eq!(err, NetworkError::AddressNotAvailable);
+    }
+
+    #[tokio::test]
+    async fn udp_datagrams_are_delivered_in_memory() {
+        let net = LoopbackNetworking::new();
+        net.ip_add("10.1.0.1".parse().unwrap(), 16).await.unwrap();
+        let mut receiver = net
+            .bind_udp("0.0.0.0:5353".parse().unwrap(), false, false)
+            .await
+            .unwrap();
+        let mut sender = net
+            .bind_udp("0.0.0.0:0".parse().unwrap(), false, false)
+            .await
+            .unwrap();
+        let port = sender.addr_local().unwrap().port();
+
+        sender
+            .send_to(b"hello", "10.1.0.1:5353".parse().unwrap())
+            .await
+            .unwrap();
+        sender
+            .send_to(b"nobody", "10.1.0.1:5354".parse().unwrap())
+            .await
+            .unwrap();
+        let mut buf = [MaybeUninit::uninit(); 16];
+        let (amt, from) = receiver.recv_from(&mut buf).await.unwrap();
+        assert_eq!(read(&buf, amt), b"hello");
+        assert_eq!(from, SocketAddr::new("10.1.0.1".parse().unwrap(), port));
+        assert_eq!(
+            receiver.try_recv_from(&mut buf),
+            Err(NetworkError::WouldBlock)
+        );
+
+        let err = net
+            .bind_udp("0.0.0.0:5353".parse().unwrap(), false, false)
+            .await
+            .unwrap_err();
+        assert_eq!(err, NetworkError::AddressInUse);
+        drop(receiver);
+        net.bind_udp("0.0.0.0:5353".parse().unwrap(), false, false)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,782 @@
+//! Networking restricted by allow and deny rules.
+//!
+//! [`PolicyNetworking`] wraps another [`VirtualNetworking`] implementation
+//! and checks every socket against a [`NetworkPolicy`] before it is opened,
+//! as well as every datagram sent by the UDP and ICMP sockets. Denied
+//! operations fail with [`NetworkError::PermissionDenied`].
+
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::{
+    DynVirtualNetworking, InterestHandler, IpCidr, IpRoute, NetworkError, Result, SocketStatus,
+    StreamSecurity, VirtualConnectionlessSocket, VirtualIcmpSocket, VirtualIoSource,
+    VirtualNetworking, VirtualRawSocket, VirtualSocket, VirtualTcpListener, VirtualTcpSocket,
+    VirtualUdpSocket,
+};
+
+/// A protocol a [`NetworkRule`] applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetworkProtocol {
+    Tcp,
+    Udp,
+    Icmp,
+    /// Raw sockets, which read and write Ethernet packets
+    Raw,
+}
+
+/// Whether a socket reaches out to its peers or waits for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetworkAccess {
+    /// Opening TCP connections and sending datagrams, checked against the
+    /// address of the peer
+    Connect,
+    /// Listening for TCP connections, binding UDP sockets to a fixed port
+    /// and binding ICMP sockets, checked against the local address
+    Listen,
+}
+
+/// An operation checked against the rules of a [`NetworkPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkRequest {
+    pub protocol: NetworkProtocol,
+    pub access: NetworkAccess,
+    /// The address of the peer, or the local address for
+    /// [`NetworkAccess::Listen`]. Raw sockets have no address.
+    pub ip: Option<IpAddr>,
+    /// The port of the address. ICMP and raw sockets have no port.
+    pub port: Option<u16>,
+}
+
+impl NetworkRequest {
+    fn new(protocol: NetworkProtocol, access: NetworkAccess, addr: SocketAddr) -> Self {
+        Self {
+            protocol,
+            access,
+            ip: Some(addr.ip()),
+            port: Some(addr.port()),
+        }
+    }
+}
+
+/// A set of sockets, matching the operations that fit all of its criteria.
+///
+/// An empty list of criteria matches everything, so the default rule
+/// matches every operation. A rule with addresses or ports never matches
+/// the operations without them, such as opening a raw socket.
+///
+/// Rules are written as a comma-separated list of protocols (`tcp`, `udp`,
+/// `icmp` and `raw`), accesses (`connect` and `listen`), IP addresses with
+/// an optional prefix length (`10.0.0.0/8`, `::1`) and ports or port ranges
+/// (`port=443`, `port=8000-8999`). A lone `*` is the default rule.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkRule {
+    pub protocols: Vec<NetworkProtocol>,
+    pub access: Vec<NetworkAccess>,
+    pub cidrs: Vec<IpCidr>,
+    /// Inclusive port ranges
+    pub ports: Vec<(u16, u16)>,
+}
+
+impl NetworkRule {
+    /// Returns whether the operation fits all the criteria of this rule.
+    pub fn matches(&self, request: &NetworkRequest) -> bool {
+        if !self.protocols.is_empty() && !self.protocols.contains(&request.protocol) {
+            return false;
+        }
+        if !self.access.is_empty() && !self.access.contains(&request.access) {
+            return false;
+        }
+        if !self.cidrs.is_empty() {
+            match request.ip {
+                Some(ip) if self.cidrs.iter().any(|cidr| cidr_contains(cidr, ip)) => {}
+                _ => return false,
+            }
+        }
+        if !self.ports.is_empty() {
+            match request.port {
+                Some(port)
+                    if self
+                        .ports
+                        .iter()
+                        .any(|(first, last)| (*first..=*last).contains(&port)) => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+fn cidr_contains(cidr: &IpCidr, ip: IpAddr) -> bool {
+    // IPv4 peers can be reached through IPv4-mapped IPv6 addresses
+    let ip = match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        ip => ip,
+    };
+    match (cidr.ip, ip) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) => prefix_matches(
+            u32::from(network).into(),
+            u32::from(ip).into(),
+            cidr.prefix,
+            32,
+        ),
+        (IpAddr::V6(network), IpAddr::V6(ip)) => {
+            prefix_matches(u128::from(network), u128::from(ip), cidr.prefix, 128)
+        }
+        _ => false,
+    }
+}
+
+fn prefix_matches(network: u128, ip: u128, prefix: u8, bits: u32) -> bool {
+    let prefix = (prefix as u32).min(bits);
+    if prefix == 0 {
+        return true;
+    }
+    let shift = bits - prefix;
+    network >> shift == ip >> shift
+}
+
+impl FromStr for NetworkRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut rule = NetworkRule::default();
+        let s = s.trim();
+        if s == "*" {
+            return Ok(rule);
+        }
+        for part in s.split(',').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "tcp" => rule.protocols.push(NetworkProtocol::Tcp),
+                "udp" => rule.protocols.push(NetworkProtocol::Udp),
+                "icmp" => rule.protocols.push(NetworkProtocol::Icmp),
+                "raw" => rule.protocols.push(NetworkProtocol::Raw),
+                "connect" => rule.access.push(NetworkAccess::Connect),
+                "listen" => rule.access.push(NetworkAccess::Listen),
+                "" => return Err(format!("empty criteria in network rule `{}`", s)),
+                _ => match part.strip_prefix("port=") {
+                    Some(ports) => rule.ports.push(parse_port_range(ports)?),
+                    None => rule.cidrs.push(parse_cidr(part)?),
+                },
+            }
+        }
+        Ok(rule)
+    }
+}
+
+fn parse_port_range(s: &str) -> std::result::Result<(u16, u16), String> {
+    let parse = |port: &str| {
+        port.parse::<u16>()
+            .map_err(|_| format!("invalid port `{}`", port))
+    };
+    let (first, last) = match s.split_once('-') {
+        Some((first, last)) => (parse(first)?, parse(last)?),
+        None => (parse(s)?, parse(s)?),
+    };
+    if first > last {
+        return Err(format!("invalid port range `{}`", s));
+    }
+    Ok((first, last))
+}
+
+fn parse_cidr(s: &str) -> std::result::Result<IpCidr, String> {
+    let invalid = || {
+        format!(
+            "invalid network rule criteria `{}`, expected a protocol, `connect`, `listen`, \
+             an IP address or `port=<port>`",
+            s
+        )
+    };
+    let (ip, prefix) = match s.split_once('/') {
+        Some((ip, prefix)) => (ip, Some(prefix)),
+        None => (s, None),
+    };
+    let ip = ip.parse::<IpAddr>().map_err(|_| invalid())?;
+    let max_prefix = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => match prefix.parse::<u8>() {
+            Ok(prefix) if prefix <= max_prefix => prefix,
+            _ => return Err(format!("invalid prefix length in `{}`", s)),
+        },
+        None => max_prefix,
+    };
+    Ok(IpCidr { ip, prefix })
+}
+
+impl fmt::Display for NetworkRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        for protocol in &self.protocols {
+            parts.push(
+                match protocol {
+                    NetworkProtocol::Tcp => "tcp",
+                    NetworkProtocol::Udp => "udp",
+                    NetworkProtocol::Icmp => "icmp",
+                    NetworkProtocol::Raw => "raw",
+                }
+                .to_string(),
+            );
+        }
+        for access in &self.access {
+            parts.push(
+                match access {
+                    NetworkAccess::Connect => "connect",
+                    NetworkAccess::Listen => "listen",
+                }
+                .to_string(),
+            );
+        }
+        for cidr in &self.cidrs {
+            parts.push(format!("{}/{}", cidr.ip, cidr.prefix));
+        }
+        for (first, last) in &self.ports {
+            if first == last {
+                parts.push(format!("port={}", first));
+            } else {
+                parts.push(format!("port={}-{}", first, last));
+            }
+        }
+        if parts.is_empty() {
+            write!(f, "*")
+        } else {
+            write!(f, "{}", parts.join(","))
+        }
+    }
+}
+
+/// The rules deciding which sockets are allowed.
+///
+/// An operation is denied if it matches any of the deny rules. Otherwise it
+/// is allowed if there are no allow rules, or if it matches one of them.
+///
+/// Raw sockets are the exception: the packets they send carry their own
+/// addresses and would bypass every other rule, so a restricted policy
+/// only opens them when an allow rule names the `raw` protocol explicitly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkPolicy {
+    pub allow: Vec<NetworkRule>,
+    pub deny: Vec<NetworkRule>,
+}
+
+impl NetworkPolicy {
+    pub fn new(allow: Vec<NetworkRule>, deny: Vec<NetworkRule>) -> Self {
+        Self { allow, deny }
+    }
+
+    /// Returns true if the policy allows every operation.
+    pub fn is_unrestricted(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Returns whether the operation is allowed by the policy.
+    pub fn allows(&self, request: &NetworkRequest) -> bool {
+        !self.deny.iter().any(|rule| rule.matches(request))
+            && (self.allow.is_empty() || self.allow.iter().any(|rule| rule.matches(request)))
+    }
+
+    /// Returns whether the policy allows opening raw sockets.
+    pub fn allows_raw(&self) -> bool {
+        if self.is_unrestricted() {
+            return true;
+        }
+        let request = NetworkRequest {
+            protocol: NetworkProtocol::Raw,
+            access: NetworkAccess::Connect,
+            ip: None,
+            port: None,
+        };
+        !self.deny.iter().any(|rule| rule.matches(&request))
+            && self.allow.iter().any(|rule| {
+                rule.protocols.contains(&NetworkProtocol::Raw) && rule.matches(&request)
+            })
+    }
+
+    fn check(&self, request: NetworkRequest) -> Result<()> {
+        if self.allows(&request) {
+            Ok(())
+        } else {
+            tracing::debug!(?request, "network operation denied by the policy");
+            Err(NetworkError::PermissionDenied)
+        }
+    }
+}
+
+/// Networking that only opens the sockets allowed by a [`NetworkPolicy`].
+///
+/// The management of the interface (addresses, routes, bridging) and DNS
+/// resolution are passed through unchecked.
+#[derive(Debug)]
+pub struct PolicyNetworking {
+    inner: DynVirtualNetworking,
+    policy: Arc<NetworkPolicy>,
+}
+
+impl PolicyNetworking {
+    pub fn new(inner: DynVirtualNetworking, policy: NetworkPolicy) -> Self {
+        Self {
+            inner,
+            policy: Arc::new(policy),
+        }
+    }
+
+    pub fn policy(&self) -> &NetworkPolicy {
+        &self.policy
+    }
+}
+
+#[async_trait::async_trait]
+impl VirtualNetworking for PolicyNetworking {
+    async fn bridge(
+        &self,
+        network: &str,
+        access_token: &str,
+        security: StreamSecurity,
+    ) -> Result<()> {
+        self.inner.bridge(network, access_token, security).await
+    }
+
+    async fn unbridge(&self) -> Result<()> {
+        self.inner.unbridge().await
+    }
+
+    async fn dhcp_acquire(&self) -> Result<Vec<IpAddr>> {
+        self.inner.dhcp_acquire().await
+    }
+
+    async fn ip_add(&self, ip: IpAddr, prefix: u8) -> Result<()> {
+        self.inner.ip_add(ip, prefix).await
+    }
+
+    async fn ip_remove(&self, ip: IpAddr) -> Result<()> {
+        self.inner.ip_remove(ip).await
+    }
+
+    async fn ip_clear(&self) -> Result<()> {
+        self.inner.ip_clear().await
+    }
+
+    async fn ip_list(&self) -> Result<Vec<IpCidr>> {
+        self.inner.ip_list().await
+    }
+
+    async fn mac(&self) -> Result<[u8; 6]> {
+        self.inner.mac().await
+    }
+
+    async fn gateway_set(&self, ip: IpAddr) -> Result<()> {
+        self.inner.gateway_set(ip).await
+    }
+
+    async fn route_add(
+        &self,
+        cidr: IpCidr,
+        via_router: IpAddr,
+        preferred_until: Option<Duration>,
+        expires_at: Option<Duration>,
+    ) -> Result<()> {
+        self.inner
+            .route_add(cidr, via_router, preferred_until, expires_at)
+            .await
+    }
+
+    async fn route_remove(&self, cidr: IpAddr) -> Result<()> {
+        self.inner.route_remove(cidr).await
+    }
+
+    async fn route_clear(&self) -> Result<()> {
+        self.inner.route_clear().await
+    }
+
+    async fn route_list(&self) -> Result<Vec<IpRoute>> {
+        self.inner.route_list().await
+    }
+
+    async fn bind_raw(&self) -> Result<Box<dyn VirtualRawSocket + Sync>> {
+        if !self.policy.allows_raw() {
+            tracing::debug!("raw socket denied by the policy");
+            return Err(NetworkError::PermissionDenied);
+        }
+        self.inner.bind_raw().await
+    }
+
+    async fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        self.policy.check(NetworkRequest::new(
+            NetworkProtocol::Tcp,
+            NetworkAccess::Listen,
+            addr,
+        ))?;
+        self.inner
+            .listen_tcp(addr, only_v6, reuse_port, reuse_addr)
+            .await
+    }
+
+    async fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualUdpSocket + Sync>> {
+        // Clients bind their UDP sockets to an ephemeral port, so only the
+        // sockets bound to a fixed port listen; the datagrams sent by all of
+        // them are checked as connections.
+        if addr.port() != 0 {
+            self.policy.check(NetworkRequest::new(
+                NetworkProtocol::Udp,
+                NetworkAccess::Listen,
+                addr,
+            ))?;
+        }
+        let socket = self.inner.bind_udp(addr, reuse_port, reuse_addr).await?;
+        Ok(Box::new(PolicyUdpSocket {
+            inner: socket,
+            policy: self.policy.clone(),
+        }))
+    }
+
+    async fn bind_icmp(&self, addr: IpAddr) -> Result<Box<dyn VirtualIcmpSocket + Sync>> {
+        self.policy.check(NetworkRequest {
+            protocol: NetworkProtocol::Icmp,
+            access: NetworkAccess::Listen,
+            ip: Some(addr),
+            port: None,
+        })?;
+        let socket = self.inner.bind_icmp(addr).await?;
+        Ok(Box::new(PolicyIcmpSocket {
+            inner: socket,
+            policy: self.policy.clone(),
+        }))
+    }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        self.policy.check(NetworkRequest::new(
+            NetworkProtocol::Tcp,
+            NetworkAccess::Connect,
+            peer,
+        ))?;
+        self.inner.connect_tcp(addr, peer).await
+    }
+
+    async fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        self.inner.resolve(host, port, dns_server).await
+    }
+}
+
+/// A UDP socket whose datagrams are checked against the policy.
+#[derive(Debug)]
+struct PolicyUdpSocket {
+    inner: Box<dyn VirtualUdpSocket + Sync>,
+    policy: Arc<NetworkPolicy>,
+}
+
+impl VirtualIoSource for PolicyUdpSocket {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualSocket for PolicyUdpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+}
+
+impl VirtualConnectionlessSocket for PolicyUdpSocket {
+    fn try_send_to(&mut self, data: &[u8], addr: SocketAddr) -> Result<usize> {
+        self.policy.check(NetworkRequest::new(
+            NetworkProtocol::Udp,
+            NetworkAccess::Connect,
+            addr,
+        ))?;
+        self.inner.try_send_to(data, addr)
+    }
+
+    fn try_recv_from(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<(usize, SocketAddr)> {
+        self.inner.try_recv_from(buf)
+    }
+}
+
+impl VirtualUdpSocket for PolicyUdpSocket {
+    fn set_broadcast(&mut self, broadcast: bool) -> Result<()> {
+        self.inner.set_broadcast(broadcast)
+    }
+
+    fn broadcast(&self) -> Result<bool> {
+        self.inner.broadcast()
+    }
+
+    fn set_multicast_loop_v4(&mut self, val: bool) -> Result<()> {
+        self.inner.set_multicast_loop_v4(val)
+    }
+
+    fn multicast_loop_v4(&self) -> Result<bool> {
+        self.inner.multicast_loop_v4()
+    }
+
+    fn set_multicast_loop_v6(&mut self, val: bool) -> Result<()> {
+        self.inner.set_multicast_loop_v6(val)
+    }
+
+    fn multicast_loop_v6(&self) -> Result<bool> {
+        self.inner.multicast_loop_v6()
+    }
+
+    fn set_multicast_ttl_v4(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_multicast_ttl_v4(ttl)
+    }
+
+    fn multicast_ttl_v4(&self) -> Result<u32> {
+        self.inner.multicast_ttl_v4()
+    }
+
+    fn join_multicast_v4(&mut self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<()> {
+        self.inner.join_multicast_v4(multiaddr, iface)
+    }
+
+    fn leave_multicast_v4(&mut self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<()> {
+        self.inner.leave_multicast_v4(multiaddr, iface)
+    }
+
+    fn join_multicast_v6(&mut self, multiaddr: Ipv6Addr, iface: u32) -> Result<()> {
+        self.inner.join_multicast_v6(multiaddr, iface)
+    }
+
+    fn leave_multicast_v6(&mut self, multiaddr: Ipv6Addr, iface: u32) -> Result<()> {
+        self.inner.leave_multicast_v6(multiaddr, iface)
+    }
+
+    fn addr_peer(&self) -> Result<Option<SocketAddr>> {
+        self.inner.addr_peer()
+    }
+}
+
+/// An ICMP socket whose packets are checked against the policy.
+#[derive(Debug)]
+struct PolicyIcmpSocket {
+    inner: Box<dyn VirtualIcmpSocket + Sync>,
+    policy: Arc<NetworkPolicy>,
+}
+
+impl VirtualIoSource for PolicyIcmpSocket {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualSocket for PolicyIcmpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+}
+
+impl VirtualConnectionlessSocket for PolicyIcmpSocket {
+    fn try_send_to(&mut self, data: &[u8], addr: SocketAddr) -> Result<usize> {
+        self.policy.check(NetworkRequest {
+            protocol: NetworkProtocol::Icmp,
+            access: NetworkAccess::Connect,
+            ip: Some(addr.ip()),
+            port: None,
+        })?;
+        self.inner.try_send_to(data, addr)
+    }
+
+    fn try_recv_from(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<(usize, SocketAddr)> {
+        self.inner.try_recv_from(buf)
+    }
+}
+
+impl VirtualIcmpSocket for PolicyIcmpSocket {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UnsupportedVirtualNetworking;
+
+    fn request(protocol: NetworkProtocol, access: NetworkAccess, addr: &str) -> NetworkRequest {
+        NetworkRequest::new(protocol, access, addr.parse().unwrap())
+    }
+
+    #[test]
+    fn rules_are_parsed() {
+        let rule: NetworkRule = "tcp, connect,10.0.0.0/8,::1,port=443,port=8000-8999"
+            .parse()
+            .unwrap();
+        assert_eq!(rule.protocols, vec![NetworkProtocol::Tcp]);
+        assert_eq!(rule.access, vec![NetworkAccess::Connect]);
+        assert_eq!(
+            rule.cidrs,
+            vec![
+                IpCidr {
+                    ip: "10.0.0.0".parse().unwrap(),
+                    prefix: 8
+                },
+                IpCidr {
+                    ip: "::1".parse().unwrap(),
+                    prefix: 128
+                },
+            ]
+        );
+        assert_eq!(rule.ports, vec![(443, 443), (8000, 8999)]);
+        assert_eq!(
+            rule.to_string(),
+            "tcp,connect,10.0.0.0/8,::1/128,port=443,port=8000-8999"
+        );
+        assert_eq!("*".parse::<NetworkRule>().unwrap(), NetworkRule::default());
+
+        assert!("tcp,,udp".parse::<NetworkRule>().is_err());
+        assert!("sctp".parse::<NetworkRule>().is_err());
+        assert!("10.0.0.0/33".parse::<NetworkRule>().is_err());
+        assert!("port=90-80".parse::<NetworkRule>().is_err());
+    }
+
+    #[test]
+    fn rules_match_all_their_criteria() {
+        let rule: NetworkRule = "tcp,connect,10.0.0.0/8,port=80-90".parse().unwrap();
+        let tcp = NetworkProtocol::Tcp;
+        let connect = NetworkAccess::Connect;
+        assert!(rule.matches(&request(tcp, connect, "10.1.2.3:80")));
+        assert!(rule.matches(&request(tcp, connect, "[::ffff:10.1.2.3]:90")));
+        assert!(!rule.matches(&request(tcp, connect, "11.1.2.3:80")));
+        assert!(!rule.matches(&request(tcp, connect, "10.1.2.3:91")));
+        assert!(!rule.matches(&request(tcp, NetworkAccess::Listen, "10.1.2.3:80")));
+        assert!(!rule.matches(&request(NetworkProtocol::Udp, connect, "10.1.2.3:80")));
+
+        let raw = NetworkRequest {
+            protocol: NetworkProtocol::Raw,
+            access: connect,
+            ip: None,
+            port: None,
+        };
+        assert!(!"10.0.0.0/8".parse::<NetworkRule>().unwrap().matches(&raw));
+        assert!(NetworkRule::default().matches(&raw));
+    }
+
+    #[test]
+    fn deny_rules_take_precedence() {
+        let policy = NetworkPolicy::new(
+            vec!["connect,10.0.0.0/8".parse().unwrap()],
+            vec!["10.0.0.1".parse().unwrap()],
+        );
+        let tcp = NetworkProtocol::Tcp;
+        let connect = NetworkAccess::Connect;
+        assert!(policy.allows(&request(tcp, connect, "10.0.0.2:80")));
+        assert!(!policy.allows(&request(tcp, connect, "10.0.0.1:80")));
+        assert!(!policy.allows(&request(tcp, connect, "192.168.0.1:80")));
+        assert!(!policy.allows(&request(tcp, NetworkAccess::Listen, "10.0.0.2:80")));
+
+        let policy = NetworkPolicy::new(vec![], vec!["listen".parse().unwrap()]);
+        assert!(policy.allows(&request(tcp, connect, "192.168.0.1:80")));
+        assert!(!policy.allows(&request(tcp, NetworkAccess::Listen, "0.0.0.0:80")));
+    }
+
+    #[test]
+    fn raw_sockets_need_an_explicit_rule() {
+        let policy = |allow: &[&str], deny: &[&str]| {
+            NetworkPolicy::new(
+                allow.iter().map(|rule| rule.parse().unwrap()).collect(),
+                deny.iter().map(|rule| rule.parse().unwrap()).collect(),
+            )
+        };
+        assert!(policy(&[], &[]).allows_raw());
+        assert!(!policy(&[], &["10.0.0.0/8"]).allows_raw());
+        assert!(!policy(&["tcp"], &[]).allows_raw());
+        assert!(!policy(&["*"], &[]).allows_raw());
+        assert!(policy(&["raw"], &["10.0.0.0/8"]).allows_raw());
+        assert!(!policy(&["raw"], &["raw"]).allows_raw());
+    }
+
+    #[tokio::test]
+    async fn denied_sockets_are_not_opened() {
+        let net = PolicyNetworking::new(
+            Arc::new(UnsupportedVirtualNetworking::default()),
+            NetworkPolicy::new(vec!["tcp,connect,127.0.0.1".parse().unwrap()], vec![]),
+        );
+        let local = "0.0.0.0:0".parse().unwrap();
+        let err = net
+            .connect_tcp(local, "127.0.0.1:80".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(err, NetworkError::Unsupported);
+        let err = net
+            .connect_tcp(local, "127.0.0.2:80".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(err, NetworkError::PermissionDenied);
+        let err = net.bind_udp(local, false, false).await.unwrap_err();
+        assert_eq!(err, NetworkError::Unsupported);
+        let err = net
+            .bind_udp("0.0.0.0:53".parse().unwrap(), false, false)
+            .await
+            .unwrap_err();
+        assert_eq!(err, NetworkError::PermissionDenied);
+        let err = net.bind_raw().await.unwrap_err();
+        assert_eq!(err, NetworkError::PermissionDenied);
+    }
+}
@@ -0,0 +1,194 @@
+//! Epoch-based interruption.
+//!
+//! The `EpochInterruption` middleware adds two globals to a module,
+//! exported as [`EPOCH_GLOBAL_NAME`] and [`EPOCH_DEADLINE_GLOBAL_NAME`],
+//! and makes the compiled code call the epoch deadline builtin whenever
+//! the first one reaches the second one. When the module is instantiated,
+//! those globals are bound to the epoch of the engine and to the deadline
+//! of the store, so that bumping the epoch from another thread interrupts
+//! the running code.
+
+use crate::store::{InternalStoreHandle, StoreObjects};
+use crate::trap::{Trap, TrapCode};
+use crate::vmcontext::VMGlobalDefinition;
+use crate::VMGlobal;
+use std::cell::UnsafeCell;
+use std::error::Error;
+use std::fmt;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use wasmer_types::entity::BoxedSlice;
+use wasmer_types::{ExportIndex, LocalGlobalIndex, ModuleInfo};
+
+/// The name of the exported global holding the epoch of the engine.
+pub const EPOCH_GLOBAL_NAME: &str = "wasmer_epoch";
+
+/// The name of the exported global holding the epoch deadline of the store.
+pub const EPOCH_DEADLINE_GLOBAL_NAME: &str = "wasmer_epoch_deadline";
+
+/// The callback invoked when the epoch deadline of a store is reached.
+///
+/// It returns the number of ticks after which the deadline is reached
+/// again, or an error that is raised as a trap.
+pub type EpochDeadlineCallback =
+    Box<dyn FnMut() -> Result<u64, Box<dyn Error + Send + Sync>> + Send + Sync>;
+
+/// The epoch counter of an engine, shared with all of its stores.
+pub struct VMEpoch {
+    definition: Box<UnsafeCell<VMGlobalDefinition>>,
+}
+
+// The definition is only accessed atomically, by the host and by the
+// generated code.
+unsafe impl Send for VMEpoch {}
+unsafe impl Sync for VMEpoch {}
+
+impl VMEpoch {
+    /// Creates an epoch counter starting at 0.
+    pub fn new() -> Self {
+        Self {
+            definition: Box::new(UnsafeCell::new(VMGlobalDefinition::new())),
+        }
+    }
+
+    fn value(&self) -> &AtomicU64 {
+        // The `i64` of a global definition is at its very start, and the
+        // definition is aligned on 16 bytes.
+        unsafe { &*(self.definition.get() as *const AtomicU64) }
+    }
+
+    /// Returns the current epoch.
+    pub fn current(&self) -> u64 {
+        self.value().load(Ordering::Relaxed)
+    }
+
+    /// Increments the epoch, returning the new one.
+    pub fn increment(&self) -> u64 {
+        self.value().fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    fn definition(&self) -> NonNull<VMGlobalDefinition> {
+        unsafe { NonNull::new_unchecked(self.definition.get()) }
+    }
+}
+
+impl Default for VMEpoch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for VMEpoch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VMEpoch")
+            .field("current", &self.current())
+            .finish()
+    }
+}
+
+/// The epoch deadline of a store.
+pub struct VMEpochDeadline {
+    epoch: Arc<VMEpoch>,
+    deadline: Box<UnsafeCell<VMGlobalDefinition>>,
+    callback: Option<EpochDeadlineCallback>,
+}
+
+impl VMEpochDeadline {
+    /// Creates a deadline that is never reached, following the given epoch.
+    pub fn new(epoch: Arc<VMEpoch>) -> Self {
+        let deadline = Self {
+            epoch,
+            deadline: Box::new(UnsafeCell::new(VMGlobalDefinition::new())),
+            callback: None,
+        };
+        deadline.set(u64::MAX);
+        deadline
+    }
+
+    /// Returns the epoch this deadline follows.
+    pub fn epoch(&self) -> &Arc<VMEpoch> {
+        &self.epoch
+    }
+
+    /// Returns the epoch at which the deadline is reached.
+    pub fn deadline(&self) -> u64 {
+        unsafe { (*self.deadline.get()).val.u64 }
+    }
+
+    fn set(&self, deadline: u64) {
+        unsafe {
+            (*self.deadline.get()).val.u64 = deadline;
+        }
+    }
+
+    /// Sets the deadline `ticks` epochs after the current one.
+    pub fn set_ticks(&mut self, ticks: u64) {
+        self.set(self.epoch.current().saturating_add(ticks));
+    }
+
+    /// Sets the callback invoked when the deadline is reached. Without a
+    /// callback, reaching the deadline traps with `TrapCode::Interrupt`.
+    pub fn set_callback(&mut self, callback: Option<EpochDeadlineCallback>) {
+        self.callback = callback;
+    }
+
+    /// Called by the generated code once the deadline was reached.
+    pub(crate) fn reached(&mut self) -> Result<(), Trap> {
+        match self.callback.as_mut() {
+            None => Err(Trap::lib(TrapCode::Interrupt)),
+            Some(callback) => {
+                let ticks = callback().map_err(Trap::user)?;
+                self.set_ticks(ticks);
+                Ok(())
+            }
+        }
+    }
+
+    /// Binds the epoch globals of a new instance to this deadline and to
+    /// its epoch.
+    pub(crate) fn bind_globals(
+        context: &mut StoreObjects,
+        module: &ModuleInfo,
+        globals: &BoxedSlice<LocalGlobalIndex, InternalStoreHandle<VMGlobal>>,
+    ) {
+        let bindings = [
+            (
+                EPOCH_GLOBAL_NAME,
+                context.epoch_deadline().epoch.definition(),
+            ),
+            (EPOCH_DEADLINE_GLOBAL_NAME, unsafe {
+                NonNull::new_unchecked(context.epoch_deadline().deadline.get())
+            }),
+        ];
+        for (name, definition) in bindings {
+            let local_index = match module.exports.get(name) {
+                Some(ExportIndex::Global(index)) => module.local_global_index(*index),
+                _ => None,
+            };
+            if let Some(local_index) = local_index {
+                let global_type = module.globals[module.global_index(local_index)];
+                // The definitions outlive the instance, as they are owned by
+                // the store and the engine.
+                *globals[local_index].get_mut(context) =
+                    unsafe { VMGlobal::from_definition(global_type, definition) };
+            }
+        }
+    }
+}
+
+impl Default for VMEpochDeadline {
+    fn default() -> Self {
+        Self::new(Arc::new(VMEpoch::new()))
+    }
+}
+
+impl fmt::Debug for VMEpochDeadline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VMEpochDeadline")
+            .field("epoch", &self.epoch)
+            .field("deadline", &self.deadline())
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
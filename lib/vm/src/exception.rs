@@ -0,0 +1,86 @@
+use crate::store::InternalStoreHandle;
+use std::error::Error;
+use std::fmt;
+use wasmer_types::{RawValue, TagType};
+
+/// An exception tag instance.
+///
+/// Tags are compared by identity: an exception is only caught by a handler
+/// referring to the very tag instance it was thrown with.
+#[derive(Debug)]
+pub struct VMTag {
+    ty: TagType,
+}
+
+impl VMTag {
+    /// Create a new tag for exceptions carrying values of the given type.
+    pub fn new(tag_type: TagType) -> Self {
+        Self { ty: tag_type }
+    }
+
+    /// Get the type of the tag.
+    pub fn ty(&self) -> &TagType {
+        &self.ty
+    }
+}
+
+/// A WebAssembly exception.
+///
+/// While an exception unwinds the stack it travels as the error of a user
+/// trap, so that it can cross host frames like any other error. Compiled code
+/// that catches it keeps it in the instance until the handler is done with it.
+#[derive(Clone)]
+pub struct VMException {
+    tag: InternalStoreHandle<VMTag>,
+    payload: Box<[RawValue]>,
+}
+
+// The payload only holds plain values and references to objects of the
+// store the exception was created in.
+unsafe impl Send for VMException {}
+unsafe impl Sync for VMException {}
+
+impl VMException {
+    /// Create a new exception with the given tag and payload.
+    pub fn new(tag: InternalStoreHandle<VMTag>, payload: Box<[RawValue]>) -> Self {
+        Self { tag, payload }
+    }
+
+    /// The tag the exception was thrown with.
+    pub fn tag(&self) -> InternalStoreHandle<VMTag> {
+        self.tag
+    }
+
+    /// The values carried by the exception.
+    pub fn payload(&self) -> &[RawValue] {
+        &self.payload
+    }
+
+    /// Find the exception carried by an error, if any, looking through the
+    /// chain of error sources.
+    pub fn find<'a>(mut err: &'a (dyn Error + 'static)) -> Option<&'a Self> {
+        loop {
+            if let Some(exception) = err.downcast_ref::<Self>() {
+                return Some(exception);
+            }
+            err = err.source()?;
+        }
+    }
+}
+
+impl fmt::Debug for VMException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VMException")
+            .field("tag", &self.tag)
+            .field("payload_len", &self.payload.len())
+            .finish()
+    }
+}
+
+impl fmt::Display for VMException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "uncaught WebAssembly exception")
+    }
+}
+
+impl Error for VMException {}
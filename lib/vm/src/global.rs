@@ -25,6 +25,22 @@ impl VMGlobal {
         }
     }
 
+    /// Create a global whose definition is stored outside of it.
+    ///
+    /// # Safety
+    ///
+    /// `definition` must stay valid for as long as the global is used by the
+    /// generated code, and it is not freed when the global is dropped.
+    pub unsafe fn from_definition(
+        global_type: GlobalType,
+        definition: NonNull<VMGlobalDefinition>,
+    ) -> Self {
+        Self {
+            ty: global_type,
+            vm_global_definition: MaybeInstanceOwned::Instance(definition),
+        }
+    }
+
     /// Get the type of the global.
     pub fn ty(&self) -> &GlobalType {
         &self.ty
@@ -1,4 +1,5 @@
 use super::{Instance, VMInstance};
+use crate::pooling::PoolSlot;
 use crate::vmcontext::VMTableDefinition;
 use crate::VMMemoryDefinition;
 use std::alloc::{self, Layout};
@@ -42,13 +43,18 @@ pub struct InstanceAllocator {
     /// `instance_ptr` buffer. If it has not when being dropped,
     /// the buffer should be freed.
     consumed: bool,
+
+    /// The pool slot holding the `instance_ptr` buffer, if it was
+    /// allocated by a `PoolingInstanceAllocator` rather than `alloc`.
+    slot: Option<PoolSlot>,
 }
 
 impl Drop for InstanceAllocator {
     fn drop(&mut self) {
-        if !self.consumed {
+        if !self.consumed && self.slot.is_none() {
             // If `consumed` has not been set, then we still have ownership
-            // over the buffer and must free it.
+            // over the buffer and must free it. A pool slot is returned to
+            // its pool when dropped.
             let instance_ptr = self.instance_ptr.as_ptr();
 
             unsafe {
@@ -75,8 +81,7 @@ impl InstanceAllocator {
         Vec<NonNull<VMMemoryDefinition>>,
         Vec<NonNull<VMTableDefinition>>,
     ) {
-        let offsets = VMOffsets::new(mem::size_of::<usize>() as u8, module);
-        let instance_layout = Self::instance_layout(&offsets);
+        let (offsets, instance_layout) = Self::layout(module);
 
         #[allow(clippy::cast_ptr_alignment)]
         let instance_ptr = unsafe { alloc::alloc(instance_layout) as *mut Instance };
@@ -87,11 +92,44 @@ impl InstanceAllocator {
             alloc::handle_alloc_error(instance_layout);
         };
 
+        Self::with_buffer(instance_ptr, instance_layout, offsets, None)
+    }
+
+    /// Like [`InstanceAllocator::new`], but the instance data is stored in
+    /// a slot of a `PoolingInstanceAllocator`, which must be large enough
+    /// for `instance_layout`.
+    pub(crate) fn in_slot(
+        offsets: VMOffsets,
+        instance_layout: Layout,
+        slot: PoolSlot,
+    ) -> (
+        Self,
+        Vec<NonNull<VMMemoryDefinition>>,
+        Vec<NonNull<VMTableDefinition>>,
+    ) {
+        debug_assert!(instance_layout.size() <= slot.len());
+        // Slots are page-aligned.
+        #[allow(clippy::cast_ptr_alignment)]
+        let instance_ptr = NonNull::new(slot.as_mut_ptr() as *mut Instance).unwrap();
+        Self::with_buffer(instance_ptr, instance_layout, offsets, Some(slot))
+    }
+
+    fn with_buffer(
+        instance_ptr: NonNull<Instance>,
+        instance_layout: Layout,
+        offsets: VMOffsets,
+        slot: Option<PoolSlot>,
+    ) -> (
+        Self,
+        Vec<NonNull<VMMemoryDefinition>>,
+        Vec<NonNull<VMTableDefinition>>,
+    ) {
         let allocator = Self {
             instance_ptr,
             instance_layout,
             offsets,
             consumed: false,
+            slot,
         };
 
         // # Safety
@@ -104,6 +142,13 @@ impl InstanceAllocator {
         (allocator, memories, tables)
     }
 
+    /// Calculate the offsets and the layout of the [`Instance`] of a module.
+    pub(crate) fn layout(module: &ModuleInfo) -> (VMOffsets, Layout) {
+        let offsets = VMOffsets::new(mem::size_of::<usize>() as u8, module);
+        let instance_layout = Self::instance_layout(&offsets);
+        (offsets, instance_layout)
+    }
+
     /// Calculate the appropriate layout for the [`Instance`].
     fn instance_layout(offsets: &VMOffsets) -> Layout {
         let vmctx_size = usize::try_from(offsets.size_of_vmctx())
@@ -202,12 +247,14 @@ impl InstanceAllocator {
         }
         let instance = self.instance_ptr;
         let instance_layout = self.instance_layout;
+        let slot = self.slot.take();
 
         // This is correct because of the invariants of `Self` and
         // because we write `Instance` to the pointer in this function.
         VMInstance {
             instance,
             instance_layout,
+            slot,
         }
     }
 
@@ -8,11 +8,15 @@
 
 mod allocator;
 
+use crate::epoch::VMEpochDeadline;
+use crate::exception::{VMException, VMTag};
 use crate::export::VMExtern;
 use crate::imports::Imports;
+use crate::memory_image::MemoryImages;
+use crate::pooling::PoolSlot;
 use crate::store::{InternalStoreHandle, StoreObjects};
 use crate::table::TableElement;
-use crate::trap::{catch_traps, Trap, TrapCode};
+use crate::trap::{catch_traps, on_host_stack, Trap, TrapCode};
 use crate::vmcontext::{
     memory32_atomic_check32, memory32_atomic_check64, memory_copy, memory_fill,
     VMBuiltinFunctionsArray, VMCallerCheckedAnyfunc, VMContext, VMFunctionContext,
@@ -27,18 +31,20 @@ use memoffset::offset_of;
 use more_asserts::assert_lt;
 use std::alloc::Layout;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::fmt;
 use std::mem;
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr::{self, NonNull};
 use std::slice;
 use std::sync::Arc;
 use wasmer_types::entity::{packed_option::ReservedValue, BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     DataIndex, DataInitializer, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, GlobalInit,
-    LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryError,
-    MemoryIndex, ModuleInfo, Pages, SignatureIndex, TableIndex, TableInitializer, VMOffsets,
+    LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, LocalTagIndex,
+    MemoryError, MemoryIndex, ModuleInfo, Pages, RawValue, SignatureIndex, TableIndex,
+    TableInitializer, TagIndex, VMOffsets,
 };
 
 /// A WebAssembly instance.
@@ -68,6 +74,9 @@ pub(crate) struct Instance {
     /// WebAssembly global data.
     globals: BoxedSlice<LocalGlobalIndex, InternalStoreHandle<VMGlobal>>,
 
+    /// WebAssembly exception tags (imported and local).
+    tags: BoxedSlice<TagIndex, InternalStoreHandle<VMTag>>,
+
     /// Pointers to functions in executable memory.
     functions: BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>,
 
@@ -90,6 +99,18 @@ pub(crate) struct Instance {
     /// will point to elements here for functions imported by this instance.
     imported_funcrefs: BoxedSlice<FunctionIndex, NonNull<VMCallerCheckedAnyfunc>>,
 
+    /// Exceptions caught by compiled code and not yet released by their
+    /// handler, keyed by the handle compiled code refers to them with.
+    exceptions: HashMap<u32, VMException>,
+
+    /// The handle given to the next caught exception.
+    next_exception: u32,
+
+    /// The trap handler and stack size used to run calls that may throw
+    /// exceptions caught by this instance.
+    trap_handler: Option<*const TrapHandlerFn<'static>>,
+    wasm_stack_size: Option<usize>,
+
     /// Additional context used by compiled WebAssembly code. This
     /// field is last, and represents a dynamically-sized array that
     /// extends beyond the nominal end of the struct (similar to a
@@ -398,12 +419,13 @@ impl Instance {
     /// Grow memory by the specified amount of pages.
     ///
     /// Returns `None` if memory can't be grown by the specified amount
-    /// of pages.
+    /// of pages, and an error if the resource limiter of the store raised
+    /// one.
     pub(crate) fn memory_grow<IntoPages>(
         &mut self,
         memory_index: LocalMemoryIndex,
         delta: IntoPages,
-    ) -> Result<Pages, MemoryError>
+    ) -> Result<Result<Pages, MemoryError>, Trap>
     where
         IntoPages: Into<Pages>,
     {
@@ -411,13 +433,14 @@ impl Instance {
             .memories
             .get(memory_index)
             .unwrap_or_else(|| panic!("no memory for index {}", memory_index.index()));
-        mem.get_mut(self.context_mut()).grow(delta.into())
+        self.context_mut().grow_memory(mem, delta.into())
     }
 
     /// Grow imported memory by the specified amount of pages.
     ///
     /// Returns `None` if memory can't be grown by the specified amount
-    /// of pages.
+    /// of pages, and an error if the resource limiter of the store raised
+    /// one.
     ///
     /// # Safety
     /// This and `imported_memory_size` are currently unsafe because they
@@ -426,13 +449,13 @@ impl Instance {
         &mut self,
         memory_index: MemoryIndex,
         delta: IntoPages,
-    ) -> Result<Pages, MemoryError>
+    ) -> Result<Result<Pages, MemoryError>, Trap>
     where
         IntoPages: Into<Pages>,
     {
         let import = self.imported_memory(memory_index);
         let mem = import.handle;
-        mem.get_mut(self.context_mut()).grow(delta.into())
+        self.context_mut().grow_memory(mem, delta.into())
     }
 
     /// Returns the number of allocated wasm pages.
@@ -477,22 +500,25 @@ impl Instance {
     /// Grow table by the specified amount of elements.
     ///
     /// Returns `None` if table can't be grown by the specified amount
-    /// of elements.
+    /// of elements, and an error if the resource limiter of the store
+    /// raised one.
     pub(crate) fn table_grow(
         &mut self,
         table_index: LocalTableIndex,
         delta: u32,
         init_value: TableElement,
-    ) -> Option<u32> {
+    ) -> Result<Option<u32>, Trap> {
         let table = *self
             .tables
             .get(table_index)
             .unwrap_or_else(|| panic!("no table for index {}", table_index.index()));
-        table.get_mut(self.context_mut()).grow(delta, init_value)
+        self.context_mut().grow_table(table, delta, init_value)
     }
 
     /// Grow table by the specified amount of elements.
     ///
+    /// Returns an error if the resource limiter of the store raised one.
+    ///
     /// # Safety
     /// `table_index` must be a valid, imported table index.
     pub(crate) unsafe fn imported_table_grow(
@@ -500,10 +526,10 @@ impl Instance {
         table_index: TableIndex,
         delta: u32,
         init_value: TableElement,
-    ) -> Option<u32> {
+    ) -> Result<Option<u32>, Trap> {
         let import = self.imported_table(table_index);
         let table = import.handle;
-        table.get_mut(self.context_mut()).grow(delta, init_value)
+        self.context_mut().grow_table(table, delta, init_value)
     }
 
     /// Get table element by index.
@@ -951,6 +977,119 @@ impl Instance {
         let location = NotifyLocation { address: dst };
         Ok(memory.do_notify(location, count))
     }
+
+    /// Create a new exception with the given tag, reading its payload from
+    /// `values`, and return its handle.
+    pub(crate) unsafe fn exception_new(
+        &mut self,
+        tag_index: TagIndex,
+        values: *const RawValue,
+    ) -> u32 {
+        let tag = self.tags[tag_index];
+        let len = tag.get(self.context()).ty().params().len();
+        // The buffer filled by compiled code is not necessarily aligned.
+        let payload = (0..len).map(|i| values.add(i).read_unaligned()).collect();
+        self.insert_exception(VMException::new(tag, payload))
+    }
+
+    /// Keep a caught exception in the instance and return its handle.
+    fn insert_exception(&mut self, exception: VMException) -> u32 {
+        // Handle 0 is reserved to mean "no exception".
+        loop {
+            self.next_exception = self.next_exception.wrapping_add(1);
+            if self.next_exception != 0 && !self.exceptions.contains_key(&self.next_exception) {
+                break;
+            }
+        }
+        self.exceptions.insert(self.next_exception, exception);
+        self.next_exception
+    }
+
+    /// Get a copy of the exception with the given handle, under a new handle.
+    pub(crate) fn exception_clone(&mut self, exception: u32) -> u32 {
+        let exception = self.exceptions[&exception].clone();
+        self.insert_exception(exception)
+    }
+
+    /// Release the exception with the given handle.
+    pub(crate) fn exception_drop(&mut self, exception: u32) {
+        self.exceptions.remove(&exception);
+    }
+
+    /// Called by the generated code once the epoch deadline of the store
+    /// was reached.
+    pub(crate) fn epoch_deadline_reached(&mut self) -> std::thread::Result<Result<(), Trap>> {
+        let epoch_deadline = self.context_mut().epoch_deadline_mut();
+        panic::catch_unwind(AssertUnwindSafe(|| epoch_deadline.reached()))
+    }
+
+    /// Release the exception with the given handle and return it, so that
+    /// it can be thrown out of the instance.
+    pub(crate) fn exception_take(&mut self, exception: u32) -> VMException {
+        self.exceptions
+            .remove(&exception)
+            .expect("invalid exception handle")
+    }
+
+    /// Check whether the exception with the given handle was thrown with
+    /// the given tag. If so, its payload is written to `values`.
+    pub(crate) unsafe fn exception_catch(
+        &self,
+        exception: u32,
+        tag_index: TagIndex,
+        values: *mut RawValue,
+    ) -> bool {
+        let exception = &self.exceptions[&exception];
+        if exception.tag() != self.tags[tag_index] {
+            return false;
+        }
+        for (i, value) in exception.payload().iter().enumerate() {
+            values.add(i).write_unaligned(*value);
+        }
+        true
+    }
+
+    /// Call a function through the trampoline for its signature, catching
+    /// the exceptions it throws.
+    ///
+    /// Returns the handle of the caught exception, or 0 if the call
+    /// returned normally. Any other trap is returned as an error, and a
+    /// panic of a host function is returned as is.
+    pub(crate) unsafe fn exception_invoke(
+        &mut self,
+        sig_index: SignatureIndex,
+        callee: *const VMFunctionBody,
+        callee_vmctx: *mut VMContext,
+        values: *mut RawValue,
+    ) -> std::thread::Result<Result<u32, Trap>> {
+        let trampoline = self.function_call_trampolines[sig_index];
+        let config = VMConfig {
+            wasm_stack_size: self.wasm_stack_size,
+        };
+        let trap_handler = self.trap_handler;
+        // The callee runs on a stack of its own, just like a call made by
+        // the host, so that a trap only unwinds up to this point.
+        let result = on_host_stack(|| {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                catch_traps(trap_handler, &config, || {
+                    trampoline(callee_vmctx, callee, values)
+                })
+            }))
+        })?;
+        Ok(match result {
+            Ok(()) => Ok(0),
+            Err(trap) => {
+                let exception = match &trap {
+                    Trap::User(err) => VMException::find(&**err).cloned(),
+                    _ => None,
+                };
+                match exception {
+                    Some(exception) => Ok(self.insert_exception(exception)),
+                    None => Err(trap),
+                }
+            }
+        })
+    }
 }
 
 /// A handle holding an `Instance` of a WebAssembly module.
@@ -972,6 +1111,11 @@ pub struct VMInstance {
     /// No one in the code has a copy of the `Instance`'s
     /// pointer. `Self` is the only one.
     instance: NonNull<Instance>,
+
+    /// The pool slot holding the `Instance`, if it was allocated by a
+    /// `PoolingInstanceAllocator`. The slot is returned to its pool once
+    /// the `Instance` is dropped.
+    slot: Option<PoolSlot>,
 }
 
 /// VMInstance are created with an InstanceAllocator
@@ -985,7 +1129,9 @@ impl Drop for VMInstance {
             // Need to drop all the actual Instance members
             instance_ptr.drop_in_place();
             // And then free the memory allocated for the Instance itself
-            std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+            if self.slot.is_none() {
+                std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+            }
         }
     }
 }
@@ -1022,14 +1168,23 @@ impl VMInstance {
         finished_memories: BoxedSlice<LocalMemoryIndex, InternalStoreHandle<VMMemory>>,
         finished_tables: BoxedSlice<LocalTableIndex, InternalStoreHandle<VMTable>>,
         finished_globals: BoxedSlice<LocalGlobalIndex, InternalStoreHandle<VMGlobal>>,
+        finished_tags: BoxedSlice<LocalTagIndex, InternalStoreHandle<VMTag>>,
         imports: Imports,
         vmshared_signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
     ) -> Result<Self, Trap> {
+        VMEpochDeadline::bind_globals(context, &module, &finished_globals);
         let vmctx_globals = finished_globals
             .values()
             .map(|m| m.get(context).vmglobal())
             .collect::<PrimaryMap<LocalGlobalIndex, _>>()
             .into_boxed_slice();
+        let tags = imports
+            .tags
+            .values()
+            .chain(finished_tags.values())
+            .copied()
+            .collect::<PrimaryMap<TagIndex, _>>()
+            .into_boxed_slice();
         let passive_data = RefCell::new(
             module
                 .passive_data
@@ -1052,12 +1207,17 @@ impl VMInstance {
                 memories: finished_memories,
                 tables: finished_tables,
                 globals: finished_globals,
+                tags,
                 functions: finished_functions,
                 function_call_trampolines: finished_function_call_trampolines,
                 passive_elements: Default::default(),
                 passive_data,
                 funcrefs,
                 imported_funcrefs,
+                exceptions: HashMap::new(),
+                next_exception: 0,
+                trap_handler: None,
+                wasm_stack_size: None,
                 vmctx: VMContext {},
             };
 
@@ -1148,12 +1308,15 @@ impl VMInstance {
         config: &VMConfig,
         trap_handler: Option<*const TrapHandlerFn<'static>>,
         data_initializers: &[DataInitializer<'_>],
+        memory_images: &MemoryImages,
     ) -> Result<(), Trap> {
         let instance = self.instance_mut();
+        instance.trap_handler = trap_handler;
+        instance.wasm_stack_size = config.wasm_stack_size;
 
         // Apply the initializers.
         initialize_tables(instance)?;
-        initialize_memories(instance, data_initializers)?;
+        initialize_memories(instance, data_initializers, memory_images)?;
 
         // The WebAssembly spec specifies that the start function is
         // invoked automatically at instantiation time.
@@ -1253,6 +1416,7 @@ impl VMInstance {
                 };
                 VMExtern::Global(handle)
             }
+            ExportIndex::Tag(index) => VMExtern::Tag(instance.tags[index]),
         }
     }
 
@@ -1282,7 +1446,9 @@ impl VMInstance {
     where
         IntoPages: Into<Pages>,
     {
-        self.instance_mut().memory_grow(memory_index, delta)
+        self.instance_mut()
+            .memory_grow(memory_index, delta)
+            .unwrap_or_else(|trap| Err(MemoryError::Generic(trap.to_string())))
     }
 
     /// Return the table index for the given `VMTableDefinition` in this instance.
@@ -1302,6 +1468,7 @@ impl VMInstance {
     ) -> Option<u32> {
         self.instance_mut()
             .table_grow(table_index, delta, init_value)
+            .unwrap_or(None)
     }
 
     /// Get table element reference.
@@ -1442,8 +1609,26 @@ fn initialize_passive_elements(instance: &Instance) {
 fn initialize_memories(
     instance: &mut Instance,
     data_initializers: &[DataInitializer<'_>],
+    memory_images: &MemoryImages,
 ) -> Result<(), Trap> {
+    // Map the images of the memories supporting them, whose data
+    // initializers are then skipped.
+    let mut mapped = HashSet::new();
+    for (index, image) in memory_images.iter() {
+        let memory_index = instance.module.memory_index(index);
+        if unsafe {
+            instance
+                .get_vmmemory(memory_index)
+                .initialize_with_image(image)?
+        } {
+            mapped.insert(memory_index);
+        }
+    }
+
     for init in data_initializers {
+        if mapped.contains(&init.location.memory_index) {
+            continue;
+        }
         let memory = instance.get_vmmemory(init.location.memory_index);
 
         let start = get_memory_init_start(init, instance);
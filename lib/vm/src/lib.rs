@@ -21,16 +21,22 @@
 )]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+mod epoch;
+mod exception;
 mod export;
 mod extern_ref;
 mod function_env;
 mod global;
 mod imports;
 mod instance;
+mod limiter;
 mod memory;
+mod memory_image;
 mod mmap;
+mod pooling;
 mod probestack;
 mod sig_registry;
+mod simd;
 mod store;
 mod table;
 mod threadconditions;
@@ -41,17 +47,24 @@ pub mod libcalls;
 
 use std::ptr::NonNull;
 
+pub use crate::epoch::{
+    EpochDeadlineCallback, VMEpoch, VMEpochDeadline, EPOCH_DEADLINE_GLOBAL_NAME, EPOCH_GLOBAL_NAME,
+};
+pub use crate::exception::{VMException, VMTag};
 pub use crate::export::*;
 pub use crate::extern_ref::{VMExternObj, VMExternRef};
 pub use crate::function_env::VMFunctionEnvironment;
 pub use crate::global::*;
 pub use crate::imports::Imports;
 pub use crate::instance::{InstanceAllocator, VMInstance};
+pub use crate::limiter::{ResourceLimiter, ResourceUsage, VMResourceLimiter};
 pub use crate::memory::{
     initialize_memory_with_data, LinearMemory, NotifyLocation, VMMemory, VMOwnedMemory,
     VMSharedMemory,
 };
+pub use crate::memory_image::{MemoryImage, MemoryImages};
 pub use crate::mmap::Mmap;
+pub use crate::pooling::{PoolingConfig, PoolingInstanceAllocator};
 pub use crate::probestack::PROBESTACK;
 pub use crate::sig_registry::SignatureRegistry;
 pub use crate::store::{InternalStoreHandle, MaybeInstanceOwned, StoreHandle, StoreObjects};
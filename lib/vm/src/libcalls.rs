@@ -39,13 +39,14 @@
 
 use crate::probestack::PROBESTACK;
 use crate::table::{RawTableElement, TableElement};
-use crate::trap::{raise_lib_trap, Trap, TrapCode};
+use crate::trap::{raise_lib_trap, raise_user_trap, resume_panic, Trap, TrapCode};
 use crate::vmcontext::VMContext;
-use crate::{on_host_stack, VMFuncRef};
+use crate::{on_host_stack, VMFuncRef, VMFunctionBody};
+use std::panic::{self, AssertUnwindSafe};
 pub use wasmer_types::LibCall;
 use wasmer_types::{
-    DataIndex, ElemIndex, FunctionIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
-    TableIndex, Type,
+    DataIndex, ElemIndex, FunctionIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, RawValue,
+    SignatureIndex, SimdOp, TableIndex, TagIndex, Type,
 };
 
 /// Implementation of f32.ceil
@@ -138,6 +139,17 @@ pub extern "C" fn wasmer_vm_f64_nearest(x: f64) -> f64 {
     }
 }
 
+/// Returns the result of growing a memory or a table, or raises the error
+/// or the panic of the resource limiter of the store.
+unsafe fn grow_result(result: std::thread::Result<Result<u32, Trap>>) -> u32 {
+    match result {
+        Ok(Ok(result)) => result,
+        Ok(Err(Trap::User(err))) => raise_user_trap(err),
+        Ok(Err(trap)) => raise_lib_trap(trap),
+        Err(panic) => resume_panic(panic),
+    }
+}
+
 /// Implementation of memory.grow for locally-defined 32-bit memories.
 ///
 /// # Safety
@@ -149,15 +161,17 @@ pub unsafe extern "C" fn wasmer_vm_memory32_grow(
     delta: u32,
     memory_index: u32,
 ) -> u32 {
-    on_host_stack(|| {
+    let result = on_host_stack(|| {
         let instance = (*vmctx).instance_mut();
         let memory_index = LocalMemoryIndex::from_u32(memory_index);
 
-        instance
-            .memory_grow(memory_index, delta)
-            .map(|pages| pages.0)
-            .unwrap_or(u32::max_value())
-    })
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            instance
+                .memory_grow(memory_index, delta)
+                .map(|grown| grown.map(|pages| pages.0).unwrap_or(u32::max_value()))
+        }))
+    });
+    grow_result(result)
 }
 
 /// Implementation of memory.grow for imported 32-bit memories.
@@ -171,15 +185,17 @@ pub unsafe extern "C" fn wasmer_vm_imported_memory32_grow(
     delta: u32,
     memory_index: u32,
 ) -> u32 {
-    on_host_stack(|| {
+    let result = on_host_stack(|| {
         let instance = (*vmctx).instance_mut();
         let memory_index = MemoryIndex::from_u32(memory_index);
 
-        instance
-            .imported_memory_grow(memory_index, delta)
-            .map(|pages| pages.0)
-            .unwrap_or(u32::max_value())
-    })
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            instance
+                .imported_memory_grow(memory_index, delta)
+                .map(|grown| grown.map(|pages| pages.0).unwrap_or(u32::max_value()))
+        }))
+    });
+    grow_result(result)
 }
 
 /// Implementation of memory.size for locally-defined 32-bit memories.
@@ -442,7 +458,7 @@ pub unsafe extern "C" fn wasmer_vm_table_grow(
     delta: u32,
     table_index: u32,
 ) -> u32 {
-    on_host_stack(|| {
+    let result = on_host_stack(|| {
         let instance = (*vmctx).instance_mut();
         let table_index = LocalTableIndex::from_u32(table_index);
 
@@ -452,10 +468,13 @@ pub unsafe extern "C" fn wasmer_vm_table_grow(
             _ => panic!("Unrecognized table type: does not contain references"),
         };
 
-        instance
-            .table_grow(table_index, delta, init_value)
-            .unwrap_or(u32::max_value())
-    })
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            instance
+                .table_grow(table_index, delta, init_value)
+                .map(|grown| grown.unwrap_or(u32::max_value()))
+        }))
+    });
+    grow_result(result)
 }
 
 /// Implementation of `table.grow` for imported tables.
@@ -470,7 +489,7 @@ pub unsafe extern "C" fn wasmer_vm_imported_table_grow(
     delta: u32,
     table_index: u32,
 ) -> u32 {
-    on_host_stack(|| {
+    let result = on_host_stack(|| {
         let instance = (*vmctx).instance_mut();
         let table_index = TableIndex::from_u32(table_index);
         let init_value = match instance.get_table(table_index).ty().ty {
@@ -479,10 +498,13 @@ pub unsafe extern "C" fn wasmer_vm_imported_table_grow(
             _ => panic!("Unrecognized table type: does not contain references"),
         };
 
-        instance
-            .imported_table_grow(table_index, delta, init_value)
-            .unwrap_or(u32::max_value())
-    })
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            instance
+                .imported_table_grow(table_index, delta, init_value)
+                .map(|grown| grown.unwrap_or(u32::max_value()))
+        }))
+    });
+    grow_result(result)
 }
 
 /// Implementation of `func.ref`.
@@ -815,6 +837,160 @@ pub unsafe extern "C" fn wasmer_vm_imported_memory32_atomic_notify(
     result.unwrap()
 }
 
+/// Implementation of the SIMD operations that a compiler does not lower
+/// natively.
+///
+/// `operands` points to three consecutive 128-bit values: the two vector
+/// operands and the lane indices (or the bit selector for `v128.bitselect`).
+/// The result is written back over the first operand.
+///
+/// # Safety
+///
+/// `operands` must be valid for reads and writes of 48 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_simd_op(_vmctx: *mut VMContext, op: u32, operands: *mut u8) {
+    let op = SimdOp::from_u32(op).expect("invalid SIMD opcode");
+    let operands = operands as *mut u128;
+    let a = operands.read_unaligned();
+    let b = operands.add(1).read_unaligned();
+    let c = operands.add(2).read_unaligned();
+    operands.write_unaligned(crate::simd::eval(op, a, b, c));
+}
+
+/// Implementation of `throw`: creates a new exception with the given tag
+/// and returns its handle.
+///
+/// `values` holds the payload of the exception, one 128-bit slot per value.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable, and `values` must be valid for reads of
+/// the payload of the tag.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_new(
+    vmctx: *mut VMContext,
+    tag_index: u32,
+    values: *const RawValue,
+) -> u32 {
+    let instance = (*vmctx).instance_mut();
+    instance.exception_new(TagIndex::from_u32(tag_index), values)
+}
+
+/// Throws the exception with the given handle out of the instance.
+///
+/// # Safety
+///
+/// Only safe to call when wasm code is on the stack, aka `wasmer_call` or
+/// `wasmer_call_trampoline` must have been previously called.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_raise(vmctx: *mut VMContext, exception: u32) -> ! {
+    let exception = {
+        let instance = (*vmctx).instance_mut();
+        Box::new(instance.exception_take(exception))
+    };
+    raise_user_trap(exception)
+}
+
+/// Copies the exception with the given handle, returning the handle of the
+/// copy.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_clone(vmctx: *mut VMContext, exception: u32) -> u32 {
+    let instance = (*vmctx).instance_mut();
+    instance.exception_clone(exception)
+}
+
+/// Releases the exception with the given handle.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_drop(vmctx: *mut VMContext, exception: u32) {
+    let instance = (*vmctx).instance_mut();
+    instance.exception_drop(exception)
+}
+
+/// Checks whether the exception with the given handle was thrown with the
+/// given tag, in which case its payload is written to `values` and 1 is
+/// returned. Otherwise returns 0.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable, and `values` must be valid for writes of
+/// the payload of the tag.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_catch(
+    vmctx: *mut VMContext,
+    exception: u32,
+    tag_index: u32,
+    values: *mut RawValue,
+) -> u32 {
+    let instance = (*vmctx).instance();
+    instance.exception_catch(exception, TagIndex::from_u32(tag_index), values) as u32
+}
+
+/// Implementation of a call that is made inside of a `try` block.
+///
+/// The callee is called through the trampoline of the signature, with
+/// `values` holding its arguments and receiving its results. Returns the
+/// handle of the exception thrown by the callee, or 0 if it returned
+/// normally. Other traps are propagated.
+///
+/// # Safety
+///
+/// Only safe to call when wasm code is on the stack, aka `wasmer_call` or
+/// `wasmer_call_trampoline` must have been previously called. `callee` must
+/// be a function of the given signature, and `values` must be valid for
+/// reads and writes of its arguments and results.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_invoke(
+    vmctx: *mut VMContext,
+    sig_index: u32,
+    callee: *const VMFunctionBody,
+    callee_vmctx: *mut VMContext,
+    values: *mut RawValue,
+) -> u32 {
+    let result = {
+        let instance = (*vmctx).instance_mut();
+        let sig_index = SignatureIndex::from_u32(sig_index);
+        instance.exception_invoke(sig_index, callee, callee_vmctx, values)
+    };
+    match result {
+        Ok(Ok(exception)) => exception,
+        Ok(Err(Trap::User(err))) => raise_user_trap(err),
+        Ok(Err(trap)) => raise_lib_trap(trap),
+        Err(panic) => resume_panic(panic),
+    }
+}
+
+/// Implementation of the epoch deadline check, called once the epoch of the
+/// engine reached the deadline of the store.
+///
+/// Traps with `TrapCode::Interrupt`, unless the store has a deadline
+/// callback, in which case it is invoked to set the next deadline.
+///
+/// # Safety
+///
+/// Only safe to call when wasm code is on the stack, aka `wasmer_call` or
+/// `wasmer_call_trampoline` must have been previously called.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_epoch_deadline(vmctx: *mut VMContext) {
+    let result = {
+        let instance = (*vmctx).instance_mut();
+        instance.epoch_deadline_reached()
+    };
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(Trap::User(err))) => raise_user_trap(err),
+        Ok(Err(trap)) => raise_lib_trap(trap),
+        Err(panic) => resume_panic(panic),
+    }
+}
+
 /// The function pointer to a libcall
 pub fn function_pointer(libcall: LibCall) -> usize {
     match libcall {
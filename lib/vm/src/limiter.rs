@@ -0,0 +1,242 @@
+//! Store-level resource limits.
+//!
+//! A store can be given a [`ResourceLimiter`], which is consulted whenever
+//! an instance, a memory or a table is created in the store, and whenever
+//! one of its memories or tables grows, with the totals of the resources
+//! used by the store so far. The limiter can deny the operation, which then
+//! fails the way it would if the resources were exhausted, or return an
+//! error, which is raised as a trap.
+
+use crate::memory::{LinearMemory, VMMemory};
+use crate::table::{TableElement, VMTable};
+use crate::trap::Trap;
+use std::error::Error;
+use std::fmt;
+use wasmer_types::{MemoryError, MemoryType, Pages, TableType};
+
+/// The resources used by all the instances, memories and tables of a store.
+///
+/// Resources are never released before the store is dropped, so the totals
+/// only grow.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceUsage {
+    /// The number of instances created in the store.
+    pub instances: usize,
+    /// The number of memories created in the store.
+    pub memories: usize,
+    /// The total size in bytes of the memories of the store.
+    pub memory_bytes: usize,
+    /// The number of tables created in the store.
+    pub tables: usize,
+    /// The total number of elements of the tables of the store.
+    pub table_elements: usize,
+}
+
+/// Limits the resources used by a store.
+///
+/// Each method returns whether the operation is allowed. Denying it makes
+/// `memory.grow` and `table.grow` return -1, and makes creating an instance,
+/// a memory or a table fail. Returning an error makes the WebAssembly code
+/// trap with it, or the host operation fail.
+pub trait ResourceLimiter: Send + Sync {
+    /// Called before a memory of the store grows from `current` to `desired`
+    /// bytes. `maximum` is the maximum size of the memory in bytes, if any.
+    ///
+    /// This is also called when a memory is created, with a `current` size
+    /// of 0 and its minimum size as the `desired` one.
+    fn memory_growing(
+        &mut self,
+        usage: &ResourceUsage,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+    /// Called before a table of the store grows from `current` to `desired`
+    /// elements. `maximum` is the maximum number of elements of the table,
+    /// if any.
+    ///
+    /// This is also called when a table is created, with a `current` size
+    /// of 0 and its minimum size as the `desired` one.
+    fn table_growing(
+        &mut self,
+        usage: &ResourceUsage,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+    /// Called before an instance is created in the store, before its own
+    /// memories and tables.
+    fn instance_creating(
+        &mut self,
+        _usage: &ResourceUsage,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        Ok(true)
+    }
+}
+
+/// The resources used by a store, with its resource limiter.
+#[derive(Default)]
+pub struct VMResourceLimiter {
+    usage: ResourceUsage,
+    limiter: Option<Box<dyn ResourceLimiter>>,
+}
+
+impl VMResourceLimiter {
+    /// Returns the resources used by the store.
+    pub fn usage(&self) -> &ResourceUsage {
+        &self.usage
+    }
+
+    /// Sets the limiter consulted before creating or growing resources.
+    pub fn set_limiter(&mut self, limiter: Option<Box<dyn ResourceLimiter>>) {
+        self.limiter = limiter;
+    }
+
+    fn check_memory(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool, Trap> {
+        match self.limiter.as_mut() {
+            Some(limiter) => limiter
+                .memory_growing(&self.usage, current, desired, maximum)
+                .map_err(Trap::user),
+            None => Ok(true),
+        }
+    }
+
+    fn check_table(
+        &mut self,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> Result<bool, Trap> {
+        match self.limiter.as_mut() {
+            Some(limiter) => limiter
+                .table_growing(&self.usage, current, desired, maximum)
+                .map_err(Trap::user),
+            None => Ok(true),
+        }
+    }
+
+    fn check_new_memory(&mut self, ty: &MemoryType) -> Result<bool, Trap> {
+        self.check_memory(
+            0,
+            ty.minimum.bytes().0,
+            ty.maximum.map(|maximum| maximum.bytes().0),
+        )
+    }
+
+    fn check_new_table(&mut self, ty: &TableType) -> Result<bool, Trap> {
+        self.check_table(0, ty.minimum, ty.maximum)
+    }
+
+    /// Consults the limiter before creating an instance with the given local
+    /// memories and tables, and accounts for them if it is allowed.
+    pub fn instance_creating(
+        &mut self,
+        memories: &[MemoryType],
+        tables: &[TableType],
+    ) -> Result<bool, Trap> {
+        if let Some(limiter) = self.limiter.as_mut() {
+            if !limiter.instance_creating(&self.usage).map_err(Trap::user)? {
+                return Ok(false);
+            }
+        }
+        for memory in memories {
+            if !self.check_new_memory(memory)? {
+                return Ok(false);
+            }
+        }
+        for table in tables {
+            if !self.check_new_table(table)? {
+                return Ok(false);
+            }
+        }
+        self.usage.instances += 1;
+        self.usage.memories += memories.len();
+        self.usage.memory_bytes += memories
+            .iter()
+            .map(|memory| memory.minimum.bytes().0)
+            .sum::<usize>();
+        self.usage.tables += tables.len();
+        self.usage.table_elements += tables
+            .iter()
+            .map(|table| table.minimum as usize)
+            .sum::<usize>();
+        Ok(true)
+    }
+
+    /// Consults the limiter before creating a memory from the host, and
+    /// accounts for it if it is allowed.
+    pub fn memory_creating(&mut self, ty: &MemoryType) -> Result<bool, Trap> {
+        if !self.check_new_memory(ty)? {
+            return Ok(false);
+        }
+        self.usage.memories += 1;
+        self.usage.memory_bytes += ty.minimum.bytes().0;
+        Ok(true)
+    }
+
+    /// Consults the limiter before creating a table from the host, and
+    /// accounts for it if it is allowed.
+    pub fn table_creating(&mut self, ty: &TableType) -> Result<bool, Trap> {
+        if !self.check_new_table(ty)? {
+            return Ok(false);
+        }
+        self.usage.tables += 1;
+        self.usage.table_elements += ty.minimum as usize;
+        Ok(true)
+    }
+
+    /// Grows a memory of the store by `delta` pages if the limiter allows
+    /// it, returning the previous number of pages.
+    pub fn grow_memory(
+        &mut self,
+        memory: &mut VMMemory,
+        delta: Pages,
+    ) -> Result<Result<Pages, MemoryError>, Trap> {
+        let current = memory.size();
+        let desired = Pages(current.0.saturating_add(delta.0));
+        let maximum = memory.ty().maximum.map(|maximum| maximum.bytes().0);
+        if !self.check_memory(current.bytes().0, desired.bytes().0, maximum)? {
+            return Ok(Err(MemoryError::CouldNotGrow {
+                current,
+                attempted_delta: delta,
+            }));
+        }
+        let result = memory.grow(delta);
+        self.usage.memory_bytes += memory.size().bytes().0.saturating_sub(current.bytes().0);
+        Ok(result)
+    }
+
+    /// Grows a table of the store by `delta` elements if the limiter allows
+    /// it, returning the previous number of elements.
+    pub fn grow_table(
+        &mut self,
+        table: &mut VMTable,
+        delta: u32,
+        init_value: TableElement,
+    ) -> Result<Option<u32>, Trap> {
+        let current = table.size();
+        let desired = current.saturating_add(delta);
+        if !self.check_table(current, desired, table.ty().maximum)? {
+            return Ok(None);
+        }
+        let result = table.grow(delta, init_value);
+        self.usage.table_elements += table.size().saturating_sub(current) as usize;
+        Ok(result)
+    }
+}
+
+impl fmt::Debug for VMResourceLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VMResourceLimiter")
+            .field("usage", &self.usage)
+            .field("limiter", &self.limiter.is_some())
+            .finish()
+    }
+}
@@ -5,6 +5,7 @@
 //!
 //! `Memory` is to WebAssembly linear memories what `Table` is to WebAssembly tables.
 
+use crate::memory_image::{map_memory_image, MemoryImage};
 use crate::threadconditions::ThreadConditions;
 pub use crate::threadconditions::{NotifyLocation, WaiterError};
 use crate::trap::Trap;
@@ -368,6 +369,11 @@ impl LinearMemory for VMOwnedMemory {
         Err(MemoryError::MemoryNotShared)
     }
 
+    /// Maps the image copy-on-write over the memory
+    unsafe fn initialize_with_image(&self, image: &MemoryImage) -> Result<bool, Trap> {
+        Ok(map_memory_image(self.vmmemory().as_ref(), image))
+    }
+
     /// Copies this memory to a new memory
     fn copy(&mut self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
         let forked = Self::copy(self)?;
@@ -577,6 +583,11 @@ impl LinearMemory for VMMemory {
         self.0.initialize_with_data(start, data)
     }
 
+    /// Initialize memory with an image
+    unsafe fn initialize_with_image(&self, image: &MemoryImage) -> Result<bool, Trap> {
+        self.0.initialize_with_image(image)
+    }
+
     /// Copies this memory to a new memory
     fn copy(&mut self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
         self.0.copy()
@@ -730,6 +741,18 @@ where
         initialize_memory_with_data(memory, start, data)
     }
 
+    #[doc(hidden)]
+    /// Initializes the memory by mapping `image` copy-on-write, returning
+    /// `false` if the memory doesn't support it, in which case its data is
+    /// initialized with `initialize_with_data` instead.
+    ///
+    /// # Safety
+    /// This function is unsafe because it replaces the contents of the memory
+    /// covered by the image, and must only be called at initialization time.
+    unsafe fn initialize_with_image(&self, _image: &MemoryImage) -> Result<bool, Trap> {
+        Ok(false)
+    }
+
     /// Copies this memory to a new memory
     fn copy(&mut self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError>;
 
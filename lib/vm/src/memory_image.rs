@@ -0,0 +1,275 @@
+//! Copy-on-write images of the initial contents of linear memories.
+//!
+//! When all the data segments of a local memory have constant offsets and
+//! fit in its minimum size, they are laid out once in a [`MemoryImage`]
+//! spanning the pages from the first segment to the last one. On Linux the
+//! image is stored in a sparse memfd, which new memories map privately over
+//! their pages instead of copying the segments: instantiating then takes the
+//! same time whatever the amount of data, and a page is only copied when it
+//! is first written to. Memories without an image, or which don't support
+//! mapping one, have their segments copied eagerly.
+
+use crate::vmcontext::VMMemoryDefinition;
+use std::fmt;
+use std::ops::Range;
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{DataInitializer, LocalMemoryIndex, ModuleInfo, WASM_PAGE_SIZE};
+
+/// Round `size` up to the nearest multiple of `page_size`.
+fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
+    (size + (page_size - 1)) & !(page_size - 1)
+}
+
+/// The initial contents of a linear memory, which can be mapped
+/// copy-on-write into new memories.
+pub struct MemoryImage {
+    // The offset of the image in the memory, a multiple of the page size.
+    offset: usize,
+    // The length of the image, a multiple of the page size.
+    len: usize,
+    #[cfg(target_os = "linux")]
+    file: std::fs::File,
+}
+
+impl MemoryImage {
+    /// Creates an image of `len` bytes starting at `offset` in the memory,
+    /// filled with the given segments, in order.
+    #[cfg(target_os = "linux")]
+    fn new(offset: usize, len: usize, segments: &[&DataInitializer<'_>]) -> Option<Self> {
+        use std::os::unix::fs::FileExt;
+        use std::os::unix::io::FromRawFd;
+
+        let fd = unsafe {
+            libc::memfd_create(
+                b"wasmer-memory-image\0".as_ptr() as *const libc::c_char,
+                libc::MFD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return None;
+        }
+        let file = unsafe { std::fs::File::from_raw_fd(fd) };
+        // The file stays sparse: only the pages holding data take memory.
+        file.set_len(len as u64).ok()?;
+        for segment in segments {
+            file.write_all_at(segment.data, (segment.location.offset - offset) as u64)
+                .ok()?;
+        }
+        Some(Self { offset, len, file })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn new(_offset: usize, _len: usize, _segments: &[&DataInitializer<'_>]) -> Option<Self> {
+        None
+    }
+
+    /// Returns the range of bytes of the memory covered by the image.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.len
+    }
+
+    /// Maps the image copy-on-write over the memory starting at `base`.
+    ///
+    /// # Safety
+    /// - `base` must be page-aligned and point to the start of a mapping
+    ///   covering at least the range of the image, whose contents are
+    ///   discarded.
+    #[cfg(target_os = "linux")]
+    pub unsafe fn map_at(&self, base: *mut u8) -> Result<(), String> {
+        use std::os::unix::io::AsRawFd;
+
+        let ptr = libc::mmap(
+            base.add(self.offset) as _,
+            self.len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_FIXED,
+            self.file.as_raw_fd(),
+            0,
+        );
+        if ptr as isize == -1_isize {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    /// Maps the image copy-on-write over the memory starting at `base`.
+    ///
+    /// # Safety
+    /// - `base` must be page-aligned and point to the start of a mapping
+    ///   covering at least the range of the image, whose contents are
+    ///   discarded.
+    #[cfg(not(target_os = "linux"))]
+    pub unsafe fn map_at(&self, _base: *mut u8) -> Result<(), String> {
+        Err("memory images are not supported on this platform".to_string())
+    }
+}
+
+/// Maps `image` over a memory whose base is the start of a page-aligned
+/// mapping, returning whether it was mapped.
+pub(crate) unsafe fn map_memory_image(memory: &VMMemoryDefinition, image: &MemoryImage) -> bool {
+    image.range().end <= memory.current_length && image.map_at(memory.base).is_ok()
+}
+
+impl fmt::Debug for MemoryImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryImage")
+            .field("range", &self.range())
+            .finish()
+    }
+}
+
+/// The images of the local memories of a module.
+#[derive(Debug, Default)]
+pub struct MemoryImages {
+    images: PrimaryMap<LocalMemoryIndex, Option<MemoryImage>>,
+}
+
+impl MemoryImages {
+    /// Builds the images of the local memories of `module` from its data
+    /// initializers.
+    ///
+    /// A memory gets no image if it is shared, if one of its segments has
+    /// an offset relative to a global or doesn't fit in its minimum size,
+    /// or if images are not supported on this platform.
+    pub fn new(module: &ModuleInfo, data_initializers: &[DataInitializer<'_>]) -> Self {
+        let page_size = region::page::size();
+        let mut images = PrimaryMap::with_capacity(module.memories.len());
+        for (index, memory) in module.memories.iter().skip(module.num_imported_memories) {
+            let segments = data_initializers
+                .iter()
+                .filter(|init| init.location.memory_index == index)
+                .collect::<Vec<_>>();
+            let image = if memory.shared || WASM_PAGE_SIZE % page_size != 0 {
+                None
+            } else {
+                Self::build(memory.minimum.bytes().0, &segments, page_size)
+            };
+            images.push(image);
+        }
+        Self { images }
+    }
+
+    fn build(
+        minimum_bytes: usize,
+        segments: &[&DataInitializer<'_>],
+        page_size: usize,
+    ) -> Option<MemoryImage> {
+        let mut start = usize::MAX;
+        let mut end = 0;
+        for segment in segments {
+            if segment.location.base.is_some() {
+                return None;
+            }
+            let segment_end = segment
+                .location
+                .offset
+                .checked_add(segment.data.len())
+                .filter(|end| *end <= minimum_bytes)?;
+            if !segment.data.is_empty() {
+                start = start.min(segment.location.offset);
+                end = end.max(segment_end);
+            }
+        }
+        if start >= end {
+            return None;
+        }
+        let start = start & !(page_size - 1);
+        let end = round_up_to_page_size(end, page_size);
+        MemoryImage::new(start, end - start, segments)
+    }
+
+    /// Returns the image of a local memory, if it has one.
+    pub fn get(&self, index: LocalMemoryIndex) -> Option<&MemoryImage> {
+        self.images.get(index).and_then(Option::as_ref)
+    }
+
+    /// Iterates over the local memories which have an image.
+    pub fn iter(&self) -> impl Iterator<Item = (LocalMemoryIndex, &MemoryImage)> {
+        self.images
+            .iter()
+            .filter_map(|(index, image)| image.as_ref().map(|image| (index, image)))
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::mmap::Mmap;
+    use wasmer_types::entity::EntityRef;
+    use wasmer_types::{DataInitializerLocation, GlobalIndex, MemoryIndex, MemoryType};
+
+    fn module(memories: &[MemoryType]) -> ModuleInfo {
+        let mut module = ModuleInfo::new();
+        for memory in memories {
+            module.memories.push(*memory);
+        }
+        module
+    }
+
+    fn segment(offset: usize, data: &[u8]) -> DataInitializer<'_> {
+        DataInitializer {
+            location: DataInitializerLocation {
+                memory_index: MemoryIndex::new(0),
+                base: None,
+                offset,
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn images_are_mapped_copy_on_write() {
+        let module = module(&[MemoryType::new(2, None, false)]);
+        let inits = [
+            segment(10, b"hello"),
+            segment(WASM_PAGE_SIZE + 3, b"world"),
+            segment(12, b"L"),
+        ];
+        let images = MemoryImages::new(&module, &inits);
+        let image = images.get(LocalMemoryIndex::new(0)).unwrap();
+        let page_size = region::page::size();
+        assert_eq!(
+            image.range(),
+            0..round_up_to_page_size(WASM_PAGE_SIZE + 8, page_size)
+        );
+
+        let mut first = Mmap::with_at_least(2 * WASM_PAGE_SIZE).unwrap();
+        let mut second = Mmap::with_at_least(2 * WASM_PAGE_SIZE).unwrap();
+        unsafe {
+            image.map_at(first.as_mut_ptr()).unwrap();
+            image.map_at(second.as_mut_ptr()).unwrap();
+        }
+        assert_eq!(&first.as_slice()[10..15], b"heLlo");
+        assert_eq!(
+            &first.as_slice()[WASM_PAGE_SIZE + 3..WASM_PAGE_SIZE + 8],
+            b"world"
+        );
+        assert!(first.as_slice()[WASM_PAGE_SIZE + 8..]
+            .iter()
+            .all(|b| *b == 0));
+
+        // Writes are private to each mapping
+        first.as_mut_slice()[10] = b'j';
+        assert_eq!(&first.as_slice()[10..15], b"jeLlo");
+        assert_eq!(&second.as_slice()[10..15], b"heLlo");
+    }
+
+    #[test]
+    fn memories_without_static_segments_have_no_image() {
+        let module = module(&[
+            MemoryType::new(1, None, false),
+            MemoryType::new(1, None, true),
+            MemoryType::new(1, None, false),
+            MemoryType::new(1, None, false),
+        ]);
+        let mut relative = segment(0, b"relative");
+        relative.location.base = Some(GlobalIndex::new(0));
+        let mut shared = segment(0, b"shared");
+        shared.location.memory_index = MemoryIndex::new(1);
+        let mut out_of_bounds = segment(WASM_PAGE_SIZE - 1, b"out of bounds");
+        out_of_bounds.location.memory_index = MemoryIndex::new(2);
+
+        let images = MemoryImages::new(&module, &[relative, shared, out_of_bounds]);
+        assert_eq!(images.iter().count(), 0);
+    }
+}
@@ -0,0 +1,633 @@
+//! Pooling allocation of instances, linear memories and tables.
+//!
+//! The [`PoolingInstanceAllocator`] reserves the virtual memory of a fixed
+//! number of instances, memories and tables upfront, divided into slots.
+//! Instantiating a module takes free slots instead of allocating and
+//! mapping memory, and dropping the instance decommits its slots and
+//! returns them to the pool, to be reused by the next instantiation.
+
+use crate::instance::InstanceAllocator;
+use crate::memory::{LinearMemory, VMMemory, VMOwnedMemory};
+use crate::memory_image::{map_memory_image, MemoryImage};
+use crate::mmap::Mmap;
+use crate::store::MaybeInstanceOwned;
+use crate::table::VMTable;
+use crate::trap::Trap;
+use crate::vmcontext::{VMMemoryDefinition, VMTableDefinition};
+use std::convert::TryInto;
+use std::fmt;
+use std::io;
+use std::ptr::{self, NonNull};
+use std::sync::{Arc, Mutex};
+use wasmer_types::{
+    Bytes, MemoryError, MemoryStyle, MemoryType, ModuleInfo, Pages, TableStyle, TableType,
+    WASM_PAGE_SIZE,
+};
+
+/// Round `size` up to the nearest multiple of `page_size`.
+fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
+    (size + (page_size - 1)) & !(page_size - 1)
+}
+
+/// The configuration of a [`PoolingInstanceAllocator`].
+#[derive(Debug, Clone)]
+pub struct PoolingConfig {
+    max_instances: usize,
+    max_instance_size: usize,
+    max_memories: usize,
+    memory_reservation: usize,
+    max_tables: usize,
+    max_table_elements: u32,
+}
+
+impl PoolingConfig {
+    /// Creates the default configuration, pooling up to 1000 instances,
+    /// memories and tables.
+    ///
+    /// Each memory slot reserves enough virtual memory for the static
+    /// memories of `BaseTunables`, including their guard pages.
+    pub fn new() -> Self {
+        // 4 GiB and 2 GiB of guard pages on 64-bit hosts, 1 GiB and 64 KiB
+        // otherwise, see `BaseTunables::for_target`.
+        #[cfg(target_pointer_width = "64")]
+        let memory_reservation = 0x1_8000_0000;
+        #[cfg(not(target_pointer_width = "64"))]
+        let memory_reservation = 0x4001_0000;
+
+        Self {
+            max_instances: 1000,
+            max_instance_size: 1 << 20,
+            max_memories: 1000,
+            memory_reservation,
+            max_tables: 1000,
+            max_table_elements: 10_000,
+        }
+    }
+
+    /// Set the number of instance slots
+    pub fn set_max_instances(mut self, max_instances: usize) -> Self {
+        self.max_instances = max_instances;
+        self
+    }
+
+    /// Set the size in bytes of an instance slot, holding the instance and
+    /// its `VMContext`
+    pub fn set_max_instance_size(mut self, max_instance_size: usize) -> Self {
+        self.max_instance_size = max_instance_size;
+        self
+    }
+
+    /// Set the number of linear memory slots
+    pub fn set_max_memories(mut self, max_memories: usize) -> Self {
+        self.max_memories = max_memories;
+        self
+    }
+
+    /// Set the size in bytes of the virtual memory reserved for a linear
+    /// memory slot, including its guard pages
+    pub fn set_memory_reservation(mut self, memory_reservation: usize) -> Self {
+        self.memory_reservation = memory_reservation;
+        self
+    }
+
+    /// Set the number of table slots
+    pub fn set_max_tables(mut self, max_tables: usize) -> Self {
+        self.max_tables = max_tables;
+        self
+    }
+
+    /// Set the number of elements of a table slot
+    pub fn set_max_table_elements(mut self, max_table_elements: u32) -> Self {
+        self.max_table_elements = max_table_elements;
+        self
+    }
+}
+
+impl Default for PoolingConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Makes `len` bytes at `ptr` accessible.
+#[cfg(not(target_os = "windows"))]
+unsafe fn commit(ptr: *mut u8, len: usize) -> Result<(), String> {
+    region::protect(ptr, len, region::Protection::READ_WRITE).map_err(|e| e.to_string())
+}
+
+/// Makes `len` bytes at `ptr` accessible.
+#[cfg(target_os = "windows")]
+unsafe fn commit(ptr: *mut u8, len: usize) -> Result<(), String> {
+    use winapi::ctypes::c_void;
+    use winapi::um::memoryapi::VirtualAlloc;
+    use winapi::um::winnt::{MEM_COMMIT, PAGE_READWRITE};
+
+    if VirtualAlloc(ptr as *mut c_void, len, MEM_COMMIT, PAGE_READWRITE).is_null() {
+        return Err(io::Error::last_os_error().to_string());
+    }
+    Ok(())
+}
+
+/// Releases the physical pages of `len` bytes at `ptr`, and makes them
+/// inaccessible. They are zeroed when they are made accessible again.
+#[cfg(not(target_os = "windows"))]
+unsafe fn decommit(ptr: *mut u8, len: usize) -> io::Result<()> {
+    // Mapping fresh pages over the range drops the old ones at once.
+    let ret = libc::mmap(
+        ptr as *mut libc::c_void,
+        len,
+        libc::PROT_NONE,
+        libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_FIXED,
+        -1,
+        0,
+    );
+    if ret as isize == -1_isize {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Releases the physical pages of `len` bytes at `ptr`, and makes them
+/// inaccessible. They are zeroed when they are made accessible again.
+#[cfg(target_os = "windows")]
+unsafe fn decommit(ptr: *mut u8, len: usize) -> io::Result<()> {
+    use winapi::ctypes::c_void;
+    use winapi::um::memoryapi::VirtualFree;
+    use winapi::um::winnt::MEM_DECOMMIT;
+
+    if VirtualFree(ptr as *mut c_void, len, MEM_DECOMMIT) == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// A reservation of virtual memory divided into slots of the same size.
+struct SlotPool {
+    mmap: Mmap,
+    slot_size: usize,
+    /// The indexes of the free slots, the last released slot is reused
+    /// first.
+    free: Mutex<Vec<usize>>,
+}
+
+impl SlotPool {
+    fn new(slots: usize, slot_size: usize) -> Result<Self, String> {
+        let slot_size = round_up_to_page_size(slot_size, region::page::size());
+        let size = slots
+            .checked_mul(slot_size)
+            .ok_or_else(|| format!("cannot reserve {} slots of {} bytes", slots, slot_size))?;
+        Ok(Self {
+            // The slots are only committed when they are allocated.
+            mmap: Mmap::accessible_reserved(0, size)?,
+            slot_size: if size == 0 { 0 } else { slot_size },
+            free: Mutex::new((0..slots).rev().collect()),
+        })
+    }
+
+    fn allocate(self: &Arc<Self>) -> Option<PoolSlot> {
+        if self.slot_size == 0 {
+            return None;
+        }
+        let index = self.free.lock().unwrap().pop()?;
+        Some(PoolSlot {
+            pool: self.clone(),
+            index,
+            committed: 0,
+        })
+    }
+
+    fn capacity(&self) -> usize {
+        self.mmap.len() / self.slot_size.max(1)
+    }
+}
+
+/// A slot taken from a pool, which is decommitted and returned to the pool
+/// when dropped.
+pub(crate) struct PoolSlot {
+    pool: Arc<SlotPool>,
+    index: usize,
+    /// The number of bytes made accessible at the start of the slot.
+    committed: usize,
+}
+
+impl PoolSlot {
+    /// Returns a pointer to the start of the slot.
+    pub(crate) fn as_mut_ptr(&self) -> *mut u8 {
+        unsafe { (self.pool.mmap.as_ptr() as *mut u8).add(self.index * self.pool.slot_size) }
+    }
+
+    /// Returns the size of the slot in bytes.
+    pub(crate) fn len(&self) -> usize {
+        self.pool.slot_size
+    }
+
+    /// Makes the first `len` bytes of the slot accessible. `len` is rounded
+    /// up to the page size.
+    pub(crate) fn commit(&mut self, len: usize) -> Result<(), String> {
+        let len = round_up_to_page_size(len, region::page::size());
+        assert!(len <= self.len(), "committing past the end of a pool slot");
+        if len > self.committed {
+            unsafe {
+                commit(self.as_mut_ptr().add(self.committed), len - self.committed)?;
+            }
+            self.committed = len;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PoolSlot {
+    fn drop(&mut self) {
+        if self.committed != 0 {
+            // A slot that can't be decommitted is never reused, rather than
+            // leaking its contents to the next instance.
+            if unsafe { decommit(self.as_mut_ptr(), self.committed) }.is_err() {
+                return;
+            }
+        }
+        self.pool.free.lock().unwrap().push(self.index);
+    }
+}
+
+impl PartialEq for PoolSlot {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.pool, &other.pool) && self.index == other.index
+    }
+}
+
+impl Eq for PoolSlot {}
+
+impl fmt::Debug for PoolSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolSlot")
+            .field("index", &self.index)
+            .field("committed", &self.committed)
+            .finish()
+    }
+}
+
+/// An allocator taking the instances, linear memories and tables from pools
+/// of pre-reserved virtual memory.
+///
+/// The instances, memories and tables that don't fit in a slot are
+/// allocated as usual, while running out of free slots is an error.
+/// Shared memories and memories with a dynamic style are never pooled.
+pub struct PoolingInstanceAllocator {
+    config: PoolingConfig,
+    instances: Arc<SlotPool>,
+    memories: Arc<SlotPool>,
+    tables: Arc<SlotPool>,
+}
+
+impl PoolingInstanceAllocator {
+    /// Reserves the virtual memory of the pools described by `config`.
+    pub fn new(config: PoolingConfig) -> Result<Self, String> {
+        let table_slot_size = (config.max_table_elements as usize)
+            .checked_mul(VMTable::element_size())
+            .ok_or_else(|| "the table slots are too large".to_string())?;
+        Ok(Self {
+            instances: Arc::new(SlotPool::new(
+                config.max_instances,
+                config.max_instance_size,
+            )?),
+            memories: Arc::new(SlotPool::new(
+                config.max_memories,
+                config.memory_reservation,
+            )?),
+            tables: Arc::new(SlotPool::new(config.max_tables, table_slot_size)?),
+            config,
+        })
+    }
+
+    /// Returns the configuration of the pools.
+    pub fn config(&self) -> &PoolingConfig {
+        &self.config
+    }
+
+    /// Returns the number of free instance, memory and table slots.
+    pub fn free_slots(&self) -> (usize, usize, usize) {
+        let free = |pool: &SlotPool| pool.free.lock().unwrap().len();
+        (
+            free(&self.instances),
+            free(&self.memories),
+            free(&self.tables),
+        )
+    }
+
+    /// Allocates the instance data of `module` in a slot, like
+    /// [`InstanceAllocator::new`].
+    ///
+    /// Returns `None` if the instance doesn't fit in a slot.
+    #[allow(clippy::type_complexity)]
+    pub fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        Option<(
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        )>,
+        String,
+    > {
+        let (offsets, layout) = InstanceAllocator::layout(module);
+        if layout.size() > self.instances.slot_size {
+            return Ok(None);
+        }
+        let mut slot = self.instances.allocate().ok_or_else(|| {
+            format!(
+                "no free instance slot in the pool (max {} instances)",
+                self.instances.capacity()
+            )
+        })?;
+        slot.commit(layout.size())?;
+        Ok(Some(InstanceAllocator::in_slot(offsets, layout, slot)))
+    }
+
+    /// Creates a linear memory owned by the VM in a slot, like
+    /// `VMMemory::from_definition`.
+    ///
+    /// Returns `None` if the memory can't be pooled.
+    ///
+    /// # Safety
+    /// - `vm_memory_location` must point to a valid location in VM memory.
+    pub unsafe fn create_memory(
+        &self,
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        vm_memory_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Option<VMMemory>, MemoryError> {
+        let bound = match style {
+            MemoryStyle::Static {
+                bound,
+                offset_guard_size,
+            } if !memory.shared => {
+                // The whole bound and its guard pages must be reserved for
+                // the generated code to skip the bounds checks.
+                let reserved = bound.bytes().0.checked_add(*offset_guard_size as usize);
+                if reserved.map_or(true, |reserved| reserved > self.memories.slot_size) {
+                    return Ok(None);
+                }
+                *bound
+            }
+            _ => return Ok(None),
+        };
+        if memory.minimum > bound {
+            return Err(MemoryError::MinimumMemoryTooLarge {
+                min_requested: memory.minimum,
+                max_allowed: bound,
+            });
+        }
+        if let Some(max) = memory.maximum {
+            if max < memory.minimum {
+                return Err(MemoryError::InvalidMemory {
+                    reason: format!(
+                        "the maximum ({} pages) is less than the minimum ({} pages)",
+                        max.0, memory.minimum.0
+                    ),
+                });
+            }
+        }
+
+        let mut slot = self.memories.allocate().ok_or_else(|| {
+            MemoryError::Region(format!(
+                "no free memory slot in the pool (max {} memories)",
+                self.memories.capacity()
+            ))
+        })?;
+        let mem_length = memory.minimum.bytes().0;
+        slot.commit(mem_length).map_err(MemoryError::Region)?;
+
+        let mut definition = vm_memory_location;
+        {
+            let md = definition.as_mut();
+            md.base = slot.as_mut_ptr();
+            md.current_length = mem_length;
+        }
+        Ok(Some(VMMemory(Box::new(VMPooledMemory {
+            slot,
+            bound,
+            memory: *memory,
+            style: *style,
+            vm_memory_definition: MaybeInstanceOwned::Instance(vm_memory_location),
+        }))))
+    }
+
+    /// Creates a table owned by the VM in a slot, like
+    /// `VMTable::from_definition`. The table can't grow past the number of
+    /// elements of a slot.
+    ///
+    /// Returns `None` if the table doesn't fit in a slot.
+    ///
+    /// # Safety
+    /// - `vm_table_location` must point to a valid location in VM memory.
+    pub unsafe fn create_table(
+        &self,
+        table: &TableType,
+        style: &TableStyle,
+        vm_table_location: NonNull<VMTableDefinition>,
+    ) -> Result<Option<VMTable>, String> {
+        if table.minimum > self.config.max_table_elements {
+            return Ok(None);
+        }
+        let mut slot = self.tables.allocate().ok_or_else(|| {
+            format!(
+                "no free table slot in the pool (max {} tables)",
+                self.tables.capacity()
+            )
+        })?;
+        // The elements are initialized by zeroing them.
+        slot.commit(slot.len())?;
+        VMTable::from_definition_in_slot(table, style, vm_table_location, slot).map(Some)
+    }
+}
+
+impl fmt::Debug for PoolingInstanceAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolingInstanceAllocator")
+            .field("config", &self.config)
+            .field("free_slots", &self.free_slots())
+            .finish()
+    }
+}
+
+/// A linear memory in a slot of a [`PoolingInstanceAllocator`].
+#[derive(Debug)]
+struct VMPooledMemory {
+    slot: PoolSlot,
+    /// The size of the memory can't grow past the bound of its static style.
+    bound: Pages,
+    /// The WebAssembly linear memory description.
+    memory: MemoryType,
+    style: MemoryStyle,
+    vm_memory_definition: MaybeInstanceOwned<VMMemoryDefinition>,
+}
+
+unsafe impl Send for VMPooledMemory {}
+unsafe impl Sync for VMPooledMemory {}
+
+impl VMPooledMemory {
+    fn current_length(&self) -> usize {
+        unsafe { self.vm_memory_definition.as_ptr().as_ref().current_length }
+    }
+
+    fn set_current_length(&mut self, length: usize) {
+        unsafe {
+            self.vm_memory_definition.as_ptr().as_mut().current_length = length;
+        }
+    }
+}
+
+impl LinearMemory for VMPooledMemory {
+    fn ty(&self) -> MemoryType {
+        let mut ty = self.memory;
+        ty.minimum = self.size();
+        ty
+    }
+
+    fn size(&self) -> Pages {
+        Bytes::from(self.current_length()).try_into().unwrap()
+    }
+
+    fn style(&self) -> MemoryStyle {
+        self.style
+    }
+
+    fn grow(&mut self, delta: Pages) -> Result<Pages, MemoryError> {
+        let prev_pages = self.size();
+        if delta.0 == 0 {
+            return Ok(prev_pages);
+        }
+        let could_not_grow = MemoryError::CouldNotGrow {
+            current: prev_pages,
+            attempted_delta: delta,
+        };
+        let new_pages = prev_pages
+            .checked_add(delta)
+            .ok_or(could_not_grow.clone())?;
+        if new_pages > self.bound
+            || new_pages >= Pages::max_value()
+            || self.memory.maximum.map_or(false, |max| new_pages > max)
+        {
+            return Err(could_not_grow);
+        }
+
+        let new_bytes = new_pages.bytes().0;
+        self.slot.commit(new_bytes).map_err(MemoryError::Region)?;
+        self.set_current_length(new_bytes);
+        Ok(prev_pages)
+    }
+
+    fn grow_at_least(&mut self, min_size: u64) -> Result<(), MemoryError> {
+        let cur_size = self.current_length() as u64;
+        if cur_size < min_size {
+            let growth = min_size - cur_size;
+            let growth_pages = ((growth - 1) / WASM_PAGE_SIZE as u64) + 1;
+            self.grow(Pages(growth_pages as u32))?;
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<(), MemoryError> {
+        self.set_current_length(0);
+        Ok(())
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.vm_memory_definition.as_ptr()
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
+        Err(MemoryError::MemoryNotShared)
+    }
+
+    /// Maps the image over the slot, which is remapped when it is decommitted
+    unsafe fn initialize_with_image(&self, image: &MemoryImage) -> Result<bool, Trap> {
+        Ok(map_memory_image(self.vmmemory().as_ref(), image))
+    }
+
+    /// Copies this memory to a new memory, which isn't pooled
+    fn copy(&mut self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
+        let copy = VMOwnedMemory::new(&self.ty(), &self.style)?;
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.slot.as_mut_ptr(),
+                copy.vmmemory().as_ref().base,
+                self.current_length(),
+            );
+        }
+        Ok(Box::new(copy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::UnsafeCell;
+
+    #[test]
+    fn slots_are_reused_and_zeroed() {
+        let pool = Arc::new(SlotPool::new(2, 1).unwrap());
+        assert_eq!(pool.slot_size, region::page::size());
+
+        let mut first = pool.allocate().unwrap();
+        first.commit(1).unwrap();
+        unsafe { *first.as_mut_ptr() = 42 };
+        let second = pool.allocate().unwrap();
+        assert!(pool.allocate().is_none());
+
+        let first_ptr = first.as_mut_ptr();
+        drop(first);
+        let mut reused = pool.allocate().unwrap();
+        assert_eq!(reused.as_mut_ptr(), first_ptr);
+        reused.commit(1).unwrap();
+        assert_eq!(unsafe { *reused.as_mut_ptr() }, 0);
+
+        drop(second);
+        drop(reused);
+        assert_eq!(pool.free.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn pooled_memories_grow_within_their_bound() {
+        let allocator = PoolingInstanceAllocator::new(
+            PoolingConfig::new()
+                .set_max_memories(1)
+                .set_memory_reservation(4 * WASM_PAGE_SIZE),
+        )
+        .unwrap();
+        let style = MemoryStyle::Static {
+            bound: Pages(3),
+            offset_guard_size: WASM_PAGE_SIZE as u64,
+        };
+        let definition = UnsafeCell::new(VMMemoryDefinition {
+            base: ptr::null_mut(),
+            current_length: 0,
+        });
+        let location = NonNull::new(definition.get()).unwrap();
+
+        let mut memory =
+            unsafe { allocator.create_memory(&MemoryType::new(1, None, false), &style, location) }
+                .unwrap()
+                .unwrap();
+        assert_eq!(allocator.free_slots().1, 0);
+        assert_eq!(memory.size(), Pages(1));
+        assert_eq!(memory.grow(Pages(2)).unwrap(), Pages(1));
+        assert!(memory.grow(Pages(1)).is_err());
+        unsafe {
+            *memory.vmmemory().as_ref().base.add(2 * WASM_PAGE_SIZE) = 1;
+        }
+
+        // Too large for a slot
+        let large = MemoryStyle::Static {
+            bound: Pages(4),
+            offset_guard_size: WASM_PAGE_SIZE as u64,
+        };
+        let pooled =
+            unsafe { allocator.create_memory(&MemoryType::new(1, None, false), &large, location) };
+        assert!(pooled.unwrap().is_none());
+
+        drop(memory);
+        assert_eq!(allocator.free_slots().1, 1);
+    }
+}
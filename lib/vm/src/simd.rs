@@ -0,0 +1,570 @@
+//! Portable implementation of the fixed-width SIMD operations.
+//!
+//! This backs the `wasmer_vm_simd_op` builtin, which compilers call for
+//! the operations they do not lower to native instructions.
+
+#![allow(clippy::float_arithmetic, clippy::float_cmp)]
+
+use crate::libcalls::{wasmer_vm_f32_nearest, wasmer_vm_f64_nearest};
+use std::convert::TryInto;
+use wasmer_types::SimdOp;
+
+macro_rules! lanes {
+    ($split:ident, $join:ident, $ty:ty, $n:literal) => {
+        fn $split(v: u128) -> [$ty; $n] {
+            const SIZE: usize = 16 / $n;
+            let bytes = v.to_le_bytes();
+            let mut lanes = [<$ty>::default(); $n];
+            for (lane, chunk) in lanes.iter_mut().zip(bytes.chunks_exact(SIZE)) {
+                *lane = <$ty>::from_le_bytes(chunk.try_into().unwrap());
+            }
+            lanes
+        }
+
+        fn $join(lanes: [$ty; $n]) -> u128 {
+            const SIZE: usize = 16 / $n;
+            let mut bytes = [0u8; 16];
+            for (lane, chunk) in lanes.iter().zip(bytes.chunks_exact_mut(SIZE)) {
+                chunk.copy_from_slice(&lane.to_le_bytes());
+            }
+            u128::from_le_bytes(bytes)
+        }
+    };
+}
+
+lanes!(i8x16, from_i8x16, i8, 16);
+lanes!(u8x16, from_u8x16, u8, 16);
+lanes!(i16x8, from_i16x8, i16, 8);
+lanes!(u16x8, from_u16x8, u16, 8);
+lanes!(i32x4, from_i32x4, i32, 4);
+lanes!(u32x4, from_u32x4, u32, 4);
+lanes!(i64x2, from_i64x2, i64, 2);
+lanes!(u64x2, from_u64x2, u64, 2);
+lanes!(f32x4, from_f32x4, f32, 4);
+lanes!(f64x2, from_f64x2, f64, 2);
+
+fn zip<T: Copy, U: Copy + Default, const N: usize>(
+    a: [T; N],
+    b: [T; N],
+    f: impl Fn(T, T) -> U,
+) -> [U; N] {
+    let mut out = [U::default(); N];
+    for i in 0..N {
+        out[i] = f(a[i], b[i]);
+    }
+    out
+}
+
+/// Lane-wise comparison, producing all ones for true and all zeros for false.
+macro_rules! cmp {
+    ($split:ident, $a:expr, $b:expr, $join:ident, |$x:ident, $y:ident| $e:expr) => {
+        $join(zip(
+            $split($a),
+            $split($b),
+            |$x, $y| if $e { -1 } else { 0 },
+        ))
+    };
+}
+
+macro_rules! unop {
+    ($split:ident, $a:expr, $join:ident, |$x:ident| $e:expr) => {
+        $join($split($a).map(|$x| $e))
+    };
+}
+
+macro_rules! binop {
+    ($split:ident, $a:expr, $b:expr, $join:ident, |$x:ident, $y:ident| $e:expr) => {
+        $join(zip($split($a), $split($b), |$x, $y| $e))
+    };
+}
+
+/// Builds a vector by picking the lanes of `lo` then the lanes of `hi`.
+fn concat<T: Copy + Default, const N: usize, const M: usize>(lo: [T; N], hi: [T; N]) -> [T; M] {
+    let mut out = [T::default(); M];
+    out[..N].copy_from_slice(&lo);
+    out[N..].copy_from_slice(&hi);
+    out
+}
+
+/// Widens the lower (`high == false`) or upper half of the lanes of `a`.
+fn extend<T: Copy, U: Copy + Default, const N: usize, const M: usize>(
+    a: [T; N],
+    high: bool,
+    f: impl Fn(T) -> U,
+) -> [U; M] {
+    let base = if high { M } else { 0 };
+    let mut out = [U::default(); M];
+    for (i, lane) in out.iter_mut().enumerate() {
+        *lane = f(a[base + i]);
+    }
+    out
+}
+
+/// Adds adjacent pairs of lanes after widening them.
+fn pairwise<
+    T: Copy,
+    U: Copy + Default + std::ops::Add<Output = U>,
+    const N: usize,
+    const M: usize,
+>(
+    a: [T; N],
+    f: impl Fn(T) -> U,
+) -> [U; M] {
+    let mut out = [U::default(); M];
+    for (i, lane) in out.iter_mut().enumerate() {
+        *lane = f(a[2 * i]) + f(a[2 * i + 1]);
+    }
+    out
+}
+
+fn bitmask<T: Copy, const N: usize>(a: [T; N], negative: impl Fn(T) -> bool) -> u128 {
+    a.iter()
+        .enumerate()
+        .fold(0, |mask, (i, &lane)| mask | ((negative(lane) as u128) << i))
+}
+
+fn f32_min(a: f32, b: f32) -> f32 {
+    if a.is_nan() || b.is_nan() {
+        f32::NAN
+    } else if a == b {
+        // Distinguish between -0.0 and +0.0.
+        f32::from_bits(a.to_bits() | b.to_bits())
+    } else {
+        a.min(b)
+    }
+}
+
+fn f32_max(a: f32, b: f32) -> f32 {
+    if a.is_nan() || b.is_nan() {
+        f32::NAN
+    } else if a == b {
+        // Distinguish between -0.0 and +0.0.
+        f32::from_bits(a.to_bits() & b.to_bits())
+    } else {
+        a.max(b)
+    }
+}
+
+fn f64_min(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        f64::from_bits(a.to_bits() | b.to_bits())
+    } else {
+        a.min(b)
+    }
+}
+
+fn f64_max(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        f64::from_bits(a.to_bits() & b.to_bits())
+    } else {
+        a.max(b)
+    }
+}
+
+/// Evaluates `op` on its operands.
+///
+/// Operands that are scalars (the value of a splat or of a replaced lane,
+/// a shift count) are passed in the low bits of `b` (or of `a` for splats).
+/// Lane indices are passed in `c`: one byte for the lane accessors, sixteen
+/// for `i8x16.shuffle`. Scalar results are returned in the low bits.
+pub(crate) fn eval(op: SimdOp, a: u128, b: u128, c: u128) -> u128 {
+    use SimdOp::*;
+
+    let lane = c as u8 as usize;
+    match op {
+        I8x16Shuffle => {
+            let input: [u8; 32] = concat(u8x16(a), u8x16(b));
+            from_u8x16(u8x16(c).map(|i| input[i as usize & 31]))
+        }
+        I8x16Swizzle => {
+            let input = u8x16(a);
+            from_u8x16(u8x16(b).map(|i| input.get(i as usize).copied().unwrap_or(0)))
+        }
+
+        I8x16Splat => from_u8x16([a as u8; 16]),
+        I16x8Splat => from_u16x8([a as u16; 8]),
+        I32x4Splat | F32x4Splat => from_u32x4([a as u32; 4]),
+        I64x2Splat | F64x2Splat => from_u64x2([a as u64; 2]),
+
+        I8x16ExtractLaneS => i8x16(a)[lane] as i32 as u32 as u128,
+        I8x16ExtractLaneU => u8x16(a)[lane] as u128,
+        I16x8ExtractLaneS => i16x8(a)[lane] as i32 as u32 as u128,
+        I16x8ExtractLaneU => u16x8(a)[lane] as u128,
+        I32x4ExtractLane | F32x4ExtractLane => u32x4(a)[lane] as u128,
+        I64x2ExtractLane | F64x2ExtractLane => u64x2(a)[lane] as u128,
+
+        I8x16ReplaceLane => {
+            let mut v = u8x16(a);
+            v[lane] = b as u8;
+            from_u8x16(v)
+        }
+        I16x8ReplaceLane => {
+            let mut v = u16x8(a);
+            v[lane] = b as u16;
+            from_u16x8(v)
+        }
+        I32x4ReplaceLane | F32x4ReplaceLane => {
+            let mut v = u32x4(a);
+            v[lane] = b as u32;
+            from_u32x4(v)
+        }
+        I64x2ReplaceLane | F64x2ReplaceLane => {
+            let mut v = u64x2(a);
+            v[lane] = b as u64;
+            from_u64x2(v)
+        }
+
+        I8x16Eq => cmp!(i8x16, a, b, from_i8x16, |x, y| x == y),
+        I8x16Ne => cmp!(i8x16, a, b, from_i8x16, |x, y| x != y),
+        I8x16LtS => cmp!(i8x16, a, b, from_i8x16, |x, y| x < y),
+        I8x16LtU => cmp!(u8x16, a, b, from_i8x16, |x, y| x < y),
+        I8x16GtS => cmp!(i8x16, a, b, from_i8x16, |x, y| x > y),
+        I8x16GtU => cmp!(u8x16, a, b, from_i8x16, |x, y| x > y),
+        I8x16LeS => cmp!(i8x16, a, b, from_i8x16, |x, y| x <= y),
+        I8x16LeU => cmp!(u8x16, a, b, from_i8x16, |x, y| x <= y),
+        I8x16GeS => cmp!(i8x16, a, b, from_i8x16, |x, y| x >= y),
+        I8x16GeU => cmp!(u8x16, a, b, from_i8x16, |x, y| x >= y),
+        I16x8Eq => cmp!(i16x8, a, b, from_i16x8, |x, y| x == y),
+        I16x8Ne => cmp!(i16x8, a, b, from_i16x8, |x, y| x != y),
+        I16x8LtS => cmp!(i16x8, a, b, from_i16x8, |x, y| x < y),
+        I16x8LtU => cmp!(u16x8, a, b, from_i16x8, |x, y| x < y),
+        I16x8GtS => cmp!(i16x8, a, b, from_i16x8, |x, y| x > y),
+        I16x8GtU => cmp!(u16x8, a, b, from_i16x8, |x, y| x > y),
+        I16x8LeS => cmp!(i16x8, a, b, from_i16x8, |x, y| x <= y),
+        I16x8LeU => cmp!(u16x8, a, b, from_i16x8, |x, y| x <= y),
+        I16x8GeS => cmp!(i16x8, a, b, from_i16x8, |x, y| x >= y),
+        I16x8GeU => cmp!(u16x8, a, b, from_i16x8, |x, y| x >= y),
+        I32x4Eq => cmp!(i32x4, a, b, from_i32x4, |x, y| x == y),
+        I32x4Ne => cmp!(i32x4, a, b, from_i32x4, |x, y| x != y),
+        I32x4LtS => cmp!(i32x4, a, b, from_i32x4, |x, y| x < y),
+        I32x4LtU => cmp!(u32x4, a, b, from_i32x4, |x, y| x < y),
+        I32x4GtS => cmp!(i32x4, a, b, from_i32x4, |x, y| x > y),
+        I32x4GtU => cmp!(u32x4, a, b, from_i32x4, |x, y| x > y),
+        I32x4LeS => cmp!(i32x4, a, b, from_i32x4, |x, y| x <= y),
+        I32x4LeU => cmp!(u32x4, a, b, from_i32x4, |x, y| x <= y),
+        I32x4GeS => cmp!(i32x4, a, b, from_i32x4, |x, y| x >= y),
+        I32x4GeU => cmp!(u32x4, a, b, from_i32x4, |x, y| x >= y),
+        I64x2Eq => cmp!(i64x2, a, b, from_i64x2, |x, y| x == y),
+        I64x2Ne => cmp!(i64x2, a, b, from_i64x2, |x, y| x != y),
+        I64x2LtS => cmp!(i64x2, a, b, from_i64x2, |x, y| x < y),
+        I64x2GtS => cmp!(i64x2, a, b, from_i64x2, |x, y| x > y),
+        I64x2LeS => cmp!(i64x2, a, b, from_i64x2, |x, y| x <= y),
+        I64x2GeS => cmp!(i64x2, a, b, from_i64x2, |x, y| x >= y),
+        F32x4Eq => cmp!(f32x4, a, b, from_i32x4, |x, y| x == y),
+        F32x4Ne => cmp!(f32x4, a, b, from_i32x4, |x, y| x != y),
+        F32x4Lt => cmp!(f32x4, a, b, from_i32x4, |x, y| x < y),
+        F32x4Gt => cmp!(f32x4, a, b, from_i32x4, |x, y| x > y),
+        F32x4Le => cmp!(f32x4, a, b, from_i32x4, |x, y| x <= y),
+        F32x4Ge => cmp!(f32x4, a, b, from_i32x4, |x, y| x >= y),
+        F64x2Eq => cmp!(f64x2, a, b, from_i64x2, |x, y| x == y),
+        F64x2Ne => cmp!(f64x2, a, b, from_i64x2, |x, y| x != y),
+        F64x2Lt => cmp!(f64x2, a, b, from_i64x2, |x, y| x < y),
+        F64x2Gt => cmp!(f64x2, a, b, from_i64x2, |x, y| x > y),
+        F64x2Le => cmp!(f64x2, a, b, from_i64x2, |x, y| x <= y),
+        F64x2Ge => cmp!(f64x2, a, b, from_i64x2, |x, y| x >= y),
+
+        V128Not => !a,
+        V128And => a & b,
+        V128AndNot => a & !b,
+        V128Or => a | b,
+        V128Xor => a ^ b,
+        V128Bitselect => (a & c) | (b & !c),
+        V128AnyTrue => (a != 0) as u128,
+
+        F32x4DemoteF64x2Zero => {
+            let [x, y] = f64x2(a);
+            from_f32x4([x as f32, y as f32, 0.0, 0.0])
+        }
+        F64x2PromoteLowF32x4 => {
+            let [x, y, _, _] = f32x4(a);
+            from_f64x2([x as f64, y as f64])
+        }
+
+        I8x16Abs => unop!(i8x16, a, from_i8x16, |x| x.wrapping_abs()),
+        I8x16Neg => unop!(i8x16, a, from_i8x16, |x| x.wrapping_neg()),
+        I8x16Popcnt => unop!(u8x16, a, from_u8x16, |x| x.count_ones() as u8),
+        I8x16AllTrue => u8x16(a).iter().all(|&x| x != 0) as u128,
+        I8x16Bitmask => bitmask(i8x16(a), |x| x < 0),
+        I8x16NarrowI16x8S => {
+            let lanes: [i16; 16] = concat(i16x8(a), i16x8(b));
+            from_i8x16(lanes.map(|x| x.clamp(i8::MIN as i16, i8::MAX as i16) as i8))
+        }
+        I8x16NarrowI16x8U => {
+            let lanes: [i16; 16] = concat(i16x8(a), i16x8(b));
+            from_u8x16(lanes.map(|x| x.clamp(0, u8::MAX as i16) as u8))
+        }
+        F32x4Ceil => unop!(f32x4, a, from_f32x4, |x| x.ceil()),
+        F32x4Floor => unop!(f32x4, a, from_f32x4, |x| x.floor()),
+        F32x4Trunc => unop!(f32x4, a, from_f32x4, |x| x.trunc()),
+        F32x4Nearest => unop!(f32x4, a, from_f32x4, |x| wasmer_vm_f32_nearest(x)),
+        I8x16Shl => unop!(i8x16, a, from_i8x16, |x| x.wrapping_shl(b as u32)),
+        I8x16ShrS => unop!(i8x16, a, from_i8x16, |x| x.wrapping_shr(b as u32)),
+        I8x16ShrU => unop!(u8x16, a, from_u8x16, |x| x.wrapping_shr(b as u32)),
+        I8x16Add => binop!(i8x16, a, b, from_i8x16, |x, y| x.wrapping_add(y)),
+        I8x16AddSatS => binop!(i8x16, a, b, from_i8x16, |x, y| x.saturating_add(y)),
+        I8x16AddSatU => binop!(u8x16, a, b, from_u8x16, |x, y| x.saturating_add(y)),
+        I8x16Sub => binop!(i8x16, a, b, from_i8x16, |x, y| x.wrapping_sub(y)),
+        I8x16SubSatS => binop!(i8x16, a, b, from_i8x16, |x, y| x.saturating_sub(y)),
+        I8x16SubSatU => binop!(u8x16, a, b, from_u8x16, |x, y| x.saturating_sub(y)),
+        F64x2Ceil => unop!(f64x2, a, from_f64x2, |x| x.ceil()),
+        F64x2Floor => unop!(f64x2, a, from_f64x2, |x| x.floor()),
+        I8x16MinS => binop!(i8x16, a, b, from_i8x16, |x, y| x.min(y)),
+        I8x16MinU => binop!(u8x16, a, b, from_u8x16, |x, y| x.min(y)),
+        I8x16MaxS => binop!(i8x16, a, b, from_i8x16, |x, y| x.max(y)),
+        I8x16MaxU => binop!(u8x16, a, b, from_u8x16, |x, y| x.max(y)),
+        F64x2Trunc => unop!(f64x2, a, from_f64x2, |x| x.trunc()),
+        I8x16AvgrU => binop!(u8x16, a, b, from_u8x16, |x, y| {
+            ((x as u16 + y as u16 + 1) >> 1) as u8
+        }),
+        I16x8ExtAddPairwiseI8x16S => from_i16x8(pairwise(i8x16(a), i16::from)),
+        I16x8ExtAddPairwiseI8x16U => from_u16x8(pairwise(u8x16(a), u16::from)),
+        I32x4ExtAddPairwiseI16x8S => from_i32x4(pairwise(i16x8(a), i32::from)),
+        I32x4ExtAddPairwiseI16x8U => from_u32x4(pairwise(u16x8(a), u32::from)),
+
+        I16x8Abs => unop!(i16x8, a, from_i16x8, |x| x.wrapping_abs()),
+        I16x8Neg => unop!(i16x8, a, from_i16x8, |x| x.wrapping_neg()),
+        I16x8Q15MulrSatS => binop!(i16x8, a, b, from_i16x8, |x, y| {
+            ((x as i32 * y as i32 + 0x4000) >> 15).clamp(i16::MIN as i32, i16::MAX as i32) as i16
+        }),
+        I16x8AllTrue => u16x8(a).iter().all(|&x| x != 0) as u128,
+        I16x8Bitmask => bitmask(i16x8(a), |x| x < 0),
+        I16x8NarrowI32x4S => {
+            let lanes: [i32; 8] = concat(i32x4(a), i32x4(b));
+            from_i16x8(lanes.map(|x| x.clamp(i16::MIN as i32, i16::MAX as i32) as i16))
+        }
+        I16x8NarrowI32x4U => {
+            let lanes: [i32; 8] = concat(i32x4(a), i32x4(b));
+            from_u16x8(lanes.map(|x| x.clamp(0, u16::MAX as i32) as u16))
+        }
+        I16x8ExtendLowI8x16S => from_i16x8(extend(i8x16(a), false, i16::from)),
+        I16x8ExtendHighI8x16S => from_i16x8(extend(i8x16(a), true, i16::from)),
+        I16x8ExtendLowI8x16U => from_u16x8(extend(u8x16(a), false, u16::from)),
+        I16x8ExtendHighI8x16U => from_u16x8(extend(u8x16(a), true, u16::from)),
+        I16x8Shl => unop!(i16x8, a, from_i16x8, |x| x.wrapping_shl(b as u32)),
+        I16x8ShrS => unop!(i16x8, a, from_i16x8, |x| x.wrapping_shr(b as u32)),
+        I16x8ShrU => unop!(u16x8, a, from_u16x8, |x| x.wrapping_shr(b as u32)),
+        I16x8Add => binop!(i16x8, a, b, from_i16x8, |x, y| x.wrapping_add(y)),
+        I16x8AddSatS => binop!(i16x8, a, b, from_i16x8, |x, y| x.saturating_add(y)),
+        I16x8AddSatU => binop!(u16x8, a, b, from_u16x8, |x, y| x.saturating_add(y)),
+        I16x8Sub => binop!(i16x8, a, b, from_i16x8, |x, y| x.wrapping_sub(y)),
+        I16x8SubSatS => binop!(i16x8, a, b, from_i16x8, |x, y| x.saturating_sub(y)),
+        I16x8SubSatU => binop!(u16x8, a, b, from_u16x8, |x, y| x.saturating_sub(y)),
+        F64x2Nearest => unop!(f64x2, a, from_f64x2, |x| wasmer_vm_f64_nearest(x)),
+        I16x8Mul => binop!(i16x8, a, b, from_i16x8, |x, y| x.wrapping_mul(y)),
+        I16x8MinS => binop!(i16x8, a, b, from_i16x8, |x, y| x.min(y)),
+        I16x8MinU => binop!(u16x8, a, b, from_u16x8, |x, y| x.min(y)),
+        I16x8MaxS => binop!(i16x8, a, b, from_i16x8, |x, y| x.max(y)),
+        I16x8MaxU => binop!(u16x8, a, b, from_u16x8, |x, y| x.max(y)),
+        I16x8AvgrU => binop!(u16x8, a, b, from_u16x8, |x, y| {
+            ((x as u32 + y as u32 + 1) >> 1) as u16
+        }),
+        I16x8ExtMulLowI8x16S | I16x8ExtMulHighI8x16S => {
+            let high = op == I16x8ExtMulHighI8x16S;
+            let x: [i16; 8] = extend(i8x16(a), high, i16::from);
+            let y: [i16; 8] = extend(i8x16(b), high, i16::from);
+            from_i16x8(zip(x, y, |x, y| x.wrapping_mul(y)))
+        }
+        I16x8ExtMulLowI8x16U | I16x8ExtMulHighI8x16U => {
+            let high = op == I16x8ExtMulHighI8x16U;
+            let x: [u16; 8] = extend(u8x16(a), high, u16::from);
+            let y: [u16; 8] = extend(u8x16(b), high, u16::from);
+            from_u16x8(zip(x, y, |x, y| x.wrapping_mul(y)))
+        }
+
+        I32x4Abs => unop!(i32x4, a, from_i32x4, |x| x.wrapping_abs()),
+        I32x4Neg => unop!(i32x4, a, from_i32x4, |x| x.wrapping_neg()),
+        I32x4AllTrue => u32x4(a).iter().all(|&x| x != 0) as u128,
+        I32x4Bitmask => bitmask(i32x4(a), |x| x < 0),
+        I32x4ExtendLowI16x8S => from_i32x4(extend(i16x8(a), false, i32::from)),
+        I32x4ExtendHighI16x8S => from_i32x4(extend(i16x8(a), true, i32::from)),
+        I32x4ExtendLowI16x8U => from_u32x4(extend(u16x8(a), false, u32::from)),
+        I32x4ExtendHighI16x8U => from_u32x4(extend(u16x8(a), true, u32::from)),
+        I32x4Shl => unop!(i32x4, a, from_i32x4, |x| x.wrapping_shl(b as u32)),
+        I32x4ShrS => unop!(i32x4, a, from_i32x4, |x| x.wrapping_shr(b as u32)),
+        I32x4ShrU => unop!(u32x4, a, from_u32x4, |x| x.wrapping_shr(b as u32)),
+        I32x4Add => binop!(i32x4, a, b, from_i32x4, |x, y| x.wrapping_add(y)),
+        I32x4Sub => binop!(i32x4, a, b, from_i32x4, |x, y| x.wrapping_sub(y)),
+        I32x4Mul => binop!(i32x4, a, b, from_i32x4, |x, y| x.wrapping_mul(y)),
+        I32x4MinS => binop!(i32x4, a, b, from_i32x4, |x, y| x.min(y)),
+        I32x4MinU => binop!(u32x4, a, b, from_u32x4, |x, y| x.min(y)),
+        I32x4MaxS => binop!(i32x4, a, b, from_i32x4, |x, y| x.max(y)),
+        I32x4MaxU => binop!(u32x4, a, b, from_u32x4, |x, y| x.max(y)),
+        I32x4DotI16x8S => {
+            let products = zip(i16x8(a), i16x8(b), |x, y| x as i32 * y as i32);
+            let mut out = [0i32; 4];
+            for (i, lane) in out.iter_mut().enumerate() {
+                *lane = products[2 * i].wrapping_add(products[2 * i + 1]);
+            }
+            from_i32x4(out)
+        }
+        I32x4ExtMulLowI16x8S | I32x4ExtMulHighI16x8S => {
+            let high = op == I32x4ExtMulHighI16x8S;
+            let x: [i32; 4] = extend(i16x8(a), high, i32::from);
+            let y: [i32; 4] = extend(i16x8(b), high, i32::from);
+            from_i32x4(zip(x, y, |x, y| x.wrapping_mul(y)))
+        }
+        I32x4ExtMulLowI16x8U | I32x4ExtMulHighI16x8U => {
+            let high = op == I32x4ExtMulHighI16x8U;
+            let x: [u32; 4] = extend(u16x8(a), high, u32::from);
+            let y: [u32; 4] = extend(u16x8(b), high, u32::from);
+            from_u32x4(zip(x, y, |x, y| x.wrapping_mul(y)))
+        }
+
+        I64x2Abs => unop!(i64x2, a, from_i64x2, |x| x.wrapping_abs()),
+        I64x2Neg => unop!(i64x2, a, from_i64x2, |x| x.wrapping_neg()),
+        I64x2AllTrue => u64x2(a).iter().all(|&x| x != 0) as u128,
+        I64x2Bitmask => bitmask(i64x2(a), |x| x < 0),
+        I64x2ExtendLowI32x4S => from_i64x2(extend(i32x4(a), false, i64::from)),
+        I64x2ExtendHighI32x4S => from_i64x2(extend(i32x4(a), true, i64::from)),
+        I64x2ExtendLowI32x4U => from_u64x2(extend(u32x4(a), false, u64::from)),
+        I64x2ExtendHighI32x4U => from_u64x2(extend(u32x4(a), true, u64::from)),
+        I64x2Shl => unop!(i64x2, a, from_i64x2, |x| x.wrapping_shl(b as u32)),
+        I64x2ShrS => unop!(i64x2, a, from_i64x2, |x| x.wrapping_shr(b as u32)),
+        I64x2ShrU => unop!(u64x2, a, from_u64x2, |x| x.wrapping_shr(b as u32)),
+        I64x2Add => binop!(i64x2, a, b, from_i64x2, |x, y| x.wrapping_add(y)),
+        I64x2Sub => binop!(i64x2, a, b, from_i64x2, |x, y| x.wrapping_sub(y)),
+        I64x2Mul => binop!(i64x2, a, b, from_i64x2, |x, y| x.wrapping_mul(y)),
+        I64x2ExtMulLowI32x4S | I64x2ExtMulHighI32x4S => {
+            let high = op == I64x2ExtMulHighI32x4S;
+            let x: [i64; 2] = extend(i32x4(a), high, i64::from);
+            let y: [i64; 2] = extend(i32x4(b), high, i64::from);
+            from_i64x2(zip(x, y, |x, y| x.wrapping_mul(y)))
+        }
+        I64x2ExtMulLowI32x4U | I64x2ExtMulHighI32x4U => {
+            let high = op == I64x2ExtMulHighI32x4U;
+            let x: [u64; 2] = extend(u32x4(a), high, u64::from);
+            let y: [u64; 2] = extend(u32x4(b), high, u64::from);
+            from_u64x2(zip(x, y, |x, y| x.wrapping_mul(y)))
+        }
+
+        F32x4Abs => unop!(u32x4, a, from_u32x4, |x| x & !(1 << 31)),
+        F32x4Neg => unop!(u32x4, a, from_u32x4, |x| x ^ (1 << 31)),
+        F32x4Sqrt => unop!(f32x4, a, from_f32x4, |x| x.sqrt()),
+        F32x4Add => binop!(f32x4, a, b, from_f32x4, |x, y| x + y),
+        F32x4Sub => binop!(f32x4, a, b, from_f32x4, |x, y| x - y),
+        F32x4Mul => binop!(f32x4, a, b, from_f32x4, |x, y| x * y),
+        F32x4Div => binop!(f32x4, a, b, from_f32x4, |x, y| x / y),
+        F32x4Min => from_f32x4(zip(f32x4(a), f32x4(b), f32_min)),
+        F32x4Max => from_f32x4(zip(f32x4(a), f32x4(b), f32_max)),
+        F32x4PMin => binop!(f32x4, a, b, from_f32x4, |x, y| if y < x { y } else { x }),
+        F32x4PMax => binop!(f32x4, a, b, from_f32x4, |x, y| if x < y { y } else { x }),
+        F64x2Abs => unop!(u64x2, a, from_u64x2, |x| x & !(1 << 63)),
+        F64x2Neg => unop!(u64x2, a, from_u64x2, |x| x ^ (1 << 63)),
+        F64x2Sqrt => unop!(f64x2, a, from_f64x2, |x| x.sqrt()),
+        F64x2Add => binop!(f64x2, a, b, from_f64x2, |x, y| x + y),
+        F64x2Sub => binop!(f64x2, a, b, from_f64x2, |x, y| x - y),
+        F64x2Mul => binop!(f64x2, a, b, from_f64x2, |x, y| x * y),
+        F64x2Div => binop!(f64x2, a, b, from_f64x2, |x, y| x / y),
+        F64x2Min => from_f64x2(zip(f64x2(a), f64x2(b), f64_min)),
+        F64x2Max => from_f64x2(zip(f64x2(a), f64x2(b), f64_max)),
+        F64x2PMin => binop!(f64x2, a, b, from_f64x2, |x, y| if y < x { y } else { x }),
+        F64x2PMax => binop!(f64x2, a, b, from_f64x2, |x, y| if x < y { y } else { x }),
+
+        // Float to integer casts saturate, and turn NaN into 0.
+        I32x4TruncSatF32x4S => unop!(f32x4, a, from_i32x4, |x| x as i32),
+        I32x4TruncSatF32x4U => unop!(f32x4, a, from_u32x4, |x| x as u32),
+        F32x4ConvertI32x4S => unop!(i32x4, a, from_f32x4, |x| x as f32),
+        F32x4ConvertI32x4U => unop!(u32x4, a, from_f32x4, |x| x as f32),
+        I32x4TruncSatF64x2SZero => {
+            let [x, y] = f64x2(a);
+            from_i32x4([x as i32, y as i32, 0, 0])
+        }
+        I32x4TruncSatF64x2UZero => {
+            let [x, y] = f64x2(a);
+            from_u32x4([x as u32, y as u32, 0, 0])
+        }
+        F64x2ConvertLowI32x4S => {
+            let [x, y, _, _] = i32x4(a);
+            from_f64x2([x as f64, y as f64])
+        }
+        F64x2ConvertLowI32x4U => {
+            let [x, y, _, _] = u32x4(a);
+            from_f64x2([x as f64, y as f64])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lanes_are_little_endian() {
+        let v = 0x0f0e0d0c_0b0a0908_07060504_03020100u128;
+        assert_eq!(u8x16(v)[0], 0x00);
+        assert_eq!(u8x16(v)[15], 0x0f);
+        assert_eq!(u32x4(v), [0x03020100, 0x07060504, 0x0b0a0908, 0x0f0e0d0c]);
+        assert_eq!(from_u32x4(u32x4(v)), v);
+    }
+
+    #[test]
+    fn shuffle_and_swizzle() {
+        let a = from_u8x16([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let b = from_u8x16([
+            16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31,
+        ]);
+        let lanes = from_u8x16([31, 0, 30, 1, 29, 2, 28, 3, 27, 4, 26, 5, 25, 6, 24, 7]);
+        assert_eq!(eval(SimdOp::I8x16Shuffle, a, b, lanes), lanes);
+
+        let indices = from_u8x16([15, 16, 0, 255, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        assert_eq!(
+            u8x16(eval(SimdOp::I8x16Swizzle, a, indices, 0)),
+            [15, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]
+        );
+    }
+
+    #[test]
+    fn float_min_max() {
+        let a = from_f32x4([-0.0, 1.0, f32::NAN, 2.0]);
+        let b = from_f32x4([0.0, -1.0, 1.0, 2.0]);
+        let min = f32x4(eval(SimdOp::F32x4Min, a, b, 0));
+        assert_eq!(min[0].to_bits(), (-0.0f32).to_bits());
+        assert_eq!(min[1], -1.0);
+        assert!(min[2].is_nan());
+        let max = f32x4(eval(SimdOp::F32x4Max, a, b, 0));
+        assert_eq!(max[0].to_bits(), 0.0f32.to_bits());
+        assert!(max[2].is_nan());
+        // pmin/pmax return the first operand unless the second one is strictly
+        // smaller/bigger.
+        let pmin = f32x4(eval(SimdOp::F32x4PMin, a, b, 0));
+        assert_eq!(pmin[0].to_bits(), (-0.0f32).to_bits());
+        assert!(pmin[2].is_nan());
+    }
+
+    #[test]
+    fn saturating_conversions() {
+        let a = from_f32x4([f32::NAN, -1.0, 3e9, -3e9]);
+        assert_eq!(
+            i32x4(eval(SimdOp::I32x4TruncSatF32x4S, a, 0, 0)),
+            [0, -1, i32::MAX, i32::MIN]
+        );
+        assert_eq!(
+            u32x4(eval(SimdOp::I32x4TruncSatF32x4U, a, 0, 0)),
+            [0, 0, 3_000_000_000, 0]
+        );
+        let narrowed = eval(
+            SimdOp::I8x16NarrowI16x8U,
+            from_i16x8([-1, 0, 255, 256, 1, 2, 3, 4]),
+            from_i16x8([i16::MIN; 8]),
+            0,
+        );
+        assert_eq!(&u8x16(narrowed)[..8], &[0, 0, 255, 255, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn scalar_results() {
+        let v = from_i16x8([-1, 2, -3, 4, -5, 6, -7, 8]);
+        assert_eq!(eval(SimdOp::I16x8Bitmask, v, 0, 0), 0b0101_0101);
+        assert_eq!(eval(SimdOp::I16x8AllTrue, v, 0, 0), 1);
+        assert_eq!(
+            eval(SimdOp::I16x8ExtractLaneS, v, 0, 2),
+            (-3i32) as u32 as u128
+        );
+        assert_eq!(eval(SimdOp::I16x8ExtractLaneU, v, 0, 2), 0xfffd);
+        assert_eq!(eval(SimdOp::V128AnyTrue, 0, 0, 0), 0);
+    }
+}
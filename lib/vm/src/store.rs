@@ -1,9 +1,13 @@
+use crate::epoch::VMEpochDeadline;
+use crate::limiter::VMResourceLimiter;
+use crate::trap::Trap;
+use crate::TableElement;
 use crate::{
-    VMExternObj, VMFunction, VMFunctionEnvironment, VMGlobal, VMInstance, VMMemory, VMTable,
+    VMExternObj, VMFunction, VMFunctionEnvironment, VMGlobal, VMInstance, VMMemory, VMTable, VMTag,
 };
 use core::slice::Iter;
 use std::{cell::UnsafeCell, fmt, marker::PhantomData, num::NonZeroUsize, ptr::NonNull};
-use wasmer_types::StoreId;
+use wasmer_types::{MemoryError, Pages, StoreId};
 
 /// Trait to represent an object managed by a context. This is implemented on
 /// the VM types managed by the context.
@@ -29,6 +33,7 @@ impl_context_object! {
     functions => VMFunction,
     tables => VMTable,
     globals => VMGlobal,
+    tags => VMTag,
     instances => VMInstance,
     memories => VMMemory,
     extern_objs => VMExternObj,
@@ -42,10 +47,13 @@ pub struct StoreObjects {
     memories: Vec<VMMemory>,
     tables: Vec<VMTable>,
     globals: Vec<VMGlobal>,
+    tags: Vec<VMTag>,
     functions: Vec<VMFunction>,
     instances: Vec<VMInstance>,
     extern_objs: Vec<VMExternObj>,
     function_environments: Vec<VMFunctionEnvironment>,
+    epoch_deadline: VMEpochDeadline,
+    resource_limiter: VMResourceLimiter,
 }
 
 impl StoreObjects {
@@ -59,6 +67,53 @@ impl StoreObjects {
         self.id = id;
     }
 
+    /// Returns the epoch deadline of this store.
+    pub fn epoch_deadline(&self) -> &VMEpochDeadline {
+        &self.epoch_deadline
+    }
+
+    /// Returns the epoch deadline of this store, mutably.
+    pub fn epoch_deadline_mut(&mut self) -> &mut VMEpochDeadline {
+        &mut self.epoch_deadline
+    }
+
+    /// Returns the resource limiter of this store.
+    pub fn resource_limiter(&self) -> &VMResourceLimiter {
+        &self.resource_limiter
+    }
+
+    /// Returns the resource limiter of this store, mutably.
+    pub fn resource_limiter_mut(&mut self) -> &mut VMResourceLimiter {
+        &mut self.resource_limiter
+    }
+
+    /// Grows a memory of this store by `delta` pages if its resource limiter
+    /// allows it, returning the previous number of pages.
+    ///
+    /// Returns an error if the limiter raised one.
+    pub fn grow_memory(
+        &mut self,
+        handle: InternalStoreHandle<VMMemory>,
+        delta: Pages,
+    ) -> Result<Result<Pages, MemoryError>, Trap> {
+        let memory = &mut self.memories[handle.index() - 1];
+        self.resource_limiter.grow_memory(memory, delta)
+    }
+
+    /// Grows a table of this store by `delta` elements if its resource
+    /// limiter allows it, returning the previous number of elements.
+    ///
+    /// Returns an error if the limiter raised one.
+    pub fn grow_table(
+        &mut self,
+        handle: InternalStoreHandle<VMTable>,
+        delta: u32,
+        init_value: TableElement,
+    ) -> Result<Option<u32>, Trap> {
+        let table = &mut self.tables[handle.index() - 1];
+        self.resource_limiter.grow_table(table, delta, init_value)
+    }
+
     /// Returns a pair of mutable references from two handles.
     ///
     /// Panics if both handles point to the same object.
@@ -5,6 +5,7 @@
 //!
 //! `Table` is to WebAssembly tables what `Memory` is to WebAssembly linear memories.
 
+use crate::pooling::PoolSlot;
 use crate::store::MaybeInstanceOwned;
 use crate::vmcontext::VMTableDefinition;
 use crate::Trap;
@@ -14,7 +15,9 @@ use derivative::Derivative;
 use std::cell::UnsafeCell;
 use std::convert::TryFrom;
 use std::fmt;
+use std::mem;
 use std::ptr::NonNull;
+use std::slice;
 use wasmer_types::TableStyle;
 use wasmer_types::{TableType, TrapCode, Type as ValType};
 
@@ -69,12 +72,62 @@ impl Default for TableElement {
     }
 }
 
+/// The elements of a table, either allocated on the heap or stored in a
+/// slot of a `PoolingInstanceAllocator`.
+enum TableElements {
+    Vec(Vec<RawTableElement>),
+    Pooled { slot: PoolSlot, len: usize },
+}
+
+impl TableElements {
+    fn as_slice(&self) -> &[RawTableElement] {
+        match self {
+            Self::Vec(vec) => vec,
+            Self::Pooled { slot, len } => unsafe {
+                slice::from_raw_parts(slot.as_mut_ptr() as *const RawTableElement, *len)
+            },
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [RawTableElement] {
+        match self {
+            Self::Vec(vec) => vec,
+            Self::Pooled { slot, len } => unsafe {
+                slice::from_raw_parts_mut(slot.as_mut_ptr() as *mut RawTableElement, *len)
+            },
+        }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut RawTableElement {
+        self.as_mut_slice().as_mut_ptr()
+    }
+
+    /// Resizes the elements to `new_len`, filling the new elements with
+    /// `value`. Returns `false` if the slot of pooled elements is too small.
+    fn resize(&mut self, new_len: usize, value: RawTableElement) -> bool {
+        match self {
+            Self::Vec(vec) => vec.resize(new_len, value),
+            Self::Pooled { slot, len } => {
+                if new_len > slot.len() / VMTable::element_size() {
+                    return false;
+                }
+                let old_len = *len;
+                *len = new_len;
+                if new_len > old_len {
+                    self.as_mut_slice()[old_len..].fill(value);
+                }
+            }
+        }
+        true
+    }
+}
+
 /// A table instance.
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct VMTable {
     #[derivative(Debug = "ignore")]
-    vec: Vec<RawTableElement>,
+    elements: TableElements,
     maximum: Option<u32>,
     /// The WebAssembly table description.
     table: TableType,
@@ -90,12 +143,17 @@ impl VMTable {
     /// This creates a `Table` with metadata owned by a VM, pointed to by
     /// `vm_table_location`: this can be used to create a local table.
     pub fn new(table: &TableType, style: &TableStyle) -> Result<Self, String> {
-        unsafe { Self::new_inner(table, style, None) }
+        unsafe { Self::new_inner(table, style, None, None) }
     }
 
     /// Returns the size of the table
     pub fn get_runtime_size(&self) -> u32 {
-        self.vec.len() as u32
+        self.elements.as_slice().len() as u32
+    }
+
+    /// Returns the size in bytes of a table element.
+    pub(crate) fn element_size() -> usize {
+        mem::size_of::<RawTableElement>()
     }
 
     /// Create a new linear table instance with specified minimum and maximum number of elements.
@@ -110,7 +168,21 @@ impl VMTable {
         style: &TableStyle,
         vm_table_location: NonNull<VMTableDefinition>,
     ) -> Result<Self, String> {
-        Self::new_inner(table, style, Some(vm_table_location))
+        Self::new_inner(table, style, Some(vm_table_location), None)
+    }
+
+    /// Create a new table with VM owned metadata, storing its elements in a
+    /// committed pool slot.
+    ///
+    /// # Safety
+    /// - `vm_table_location` must point to a valid location in VM memory.
+    pub(crate) unsafe fn from_definition_in_slot(
+        table: &TableType,
+        style: &TableStyle,
+        vm_table_location: NonNull<VMTableDefinition>,
+        slot: PoolSlot,
+    ) -> Result<Self, String> {
+        Self::new_inner(table, style, Some(vm_table_location), Some(slot))
     }
 
     /// Create a new `Table` with either self-owned or VM owned metadata.
@@ -118,6 +190,7 @@ impl VMTable {
         table: &TableType,
         style: &TableStyle,
         vm_table_location: Option<NonNull<VMTableDefinition>>,
+        slot: Option<PoolSlot>,
     ) -> Result<Self, String> {
         match table.ty {
             ValType::FuncRef | ValType::ExternRef => (),
@@ -138,11 +211,23 @@ impl VMTable {
         }
         let table_minimum = usize::try_from(table.minimum)
             .map_err(|_| "Table minimum is bigger than usize".to_string())?;
-        let mut vec = vec![RawTableElement::default(); table_minimum];
-        let base = vec.as_mut_ptr();
+        let mut elements = match slot {
+            // The zeroed elements of the slot are null references.
+            Some(slot) => {
+                if table_minimum > slot.len() / Self::element_size() {
+                    return Err("Table minimum is larger than its pool slot".to_string());
+                }
+                TableElements::Pooled {
+                    slot,
+                    len: table_minimum,
+                }
+            }
+            None => TableElements::Vec(vec![RawTableElement::default(); table_minimum]),
+        };
+        let base = elements.as_mut_ptr();
         match style {
             TableStyle::CallerChecksSignature => Ok(Self {
-                vec,
+                elements,
                 maximum: table.maximum,
                 table: *table,
                 style: style.clone(),
@@ -204,15 +289,19 @@ impl VMTable {
             return Some(size);
         }
 
-        self.vec
-            .resize(usize::try_from(new_len).unwrap(), init_value.into());
+        if !self
+            .elements
+            .resize(usize::try_from(new_len).unwrap(), init_value.into())
+        {
+            return None;
+        }
 
         // update table definition
         unsafe {
             let mut td_ptr = self.get_vm_table_definition();
             let td = td_ptr.as_mut();
             td.current_elements = new_len;
-            td.base = self.vec.as_mut_ptr() as _;
+            td.base = self.elements.as_mut_ptr() as _;
         }
         Some(size)
     }
@@ -221,7 +310,7 @@ impl VMTable {
     ///
     /// Returns `None` if the index is out of bounds.
     pub fn get(&self, index: u32) -> Option<TableElement> {
-        let raw_data = self.vec.get(index as usize).cloned()?;
+        let raw_data = self.elements.as_slice().get(index as usize).cloned()?;
         Some(match self.table.ty {
             ValType::ExternRef => TableElement::ExternRef(unsafe { raw_data.extern_ref }),
             ValType::FuncRef => TableElement::FuncRef(unsafe { raw_data.func_ref }),
@@ -235,7 +324,7 @@ impl VMTable {
     ///
     /// Returns an error if the index is out of bounds.
     pub fn set(&mut self, index: u32, reference: TableElement) -> Result<(), Trap> {
-        match self.vec.get_mut(index as usize) {
+        match self.elements.as_mut_slice().get_mut(index as usize) {
             Some(slot) => {
                 match (self.table.ty, reference) {
                     (ValType::ExternRef, r @ TableElement::ExternRef(_)) => {
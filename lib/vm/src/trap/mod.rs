@@ -11,7 +11,8 @@ mod traphandlers;
 pub use trap::Trap;
 pub use traphandlers::{
     catch_traps, on_host_stack, raise_lib_trap, raise_user_trap, set_stack_size,
-    wasmer_call_trampoline, TrapHandlerFn, VMConfig,
+    suspend_until_ready, wasmer_call_trampoline, wasmer_call_trampoline_async, AsyncWasmCall,
+    TrapHandlerFn, VMConfig,
 };
 pub use traphandlers::{init_traps, resume_panic};
 pub use wasmer_types::TrapCode;
@@ -10,18 +10,22 @@ use backtrace::Backtrace;
 use core::ptr::{read, read_unaligned};
 use corosensei::stack::DefaultStack;
 use corosensei::trap::{CoroutineTrapHandler, TrapHandlerRegs};
-use corosensei::{CoroutineResult, ScopedCoroutine, Yielder};
+use corosensei::{Coroutine, CoroutineResult, ScopedCoroutine, Yielder};
 use scopeguard::defer;
 use std::any::Any;
 use std::cell::Cell;
 use std::error::Error;
+use std::future::Future;
 use std::io;
 use std::mem;
 #[cfg(unix)]
 use std::mem::MaybeUninit;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::{pin, Pin};
 use std::ptr::{self, NonNull};
 use std::sync::atomic::{compiler_fence, AtomicPtr, AtomicUsize, Ordering};
 use std::sync::Once;
+use std::task::{Context, Poll};
 use wasmer_types::TrapCode;
 
 /// Configuration for the the runtime VM
@@ -130,6 +134,8 @@ unsafe fn process_illegal_op(addr: usize) -> Option<TrapCode> {
             8 => Some(TrapCode::BadConversionToInteger),
             9 => Some(TrapCode::UnreachableCodeReached),
             10 => Some(TrapCode::UnalignedAtomic),
+            11 => Some(TrapCode::CallDepthExceeded),
+            12 => Some(TrapCode::Interrupt),
             _ => None,
         },
     }
@@ -669,6 +675,188 @@ pub unsafe fn wasmer_call_trampoline(
     })
 }
 
+/// Call the wasm function pointed to by `callee`, like
+/// [`wasmer_call_trampoline`], on a stack that the async host functions
+/// it calls can suspend while they wait for a future, see
+/// [`suspend_until_ready`].
+///
+/// The call only starts once the returned [`AsyncWasmCall`] is polled.
+///
+/// # Safety
+///
+/// Wildly unsafe because it calls raw function pointers and reads/writes raw
+/// function pointers. `values_vec` must stay valid until the call completes.
+pub unsafe fn wasmer_call_trampoline_async(
+    trap_handler: Option<*const TrapHandlerFn<'static>>,
+    config: &VMConfig,
+    vmctx: VMFunctionContext,
+    trampoline: VMTrampoline,
+    callee: *const VMFunctionBody,
+    values_vec: *mut u8,
+) -> AsyncWasmCall {
+    let stack_size = config
+        .wasm_stack_size
+        .unwrap_or_else(|| DEFAULT_STACK_SIZE.load(Ordering::Relaxed));
+    let stack = STACK_POOL
+        .pop()
+        .unwrap_or_else(|| DefaultStack::new(stack_size).unwrap());
+    let coro = Coroutine::with_stack(stack, move |yielder, _: Resume| {
+        set_yielder(Some(yielder.into()));
+
+        mem::transmute::<_, extern "C" fn(VMFunctionContext, *const VMFunctionBody, *mut u8)>(
+            trampoline,
+        )(vmctx, callee, values_vec);
+        Ok(())
+    });
+    AsyncWasmCall {
+        coro: Some(coro),
+        trap_handler,
+    }
+}
+
+/// A call into wasm created by [`wasmer_call_trampoline_async`], which
+/// completes when it is polled as a future.
+///
+/// The call runs on a stack of its own. When an async host function waits
+/// for a future that is pending, the stack is suspended and the call
+/// returns `Poll::Pending`; it resumes where it left off on the next poll.
+///
+/// Dropping the call while it is suspended resumes it one last time: the
+/// async host function it is suspended in drops its future and traps,
+/// unwinding the call so that the objects on its stack are dropped.
+pub struct AsyncWasmCall {
+    coro: Option<Coroutine<Resume, Suspend, Result<(), UnwindReason>, DefaultStack>>,
+    trap_handler: Option<*const TrapHandlerFn<'static>>,
+}
+
+// The stack of the call, and the host futures suspended on it, are only
+// accessed by the task polling the call, which holds the `Store` mutably.
+unsafe impl Send for AsyncWasmCall {}
+
+impl Future for AsyncWasmCall {
+    type Output = Result<(), Trap>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Ensure that per-thread initialization is done, as the call may be
+        // polled from another thread than the one it started on.
+        lazy_per_thread_init()?;
+
+        let trap_handler = self.trap_handler;
+        let coro = self
+            .coro
+            .as_mut()
+            .expect("`AsyncWasmCall` polled after completion");
+
+        // The host functions suspending the call wait for the future on the
+        // task of this poll.
+        let context = NonNull::from(cx).cast::<Context<'static>>();
+        let prev_context = ASYNC_CONTEXT.with(|cell| cell.replace(Some(context)));
+        let prev_yielder = YIELDER.with(|cell| cell.replace(None));
+        defer! {
+            YIELDER.with(|cell| cell.set(prev_yielder));
+            ASYNC_CONTEXT.with(|cell| cell.set(prev_context));
+        }
+
+        let result = TrapHandlerContext::install(trap_handler, coro.trap_handler(), || {
+            match coro.resume(Resume::Poll) {
+                CoroutineResult::Yield(Suspend::Pending) => None,
+                CoroutineResult::Yield(Suspend::Unwind(trap)) => {
+                    // This came from unwind_with which requires that there be
+                    // only Wasm code on the stack.
+                    unsafe {
+                        coro.force_reset();
+                    }
+                    Some(Err(trap))
+                }
+                CoroutineResult::Return(result) => Some(result),
+            }
+        });
+        match result {
+            None => Poll::Pending,
+            Some(result) => {
+                if let Some(coro) = self.coro.take() {
+                    STACK_POOL.push(coro.into_stack());
+                }
+                Poll::Ready(result.map_err(UnwindReason::into_trap))
+            }
+        }
+    }
+}
+
+impl Drop for AsyncWasmCall {
+    fn drop(&mut self) {
+        let mut coro = match self.coro.take() {
+            Some(coro) => coro,
+            None => return,
+        };
+        if coro.started() && !coro.done() {
+            // The call is suspended in an async host function, which traps
+            // once it is resumed to be cancelled.
+            let prev_context = ASYNC_CONTEXT.with(|cell| cell.replace(None));
+            let prev_yielder = YIELDER.with(|cell| cell.replace(None));
+            defer! {
+                YIELDER.with(|cell| cell.set(prev_yielder));
+                ASYNC_CONTEXT.with(|cell| cell.set(prev_context));
+            }
+
+            let result = lazy_per_thread_init().map(|()| {
+                TrapHandlerContext::install(self.trap_handler, coro.trap_handler(), || {
+                    coro.resume(Resume::Cancel)
+                })
+            });
+            match result {
+                Ok(CoroutineResult::Return(_)) => {}
+                // Either only Wasm code is left on the stack after the trap,
+                // or the call couldn't be resumed and its frames are leaked.
+                _ => unsafe {
+                    coro.force_reset();
+                },
+            }
+        }
+        STACK_POOL.push(coro.into_stack());
+    }
+}
+
+/// Waits for `future` in a host function called by an [`AsyncWasmCall`],
+/// suspending the call whenever the future is pending.
+///
+/// The future is polled on the host stack, by the task polling the call.
+/// Returns `None` without polling the future if the host function was not
+/// called by an `AsyncWasmCall`, since the call could not be suspended.
+/// Also returns `None` once the future is dropped if the call is dropped
+/// while suspended, the host function must then trap to unwind the call.
+///
+/// This must be called on the Wasm stack, and not inside [`on_host_stack`].
+pub fn suspend_until_ready<F: Future>(future: F) -> Option<F::Output> {
+    let mut future = pin!(Some(future));
+    loop {
+        let mut context = async_context()?;
+        let poll = on_host_stack(|| {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                let future = future.as_mut().as_pin_mut().unwrap();
+                future.poll(unsafe { context.as_mut() })
+            }))
+        });
+        match poll {
+            Ok(Poll::Ready(output)) => return Some(output),
+            Ok(Poll::Pending) => {
+                if suspend_pending() == Resume::Cancel {
+                    // The future is dropped on the host stack, like it is
+                    // polled.
+                    let drop = on_host_stack(|| {
+                        panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().set(None)))
+                    });
+                    if let Err(panic) = drop {
+                        unsafe { resume_panic(panic) }
+                    }
+                    return None;
+                }
+            }
+            Err(panic) => unsafe { resume_panic(panic) },
+        }
+    }
+}
+
 /// Catches any wasm traps that happen within the execution of `closure`,
 /// returning them as a `Result`.
 ///
@@ -699,9 +887,49 @@ where
 //
 // We also do per-thread signal stack initialization on the first time
 // TRAP_HANDLER is accessed.
+//
+// ASYNC_CONTEXT is set while an `AsyncWasmCall` is polled, and holds the
+// context of the task polling it.
 thread_local! {
-    static YIELDER: Cell<Option<NonNull<Yielder<(), UnwindReason>>>> = Cell::new(None);
+    static YIELDER: Cell<Option<NonNull<Yielder<Resume, Suspend>>>> = Cell::new(None);
     static TRAP_HANDLER: AtomicPtr<TrapHandlerContext> = AtomicPtr::new(ptr::null_mut());
+    static ASYNC_CONTEXT: Cell<Option<NonNull<Context<'static>>>> = Cell::new(None);
+}
+
+// Allocating a new stack is pretty expensive since it involves several
+// system calls. We therefore keep a cache of pre-allocated stacks which
+// allows them to be reused multiple times.
+// FIXME(Amanieu): We should refactor this to avoid the lock.
+lazy_static::lazy_static! {
+    static ref STACK_POOL: crossbeam_queue::SegQueue<DefaultStack> = crossbeam_queue::SegQueue::new();
+}
+
+// The thread-local variables are accessed through functions that are never
+// inlined, since an `AsyncWasmCall` may be resumed on another thread after
+// being suspended, and the address of a thread-local variable must not be
+// reused across a suspension.
+#[inline(never)]
+fn set_yielder(yielder: Option<NonNull<Yielder<Resume, Suspend>>>) {
+    YIELDER.with(|cell| cell.set(yielder));
+}
+
+#[inline(never)]
+fn take_yielder() -> Option<NonNull<Yielder<Resume, Suspend>>> {
+    YIELDER.with(|cell| cell.replace(None))
+}
+
+#[inline(never)]
+fn async_context() -> Option<NonNull<Context<'static>>> {
+    ASYNC_CONTEXT.with(|cell| cell.get())
+}
+
+/// Suspends the `AsyncWasmCall` running on the current stack, until it is
+/// polled again or dropped.
+fn suspend_pending() -> Resume {
+    let yielder = take_yielder().expect("not running on Wasm stack");
+    let resume = unsafe { yielder.as_ref().suspend(Suspend::Pending) };
+    set_yielder(Some(yielder));
+    resume
 }
 
 /// Read-only information that is used by signal handlers to handle and recover
@@ -860,6 +1088,24 @@ impl<T> TrapHandlerContextInner<T> {
     }
 }
 
+/// The reason why the Wasm stack switched back to its parent.
+enum Suspend {
+    /// The Wasm stack is unwound, and reset.
+    Unwind(UnwindReason),
+    /// An async host function waits for a pending future, the Wasm stack is
+    /// resumed on the next poll of the `AsyncWasmCall`.
+    Pending,
+}
+
+/// Why a suspended Wasm stack is resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resume {
+    /// The `AsyncWasmCall` is polled.
+    Poll,
+    /// The `AsyncWasmCall` is dropped, and its stack must be unwound.
+    Cancel,
+}
+
 enum UnwindReason {
     /// A panic caused by the host
     Panic(Box<dyn Any + Send>),
@@ -891,11 +1137,9 @@ impl UnwindReason {
 }
 
 unsafe fn unwind_with(reason: UnwindReason) -> ! {
-    let yielder = YIELDER
-        .with(|cell| cell.replace(None))
-        .expect("not running on Wasm stack");
+    let yielder = take_yielder().expect("not running on Wasm stack");
 
-    yielder.as_ref().suspend(reason);
+    yielder.as_ref().suspend(Suspend::Unwind(reason));
 
     // on_wasm_stack will forcibly reset the coroutine stack after yielding.
     unreachable!();
@@ -909,36 +1153,34 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
     trap_handler: Option<*const TrapHandlerFn<'static>>,
     f: F,
 ) -> Result<T, UnwindReason> {
-    // Allocating a new stack is pretty expensive since it involves several
-    // system calls. We therefore keep a cache of pre-allocated stacks which
-    // allows them to be reused multiple times.
-    // FIXME(Amanieu): We should refactor this to avoid the lock.
-    lazy_static::lazy_static! {
-        static ref STACK_POOL: crossbeam_queue::SegQueue<DefaultStack> = crossbeam_queue::SegQueue::new();
-    }
     let stack = STACK_POOL
         .pop()
         .unwrap_or_else(|| DefaultStack::new(stack_size).unwrap());
     let mut stack = scopeguard::guard(stack, |stack| STACK_POOL.push(stack));
 
     // Create a coroutine with a new stack to run the function on.
-    let mut coro = ScopedCoroutine::with_stack(&mut *stack, move |yielder, ()| {
+    let mut coro = ScopedCoroutine::with_stack(&mut *stack, move |yielder, _: Resume| {
         // Save the yielder to TLS so that it can be used later.
         YIELDER.with(|cell| cell.set(Some(yielder.into())));
 
         Ok(f())
     });
 
+    // Async host functions can't suspend this call, even if it is made by
+    // the host function of an `AsyncWasmCall`.
+    let prev_context = ASYNC_CONTEXT.with(|cell| cell.replace(None));
+
     // Ensure that YIELDER is reset on exit even if the coroutine panics,
     defer! {
         YIELDER.with(|cell| cell.set(None));
+        ASYNC_CONTEXT.with(|cell| cell.set(prev_context));
     }
 
     // Set up metadata for the trap handler for the duration of the coroutine
     // execution. This is restored to its previous value afterwards.
     TrapHandlerContext::install(trap_handler, coro.trap_handler(), || {
-        match coro.resume(()) {
-            CoroutineResult::Yield(trap) => {
+        match coro.resume(Resume::Poll) {
+            CoroutineResult::Yield(Suspend::Unwind(trap)) => {
                 // This came from unwind_with which requires that there be only
                 // Wasm code on the stack.
                 unsafe {
@@ -946,6 +1188,9 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
                 }
                 Err(trap)
             }
+            CoroutineResult::Yield(Suspend::Pending) => {
+                unreachable!("synchronous calls can't be suspended")
+            }
             CoroutineResult::Return(result) => result,
         }
     })
@@ -962,7 +1207,7 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
 pub fn on_host_stack<F: FnOnce() -> T, T>(f: F) -> T {
     // Reset YIEDER to None for the duration of this call to indicate that we
     // are no longer on the Wasm stack.
-    let yielder_ptr = YIELDER.with(|cell| cell.replace(None));
+    let yielder_ptr = take_yielder();
 
     // If we are already on the host stack, execute the function directly. This
     // happens if a host function is called directly from the API.
@@ -973,7 +1218,7 @@ pub fn on_host_stack<F: FnOnce() -> T, T>(f: F) -> T {
 
     // Restore YIELDER upon exiting normally or unwinding.
     defer! {
-        YIELDER.with(|cell| cell.set(yielder_ptr));
+        set_yielder(yielder_ptr);
     }
 
     // on_parent_stack requires the closure to be Send so that the Yielder
@@ -710,6 +710,22 @@ impl VMBuiltinFunctionsArray {
             wasmer_vm_memory32_atomic_notify as usize;
         ptrs[VMBuiltinFunctionIndex::get_imported_memory_atomic_notify_index().index() as usize] =
             wasmer_vm_imported_memory32_atomic_notify as usize;
+        ptrs[VMBuiltinFunctionIndex::get_simd_op_index().index() as usize] =
+            wasmer_vm_simd_op as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_new_index().index() as usize] =
+            wasmer_vm_exception_new as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_raise_index().index() as usize] =
+            wasmer_vm_exception_raise as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_clone_index().index() as usize] =
+            wasmer_vm_exception_clone as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_drop_index().index() as usize] =
+            wasmer_vm_exception_drop as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_catch_index().index() as usize] =
+            wasmer_vm_exception_catch as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_invoke_index().index() as usize] =
+            wasmer_vm_exception_invoke as usize;
+        ptrs[VMBuiltinFunctionIndex::get_epoch_deadline_index().index() as usize] =
+            wasmer_vm_epoch_deadline as usize;
 
         debug_assert!(ptrs.iter().cloned().all(|p| p != 0));
 
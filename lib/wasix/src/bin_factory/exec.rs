@@ -1,4 +1,8 @@
-use std::{pin::Pin, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+};
 
 use crate::{
     os::task::{thread::WasiThreadRunGuard, TaskJoinHandle},
@@ -295,6 +299,19 @@ impl BinFactory {
         env: WasiEnv,
     ) -> Pin<Box<dyn Future<Output = Result<TaskJoinHandle, SpawnError>> + 'a>> {
         Box::pin(async move {
+            let name = program_path(&env.state.fs.current_dir.lock().unwrap(), name);
+
+            // Files of the guest can only be run if its capabilities allow it
+            if name.starts_with('/')
+                && !env
+                    .capabilities
+                    .filesystem
+                    .can_execute(env.fs_root(), Path::new(&name))
+            {
+                env.on_exit(Some(Errno::Access.into())).await;
+                return Err(SpawnError::AccessDenied);
+            }
+
             // Find the binary (or die trying) and make the spawn type
             let binary = self
                 .get_binary(name.as_str(), Some(env.fs_root()))
@@ -330,3 +347,78 @@ impl BinFactory {
         Err(SpawnError::NotFound)
     }
 }
+
+/// The path of the file a program is run from when its name contains a `/`,
+/// which is relative to the current directory like with `execve`. The other
+/// names are left as they are.
+fn program_path(current_dir: &str, name: String) -> String {
+    if name.starts_with('/') || !name.contains('/') {
+        return name;
+    }
+    let path: PathBuf = Path::new(current_dir).join(name).components().collect();
+    path.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use virtual_fs::{FileSystem, TmpFileSystem};
+
+    use super::*;
+    use crate::{capabilities::Capabilities, WasiEnvBuilder};
+
+    /// An environment that can't run the files of `/data/out`, with a
+    /// symlink to one of them.
+    fn env() -> WasiEnv {
+        let fs = TmpFileSystem::new();
+        fs.create_dir(Path::new("/data")).unwrap();
+        fs.create_dir(Path::new("/data/out")).unwrap();
+        fs.new_open_options()
+            .write(true)
+            .create(true)
+            .open("/data/out/tool")
+            .unwrap();
+        fs.symlink(Path::new("out/tool"), Path::new("/data/link"))
+            .unwrap();
+
+        let mut capabilities = Capabilities::default();
+        capabilities.filesystem.rules = vec!["/data/out:rw,noexec".parse().unwrap()];
+        WasiEnvBuilder::new("program-name")
+            .sandbox_fs(fs)
+            .capabilities(capabilities)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn program_paths_are_relative_to_the_current_directory() {
+        assert_eq!(program_path("/data", "./tool".to_string()), "/data/tool");
+        assert_eq!(
+            program_path("/data", "out/tool".to_string()),
+            "/data/out/tool"
+        );
+        assert_eq!(program_path("/data", "/bin/tool".to_string()), "/bin/tool");
+        assert_eq!(program_path("/data", "python".to_string()), "python");
+    }
+
+    #[tokio::test]
+    async fn denied_files_cant_be_run_through_relative_paths_or_symlinks() {
+        for (current_dir, name) in [
+            ("/data/out", "./tool"),
+            ("/data", "out/tool"),
+            ("/data", "./link"),
+            ("/", "/data/link"),
+        ] {
+            let env = env();
+            env.state.fs.set_current_dir(current_dir);
+            let store = env.runtime().new_store();
+            let bin_factory = env.bin_factory.clone();
+            let result = bin_factory.spawn(name.to_string(), store, env).await;
+            assert!(
+                matches!(result, Err(SpawnError::AccessDenied)),
+                "{} was run from {}",
+                name,
+                current_dir
+            );
+        }
+    }
+}
@@ -1,4 +1,13 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use virtual_fs::{FileSystem, FsPattern, FsPolicy, FsRule, PolicyFileSystem};
+use virtual_net::{DynVirtualNetworking, NetworkPolicy, NetworkRule, PolicyNetworking};
+
 use crate::http::HttpClientCapabilityV1;
+pub use crate::syscalls::policy::{SyscallAction, SyscallRule, SYSCALLS};
 
 /// Defines capabilities for a Wasi environment.
 #[derive(Clone, Debug)]
@@ -6,6 +15,9 @@ pub struct Capabilities {
     pub insecure_allow_all: bool,
     pub http_client: HttpClientCapabilityV1,
     pub threading: CapabilityThreadingV1,
+    pub networking: CapabilityNetworkingV1,
+    pub filesystem: CapabilityFileSystemV1,
+    pub syscalls: CapabilitySyscallsV1,
 }
 
 impl Capabilities {
@@ -14,6 +26,9 @@ impl Capabilities {
             insecure_allow_all: false,
             http_client: Default::default(),
             threading: Default::default(),
+            networking: Default::default(),
+            filesystem: Default::default(),
+            syscalls: Default::default(),
         }
     }
 
@@ -24,10 +39,51 @@ impl Capabilities {
             insecure_allow_all,
             http_client,
             threading,
+            networking,
+            filesystem,
+            syscalls,
         } = other;
         self.insecure_allow_all |= insecure_allow_all;
         self.http_client.update(http_client);
         self.threading.update(threading);
+        self.networking.update(networking);
+        self.filesystem.update(filesystem);
+        self.syscalls.update(syscalls);
+    }
+
+    /// Restricts the networking of the environment to the sockets allowed
+    /// by these capabilities.
+    ///
+    /// The networking rules are enforced even with `insecure_allow_all`, as
+    /// they only exist when they are explicitly given.
+    pub fn restrict_networking(&self, net: DynVirtualNetworking) -> DynVirtualNetworking {
+        if self.networking.is_unrestricted() {
+            return net;
+        }
+        Arc::new(PolicyNetworking::new(
+            net,
+            NetworkPolicy::new(self.networking.allow.clone(), self.networking.deny.clone()),
+        ))
+    }
+
+    /// Restricts what can be done with a directory mounted at `mount_point`
+    /// in the guest to what these capabilities allow.
+    ///
+    /// Like the networking rules, the filesystem rules are enforced even with
+    /// `insecure_allow_all`.
+    pub fn restrict_filesystem(
+        &self,
+        fs: Arc<dyn FileSystem + Send + Sync>,
+        mount_point: &Path,
+    ) -> Arc<dyn FileSystem + Send + Sync> {
+        if self.filesystem.is_unrestricted() {
+            return fs;
+        }
+        Arc::new(PolicyFileSystem::new(
+            fs,
+            Arc::new(self.filesystem.policy()),
+            mount_point,
+        ))
     }
 }
 
@@ -60,3 +116,119 @@ impl CapabilityThreadingV1 {
         self.max_threads = max_threads.or(self.max_threads);
     }
 }
+
+/// Defines which sockets can be opened.
+///
+/// A socket is denied if it matches any of the deny rules. Otherwise it is
+/// allowed if there are no allow rules, or if it matches one of them.
+#[derive(Debug, Default, Clone)]
+pub struct CapabilityNetworkingV1 {
+    /// Rules of the allowed sockets.
+    ///
+    /// Empty means all the sockets that are not denied are allowed.
+    pub allow: Vec<NetworkRule>,
+
+    /// Rules of the denied sockets, which take precedence over the allowed
+    /// ones.
+    pub deny: Vec<NetworkRule>,
+}
+
+impl CapabilityNetworkingV1 {
+    pub fn is_unrestricted(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    pub fn update(&mut self, other: CapabilityNetworkingV1) {
+        let CapabilityNetworkingV1 { allow, deny } = other;
+        self.allow.extend(allow);
+        self.deny.extend(deny);
+    }
+}
+
+/// Defines what can be done with the mounted directories.
+///
+/// A path is denied if it, or one of its parent directories, matches any of
+/// the deny patterns. Otherwise it can be accessed as allowed by the rule of
+/// its closest directory, or without restrictions if there is none.
+#[derive(Debug, Default, Clone)]
+pub struct CapabilityFileSystemV1 {
+    /// Access modes of the guest directories, and of everything below them.
+    pub rules: Vec<FsRule>,
+
+    /// Patterns of the guest paths that can't be accessed at all.
+    pub deny: Vec<FsPattern>,
+}
+
+impl CapabilityFileSystemV1 {
+    pub fn is_unrestricted(&self) -> bool {
+        self.policy().is_unrestricted()
+    }
+
+    pub fn policy(&self) -> FsPolicy {
+        FsPolicy::new(self.rules.clone(), self.deny.clone())
+    }
+
+    /// Whether a file of the guest can be run as the binary of a child
+    /// process, once the symlinks leading to it are resolved in the
+    /// filesystem of the guest.
+    pub fn can_execute<F>(&self, fs: &F, path: &Path) -> bool
+    where
+        F: FileSystem + ?Sized,
+    {
+        self.policy().can_execute_in(fs, path)
+    }
+
+    /// Checks that the rules restricting reads or writes apply to mounted
+    /// directories, as only those are restricted. Running files as child
+    /// processes is restricted everywhere.
+    pub fn check_mount_points(&self, mount_points: &[PathBuf]) -> Result<(), String> {
+        let unmounted = self.rules.iter().find(|rule| {
+            (!rule.access.read || !rule.access.write)
+                && !mount_points
+                    .iter()
+                    .any(|mount_point| rule.path.starts_with(mount_point))
+        });
+        match unmounted {
+            Some(rule) => Err(format!(
+                "the `{}` filesystem rule doesn't apply to a mounted directory",
+                rule
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn update(&mut self, other: CapabilityFileSystemV1) {
+        let CapabilityFileSystemV1 { rules, deny } = other;
+        self.rules.extend(rules);
+        self.deny.extend(deny);
+    }
+}
+
+/// Defines what happens when the guest calls a syscall.
+#[derive(Debug, Default, Clone)]
+pub struct CapabilitySyscallsV1 {
+    /// Actions taken for the syscalls, keyed by their name.
+    ///
+    /// The rules naming a syscall take precedence over the `*` ones, and the
+    /// last matching rule wins. Syscalls without a rule are allowed.
+    /// The rules parsed from strings can only name the [`SYSCALLS`].
+    pub rules: Vec<SyscallRule>,
+
+    /// Print every syscall to the standard error of the host, like `strace`.
+    pub strace: bool,
+}
+
+impl CapabilitySyscallsV1 {
+    pub fn action(&self, name: &str) -> SyscallAction {
+        let last_rule = |name: &str| self.rules.iter().rev().find(|rule| rule.name == name);
+        last_rule(name)
+            .or_else(|| last_rule("*"))
+            .map_or(SyscallAction::Allow, |rule| rule.action)
+    }
+
+    pub fn update(&mut self, other: CapabilitySyscallsV1) {
+        let CapabilitySyscallsV1 { rules, strace } = other;
+        self.rules.extend(rules);
+        self.strace |= strace;
+    }
+}
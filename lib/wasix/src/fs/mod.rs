@@ -336,6 +336,18 @@ impl FileSystem for WasiFsRoot {
             WasiFsRoot::Backing(fs) => fs.symlink_metadata(path),
         }
     }
+    fn symlink(&self, target: &Path, link: &Path) -> virtual_fs::Result<()> {
+        match self {
+            WasiFsRoot::Sandbox(fs) => fs.symlink(target, link),
+            WasiFsRoot::Backing(fs) => fs.symlink(target, link),
+        }
+    }
+    fn readlink(&self, path: &Path) -> virtual_fs::Result<PathBuf> {
+        match self {
+            WasiFsRoot::Sandbox(fs) => fs.readlink(path),
+            WasiFsRoot::Backing(fs) => fs.readlink(path),
+        }
+    }
     fn remove_file(&self, path: &Path) -> virtual_fs::Result<()> {
         match self {
             WasiFsRoot::Sandbox(fs) => fs.remove_file(path),
@@ -924,7 +936,10 @@ impl WasiFs {
                                 }
                             } else if file_type.is_symlink() {
                                 should_insert = false;
-                                let link_value = file.read_link().map_err(map_io_err)?;
+                                let link_value = self
+                                    .root_fs
+                                    .readlink(&file)
+                                    .map_err(fs_error_into_wasi_err)?;
                                 debug!("attempting to decompose path {:?}", link_value);
 
                                 let (pre_open_dir_fd, relative_path) = if link_value.is_relative() {
@@ -1966,6 +1981,12 @@ impl FileSystem for FallbackFileSystem {
     fn symlink_metadata(&self, _path: &Path) -> Result<virtual_fs::Metadata, FsError> {
         Self::fail();
     }
+    fn symlink(&self, _target: &Path, _link: &Path) -> Result<(), FsError> {
+        Self::fail();
+    }
+    fn readlink(&self, _path: &Path) -> Result<PathBuf, FsError> {
+        Self::fail();
+    }
     fn remove_file(&self, _path: &Path) -> Result<(), FsError> {
         Self::fail();
     }
@@ -2039,6 +2060,7 @@ pub fn fs_error_into_wasi_err(fs_error: FsError) -> Errno {
         FsError::WriteZero => Errno::Nospc,
         FsError::DirectoryNotEmpty => Errno::Notempty,
         FsError::StorageFull => Errno::Overflow,
+        FsError::TooManySymlinks => Errno::Loop,
         FsError::Lock | FsError::UnknownError => Errno::Io,
     }
 }
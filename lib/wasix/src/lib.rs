@@ -311,7 +311,7 @@ pub fn generate_import_object_from_env(
 fn wasi_exports_generic(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Exports {
     use syscalls::*;
     let namespace = namespace! {
-        "thread-spawn" => Function::new_typed_with_env(&mut store, env, thread_spawn::<Memory32>),
+        "thread-spawn" => policy::syscall(&mut store, env, "thread-spawn", thread_spawn::<Memory32>),
     };
     namespace
 }
@@ -319,52 +319,52 @@ fn wasi_exports_generic(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiE
 fn wasi_unstable_exports(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Exports {
     use syscalls::*;
     let namespace = namespace! {
-        "args_get" => Function::new_typed_with_env(&mut store, env, args_get::<Memory32>),
-        "args_sizes_get" => Function::new_typed_with_env(&mut store, env, args_sizes_get::<Memory32>),
-        "clock_res_get" => Function::new_typed_with_env(&mut store, env, clock_res_get::<Memory32>),
-        "clock_time_get" => Function::new_typed_with_env(&mut store, env, clock_time_get::<Memory32>),
-        "environ_get" => Function::new_typed_with_env(&mut store, env, environ_get::<Memory32>),
-        "environ_sizes_get" => Function::new_typed_with_env(&mut store, env, environ_sizes_get::<Memory32>),
-        "fd_advise" => Function::new_typed_with_env(&mut store, env, fd_advise),
-        "fd_allocate" => Function::new_typed_with_env(&mut store, env, fd_allocate),
-        "fd_close" => Function::new_typed_with_env(&mut store, env, fd_close),
-        "fd_datasync" => Function::new_typed_with_env(&mut store, env, fd_datasync),
-        "fd_fdstat_get" => Function::new_typed_with_env(&mut store, env, fd_fdstat_get::<Memory32>),
-        "fd_fdstat_set_flags" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_flags),
-        "fd_fdstat_set_rights" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_rights),
-        "fd_filestat_get" => Function::new_typed_with_env(&mut store, env, legacy::snapshot0::fd_filestat_get),
-        "fd_filestat_set_size" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_size),
-        "fd_filestat_set_times" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_times),
-        "fd_pread" => Function::new_typed_with_env(&mut store, env, fd_pread::<Memory32>),
-        "fd_prestat_get" => Function::new_typed_with_env(&mut store, env, fd_prestat_get::<Memory32>),
-        "fd_prestat_dir_name" => Function::new_typed_with_env(&mut store, env, fd_prestat_dir_name::<Memory32>),
-        "fd_pwrite" => Function::new_typed_with_env(&mut store, env, fd_pwrite::<Memory32>),
-        "fd_read" => Function::new_typed_with_env(&mut store, env, fd_read::<Memory32>),
-        "fd_readdir" => Function::new_typed_with_env(&mut store, env, fd_readdir::<Memory32>),
-        "fd_renumber" => Function::new_typed_with_env(&mut store, env, fd_renumber),
-        "fd_seek" => Function::new_typed_with_env(&mut store, env, legacy::snapshot0::fd_seek),
-        "fd_sync" => Function::new_typed_with_env(&mut store, env, fd_sync),
-        "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory32>),
-        "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory32>),
-        "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory32>),
-        "path_filestat_get" => Function::new_typed_with_env(&mut store, env, legacy::snapshot0::path_filestat_get),
-        "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory32>),
-        "path_link" => Function::new_typed_with_env(&mut store, env, path_link::<Memory32>),
-        "path_open" => Function::new_typed_with_env(&mut store, env, path_open::<Memory32>),
-        "path_readlink" => Function::new_typed_with_env(&mut store, env, path_readlink::<Memory32>),
-        "path_remove_directory" => Function::new_typed_with_env(&mut store, env, path_remove_directory::<Memory32>),
-        "path_rename" => Function::new_typed_with_env(&mut store, env, path_rename::<Memory32>),
-        "path_symlink" => Function::new_typed_with_env(&mut store, env, path_symlink::<Memory32>),
-        "path_unlink_file" => Function::new_typed_with_env(&mut store, env, path_unlink_file::<Memory32>),
-        "poll_oneoff" => Function::new_typed_with_env(&mut store, env, legacy::snapshot0::poll_oneoff::<Memory32>),
-        "proc_exit" => Function::new_typed_with_env(&mut store, env, proc_exit::<Memory32>),
-        "proc_raise" => Function::new_typed_with_env(&mut store, env, proc_raise),
-        "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory32>),
-        "sched_yield" => Function::new_typed_with_env(&mut store, env, sched_yield::<Memory32>),
-        "sock_recv" => Function::new_typed_with_env(&mut store, env, sock_recv::<Memory32>),
-        "sock_send" => Function::new_typed_with_env(&mut store, env, sock_send::<Memory32>),
-        "sock_shutdown" => Function::new_typed_with_env(&mut store, env, sock_shutdown),
-        "thread-spawn" => Function::new_typed_with_env(&mut store, env, thread_spawn::<Memory32>),
+        "args_get" => policy::syscall(&mut store, env, "args_get", args_get::<Memory32>),
+        "args_sizes_get" => policy::syscall(&mut store, env, "args_sizes_get", args_sizes_get::<Memory32>),
+        "clock_res_get" => policy::syscall(&mut store, env, "clock_res_get", clock_res_get::<Memory32>),
+        "clock_time_get" => policy::syscall(&mut store, env, "clock_time_get", clock_time_get::<Memory32>),
+        "environ_get" => policy::syscall(&mut store, env, "environ_get", environ_get::<Memory32>),
+        "environ_sizes_get" => policy::syscall(&mut store, env, "environ_sizes_get", environ_sizes_get::<Memory32>),
+        "fd_advise" => policy::syscall(&mut store, env, "fd_advise", fd_advise),
+        "fd_allocate" => policy::syscall(&mut store, env, "fd_allocate", fd_allocate),
+        "fd_close" => policy::syscall(&mut store, env, "fd_close", fd_close),
+        "fd_datasync" => policy::syscall(&mut store, env, "fd_datasync", fd_datasync),
+        "fd_fdstat_get" => policy::syscall(&mut store, env, "fd_fdstat_get", fd_fdstat_get::<Memory32>),
+        "fd_fdstat_set_flags" => policy::syscall(&mut store, env, "fd_fdstat_set_flags", fd_fdstat_set_flags),
+        "fd_fdstat_set_rights" => policy::syscall(&mut store, env, "fd_fdstat_set_rights", fd_fdstat_set_rights),
+        "fd_filestat_get" => policy::syscall(&mut store, env, "fd_filestat_get", legacy::snapshot0::fd_filestat_get),
+        "fd_filestat_set_size" => policy::syscall(&mut store, env, "fd_filestat_set_size", fd_filestat_set_size),
+        "fd_filestat_set_times" => policy::syscall(&mut store, env, "fd_filestat_set_times", fd_filestat_set_times),
+        "fd_pread" => policy::syscall(&mut store, env, "fd_pread", fd_pread::<Memory32>),
+        "fd_prestat_get" => policy::syscall(&mut store, env, "fd_prestat_get", fd_prestat_get::<Memory32>),
+        "fd_prestat_dir_name" => policy::syscall(&mut store, env, "fd_prestat_dir_name", fd_prestat_dir_name::<Memory32>),
+        "fd_pwrite" => policy::syscall(&mut store, env, "fd_pwrite", fd_pwrite::<Memory32>),
+        "fd_read" => policy::syscall(&mut store, env, "fd_read", fd_read::<Memory32>),
+        "fd_readdir" => policy::syscall(&mut store, env, "fd_readdir", fd_readdir::<Memory32>),
+        "fd_renumber" => policy::syscall(&mut store, env, "fd_renumber", fd_renumber),
+        "fd_seek" => policy::syscall(&mut store, env, "fd_seek", legacy::snapshot0::fd_seek),
+        "fd_sync" => policy::syscall(&mut store, env, "fd_sync", fd_sync),
+        "fd_tell" => policy::syscall(&mut store, env, "fd_tell", fd_tell::<Memory32>),
+        "fd_write" => policy::syscall(&mut store, env, "fd_write", fd_write::<Memory32>),
+        "path_create_directory" => policy::syscall(&mut store, env, "path_create_directory", path_create_directory::<Memory32>),
+        "path_filestat_get" => policy::syscall(&mut store, env, "path_filestat_get", legacy::snapshot0::path_filestat_get),
+        "path_filestat_set_times" => policy::syscall(&mut store, env, "path_filestat_set_times", path_filestat_set_times::<Memory32>),
+        "path_link" => policy::syscall(&mut store, env, "path_link", path_link::<Memory32>),
+        "path_open" => policy::syscall(&mut store, env, "path_open", path_open::<Memory32>),
+        "path_readlink" => policy::syscall(&mut store, env, "path_readlink", path_readlink::<Memory32>),
+        "path_remove_directory" => policy::syscall(&mut store, env, "path_remove_directory", path_remove_directory::<Memory32>),
+        "path_rename" => policy::syscall(&mut store, env, "path_rename", path_rename::<Memory32>),
+        "path_symlink" => policy::syscall(&mut store, env, "path_symlink", path_symlink::<Memory32>),
+        "path_unlink_file" => policy::syscall(&mut store, env, "path_unlink_file", path_unlink_file::<Memory32>),
+        "poll_oneoff" => policy::syscall(&mut store, env, "poll_oneoff", legacy::snapshot0::poll_oneoff::<Memory32>),
+        "proc_exit" => policy::syscall(&mut store, env, "proc_exit", proc_exit::<Memory32>),
+        "proc_raise" => policy::syscall(&mut store, env, "proc_raise", proc_raise),
+        "random_get" => policy::syscall(&mut store, env, "random_get", random_get::<Memory32>),
+        "sched_yield" => policy::syscall(&mut store, env, "sched_yield", sched_yield::<Memory32>),
+        "sock_recv" => policy::syscall(&mut store, env, "sock_recv", sock_recv::<Memory32>),
+        "sock_send" => policy::syscall(&mut store, env, "sock_send", sock_send::<Memory32>),
+        "sock_shutdown" => policy::syscall(&mut store, env, "sock_shutdown", sock_shutdown),
+        "thread-spawn" => policy::syscall(&mut store, env, "thread-spawn", thread_spawn::<Memory32>),
     };
     namespace
 }
@@ -375,53 +375,53 @@ fn wasi_snapshot_preview1_exports(
 ) -> Exports {
     use syscalls::*;
     let namespace = namespace! {
-        "args_get" => Function::new_typed_with_env(&mut store, env, args_get::<Memory32>),
-        "args_sizes_get" => Function::new_typed_with_env(&mut store, env, args_sizes_get::<Memory32>),
-        "clock_res_get" => Function::new_typed_with_env(&mut store, env, clock_res_get::<Memory32>),
-        "clock_time_get" => Function::new_typed_with_env(&mut store, env, clock_time_get::<Memory32>),
-        "environ_get" => Function::new_typed_with_env(&mut store, env, environ_get::<Memory32>),
-        "environ_sizes_get" => Function::new_typed_with_env(&mut store, env, environ_sizes_get::<Memory32>),
-        "fd_advise" => Function::new_typed_with_env(&mut store, env, fd_advise),
-        "fd_allocate" => Function::new_typed_with_env(&mut store, env, fd_allocate),
-        "fd_close" => Function::new_typed_with_env(&mut store, env, fd_close),
-        "fd_datasync" => Function::new_typed_with_env(&mut store, env, fd_datasync),
-        "fd_fdstat_get" => Function::new_typed_with_env(&mut store, env, fd_fdstat_get::<Memory32>),
-        "fd_fdstat_set_flags" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_flags),
-        "fd_fdstat_set_rights" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_rights),
-        "fd_filestat_get" => Function::new_typed_with_env(&mut store, env, fd_filestat_get::<Memory32>),
-        "fd_filestat_set_size" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_size),
-        "fd_filestat_set_times" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_times),
-        "fd_pread" => Function::new_typed_with_env(&mut store, env, fd_pread::<Memory32>),
-        "fd_prestat_get" => Function::new_typed_with_env(&mut store, env, fd_prestat_get::<Memory32>),
-        "fd_prestat_dir_name" => Function::new_typed_with_env(&mut store, env, fd_prestat_dir_name::<Memory32>),
-        "fd_pwrite" => Function::new_typed_with_env(&mut store, env, fd_pwrite::<Memory32>),
-        "fd_read" => Function::new_typed_with_env(&mut store, env, fd_read::<Memory32>),
-        "fd_readdir" => Function::new_typed_with_env(&mut store, env, fd_readdir::<Memory32>),
-        "fd_renumber" => Function::new_typed_with_env(&mut store, env, fd_renumber),
-        "fd_seek" => Function::new_typed_with_env(&mut store, env, fd_seek::<Memory32>),
-        "fd_sync" => Function::new_typed_with_env(&mut store, env, fd_sync),
-        "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory32>),
-        "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory32>),
-        "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory32>),
-        "path_filestat_get" => Function::new_typed_with_env(&mut store, env, path_filestat_get::<Memory32>),
-        "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory32>),
-        "path_link" => Function::new_typed_with_env(&mut store, env, path_link::<Memory32>),
-        "path_open" => Function::new_typed_with_env(&mut store, env, path_open::<Memory32>),
-        "path_readlink" => Function::new_typed_with_env(&mut store, env, path_readlink::<Memory32>),
-        "path_remove_directory" => Function::new_typed_with_env(&mut store, env, path_remove_directory::<Memory32>),
-        "path_rename" => Function::new_typed_with_env(&mut store, env, path_rename::<Memory32>),
-        "path_symlink" => Function::new_typed_with_env(&mut store, env, path_symlink::<Memory32>),
-        "path_unlink_file" => Function::new_typed_with_env(&mut store, env, path_unlink_file::<Memory32>),
-        "poll_oneoff" => Function::new_typed_with_env(&mut store, env, poll_oneoff::<Memory32>),
-        "proc_exit" => Function::new_typed_with_env(&mut store, env, proc_exit::<Memory32>),
-        "proc_raise" => Function::new_typed_with_env(&mut store, env, proc_raise),
-        "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory32>),
-        "sched_yield" => Function::new_typed_with_env(&mut store, env, sched_yield::<Memory32>),
-        "sock_accept" => Function::new_typed_with_env(&mut store, env, sock_accept::<Memory32>),
-        "sock_recv" => Function::new_typed_with_env(&mut store, env, sock_recv::<Memory32>),
-        "sock_send" => Function::new_typed_with_env(&mut store, env, sock_send::<Memory32>),
-        "sock_shutdown" => Function::new_typed_with_env(&mut store, env, sock_shutdown),
-        "thread-spawn" => Function::new_typed_with_env(&mut store, env, thread_spawn::<Memory32>),
+        "args_get" => policy::syscall(&mut store, env, "args_get", args_get::<Memory32>),
+        "args_sizes_get" => policy::syscall(&mut store, env, "args_sizes_get", args_sizes_get::<Memory32>),
+        "clock_res_get" => policy::syscall(&mut store, env, "clock_res_get", clock_res_get::<Memory32>),
+        "clock_time_get" => policy::syscall(&mut store, env, "clock_time_get", clock_time_get::<Memory32>),
+        "environ_get" => policy::syscall(&mut store, env, "environ_get", environ_get::<Memory32>),
+        "environ_sizes_get" => policy::syscall(&mut store, env, "environ_sizes_get", environ_sizes_get::<Memory32>),
+        "fd_advise" => policy::syscall(&mut store, env, "fd_advise", fd_advise),
+        "fd_allocate" => policy::syscall(&mut store, env, "fd_allocate", fd_allocate),
+        "fd_close" => policy::syscall(&mut store, env, "fd_close", fd_close),
+        "fd_datasync" => policy::syscall(&mut store, env, "fd_datasync", fd_datasync),
+        "fd_fdstat_get" => policy::syscall(&mut store, env, "fd_fdstat_get", fd_fdstat_get::<Memory32>),
+        "fd_fdstat_set_flags" => policy::syscall(&mut store, env, "fd_fdstat_set_flags", fd_fdstat_set_flags),
+        "fd_fdstat_set_rights" => policy::syscall(&mut store, env, "fd_fdstat_set_rights", fd_fdstat_set_rights),
+        "fd_filestat_get" => policy::syscall(&mut store, env, "fd_filestat_get", fd_filestat_get::<Memory32>),
+        "fd_filestat_set_size" => policy::syscall(&mut store, env, "fd_filestat_set_size", fd_filestat_set_size),
+        "fd_filestat_set_times" => policy::syscall(&mut store, env, "fd_filestat_set_times", fd_filestat_set_times),
+        "fd_pread" => policy::syscall(&mut store, env, "fd_pread", fd_pread::<Memory32>),
+        "fd_prestat_get" => policy::syscall(&mut store, env, "fd_prestat_get", fd_prestat_get::<Memory32>),
+        "fd_prestat_dir_name" => policy::syscall(&mut store, env, "fd_prestat_dir_name", fd_prestat_dir_name::<Memory32>),
+        "fd_pwrite" => policy::syscall(&mut store, env, "fd_pwrite", fd_pwrite::<Memory32>),
+        "fd_read" => policy::syscall(&mut store, env, "fd_read", fd_read::<Memory32>),
+        "fd_readdir" => policy::syscall(&mut store, env, "fd_readdir", fd_readdir::<Memory32>),
+        "fd_renumber" => policy::syscall(&mut store, env, "fd_renumber", fd_renumber),
+        "fd_seek" => policy::syscall(&mut store, env, "fd_seek", fd_seek::<Memory32>),
+        "fd_sync" => policy::syscall(&mut store, env, "fd_sync", fd_sync),
+        "fd_tell" => policy::syscall(&mut store, env, "fd_tell", fd_tell::<Memory32>),
+        "fd_write" => policy::syscall(&mut store, env, "fd_write", fd_write::<Memory32>),
+        "path_create_directory" => policy::syscall(&mut store, env, "path_create_directory", path_create_directory::<Memory32>),
+        "path_filestat_get" => policy::syscall(&mut store, env, "path_filestat_get", path_filestat_get::<Memory32>),
+        "path_filestat_set_times" => policy::syscall(&mut store, env, "path_filestat_set_times", path_filestat_set_times::<Memory32>),
+        "path_link" => policy::syscall(&mut store, env, "path_link", path_link::<Memory32>),
+        "path_open" => policy::syscall(&mut store, env, "path_open", path_open::<Memory32>),
+        "path_readlink" => policy::syscall(&mut store, env, "path_readlink", path_readlink::<Memory32>),
+        "path_remove_directory" => policy::syscall(&mut store, env, "path_remove_directory", path_remove_directory::<Memory32>),
+        "path_rename" => policy::syscall(&mut store, env, "path_rename", path_rename::<Memory32>),
+        "path_symlink" => policy::syscall(&mut store, env, "path_symlink", path_symlink::<Memory32>),
+        "path_unlink_file" => policy::syscall(&mut store, env, "path_unlink_file", path_unlink_file::<Memory32>),
+        "poll_oneoff" => policy::syscall(&mut store, env, "poll_oneoff", poll_oneoff::<Memory32>),
+        "proc_exit" => policy::syscall(&mut store, env, "proc_exit", proc_exit::<Memory32>),
+        "proc_raise" => policy::syscall(&mut store, env, "proc_raise", proc_raise),
+        "random_get" => policy::syscall(&mut store, env, "random_get", random_get::<Memory32>),
+        "sched_yield" => policy::syscall(&mut store, env, "sched_yield", sched_yield::<Memory32>),
+        "sock_accept" => policy::syscall(&mut store, env, "sock_accept", sock_accept::<Memory32>),
+        "sock_recv" => policy::syscall(&mut store, env, "sock_recv", sock_recv::<Memory32>),
+        "sock_send" => policy::syscall(&mut store, env, "sock_send", sock_send::<Memory32>),
+        "sock_shutdown" => policy::syscall(&mut store, env, "sock_shutdown", sock_shutdown),
+        "thread-spawn" => policy::syscall(&mut store, env, "thread-spawn", thread_spawn::<Memory32>),
     };
     namespace
 }
@@ -429,120 +429,120 @@ fn wasi_snapshot_preview1_exports(
 fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Exports {
     use syscalls::*;
     let namespace = namespace! {
-        "args_get" => Function::new_typed_with_env(&mut store, env, args_get::<Memory32>),
-        "args_sizes_get" => Function::new_typed_with_env(&mut store, env, args_sizes_get::<Memory32>),
-        "clock_res_get" => Function::new_typed_with_env(&mut store, env, clock_res_get::<Memory32>),
-        "clock_time_get" => Function::new_typed_with_env(&mut store, env, clock_time_get::<Memory32>),
-        "clock_time_set" => Function::new_typed_with_env(&mut store, env, clock_time_set::<Memory32>),
-        "environ_get" => Function::new_typed_with_env(&mut store, env, environ_get::<Memory32>),
-        "environ_sizes_get" => Function::new_typed_with_env(&mut store, env, environ_sizes_get::<Memory32>),
-        "epoll_create" => Function::new_typed_with_env(&mut store, env, epoll_create::<Memory32>),
-        "epoll_ctl" => Function::new_typed_with_env(&mut store, env, epoll_ctl::<Memory32>),
-        "epoll_wait" => Function::new_typed_with_env(&mut store, env, epoll_wait::<Memory32>),
-        "fd_advise" => Function::new_typed_with_env(&mut store, env, fd_advise),
-        "fd_allocate" => Function::new_typed_with_env(&mut store, env, fd_allocate),
-        "fd_close" => Function::new_typed_with_env(&mut store, env, fd_close),
-        "fd_datasync" => Function::new_typed_with_env(&mut store, env, fd_datasync),
-        "fd_fdstat_get" => Function::new_typed_with_env(&mut store, env, fd_fdstat_get::<Memory32>),
-        "fd_fdstat_set_flags" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_flags),
-        "fd_fdstat_set_rights" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_rights),
-        "fd_filestat_get" => Function::new_typed_with_env(&mut store, env, fd_filestat_get::<Memory32>),
-        "fd_filestat_set_size" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_size),
-        "fd_filestat_set_times" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_times),
-        "fd_pread" => Function::new_typed_with_env(&mut store, env, fd_pread::<Memory32>),
-        "fd_prestat_get" => Function::new_typed_with_env(&mut store, env, fd_prestat_get::<Memory32>),
-        "fd_prestat_dir_name" => Function::new_typed_with_env(&mut store, env, fd_prestat_dir_name::<Memory32>),
-        "fd_pwrite" => Function::new_typed_with_env(&mut store, env, fd_pwrite::<Memory32>),
-        "fd_read" => Function::new_typed_with_env(&mut store, env, fd_read::<Memory32>),
-        "fd_readdir" => Function::new_typed_with_env(&mut store, env, fd_readdir::<Memory32>),
-        "fd_renumber" => Function::new_typed_with_env(&mut store, env, fd_renumber),
-        "fd_dup" => Function::new_typed_with_env(&mut store, env, fd_dup::<Memory32>),
-        "fd_event" => Function::new_typed_with_env(&mut store, env, fd_event::<Memory32>),
-        "fd_seek" => Function::new_typed_with_env(&mut store, env, fd_seek::<Memory32>),
-        "fd_sync" => Function::new_typed_with_env(&mut store, env, fd_sync),
-        "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory32>),
-        "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory32>),
-        "fd_pipe" => Function::new_typed_with_env(&mut store, env, fd_pipe::<Memory32>),
-        "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory32>),
-        "path_filestat_get" => Function::new_typed_with_env(&mut store, env, path_filestat_get::<Memory32>),
-        "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory32>),
-        "path_link" => Function::new_typed_with_env(&mut store, env, path_link::<Memory32>),
-        "path_open" => Function::new_typed_with_env(&mut store, env, path_open::<Memory32>),
-        "path_readlink" => Function::new_typed_with_env(&mut store, env, path_readlink::<Memory32>),
-        "path_remove_directory" => Function::new_typed_with_env(&mut store, env, path_remove_directory::<Memory32>),
-        "path_rename" => Function::new_typed_with_env(&mut store, env, path_rename::<Memory32>),
-        "path_symlink" => Function::new_typed_with_env(&mut store, env, path_symlink::<Memory32>),
-        "path_unlink_file" => Function::new_typed_with_env(&mut store, env, path_unlink_file::<Memory32>),
-        "poll_oneoff" => Function::new_typed_with_env(&mut store, env, poll_oneoff::<Memory32>),
-        "proc_exit" => Function::new_typed_with_env(&mut store, env, proc_exit::<Memory32>),
-        "proc_fork" => Function::new_typed_with_env(&mut store, env, proc_fork::<Memory32>),
-        "proc_join" => Function::new_typed_with_env(&mut store, env, proc_join::<Memory32>),
-        "proc_signal" => Function::new_typed_with_env(&mut store, env, proc_signal::<Memory32>),
-        "proc_exec" => Function::new_typed_with_env(&mut store, env, proc_exec::<Memory32>),
-        "proc_raise" => Function::new_typed_with_env(&mut store, env, proc_raise),
-        "proc_raise_interval" => Function::new_typed_with_env(&mut store, env, proc_raise_interval),
-        "proc_spawn" => Function::new_typed_with_env(&mut store, env, proc_spawn::<Memory32>),
-        "proc_id" => Function::new_typed_with_env(&mut store, env, proc_id::<Memory32>),
-        "proc_parent" => Function::new_typed_with_env(&mut store, env, proc_parent::<Memory32>),
-        "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory32>),
-        "tty_get" => Function::new_typed_with_env(&mut store, env, tty_get::<Memory32>),
-        "tty_set" => Function::new_typed_with_env(&mut store, env, tty_set::<Memory32>),
-        "getcwd" => Function::new_typed_with_env(&mut store, env, getcwd::<Memory32>),
-        "chdir" => Function::new_typed_with_env(&mut store, env, chdir::<Memory32>),
-        "callback_signal" => Function::new_typed_with_env(&mut store, env, callback_signal::<Memory32>),
-        "thread_spawn" => Function::new_typed_with_env(&mut store, env, thread_spawn_v2::<Memory32>),
-        "thread_spawn_v2" => Function::new_typed_with_env(&mut store, env, thread_spawn_v2::<Memory32>),
-        "thread_sleep" => Function::new_typed_with_env(&mut store, env, thread_sleep::<Memory32>),
-        "thread_id" => Function::new_typed_with_env(&mut store, env, thread_id::<Memory32>),
-        "thread_signal" => Function::new_typed_with_env(&mut store, env, thread_signal),
-        "thread_join" => Function::new_typed_with_env(&mut store, env, thread_join::<Memory32>),
-        "thread_parallelism" => Function::new_typed_with_env(&mut store, env, thread_parallelism::<Memory32>),
-        "thread_exit" => Function::new_typed_with_env(&mut store, env, thread_exit),
-        "sched_yield" => Function::new_typed_with_env(&mut store, env, sched_yield::<Memory32>),
-        "stack_checkpoint" => Function::new_typed_with_env(&mut store, env, stack_checkpoint::<Memory32>),
-        "stack_restore" => Function::new_typed_with_env(&mut store, env, stack_restore::<Memory32>),
-        "futex_wait" => Function::new_typed_with_env(&mut store, env, futex_wait::<Memory32>),
-        "futex_wake" => Function::new_typed_with_env(&mut store, env, futex_wake::<Memory32>),
-        "futex_wake_all" => Function::new_typed_with_env(&mut store, env, futex_wake_all::<Memory32>),
-        "port_bridge" => Function::new_typed_with_env(&mut store, env, port_bridge::<Memory32>),
-        "port_unbridge" => Function::new_typed_with_env(&mut store, env, port_unbridge),
-        "port_dhcp_acquire" => Function::new_typed_with_env(&mut store, env, port_dhcp_acquire),
-        "port_addr_add" => Function::new_typed_with_env(&mut store, env, port_addr_add::<Memory32>),
-        "port_addr_remove" => Function::new_typed_with_env(&mut store, env, port_addr_remove::<Memory32>),
-        "port_addr_clear" => Function::new_typed_with_env(&mut store, env, port_addr_clear),
-        "port_addr_list" => Function::new_typed_with_env(&mut store, env, port_addr_list::<Memory32>),
-        "port_mac" => Function::new_typed_with_env(&mut store, env, port_mac::<Memory32>),
-        "port_gateway_set" => Function::new_typed_with_env(&mut store, env, port_gateway_set::<Memory32>),
-        "port_route_add" => Function::new_typed_with_env(&mut store, env, port_route_add::<Memory32>),
-        "port_route_remove" => Function::new_typed_with_env(&mut store, env, port_route_remove::<Memory32>),
-        "port_route_clear" => Function::new_typed_with_env(&mut store, env, port_route_clear),
-        "port_route_list" => Function::new_typed_with_env(&mut store, env, port_route_list::<Memory32>),
-        "sock_status" => Function::new_typed_with_env(&mut store, env, sock_status::<Memory32>),
-        "sock_addr_local" => Function::new_typed_with_env(&mut store, env, sock_addr_local::<Memory32>),
-        "sock_addr_peer" => Function::new_typed_with_env(&mut store, env, sock_addr_peer::<Memory32>),
-        "sock_open" => Function::new_typed_with_env(&mut store, env, sock_open::<Memory32>),
-        "sock_set_opt_flag" => Function::new_typed_with_env(&mut store, env, sock_set_opt_flag),
-        "sock_get_opt_flag" => Function::new_typed_with_env(&mut store, env, sock_get_opt_flag::<Memory32>),
-        "sock_set_opt_time" => Function::new_typed_with_env(&mut store, env, sock_set_opt_time::<Memory32>),
-        "sock_get_opt_time" => Function::new_typed_with_env(&mut store, env, sock_get_opt_time::<Memory32>),
-        "sock_set_opt_size" => Function::new_typed_with_env(&mut store, env, sock_set_opt_size),
-        "sock_get_opt_size" => Function::new_typed_with_env(&mut store, env, sock_get_opt_size::<Memory32>),
-        "sock_join_multicast_v4" => Function::new_typed_with_env(&mut store, env, sock_join_multicast_v4::<Memory32>),
-        "sock_leave_multicast_v4" => Function::new_typed_with_env(&mut store, env, sock_leave_multicast_v4::<Memory32>),
-        "sock_join_multicast_v6" => Function::new_typed_with_env(&mut store, env, sock_join_multicast_v6::<Memory32>),
-        "sock_leave_multicast_v6" => Function::new_typed_with_env(&mut store, env, sock_leave_multicast_v6::<Memory32>),
-        "sock_bind" => Function::new_typed_with_env(&mut store, env, sock_bind::<Memory32>),
-        "sock_listen" => Function::new_typed_with_env(&mut store, env, sock_listen::<Memory32>),
-        "sock_accept" => Function::new_typed_with_env(&mut store, env, sock_accept_v2::<Memory32>),
-        "sock_accept_v2" => Function::new_typed_with_env(&mut store, env, sock_accept_v2::<Memory32>),
-        "sock_connect" => Function::new_typed_with_env(&mut store, env, sock_connect::<Memory32>),
-        "sock_recv" => Function::new_typed_with_env(&mut store, env, sock_recv::<Memory32>),
-        "sock_recv_from" => Function::new_typed_with_env(&mut store, env, sock_recv_from::<Memory32>),
-        "sock_send" => Function::new_typed_with_env(&mut store, env, sock_send::<Memory32>),
-        "sock_send_to" => Function::new_typed_with_env(&mut store, env, sock_send_to::<Memory32>),
-        "sock_send_file" => Function::new_typed_with_env(&mut store, env, sock_send_file::<Memory32>),
-        "sock_shutdown" => Function::new_typed_with_env(&mut store, env, sock_shutdown),
-        "resolve" => Function::new_typed_with_env(&mut store, env, resolve::<Memory32>),
+        "args_get" => policy::syscall(&mut store, env, "args_get", args_get::<Memory32>),
+        "args_sizes_get" => policy::syscall(&mut store, env, "args_sizes_get", args_sizes_get::<Memory32>),
+        "clock_res_get" => policy::syscall(&mut store, env, "clock_res_get", clock_res_get::<Memory32>),
+        "clock_time_get" => policy::syscall(&mut store, env, "clock_time_get", clock_time_get::<Memory32>),
+        "clock_time_set" => policy::syscall(&mut store, env, "clock_time_set", clock_time_set::<Memory32>),
+        "environ_get" => policy::syscall(&mut store, env, "environ_get", environ_get::<Memory32>),
+        "environ_sizes_get" => policy::syscall(&mut store, env, "environ_sizes_get", environ_sizes_get::<Memory32>),
+        "epoll_create" => policy::syscall(&mut store, env, "epoll_create", epoll_create::<Memory32>),
+        "epoll_ctl" => policy::syscall(&mut store, env, "epoll_ctl", epoll_ctl::<Memory32>),
+        "epoll_wait" => policy::syscall(&mut store, env, "epoll_wait", epoll_wait::<Memory32>),
+        "fd_advise" => policy::syscall(&mut store, env, "fd_advise", fd_advise),
+        "fd_allocate" => policy::syscall(&mut store, env, "fd_allocate", fd_allocate),
+        "fd_close" => policy::syscall(&mut store, env, "fd_close", fd_close),
+        "fd_datasync" => policy::syscall(&mut store, env, "fd_datasync", fd_datasync),
+        "fd_fdstat_get" => policy::syscall(&mut store, env, "fd_fdstat_get", fd_fdstat_get::<Memory32>),
+        "fd_fdstat_set_flags" => policy::syscall(&mut store, env, "fd_fdstat_set_flags", fd_fdstat_set_flags),
+        "fd_fdstat_set_rights" => policy::syscall(&mut store, env, "fd_fdstat_set_rights", fd_fdstat_set_rights),
+        "fd_filestat_get" => policy::syscall(&mut store, env, "fd_filestat_get", fd_filestat_get::<Memory32>),
+        "fd_filestat_set_size" => policy::syscall(&mut store, env, "fd_filestat_set_size", fd_filestat_set_size),
+        "fd_filestat_set_times" => policy::syscall(&mut store, env, "fd_filestat_set_times", fd_filestat_set_times),
+        "fd_pread" => policy::syscall(&mut store, env, "fd_pread", fd_pread::<Memory32>),
+        "fd_prestat_get" => policy::syscall(&mut store, env, "fd_prestat_get", fd_prestat_get::<Memory32>),
+        "fd_prestat_dir_name" => policy::syscall(&mut store, env, "fd_prestat_dir_name", fd_prestat_dir_name::<Memory32>),
+        "fd_pwrite" => policy::syscall(&mut store, env, "fd_pwrite", fd_pwrite::<Memory32>),
+        "fd_read" => policy::syscall(&mut store, env, "fd_read", fd_read::<Memory32>),
+        "fd_readdir" => policy::syscall(&mut store, env, "fd_readdir", fd_readdir::<Memory32>),
+        "fd_renumber" => policy::syscall(&mut store, env, "fd_renumber", fd_renumber),
+        "fd_dup" => policy::syscall(&mut store, env, "fd_dup", fd_dup::<Memory32>),
+        "fd_event" => policy::syscall(&mut store, env, "fd_event", fd_event::<Memory32>),
+        "fd_seek" => policy::syscall(&mut store, env, "fd_seek", fd_seek::<Memory32>),
+        "fd_sync" => policy::syscall(&mut store, env, "fd_sync", fd_sync),
+        "fd_tell" => policy::syscall(&mut store, env, "fd_tell", fd_tell::<Memory32>),
+        "fd_write" => policy::syscall(&mut store, env, "fd_write", fd_write::<Memory32>),
+        "fd_pipe" => policy::syscall(&mut store, env, "fd_pipe", fd_pipe::<Memory32>),
+        "path_create_directory" => policy::syscall(&mut store, env, "path_create_directory", path_create_directory::<Memory32>),
+        "path_filestat_get" => policy::syscall(&mut store, env, "path_filestat_get", path_filestat_get::<Memory32>),
+        "path_filestat_set_times" => policy::syscall(&mut store, env, "path_filestat_set_times", path_filestat_set_times::<Memory32>),
+        "path_link" => policy::syscall(&mut store, env, "path_link", path_link::<Memory32>),
+        "path_open" => policy::syscall(&mut store, env, "path_open", path_open::<Memory32>),
+        "path_readlink" => policy::syscall(&mut store, env, "path_readlink", path_readlink::<Memory32>),
+        "path_remove_directory" => policy::syscall(&mut store, env, "path_remove_directory", path_remove_directory::<Memory32>),
+        "path_rename" => policy::syscall(&mut store, env, "path_rename", path_rename::<Memory32>),
+        "path_symlink" => policy::syscall(&mut store, env, "path_symlink", path_symlink::<Memory32>),
+        "path_unlink_file" => policy::syscall(&mut store, env, "path_unlink_file", path_unlink_file::<Memory32>),
+        "poll_oneoff" => policy::syscall(&mut store, env, "poll_oneoff", poll_oneoff::<Memory32>),
+        "proc_exit" => policy::syscall(&mut store, env, "proc_exit", proc_exit::<Memory32>),
+        "proc_fork" => policy::syscall(&mut store, env, "proc_fork", proc_fork::<Memory32>),
+        "proc_join" => policy::syscall(&mut store, env, "proc_join", proc_join::<Memory32>),
+        "proc_signal" => policy::syscall(&mut store, env, "proc_signal", proc_signal::<Memory32>),
+        "proc_exec" => policy::syscall(&mut store, env, "proc_exec", proc_exec::<Memory32>),
+        "proc_raise" => policy::syscall(&mut store, env, "proc_raise", proc_raise),
+        "proc_raise_interval" => policy::syscall(&mut store, env, "proc_raise_interval", proc_raise_interval),
+        "proc_spawn" => policy::syscall(&mut store, env, "proc_spawn", proc_spawn::<Memory32>),
+        "proc_id" => policy::syscall(&mut store, env, "proc_id", proc_id::<Memory32>),
+        "proc_parent" => policy::syscall(&mut store, env, "proc_parent", proc_parent::<Memory32>),
+        "random_get" => policy::syscall(&mut store, env, "random_get", random_get::<Memory32>),
+        "tty_get" => policy::syscall(&mut store, env, "tty_get", tty_get::<Memory32>),
+        "tty_set" => policy::syscall(&mut store, env, "tty_set", tty_set::<Memory32>),
+        "getcwd" => policy::syscall(&mut store, env, "getcwd", getcwd::<Memory32>),
+        "chdir" => policy::syscall(&mut store, env, "chdir", chdir::<Memory32>),
+        "callback_signal" => policy::syscall(&mut store, env, "callback_signal", callback_signal::<Memory32>),
+        "thread_spawn" => policy::syscall(&mut store, env, "thread_spawn", thread_spawn_v2::<Memory32>),
+        "thread_spawn_v2" => policy::syscall(&mut store, env, "thread_spawn_v2", thread_spawn_v2::<Memory32>),
+        "thread_sleep" => policy::syscall(&mut store, env, "thread_sleep", thread_sleep::<Memory32>),
+        "thread_id" => policy::syscall(&mut store, env, "thread_id", thread_id::<Memory32>),
+        "thread_signal" => policy::syscall(&mut store, env, "thread_signal", thread_signal),
+        "thread_join" => policy::syscall(&mut store, env, "thread_join", thread_join::<Memory32>),
+        "thread_parallelism" => policy::syscall(&mut store, env, "thread_parallelism", thread_parallelism::<Memory32>),
+        "thread_exit" => policy::syscall(&mut store, env, "thread_exit", thread_exit),
+        "sched_yield" => policy::syscall(&mut store, env, "sched_yield", sched_yield::<Memory32>),
+        "stack_checkpoint" => policy::syscall(&mut store, env, "stack_checkpoint", stack_checkpoint::<Memory32>),
+        "stack_restore" => policy::syscall(&mut store, env, "stack_restore", stack_restore::<Memory32>),
+        "futex_wait" => policy::syscall(&mut store, env, "futex_wait", futex_wait::<Memory32>),
+        "futex_wake" => policy::syscall(&mut store, env, "futex_wake", futex_wake::<Memory32>),
+        "futex_wake_all" => policy::syscall(&mut store, env, "futex_wake_all", futex_wake_all::<Memory32>),
+        "port_bridge" => policy::syscall(&mut store, env, "port_bridge", port_bridge::<Memory32>),
+        "port_unbridge" => policy::syscall(&mut store, env, "port_unbridge", port_unbridge),
+        "port_dhcp_acquire" => policy::syscall(&mut store, env, "port_dhcp_acquire", port_dhcp_acquire),
+        "port_addr_add" => policy::syscall(&mut store, env, "port_addr_add", port_addr_add::<Memory32>),
+        "port_addr_remove" => policy::syscall(&mut store, env, "port_addr_remove", port_addr_remove::<Memory32>),
+        "port_addr_clear" => policy::syscall(&mut store, env, "port_addr_clear", port_addr_clear),
+        "port_addr_list" => policy::syscall(&mut store, env, "port_addr_list", port_addr_list::<Memory32>),
+        "port_mac" => policy::syscall(&mut store, env, "port_mac", port_mac::<Memory32>),
+        "port_gateway_set" => policy::syscall(&mut store, env, "port_gateway_set", port_gateway_set::<Memory32>),
+        "port_route_add" => policy::syscall(&mut store, env, "port_route_add", port_route_add::<Memory32>),
+        "port_route_remove" => policy::syscall(&mut store, env, "port_route_remove", port_route_remove::<Memory32>),
+        "port_route_clear" => policy::syscall(&mut store, env, "port_route_clear", port_route_clear),
+        "port_route_list" => policy::syscall(&mut store, env, "port_route_list", port_route_list::<Memory32>),
+        "sock_status" => policy::syscall(&mut store, env, "sock_status", sock_status::<Memory32>),
+        "sock_addr_local" => policy::syscall(&mut store, env, "sock_addr_local", sock_addr_local::<Memory32>),
+        "sock_addr_peer" => policy::syscall(&mut store, env, "sock_addr_peer", sock_addr_peer::<Memory32>),
+        "sock_open" => policy::syscall(&mut store, env, "sock_open", sock_open::<Memory32>),
+        "sock_set_opt_flag" => policy::syscall(&mut store, env, "sock_set_opt_flag", sock_set_opt_flag),
+        "sock_get_opt_flag" => policy::syscall(&mut store, env, "sock_get_opt_flag", sock_get_opt_flag::<Memory32>),
+        "sock_set_opt_time" => policy::syscall(&mut store, env, "sock_set_opt_time", sock_set_opt_time::<Memory32>),
+        "sock_get_opt_time" => policy::syscall(&mut store, env, "sock_get_opt_time", sock_get_opt_time::<Memory32>),
+        "sock_set_opt_size" => policy::syscall(&mut store, env, "sock_set_opt_size", sock_set_opt_size),
+        "sock_get_opt_size" => policy::syscall(&mut store, env, "sock_get_opt_size", sock_get_opt_size::<Memory32>),
+        "sock_join_multicast_v4" => policy::syscall(&mut store, env, "sock_join_multicast_v4", sock_join_multicast_v4::<Memory32>),
+        "sock_leave_multicast_v4" => policy::syscall(&mut store, env, "sock_leave_multicast_v4", sock_leave_multicast_v4::<Memory32>),
+        "sock_join_multicast_v6" => policy::syscall(&mut store, env, "sock_join_multicast_v6", sock_join_multicast_v6::<Memory32>),
+        "sock_leave_multicast_v6" => policy::syscall(&mut store, env, "sock_leave_multicast_v6", sock_leave_multicast_v6::<Memory32>),
+        "sock_bind" => policy::syscall(&mut store, env, "sock_bind", sock_bind::<Memory32>),
+        "sock_listen" => policy::syscall(&mut store, env, "sock_listen", sock_listen::<Memory32>),
+        "sock_accept" => policy::syscall(&mut store, env, "sock_accept", sock_accept_v2::<Memory32>),
+        "sock_accept_v2" => policy::syscall(&mut store, env, "sock_accept_v2", sock_accept_v2::<Memory32>),
+        "sock_connect" => policy::syscall(&mut store, env, "sock_connect", sock_connect::<Memory32>),
+        "sock_recv" => policy::syscall(&mut store, env, "sock_recv", sock_recv::<Memory32>),
+        "sock_recv_from" => policy::syscall(&mut store, env, "sock_recv_from", sock_recv_from::<Memory32>),
+        "sock_send" => policy::syscall(&mut store, env, "sock_send", sock_send::<Memory32>),
+        "sock_send_to" => policy::syscall(&mut store, env, "sock_send_to", sock_send_to::<Memory32>),
+        "sock_send_file" => policy::syscall(&mut store, env, "sock_send_file", sock_send_file::<Memory32>),
+        "sock_shutdown" => policy::syscall(&mut store, env, "sock_shutdown", sock_shutdown),
+        "resolve" => policy::syscall(&mut store, env, "resolve", resolve::<Memory32>),
     };
     namespace
 }
@@ -550,120 +550,120 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
 fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Exports {
     use syscalls::*;
     let namespace = namespace! {
-        "args_get" => Function::new_typed_with_env(&mut store, env, args_get::<Memory64>),
-        "args_sizes_get" => Function::new_typed_with_env(&mut store, env, args_sizes_get::<Memory64>),
-        "clock_res_get" => Function::new_typed_with_env(&mut store, env, clock_res_get::<Memory64>),
-        "clock_time_get" => Function::new_typed_with_env(&mut store, env, clock_time_get::<Memory64>),
-        "clock_time_set" => Function::new_typed_with_env(&mut store, env, clock_time_set::<Memory64>),
-        "environ_get" => Function::new_typed_with_env(&mut store, env, environ_get::<Memory64>),
-        "environ_sizes_get" => Function::new_typed_with_env(&mut store, env, environ_sizes_get::<Memory64>),
-        "epoll_create" => Function::new_typed_with_env(&mut store, env, epoll_create::<Memory64>),
-        "epoll_ctl" => Function::new_typed_with_env(&mut store, env, epoll_ctl::<Memory64>),
-        "epoll_wait" => Function::new_typed_with_env(&mut store, env, epoll_wait::<Memory64>),
-        "fd_advise" => Function::new_typed_with_env(&mut store, env, fd_advise),
-        "fd_allocate" => Function::new_typed_with_env(&mut store, env, fd_allocate),
-        "fd_close" => Function::new_typed_with_env(&mut store, env, fd_close),
-        "fd_datasync" => Function::new_typed_with_env(&mut store, env, fd_datasync),
-        "fd_fdstat_get" => Function::new_typed_with_env(&mut store, env, fd_fdstat_get::<Memory64>),
-        "fd_fdstat_set_flags" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_flags),
-        "fd_fdstat_set_rights" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_rights),
-        "fd_filestat_get" => Function::new_typed_with_env(&mut store, env, fd_filestat_get::<Memory64>),
-        "fd_filestat_set_size" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_size),
-        "fd_filestat_set_times" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_times),
-        "fd_pread" => Function::new_typed_with_env(&mut store, env, fd_pread::<Memory64>),
-        "fd_prestat_get" => Function::new_typed_with_env(&mut store, env, fd_prestat_get::<Memory64>),
-        "fd_prestat_dir_name" => Function::new_typed_with_env(&mut store, env, fd_prestat_dir_name::<Memory64>),
-        "fd_pwrite" => Function::new_typed_with_env(&mut store, env, fd_pwrite::<Memory64>),
-        "fd_read" => Function::new_typed_with_env(&mut store, env, fd_read::<Memory64>),
-        "fd_readdir" => Function::new_typed_with_env(&mut store, env, fd_readdir::<Memory64>),
-        "fd_renumber" => Function::new_typed_with_env(&mut store, env, fd_renumber),
-        "fd_dup" => Function::new_typed_with_env(&mut store, env, fd_dup::<Memory64>),
-        "fd_event" => Function::new_typed_with_env(&mut store, env, fd_event::<Memory64>),
-        "fd_seek" => Function::new_typed_with_env(&mut store, env, fd_seek::<Memory64>),
-        "fd_sync" => Function::new_typed_with_env(&mut store, env, fd_sync),
-        "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory64>),
-        "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory64>),
-        "fd_pipe" => Function::new_typed_with_env(&mut store, env, fd_pipe::<Memory64>),
-        "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory64>),
-        "path_filestat_get" => Function::new_typed_with_env(&mut store, env, path_filestat_get::<Memory64>),
-        "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory64>),
-        "path_link" => Function::new_typed_with_env(&mut store, env, path_link::<Memory64>),
-        "path_open" => Function::new_typed_with_env(&mut store, env, path_open::<Memory64>),
-        "path_readlink" => Function::new_typed_with_env(&mut store, env, path_readlink::<Memory64>),
-        "path_remove_directory" => Function::new_typed_with_env(&mut store, env, path_remove_directory::<Memory64>),
-        "path_rename" => Function::new_typed_with_env(&mut store, env, path_rename::<Memory64>),
-        "path_symlink" => Function::new_typed_with_env(&mut store, env, path_symlink::<Memory64>),
-        "path_unlink_file" => Function::new_typed_with_env(&mut store, env, path_unlink_file::<Memory64>),
-        "poll_oneoff" => Function::new_typed_with_env(&mut store, env, poll_oneoff::<Memory64>),
-        "proc_exit" => Function::new_typed_with_env(&mut store, env, proc_exit::<Memory64>),
-        "proc_fork" => Function::new_typed_with_env(&mut store, env, proc_fork::<Memory64>),
-        "proc_join" => Function::new_typed_with_env(&mut store, env, proc_join::<Memory64>),
-        "proc_signal" => Function::new_typed_with_env(&mut store, env, proc_signal::<Memory64>),
-        "proc_exec" => Function::new_typed_with_env(&mut store, env, proc_exec::<Memory64>),
-        "proc_raise" => Function::new_typed_with_env(&mut store, env, proc_raise),
-        "proc_raise_interval" => Function::new_typed_with_env(&mut store, env, proc_raise_interval),
-        "proc_spawn" => Function::new_typed_with_env(&mut store, env, proc_spawn::<Memory64>),
-        "proc_id" => Function::new_typed_with_env(&mut store, env, proc_id::<Memory64>),
-        "proc_parent" => Function::new_typed_with_env(&mut store, env, proc_parent::<Memory64>),
-        "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory64>),
-        "tty_get" => Function::new_typed_with_env(&mut store, env, tty_get::<Memory64>),
-        "tty_set" => Function::new_typed_with_env(&mut store, env, tty_set::<Memory64>),
-        "getcwd" => Function::new_typed_with_env(&mut store, env, getcwd::<Memory64>),
-        "chdir" => Function::new_typed_with_env(&mut store, env, chdir::<Memory64>),
-        "callback_signal" => Function::new_typed_with_env(&mut store, env, callback_signal::<Memory64>),
-        "thread_spawn" => Function::new_typed_with_env(&mut store, env, thread_spawn_v2::<Memory64>),
-        "thread_spawn_v2" => Function::new_typed_with_env(&mut store, env, thread_spawn_v2::<Memory64>),
-        "thread_sleep" => Function::new_typed_with_env(&mut store, env, thread_sleep::<Memory64>),
-        "thread_id" => Function::new_typed_with_env(&mut store, env, thread_id::<Memory64>),
-        "thread_signal" => Function::new_typed_with_env(&mut store, env, thread_signal),
-        "thread_join" => Function::new_typed_with_env(&mut store, env, thread_join::<Memory64>),
-        "thread_parallelism" => Function::new_typed_with_env(&mut store, env, thread_parallelism::<Memory64>),
-        "thread_exit" => Function::new_typed_with_env(&mut store, env, thread_exit),
-        "sched_yield" => Function::new_typed_with_env(&mut store, env, sched_yield::<Memory64>),
-        "stack_checkpoint" => Function::new_typed_with_env(&mut store, env, stack_checkpoint::<Memory64>),
-        "stack_restore" => Function::new_typed_with_env(&mut store, env, stack_restore::<Memory64>),
-        "futex_wait" => Function::new_typed_with_env(&mut store, env, futex_wait::<Memory64>),
-        "futex_wake" => Function::new_typed_with_env(&mut store, env, futex_wake::<Memory64>),
-        "futex_wake_all" => Function::new_typed_with_env(&mut store, env, futex_wake_all::<Memory64>),
-        "port_bridge" => Function::new_typed_with_env(&mut store, env, port_bridge::<Memory64>),
-        "port_unbridge" => Function::new_typed_with_env(&mut store, env, port_unbridge),
-        "port_dhcp_acquire" => Function::new_typed_with_env(&mut store, env, port_dhcp_acquire),
-        "port_addr_add" => Function::new_typed_with_env(&mut store, env, port_addr_add::<Memory64>),
-        "port_addr_remove" => Function::new_typed_with_env(&mut store, env, port_addr_remove::<Memory64>),
-        "port_addr_clear" => Function::new_typed_with_env(&mut store, env, port_addr_clear),
-        "port_addr_list" => Function::new_typed_with_env(&mut store, env, port_addr_list::<Memory64>),
-        "port_mac" => Function::new_typed_with_env(&mut store, env, port_mac::<Memory64>),
-        "port_gateway_set" => Function::new_typed_with_env(&mut store, env, port_gateway_set::<Memory64>),
-        "port_route_add" => Function::new_typed_with_env(&mut store, env, port_route_add::<Memory64>),
-        "port_route_remove" => Function::new_typed_with_env(&mut store, env, port_route_remove::<Memory64>),
-        "port_route_clear" => Function::new_typed_with_env(&mut store, env, port_route_clear),
-        "port_route_list" => Function::new_typed_with_env(&mut store, env, port_route_list::<Memory64>),
-        "sock_status" => Function::new_typed_with_env(&mut store, env, sock_status::<Memory64>),
-        "sock_addr_local" => Function::new_typed_with_env(&mut store, env, sock_addr_local::<Memory64>),
-        "sock_addr_peer" => Function::new_typed_with_env(&mut store, env, sock_addr_peer::<Memory64>),
-        "sock_open" => Function::new_typed_with_env(&mut store, env, sock_open::<Memory64>),
-        "sock_set_opt_flag" => Function::new_typed_with_env(&mut store, env, sock_set_opt_flag),
-        "sock_get_opt_flag" => Function::new_typed_with_env(&mut store, env, sock_get_opt_flag::<Memory64>),
-        "sock_set_opt_time" => Function::new_typed_with_env(&mut store, env, sock_set_opt_time::<Memory64>),
-        "sock_get_opt_time" => Function::new_typed_with_env(&mut store, env, sock_get_opt_time::<Memory64>),
-        "sock_set_opt_size" => Function::new_typed_with_env(&mut store, env, sock_set_opt_size),
-        "sock_get_opt_size" => Function::new_typed_with_env(&mut store, env, sock_get_opt_size::<Memory64>),
-        "sock_join_multicast_v4" => Function::new_typed_with_env(&mut store, env, sock_join_multicast_v4::<Memory64>),
-        "sock_leave_multicast_v4" => Function::new_typed_with_env(&mut store, env, sock_leave_multicast_v4::<Memory64>),
-        "sock_join_multicast_v6" => Function::new_typed_with_env(&mut store, env, sock_join_multicast_v6::<Memory64>),
-        "sock_leave_multicast_v6" => Function::new_typed_with_env(&mut store, env, sock_leave_multicast_v6::<Memory64>),
-        "sock_bind" => Function::new_typed_with_env(&mut store, env, sock_bind::<Memory64>),
-        "sock_listen" => Function::new_typed_with_env(&mut store, env, sock_listen::<Memory64>),
-        "sock_accept" => Function::new_typed_with_env(&mut store, env, sock_accept_v2::<Memory64>),
-        "sock_accept_v2" => Function::new_typed_with_env(&mut store, env, sock_accept_v2::<Memory64>),
-        "sock_connect" => Function::new_typed_with_env(&mut store, env, sock_connect::<Memory64>),
-        "sock_recv" => Function::new_typed_with_env(&mut store, env, sock_recv::<Memory64>),
-        "sock_recv_from" => Function::new_typed_with_env(&mut store, env, sock_recv_from::<Memory64>),
-        "sock_send" => Function::new_typed_with_env(&mut store, env, sock_send::<Memory64>),
-        "sock_send_to" => Function::new_typed_with_env(&mut store, env, sock_send_to::<Memory64>),
-        "sock_send_file" => Function::new_typed_with_env(&mut store, env, sock_send_file::<Memory64>),
-        "sock_shutdown" => Function::new_typed_with_env(&mut store, env, sock_shutdown),
-        "resolve" => Function::new_typed_with_env(&mut store, env, resolve::<Memory64>),
+        "args_get" => policy::syscall(&mut store, env, "args_get", args_get::<Memory64>),
+        "args_sizes_get" => policy::syscall(&mut store, env, "args_sizes_get", args_sizes_get::<Memory64>),
+        "clock_res_get" => policy::syscall(&mut store, env, "clock_res_get", clock_res_get::<Memory64>),
+        "clock_time_get" => policy::syscall(&mut store, env, "clock_time_get", clock_time_get::<Memory64>),
+        "clock_time_set" => policy::syscall(&mut store, env, "clock_time_set", clock_time_set::<Memory64>),
+        "environ_get" => policy::syscall(&mut store, env, "environ_get", environ_get::<Memory64>),
+        "environ_sizes_get" => policy::syscall(&mut store, env, "environ_sizes_get", environ_sizes_get::<Memory64>),
+        "epoll_create" => policy::syscall(&mut store, env, "epoll_create", epoll_create::<Memory64>),
+        "epoll_ctl" => policy::syscall(&mut store, env, "epoll_ctl", epoll_ctl::<Memory64>),
+        "epoll_wait" => policy::syscall(&mut store, env, "epoll_wait", epoll_wait::<Memory64>),
+        "fd_advise" => policy::syscall(&mut store, env, "fd_advise", fd_advise),
+        "fd_allocate" => policy::syscall(&mut store, env, "fd_allocate", fd_allocate),
+        "fd_close" => policy::syscall(&mut store, env, "fd_close", fd_close),
+        "fd_datasync" => policy::syscall(&mut store, env, "fd_datasync", fd_datasync),
+        "fd_fdstat_get" => policy::syscall(&mut store, env, "fd_fdstat_get", fd_fdstat_get::<Memory64>),
+        "fd_fdstat_set_flags" => policy::syscall(&mut store, env, "fd_fdstat_set_flags", fd_fdstat_set_flags),
+        "fd_fdstat_set_rights" => policy::syscall(&mut store, env, "fd_fdstat_set_rights", fd_fdstat_set_rights),
+        "fd_filestat_get" => policy::syscall(&mut store, env, "fd_filestat_get", fd_filestat_get::<Memory64>),
+        "fd_filestat_set_size" => policy::syscall(&mut store, env, "fd_filestat_set_size", fd_filestat_set_size),
+        "fd_filestat_set_times" => policy::syscall(&mut store, env, "fd_filestat_set_times", fd_filestat_set_times),
+        "fd_pread" => policy::syscall(&mut store, env, "fd_pread", fd_pread::<Memory64>),
+        "fd_prestat_get" => policy::syscall(&mut store, env, "fd_prestat_get", fd_prestat_get::<Memory64>),
+        "fd_prestat_dir_name" => policy::syscall(&mut store, env, "fd_prestat_dir_name", fd_prestat_dir_name::<Memory64>),
+        "fd_pwrite" => policy::syscall(&mut store, env, "fd_pwrite", fd_pwrite::<Memory64>),
+        "fd_read" => policy::syscall(&mut store, env, "fd_read", fd_read::<Memory64>),
+        "fd_readdir" => policy::syscall(&mut store, env, "fd_readdir", fd_readdir::<Memory64>),
+        "fd_renumber" => policy::syscall(&mut store, env, "fd_renumber", fd_renumber),
+        "fd_dup" => policy::syscall(&mut store, env, "fd_dup", fd_dup::<Memory64>),
+        "fd_event" => policy::syscall(&mut store, env, "fd_event", fd_event::<Memory64>),
+        "fd_seek" => policy::syscall(&mut store, env, "fd_seek", fd_seek::<Memory64>),
+        "fd_sync" => policy::syscall(&mut store, env, "fd_sync", fd_sync),
+        "fd_tell" => policy::syscall(&mut store, env, "fd_tell", fd_tell::<Memory64>),
+        "fd_write" => policy::syscall(&mut store, env, "fd_write", fd_write::<Memory64>),
+        "fd_pipe" => policy::syscall(&mut store, env, "fd_pipe", fd_pipe::<Memory64>),
+        "path_create_directory" => policy::syscall(&mut store, env, "path_create_directory", path_create_directory::<Memory64>),
+        "path_filestat_get" => policy::syscall(&mut store, env, "path_filestat_get", path_filestat_get::<Memory64>),
+        "path_filestat_set_times" => policy::syscall(&mut store, env, "path_filestat_set_times", path_filestat_set_times::<Memory64>),
+        "path_link" => policy::syscall(&mut store, env, "path_link", path_link::<Memory64>),
+        "path_open" => policy::syscall(&mut store, env, "path_open", path_open::<Memory64>),
+        "path_readlink" => policy::syscall(&mut store, env, "path_readlink", path_readlink::<Memory64>),
+        "path_remove_directory" => policy::syscall(&mut store, env, "path_remove_directory", path_remove_directory::<Memory64>),
+        "path_rename" => policy::syscall(&mut store, env, "path_rename", path_rename::<Memory64>),
+        "path_symlink" => policy::syscall(&mut store, env, "path_symlink", path_symlink::<Memory64>),
+        "path_unlink_file" => policy::syscall(&mut store, env, "path_unlink_file", path_unlink_file::<Memory64>),
+        "poll_oneoff" => policy::syscall(&mut store, env, "poll_oneoff", poll_oneoff::<Memory64>),
+        "proc_exit" => policy::syscall(&mut store, env, "proc_exit", proc_exit::<Memory64>),
+        "proc_fork" => policy::syscall(&mut store, env, "proc_fork", proc_fork::<Memory64>),
+        "proc_join" => policy::syscall(&mut store, env, "proc_join", proc_join::<Memory64>),
+        "proc_signal" => policy::syscall(&mut store, env, "proc_signal", proc_signal::<Memory64>),
+        "proc_exec" => policy::syscall(&mut store, env, "proc_exec", proc_exec::<Memory64>),
+        "proc_raise" => policy::syscall(&mut store, env, "proc_raise", proc_raise),
+        "proc_raise_interval" => policy::syscall(&mut store, env, "proc_raise_interval", proc_raise_interval),
+        "proc_spawn" => policy::syscall(&mut store, env, "proc_spawn", proc_spawn::<Memory64>),
+        "proc_id" => policy::syscall(&mut store, env, "proc_id", proc_id::<Memory64>),
+        "proc_parent" => policy::syscall(&mut store, env, "proc_parent", proc_parent::<Memory64>),
+        "random_get" => policy::syscall(&mut store, env, "random_get", random_get::<Memory64>),
+        "tty_get" => policy::syscall(&mut store, env, "tty_get", tty_get::<Memory64>),
+        "tty_set" => policy::syscall(&mut store, env, "tty_set", tty_set::<Memory64>),
+        "getcwd" => policy::syscall(&mut store, env, "getcwd", getcwd::<Memory64>),
+        "chdir" => policy::syscall(&mut store, env, "chdir", chdir::<Memory64>),
+        "callback_signal" => policy::syscall(&mut store, env, "callback_signal", callback_signal::<Memory64>),
+        "thread_spawn" => policy::syscall(&mut store, env, "thread_spawn", thread_spawn_v2::<Memory64>),
+        "thread_spawn_v2" => policy::syscall(&mut store, env, "thread_spawn_v2", thread_spawn_v2::<Memory64>),
+        "thread_sleep" => policy::syscall(&mut store, env, "thread_sleep", thread_sleep::<Memory64>),
+        "thread_id" => policy::syscall(&mut store, env, "thread_id", thread_id::<Memory64>),
+        "thread_signal" => policy::syscall(&mut store, env, "thread_signal", thread_signal),
+        "thread_join" => policy::syscall(&mut store, env, "thread_join", thread_join::<Memory64>),
+        "thread_parallelism" => policy::syscall(&mut store, env, "thread_parallelism", thread_parallelism::<Memory64>),
+        "thread_exit" => policy::syscall(&mut store, env, "thread_exit", thread_exit),
+        "sched_yield" => policy::syscall(&mut store, env, "sched_yield", sched_yield::<Memory64>),
+        "stack_checkpoint" => policy::syscall(&mut store, env, "stack_checkpoint", stack_checkpoint::<Memory64>),
+        "stack_restore" => policy::syscall(&mut store, env, "stack_restore", stack_restore::<Memory64>),
+        "futex_wait" => policy::syscall(&mut store, env, "futex_wait", futex_wait::<Memory64>),
+        "futex_wake" => policy::syscall(&mut store, env, "futex_wake", futex_wake::<Memory64>),
+        "futex_wake_all" => policy::syscall(&mut store, env, "futex_wake_all", futex_wake_all::<Memory64>),
+        "port_bridge" => policy::syscall(&mut store, env, "port_bridge", port_bridge::<Memory64>),
+        "port_unbridge" => policy::syscall(&mut store, env, "port_unbridge", port_unbridge),
+        "port_dhcp_acquire" => policy::syscall(&mut store, env, "port_dhcp_acquire", port_dhcp_acquire),
+        "port_addr_add" => policy::syscall(&mut store, env, "port_addr_add", port_addr_add::<Memory64>),
+        "port_addr_remove" => policy::syscall(&mut store, env, "port_addr_remove", port_addr_remove::<Memory64>),
+        "port_addr_clear" => policy::syscall(&mut store, env, "port_addr_clear", port_addr_clear),
+        "port_addr_list" => policy::syscall(&mut store, env, "port_addr_list", port_addr_list::<Memory64>),
+        "port_mac" => policy::syscall(&mut store, env, "port_mac", port_mac::<Memory64>),
+        "port_gateway_set" => policy::syscall(&mut store, env, "port_gateway_set", port_gateway_set::<Memory64>),
+        "port_route_add" => policy::syscall(&mut store, env, "port_route_add", port_route_add::<Memory64>),
+        "port_route_remove" => policy::syscall(&mut store, env, "port_route_remove", port_route_remove::<Memory64>),
+        "port_route_clear" => policy::syscall(&mut store, env, "port_route_clear", port_route_clear),
+        "port_route_list" => policy::syscall(&mut store, env, "port_route_list", port_route_list::<Memory64>),
+        "sock_status" => policy::syscall(&mut store, env, "sock_status", sock_status::<Memory64>),
+        "sock_addr_local" => policy::syscall(&mut store, env, "sock_addr_local", sock_addr_local::<Memory64>),
+        "sock_addr_peer" => policy::syscall(&mut store, env, "sock_addr_peer", sock_addr_peer::<Memory64>),
+        "sock_open" => policy::syscall(&mut store, env, "sock_open", sock_open::<Memory64>),
+        "sock_set_opt_flag" => policy::syscall(&mut store, env, "sock_set_opt_flag", sock_set_opt_flag),
+        "sock_get_opt_flag" => policy::syscall(&mut store, env, "sock_get_opt_flag", sock_get_opt_flag::<Memory64>),
+        "sock_set_opt_time" => policy::syscall(&mut store, env, "sock_set_opt_time", sock_set_opt_time::<Memory64>),
+        "sock_get_opt_time" => policy::syscall(&mut store, env, "sock_get_opt_time", sock_get_opt_time::<Memory64>),
+        "sock_set_opt_size" => policy::syscall(&mut store, env, "sock_set_opt_size", sock_set_opt_size),
+        "sock_get_opt_size" => policy::syscall(&mut store, env, "sock_get_opt_size", sock_get_opt_size::<Memory64>),
+        "sock_join_multicast_v4" => policy::syscall(&mut store, env, "sock_join_multicast_v4", sock_join_multicast_v4::<Memory64>),
+        "sock_leave_multicast_v4" => policy::syscall(&mut store, env, "sock_leave_multicast_v4", sock_leave_multicast_v4::<Memory64>),
+        "sock_join_multicast_v6" => policy::syscall(&mut store, env, "sock_join_multicast_v6", sock_join_multicast_v6::<Memory64>),
+        "sock_leave_multicast_v6" => policy::syscall(&mut store, env, "sock_leave_multicast_v6", sock_leave_multicast_v6::<Memory64>),
+        "sock_bind" => policy::syscall(&mut store, env, "sock_bind", sock_bind::<Memory64>),
+        "sock_listen" => policy::syscall(&mut store, env, "sock_listen", sock_listen::<Memory64>),
+        "sock_accept" => policy::syscall(&mut store, env, "sock_accept", sock_accept_v2::<Memory64>),
+        "sock_accept_v2" => policy::syscall(&mut store, env, "sock_accept_v2", sock_accept_v2::<Memory64>),
+        "sock_connect" => policy::syscall(&mut store, env, "sock_connect", sock_connect::<Memory64>),
+        "sock_recv" => policy::syscall(&mut store, env, "sock_recv", sock_recv::<Memory64>),
+        "sock_recv_from" => policy::syscall(&mut store, env, "sock_recv_from", sock_recv_from::<Memory64>),
+        "sock_send" => policy::syscall(&mut store, env, "sock_send", sock_send::<Memory64>),
+        "sock_send_to" => policy::syscall(&mut store, env, "sock_send_to", sock_send_to::<Memory64>),
+        "sock_send_file" => policy::syscall(&mut store, env, "sock_send_file", sock_send_file::<Memory64>),
+        "sock_shutdown" => policy::syscall(&mut store, env, "sock_shutdown", sock_shutdown),
+        "resolve" => policy::syscall(&mut store, env, "resolve", resolve::<Memory64>),
     };
     namespace
 }
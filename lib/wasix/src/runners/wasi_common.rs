@@ -53,7 +53,7 @@ impl CommonWasiOptions {
         root_fs: Option<TmpFileSystem>,
     ) -> Result<(), anyhow::Error> {
         let root_fs = root_fs.unwrap_or_else(|| RootFileSystemBuilder::default().build());
-        let fs = prepare_filesystem(root_fs, &self.mounts, container_fs)?;
+        let fs = prepare_filesystem(root_fs, &self.mounts, container_fs, &self.capabilities)?;
 
         builder.add_preopen_dir("/")?;
 
@@ -121,6 +121,7 @@ impl CommonWasiOptions {
 fn build_directory_mappings(
     root_fs: &mut TmpFileSystem,
     mounted_dirs: &[MountedDirectory],
+    capabilities: &Capabilities,
 ) -> Result<(), anyhow::Error> {
     for dir in mounted_dirs {
         let MountedDirectory {
@@ -145,6 +146,7 @@ fn build_directory_mappings(
                     guest_path.display()
                 )
             })?;
+        let fs = &capabilities.restrict_filesystem(fs.clone(), &guest_path);
 
         if guest_path == Path::new("/") {
             root_fs
@@ -170,9 +172,26 @@ fn prepare_filesystem(
     mut root_fs: TmpFileSystem,
     mounted_dirs: &[MountedDirectory],
     container_fs: Option<Arc<dyn FileSystem + Send + Sync>>,
+    capabilities: &Capabilities,
 ) -> Result<Box<dyn FileSystem + Send + Sync>, Error> {
+    let mount_points: Vec<PathBuf> = mounted_dirs
+        .iter()
+        .map(|dir| {
+            let guest_path = Path::new(&dir.guest);
+            if guest_path.is_relative() {
+                apply_relative_path_mounting_hack(guest_path)
+            } else {
+                guest_path.to_path_buf()
+            }
+        })
+        .collect();
+    capabilities
+        .filesystem
+        .check_mount_points(&mount_points)
+        .map_err(Error::msg)?;
+
     if !mounted_dirs.is_empty() {
-        build_directory_mappings(&mut root_fs, mounted_dirs)?;
+        build_directory_mappings(&mut root_fs, mounted_dirs, capabilities)?;
     }
 
     // HACK(Michael-F-Bryan): The WebcVolumeFileSystem only accepts relative
@@ -324,6 +343,18 @@ impl<F: FileSystem> virtual_fs::FileSystem for RelativeOrAbsolutePathHack<F> {
         self.execute(path, |fs, p| fs.metadata(p))
     }
 
+    fn symlink_metadata(&self, path: &Path) -> virtual_fs::Result<virtual_fs::Metadata> {
+        self.execute(path, |fs, p| fs.symlink_metadata(p))
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> virtual_fs::Result<()> {
+        self.execute(link, |fs, p| fs.symlink(target, p))
+    }
+
+    fn readlink(&self, path: &Path) -> virtual_fs::Result<PathBuf> {
+        self.execute(path, |fs, p| fs.readlink(p))
+    }
+
     fn remove_file(&self, path: &Path) -> virtual_fs::Result<()> {
         self.execute(path, |fs, p| fs.remove_file(p))
     }
@@ -432,7 +463,13 @@ mod tests {
         let webc_fs = WebcVolumeFileSystem::mount_all(&container);
 
         let root_fs = RootFileSystemBuilder::default().build();
-        let fs = prepare_filesystem(root_fs, &mapping, Some(Arc::new(webc_fs))).unwrap();
+        let fs = prepare_filesystem(
+            root_fs,
+            &mapping,
+            Some(Arc::new(webc_fs)),
+            &Capabilities::default(),
+        )
+        .unwrap();
 
         assert!(fs.metadata("/home/file.txt".as_ref()).unwrap().is_file());
         assert!(fs.metadata("lib".as_ref()).unwrap().is_dir());
@@ -446,6 +483,61 @@ mod tests {
             .is_file());
     }
 
+    #[tokio::test]
+    #[cfg_attr(not(feature = "host-fs"), ignore)]
+    async fn mounts_are_restricted_by_the_capabilities() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("file.txt"), b"Hello, World!").unwrap();
+        std::fs::write(temp.path().join("secret.key"), b"hunter2").unwrap();
+        let mapping = [MountedDirectory::from(MappedDirectory {
+            guest: "/home".to_string(),
+            host: temp.path().to_path_buf(),
+        })];
+        let mut capabilities = Capabilities::default();
+        capabilities.filesystem.rules = vec!["/home:ro".parse().unwrap()];
+        capabilities.filesystem.deny = vec!["*.key".parse().unwrap()];
+
+        let root_fs = RootFileSystemBuilder::default().build();
+        let fs = prepare_filesystem(root_fs, &mapping, None, &capabilities).unwrap();
+
+        assert!(fs
+            .new_open_options()
+            .read(true)
+            .open("/home/file.txt")
+            .is_ok());
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .open("/home/file.txt")
+                .unwrap_err(),
+            FsError::PermissionDenied
+        );
+        assert_eq!(
+            fs.metadata("/home/secret.key".as_ref()).unwrap_err(),
+            FsError::PermissionDenied
+        );
+        assert!(fs.create_dir("/tmp/dir".as_ref()).is_ok());
+    }
+
+    #[test]
+    fn rules_on_unmounted_directories_are_rejected() {
+        let mapping = [MountedDirectory {
+            guest: "/home".to_string(),
+            fs: Arc::new(virtual_fs::EmptyFileSystem::default()),
+        }];
+        let mut capabilities = Capabilities::default();
+        capabilities.filesystem.rules = vec![
+            "/home/out:wo".parse().unwrap(),
+            "/bin:rw,noexec".parse().unwrap(),
+        ];
+        let root_fs = RootFileSystemBuilder::default().build();
+        assert!(prepare_filesystem(root_fs, &mapping, None, &capabilities).is_ok());
+
+        capabilities.filesystem.rules = vec!["/etc:ro".parse().unwrap()];
+        let root_fs = RootFileSystemBuilder::default().build();
+        assert!(prepare_filesystem(root_fs, &mapping, None, &capabilities).is_err());
+    }
+
     fn unix_timestamp_nanos(instant: SystemTime) -> Option<u64> {
         let duration = instant.duration_since(SystemTime::UNIX_EPOCH).ok()?;
         Some(duration.as_nanos() as u64)
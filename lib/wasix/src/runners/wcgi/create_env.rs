@@ -26,6 +26,9 @@ pub(crate) async fn default_create_env(conf: CreateEnvConfig) -> anyhow::Result<
     (conf.setup_builder)(&mut builder)?;
 
     builder.add_envs(conf.env);
+    let networking = builder.capabilities_mut().networking.clone();
+    let filesystem = builder.capabilities_mut().filesystem.clone();
+    let syscalls = builder.capabilities_mut().syscalls.clone();
 
     let builder = builder
         .stdin(Box::new(req_body_receiver))
@@ -35,6 +38,9 @@ pub(crate) async fn default_create_env(conf: CreateEnvConfig) -> anyhow::Result<
             insecure_allow_all: true,
             http_client: HttpClientCapabilityV1::new_allow_all(),
             threading: Default::default(),
+            networking,
+            filesystem,
+            syscalls,
         });
     let env = builder.build()?;
 
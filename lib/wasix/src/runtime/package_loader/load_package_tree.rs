@@ -425,6 +425,21 @@ where
         self.inner.metadata(&path)
     }
 
+    fn symlink_metadata(&self, path: &Path) -> virtual_fs::Result<virtual_fs::Metadata> {
+        let path = self.path(path)?;
+        self.inner.symlink_metadata(&path)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> virtual_fs::Result<()> {
+        let link = self.path(link)?;
+        self.inner.symlink(target, &link)
+    }
+
+    fn readlink(&self, path: &Path) -> virtual_fs::Result<PathBuf> {
+        let path = self.path(path)?;
+        self.inner.readlink(&path)
+    }
+
     fn remove_file(&self, path: &Path) -> virtual_fs::Result<()> {
         let path = self.path(path)?;
         self.inner.remove_file(&path)
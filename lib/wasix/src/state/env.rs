@@ -313,6 +313,9 @@ pub struct WasiEnv {
 
     pub capabilities: Capabilities,
 
+    /// The networking of the runtime, restricted by the capabilities
+    net: DynVirtualNetworking,
+
     /// Is this environment capable and setup for deep sleeping
     pub enable_deep_sleep: bool,
 
@@ -359,6 +362,7 @@ impl Clone for WasiEnv {
             owned_handles: self.owned_handles.clone(),
             runtime: self.runtime.clone(),
             capabilities: self.capabilities.clone(),
+            net: self.net.clone(),
             enable_deep_sleep: self.enable_deep_sleep,
             enable_journal: self.enable_journal,
             replaying_journal: self.replaying_journal,
@@ -400,6 +404,7 @@ impl WasiEnv {
             owned_handles: Vec::new(),
             runtime: self.runtime.clone(),
             capabilities: self.capabilities.clone(),
+            net: self.net.clone(),
             enable_deep_sleep: self.enable_deep_sleep,
             enable_journal: self.enable_journal,
             replaying_journal: false,
@@ -524,6 +529,9 @@ impl WasiEnv {
             enable_journal: false,
             replaying_journal: false,
             enable_deep_sleep: init.capabilities.threading.enable_asynchronous_threading,
+            net: init
+                .capabilities
+                .restrict_networking(init.runtime.networking().clone()),
             runtime: init.runtime,
             bin_factory: init.bin_factory,
             capabilities: init.capabilities,
@@ -839,7 +847,7 @@ impl WasiEnv {
 
     /// Accesses the virtual networking implementation
     pub fn net(&self) -> &DynVirtualNetworking {
-        self.runtime.networking()
+        &self.net
     }
 
     /// Providers safe access to the initialized part of WasiEnv
@@ -201,6 +201,17 @@ impl WasiState {
             .map_err(fs_error_into_wasi_err)
     }
 
+    pub(crate) fn fs_symlink<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        target: P,
+        link: Q,
+    ) -> Result<(), Errno> {
+        self.fs
+            .root_fs
+            .symlink(target.as_ref(), link.as_ref())
+            .map_err(fs_error_into_wasi_err)
+    }
+
     pub(crate) fn fs_new_open_options(&self) -> OpenOptions {
         self.fs.root_fs.new_open_options()
     }
@@ -17,6 +17,7 @@ pub mod wasm;
 pub mod windows;
 
 pub mod journal;
+pub mod policy;
 pub mod wasi;
 pub mod wasix;
 
@@ -0,0 +1,693 @@
+//! The syscall policy of an environment, enforced by wrapping the host
+//! functions of the syscalls it doesn't simply allow.
+//!
+//! The wrapped syscalls can also be traced, printing every call with its
+//! arguments, result and duration to the standard error of the host, like
+//! `strace` does for native processes. The strings and io vectors passed to
+//! the syscalls are read from the memory of the guest when they are called,
+//! so the buffers the syscalls write to show their previous contents.
+
+use std::{fmt, str::FromStr, time::Instant};
+
+use wasmer::{
+    AsStoreMut, FromToNativeWasmType, Function, FunctionEnv, FunctionEnvMut, MemorySize,
+    MemoryView, ValueType, WasmPtr,
+};
+use wasmer_wasix_types::{
+    types::{
+        Route, __wasi_addr_ip4_t, __wasi_addr_ip6_t, __wasi_addr_port_t, __wasi_addr_t,
+        __wasi_cidr_t, __wasi_ciovec_t, __wasi_hardwareaddress_t, __wasi_iovec_t,
+    },
+    wasi::{
+        Addressfamily, Advice, Bool, EpollCtl, EpollEvent, Errno, Event, ExitCode, Fdflags, Fdstat,
+        Filestat, Fstflags, JoinFlags, JoinStatus, Oflags, OptionPid, OptionTimestamp, Prestat,
+        ProcessHandles, Rights, Signal, Snapshot0Clockid, Snapshot0Event, Snapshot0Filestat,
+        Snapshot0Subscription, Snapshot0Whence, SockProto, Sockoption, Sockstatus, Socktype,
+        StackSnapshot, StdioMode, Streamsecurity, Subscription, ThreadStart, Tty, Whence,
+    },
+};
+
+use crate::{WasiEnv, WasiError};
+
+/// The names of the syscalls the environments export, in any of the WASI
+/// and WASIX namespaces.
+pub const SYSCALLS: &[&str] = &[
+    "args_get",
+    "args_sizes_get",
+    "callback_signal",
+    "chdir",
+    "clock_res_get",
+    "clock_time_get",
+    "clock_time_set",
+    "environ_get",
+    "environ_sizes_get",
+    "epoll_create",
+    "epoll_ctl",
+    "epoll_wait",
+    "fd_advise",
+    "fd_allocate",
+    "fd_close",
+    "fd_datasync",
+    "fd_dup",
+    "fd_event",
+    "fd_fdstat_get",
+    "fd_fdstat_set_flags",
+    "fd_fdstat_set_rights",
+    "fd_filestat_get",
+    "fd_filestat_set_size",
+    "fd_filestat_set_times",
+    "fd_pipe",
+    "fd_pread",
+    "fd_prestat_dir_name",
+    "fd_prestat_get",
+    "fd_pwrite",
+    "fd_read",
+    "fd_readdir",
+    "fd_renumber",
+    "fd_seek",
+    "fd_sync",
+    "fd_tell",
+    "fd_write",
+    "futex_wait",
+    "futex_wake",
+    "futex_wake_all",
+    "getcwd",
+    "path_create_directory",
+    "path_filestat_get",
+    "path_filestat_set_times",
+    "path_link",
+    "path_open",
+    "path_readlink",
+    "path_remove_directory",
+    "path_rename",
+    "path_symlink",
+    "path_unlink_file",
+    "poll_oneoff",
+    "port_addr_add",
+    "port_addr_clear",
+    "port_addr_list",
+    "port_addr_remove",
+    "port_bridge",
+    "port_dhcp_acquire",
+    "port_gateway_set",
+    "port_mac",
+    "port_route_add",
+    "port_route_clear",
+    "port_route_list",
+    "port_route_remove",
+    "port_unbridge",
+    "proc_exec",
+    "proc_exit",
+    "proc_fork",
+    "proc_id",
+    "proc_join",
+    "proc_parent",
+    "proc_raise",
+    "proc_raise_interval",
+    "proc_signal",
+    "proc_spawn",
+    "random_get",
+    "resolve",
+    "sched_yield",
+    "sock_accept",
+    "sock_accept_v2",
+    "sock_addr_local",
+    "sock_addr_peer",
+    "sock_bind",
+    "sock_connect",
+    "sock_get_opt_flag",
+    "sock_get_opt_size",
+    "sock_get_opt_time",
+    "sock_join_multicast_v4",
+    "sock_join_multicast_v6",
+    "sock_leave_multicast_v4",
+    "sock_leave_multicast_v6",
+    "sock_listen",
+    "sock_open",
+    "sock_recv",
+    "sock_recv_from",
+    "sock_send",
+    "sock_send_file",
+    "sock_send_to",
+    "sock_set_opt_flag",
+    "sock_set_opt_size",
+    "sock_set_opt_time",
+    "sock_shutdown",
+    "sock_status",
+    "stack_checkpoint",
+    "stack_restore",
+    "thread-spawn",
+    "thread_exit",
+    "thread_id",
+    "thread_join",
+    "thread_parallelism",
+    "thread_signal",
+    "thread_sleep",
+    "thread_spawn",
+    "thread_spawn_v2",
+    "tty_get",
+    "tty_set",
+];
+
+/// The number of bytes of a string or buffer shown in the traces.
+const MAX_TRACED_BYTES: u64 = 64;
+
+/// The number of io vectors of a syscall shown in the traces.
+const MAX_TRACED_IOVECS: u64 = 8;
+
+/// What happens when a guest calls a syscall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyscallAction {
+    /// The syscall runs normally.
+    Allow,
+    /// The syscall fails with this error, without running.
+    Deny(Errno),
+    /// The syscall runs, and is logged with its arguments, result and
+    /// duration as an `info` event of the `wasmer_wasix::syscalls` target.
+    Log,
+}
+
+impl FromStr for SyscallAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "allow" => Ok(SyscallAction::Allow),
+            None if s == "log" => Ok(SyscallAction::Log),
+            None if s == "deny" => Ok(SyscallAction::Deny(Errno::Notcapable)),
+            Some(("deny", errno)) => (0u16..)
+                .map_while(|n| Errno::try_from(n).ok())
+                .find(|candidate| candidate.name() == errno)
+                .map(SyscallAction::Deny)
+                .ok_or_else(|| format!("unknown errno `{}`", errno)),
+            _ => Err(format!(
+                "unknown syscall action `{}`, expected `allow`, `log`, `deny` or `deny:ERRNO`",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SyscallAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyscallAction::Allow => write!(f, "allow"),
+            SyscallAction::Deny(errno) => write!(f, "deny:{}", errno.name()),
+            SyscallAction::Log => write!(f, "log"),
+        }
+    }
+}
+
+/// The action taken when a guest calls the syscalls with a given name, or
+/// any syscall for `*`.
+///
+/// The syntax is `NAME=ACTION`, where the name is one of [`SYSCALLS`] or
+/// `*`, and the action is `allow`, `log`, `deny`
+/// (failing with `notcapable`) or `deny:ERRNO` with the name of the error,
+/// e.g. `sock_open=deny:access`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyscallRule {
+    pub name: String,
+    pub action: SyscallAction,
+}
+
+impl SyscallRule {
+    pub fn new(name: impl Into<String>, action: SyscallAction) -> Self {
+        SyscallRule {
+            name: name.into(),
+            action,
+        }
+    }
+}
+
+impl FromStr for SyscallRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, action) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected `NAME=ACTION`, found `{}`", s))?;
+        if name.is_empty() {
+            return Err(format!("the syscall name of `{}` is empty", s));
+        }
+        if name != "*" && !SYSCALLS.contains(&name) {
+            return Err(format!("unknown syscall `{}` in `{}`", name, s));
+        }
+        Ok(SyscallRule::new(name, action.parse()?))
+    }
+}
+
+impl fmt::Display for SyscallRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.action)
+    }
+}
+
+/// The result of a syscall, as returned by the host function.
+pub(crate) trait SyscallResult {
+    /// The result of a syscall denied with an error.
+    fn denied(errno: Errno) -> Self;
+
+    fn describe(&self) -> String;
+}
+
+impl SyscallResult for Errno {
+    fn denied(errno: Errno) -> Self {
+        errno
+    }
+
+    fn describe(&self) -> String {
+        self.name().to_string()
+    }
+}
+
+impl SyscallResult for Result<Errno, WasiError> {
+    fn denied(errno: Errno) -> Self {
+        Ok(errno)
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Ok(errno) => errno.name().to_string(),
+            Err(err) => err.to_string(),
+        }
+    }
+}
+
+impl SyscallResult for Result<(), WasiError> {
+    // These syscalls don't return an error to the guest, so denying them
+    // terminates the process instead.
+    fn denied(errno: Errno) -> Self {
+        Err(WasiError::Exit(ExitCode::Errno(errno)))
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Ok(()) => "ok".to_string(),
+            Err(err) => err.to_string(),
+        }
+    }
+}
+
+impl SyscallResult for i32 {
+    fn denied(errno: Errno) -> Self {
+        -(errno as i32)
+    }
+
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// What is done around the calls of a syscall.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SyscallHooks {
+    name: &'static str,
+    action: SyscallAction,
+    strace: bool,
+}
+
+/// An argument of a syscall, as shown in the traces.
+pub(crate) trait TraceArg: fmt::Debug {
+    /// Returns the value of an integer, or the address of a pointer.
+    fn value(&self) -> Option<u64> {
+        None
+    }
+
+    /// Returns the argument read from the memory of the guest, if it points
+    /// to a string or io vectors, whose length is `len`.
+    fn read(&self, _memory: &MemoryView<'_>, _len: u64) -> Option<String> {
+        None
+    }
+
+    /// Returns the argument as printed when it isn't read from the memory.
+    fn describe(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// A type pointed to by the arguments of the syscalls.
+pub(crate) trait TracePointee {
+    /// Returns the `len` values at `pointer` in the memory of the guest, if
+    /// they are shown in the traces.
+    fn read(_memory: &MemoryView<'_>, _pointer: u64, _len: u64) -> Option<String> {
+        None
+    }
+}
+
+impl<T: TracePointee + ValueType, M: MemorySize> TraceArg for WasmPtr<T, M> {
+    fn value(&self) -> Option<u64> {
+        Some(self.offset().into())
+    }
+
+    fn read(&self, memory: &MemoryView<'_>, len: u64) -> Option<String> {
+        T::read(memory, self.offset().into(), len)
+    }
+
+    fn describe(&self) -> String {
+        format!("@{}", self.offset())
+    }
+}
+
+impl TracePointee for u8 {
+    fn read(memory: &MemoryView<'_>, pointer: u64, len: u64) -> Option<String> {
+        read_bytes(memory, pointer, len)
+    }
+}
+
+impl<M: MemorySize> TracePointee for __wasi_ciovec_t<M> {
+    fn read(memory: &MemoryView<'_>, pointer: u64, len: u64) -> Option<String> {
+        read_iovecs(memory, pointer, len, M::is_64bit(), true)
+    }
+}
+
+impl<M: MemorySize> TracePointee for __wasi_iovec_t<M> {
+    fn read(memory: &MemoryView<'_>, pointer: u64, len: u64) -> Option<String> {
+        read_iovecs(memory, pointer, len, M::is_64bit(), false)
+    }
+}
+
+macro_rules! impl_trace_integer {
+    ( $( $t:ty ),* ) => {
+        $(
+            impl TraceArg for $t {
+                fn value(&self) -> Option<u64> {
+                    u64::try_from(*self).ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_trace_integer!(u8, u16, u32, u64, i32, i64);
+
+macro_rules! impl_trace_opaque {
+    (args: $( $t:ty ),*; pointees: $( $p:ty ),* ) => {
+        $( impl TraceArg for $t {} )*
+        $( impl TracePointee for $p {} )*
+    };
+}
+
+impl_trace_opaque!(
+    args: Addressfamily, Advice, Bool, EpollCtl, ExitCode, Fdflags, Fstflags, JoinFlags, Oflags,
+        Rights, Signal, Snapshot0Clockid, Snapshot0Whence, SockProto, Sockoption, Socktype,
+        StdioMode, Streamsecurity, Whence;
+    pointees: u16, u32, u64, Bool, Event, Fdstat, Filestat, JoinStatus, OptionPid,
+        OptionTimestamp, Prestat, ProcessHandles, Route, Snapshot0Event, Snapshot0Filestat,
+        Snapshot0Subscription, Sockstatus, StackSnapshot, Subscription, Tty, __wasi_addr_ip4_t,
+        __wasi_addr_ip6_t, __wasi_addr_port_t, __wasi_addr_t, __wasi_cidr_t,
+        __wasi_hardwareaddress_t
+);
+
+impl<M: MemorySize> TracePointee for EpollEvent<M> {}
+
+impl<M: MemorySize> TracePointee for ThreadStart<M> {}
+
+impl<T, M: MemorySize> TracePointee for WasmPtr<T, M> {}
+
+/// Returns the arguments of a syscall, reading the strings and io vectors
+/// they point to in `memory`.
+fn describe_args(memory: Option<&MemoryView<'_>>, args: &[&dyn TraceArg]) -> String {
+    args.iter()
+        .enumerate()
+        .map(|(index, arg)| {
+            memory
+                .zip(args.get(index + 1).and_then(|len| len.value()))
+                .and_then(|(memory, len)| arg.read(memory, len))
+                .unwrap_or_else(|| arg.describe())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the `len` bytes at `pointer` as a quoted string.
+fn read_bytes(memory: &MemoryView<'_>, pointer: u64, len: u64) -> Option<String> {
+    let mut bytes = vec![0; len.min(MAX_TRACED_BYTES) as usize];
+    memory.read(pointer, &mut bytes).ok()?;
+    let mut s = format!("{:?}", String::from_utf8_lossy(&bytes));
+    if len > MAX_TRACED_BYTES {
+        s.push_str("...");
+    }
+    Some(s)
+}
+
+/// Returns the `count` io vectors at `pointer`, with the contents of their
+/// buffers when `contents` is set.
+fn read_iovecs(
+    memory: &MemoryView<'_>,
+    pointer: u64,
+    count: u64,
+    memory64: bool,
+    contents: bool,
+) -> Option<String> {
+    let size = if memory64 { 16 } else { 8 };
+    // The fields of the io vectors are little-endian offsets.
+    let offset = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .rev()
+            .fold(0u64, |offset, byte| (offset << 8) | u64::from(*byte))
+    };
+    let mut iovecs = Vec::new();
+    for index in 0..count.min(MAX_TRACED_IOVECS) {
+        let mut iovec = vec![0; size];
+        memory
+            .read(pointer.checked_add(index * size as u64)?, &mut iovec)
+            .ok()?;
+        let (base, len) = iovec.split_at(size / 2);
+        let (base, len) = (offset(base), offset(len));
+        let base = if contents {
+            read_bytes(memory, base, len)?
+        } else {
+            format!("@{}", base)
+        };
+        iovecs.push(format!("{{iov_base={}, iov_len={}}}", base, len));
+    }
+    if count > MAX_TRACED_IOVECS {
+        iovecs.push("...".to_string());
+    }
+    Some(format!("[{}]", iovecs.join(", ")))
+}
+
+struct SyscallCall {
+    prefix: String,
+    args: String,
+    start: Instant,
+}
+
+impl SyscallHooks {
+    fn enter(&self, ctx: &FunctionEnvMut<'_, WasiEnv>, args: &[&dyn TraceArg]) -> SyscallCall {
+        let env = ctx.data();
+        let memory = env.try_memory_view(ctx);
+        SyscallCall {
+            prefix: format!("[pid {}:{}]", env.pid(), env.tid()),
+            args: describe_args(memory.as_ref(), args),
+            start: Instant::now(),
+        }
+    }
+
+    fn exit(&self, call: SyscallCall, result: &impl SyscallResult) {
+        let duration = call.start.elapsed();
+        let mut result = result.describe();
+        if let SyscallAction::Deny(_) = self.action {
+            result.push_str(" (denied)");
+        }
+        if self.action == SyscallAction::Log {
+            tracing::info!(
+                target: "wasmer_wasix::syscalls",
+                process = %call.prefix,
+                syscall = self.name,
+                args = %call.args,
+                %result,
+                ?duration,
+                "syscall"
+            );
+        }
+        if self.strace {
+            eprintln!(
+                "{} {}({}) = {} <{:.6}>",
+                call.prefix,
+                self.name,
+                call.args,
+                result,
+                duration.as_secs_f64()
+            );
+        }
+    }
+}
+
+/// A host function implementing a syscall, which can be wrapped with the
+/// hooks of the syscall policy.
+pub(crate) trait HookableSyscall<Args, Ret> {
+    fn into_function(self, store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Function;
+
+    fn into_hooked_function(
+        self,
+        store: &mut impl AsStoreMut,
+        env: &FunctionEnv<WasiEnv>,
+        hooks: SyscallHooks,
+    ) -> Function;
+}
+
+macro_rules! impl_hookable_syscall {
+    ( $( $x:ident ),* ) => {
+        impl_hookable_syscall!(@ret [Errno] $( $x ),*);
+        impl_hookable_syscall!(@ret [Result<Errno, WasiError>] $( $x ),*);
+        impl_hookable_syscall!(@ret [Result<(), WasiError>] $( $x ),*);
+        impl_hookable_syscall!(@ret [i32] $( $x ),*);
+    };
+    (@ret [$ret:ty] $( $x:ident ),* ) => {
+        #[allow(non_snake_case)]
+        impl<Func, $( $x, )*> HookableSyscall<( $( $x, )* ), $ret> for Func
+        where
+            Func: Fn(FunctionEnvMut<'_, WasiEnv>, $( $x, )*) -> $ret + Send + Sync + 'static,
+            $( $x: FromToNativeWasmType + TraceArg, )*
+        {
+            fn into_function(
+                self,
+                store: &mut impl AsStoreMut,
+                env: &FunctionEnv<WasiEnv>,
+            ) -> Function {
+                Function::new_typed_with_env(store, env, self)
+            }
+
+            fn into_hooked_function(
+                self,
+                store: &mut impl AsStoreMut,
+                env: &FunctionEnv<WasiEnv>,
+                hooks: SyscallHooks,
+            ) -> Function {
+                Function::new_typed_with_env(
+                    store,
+                    env,
+                    move |ctx: FunctionEnvMut<'_, WasiEnv>, $( $x: $x, )*| -> $ret {
+                        let call = hooks.enter(&ctx, &[$( &$x as &dyn TraceArg ),*]);
+                        let result = match hooks.action {
+                            SyscallAction::Deny(errno) => <$ret as SyscallResult>::denied(errno),
+                            _ => self(ctx, $( $x, )*),
+                        };
+                        hooks.exit(call, &result);
+                        result
+                    },
+                )
+            }
+        }
+    };
+}
+
+impl_hookable_syscall!();
+impl_hookable_syscall!(A1);
+impl_hookable_syscall!(A1, A2);
+impl_hookable_syscall!(A1, A2, A3);
+impl_hookable_syscall!(A1, A2, A3, A4);
+impl_hookable_syscall!(A1, A2, A3, A4, A5);
+impl_hookable_syscall!(A1, A2, A3, A4, A5, A6);
+impl_hookable_syscall!(A1, A2, A3, A4, A5, A6, A7);
+impl_hookable_syscall!(A1, A2, A3, A4, A5, A6, A7, A8);
+impl_hookable_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9);
+impl_hookable_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10);
+impl_hookable_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11);
+impl_hookable_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12);
+impl_hookable_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13);
+impl_hookable_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14);
+impl_hookable_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15);
+impl_hookable_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16);
+impl_hookable_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17);
+impl_hookable_syscall!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18
+);
+impl_hookable_syscall!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19
+);
+impl_hookable_syscall!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20
+);
+
+/// Creates the host function of a syscall, wrapped with hooks if the
+/// capabilities of the environment don't simply allow it.
+pub(crate) fn syscall<F, Args, Ret>(
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<WasiEnv>,
+    name: &'static str,
+    func: F,
+) -> Function
+where
+    F: HookableSyscall<Args, Ret>,
+{
+    debug_assert!(SYSCALLS.contains(&name), "`{}` isn't in SYSCALLS", name);
+    let syscalls = &env.as_ref(store).capabilities.syscalls;
+    let hooks = SyscallHooks {
+        name,
+        action: syscalls.action(name),
+        strace: syscalls.strace,
+    };
+    // Closures capturing state aren't supported by the JS backends.
+    if cfg!(feature = "sys") && (hooks.action != SyscallAction::Allow || hooks.strace) {
+        func.into_hooked_function(store, env, hooks)
+    } else {
+        func.into_function(store, env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmer::{Memory, Memory32, MemoryType, Store};
+
+    use super::*;
+
+    #[test]
+    fn rules_are_parsed() {
+        let rule: SyscallRule = "sock_open=deny:access".parse().unwrap();
+        assert_eq!(
+            rule,
+            SyscallRule::new("sock_open", SyscallAction::Deny(Errno::Access))
+        );
+        assert_eq!(rule.to_string(), "sock_open=deny:access");
+        assert_eq!(
+            "*=log".parse::<SyscallRule>().unwrap(),
+            SyscallRule::new("*", SyscallAction::Log)
+        );
+        assert_eq!(
+            "proc_exec=deny".parse::<SyscallRule>().unwrap().action,
+            SyscallAction::Deny(Errno::Notcapable)
+        );
+        assert!("fd_write".parse::<SyscallRule>().is_err());
+        assert!("fd_write=deny:nope".parse::<SyscallRule>().is_err());
+        assert!("fd_write=block".parse::<SyscallRule>().is_err());
+        assert!("fd_wirte=deny".parse::<SyscallRule>().is_err());
+        assert!("thread-spawn=deny".parse::<SyscallRule>().is_ok());
+    }
+
+    #[test]
+    fn strings_and_iovecs_are_read() {
+        let mut store = Store::default();
+        let memory = Memory::new(&mut store, MemoryType::new(1, None, false)).unwrap();
+        let view = memory.view(&store);
+        view.write(16, b"hello.txt").unwrap();
+        // Two io vectors, of 5 and 4 bytes
+        view.write(32, &[16, 0, 0, 0, 5, 0, 0, 0, 21, 0, 0, 0, 4, 0, 0, 0])
+            .unwrap();
+
+        let args: [&dyn TraceArg; 3] = [&3u32, &WasmPtr::<u8, Memory32>::new(16), &9u32];
+        assert_eq!(describe_args(Some(&view), &args), r#"3, "hello.txt", 9"#);
+
+        let args: [&dyn TraceArg; 2] = [
+            &WasmPtr::<__wasi_ciovec_t<Memory32>, Memory32>::new(32),
+            &2u32,
+        ];
+        assert_eq!(
+            describe_args(Some(&view), &args),
+            r#"[{iov_base="hello", iov_len=5}, {iov_base=".txt", iov_len=4}], 2"#
+        );
+
+        let args: [&dyn TraceArg; 2] = [
+            &WasmPtr::<__wasi_iovec_t<Memory32>, Memory32>::new(32),
+            &1u32,
+        ];
+        assert_eq!(
+            describe_args(Some(&view), &args),
+            "[{iov_base=@16, iov_len=5}], 1"
+        );
+        assert_eq!(describe_args(None, &args), "@32, 1");
+    }
+}
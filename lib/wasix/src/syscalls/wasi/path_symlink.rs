@@ -106,6 +106,18 @@ pub fn path_symlink_internal(
         }
     }
 
+    // Create the link in the file system too, so that it outlives the inode
+    // cache; file systems that can't hold symlinks only get the inode.
+    {
+        let guard = target_parent_inode.read();
+        if let Kind::Dir { path, .. } = guard.deref() {
+            match state.fs_symlink(old_path, path.join(&entry_name)) {
+                Ok(()) | Err(Errno::Perm) => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
     let mut source_path = std::path::Path::new(old_path);
     let mut relative_path = std::path::PathBuf::new();
     for _ in 0..depth {
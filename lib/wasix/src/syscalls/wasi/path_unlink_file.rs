@@ -66,17 +66,19 @@ pub(crate) fn path_unlink_file_internal(
         false
     ));
 
-    let removed_inode = {
+    let (removed_inode, removed_path) = {
         let mut guard = parent_inode.write();
         match guard.deref_mut() {
             Kind::Dir {
-                ref mut entries, ..
+                ref mut entries,
+                ref path,
+                ..
             } => {
                 let removed_inode = wasi_try_ok!(entries.remove(&childs_name).ok_or(Errno::Inval));
                 // TODO: make this a debug assert in the future
                 assert!(inode.ino() == removed_inode.ino());
                 debug_assert!(inode.stat.read().unwrap().st_nlink > 0);
-                removed_inode
+                (removed_inode, path.join(&childs_name))
             }
             Kind::Root { .. } => return Ok(Errno::Access),
             _ => unreachable!(
@@ -114,7 +116,12 @@ pub(crate) fn path_unlink_file_internal(
                 }
                 Kind::Dir { .. } | Kind::Root { .. } => return Ok(Errno::Isdir),
                 Kind::Symlink { .. } => {
-                    // TODO: actually delete real symlinks and do nothing for virtual symlinks
+                    // Delete the link from the file system, if it made it there
+                    drop(guard);
+                    match state.fs_remove_file(removed_path) {
+                        Ok(()) | Err(Errno::Noent) => {}
+                        Err(err) => return Ok(err),
+                    }
                 }
                 _ => unimplemented!("wasi::path_unlink_file for Buffer"),
             }
@@ -0,0 +1,60 @@
+use virtual_fs::AsyncReadExt;
+use wasmer::{Module, Store};
+use wasmer_wasix::{
+    capabilities::{SyscallAction, SyscallRule},
+    wasmer_wasix_types::wasi::Errno,
+    Pipe, WasiEnv,
+};
+
+#[tokio::test]
+async fn denied_syscalls_return_the_configured_errno() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 8) "hello world")
+
+        (func $main (export "_start")
+            (i32.store (i32.const 0) (i32.const 8))
+            (i32.store (i32.const 4) (i32.const 11))
+
+            ;; Exit with the errno returned by fd_write
+            (call $proc_exit
+                (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 20)))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let (stdout_tx, mut stdout_rx) = Pipe::channel();
+    let mut builder = WasiEnv::builder("command-name").stdout(Box::new(stdout_tx));
+    builder
+        .capabilities_mut()
+        .syscalls
+        .rules
+        .push(SyscallRule::new(
+            "fd_write",
+            SyscallAction::Deny(Errno::Access),
+        ));
+
+    let err = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(
+        err.as_exit_code().map(|code| code.raw()),
+        Some(Errno::Access as i32)
+    );
+
+    let mut stdout = String::new();
+    stdout_rx.read_to_string(&mut stdout).await.unwrap();
+    assert_eq!(stdout, "");
+}
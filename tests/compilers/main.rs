@@ -11,9 +11,12 @@ mod imports;
 mod issues;
 mod metering;
 mod middlewares;
+mod profiling;
 // mod multi_value_imports;
 mod artifact;
 mod serialize;
+mod tail_calls;
+mod tiering;
 mod traps;
 mod typed_functions;
 mod wasi;
@@ -0,0 +1,52 @@
+#![cfg(target_os = "linux")]
+
+use anyhow::Result;
+use wasmer::sys::{EngineBuilder, ProfilingStrategy};
+use wasmer::*;
+
+const WAT: &str = r#"
+(module $profiled
+  (func $profiled_function (export "run") (result i32)
+    (i32.const 42)))
+"#;
+
+fn store(config: &crate::Config, profiler: ProfilingStrategy) -> Store {
+    let engine = EngineBuilder::new(config.compiler_config(false))
+        .set_profiler(Some(profiler))
+        .engine();
+    Store::new(engine)
+}
+
+#[compiler_test(profiling)]
+fn perf_map_names_functions(config: crate::Config) -> Result<()> {
+    let store = store(&config, ProfilingStrategy::PerfMap);
+    Module::new(&store, WAT)?;
+
+    let perf_map = std::fs::read_to_string(format!("/tmp/perf-{}.map", std::process::id()))?;
+    let entry = perf_map
+        .lines()
+        .find(|line| line.ends_with(" wasm[profiled]::profiled_function"))
+        .expect("the function is in the perf map");
+    let mut fields = entry.split(' ');
+    let start = u64::from_str_radix(fields.next().unwrap(), 16)?;
+    let len = u64::from_str_radix(fields.next().unwrap(), 16)?;
+    assert_ne!(start, 0);
+    assert_ne!(len, 0);
+    assert!(perf_map.contains(" wasm[profiled]::call_trampoline[0]"));
+
+    Ok(())
+}
+
+#[compiler_test(profiling)]
+fn jit_dump_records_functions(config: crate::Config) -> Result<()> {
+    let store = store(&config, ProfilingStrategy::JitDump);
+    Module::new(&store, WAT)?;
+
+    let path = std::env::temp_dir().join(format!("jit-{}.dump", std::process::id()));
+    let jit_dump = std::fs::read(path)?;
+    assert_eq!(&jit_dump[..4], &0x4A69_5444u32.to_ne_bytes());
+    let name = b"wasm[profiled]::profiled_function\0";
+    assert!(jit_dump.windows(name.len()).any(|window| window == name));
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use wasmer::*;
+
+use crate::Compiler;
+
+/// Singlepass reuses the stack arguments area of the caller for tail calls,
+/// so it can't tail call a function taking more stack arguments than the
+/// caller, and rejects such modules instead of growing the stack.
+#[compiler_test(tail_calls)]
+fn tail_calls_growing_the_stack_arguments(mut config: crate::Config) -> Result<()> {
+    let mut features = sys::Features::new();
+    features.tail_call(true);
+    config.set_features(features);
+    let mut store = config.store();
+
+    let wat = r#"
+    (module
+      (type $t9 (func (param i64 i64 i64 i64 i64 i64 i64 i64 i64) (result i64)))
+      (table funcref (elem $loop9))
+      (func $loop9 (type $t9)
+        (if (result i64) (i64.eqz (local.get 0))
+          (then (local.get 8))
+          (else
+            (return_call $loop9
+              (i64.sub (local.get 0) (i64.const 1))
+              (local.get 1) (local.get 2) (local.get 3) (local.get 4)
+              (local.get 5) (local.get 6) (local.get 7)
+              (i64.add (local.get 8) (local.get 0))))))
+      (func (export "grow") (param $n i64) (result i64)
+        (return_call_indirect (type $t9)
+          (local.get $n)
+          (i64.const 0) (i64.const 0) (i64.const 0) (i64.const 0)
+          (i64.const 0) (i64.const 0) (i64.const 0) (i64.const 0)
+          (i32.const 0))))
+    "#;
+    let result = Module::new(&store, wat);
+    if config.compiler != Compiler::LLVM {
+        // Cranelift doesn't implement tail calls at all.
+        assert!(
+            matches!(result, Err(CompileError::UnsupportedFeature(_))),
+            "{:?}",
+            result.err()
+        );
+        return Ok(());
+    }
+
+    let module = result?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let grow: TypedFunction<i64, i64> = instance.exports.get_typed_function(&store, "grow")?;
+    assert_eq!(grow.call(&mut store, 1000000)?, 500000500000);
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+#![cfg(all(feature = "singlepass", feature = "cranelift"))]
+
+use anyhow::Result;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use wasmer::sys::{EngineBuilder, TieringConfig};
+use wasmer::wasmparser::Operator;
+use wasmer::*;
+use wasmer_compiler_cranelift::Cranelift;
+use wasmer_compiler_singlepass::Singlepass;
+
+const WAT: &str = r#"
+(module
+  (func $fib (export "fib") (param i32) (result i32)
+    (if (result i32) (i32.lt_u (local.get 0) (i32.const 2))
+      (then (local.get 0))
+      (else
+        (i32.add
+          (call $fib (i32.sub (local.get 0) (i32.const 1)))
+          (call $fib (i32.sub (local.get 0) (i32.const 2)))))))
+  (func (export "cold") (result i32)
+    (i32.const 42)))
+"#;
+
+#[test]
+fn tiering_swaps_hot_functions() -> Result<()> {
+    let tiering = TieringConfig::new(Cranelift::new())
+        .set_hot_threshold(1000)
+        .set_poll_interval(Duration::from_millis(1));
+    let engine = EngineBuilder::new(Singlepass::new())
+        .set_tiering(Some(tiering))
+        .engine();
+    assert_eq!(engine.name(), "engine-singlepass-tiered-cranelift");
+    assert_eq!(engine.optimized_functions(), Some(0));
+
+    let mut store = Store::new(engine.clone());
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let fib: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "fib")?;
+    assert_eq!(fib.call(&mut store, 20)?, 6765);
+
+    // The counters are shared by all the instances of the module
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let cold: TypedFunction<(), i32> = instance.exports.get_typed_function(&store, "cold")?;
+    assert_eq!(cold.call(&mut store)?, 42);
+
+    let start = Instant::now();
+    while engine.optimized_functions() == Some(0) && start.elapsed() < Duration::from_secs(30) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(engine.optimized_functions(), Some(1));
+
+    // New instances run the optimized code
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let fib: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "fib")?;
+    assert_eq!(fib.call(&mut store, 20)?, 6765);
+    let cold: TypedFunction<(), i32> = instance.exports.get_typed_function(&store, "cold")?;
+    assert_eq!(cold.call(&mut store)?, 42);
+
+    Ok(())
+}
+
+/// Makes the optimized code of `$tier` return 2 instead of 1, so that the
+/// code running can be told apart.
+#[derive(Debug)]
+struct MarkOptimized;
+
+impl ModuleMiddleware for MarkOptimized {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(MarkOptimized)
+    }
+}
+
+impl FunctionMiddleware for MarkOptimized {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        match operator {
+            Operator::I32Const { value: 1 } => state.push_operator(Operator::I32Const { value: 2 }),
+            operator => state.push_operator(operator),
+        }
+        Ok(())
+    }
+}
+
+const SPIN_WAT: &str = r#"
+(module
+  (import "host" "timed_out" (func $timed_out (result i32)))
+  (func $tier (result i32)
+    (i32.const 1))
+  (func (export "tier") (result i32)
+    (call $tier))
+  ;; Calls `$tier` until it runs its optimized code, and returns the number
+  ;; of calls, or -1 on timeout.
+  (func (export "spin") (result i64)
+    (local $calls i64)
+    (loop $continue
+      (local.set $calls (i64.add (local.get $calls) (i64.const 1)))
+      (if (i32.eq (call $tier) (i32.const 2))
+        (then (return (local.get $calls))))
+      (br_if $continue (i32.eqz (call $timed_out))))
+    (i64.const -1)))
+"#;
+
+#[test]
+fn tiering_switches_running_instances() -> Result<()> {
+    let mut optimizing = Cranelift::new();
+    optimizing.push_middleware(Arc::new(MarkOptimized));
+    let tiering = TieringConfig::new(optimizing)
+        .set_hot_threshold(1000)
+        .set_poll_interval(Duration::from_millis(1));
+    let engine = EngineBuilder::new(Singlepass::new())
+        .set_tiering(Some(tiering))
+        .engine();
+
+    let mut store = Store::new(engine.clone());
+    let module = Module::new(&store, SPIN_WAT)?;
+    let start = Instant::now();
+    let timed_out = Function::new_typed(&mut store, move || {
+        (start.elapsed() > Duration::from_secs(30)) as i32
+    });
+    let imports = imports! {
+        "host" => {
+            "timed_out" => timed_out,
+        },
+    };
+    let instance = Instance::new(&mut store, &module, &imports)?;
+    let tier: TypedFunction<(), i32> = instance.exports.get_typed_function(&store, "tier")?;
+    let spin: TypedFunction<(), i64> = instance.exports.get_typed_function(&store, "spin")?;
+    assert_eq!(tier.call(&mut store)?, 1);
+
+    // `spin` keeps running its baseline code, and its direct calls to
+    // `$tier` switch to the optimized code once it is swapped.
+    let calls = spin.call(&mut store)?;
+    assert!(calls >= 1000, "{} calls", calls);
+    assert!(engine.optimized_functions() >= Some(1));
+    // So do the calls from the host, and the calls of the baseline code
+    // of the instances created afterwards.
+    assert_eq!(tier.call(&mut store)?, 2);
+    let instance = Instance::new(&mut store, &module, &imports)?;
+    let spin: TypedFunction<(), i64> = instance.exports.get_typed_function(&store, "spin")?;
+    assert_eq!(spin.call(&mut store)?, 1);
+
+    Ok(())
+}
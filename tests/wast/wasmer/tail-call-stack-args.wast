@@ -0,0 +1,90 @@
+;; Tail calls whose arguments don't all fit in registers.
+;; Each loop runs a million iterations, which overflows the stack unless
+;; the calls reuse the frame of the caller.
+
+(module
+  (import "spectest" "print_i32" (func $print (param i32)))
+  (type $t8 (func (param i64 i64 i64 i64 i64 i64 i64 i64) (result i64)))
+  (table funcref (elem $odd))
+
+  ;; Arguments passed on the stack, shuffled at each iteration.
+  (func $loop9 (export "loop9")
+    (param $n i64) (param i64 i64 i64 i64 i64 i64 i64) (param $acc i64) (result i64)
+    (if (result i64) (i64.eqz (local.get $n))
+      (then (i64.add (local.get $acc) (i64.add (local.get 1) (local.get 7))))
+      (else
+        (return_call $loop9
+          (i64.sub (local.get $n) (i64.const 1))
+          (local.get 7) (local.get 6) (local.get 5) (local.get 4)
+          (local.get 3) (local.get 2) (local.get 1)
+          (i64.add (local.get $acc) (local.get $n))))))
+
+  ;; Mutually recursive functions taking arguments on the stack, the odd
+  ;; one being called through the table.
+  (func $even (export "even") (type $t8)
+    (if (result i64) (i64.eqz (local.get 0))
+      (then (i64.add (local.get 1) (local.get 7)))
+      (else
+        (return_call_indirect (type $t8)
+          (i64.sub (local.get 0) (i64.const 1))
+          (local.get 2) (local.get 3) (local.get 4) (local.get 5)
+          (local.get 6) (local.get 7) (i64.add (local.get 1) (local.get 0))
+          (i32.const 0)))))
+
+  (func $odd (type $t8)
+    (if (result i64) (i64.eqz (local.get 0))
+      (then (i64.sub (local.get 1) (local.get 7)))
+      (else
+        (return_call $even
+          (i64.sub (local.get 0) (i64.const 1))
+          (local.get 7) (local.get 1) (local.get 2) (local.get 3)
+          (local.get 4) (local.get 5) (local.get 6)))))
+
+  (func $floats (export "floats") (param $n i32) (param $a f64) (param $b f32) (result f64)
+    (if (result f64) (i32.eqz (local.get $n))
+      (then (f64.add (local.get $a) (f64.promote_f32 (local.get $b))))
+      (else
+        (return_call $floats
+          (i32.sub (local.get $n) (i32.const 1))
+          (f64.add (local.get $a) (f64.const 0.5))
+          (local.get $b)))))
+
+  (func $vector (export "vector") (param $n i32) (param $v v128) (result i32)
+    (if (result i32) (i32.eqz (local.get $n))
+      (then (i32x4.extract_lane 3 (local.get $v)))
+      (else
+        (return_call $vector
+          (i32.sub (local.get $n) (i32.const 1))
+          (i32x4.add (local.get $v) (v128.const i32x4 0 0 0 1))))))
+
+  ;; Imported functions can be tail called too.
+  (func (export "import") (param i32)
+    (return_call $print (local.get 0)))
+)
+
+(assert_return
+  (invoke "loop9"
+    (i64.const 1000000)
+    (i64.const 1) (i64.const 2) (i64.const 3) (i64.const 4)
+    (i64.const 5) (i64.const 6) (i64.const 7)
+    (i64.const 0))
+  (i64.const 500000500008))
+(assert_return
+  (invoke "even"
+    (i64.const 1000000)
+    (i64.const 1) (i64.const 2) (i64.const 3) (i64.const 4)
+    (i64.const 5) (i64.const 6) (i64.const 7))
+  (i64.const 250000500008))
+(assert_return
+  (invoke "even"
+    (i64.const 1000001)
+    (i64.const 1) (i64.const 2) (i64.const 3) (i64.const 4)
+    (i64.const 5) (i64.const 6) (i64.const 7))
+  (i64.const -250001000000))
+(assert_return
+  (invoke "floats" (i32.const 1000000) (f64.const 0) (f32.const 1.5))
+  (f64.const 500001.5))
+(assert_return
+  (invoke "vector" (i32.const 1000000) (v128.const i32x4 0 0 0 7))
+  (i32.const 1000007))
+(assert_return (invoke "import" (i32.const 3)))